@@ -0,0 +1,357 @@
+//! Utilities for parsing and formatting addresses with EIP-55 mixed-case
+//! checksums, ERC-3770 chain prefixes or as ICAP, and for computing the
+//! addresses of created contracts.
+
+use crate::errors::ParseAddressError;
+use crate::hash::keccak256;
+use crate::Address;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Formats an address as a `0x`-prefixed hex string with an EIP-55 mixed-case
+/// checksum.
+pub fn to_checksum(address: &Address) -> String {
+    let hex = hex::encode(address.as_bytes());
+    let hash = keccak256(&hex);
+
+    let mut checksummed = String::with_capacity(42);
+    checksummed.push_str("0x");
+    for (i, c) in hex.chars().enumerate() {
+        let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+        if nibble >= 8 {
+            checksummed.push(c.to_ascii_uppercase());
+        } else {
+            checksummed.push(c);
+        }
+    }
+    checksummed
+}
+
+/// Parses a `0x`-prefixed hex address string, verifying its EIP-55 checksum.
+///
+/// Addresses that are entirely lowercase or entirely uppercase do not encode a
+/// checksum and are accepted as is. Mixed-case addresses must have a valid
+/// checksum, in order to catch typos in addresses that are copied by hand.
+pub fn parse_checksummed(s: &str) -> Result<Address, ParseAddressError> {
+    if !s.starts_with("0x") {
+        return Err(ParseAddressError::MissingPrefix);
+    }
+
+    let hex = &s[2..];
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseAddressError::InvalidHex(s.to_owned()));
+    }
+    let address =
+        Address::from_str(hex).map_err(|_| ParseAddressError::InvalidHex(s.to_owned()))?;
+
+    let is_lowercase = !hex.bytes().any(|b| b.is_ascii_uppercase());
+    let is_uppercase = !hex.bytes().any(|b| b.is_ascii_lowercase());
+    if !is_lowercase && !is_uppercase {
+        let expected = to_checksum(&address);
+        if expected != s {
+            return Err(ParseAddressError::InvalidChecksum(expected));
+        }
+    }
+
+    Ok(address)
+}
+
+/// Formats an address with an ERC-3770 chain short name prefix, for example
+/// `eth:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`. The address part has an
+/// EIP-55 checksum.
+pub fn to_chain_prefixed(short_name: &str, address: &Address) -> String {
+    format!("{}:{}", short_name, to_checksum(address))
+}
+
+/// Parses an address with an ERC-3770 chain short name prefix, returning the
+/// short name and the address. The address part is parsed with
+/// [`parse_checksummed`].
+pub fn parse_chain_prefixed(s: &str) -> Result<(&str, Address), ParseAddressError> {
+    let (short_name, address) = s
+        .split_once(':')
+        .filter(|(short_name, _)| is_short_name(short_name))
+        .ok_or_else(|| ParseAddressError::InvalidChainPrefix(s.to_owned()))?;
+    Ok((short_name, parse_checksummed(address)?))
+}
+
+/// Returns `true` if the string is a valid ERC-3770 chain short name.
+fn is_short_name(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Formats an address as an ICAP (Inter exchange Client Address Protocol)
+/// IBAN-compatible string, for example
+/// `XE65GB6LDNXYOFTX0NSV3FUWKOWIXAMJK36`.
+pub fn to_icap(address: &Address) -> String {
+    let mut value = address.to_fixed_bytes();
+    let mut digits = Vec::with_capacity(31);
+    while value.iter().any(|byte| *byte != 0) {
+        let mut remainder = 0;
+        for byte in value.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 36) as u8;
+            remainder = acc % 36;
+        }
+        digits.push(
+            char::from_digit(remainder, 36)
+                .unwrap()
+                .to_ascii_uppercase(),
+        );
+    }
+    digits.resize(digits.len().max(30), '0');
+    let bban = digits.into_iter().rev().collect::<String>();
+
+    let checksum = 98 - iban_remainder(&format!("{}XE00", bban));
+    format!("XE{:02}{}", checksum, bban)
+}
+
+/// Parses an ICAP address string, verifying its checksum. Both the direct
+/// encoding with 30 and the basic encoding with 31 base 36 digits are
+/// supported.
+pub fn parse_icap(s: &str) -> Result<Address, ParseAddressError> {
+    let invalid = || ParseAddressError::InvalidIcap(s.to_owned());
+
+    let icap = s.to_ascii_uppercase();
+    if !icap.starts_with("XE")
+        || !(icap.len() == 34 || icap.len() == 35)
+        || !icap.bytes().all(|b| b.is_ascii_alphanumeric())
+        || iban_remainder(&format!("{}{}", &icap[4..], &icap[..4])) != 1
+    {
+        return Err(invalid());
+    }
+
+    let mut value = [0_u8; 20];
+    for digit in icap[4..].chars() {
+        let mut carry = digit.to_digit(36).ok_or_else(invalid)?;
+        for byte in value.iter_mut().rev() {
+            let acc = *byte as u32 * 36 + carry;
+            *byte = acc as u8;
+            carry = acc >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid());
+        }
+    }
+
+    Ok(Address::from(value))
+}
+
+/// Computes the IBAN remainder modulo 97 of an alphanumeric string, where
+/// letters are converted to the numbers 10 to 35.
+fn iban_remainder(s: &str) -> u32 {
+    s.chars().fold(0, |remainder, c| {
+        let value = c.to_digit(36).unwrap_or_default();
+        if value < 10 {
+            (remainder * 10 + value) % 97
+        } else {
+            (remainder * 100 + value) % 97
+        }
+    })
+}
+
+/// Computes the address of a contract created with `CREATE` by the specified
+/// deployer with the specified nonce, which is the nonce of the deployment
+/// transaction for externally owned accounts.
+pub fn create_address(deployer: &Address, nonce: u64) -> Address {
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+
+    // NOTE: RLP encoding of the `[deployer, nonce]` list, which is always
+    //   shorter than 56 bytes.
+    let mut rlp = Vec::with_capacity(31);
+    rlp.push(0);
+    rlp.push(0x80 + 20);
+    rlp.extend_from_slice(deployer.as_bytes());
+    match nonce_bytes {
+        [byte] if *byte < 0x80 => rlp.push(*byte),
+        _ => {
+            rlp.push(0x80 + nonce_bytes.len() as u8);
+            rlp.extend_from_slice(nonce_bytes);
+        }
+    }
+    rlp[0] = 0xc0 + (rlp.len() - 1) as u8;
+
+    Address::from_slice(&keccak256(&rlp)[12..])
+}
+
+/// Computes the address of a contract created with `CREATE2` by the specified
+/// deployer with the specified salt and contract init code, as specified in
+/// EIP-1014.
+pub fn create2_address(deployer: &Address, salt: [u8; 32], init_code: &[u8]) -> Address {
+    let mut data = Vec::with_capacity(85);
+    data.push(0xff);
+    data.extend_from_slice(deployer.as_bytes());
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&keccak256(init_code));
+
+    Address::from_slice(&keccak256(&data)[12..])
+}
+
+/// A wrapper around an address that is displayed with an EIP-55 checksum and
+/// verifies checksums when parsed from a string.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Checksummed(pub Address);
+
+impl Display for Checksummed {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&to_checksum(&self.0))
+    }
+}
+
+impl FromStr for Checksummed {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_checksummed(s).map(Checksummed)
+    }
+}
+
+impl From<Address> for Checksummed {
+    fn from(address: Address) -> Self {
+        Checksummed(address)
+    }
+}
+
+impl From<Checksummed> for Address {
+    fn from(address: Checksummed) -> Self {
+        address.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors from the EIP-55 specification
+    const CHECKSUMMED: &[&str] = &[
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksum_test_vectors() {
+        for s in CHECKSUMMED {
+            let address = Address::from_str(&s[2..]).unwrap();
+            assert_eq!(to_checksum(&address), *s);
+            assert_eq!(Checksummed(address).to_string(), *s);
+            assert_eq!(parse_checksummed(s).unwrap(), address);
+        }
+    }
+
+    #[test]
+    fn parse_without_checksum() {
+        for s in CHECKSUMMED {
+            let lower = format!("0x{}", s[2..].to_lowercase());
+            let upper = format!("0x{}", s[2..].to_uppercase());
+            assert!(parse_checksummed(&lower).is_ok());
+            assert!(parse_checksummed(&upper).is_ok());
+        }
+    }
+
+    #[test]
+    fn compute_create_addresses() {
+        let deployer = Address::from_str("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        for (nonce, expected) in &[
+            (0, "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+            (1, "343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+            (2, "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+            (3, "fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"),
+        ] {
+            assert_eq!(
+                create_address(&deployer, *nonce),
+                Address::from_str(expected).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn compute_create2_addresses() {
+        // test vectors from the EIP-1014 specification
+        assert_eq!(
+            create2_address(&Address::zero(), [0; 32], &[0x00]),
+            Address::from_str("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38").unwrap(),
+        );
+        assert_eq!(
+            create2_address(
+                &Address::from_str("deadbeef00000000000000000000000000000000").unwrap(),
+                [0; 32],
+                &[0x00],
+            ),
+            Address::from_str("b928f69bb1d91cd65274e3c79d8986362984fda3").unwrap(),
+        );
+    }
+
+    #[test]
+    fn chain_prefixed_addresses() {
+        let address = Address::from_str(&CHECKSUMMED[0][2..]).unwrap();
+        let prefixed = format!("oeth:{}", CHECKSUMMED[0]);
+        assert_eq!(to_chain_prefixed("oeth", &address), prefixed);
+        assert_eq!(parse_chain_prefixed(&prefixed).unwrap(), ("oeth", address));
+
+        for invalid in &[CHECKSUMMED[0].to_owned(), format!(":{}", CHECKSUMMED[0])] {
+            assert!(matches!(
+                parse_chain_prefixed(invalid),
+                Err(ParseAddressError::InvalidChainPrefix(_))
+            ));
+        }
+        assert!(matches!(
+            parse_chain_prefixed("eth:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            Err(ParseAddressError::InvalidChecksum(_))
+        ));
+    }
+
+    #[test]
+    fn icap_addresses() {
+        // test vectors from the ethers.js documentation
+        let address = Address::from_str("8ba1f109551bd432803012645ac136ddd64dba72").unwrap();
+        assert_eq!(to_icap(&address), "XE65GB6LDNXYOFTX0NSV3FUWKOWIXAMJK36");
+        assert_eq!(
+            parse_icap("XE65GB6LDNXYOFTX0NSV3FUWKOWIXAMJK36").unwrap(),
+            address,
+        );
+
+        for address in &[Address::zero(), Address::repeat_byte(0xff)] {
+            let icap = to_icap(address);
+            assert_eq!(parse_icap(&icap).unwrap(), *address);
+            assert_eq!(parse_icap(&icap.to_lowercase()).unwrap(), *address);
+        }
+
+        for invalid in &[
+            "XE66GB6LDNXYOFTX0NSV3FUWKOWIXAMJK36",
+            "XE65GB6LDNXYOFTX0NSV3FUWKOWIXAMJK3",
+            "DE65GB6LDNXYOFTX0NSV3FUWKOWIXAMJK36",
+        ] {
+            assert!(matches!(
+                parse_icap(invalid),
+                Err(ParseAddressError::InvalidIcap(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_invalid_checksum() {
+        assert!(matches!(
+            parse_checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            Err(ParseAddressError::InvalidChecksum(expected))
+                if expected == "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+    }
+
+    #[test]
+    fn parse_invalid_address() {
+        assert!(matches!(
+            parse_checksummed("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            Err(ParseAddressError::MissingPrefix)
+        ));
+        assert!(matches!(
+            parse_checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"),
+            Err(ParseAddressError::InvalidHex(_))
+        ));
+        assert!(matches!(
+            parse_checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg"),
+            Err(ParseAddressError::InvalidHex(_))
+        ));
+    }
+}
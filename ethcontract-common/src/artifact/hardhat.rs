@@ -28,9 +28,10 @@ use crate::artifact::Artifact;
 use crate::contract::Network;
 use crate::errors::ArtifactError;
 use crate::{Address, Contract, DeploymentInformation, TransactionHash};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
 use serde::Deserialize;
-use serde_json::{from_reader, from_slice, from_str, from_value, Value};
-use std::collections::HashMap;
+use serde_json::{from_reader, Deserializer as JsonDeserializer, Value};
+use std::fmt::{self, Formatter};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
@@ -183,22 +184,27 @@ impl HardHatLoader {
 
     /// Loads an artifact from a JSON value.
     pub fn load_from_reader(&self, f: Format, v: impl Read) -> Result<Artifact, ArtifactError> {
-        self.load_artifact(f, "<unknown>", v, from_reader, from_reader)
+        self.load_artifact(f, "<unknown>", JsonDeserializer::from_reader(v))
     }
 
     /// Loads an artifact from bytes of JSON text.
     pub fn load_from_slice(&self, f: Format, v: &[u8]) -> Result<Artifact, ArtifactError> {
-        self.load_artifact(f, "<unknown>", v, from_slice, from_slice)
+        self.load_artifact(f, "<unknown>", JsonDeserializer::from_slice(v))
     }
 
     /// Loads an artifact from string of JSON text.
     pub fn load_from_str(&self, f: Format, v: &str) -> Result<Artifact, ArtifactError> {
-        self.load_artifact(f, "<unknown>", v, from_str, from_str)
+        self.load_artifact(f, "<unknown>", JsonDeserializer::from_str(v))
     }
 
     /// Loads an artifact from a loaded JSON value.
     pub fn load_from_value(&self, f: Format, v: Value) -> Result<Artifact, ArtifactError> {
-        self.load_artifact(f, "<unknown>", v, from_value, from_value)
+        let origin = self
+            .origin
+            .clone()
+            .unwrap_or_else(|| "<unknown>".to_string());
+        let exports = ExportsSeed::new(self, f).deserialize(v)?;
+        self.fill_artifact(origin, exports)
     }
 
     /// Loads an artifact from disk.
@@ -210,7 +216,7 @@ impl HardHatLoader {
         let path = p.as_ref();
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        self.load_artifact(f, path.display(), reader, from_reader, from_reader)
+        self.load_artifact(f, path.display(), JsonDeserializer::from_reader(reader))
     }
 
     /// Loads an artifact from `deployments` directory.
@@ -345,38 +351,38 @@ impl HardHatLoader {
         Ok(artifact)
     }
 
-    fn load_artifact<T>(
+    /// Loads an artifact from a JSON deserializer.
+    ///
+    /// The artifact is deserialized in a streaming fashion, contracts and
+    /// networks that are filtered out by the allow and deny lists are skipped
+    /// without being materialized. This keeps memory usage low when only a
+    /// few contracts are needed from a huge multi-export file.
+    fn load_artifact<'de, R: serde_json::de::Read<'de>>(
         &self,
         format: Format,
         origin: impl ToString,
-        source: T,
-        single_loader: impl FnOnce(T) -> serde_json::Result<HardHatExport>,
-        multi_loader: impl FnOnce(T) -> serde_json::Result<HardHatMultiExport>,
+        mut deserializer: JsonDeserializer<R>,
     ) -> Result<Artifact, ArtifactError> {
         let origin = self.origin.clone().unwrap_or_else(|| origin.to_string());
 
-        let mut artifact = Artifact::with_origin(origin);
+        let exports = ExportsSeed::new(self, format).deserialize(&mut deserializer)?;
+        deserializer.end()?;
 
-        match format {
-            Format::SingleExport => {
-                let loaded = single_loader(source)?;
-                self.fill_artifact(&mut artifact, loaded)?
-            }
-            Format::MultiExport => {
-                let loaded = multi_loader(source)?;
-                self.fill_artifact_multi(&mut artifact, loaded)?
-            }
-        }
-
-        Ok(artifact)
+        self.fill_artifact(origin, exports)
     }
 
     fn fill_artifact(
         &self,
-        artifact: &mut Artifact,
-        export: HardHatExport,
-    ) -> Result<(), ArtifactError> {
-        if self.network_allowed(&export.chain_id, &export.chain_name) {
+        origin: String,
+        exports: Vec<HardHatExport>,
+    ) -> Result<Artifact, ArtifactError> {
+        let mut artifact = Artifact::with_origin(origin);
+
+        for export in exports {
+            if !self.network_allowed(&export.chain_id, &export.chain_name) {
+                continue;
+            }
+
             for (name, contract) in export.contracts {
                 let HardHatContract {
                     address,
@@ -384,14 +390,10 @@ impl HardHatLoader {
                     mut contract,
                 } = contract;
 
-                if !self.contract_allowed(&name) {
-                    continue;
-                }
-
                 contract.name = name;
 
                 self.add_contract_to_artifact(
-                    artifact,
+                    &mut artifact,
                     contract,
                     export.chain_id.clone(),
                     address,
@@ -400,21 +402,7 @@ impl HardHatLoader {
             }
         }
 
-        Ok(())
-    }
-
-    fn fill_artifact_multi(
-        &self,
-        artifact: &mut Artifact,
-        export: HardHatMultiExport,
-    ) -> Result<(), ArtifactError> {
-        for (_, export) in export.networks {
-            for (_, export) in export {
-                self.fill_artifact(artifact, export)?;
-            }
-        }
-
-        Ok(())
+        Ok(artifact)
     }
 
     fn add_contract_to_artifact(
@@ -520,19 +508,172 @@ impl NetworkEntry {
     }
 }
 
-#[derive(Deserialize)]
-struct HardHatMultiExport {
-    #[serde(flatten)]
-    networks: HashMap<String, HashMap<String, HardHatExport>>,
-}
-
-#[derive(Deserialize)]
 struct HardHatExport {
-    #[serde(rename = "name")]
     chain_name: String,
-    #[serde(rename = "chainId")]
     chain_id: String,
-    contracts: HashMap<String, HardHatContract>,
+    contracts: Vec<(String, HardHatContract)>,
+}
+
+/// Deserializes the exports of an artifact, skipping filtered out networks
+/// and contracts.
+///
+/// For single exports this produces exactly one export. Multi-exports are
+/// JSON objects keyed by chain ID and then by network name, so networks that
+/// are not allowed are skipped entirely.
+#[derive(Clone, Copy)]
+struct ExportsSeed<'a> {
+    loader: &'a HardHatLoader,
+    format: Format,
+}
+
+impl<'a> ExportsSeed<'a> {
+    fn new(loader: &'a HardHatLoader, format: Format) -> Self {
+        ExportsSeed { loader, format }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for ExportsSeed<'_> {
+    type Value = Vec<HardHatExport>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        match self.format {
+            Format::SingleExport => ExportSeed(self.loader)
+                .deserialize(deserializer)
+                .map(|export| vec![export]),
+            Format::MultiExport => deserializer.deserialize_map(self),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for ExportsSeed<'_> {
+    type Value = Vec<HardHatExport>;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a map of chain IDs to hardhat exports")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut exports = Vec::new();
+        while let Some(chain_id) = map.next_key::<String>()? {
+            map.next_value_seed(NetworkExportsSeed {
+                loader: self.loader,
+                chain_id: &chain_id,
+                exports: &mut exports,
+            })?;
+        }
+        Ok(exports)
+    }
+}
+
+/// Deserializes the exports of all networks with a given chain ID.
+struct NetworkExportsSeed<'a> {
+    loader: &'a HardHatLoader,
+    chain_id: &'a str,
+    exports: &'a mut Vec<HardHatExport>,
+}
+
+impl<'de> DeserializeSeed<'de> for NetworkExportsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NetworkExportsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a map of network names to hardhat exports")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(chain_name) = map.next_key::<String>()? {
+            if self.loader.network_allowed(self.chain_id, &chain_name) {
+                self.exports
+                    .push(map.next_value_seed(ExportSeed(self.loader))?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes a single network export, skipping contracts that are not
+/// allowed.
+#[derive(Clone, Copy)]
+struct ExportSeed<'a>(&'a HardHatLoader);
+
+impl<'de> DeserializeSeed<'de> for ExportSeed<'_> {
+    type Value = HardHatExport;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ExportSeed<'_> {
+    type Value = HardHatExport;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a hardhat export")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut chain_name = None;
+        let mut chain_id = None;
+        let mut contracts = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => chain_name = Some(map.next_value()?),
+                "chainId" => chain_id = Some(map.next_value()?),
+                "contracts" => contracts = Some(map.next_value_seed(ContractsSeed(self.0))?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(HardHatExport {
+            chain_name: chain_name.ok_or_else(|| de::Error::missing_field("name"))?,
+            chain_id: chain_id.ok_or_else(|| de::Error::missing_field("chainId"))?,
+            contracts: contracts.ok_or_else(|| de::Error::missing_field("contracts"))?,
+        })
+    }
+}
+
+/// Deserializes the contracts of a network export, skipping contracts that are
+/// not allowed.
+#[derive(Clone, Copy)]
+struct ContractsSeed<'a>(&'a HardHatLoader);
+
+impl<'de> DeserializeSeed<'de> for ContractsSeed<'_> {
+    type Value = Vec<(String, HardHatContract)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ContractsSeed<'_> {
+    type Value = Vec<(String, HardHatContract)>;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a map of contract names to contracts")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut contracts = Vec::new();
+        while let Some(name) = map.next_key::<String>()? {
+            if self.0.contract_allowed(&name) {
+                contracts.push((name, map.next_value()?));
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(contracts)
+    }
 }
 
 #[derive(Deserialize)]
@@ -779,6 +920,49 @@ mod test {
         }
     }
 
+    #[test]
+    fn load_skips_filtered_out_entries() {
+        // Filtered out contracts and networks are skipped without being
+        // deserialized, so they may contain data that would fail to parse.
+        let json = r#"
+          {
+            "1": {
+              "mainnet": {
+                "name": "mainnet",
+                "chainId": "1",
+                "contracts": {
+                  "A": {
+                    "address": "0x000000000000000000000000000000000000000A"
+                  },
+                  "B": {
+                    "address": 42
+                  }
+                }
+              },
+              "mainnet_beta": {
+                "contracts": null
+              }
+            }
+          }
+        "#;
+
+        let artifact = HardHatLoader::new()
+            .allow_contract("A")
+            .deny_network_by_name("mainnet_beta")
+            .load_from_str(Format::MultiExport, json)
+            .unwrap();
+
+        assert_eq!(artifact.len(), 1);
+        assert_eq!(
+            artifact.get("A").unwrap().networks["1"].address,
+            address(0xA)
+        );
+
+        assert!(HardHatLoader::new()
+            .load_from_str(Format::MultiExport, json)
+            .is_err());
+    }
+
     static NETWORK_CONFLICTS: &str = r#"
       {
         "1": {
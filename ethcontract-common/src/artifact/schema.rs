@@ -0,0 +1,269 @@
+//! Detection and migration of artifact schema versions.
+//!
+//! The artifact schema used by Truffle changed over time, and contracts
+//! compiled directly with `solc --combined-json` use yet another layout. This
+//! module detects the schema of an artifact JSON document and migrates it to
+//! the current Truffle schema, so that artifacts keep loading after toolchain
+//! upgrades. Deprecated fields are reported as [`Diagnostic`]s.
+
+use crate::errors::ArtifactError;
+use serde_json::{Map, Value};
+use std::fmt::{self, Display, Formatter};
+
+/// The schema of an artifact JSON document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SchemaVersion {
+    /// Artifacts produced by Truffle 3 and earlier, which contain the contract
+    /// bytecode in `unlinked_binary` and its name in `contract_name`.
+    TruffleLegacy,
+
+    /// Artifacts produced by current versions of Truffle and by Waffle, which
+    /// contain the contract bytecode in `bytecode`.
+    Truffle,
+
+    /// Output of `solc --combined-json`, containing multiple contracts keyed
+    /// by `<source>:<name>`.
+    CombinedJson,
+}
+
+impl SchemaVersion {
+    /// Detects the schema of an artifact JSON document.
+    pub fn detect(json: &Value) -> Self {
+        let object = match json.as_object() {
+            Some(object) => object,
+            None => return SchemaVersion::Truffle,
+        };
+
+        if object.get("contracts").map_or(false, Value::is_object) && !object.contains_key("abi") {
+            SchemaVersion::CombinedJson
+        } else if object.contains_key("unlinked_binary") || object.contains_key("contract_name") {
+            SchemaVersion::TruffleLegacy
+        } else {
+            SchemaVersion::Truffle
+        }
+    }
+}
+
+/// A diagnostic about an artifact that was loaded successfully, but that uses
+/// deprecated fields which may stop being supported in the future.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// A JSON pointer to the deprecated field in the artifact.
+    pub pointer: String,
+
+    /// A description of the deprecation.
+    pub message: String,
+}
+
+/// A callback for diagnostics emitted while loading artifacts.
+pub type DiagnosticCallback = Box<dyn Fn(&Diagnostic) + Send + Sync>;
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.pointer, self.message)
+    }
+}
+
+/// Migrates an artifact JSON document to the current Truffle schema, returning
+/// one document per contract in the artifact.
+pub(crate) fn migrate(
+    json: Value,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) -> Result<Vec<Value>, ArtifactError> {
+    match SchemaVersion::detect(&json) {
+        SchemaVersion::TruffleLegacy => Ok(vec![migrate_legacy(json, diagnostics)?]),
+        SchemaVersion::Truffle => Ok(vec![json]),
+        SchemaVersion::CombinedJson => migrate_combined_json(json, diagnostics),
+    }
+}
+
+fn migrate_legacy(
+    mut json: Value,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) -> Result<Value, ArtifactError> {
+    if let Some(object) = json.as_object_mut() {
+        rename(object, "", "contract_name", "contractName", diagnostics);
+        rename(object, "", "unlinked_binary", "bytecode", diagnostics);
+        parse_embedded_json(object, "", "abi", diagnostics)?;
+    }
+    Ok(json)
+}
+
+fn migrate_combined_json(
+    json: Value,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) -> Result<Vec<Value>, ArtifactError> {
+    let contracts = match json {
+        Value::Object(mut object) => match object.remove("contracts") {
+            Some(Value::Object(contracts)) => contracts,
+            _ => Map::new(),
+        },
+        _ => Map::new(),
+    };
+
+    let mut migrated = Vec::with_capacity(contracts.len());
+    for (key, contract) in contracts {
+        let mut object = match contract {
+            Value::Object(object) => object,
+            _ => continue,
+        };
+        let root = format!("/contracts/{}", escape(&key));
+
+        let name = combined_json_name(&key).to_owned();
+        object.insert("contractName".to_owned(), Value::String(name));
+        if let Some(bin) = object.remove("bin") {
+            object.insert("bytecode".to_owned(), bin);
+        }
+        if let Some(layout) = object.remove("storage-layout") {
+            object.insert("storageLayout".to_owned(), layout);
+        }
+        for field in &["abi", "devdoc", "userdoc", "storageLayout"] {
+            parse_embedded_json(&mut object, &root, field, diagnostics)?;
+        }
+
+        migrated.push(Value::Object(object));
+    }
+
+    Ok(migrated)
+}
+
+/// Returns the name of a contract in `solc --combined-json` output from its
+/// `<source>:<name>` key.
+pub(crate) fn combined_json_name(key: &str) -> &str {
+    key.rsplit(':').next().unwrap_or(key)
+}
+
+/// Renames a deprecated field.
+fn rename(
+    object: &mut Map<String, Value>,
+    root: &str,
+    deprecated: &str,
+    field: &str,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) {
+    if let Some(value) = object.remove(deprecated) {
+        diagnostics(Diagnostic {
+            pointer: format!("{}/{}", root, escape(deprecated)),
+            message: format!(
+                "field `{}` is deprecated, use `{}` instead",
+                deprecated, field
+            ),
+        });
+        object.entry(field).or_insert(value);
+    }
+}
+
+/// Parses a field containing a JSON document encoded as a string, as emitted
+/// by older compilers.
+fn parse_embedded_json(
+    object: &mut Map<String, Value>,
+    root: &str,
+    field: &str,
+    diagnostics: &mut dyn FnMut(Diagnostic),
+) -> Result<(), ArtifactError> {
+    if let Some(value) = object.get_mut(field) {
+        if let Value::String(embedded) = value {
+            diagnostics(Diagnostic {
+                pointer: format!("{}/{}", root, escape(field)),
+                message: format!(
+                    "field `{}` is a JSON encoded string, which is deprecated",
+                    field
+                ),
+            });
+            *value = serde_json::from_str(embedded)?;
+        }
+    }
+    Ok(())
+}
+
+/// Escapes a JSON pointer reference token.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn migrate_with_diagnostics(json: Value) -> (Vec<Value>, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let migrated = migrate(json, &mut |diagnostic| diagnostics.push(diagnostic)).unwrap();
+        (migrated, diagnostics)
+    }
+
+    #[test]
+    fn detect_schema_version() {
+        for (json, version) in &[
+            (
+                json!({ "abi": [], "bytecode": "0x" }),
+                SchemaVersion::Truffle,
+            ),
+            (json!([]), SchemaVersion::Truffle),
+            (
+                json!({ "abi": [], "unlinked_binary": "0x" }),
+                SchemaVersion::TruffleLegacy,
+            ),
+            (
+                json!({ "contracts": {}, "version": "0.8.0" }),
+                SchemaVersion::CombinedJson,
+            ),
+        ] {
+            assert_eq!(SchemaVersion::detect(json), *version);
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_truffle() {
+        let (migrated, diagnostics) = migrate_with_diagnostics(json!({
+            "contract_name": "Token",
+            "abi": "[]",
+            "unlinked_binary": "0x00",
+        }));
+
+        assert_eq!(
+            migrated,
+            vec![json!({ "contractName": "Token", "abi": [], "bytecode": "0x00" })],
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.pointer.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/contract_name", "/unlinked_binary", "/abi"],
+        );
+    }
+
+    #[test]
+    fn migrate_combined_json_contracts() {
+        let (migrated, diagnostics) = migrate_with_diagnostics(json!({
+            "contracts": {
+                "contracts/Token.sol:Token": {
+                    "abi": [],
+                    "bin": "00",
+                    "devdoc": "{\"methods\":{}}",
+                    "storage-layout": { "storage": [], "types": null },
+                },
+            },
+            "version": "0.7.6",
+        }));
+
+        assert_eq!(
+            migrated,
+            vec![json!({
+                "contractName": "Token",
+                "abi": [],
+                "bytecode": "00",
+                "devdoc": { "methods": {} },
+                "storageLayout": { "storage": [], "types": null },
+            })],
+        );
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                pointer: "/contracts/contracts~1Token.sol:Token/devdoc".to_owned(),
+                message: "field `devdoc` is a JSON encoded string, which is deprecated".to_owned(),
+            }],
+        );
+    }
+}
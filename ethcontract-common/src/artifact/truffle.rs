@@ -9,11 +9,21 @@
 //! - `bytecode`: contract's compiled bytecode (optional);
 //! - `networks`: info about known contract deployments (optional);
 //! - `devdoc`, `userdoc`: additional documentation for contract's methods.
+//!
+//! Artifacts using the legacy Truffle schema and the output of
+//! `solc --combined-json` are detected and migrated automatically, see
+//! [`schema`](crate::artifact::schema) for more details. When a contract is
+//! selected by name, the other contracts of `solc --combined-json` output are
+//! skipped while parsing, so that only the selected contract is kept in
+//! memory.
 
+use crate::artifact::schema::{self, Diagnostic, DiagnosticCallback};
 use crate::artifact::Artifact;
 use crate::errors::ArtifactError;
 use crate::Contract;
-use serde_json::{from_reader, from_slice, from_str, from_value, to_string, Value};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_json::{from_value, to_string, Deserializer as JsonDeserializer, Map, Value};
+use std::fmt::{self, Debug, Formatter};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
@@ -25,10 +35,18 @@ pub struct TruffleLoader {
     /// If empty, origin will be derived automatically.
     pub origin: Option<String>,
 
-    /// Override for contract's name.
+    /// Name of the contract to load.
     ///
-    /// Truffle artifacts contain a single contract which may be unnamed.
+    /// Truffle artifacts contain a single contract which may be unnamed, in
+    /// which case it is given this name. Loading a contract with a different
+    /// name fails with [`ArtifactError::ContractNotFound`]. For
+    /// `solc --combined-json` output, this selects the contract to load.
     pub name: Option<String>,
+
+    /// Callback for diagnostics about deprecated fields in loaded artifacts.
+    ///
+    /// If empty, diagnostics are ignored.
+    pub diagnostics: Option<DiagnosticCallback>,
 }
 
 impl TruffleLoader {
@@ -37,6 +55,7 @@ impl TruffleLoader {
         TruffleLoader {
             origin: None,
             name: None,
+            diagnostics: None,
         }
     }
 
@@ -45,6 +64,7 @@ impl TruffleLoader {
         TruffleLoader {
             origin: Some(origin.into()),
             name: None,
+            diagnostics: None,
         }
     }
 
@@ -56,7 +76,7 @@ impl TruffleLoader {
         self
     }
 
-    /// Sets new override for artifact's name. See [`name`] for more info.
+    /// Sets the name of the contract to load. See [`name`] for more info.
     ///
     /// [`name`]: #structfield.name
     pub fn name(mut self, name: impl Into<String>) -> Self {
@@ -64,24 +84,33 @@ impl TruffleLoader {
         self
     }
 
+    /// Sets a callback for diagnostics about deprecated fields in loaded
+    /// artifacts. See [`diagnostics`] for more info.
+    ///
+    /// [`diagnostics`]: #structfield.diagnostics
+    pub fn diagnostics(mut self, callback: impl Fn(&Diagnostic) + Send + Sync + 'static) -> Self {
+        self.diagnostics = Some(Box::new(callback));
+        self
+    }
+
     /// Loads an artifact from a loaded JSON value.
     pub fn load_from_reader(&self, v: impl Read) -> Result<Artifact, ArtifactError> {
-        self.load_artifact("<unknown>", v, from_reader)
+        self.load_artifact("<unknown>", self.read(JsonDeserializer::from_reader(v))?)
     }
 
     /// Loads an artifact from bytes of JSON text.
     pub fn load_from_slice(&self, v: &[u8]) -> Result<Artifact, ArtifactError> {
-        self.load_artifact("<unknown>", v, from_slice)
+        self.load_artifact("<unknown>", self.read(JsonDeserializer::from_slice(v))?)
     }
 
     /// Loads an artifact from string of JSON text.
     pub fn load_from_str(&self, v: &str) -> Result<Artifact, ArtifactError> {
-        self.load_artifact("<unknown>", v, from_str)
+        self.load_artifact("<unknown>", self.read(JsonDeserializer::from_str(v))?)
     }
 
     /// Loads an artifact from a loaded JSON value.
     pub fn load_from_value(&self, v: Value) -> Result<Artifact, ArtifactError> {
-        self.load_artifact("<unknown>", v, from_value)
+        self.load_artifact("<unknown>", self.select(v)?)
     }
 
     /// Loads an artifact from disk.
@@ -89,27 +118,30 @@ impl TruffleLoader {
         let path = p.as_ref();
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        self.load_artifact(path.display(), reader, from_reader)
+        self.load_artifact(
+            path.display(),
+            self.read(JsonDeserializer::from_reader(reader))?,
+        )
     }
 
     /// Loads a contract from a loaded JSON value.
     pub fn load_contract_from_reader(&self, v: impl Read) -> Result<Contract, ArtifactError> {
-        self.load_contract(v, from_reader)
+        self.load_contract(self.read(JsonDeserializer::from_reader(v))?)
     }
 
     /// Loads a contract from bytes of JSON text.
     pub fn load_contract_from_slice(&self, v: &[u8]) -> Result<Contract, ArtifactError> {
-        self.load_contract(v, from_slice)
+        self.load_contract(self.read(JsonDeserializer::from_slice(v))?)
     }
 
     /// Loads a contract from string of JSON text.
     pub fn load_contract_from_str(&self, v: &str) -> Result<Contract, ArtifactError> {
-        self.load_contract(v, from_str)
+        self.load_contract(self.read(JsonDeserializer::from_str(v))?)
     }
 
     /// Loads a contract from a loaded JSON value.
     pub fn load_contract_from_value(&self, v: Value) -> Result<Contract, ArtifactError> {
-        self.load_contract(v, from_value)
+        self.load_contract(self.select(v)?)
     }
 
     /// Loads a contract from disk.
@@ -117,32 +149,83 @@ impl TruffleLoader {
         let path = p.as_ref();
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        self.load_contract(reader, from_reader)
+        self.load_contract(self.read(JsonDeserializer::from_reader(reader))?)
     }
 
-    fn load_artifact<T>(
+    /// Reads an artifact JSON document from a JSON deserializer.
+    ///
+    /// The document is deserialized in a streaming fashion, contracts of
+    /// `solc --combined-json` output that are not selected by name are skipped
+    /// without being materialized. This keeps memory usage low when a single
+    /// contract is needed from the combined output of a large project.
+    fn read<'de, R: serde_json::de::Read<'de>>(
         &self,
-        origin: impl ToString,
-        source: T,
-        loader: impl FnOnce(T) -> serde_json::Result<Contract>,
-    ) -> Result<Artifact, ArtifactError> {
+        mut deserializer: JsonDeserializer<R>,
+    ) -> Result<Value, ArtifactError> {
+        let json = DocumentSeed(self.name.as_deref()).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(json)
+    }
+
+    /// Selects the contracts of an already loaded artifact JSON document, in
+    /// the same way as [`TruffleLoader::read`].
+    fn select(&self, json: Value) -> Result<Value, ArtifactError> {
+        match self.name {
+            Some(_) => Ok(DocumentSeed(self.name.as_deref()).deserialize(json)?),
+            None => Ok(json),
+        }
+    }
+
+    fn load_artifact(&self, origin: impl ToString, json: Value) -> Result<Artifact, ArtifactError> {
         let origin = self.origin.clone().unwrap_or_else(|| origin.to_string());
         let mut artifact = Artifact::with_origin(origin);
-        artifact.insert(self.load_contract(source, loader)?);
+        let contracts = match self.name {
+            Some(_) => vec![self.load_contract(json)?],
+            None => self.load_contracts(json)?,
+        };
+        for contract in contracts {
+            artifact.insert(contract);
+        }
         Ok(artifact)
     }
 
-    fn load_contract<T>(
-        &self,
-        source: T,
-        loader: impl FnOnce(T) -> serde_json::Result<Contract>,
-    ) -> Result<Contract, ArtifactError> {
-        let mut contract: Contract = loader(source)?;
-
-        if let Some(name) = &self.name {
-            contract.name = name.clone();
+    fn load_contract(&self, json: Value) -> Result<Contract, ArtifactError> {
+        let mut contracts = self.load_contracts(json)?;
+        if contracts.len() == 1 {
+            return self.rename(contracts.remove(0));
         }
 
+        let name = self
+            .name
+            .as_ref()
+            .ok_or(ArtifactError::AmbiguousContract(contracts.len()))?;
+        contracts
+            .into_iter()
+            .find(|contract| contract.name == *name)
+            .ok_or_else(|| ArtifactError::ContractNotFound(name.clone()))
+    }
+
+    fn load_contracts(&self, json: Value) -> Result<Vec<Contract>, ArtifactError> {
+        let mut diagnostics = |diagnostic: Diagnostic| {
+            if let Some(callback) = &self.diagnostics {
+                callback(&diagnostic);
+            }
+        };
+
+        schema::migrate(json, &mut diagnostics)?
+            .into_iter()
+            .map(|json| from_value(json).map_err(ArtifactError::from))
+            .collect()
+    }
+
+    fn rename(&self, mut contract: Contract) -> Result<Contract, ArtifactError> {
+        match &self.name {
+            Some(name) if contract.name.is_empty() => contract.name = name.clone(),
+            Some(name) if contract.name != *name => {
+                return Err(ArtifactError::ContractNotFound(name.clone()))
+            }
+            _ => {}
+        }
         Ok(contract)
     }
 
@@ -152,8 +235,220 @@ impl TruffleLoader {
     }
 }
 
+impl Debug for TruffleLoader {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("TruffleLoader")
+            .field("origin", &self.origin)
+            .field("name", &self.name)
+            .field("diagnostics", &self.diagnostics.is_some())
+            .finish()
+    }
+}
+
 impl Default for TruffleLoader {
     fn default() -> Self {
         TruffleLoader::new()
     }
 }
+
+/// Deserializes an artifact JSON document, skipping the contracts of
+/// `solc --combined-json` output that are not selected by name.
+#[derive(Clone, Copy)]
+struct DocumentSeed<'a>(Option<&'a str>);
+
+impl<'de> DeserializeSeed<'de> for DocumentSeed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DocumentSeed<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("an artifact JSON document")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut object = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let value = match (key.as_str(), self.0) {
+                ("contracts", Some(name)) => map.next_value_seed(ContractsSeed(name))?,
+                _ => map.next_value()?,
+            };
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+/// Deserializes the contracts of `solc --combined-json` output, keeping only
+/// the contract with the specified name.
+#[derive(Clone, Copy)]
+struct ContractsSeed<'a>(&'a str);
+
+impl<'de> DeserializeSeed<'de> for ContractsSeed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ContractsSeed<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a map of contract keys to contracts")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut contracts = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if schema::combined_json_name(&key) == self.0 {
+                contracts.insert(key, map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(Value::Object(contracts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn load_legacy_artifact_with_diagnostics() {
+        let diagnostics = Arc::new(Mutex::new(Vec::new()));
+        let loader = TruffleLoader::new().diagnostics({
+            let diagnostics = diagnostics.clone();
+            move |diagnostic| diagnostics.lock().unwrap().push(diagnostic.clone())
+        });
+
+        let contract = loader
+            .load_contract_from_str(
+                r#"{"contract_name":"Token","abi":[],"unlinked_binary":"0x00"}"#,
+            )
+            .unwrap();
+
+        assert_eq!(contract.name, "Token");
+        assert!(!contract.bytecode.is_empty());
+        assert_eq!(diagnostics.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn load_combined_json() {
+        let json = r#"{
+            "contracts": {
+                "Token.sol:Token": { "abi": "[]", "bin": "00" },
+                "Token.sol:SafeMath": { "abi": [], "bin": "" }
+            },
+            "version": "0.6.12"
+        }"#;
+
+        let artifact = TruffleLoader::new().load_from_str(json).unwrap();
+        assert_eq!(artifact.len(), 2);
+        assert!(artifact.contains("Token") && artifact.contains("SafeMath"));
+
+        assert!(matches!(
+            TruffleLoader::new().load_contract_from_str(json),
+            Err(ArtifactError::AmbiguousContract(2))
+        ));
+        let contract = TruffleLoader::new()
+            .name("Token")
+            .load_contract_from_str(json)
+            .unwrap();
+        assert!(!contract.bytecode.is_empty());
+        assert!(matches!(
+            TruffleLoader::new().name("Missing").load_contract_from_str(json),
+            Err(ArtifactError::ContractNotFound(name)) if name == "Missing"
+        ));
+    }
+
+    #[test]
+    fn load_combined_json_only_migrates_selected_contract() {
+        // NOTE: The ABI of `Broken` is invalid, so loading fails if any
+        //   contract other than the selected one is migrated.
+        let json = r#"{
+            "contracts": {
+                "Token.sol:Token": { "abi": "[]", "bin": "00" },
+                "Broken.sol:Broken": { "abi": "not json", "bin": "" }
+            }
+        }"#;
+
+        assert!(TruffleLoader::new().load_from_str(json).is_err());
+        let artifact = TruffleLoader::new()
+            .name("Token")
+            .load_from_str(json)
+            .unwrap();
+        assert_eq!(artifact.len(), 1);
+        assert!(artifact.contains("Token"));
+        let contract = TruffleLoader::new()
+            .name("Token")
+            .load_contract_from_value(serde_json::from_str(json).unwrap())
+            .unwrap();
+        assert_eq!(contract.name, "Token");
+    }
+
+    #[test]
+    fn stream_selected_combined_json_contract() {
+        let read = |name: &str, json: &str| {
+            TruffleLoader::new()
+                .name(name)
+                .read(JsonDeserializer::from_str(json))
+                .unwrap()
+        };
+
+        let json = r#"{
+            "contracts": {
+                "SafeMath.sol:SafeMath": { "abi": [], "bin": "" },
+                "Token.sol:Token": { "abi": [], "bin": "00" },
+                "Ownable.sol:Ownable": { "abi": [], "bin": "" }
+            },
+            "version": "0.8.0"
+        }"#;
+        assert_eq!(
+            read("Token", json),
+            serde_json::json!({
+                "contracts": {
+                    "Token.sol:Token": { "abi": [], "bin": "00" },
+                },
+                "version": "0.8.0",
+            }),
+        );
+
+        let json = r#"{ "contracts": { "Token.sol:Token": { "abi": [], "bin": "" } } }"#;
+        assert_eq!(
+            read("Missing", json),
+            serde_json::json!({ "contracts": {} })
+        );
+        assert!(matches!(
+            TruffleLoader::new().name("Missing").load_contract_from_str(json),
+            Err(ArtifactError::ContractNotFound(name)) if name == "Missing"
+        ));
+    }
+
+    #[test]
+    fn name_only_unnamed_contracts() {
+        let contract = TruffleLoader::new()
+            .name("Token")
+            .load_contract_from_str(r#"{"abi":[]}"#)
+            .unwrap();
+        assert_eq!(contract.name, "Token");
+
+        let json = r#"{"contractName":"Token","abi":[]}"#;
+        assert!(TruffleLoader::new()
+            .name("Token")
+            .load_from_str(json)
+            .is_ok());
+        assert!(matches!(
+            TruffleLoader::new().name("Other").load_from_str(json),
+            Err(ArtifactError::ContractNotFound(name)) if name == "Other"
+        ));
+    }
+}
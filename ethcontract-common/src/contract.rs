@@ -1,5 +1,6 @@
 //! Module for reading and examining data produced by truffle.
 
+use crate::storage::StorageLayout;
 use crate::Abi;
 use crate::{bytecode::Bytecode, DeploymentInformation};
 use serde::{Deserialize, Serialize};
@@ -23,6 +24,14 @@ pub struct Contract {
     pub devdoc: Documentation,
     /// The user documentation.
     pub userdoc: Documentation,
+    /// The storage layout, if the contract was compiled with the
+    /// `storageLayout` output selection.
+    #[serde(
+        rename = "storageLayout",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub storage_layout: Option<StorageLayout>,
 }
 
 impl Contract {
@@ -46,6 +55,7 @@ impl Contract {
             networks: HashMap::new(),
             devdoc: Default::default(),
             userdoc: Default::default(),
+            storage_layout: None,
         }
     }
 }
@@ -22,6 +22,15 @@ pub enum ArtifactError {
     /// Contract have multiple deployment addresses on the same chain.
     #[error("chain with id {0} appears several times in the artifact")]
     DuplicateChain(String),
+
+    /// The artifact contains multiple contracts, and the contract to load was
+    /// not specified.
+    #[error("artifact contains {0} contracts, a contract name must be specified")]
+    AmbiguousContract(usize),
+
+    /// The artifact does not contain the requested contract.
+    #[error("artifact does not contain contract {0}")]
+    ContractNotFound(String),
 }
 
 /// An error reading bytecode string representation.
@@ -59,3 +68,68 @@ pub enum LinkError {
 #[derive(Clone, Debug, Error)]
 #[error("'{0}' is not a valid Solidity type")]
 pub struct ParseParamTypeError(pub String);
+
+/// An error parsing a human readable function or event signature.
+#[derive(Clone, Debug, Error)]
+pub enum ParseSignatureError {
+    /// The signature is malformed.
+    #[error("invalid signature '{0}'")]
+    Invalid(String),
+
+    /// A parameter in the signature is not a valid Solidity type.
+    #[error(transparent)]
+    ParamType(#[from] ParseParamTypeError),
+}
+
+/// An error parsing a hex address string.
+#[derive(Clone, Debug, Error)]
+pub enum ParseAddressError {
+    /// The address string does not start with `0x`.
+    #[error("address must start with '0x'")]
+    MissingPrefix,
+
+    /// The address string is not 20 bytes of hex data.
+    #[error("'{0}' is not a valid hex address")]
+    InvalidHex(String),
+
+    /// The mixed-case address string does not have a valid EIP-55 checksum.
+    /// Contains the correctly checksummed address.
+    #[error("invalid address checksum, expected {0}")]
+    InvalidChecksum(String),
+
+    /// The address string does not have an ERC-3770 chain short name prefix,
+    /// or the prefix is not a valid short name.
+    #[error("'{0}' is not a valid chain-prefixed address")]
+    InvalidChainPrefix(String),
+
+    /// The address string is not a valid ICAP address, or its checksum is
+    /// invalid.
+    #[error("'{0}' is not a valid ICAP address")]
+    InvalidIcap(String),
+}
+
+/// An error parsing or verifying IPFS content.
+#[derive(Clone, Debug, Error)]
+pub enum IpfsError {
+    /// The IPFS path is not a valid `ipfs://` URI or `/ipfs/` path.
+    #[error("invalid IPFS path '{0}'")]
+    InvalidPath(String),
+
+    /// The content identifier is invalid or uses an unsupported encoding or
+    /// hash function.
+    #[error("unsupported IPFS content identifier '{0}'")]
+    UnsupportedCid(String),
+
+    /// The retrieved content does not match its content identifier.
+    #[error("content retrieved for '{0}' does not match its content identifier")]
+    HashMismatch(String),
+
+    /// The retrieved content cannot be verified against its content
+    /// identifier, and verification is required.
+    #[error("content retrieved for '{0}' cannot be verified")]
+    Unverifiable(String),
+
+    /// No IPFS gateways are configured.
+    #[error("no IPFS gateways configured")]
+    NoGateways,
+}
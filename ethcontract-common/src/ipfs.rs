@@ -0,0 +1,462 @@
+//! Utilities for retrieving content from IPFS through HTTP gateways, and for
+//! verifying the retrieved content against its content identifier (CID).
+//!
+//! This module does not perform any requests itself, instead it provides the
+//! gateway URLs to request content from and verification of the responses.
+//! It is used both for retrieving artifacts published to IPFS and token
+//! metadata.
+
+use crate::errors::IpfsError;
+use crate::hash::sha256;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The default public IPFS gateways.
+pub const DEFAULT_GATEWAYS: &[&str] = &["https://ipfs.io/ipfs/", "https://dweb.link/ipfs/"];
+
+/// The multicodec for raw binary content.
+const RAW: u64 = 0x55;
+
+/// The multicodec for MerkleDAG protobuf nodes.
+const DAG_PB: u64 = 0x70;
+
+/// The multihash code for SHA-256.
+const SHA2_256: u8 = 0x12;
+
+/// The maximum size of a file that is stored in a single block with the
+/// default IPFS chunker.
+const MAX_BLOCK_SIZE: usize = 256 * 1024;
+
+/// An IPFS content identifier for content hashed with SHA-256.
+///
+/// Both version 0 CIDs (`Qm...`) and version 1 CIDs in base32 (`b...`) or
+/// base58 (`z...`) encoding are supported.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Cid {
+    encoded: String,
+    codec: u64,
+    digest: [u8; 32],
+}
+
+impl Cid {
+    /// Creates a version 0 CID (`Qm...`) for a MerkleDAG protobuf node with
+    /// the specified SHA-256 digest, as embedded by the Solidity compiler in
+    /// the metadata of deployed contracts.
+    pub fn v0(digest: [u8; 32]) -> Self {
+        let mut multihash = vec![SHA2_256, 32];
+        multihash.extend_from_slice(&digest);
+        Cid {
+            encoded: encode_base58(&multihash),
+            codec: DAG_PB,
+            digest,
+        }
+    }
+
+    /// Returns the multicodec of the content, for example `0x55` for raw
+    /// binary content or `0x70` for MerkleDAG protobuf nodes.
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// Returns the SHA-256 digest of the content.
+    pub fn digest(&self) -> &[u8; 32] {
+        &self.digest
+    }
+
+    /// Verifies that the content of a file matches the CID.
+    ///
+    /// Returns `None` if the content cannot be verified, this is the case for
+    /// files that are too large to fit in a single block and are split across
+    /// multiple blocks.
+    pub fn verify(&self, content: &[u8]) -> Option<bool> {
+        let digest = match self.codec {
+            RAW => sha256(content),
+            DAG_PB if content.len() <= MAX_BLOCK_SIZE => sha256(unixfs_file_node(content)),
+            _ => return None,
+        };
+        Some(digest == self.digest)
+    }
+}
+
+impl Display for Cid {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.encoded)
+    }
+}
+
+impl FromStr for Cid {
+    type Err = IpfsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unsupported = || IpfsError::UnsupportedCid(s.to_owned());
+
+        let (codec, multihash) = if s.len() == 46 && s.starts_with("Qm") {
+            (DAG_PB, decode_base58(s).ok_or_else(unsupported)?)
+        } else {
+            let bytes = match s.as_bytes().first() {
+                Some(b'b') => decode_base32(&s[1..]),
+                Some(b'z') => decode_base58(&s[1..]),
+                _ => None,
+            }
+            .ok_or_else(unsupported)?;
+
+            let mut rest = &bytes[..];
+            let version = decode_varint(&mut rest).ok_or_else(unsupported)?;
+            let codec = decode_varint(&mut rest).ok_or_else(unsupported)?;
+            if version != 1 {
+                return Err(unsupported());
+            }
+            (codec, rest.to_vec())
+        };
+
+        match &multihash[..] {
+            [SHA2_256, 32, digest @ ..] if digest.len() == 32 => {
+                let mut cid = Cid {
+                    encoded: s.to_owned(),
+                    codec,
+                    digest: [0; 32],
+                };
+                cid.digest.copy_from_slice(digest);
+                Ok(cid)
+            }
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+/// A path to IPFS content, consisting of a CID and an optional path within
+/// the content if it is a directory.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct IpfsPath {
+    cid: Cid,
+    path: String,
+}
+
+impl IpfsPath {
+    /// Returns the content identifier.
+    pub fn cid(&self) -> &Cid {
+        &self.cid
+    }
+
+    /// Returns the path within the content, for example `/artifact.json`. This
+    /// is empty if the path refers to the content of the CID itself.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Display for IpfsPath {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "ipfs://{}{}", self.cid, self.path)
+    }
+}
+
+impl FromStr for IpfsPath {
+    type Err = IpfsError;
+
+    /// Parses an `ipfs://` URI or a `/ipfs/` path.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = s
+            .strip_prefix("ipfs://")
+            .map(|path| path.strip_prefix("ipfs/").unwrap_or(path))
+            .or_else(|| s.strip_prefix("/ipfs/"))
+            .ok_or_else(|| IpfsError::InvalidPath(s.to_owned()))?;
+        let (cid, path) = path.split_at(path.find('/').unwrap_or(path.len()));
+
+        Ok(IpfsPath {
+            cid: cid.parse()?,
+            path: path.trim_end_matches('/').to_owned(),
+        })
+    }
+}
+
+/// Configuration of the IPFS gateways used for retrieving content.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IpfsGateways {
+    gateways: Vec<String>,
+    require_verified: bool,
+}
+
+impl IpfsGateways {
+    /// Creates a new gateway configuration with the specified gateway URLs,
+    /// for example `https://ipfs.io/ipfs/`. Gateways are tried in order until
+    /// one of them returns valid content.
+    pub fn new<I, S>(gateways: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        IpfsGateways {
+            gateways: gateways.into_iter().map(Into::into).collect(),
+            require_verified: false,
+        }
+    }
+
+    /// Specify whether or not content that cannot be verified against its CID
+    /// should be rejected. This is the case for content at a path within a
+    /// directory, or for files that are split across multiple blocks. Content
+    /// that can be verified is always verified.
+    pub fn require_verified(mut self, value: bool) -> Self {
+        self.require_verified = value;
+        self
+    }
+
+    /// Returns the gateways.
+    pub fn gateways(&self) -> &[String] {
+        &self.gateways
+    }
+
+    /// Returns the gateway URLs for retrieving content, in the order that they
+    /// should be tried.
+    pub fn urls(&self, path: &IpfsPath) -> Vec<String> {
+        self.gateways
+            .iter()
+            .map(|gateway| {
+                format!(
+                    "{}/{}{}",
+                    gateway.trim_end_matches('/'),
+                    path.cid,
+                    path.path
+                )
+            })
+            .collect()
+    }
+
+    /// Verifies content retrieved from a gateway against its CID.
+    pub fn verify(&self, path: &IpfsPath, content: &[u8]) -> Result<(), IpfsError> {
+        let verified = if path.path.is_empty() {
+            path.cid.verify(content)
+        } else {
+            None
+        };
+
+        match verified {
+            Some(true) => Ok(()),
+            Some(false) => Err(IpfsError::HashMismatch(path.to_string())),
+            None if self.require_verified => Err(IpfsError::Unverifiable(path.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for IpfsGateways {
+    fn default() -> Self {
+        IpfsGateways::new(DEFAULT_GATEWAYS.iter().copied())
+    }
+}
+
+/// Encodes the MerkleDAG protobuf node of a file that fits in a single block,
+/// as it is added by IPFS with the default settings.
+fn unixfs_file_node(content: &[u8]) -> Vec<u8> {
+    // NOTE: The node only has a `Data` field containing a UnixFS `File`
+    //   message with the file content and size.
+    let mut data = vec![0x08, 0x02];
+    if !content.is_empty() {
+        data.push(0x12);
+        encode_varint(&mut data, content.len() as u64);
+        data.extend_from_slice(content);
+    }
+    data.push(0x18);
+    encode_varint(&mut data, content.len() as u64);
+
+    let mut node = vec![0x0a];
+    encode_varint(&mut node, data.len() as u64);
+    node.extend(data);
+    node
+}
+
+fn encode_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn decode_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for (i, byte) in data.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn decode_base58(s: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    let mut bytes = Vec::<u8>::new();
+    for c in s.bytes() {
+        let mut carry = ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    let mut decoded = vec![0; zeros];
+    decoded.extend(bytes);
+    Some(decoded)
+}
+
+fn encode_base58(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    let mut digits = Vec::<u8>::new();
+    for byte in bytes {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut().rev() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.insert(0, (carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    std::iter::repeat(b'1')
+        .take(zeros)
+        .chain(digits.into_iter().map(|digit| ALPHABET[digit as usize]))
+        .map(char::from)
+        .collect()
+}
+
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+    let mut decoded = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in s.bytes() {
+        buffer = (buffer << 5) | ALPHABET.iter().position(|a| *a == c)? as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // CIDs retrieved by adding files with `ipfs add`, with and without the
+    // `--cid-version=1` flag.
+    const EMPTY_FILE: &str = "QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH";
+    const HELLO_WORLD: &str = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+    const HELLO_WORLD_V1: &str = "bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2devei4";
+
+    #[test]
+    fn parse_cid() {
+        let cid = HELLO_WORLD.parse::<Cid>().unwrap();
+        assert_eq!(cid.codec(), DAG_PB);
+        assert_eq!(cid.to_string(), HELLO_WORLD);
+
+        let cid = HELLO_WORLD_V1.parse::<Cid>().unwrap();
+        assert_eq!(cid.codec(), RAW);
+        assert_eq!(cid.digest(), &sha256("hello world\n"));
+
+        for invalid in &["", "Qm", "QmInvalid0OIl", "bafy", "kinvalid"] {
+            assert!(invalid.parse::<Cid>().is_err());
+        }
+    }
+
+    #[test]
+    fn cid_v0_from_digest() {
+        for encoded in &[EMPTY_FILE, HELLO_WORLD] {
+            let cid = encoded.parse::<Cid>().unwrap();
+            assert_eq!(Cid::v0(*cid.digest()), cid);
+        }
+        assert_eq!(encode_base58(&[0, 0, 1]), "112");
+    }
+
+    #[test]
+    fn verify_content() {
+        let cid = EMPTY_FILE.parse::<Cid>().unwrap();
+        assert_eq!(cid.verify(b""), Some(true));
+
+        for cid in &[HELLO_WORLD, HELLO_WORLD_V1] {
+            let cid = cid.parse::<Cid>().unwrap();
+            assert_eq!(cid.verify(b"hello world\n"), Some(true));
+            assert_eq!(cid.verify(b"hello world"), Some(false));
+        }
+
+        let cid = HELLO_WORLD.parse::<Cid>().unwrap();
+        assert_eq!(cid.verify(&vec![0; MAX_BLOCK_SIZE + 1]), None);
+    }
+
+    #[test]
+    fn parse_ipfs_path() {
+        let path = format!("ipfs://{}/build/Contract.json", HELLO_WORLD)
+            .parse::<IpfsPath>()
+            .unwrap();
+        assert_eq!(path.cid().to_string(), HELLO_WORLD);
+        assert_eq!(path.path(), "/build/Contract.json");
+
+        for uri in &[
+            format!("ipfs://{}", HELLO_WORLD),
+            format!("ipfs://ipfs/{}/", HELLO_WORLD),
+            format!("/ipfs/{}", HELLO_WORLD),
+        ] {
+            let path = uri.parse::<IpfsPath>().unwrap();
+            assert_eq!(path.to_string(), format!("ipfs://{}", HELLO_WORLD));
+        }
+
+        assert!(HELLO_WORLD.parse::<IpfsPath>().is_err());
+        assert!("https://ipfs.io/ipfs/Qm".parse::<IpfsPath>().is_err());
+    }
+
+    #[test]
+    fn gateway_urls() {
+        let gateways = IpfsGateways::new(vec!["https://one.test/ipfs/", "https://two.test/ipfs"]);
+        let path = format!("ipfs://{}/1.json", HELLO_WORLD)
+            .parse::<IpfsPath>()
+            .unwrap();
+
+        assert_eq!(
+            gateways.urls(&path),
+            vec![
+                format!("https://one.test/ipfs/{}/1.json", HELLO_WORLD),
+                format!("https://two.test/ipfs/{}/1.json", HELLO_WORLD),
+            ],
+        );
+    }
+
+    #[test]
+    fn verify_gateway_content() {
+        let file = format!("ipfs://{}", HELLO_WORLD)
+            .parse::<IpfsPath>()
+            .unwrap();
+        let nested = format!("ipfs://{}/1.json", HELLO_WORLD)
+            .parse::<IpfsPath>()
+            .unwrap();
+
+        let gateways = IpfsGateways::default();
+        assert!(gateways.verify(&file, b"hello world\n").is_ok());
+        assert!(matches!(
+            gateways.verify(&file, b"evil"),
+            Err(IpfsError::HashMismatch(_))
+        ));
+        assert!(gateways.verify(&nested, b"anything").is_ok());
+
+        let gateways = gateways.require_verified(true);
+        assert!(matches!(
+            gateways.verify(&nested, b"anything"),
+            Err(IpfsError::Unverifiable(_))
+        ));
+    }
+}
@@ -4,11 +4,15 @@
 //! the `ethcontract-derive` crate.
 
 pub mod abiext;
+pub mod address;
 pub mod artifact;
 pub mod bytecode;
 pub mod contract;
 pub mod errors;
 pub mod hash;
+pub mod ipfs;
+pub mod signature;
+pub mod storage;
 
 pub use crate::abiext::FunctionExt;
 pub use crate::bytecode::Bytecode;
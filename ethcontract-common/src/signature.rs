@@ -0,0 +1,219 @@
+//! Parsing of human readable function and event signatures.
+//!
+//! Functions are described by signatures such as `transfer(address,uint256)`,
+//! optionally followed by their outputs as in `balanceOf(address)(uint256)`.
+//! Events are described by signatures that mark indexed parameters, such as
+//! `Transfer(address indexed,address indexed,uint256)`.
+
+use crate::abi::{Event, EventParam, Function, Param, ParamType, StateMutability};
+use crate::abiext::ParamTypeExt;
+use crate::errors::ParseSignatureError;
+
+/// Parses a function signature into an ABI function specification.
+///
+/// Parameters in the resulting function are unnamed. If the signature does not
+/// specify outputs, then the function will have none.
+pub fn parse_function(signature: &str) -> Result<Function, ParseSignatureError> {
+    let (name, params) = split_signature(signature)?;
+    let (inputs, rest) = take_group(params).ok_or_else(|| invalid_signature(signature))?;
+    let outputs = match rest.trim() {
+        "" => "",
+        rest => match take_group(rest) {
+            Some((outputs, "")) => outputs,
+            _ => return Err(invalid_signature(signature)),
+        },
+    };
+
+    let params = |group: &str| -> Result<Vec<Param>, ParseSignatureError> {
+        Ok(parse_types(group)?
+            .into_iter()
+            .map(|kind| Param {
+                name: String::new(),
+                kind,
+            })
+            .collect())
+    };
+
+    #[allow(deprecated)]
+    let function = Function {
+        name: name.to_owned(),
+        inputs: params(inputs)?,
+        outputs: params(outputs)?,
+        constant: false,
+        state_mutability: StateMutability::NonPayable,
+    };
+    Ok(function)
+}
+
+/// Parses an event signature into an ABI event specification.
+///
+/// Indexed parameters are marked with the `indexed` keyword, and anonymous
+/// events are marked with a trailing `anonymous` keyword.
+pub fn parse_event(signature: &str) -> Result<Event, ParseSignatureError> {
+    let (name, params) = split_signature(signature)?;
+    let (params, rest) = take_group(params).ok_or_else(|| invalid_signature(signature))?;
+    let anonymous = match rest.trim() {
+        "" => false,
+        "anonymous" => true,
+        _ => return Err(invalid_signature(signature)),
+    };
+
+    let inputs = split_params(params)
+        .into_iter()
+        .map(|param| {
+            let mut words = param.split_whitespace();
+            let kind = words.next().ok_or_else(|| invalid_signature(signature))?;
+            let indexed = match words.next() {
+                None => false,
+                Some("indexed") => true,
+                Some(_) => return Err(invalid_signature(signature)),
+            };
+            if words.next().is_some() {
+                return Err(invalid_signature(signature));
+            }
+
+            Ok(EventParam {
+                name: String::new(),
+                kind: ParamType::from_str(kind)?,
+                indexed,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Event {
+        name: name.to_owned(),
+        inputs,
+        anonymous,
+    })
+}
+
+/// Parses a comma separated list of parameter types, such as
+/// `uint256,address`. The list may optionally be surrounded by parentheses.
+pub fn parse_types(types: &str) -> Result<Vec<ParamType>, ParseSignatureError> {
+    let types = match take_group(types) {
+        Some((inner, "")) => inner,
+        _ => types,
+    };
+
+    split_params(types)
+        .into_iter()
+        .map(|kind| Ok(ParamType::from_str(kind)?))
+        .collect()
+}
+
+/// Splits a signature into its name and the remaining parameter groups.
+fn split_signature(signature: &str) -> Result<(&str, &str), ParseSignatureError> {
+    let signature = signature.trim();
+    let open = signature
+        .find('(')
+        .ok_or_else(|| invalid_signature(signature))?;
+    let name = signature[..open].trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(invalid_signature(signature));
+    }
+
+    Ok((name, &signature[open..]))
+}
+
+/// Takes a parenthesized group from the start of a string, returning its
+/// contents and the remaining string. Returns `None` if the string does not
+/// start with a balanced group.
+fn take_group(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if !s.starts_with('(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[1..i], &s[i + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits a parameter list on its top-level commas.
+fn split_params(params: &str) -> Vec<&str> {
+    if params.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(params[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(params[start..].trim());
+    result
+}
+
+fn invalid_signature(signature: &str) -> ParseSignatureError {
+    ParseSignatureError::Invalid(signature.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abiext::FunctionExt;
+
+    fn param_kinds(params: &[Param]) -> Vec<ParamType> {
+        params.iter().map(|param| param.kind.clone()).collect()
+    }
+
+    #[test]
+    fn parse_function_signature() {
+        let function = parse_function("balanceOf(address)(uint256)").unwrap();
+        assert_eq!(function.name, "balanceOf");
+        assert_eq!(param_kinds(&function.inputs), vec![ParamType::Address]);
+        assert_eq!(param_kinds(&function.outputs), vec![ParamType::Uint(256)]);
+        assert_eq!(function.abi_signature(), "balanceOf(address)");
+
+        let function = parse_function("foo((uint256,bool)[],bytes32)").unwrap();
+        assert_eq!(
+            param_kinds(&function.inputs),
+            vec![
+                ParamType::Array(Box::new(ParamType::Tuple(vec![
+                    ParamType::Uint(256),
+                    ParamType::Bool
+                ]))),
+                ParamType::FixedBytes(32),
+            ]
+        );
+        assert!(function.outputs.is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_signatures() {
+        for signature in &["", "foo", "(uint256)", "foo(uint256", "foo(uint256)bar"] {
+            assert!(matches!(
+                parse_function(signature),
+                Err(ParseSignatureError::Invalid(_))
+            ));
+        }
+        assert!(matches!(
+            parse_function("foo(notatype)"),
+            Err(ParseSignatureError::ParamType(_))
+        ));
+        assert!(matches!(
+            parse_event("Foo(uint256 notindexed)"),
+            Err(ParseSignatureError::Invalid(_))
+        ));
+    }
+}
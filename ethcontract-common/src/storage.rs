@@ -0,0 +1,300 @@
+//! Module for contract storage layouts, as produced by the Solidity compiler
+//! for the `storageLayout` output selection, and for computing the storage
+//! slots of state variables.
+
+use crate::abi::{self, Token};
+use crate::hash::keccak256;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use web3::types::{Address, H256, U256};
+
+/// The storage layout of a contract.
+///
+/// Only state variables of value types that are stored in place, such as
+/// integers, addresses, booleans, fixed size byte arrays and enums, can be
+/// decoded from a changed storage slot. The slots of mappings and dynamic
+/// arrays are derived from hashes and cannot be attributed to their state
+/// variables.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StorageLayout {
+    /// The state variables of the contract.
+    pub storage: Vec<StorageVariable>,
+    /// The types of the state variables, keyed by type identifier.
+    #[serde(default, deserialize_with = "deserialize_types")]
+    pub types: BTreeMap<String, StorageType>,
+}
+
+/// A state variable in a contract storage layout, or a member of a struct
+/// type.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StorageVariable {
+    /// The name of the state variable.
+    pub label: String,
+    /// The storage slot of the state variable.
+    #[serde(
+        serialize_with = "serialize_decimal",
+        deserialize_with = "deserialize_decimal"
+    )]
+    pub slot: U256,
+    /// The byte offset of the state variable within its slot.
+    pub offset: usize,
+    /// The type identifier of the state variable, for example `t_uint256`.
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+/// A type in a contract storage layout.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    /// The storage encoding of the type, for example `inplace` or `mapping`.
+    pub encoding: String,
+    /// The canonical name of the type, for example `uint256`.
+    pub label: String,
+    /// The number of bytes used by the type.
+    #[serde(
+        serialize_with = "serialize_decimal",
+        deserialize_with = "deserialize_decimal"
+    )]
+    pub number_of_bytes: U256,
+    /// The type identifier of the keys of a mapping type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// The type identifier of the values of a mapping type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// The type identifier of the elements of an array type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// The members of a struct type.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<StorageVariable>,
+}
+
+impl StorageLayout {
+    /// Loads a storage layout from its JSON representation.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Decodes the value of a storage slot into the state variables stored in
+    /// it.
+    pub fn decode_slot(&self, slot: H256, value: H256) -> Vec<(&StorageVariable, Token)> {
+        let slot = U256::from_big_endian(slot.as_bytes());
+        self.storage
+            .iter()
+            .filter(|variable| variable.slot == slot)
+            .filter_map(|variable| {
+                let ty = self.types.get(&variable.type_id)?;
+                if ty.encoding != "inplace" || ty.number_of_bytes > 32.into() {
+                    return None;
+                }
+                let size = ty.number_of_bytes.as_usize();
+                let end = 32usize.checked_sub(variable.offset)?;
+                let start = end.checked_sub(size)?;
+                let token = decode_value(&ty.label, &value.as_bytes()[start..end])?;
+                Some((variable, token))
+            })
+            .collect()
+    }
+
+    /// Returns the canonical name of the type of a state variable, or its type
+    /// identifier if the type is missing from the layout.
+    pub fn type_label<'a>(&'a self, variable: &'a StorageVariable) -> &'a str {
+        self.types
+            .get(&variable.type_id)
+            .map_or(&variable.type_id, |ty| &ty.label)
+    }
+}
+
+/// Decodes the big endian bytes of an in place stored value type with the
+/// specified canonical type name. Returns `None` for types that are not value
+/// types.
+pub fn decode_value(label: &str, bytes: &[u8]) -> Option<Token> {
+    let uint = || U256::from_big_endian(bytes);
+    let token =
+        if label == "address" || label == "address payable" || label.starts_with("contract ") {
+            Token::Address(Address::from_slice(
+                bytes.get(bytes.len().checked_sub(20)?..)?,
+            ))
+        } else if label == "bool" {
+            Token::Bool(!uint().is_zero())
+        } else if label.starts_with("uint") || label.starts_with("enum ") {
+            Token::Uint(uint())
+        } else if label.starts_with("int") {
+            let mut extended = if bytes.first().map_or(false, |byte| byte & 0x80 != 0) {
+                [0xff; 32]
+            } else {
+                [0; 32]
+            };
+            extended[32 - bytes.len()..].copy_from_slice(bytes);
+            Token::Int(U256::from_big_endian(&extended))
+        } else if label.starts_with("bytes") {
+            Token::FixedBytes(bytes.to_vec())
+        } else {
+            return None;
+        };
+    Some(token)
+}
+
+/// Encodes a value type into the `size` big endian bytes it is stored as in
+/// place. Returns `None` for tokens that are not value types or that do not
+/// fit.
+pub fn encode_value(token: &Token, size: usize) -> Option<Vec<u8>> {
+    if size > 32 {
+        return None;
+    }
+    match token {
+        Token::FixedBytes(bytes) if bytes.len() == size => Some(bytes.clone()),
+        Token::FixedBytes(_) => None,
+        Token::Address(_) | Token::Bool(_) | Token::Uint(_) | Token::Int(_) => {
+            let word = abi::encode(std::slice::from_ref(token));
+            let (padding, bytes) = word.split_at(32 - size);
+            let (extension, negative) = match token {
+                Token::Int(value) if value.bit(255) => (0xff, true),
+                _ => (0, false),
+            };
+            let sign_fits = match token {
+                Token::Int(_) => bytes
+                    .first()
+                    .map_or(true, |byte| (byte & 0x80 != 0) == negative),
+                _ => true,
+            };
+            if sign_fits && padding.iter().all(|byte| *byte == extension) {
+                Some(bytes.to_vec())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Computes the storage slot of the value of a mapping stored at `slot` for
+/// the specified key. Keys of value types are padded to 32 bytes, while
+/// string and byte array keys are hashed unpadded.
+pub fn mapping_slot(slot: U256, key: &Token) -> U256 {
+    let mut data = match key {
+        Token::String(key) => key.as_bytes().to_vec(),
+        Token::Bytes(key) => key.clone(),
+        key => abi::encode(std::slice::from_ref(key)),
+    };
+    data.extend_from_slice(&word(slot));
+    U256::from_big_endian(&keccak256(data))
+}
+
+/// Computes the storage slot of the first element of a dynamic array stored
+/// at `slot`. The length of the array is stored at `slot` itself.
+pub fn array_slot(slot: U256) -> U256 {
+    U256::from_big_endian(&keccak256(word(slot)))
+}
+
+/// Computes the storage slot and byte offset of the element at `index` of an
+/// array whose elements start at `slot` and use `size` bytes each. Elements
+/// of up to 16 bytes are packed into shared slots, and larger elements start
+/// a new slot.
+pub fn element_slot(slot: U256, index: U256, size: usize) -> (U256, usize) {
+    if size == 0 {
+        return (slot, 0);
+    }
+    if size <= 32 {
+        let per_slot = U256::from(32 / size);
+        let offset = (index % per_slot).as_usize() * size;
+        (slot.overflowing_add(index / per_slot).0, offset)
+    } else {
+        let slots = U256::from((size + 31) / 32);
+        (slot.overflowing_add(index.overflowing_mul(slots).0).0, 0)
+    }
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut word = [0; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+/// Serializes a value as a decimal string, as used by the Solidity compiler
+/// for slots and type sizes.
+fn serialize_decimal<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Deserializes a decimal string, as used by the Solidity compiler for slots
+/// and type sizes.
+fn deserialize_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(value) => U256::from_dec_str(&value).map_err(de::Error::custom),
+        Value::Number(value) => value
+            .as_u64()
+            .map(U256::from)
+            .ok_or_else(|| de::Error::custom("invalid decimal number")),
+        _ => Err(de::Error::custom("expected a decimal string")),
+    }
+}
+
+/// Deserializes the types of a storage layout, which are `null` for contracts
+/// without state variables.
+fn deserialize_types<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, StorageType>, D::Error> {
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_derivation() {
+        // Slot of `balances[0x0101..01]` for a mapping at slot 0, and of
+        // `values[3]` for a `uint64[]` at slot 1.
+        let key = Token::Address(Address::repeat_byte(1));
+        let mut data = [0; 64];
+        data[12..32].copy_from_slice(&[1; 20]);
+        assert_eq!(
+            mapping_slot(0.into(), &key),
+            U256::from_big_endian(&keccak256(&data[..])),
+        );
+        assert_eq!(
+            mapping_slot(2.into(), &Token::String("a".to_owned())),
+            U256::from_big_endian(&keccak256({
+                let mut data = b"a".to_vec();
+                data.extend_from_slice(&word(2.into()));
+                data
+            })),
+        );
+
+        let start = array_slot(1.into());
+        assert_eq!(start, U256::from_big_endian(&keccak256(word(1.into()))));
+        assert_eq!(element_slot(start, 3.into(), 8), (start, 24));
+        assert_eq!(element_slot(start, 4.into(), 8), (start + 1, 0));
+        assert_eq!(element_slot(start, 2.into(), 64), (start + 4, 0));
+    }
+
+    #[test]
+    fn value_encoding() {
+        for (label, token, size) in &[
+            ("uint8", Token::Uint(200.into()), 1),
+            ("int16", Token::Int(U256::MAX - 5), 2),
+            ("bool", Token::Bool(true), 1),
+            ("address", Token::Address(Address::repeat_byte(7)), 20),
+            ("bytes4", Token::FixedBytes(vec![1, 2, 3, 4]), 4),
+        ] {
+            let bytes = encode_value(token, *size).unwrap();
+            assert_eq!(bytes.len(), *size);
+            assert_eq!(decode_value(label, &bytes).as_ref(), Some(token));
+        }
+
+        assert_eq!(encode_value(&Token::Uint(256.into()), 1), None);
+        assert_eq!(encode_value(&Token::Int(U256::MAX - 200), 1), None);
+        assert_eq!(encode_value(&Token::String("a".to_owned()), 1), None);
+    }
+
+    #[test]
+    fn layout_without_state_variables() {
+        let layout = StorageLayout::from_json(r#"{ "storage": [], "types": null }"#).unwrap();
+        assert_eq!(layout, StorageLayout::default());
+    }
+}
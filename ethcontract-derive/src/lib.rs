@@ -8,23 +8,29 @@ extern crate proc_macro;
 mod spanned;
 
 use crate::spanned::{ParseInner, Spanned};
-use anyhow::{anyhow, Result};
 use ethcontract_common::abi::{Function, Param, ParamType};
 use ethcontract_common::abiext::{FunctionExt, ParamTypeExt};
 use ethcontract_common::artifact::truffle::TruffleLoader;
 use ethcontract_common::contract::Network;
+use ethcontract_common::errors::ArtifactError;
 use ethcontract_common::Address;
+use ethcontract_common::{address, signature};
 use ethcontract_generate::loaders::{HardHatFormat, HardHatLoader};
-use ethcontract_generate::{parse_address, ContractBuilder, Source};
+use ethcontract_generate::source::expand_source;
+use ethcontract_generate::{
+    parse_address, AddressFormat, ContractBindings, ContractBuilder, GenerateError, SerdeOptions,
+    Source, UintFormat, MAX_TOKEN_DECIMALS,
+};
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens as _};
 use std::collections::HashSet;
+use std::env;
 use syn::ext::IdentExt;
 use syn::parse::{Error as ParseError, Parse, ParseStream, Result as ParseResult};
 use syn::{
-    braced, parenthesized, parse_macro_input, Error as SynError, Ident, LitInt, LitStr, Path,
-    Token, Visibility,
+    braced, bracketed, parenthesized, parse_macro_input, Error as SynError, Ident, LitInt, LitStr,
+    Path, Token, Type, Visibility,
 };
 
 /// Proc macro to generate type-safe bindings to a contract.
@@ -36,6 +42,22 @@ use syn::{
 /// contract!("build/contracts/WETH9.json");
 /// ```
 ///
+/// For compatibility, relative paths that do not exist in `CARGO_MANIFEST_DIR`
+/// are resolved from the current directory, which is the workspace root when
+/// building with Cargo. The path may reference environment variables with
+/// `$NAME` or `${NAME}`, and the root of the Cargo workspace with
+/// `{workspace_root}`, so that crates sharing an artifact directory don't
+/// need relative paths:
+///
+/// ```ignore
+/// contract!("{workspace_root}/artifacts/WETH9.json");
+/// contract!("$ARTIFACTS_DIR/WETH9.json");
+/// ```
+///
+/// Local artifacts are tracked by the compiler, so the bindings are
+/// regenerated whenever the artifact file changes. Note that changes to the
+/// environment variables used in the path are not tracked.
+///
 /// Alternatively, other sources may be used, for full details consult the
 /// [`ethcontract_generate::source`] documentation. Some basic examples:
 ///
@@ -158,6 +180,23 @@ use syn::{
 ///   );
 ///   ```
 ///
+///   Alternatively, `methods` and `events` accept lists of glob patterns
+///   matching the names or signatures of the methods and events to generate
+///   bindings for, where `*` matches any sequence of characters and `?` any
+///   single character. This keeps compile times and binary sizes down when
+///   binding to a few items of a large contract. Patterns that do not match
+///   any item are an error.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "UniswapV3Pool.json",
+///       methods = ["swap", "slot0", "observe*"],
+///       events = ["Swap"],
+///   );
+///   ```
+///
 /// - `event_derives`: a list of additional derives that should be added to
 ///   contract event structs and enums.
 ///
@@ -170,6 +209,95 @@ use syn::{
 ///   );
 ///   ```
 ///
+/// - `serde`: derive `serde::Serialize` and `serde::Deserialize` for contract
+///   event structs and enums, optionally with `U256` and `I256` fields
+///   represented as `decimal` strings instead of `hex` strings, addresses
+///   represented as `checksummed` strings instead of `lowercase` strings,
+///   and event struct field names renamed to a `serde` case convention. This
+///   does not require a dependency on `serde`. See
+///   [`ContractBuilder::serde`] for details.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "build/contracts/WETH9.json",
+///       serde(uint = decimal, address = checksummed, rename_all = "camelCase"),
+///   );
+///   ```
+///
+/// - `token_decimals`: the number of decimals of an ERC20 token contract. This
+///   opts into typed token amounts, where the amount parameters and return
+///   values of the standard ERC20 methods use `ethcontract::units::TokenAmount`
+///   instead of raw `U256` values. The contract must be an ERC20 token and the
+///   number of decimals can be at most 77.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "build/contracts/WETH9.json",
+///       token_decimals = 18,
+///   );
+///   ```
+///
+/// - `types`: a list of Rust types substituted for Solidity types in the
+///   generated bindings, either for all parameters of a Solidity type or for
+///   a single `method.parameter`. The substituted types must implement
+///   `ethcontract::tokens::Tokenize`. See
+///   [`ContractBuilder::add_type_substitution`] for details.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "build/contracts/Vault.json",
+///       types {
+///           "uint256" => crate::Wad,
+///           "setPrice.price" => crate::Ray,
+///       },
+///   );
+///   ```
+///
+/// - `contract_trait`: generate a trait mirroring the contract methods that is
+///   implemented by the contract type. The trait is named after the contract
+///   prefixed with `I`, for example `IErc20`.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "build/contracts/WETH9.json",
+///       contract_trait,
+///   );
+///   ```
+///
+/// - `arbitrary_impls`: implement `ethcontract::arbitrary::Arbitrary` for the
+///   event data types, so that property tests can generate arbitrary events.
+///   This requires the `test-helpers` feature of the `ethcontract` crate.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "build/contracts/WETH9.json",
+///       arbitrary_impls,
+///   );
+///   ```
+///
+/// - `examples`: generate an `examples` module with functions connecting to a
+///   node, creating a contract instance and calling each contract method with
+///   default arguments, as starting points for using the contract bindings.
+///
+///   Example:
+///
+///   ```ignore
+///   contract!(
+///       "build/contracts/WETH9.json",
+///       examples,
+///   );
+///   ```
+///
 /// - `crate`: the name of the `ethcontract` crate. This is useful if the crate
 ///   was renamed in the `Cargo.toml` for whatever reason.
 ///
@@ -206,11 +334,157 @@ pub fn contract(input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(input as Spanned<ContractArgs>);
     let span = args.span();
     generate(args.into_inner())
-        .unwrap_or_else(|e| SynError::new(span, format!("{:?}", e)).to_compile_error())
+        .unwrap_or_else(|e| generate_error(span, e).to_compile_error())
+        .into()
+}
+
+/// Converts a code generation error into a compilation error. Each problem of
+/// a validation error is reported as a separate error message.
+fn generate_error(span: Span, err: GenerateError) -> SynError {
+    match err {
+        GenerateError::Validation(err) => {
+            let mut problems = err.problems.iter().map(|problem| {
+                SynError::new(span, format!("contract '{}': {}", err.contract, problem))
+            });
+            let mut error = problems.next().unwrap_or_else(|| SynError::new(span, &err));
+            for problem in problems {
+                error.combine(problem);
+            }
+            error
+        }
+        err => SynError::new(span, err),
+    }
+}
+
+/// Proc macro to parse an address at compile time. The macro expands to an
+/// `ethcontract::Address` expression so it can be used in constants:
+///
+/// ```ignore
+/// const WETH: Address = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+/// ```
+///
+/// Mixed-case addresses must have a valid EIP-55 checksum, otherwise a
+/// compilation error is reported with the expected checksummed address.
+///
+/// Like the `contract!` macro, the address can be followed by a `crate`
+/// parameter with the name of the `ethcontract` crate if it was renamed:
+///
+/// ```ignore
+/// const WETH: Address = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", crate = eth);
+/// ```
+#[proc_macro]
+pub fn address(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as LiteralArgs);
+    expand_address(&args)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Proc macro to compute the 4-byte selector of a function signature at
+/// compile time. The macro expands to a `[u8; 4]` array expression so it can
+/// be used in constants:
+///
+/// ```ignore
+/// const TRANSFER: [u8; 4] = selector!("transfer(address,uint256)");
+/// ```
+///
+/// The signature is parsed and validated, so invalid Solidity types are
+/// reported as compilation errors.
+#[proc_macro]
+pub fn selector(input: TokenStream) -> TokenStream {
+    let signature = parse_macro_input!(input as LitStr);
+    expand_selector(&signature)
+        .unwrap_or_else(|e| e.to_compile_error())
         .into()
 }
 
-fn generate(args: ContractArgs) -> Result<TokenStream2> {
+/// Proc macro to compute the topic of an event signature at compile time. The
+/// macro expands to an `ethcontract::H256` expression so it can be used in
+/// constants:
+///
+/// ```ignore
+/// const TRANSFER: H256 = event_topic!("Transfer(address indexed,address indexed,uint256)");
+/// ```
+///
+/// Parameters marked as `indexed` are ignored when computing the topic, so
+/// the signature can be copied verbatim from the Solidity declaration without
+/// parameter names. The signature can be followed by a `crate` parameter like
+/// for the `address!` macro.
+#[proc_macro]
+pub fn event_topic(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as LiteralArgs);
+    expand_event_topic(&args)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Arguments of the macros expanding a string literal into an `ethcontract`
+/// value, with an optional `crate = name` parameter.
+struct LiteralArgs {
+    literal: LitStr,
+    runtime_crate: Ident,
+}
+
+impl Parse for LiteralArgs {
+    fn parse(input: ParseStream) -> ParseResult<Self> {
+        let literal = input.parse::<LitStr>()?;
+        let mut runtime_crate = Ident::new("ethcontract", Span::call_site());
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        if !input.is_empty() {
+            let name = input.call(Ident::parse_any)?;
+            if name != "crate" {
+                return Err(ParseError::new(
+                    name.span(),
+                    format!("unexpected named parameter `{}`", name),
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            runtime_crate = input.call(Ident::parse_any)?;
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        Ok(LiteralArgs {
+            literal,
+            runtime_crate,
+        })
+    }
+}
+
+fn expand_address(args: &LiteralArgs) -> ParseResult<TokenStream2> {
+    let LiteralArgs {
+        literal,
+        runtime_crate,
+    } = args;
+    let address = address::parse_checksummed(&literal.value())
+        .map_err(|err| ParseError::new(literal.span(), err))?;
+    let bytes = address.0;
+
+    Ok(quote! { #runtime_crate::H160([#( #bytes ),*]) })
+}
+
+fn expand_selector(signature: &LitStr) -> ParseResult<TokenStream2> {
+    let function = signature::parse_function(&signature.value())
+        .map_err(|err| ParseError::new(signature.span(), err))?;
+    let bytes = function.selector();
+
+    Ok(quote! { [#( #bytes ),*] })
+}
+
+fn expand_event_topic(args: &LiteralArgs) -> ParseResult<TokenStream2> {
+    let LiteralArgs {
+        literal,
+        runtime_crate,
+    } = args;
+    let event = signature::parse_event(&literal.value())
+        .map_err(|err| ParseError::new(literal.span(), err))?;
+    let bytes = event.signature().0;
+
+    Ok(quote! { #runtime_crate::H256([#( #bytes ),*]) })
+}
+
+fn generate(args: ContractArgs) -> Result<TokenStream2, GenerateError> {
     let mut artifact_format = Format::Truffle;
     let mut contract_name = None;
 
@@ -243,56 +517,106 @@ fn generate(args: ContractArgs) -> Result<TokenStream2> {
                         .insert(method.signature, method.alias);
                 }
             }
+            Parameter::MethodFilter(patterns) => builder.method_filter.extend(patterns),
+            Parameter::EventFilter(patterns) => builder.event_filter.extend(patterns),
             Parameter::EventDerives(derives) => {
                 builder.event_derives.extend(derives);
             }
+            Parameter::Serde(options) => builder.serde = Some(options),
             Parameter::Format(format) => artifact_format = format,
+            Parameter::TokenDecimals(decimals) => builder.token_decimals = Some(decimals),
+            Parameter::Types(types) => builder.type_substitutions.extend(types),
+            Parameter::ContractTrait => builder.contract_trait = true,
+            Parameter::ArbitraryImpls => builder.arbitrary_impls = true,
+            Parameter::Examples => builder.examples = true,
         };
     }
 
-    let source = Source::parse(&args.artifact_path)?;
-    let json = source.artifact_json()?;
+    let artifact_path = &args.artifact_path;
+    builder.artifact_origin = Some(artifact_path.clone());
 
-    match artifact_format {
-        Format::Truffle => {
-            let mut contract = TruffleLoader::new().load_contract_from_str(&json)?;
+    let source = resolve_source(&expand_source(artifact_path)?)?;
+    let json = source.artifact_json()?;
 
-            if let Some(contract_name) = contract_name {
-                if contract.name.is_empty() {
-                    contract.name = contract_name;
-                } else if contract.name != contract_name {
-                    return Err(anyhow!(
-                        "there is no contract '{}' in artifact '{}'",
-                        contract_name,
-                        args.artifact_path
-                    ));
-                }
+    // NOTE: Include local artifacts in the expanded code so that the compiler
+    //   tracks them and rebuilds the bindings when they change.
+    let track = match &source {
+        Source::Local(path) => path.to_str().map(|path| {
+            quote! {
+                const _: &[u8] = include_bytes!(#path);
             }
+        }),
+        _ => None,
+    };
+
+    let bindings = match artifact_format {
+        Format::Truffle => {
+            // NOTE: The loader selects the named contract from the output of
+            //   `solc --combined-json`, skipping all other contracts, names
+            //   unnamed contracts and rejects contracts with other names.
+            let contract = match contract_name {
+                Some(contract_name) => TruffleLoader::new()
+                    .name(&contract_name)
+                    .load_contract_from_str(&json)
+                    .map_err(|err| match err {
+                        ArtifactError::ContractNotFound(_) => {
+                            GenerateError::UnknownContract(contract_name, artifact_path.clone())
+                        }
+                        err => err.into(),
+                    })?,
+                None => TruffleLoader::new().load_contract_from_str(&json)?,
+            };
 
-            Ok(builder.generate(&contract)?.into_tokens())
+            builder
+                .generate(&contract)
+                .map(ContractBindings::into_tokens)
         }
 
         Format::HardHat(format) => {
-            let artifact = HardHatLoader::new().load_from_str(format, &json)?;
-
             if let Some(contract_name) = contract_name {
+                // NOTE: Only load the requested contract, this avoids parsing
+                //   all other contracts in potentially huge artifacts.
+                let artifact = HardHatLoader::new()
+                    .allow_contract(&contract_name)
+                    .load_from_str(format, &json)?;
+
                 if let Some(contract) = artifact.get(&contract_name) {
-                    Ok(builder.generate(contract)?.into_tokens())
+                    builder
+                        .generate(contract)
+                        .map(ContractBindings::into_tokens)
                 } else {
-                    Err(anyhow!(
-                        "there is no contract '{}' in artifact '{}'",
+                    Err(GenerateError::UnknownContract(
                         contract_name,
-                        args.artifact_path
+                        artifact_path.clone(),
                     ))
                 }
             } else {
-                Err(anyhow!(
+                Err(GenerateError::InvalidOption(
                     "when using hardhat artifacts, you should specify \
                      contract name using 'contract' parameter"
+                        .to_owned(),
                 ))
             }
         }
+    }?;
+
+    Ok(quote! {
+        #bindings
+        #track
+    })
+}
+
+/// Resolves an artifact source, rooting relative paths in the directory of
+/// the crate being built, and falling back to the current directory if the
+/// file does not exist there.
+fn resolve_source(source: &str) -> Result<Source, GenerateError> {
+    if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+        match Source::with_root(&manifest_dir, source)? {
+            Source::Local(path) if !path.exists() => {}
+            source => return Ok(source),
+        }
     }
+    Source::parse(source)
 }
 
 /// Contract procedural macro arguments.
@@ -354,8 +678,16 @@ enum Parameter {
     Crate(String),
     Deployments(Vec<Deployment>),
     Methods(Vec<Method>),
+    MethodFilter(Vec<String>),
+    EventFilter(Vec<String>),
     EventDerives(Vec<String>),
+    Serde(SerdeOptions),
     Format(Format),
+    TokenDecimals(u32),
+    Types(Vec<(String, String)>),
+    ContractTrait,
+    ArbitraryImpls,
+    Examples,
 }
 
 impl Parse for Parameter {
@@ -423,6 +755,8 @@ impl Parse for Parameter {
 
                 Parameter::Deployments(deployments)
             }
+            "methods" if input.peek(Token![=]) => Parameter::MethodFilter(parse_patterns(input)?),
+            "events" => Parameter::EventFilter(parse_patterns(input)?),
             "methods" => {
                 let content;
                 braced!(content in input);
@@ -464,6 +798,45 @@ impl Parse for Parameter {
                     .collect();
                 Parameter::EventDerives(derives)
             }
+            "serde" => Parameter::Serde(parse_serde_options(input)?),
+            "token_decimals" => {
+                input.parse::<Token![=]>()?;
+                let literal = input.parse::<LitInt>()?;
+                let decimals = literal.base10_parse()?;
+                if decimals > MAX_TOKEN_DECIMALS {
+                    return Err(ParseError::new(
+                        literal.span(),
+                        format!("token decimals must be at most {}", MAX_TOKEN_DECIMALS),
+                    ));
+                }
+                Parameter::TokenDecimals(decimals)
+            }
+            "types" => {
+                let content;
+                braced!(content in input);
+                let parsed = content.parse_terminated::<_, Token![,]>(|input| {
+                    let target = input.parse::<LitStr>()?;
+                    input.parse::<Token![=>]>()?;
+                    let ty = input.parse::<Type>()?;
+                    Ok((target, ty))
+                })?;
+
+                let mut types = Vec::with_capacity(parsed.len());
+                let mut targets = HashSet::new();
+                for (target, ty) in parsed {
+                    if !targets.insert(target.value()) {
+                        return Err(ParseError::new(
+                            target.span(),
+                            "duplicate type substitution in `ethcontract::contract!` macro invocation",
+                        ));
+                    }
+                    types.push((target.value(), ty.to_token_stream().to_string()));
+                }
+                Parameter::Types(types)
+            }
+            "contract_trait" => Parameter::ContractTrait,
+            "arbitrary_impls" => Parameter::ArbitraryImpls,
+            "examples" => Parameter::Examples,
             _ => {
                 return Err(ParseError::new(
                     name.span(),
@@ -476,6 +849,71 @@ impl Parse for Parameter {
     }
 }
 
+/// Parses a `= ["pattern", ...]` list of glob patterns.
+fn parse_patterns(input: ParseStream) -> ParseResult<Vec<String>> {
+    input.parse::<Token![=]>()?;
+    let content;
+    bracketed!(content in input);
+    let patterns = content.parse_terminated::<_, Token![,]>(<LitStr as Parse>::parse)?;
+    Ok(patterns.iter().map(LitStr::value).collect())
+}
+
+/// Parses optional `(uint = ..., address = ..., rename_all = "...")` serde
+/// options.
+fn parse_serde_options(input: ParseStream) -> ParseResult<SerdeOptions> {
+    let mut options = SerdeOptions::default();
+    if !input.peek(syn::token::Paren) {
+        return Ok(options);
+    }
+
+    let content;
+    parenthesized!(content in input);
+    while !content.is_empty() {
+        let name = content.parse::<Ident>()?;
+        content.parse::<Token![=]>()?;
+        match name.to_string().as_str() {
+            "uint" => {
+                let token = content.parse::<Ident>()?;
+                options.uint_format = match token.to_string().as_str() {
+                    "hex" => UintFormat::Hex,
+                    "decimal" => UintFormat::Decimal,
+                    format => {
+                        return Err(ParseError::new(
+                            token.span(),
+                            format!("unknown uint format {}", format),
+                        ))
+                    }
+                };
+            }
+            "address" => {
+                let token = content.parse::<Ident>()?;
+                options.address_format = match token.to_string().as_str() {
+                    "lowercase" => AddressFormat::Lowercase,
+                    "checksummed" => AddressFormat::Checksummed,
+                    format => {
+                        return Err(ParseError::new(
+                            token.span(),
+                            format!("unknown address format {}", format),
+                        ))
+                    }
+                };
+            }
+            "rename_all" => options.rename_all = Some(content.parse::<LitStr>()?.value()),
+            _ => {
+                return Err(ParseError::new(
+                    name.span(),
+                    format!("unexpected serde option `{}`", name),
+                ))
+            }
+        }
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(options)
+}
+
 /// A manually specified dependency.
 #[cfg_attr(test, derive(Debug, Eq, PartialEq))]
 struct Deployment {
@@ -489,7 +927,7 @@ impl Parse for Deployment {
         input.parse::<Token![=>]>()?;
         let address = {
             let literal = input.parse::<LitStr>()?;
-            parse_address(&literal.value()).map_err(|err| ParseError::new(literal.span(), err))?
+            parse_address(literal.value()).map_err(|err| ParseError::new(literal.span(), err))?
         };
 
         Ok(Deployment {
@@ -588,6 +1026,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn address_expansion() {
+        let address = syn::parse_quote!("0x000102030405060708090a0b0c0d0e0f10111213");
+        assert_eq!(
+            expand_address(&address).unwrap().to_string(),
+            quote!(ethcontract::H160([
+                0u8, 1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8, 9u8, 10u8, 11u8, 12u8, 13u8, 14u8,
+                15u8, 16u8, 17u8, 18u8, 19u8
+            ]))
+            .to_string(),
+        );
+        let address = syn::parse_quote!("0x000102030405060708090a0b0c0d0e0f10111213", crate = eth);
+        assert!(expand_address(&address)
+            .unwrap()
+            .to_string()
+            .starts_with(&quote!(eth::H160).to_string()));
+        assert!(expand_address(&syn::parse_quote!(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn selector_expansion() {
+        let signature = syn::parse_quote!("transfer(address, uint256)");
+        assert_eq!(
+            expand_selector(&signature).unwrap().to_string(),
+            quote!([169u8, 5u8, 156u8, 187u8]).to_string(),
+        );
+    }
+
+    #[test]
+    fn event_topic_expansion() {
+        let signature = syn::parse_quote!("Transfer(address indexed,address indexed,uint256)");
+        let bytes = ethcontract_common::hash::keccak256("Transfer(address,address,uint256)");
+        assert_eq!(
+            expand_event_topic(&signature).unwrap().to_string(),
+            quote!(ethcontract::H256([#( #bytes ),*])).to_string(),
+        );
+
+        let signature = syn::parse_quote!("Transfer(address,address,uint256)", crate = crate,);
+        assert_eq!(
+            expand_event_topic(&signature).unwrap().to_string(),
+            quote!(crate::H256([#( #bytes ),*])).to_string(),
+        );
+    }
+
+    #[test]
+    fn literal_args_error() {
+        assert!(syn::parse_str::<LiteralArgs>(r#""Transfer()", mod = foo"#).is_err());
+        assert!(syn::parse_str::<LiteralArgs>(r#""Transfer()" crate = foo"#).is_err());
+    }
+
+    #[test]
+    fn invalid_signature_expansion() {
+        assert!(expand_selector(&syn::parse_quote!("transfer(address,")).is_err());
+        assert!(expand_event_topic(&syn::parse_quote!("Transfer(foo)")).is_err());
+    }
+
     #[test]
     fn parse_contract_args() {
         let args = contract_args!("path/to/artifact.json");
@@ -628,7 +1125,14 @@ mod tests {
                 myMethod(uint256, bool) as my_renamed_method;
                 myOtherMethod() as my_other_renamed_method;
             },
-            event_derives (Asdf, a::B, a::b::c::D)
+            event_derives (Asdf, a::B, a::b::c::D),
+            serde(uint = decimal, address = checksummed, rename_all = "camelCase"),
+            types {
+                "uint256" => crate::Wad,
+                "setPrice.price" => Vec<u8>,
+            },
+            arbitrary_impls,
+            examples,
         );
         assert_eq!(
             args,
@@ -651,12 +1155,44 @@ mod tests {
                         "Asdf".into(),
                         "a :: B".into(),
                         "a :: b :: c :: D".into()
-                    ])
+                    ]),
+                    Parameter::Serde(SerdeOptions {
+                        uint_format: UintFormat::Decimal,
+                        address_format: AddressFormat::Checksummed,
+                        rename_all: Some("camelCase".into()),
+                    }),
+                    Parameter::Types(vec![
+                        ("uint256".into(), "crate :: Wad".into()),
+                        ("setPrice.price".into(), "Vec < u8 >".into()),
+                    ]),
+                    Parameter::ArbitraryImpls,
+                    Parameter::Examples,
                 ],
             },
         );
     }
 
+    #[test]
+    fn parse_contract_args_serde_defaults() {
+        let args = contract_args!("artifact.json", serde);
+        assert_eq!(
+            args.parameters,
+            vec![Parameter::Serde(SerdeOptions::default())],
+        );
+    }
+
+    #[test]
+    fn parse_contract_args_token_decimals() {
+        let args = contract_args!("artifact.json", token_decimals = 18);
+        assert_eq!(args.parameters, vec![Parameter::TokenDecimals(18)]);
+    }
+
+    #[test]
+    fn parse_contract_args_contract_trait() {
+        let args = contract_args!("artifact.json", contract_trait);
+        assert_eq!(args.parameters, vec![Parameter::ContractTrait]);
+    }
+
     #[test]
     fn parse_contract_args_format() {
         let args = contract_args!("artifact.json", format = hardhat_multi);
@@ -688,6 +1224,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_contract_args_filters() {
+        let args = contract_args!(
+            "artifact.json",
+            methods = ["swap", "observe*"],
+            events = ["Swap",],
+        );
+        assert_eq!(
+            args.parameters,
+            vec![
+                Parameter::MethodFilter(vec!["swap".into(), "observe*".into()]),
+                Parameter::EventFilter(vec!["Swap".into()]),
+            ],
+        );
+    }
+
+    #[test]
+    fn token_decimals_overflow_error() {
+        contract_args_err!("artifact.json", token_decimals = 78);
+    }
+
     #[test]
     fn unsupported_format_error() {
         contract_args_err!("artifact.json", format = yaml);
@@ -731,4 +1288,65 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn generate_from_combined_json() {
+        let path =
+            env::temp_dir().join(format!("ethcontract-combined-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+                "contracts": {
+                    "Token.sol:Token": { "abi": "[]", "bin": "00" },
+                    "Token.sol:SafeMath": { "abi": "[]", "bin": "" }
+                },
+                "version": "0.6.12"
+            }"#,
+        )
+        .unwrap();
+        let args = |parameters| ContractArgs {
+            visibility: None,
+            artifact_path: path.to_str().unwrap().to_owned(),
+            parameters,
+        };
+
+        let token = generate(args(vec![Parameter::Contract("Token".into(), None)]));
+        let missing = generate(args(vec![Parameter::Contract("Missing".into(), None)]));
+        let ambiguous = generate(args(vec![]));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(token.unwrap().to_string().contains("Token"));
+        assert!(matches!(
+            missing,
+            Err(GenerateError::UnknownContract(name, _)) if name == "Missing"
+        ));
+        assert!(matches!(
+            ambiguous,
+            Err(GenerateError::Json(ArtifactError::AmbiguousContract(2)))
+        ));
+    }
+
+    #[test]
+    fn generate_from_artifact_with_other_contract_name() {
+        let path = env::temp_dir().join(format!("ethcontract-named-{}.json", std::process::id()));
+        let args = |contract: &str| ContractArgs {
+            visibility: None,
+            artifact_path: path.to_str().unwrap().to_owned(),
+            parameters: vec![Parameter::Contract(contract.into(), None)],
+        };
+
+        std::fs::write(&path, r#"{ "contractName": "Foo", "abi": [] }"#).unwrap();
+        let foo = generate(args("Foo"));
+        let bar = generate(args("Bar"));
+        std::fs::write(&path, r#"{ "abi": [] }"#).unwrap();
+        let unnamed = generate(args("Bar"));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(foo.is_ok());
+        assert!(matches!(
+            bar,
+            Err(GenerateError::UnknownContract(name, _)) if name == "Bar"
+        ));
+        assert!(unnamed.unwrap().to_string().contains("Bar"));
+    }
 }
@@ -0,0 +1,649 @@
+//! Diffing of contract ABIs, for reviewing the changes to the interface of a
+//! contract before regenerating its bindings.
+//!
+//! The diff covers the constructor, functions, events, and the fallback and
+//! receive functions of a contract. Custom errors are not part of the parsed
+//! contract ABI and are therefore not reported.
+
+use ethcontract_common::abi::{Event, Function, Param, StateMutability};
+use ethcontract_common::abiext::{EventExt, FunctionExt};
+use ethcontract_common::hash::H32;
+use ethcontract_common::{Contract, TransactionHash};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+/// Computes the differences between the ABIs of two versions of a contract.
+///
+/// Overloaded functions and events are first matched by their signature;
+/// the remaining overloads with the same name are matched in declaration
+/// order and reported as changed, highlighting the change of their selector.
+pub fn diff(old: &Contract, new: &Contract) -> AbiDiff {
+    let mut items = Vec::new();
+
+    match (&old.abi.constructor, &new.abi.constructor) {
+        (Some(old), Some(new)) => {
+            let mut differences = Vec::new();
+            compare_params(&old.inputs, &new.inputs, &mut differences);
+            if !differences.is_empty() {
+                items.push(ItemDiff::changed(
+                    ItemKind::Constructor,
+                    "constructor",
+                    constructor_signature(&old.inputs),
+                    constructor_signature(&new.inputs),
+                    differences,
+                ));
+            }
+        }
+        (None, Some(new)) => items.push(ItemDiff::added(
+            ItemKind::Constructor,
+            "constructor",
+            constructor_signature(&new.inputs),
+        )),
+        (Some(old), None) => items.push(ItemDiff::removed(
+            ItemKind::Constructor,
+            "constructor",
+            constructor_signature(&old.inputs),
+        )),
+        (None, None) => {}
+    }
+
+    diff_overloads(
+        &old.abi.functions,
+        &new.abi.functions,
+        ItemKind::Function,
+        |function| function.abi_signature(),
+        function_signature,
+        compare_functions,
+        &mut items,
+    );
+    diff_overloads(
+        &old.abi.events,
+        &new.abi.events,
+        ItemKind::Event,
+        |event| event.abi_signature(),
+        event_signature,
+        compare_events,
+        &mut items,
+    );
+
+    for (kind, name, old, new) in &[
+        (
+            ItemKind::Fallback,
+            "fallback",
+            old.abi.fallback,
+            new.abi.fallback,
+        ),
+        (
+            ItemKind::Receive,
+            "receive",
+            old.abi.receive,
+            new.abi.receive,
+        ),
+    ] {
+        let signature = Signature {
+            signature: format!("{}()", name),
+            selector: None,
+        };
+        match (old, new) {
+            (false, true) => items.push(ItemDiff::added(*kind, name, signature)),
+            (true, false) => items.push(ItemDiff::removed(*kind, name, signature)),
+            _ => {}
+        }
+    }
+
+    AbiDiff { items }
+}
+
+/// The differences between the ABIs of two versions of a contract.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AbiDiff {
+    /// The added, removed and changed items, ordered by kind and name.
+    pub items: Vec<ItemDiff>,
+}
+
+impl AbiDiff {
+    /// Returns `true` if the ABIs are identical.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns `true` if any of the changes breaks code using the bindings
+    /// generated for the old ABI.
+    pub fn is_breaking(&self) -> bool {
+        self.items.iter().any(ItemDiff::is_breaking)
+    }
+
+    /// Returns an iterator over the breaking changes.
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &ItemDiff> + '_ {
+        self.items.iter().filter(|item| item.is_breaking())
+    }
+}
+
+/// Formats the diff as one line per item, prefixed with `+` for added, `-`
+/// for removed and `~` for changed items.
+impl Display for AbiDiff {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of an ABI item.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ItemKind {
+    /// The contract constructor.
+    Constructor,
+    /// A contract function.
+    Function,
+    /// A contract event.
+    Event,
+    /// The fallback function.
+    Fallback,
+    /// The receive function.
+    Receive,
+}
+
+impl Display for ItemKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ItemKind::Constructor => "constructor",
+            ItemKind::Function => "function",
+            ItemKind::Event => "event",
+            ItemKind::Fallback => "fallback",
+            ItemKind::Receive => "receive",
+        })
+    }
+}
+
+/// A change to a single ABI item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ItemDiff {
+    /// The kind of the item.
+    pub kind: ItemKind,
+    /// The name of the item.
+    pub name: String,
+    /// The change to the item.
+    pub change: Change,
+}
+
+impl ItemDiff {
+    fn added(kind: ItemKind, name: &str, signature: Signature) -> Self {
+        ItemDiff {
+            kind,
+            name: name.to_owned(),
+            change: Change::Added(signature),
+        }
+    }
+
+    fn removed(kind: ItemKind, name: &str, signature: Signature) -> Self {
+        ItemDiff {
+            kind,
+            name: name.to_owned(),
+            change: Change::Removed(signature),
+        }
+    }
+
+    fn changed(
+        kind: ItemKind,
+        name: &str,
+        old: Signature,
+        new: Signature,
+        differences: Vec<Difference>,
+    ) -> Self {
+        ItemDiff {
+            kind,
+            name: name.to_owned(),
+            change: Change::Changed {
+                old,
+                new,
+                differences,
+            },
+        }
+    }
+
+    /// Returns `true` if the function selector or event topic of a changed
+    /// item is different.
+    pub fn selector_changed(&self) -> bool {
+        match &self.change {
+            Change::Changed { old, new, .. } => old.selector != new.selector,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the change breaks code using the bindings generated
+    /// for the old ABI. Added items and renamed parameters are not breaking,
+    /// except for events whose parameter names are the fields of the
+    /// generated event data.
+    pub fn is_breaking(&self) -> bool {
+        match &self.change {
+            Change::Added(_) => false,
+            Change::Removed(_) => true,
+            Change::Changed { differences, .. } => {
+                differences.iter().any(|difference| match difference {
+                    Difference::InputNames => self.kind == ItemKind::Event,
+                    Difference::StateMutability { old, new } => !matches!(
+                        (old, new),
+                        (StateMutability::NonPayable, StateMutability::Payable)
+                            | (StateMutability::Pure, StateMutability::View)
+                            | (StateMutability::View, StateMutability::Pure)
+                    ),
+                    _ => true,
+                })
+            }
+        }
+    }
+}
+
+impl Display for ItemDiff {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // the signatures of the other items already start with their kind
+        let kind = match self.kind {
+            ItemKind::Function | ItemKind::Event => format!("{} ", self.kind),
+            _ => String::new(),
+        };
+        match &self.change {
+            Change::Added(signature) => write!(f, "+ {}{}", kind, signature)?,
+            Change::Removed(signature) => write!(f, "- {}{}", kind, signature)?,
+            Change::Changed {
+                old,
+                new,
+                differences,
+            } => {
+                write!(f, "~ {}{}", kind, old.signature)?;
+                if old.signature != new.signature {
+                    write!(f, " -> {}", new.signature)?;
+                }
+                let differences = differences
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                write!(f, ": {}", differences.join(", "))?;
+                if self.selector_changed() {
+                    write!(
+                        f,
+                        "; SELECTOR CHANGED {} -> {}",
+                        display_selector(&old.selector),
+                        display_selector(&new.selector),
+                    )?;
+                }
+            }
+        }
+        if self.is_breaking() {
+            write!(f, " (breaking)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of change to an ABI item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change {
+    /// The item was added to the new ABI.
+    Added(Signature),
+    /// The item was removed from the new ABI.
+    Removed(Signature),
+    /// The item exists in both ABIs with differences.
+    Changed {
+        /// The signature of the item in the old ABI.
+        old: Signature,
+        /// The signature of the item in the new ABI.
+        new: Signature,
+        /// The differences between the two versions of the item.
+        differences: Vec<Difference>,
+    },
+}
+
+/// The signature of an ABI item along with its selector.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signature {
+    /// The signature in the standard ABI format, for example
+    /// `transfer(address,uint256)`.
+    pub signature: String,
+    /// The selector of the item, or `None` for items without a selector such
+    /// as the constructor and anonymous events.
+    pub selector: Option<Selector>,
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.signature)?;
+        if let Some(selector) = &self.selector {
+            write!(f, " [{}]", selector)?;
+        }
+        Ok(())
+    }
+}
+
+/// The selector used to identify an ABI item in transactions and logs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Selector {
+    /// The 4-byte selector of a function.
+    Function(H32),
+    /// The topic of a non-anonymous event.
+    Event(TransactionHash),
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Selector::Function(selector) => {
+                f.write_str("0x")?;
+                selector
+                    .iter()
+                    .try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+            Selector::Event(topic) => write!(f, "{:?}", topic),
+        }
+    }
+}
+
+/// A difference between two versions of an ABI item.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Difference {
+    /// The input parameter types changed, which changes the selector of
+    /// functions and events.
+    InputTypes,
+    /// The input parameter names changed.
+    InputNames,
+    /// The output parameter types or names of a function changed.
+    Outputs,
+    /// The state mutability of a function changed.
+    StateMutability {
+        /// The old state mutability.
+        old: StateMutability,
+        /// The new state mutability.
+        new: StateMutability,
+    },
+    /// The set of indexed event parameters changed.
+    Indexed,
+    /// An event was made anonymous or non-anonymous.
+    Anonymous,
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Difference::InputTypes => f.write_str("input types changed"),
+            Difference::InputNames => f.write_str("input names changed"),
+            Difference::Outputs => f.write_str("outputs changed"),
+            Difference::StateMutability { old, new } => write!(
+                f,
+                "state mutability changed from {} to {}",
+                mutability_name(*old),
+                mutability_name(*new),
+            ),
+            Difference::Indexed => f.write_str("indexed parameters changed"),
+            Difference::Anonymous => f.write_str("anonymity changed"),
+        }
+    }
+}
+
+/// Diffs functions or events grouped by name.
+fn diff_overloads<T>(
+    old: &HashMap<String, Vec<T>>,
+    new: &HashMap<String, Vec<T>>,
+    kind: ItemKind,
+    abi_signature: impl Fn(&T) -> String,
+    signature: impl Fn(&T) -> Signature,
+    compare: impl Fn(&T, &T) -> Vec<Difference>,
+    items: &mut Vec<ItemDiff>,
+) {
+    let empty = Vec::new();
+    let names = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+    for name in names {
+        let old = old.get(name).unwrap_or(&empty);
+        let mut new = new.get(name).unwrap_or(&empty).iter().collect::<Vec<_>>();
+
+        let mut unmatched = Vec::new();
+        for old in old {
+            let position = new
+                .iter()
+                .position(|new| abi_signature(new) == abi_signature(old));
+            match position {
+                Some(position) => {
+                    let new = new.remove(position);
+                    let differences = compare(old, new);
+                    if !differences.is_empty() {
+                        items.push(ItemDiff::changed(
+                            kind,
+                            name,
+                            signature(old),
+                            signature(new),
+                            differences,
+                        ));
+                    }
+                }
+                None => unmatched.push(old),
+            }
+        }
+
+        let mut new = new.into_iter();
+        for old in unmatched {
+            match new.next() {
+                Some(new) => items.push(ItemDiff::changed(
+                    kind,
+                    name,
+                    signature(old),
+                    signature(new),
+                    compare(old, new),
+                )),
+                None => items.push(ItemDiff::removed(kind, name, signature(old))),
+            }
+        }
+        for new in new {
+            items.push(ItemDiff::added(kind, name, signature(new)));
+        }
+    }
+}
+
+fn compare_params(old: &[Param], new: &[Param], differences: &mut Vec<Difference>) {
+    let kinds = |params: &[Param]| params.iter().map(|p| p.kind.clone()).collect::<Vec<_>>();
+    let names = |params: &[Param]| params.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+
+    if kinds(old) != kinds(new) {
+        differences.push(Difference::InputTypes);
+    } else if names(old) != names(new) {
+        differences.push(Difference::InputNames);
+    }
+}
+
+fn compare_functions(old: &Function, new: &Function) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    compare_params(&old.inputs, &new.inputs, &mut differences);
+    if old.outputs != new.outputs {
+        differences.push(Difference::Outputs);
+    }
+    if old.state_mutability != new.state_mutability {
+        differences.push(Difference::StateMutability {
+            old: old.state_mutability,
+            new: new.state_mutability,
+        });
+    }
+    differences
+}
+
+fn compare_events(old: &Event, new: &Event) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let kinds = |event: &Event| {
+        event
+            .inputs
+            .iter()
+            .map(|p| p.kind.clone())
+            .collect::<Vec<_>>()
+    };
+    let names = |event: &Event| {
+        event
+            .inputs
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>()
+    };
+    let indexed = |event: &Event| event.inputs.iter().map(|p| p.indexed).collect::<Vec<_>>();
+
+    if kinds(old) != kinds(new) {
+        differences.push(Difference::InputTypes);
+    } else if names(old) != names(new) {
+        differences.push(Difference::InputNames);
+    }
+    if indexed(old) != indexed(new) {
+        differences.push(Difference::Indexed);
+    }
+    if old.anonymous != new.anonymous {
+        differences.push(Difference::Anonymous);
+    }
+    differences
+}
+
+fn constructor_signature(inputs: &[Param]) -> Signature {
+    let inputs = inputs
+        .iter()
+        .map(|param| param.kind.to_string())
+        .collect::<Vec<_>>();
+    Signature {
+        signature: format!("constructor({})", inputs.join(",")),
+        selector: None,
+    }
+}
+
+fn function_signature(function: &Function) -> Signature {
+    Signature {
+        signature: function.abi_signature(),
+        selector: Some(Selector::Function(function.selector())),
+    }
+}
+
+fn event_signature(event: &Event) -> Signature {
+    Signature {
+        signature: event.abi_signature(),
+        selector: if event.anonymous {
+            None
+        } else {
+            Some(Selector::Event(event.signature()))
+        },
+    }
+}
+
+fn display_selector(selector: &Option<Selector>) -> String {
+    match selector {
+        Some(selector) => selector.to_string(),
+        None => "none".to_owned(),
+    }
+}
+
+fn mutability_name(mutability: StateMutability) -> &'static str {
+    match mutability {
+        StateMutability::Pure => "pure",
+        StateMutability::View => "view",
+        StateMutability::NonPayable => "nonpayable",
+        StateMutability::Payable => "payable",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+
+    fn contract(abi: &str) -> Contract {
+        test_contract("Token", abi)
+    }
+
+    const OLD: &str = r#"[
+        {"type":"constructor","inputs":[{"name":"supply","type":"uint256"}]},
+        {"type":"function","name":"transfer","stateMutability":"nonpayable",
+         "inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"}],
+         "outputs":[{"name":"","type":"bool"}]},
+        {"type":"function","name":"balanceOf","stateMutability":"view",
+         "inputs":[{"name":"owner","type":"address"}],
+         "outputs":[{"name":"","type":"uint256"}]},
+        {"type":"function","name":"burn","stateMutability":"nonpayable",
+         "inputs":[{"name":"value","type":"uint256"}],"outputs":[]},
+        {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+         {"name":"from","type":"address","indexed":true},
+         {"name":"to","type":"address","indexed":true},
+         {"name":"value","type":"uint256","indexed":false}]}
+    ]"#;
+
+    const NEW: &str = r#"[
+        {"type":"constructor","inputs":[{"name":"supply","type":"uint256"}]},
+        {"type":"function","name":"transfer","stateMutability":"nonpayable",
+         "inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"},
+                   {"name":"data","type":"bytes"}],
+         "outputs":[{"name":"","type":"bool"}]},
+        {"type":"function","name":"balanceOf","stateMutability":"view",
+         "inputs":[{"name":"account","type":"address"}],
+         "outputs":[{"name":"","type":"uint256"}]},
+        {"type":"function","name":"mint","stateMutability":"payable",
+         "inputs":[],"outputs":[]},
+        {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+         {"name":"from","type":"address","indexed":true},
+         {"name":"to","type":"address","indexed":true},
+         {"name":"value","type":"uint256","indexed":true}]},
+        {"type":"receive","stateMutability":"payable"}
+    ]"#;
+
+    #[test]
+    fn identical_abis_have_no_diff() {
+        let diff = diff(&contract(OLD), &contract(OLD));
+        assert!(diff.is_empty());
+        assert!(!diff.is_breaking());
+    }
+
+    #[test]
+    fn diff_reports_changes() {
+        let diff = diff(&contract(OLD), &contract(NEW));
+        assert_eq!(
+            diff.to_string(),
+            "~ function balanceOf(address): input names changed\n\
+             - function burn(uint256) [0x42966c68] (breaking)\n\
+             + function mint() [0x1249c58b]\n\
+             ~ function transfer(address,uint256) -> transfer(address,uint256,bytes): \
+                input types changed; SELECTOR CHANGED 0xa9059cbb -> 0xbe45fd62 (breaking)\n\
+             ~ event Transfer(address,address,uint256): indexed parameters changed (breaking)\n\
+             + receive()",
+        );
+        assert!(diff.is_breaking());
+        assert_eq!(
+            diff.breaking_changes()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["burn", "transfer", "Transfer"],
+        );
+    }
+
+    #[test]
+    fn diff_constructor_and_mutability() {
+        let old = contract(
+            r#"[{"type":"function","name":"deposit","stateMutability":"nonpayable",
+                 "inputs":[],"outputs":[]}]"#,
+        );
+        let new = contract(
+            r#"[{"type":"constructor","inputs":[]},
+                {"type":"function","name":"deposit","stateMutability":"payable",
+                 "inputs":[],"outputs":[]}]"#,
+        );
+
+        let diff = diff(&old, &new);
+        assert_eq!(diff.items.len(), 2);
+        assert_eq!(diff.items[0].kind, ItemKind::Constructor);
+        assert!(matches!(diff.items[0].change, Change::Added(_)));
+        assert_eq!(
+            diff.items[1].change,
+            Change::Changed {
+                old: function_signature(&old.abi.functions["deposit"][0]),
+                new: function_signature(&new.abi.functions["deposit"][0]),
+                differences: vec![Difference::StateMutability {
+                    old: StateMutability::NonPayable,
+                    new: StateMutability::Payable,
+                }],
+            },
+        );
+        assert!(!diff.items[1].selector_changed());
+        assert!(!diff.is_breaking());
+    }
+}
@@ -0,0 +1,226 @@
+//! Generation of bindings for many contracts at once, expanding contracts in
+//! parallel and writing one file per contract along with a `mod.rs` file that
+//! includes all of them.
+
+use crate::errors::GenerateError;
+use crate::loaders::TruffleLoader;
+use crate::report::BindingsReport;
+use crate::ContractBuilder;
+use ethcontract_common::artifact::Artifact;
+use ethcontract_common::Contract;
+use inflector::Inflector;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// The number of threads used when the `NUM_JOBS` environment variable that
+/// Cargo sets for build scripts is not available.
+const DEFAULT_JOBS: usize = 4;
+
+/// Loads all Truffle artifacts with a `json` extension in a directory.
+pub(crate) fn load_dir(dir: &Path) -> Result<Artifact, GenerateError> {
+    let entries = fs::read_dir(dir).map_err(|err| {
+        GenerateError::Io(format!("failed to read directory '{}'", dir.display()), err)
+    })?;
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| {
+            GenerateError::Io(format!("failed to read directory '{}'", dir.display()), err)
+        })?;
+    paths.retain(|path| path.is_file() && path.extension().map_or(false, |ext| ext == "json"));
+    paths.sort();
+
+    let loader = TruffleLoader::new();
+    let mut artifact = Artifact::with_origin(dir.display().to_string());
+    for path in paths {
+        let contract = loader.load_contract_from_file(&path)?;
+        if artifact.contains(&contract.name) {
+            return Err(GenerateError::NameCollision(format!(
+                "multiple artifacts in '{}' contain the contract '{}'",
+                dir.display(),
+                contract.name,
+            )));
+        }
+        artifact.insert(contract);
+    }
+    Ok(artifact)
+}
+
+/// Generates bindings for all contracts of an artifact in parallel and writes
+/// them to the output directory.
+pub(crate) fn generate_artifact(
+    builder: ContractBuilder,
+    artifact: &Artifact,
+    out_dir: &Path,
+) -> Result<BindingsReport, GenerateError> {
+    let mut contracts = artifact.iter().cloned().collect::<Vec<_>>();
+    contracts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut modules = HashMap::new();
+    for contract in &contracts {
+        let module = contract.name.to_snake_case();
+        if let Some(other) = modules.insert(module.clone(), &contract.name) {
+            return Err(GenerateError::NameCollision(format!(
+                "contracts '{}' and '{}' both expand to module '{}'",
+                other, contract.name, module,
+            )));
+        }
+    }
+
+    fs::create_dir_all(out_dir).map_err(|err| {
+        GenerateError::Io(
+            format!("failed to create directory '{}'", out_dir.display()),
+            err,
+        )
+    })?;
+
+    let jobs = env::var("NUM_JOBS")
+        .ok()
+        .and_then(|jobs| jobs.parse().ok())
+        .unwrap_or(DEFAULT_JOBS)
+        .max(1)
+        .min(contracts.len().max(1));
+    let results = expand_parallel(builder, contracts, jobs);
+
+    let mut report = BindingsReport::default();
+    let mut mod_rs = String::new();
+    for (module, result) in results {
+        let (source, contract_report) = result?;
+        let path = out_dir.join(format!("{}.rs", module));
+        fs::write(&path, source).map_err(|err| {
+            GenerateError::Io(format!("failed to write file '{}'", path.display()), err)
+        })?;
+        mod_rs.push_str(&format!("include!(\"{}.rs\");\n", module));
+        report.push(contract_report);
+    }
+
+    let path = out_dir.join("mod.rs");
+    fs::write(&path, mod_rs).map_err(|err| {
+        GenerateError::Io(format!("failed to write file '{}'", path.display()), err)
+    })?;
+
+    Ok(report)
+}
+
+type Expanded = Result<(String, crate::ContractReport), GenerateError>;
+
+/// Expands contracts with the specified number of threads, returning the
+/// module names and the formatted bindings in the order of the contracts.
+fn expand_parallel(
+    builder: ContractBuilder,
+    contracts: Vec<Contract>,
+    jobs: usize,
+) -> Vec<(String, Expanded)> {
+    let contracts = Arc::new(contracts);
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+
+    let workers = (0..jobs)
+        .map(|_| {
+            let builder = builder.clone();
+            let contracts = contracts.clone();
+            let next = next.clone();
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let contract = match contracts.get(index) {
+                    Some(contract) => contract,
+                    None => break,
+                };
+                let result = builder.clone().generate(contract).map(|bindings| {
+                    let report = bindings.report.clone();
+                    (bindings.source(), report)
+                });
+                if sender.send((index, result)).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    let mut results = receiver.iter().collect::<Vec<_>>();
+    for worker in workers {
+        if let Err(panic) = worker.join() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    results.sort_by_key(|(index, _)| *index);
+    results
+        .into_iter()
+        .map(|(index, result)| (contracts[index].name.to_snake_case(), result))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "ethcontract-generate-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn generates_one_file_per_contract() {
+        let artifacts = temp_dir("artifacts");
+        fs::create_dir_all(&artifacts).unwrap();
+        for name in &["Foo", "BarBaz", "Qux"] {
+            fs::write(
+                artifacts.join(format!("{}.json", name)),
+                format!(r#"{{ "contractName": "{}", "abi": [] }}"#, name),
+            )
+            .unwrap();
+        }
+        fs::write(artifacts.join("README.md"), "not an artifact").unwrap();
+
+        let out = temp_dir("bindings");
+        let report = ContractBuilder::new()
+            .rustfmt(false)
+            .generate_dir(&artifacts, &out)
+            .unwrap();
+
+        assert_eq!(
+            report
+                .contracts
+                .iter()
+                .map(|contract| contract.name.as_str())
+                .collect::<Vec<_>>(),
+            ["BarBaz", "Foo", "Qux"]
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("mod.rs")).unwrap(),
+            "include!(\"bar_baz.rs\");\ninclude!(\"foo.rs\");\ninclude!(\"qux.rs\");\n"
+        );
+        let source = fs::read_to_string(out.join("bar_baz.rs")).unwrap();
+        assert!(source.contains("mod bar_baz"), "{}", source);
+
+        fs::remove_dir_all(&artifacts).unwrap();
+        fs::remove_dir_all(&out).unwrap();
+    }
+
+    #[test]
+    fn module_collisions_are_rejected() {
+        let mut artifact = Artifact::new();
+        artifact.insert(Contract::with_name("FooBar"));
+        artifact.insert(Contract::with_name("foo_bar"));
+
+        let out = temp_dir("collisions");
+        assert!(matches!(
+            ContractBuilder::new().generate_artifact(&artifact, &out),
+            Err(GenerateError::NameCollision(_))
+        ));
+        assert!(!out.exists());
+    }
+}
@@ -0,0 +1,142 @@
+//! Module with the error type for loading contract artifacts and generating
+//! bindings for them.
+
+use crate::generate::ValidationError;
+use ethcontract_common::errors::{ArtifactError, IpfsError, LinkError};
+use std::io::Error as IoError;
+use thiserror::Error;
+
+/// An error that occurred while loading an artifact or generating contract
+/// bindings.
+///
+/// Build scripts and procedural macros can match on the variants in order to
+/// report failures precisely, for example reporting each problem of a
+/// [`ValidationError`] separately.
+#[derive(Debug, Error)]
+pub enum GenerateError {
+    /// An IO error occurred, for example when reading an artifact from the
+    /// file system or when writing generated bindings to a file. Contains a
+    /// description of the failed operation.
+    #[error("{0}: {1}")]
+    Io(String, #[source] IoError),
+
+    /// The artifact JSON could not be parsed.
+    #[error(transparent)]
+    Json(#[from] ArtifactError),
+
+    /// The artifact source string is invalid, for example because it uses an
+    /// unsupported URL scheme.
+    #[error("invalid artifact source: {0}")]
+    InvalidSource(String),
+
+    /// An artifact could not be retrieved over HTTP. Contains the resource
+    /// that was being retrieved and the reason of the failure.
+    #[error("failed to retrieve {0}: {1}")]
+    Http(String, String),
+
+    /// Content retrieved from IPFS is invalid.
+    #[error(transparent)]
+    Ipfs(#[from] IpfsError),
+
+    /// The contract ABI uses a feature that is not supported by the code
+    /// generation, such as an unsupported parameter type.
+    #[error("unsupported {0}")]
+    UnsupportedAbi(String),
+
+    /// Two items of the contract would be expanded to the same name in the
+    /// generated bindings.
+    #[error("name collision: {0}")]
+    NameCollision(String),
+
+    /// The ABIs of merged contracts contain incompatible definitions of an
+    /// item with the same signature.
+    #[error("conflicting ABIs: {0}")]
+    AbiConflict(String),
+
+    /// The contract bytecode cannot be linked with its libraries.
+    #[error("failed to link contract bytecode: {0}")]
+    Linking(#[from] LinkError),
+
+    /// The contract ABI contains problems that prevent it from being expanded
+    /// into bindings.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    /// The artifact does not contain the requested contract. Contains the
+    /// name of the contract and the origin of the artifact.
+    #[error("there is no contract '{0}' in artifact '{1}'")]
+    UnknownContract(String, String),
+
+    /// The contract is missing a name and one was not manually specified.
+    #[error(
+        "contract artifact is missing a name, this can happen when using a \
+         source that does not provide a contract name such as Etherscan; in \
+         this case the contract must be manually specified"
+    )]
+    MissingName,
+
+    /// An option of the contract builder is invalid, for example a method
+    /// alias for a method that does not exist.
+    #[error("invalid option: {0}")]
+    InvalidOption(String),
+
+    /// The generated code could not be formatted with `rustfmt`.
+    #[error("failed to format generated code: {0}")]
+    Rustfmt(String),
+
+    /// Previously generated bindings are not up to date with the bindings
+    /// generated from the current artifact. Contains the path of the file
+    /// with the outdated bindings.
+    #[error("bindings in '{0}' are out of date: {1}")]
+    OutdatedBindings(String, BindingsMismatch),
+
+    /// Generated bindings do not match their snapshots in snapshot tests.
+    #[error(
+        "{} snapshot(s) do not match the generated bindings, run with \
+         `UPDATE_SNAPSHOTS=1` to update them:\n\n{}",
+        .0.len(),
+        display_snapshot_mismatches(.0)
+    )]
+    Snapshots(Vec<SnapshotMismatch>),
+}
+
+/// The first difference between previously generated bindings and the
+/// bindings generated from the current artifact.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum BindingsMismatch {
+    /// The previously generated bindings are not valid Rust source code.
+    #[error("failed to parse file: {0}")]
+    Unparsable(String),
+
+    /// The previously generated bindings contain a different token.
+    #[error("expected {expected} but found {found} after `{context}`")]
+    Token {
+        /// The tokens preceding the difference.
+        context: String,
+        /// The expected token.
+        expected: String,
+        /// The token found in the previously generated bindings.
+        found: String,
+    },
+}
+
+/// A snapshot of generated bindings that does not match the bindings.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum SnapshotMismatch {
+    /// The snapshot file does not exist. Contains the path of the snapshot.
+    #[error("snapshot '{0}' does not exist")]
+    Missing(String),
+
+    /// The snapshot differs from the generated bindings. Contains the path of
+    /// the snapshot and a line diff from the snapshot to the bindings.
+    #[error("snapshot '{0}' differs from the generated bindings:\n{1}")]
+    Changed(String, String),
+}
+
+fn display_snapshot_mismatches(mismatches: &[SnapshotMismatch]) -> String {
+    mismatches
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -4,14 +4,30 @@
 
 mod common;
 mod deployment;
+mod erc20;
 mod events;
+mod filter;
+mod merge;
 mod methods;
+mod nft;
+mod storage;
 mod types;
+mod upgrade;
+mod validate;
 
-use crate::{util, ContractBuilder};
-use anyhow::{anyhow, Context as _, Result};
+pub use self::erc20::MAX_TOKEN_DECIMALS;
+pub use self::validate::{Problem, ValidationError};
+
+use self::methods::CustomCodec;
+use self::types::TypeSubstitutions;
+use crate::errors::GenerateError;
+use crate::report::ContractReport;
+use crate::{util, ContractBuilder, MethodDefaults, SerdeOptions};
+use ethcontract_common::abi::param_type::Reader;
+use ethcontract_common::abi::StateMutability;
+use ethcontract_common::abiext::EventExt;
 use ethcontract_common::contract::Network;
-use ethcontract_common::Contract;
+use ethcontract_common::{Contract, FunctionExt};
 use inflector::Inflector;
 use proc_macro2::{Ident, TokenStream};
 use quote::quote;
@@ -45,29 +61,55 @@ pub(crate) struct Context<'a> {
     /// Manually specified method aliases.
     method_aliases: HashMap<String, Ident>,
 
+    /// Default options for methods, by method signature.
+    method_defaults: HashMap<String, MethodDefaults>,
+
+    /// Custom codecs for methods, by method signature.
+    method_codecs: HashMap<String, CustomCodec>,
+
+    /// Rust types substituted for Solidity types.
+    type_substitutions: TypeSubstitutions,
+
     /// Derives added to event structs and enums.
     event_derives: Vec<Path>,
+
+    /// Serde representations of event structs and enums, if serde
+    /// serialization should be derived for them.
+    serde: Option<SerdeOptions>,
+
+    /// The number of decimals of the token, for ERC20 token amounts.
+    token_decimals: Option<u32>,
+
+    /// The name of the trait mirroring the contract methods, if it should be
+    /// generated.
+    contract_trait: Option<Ident>,
+
+    /// Whether to implement `Arbitrary` for event data types.
+    arbitrary_impls: bool,
+
+    /// Whether to generate a module of examples using the contract methods.
+    examples: bool,
 }
 
 impl<'a> Context<'a> {
     /// Creates a context from the code generation arguments.
-    fn from_builder(contract: &'a Contract, builder: ContractBuilder) -> Result<Self> {
+    fn from_builder(
+        contract: &'a Contract,
+        builder: ContractBuilder,
+    ) -> Result<Self, GenerateError> {
         let raw_contract_name = if let Some(name) = &builder.contract_name_override {
             name
         } else if !contract.name.is_empty() {
             &contract.name
         } else {
-            return Err(anyhow!(
-                "contract artifact is missing a name, this can happen when \
-                 using a source that does not provide a contract name such as \
-                 Etherscan; in this case the contract must be manually \
-                 specified"
-            ));
+            return Err(GenerateError::MissingName);
         };
 
         let runtime_crate = util::ident(&builder.runtime_crate_name);
         let visibility = match &builder.visibility_modifier {
-            Some(vis) => syn::parse_str(vis)?,
+            Some(vis) => syn::parse_str(vis).map_err(|err| {
+                GenerateError::InvalidOption(format!("visibility modifier '{}': {}", vis, err))
+            })?,
             None => Visibility::Inherited,
         };
         let contract_mod = if let Some(name) = &builder.contract_mod_override {
@@ -76,27 +118,148 @@ impl<'a> Context<'a> {
             util::ident(&raw_contract_name.to_snake_case())
         };
         let contract_name = util::ident(raw_contract_name);
+        let contract_trait = if builder.contract_trait {
+            Some(util::ident(&format!(
+                "I{}",
+                raw_contract_name.to_pascal_case()
+            )))
+        } else {
+            None
+        };
 
         // NOTE: We only check for duplicate signatures here, since if there are
         //   duplicate aliases, the compiler will produce a warning because a
         //   method will be re-defined.
         let mut method_aliases = HashMap::new();
         for (signature, alias) in builder.method_aliases.into_iter() {
-            let alias = syn::parse_str(&alias)?;
+            let alias = syn::parse_str(&alias).map_err(|err| {
+                GenerateError::InvalidOption(format!("method alias '{}': {}", alias, err))
+            })?;
             if method_aliases.insert(signature.clone(), alias).is_some() {
-                return Err(anyhow!(
+                return Err(GenerateError::NameCollision(format!(
                     "duplicate method signature '{}' in method aliases",
                     signature,
-                ));
+                )));
+            }
+        }
+
+        let mut method_defaults = HashMap::new();
+        for (method, defaults) in builder.method_defaults.into_iter() {
+            let function = resolve_method(contract, &method, "method defaults")?;
+            if defaults.value.is_some() && function.state_mutability != StateMutability::Payable {
+                return Err(GenerateError::InvalidOption(format!(
+                    "a default value was specified for method '{}' but it is not payable",
+                    method,
+                )));
+            }
+            if method_defaults
+                .insert(function.abi_signature(), defaults)
+                .is_some()
+            {
+                return Err(GenerateError::InvalidOption(format!(
+                    "method defaults were specified more than once for method '{}'",
+                    function.abi_signature(),
+                )));
+            }
+        }
+
+        let mut method_codecs = HashMap::new();
+        for (method, codec) in builder.method_codecs.iter() {
+            let function = resolve_method(contract, method, "method codecs")?;
+            if codec.encode.is_none() && codec.decode.is_none() {
+                return Err(GenerateError::InvalidOption(format!(
+                    "method codec for '{}' has neither an encoder nor a decoder",
+                    method,
+                )));
+            }
+            let parse = |path: &Option<String>| {
+                path.as_ref()
+                    .map(|path| {
+                        syn::parse_str::<Path>(path).map_err(|err| {
+                            GenerateError::InvalidOption(format!(
+                                "method codec function '{}': {}",
+                                path, err
+                            ))
+                        })
+                    })
+                    .transpose()
+            };
+            let codec = CustomCodec {
+                encode: parse(&codec.encode)?,
+                decode: parse(&codec.decode)?,
+            };
+            if method_codecs
+                .insert(function.abi_signature(), codec)
+                .is_some()
+            {
+                return Err(GenerateError::InvalidOption(format!(
+                    "method codecs were specified more than once for method '{}'",
+                    function.abi_signature(),
+                )));
+            }
+        }
+
+        let mut type_substitutions = TypeSubstitutions::default();
+        for (target, rust_type) in builder.type_substitutions.iter() {
+            let ty = syn::parse_str::<syn::Type>(rust_type).map_err(|err| {
+                GenerateError::InvalidOption(format!("type substitution '{}': {}", rust_type, err))
+            })?;
+            let (key, duplicate) = match target.rfind('.') {
+                Some(dot) => {
+                    let param = &target[dot + 1..];
+                    let signature = resolve_param(contract, &target[..dot], param)?;
+                    let key = format!("{}.{}", signature, param);
+                    let duplicate = type_substitutions
+                        .params
+                        .insert((signature, param.to_owned()), quote! { #ty })
+                        .is_some();
+                    (key, duplicate)
+                }
+                None => {
+                    let kind = Reader::read(target).map_err(|err| {
+                        GenerateError::InvalidOption(format!(
+                            "type substitution for '{}': {}",
+                            target, err
+                        ))
+                    })?;
+                    let duplicate = type_substitutions
+                        .types
+                        .insert(kind.to_string(), quote! { #ty })
+                        .is_some();
+                    (kind.to_string(), duplicate)
+                }
+            };
+            if duplicate {
+                return Err(GenerateError::InvalidOption(format!(
+                    "type substitutions were specified more than once for '{}'",
+                    key,
+                )));
             }
         }
 
         let event_derives = builder
             .event_derives
             .iter()
-            .map(|derive| syn::parse_str::<Path>(derive))
-            .collect::<Result<Vec<_>, _>>()
-            .context("failed to parse event derives")?;
+            .map(|derive| {
+                syn::parse_str::<Path>(derive).map_err(|err| {
+                    GenerateError::InvalidOption(format!("event derive '{}': {}", derive, err))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(rename_all) = builder
+            .serde
+            .as_ref()
+            .and_then(|serde| serde.rename_all.as_deref())
+        {
+            if !SERDE_CASES.contains(&rename_all) {
+                return Err(GenerateError::InvalidOption(format!(
+                    "serde rename convention '{}' is not one of {}",
+                    rename_all,
+                    SERDE_CASES.join(", "),
+                )));
+            }
+        }
 
         Ok(Context {
             contract,
@@ -106,28 +269,171 @@ impl<'a> Context<'a> {
             contract_name,
             networks: builder.networks,
             method_aliases,
+            method_defaults,
+            method_codecs,
+            type_substitutions,
             event_derives,
+            serde: builder.serde,
+            token_decimals: builder.token_decimals,
+            contract_trait,
+            arbitrary_impls: builder.arbitrary_impls,
+            examples: builder.examples,
         })
     }
 }
 
-pub(crate) fn expand(contract: &Contract, builder: ContractBuilder) -> Result<TokenStream> {
-    let cx = Context::from_builder(contract, builder)?;
-    let contract = expand_contract(&cx).context("error expanding contract from its ABI")?;
+/// The case conventions supported by `#[serde(rename_all = "...")]`.
+const SERDE_CASES: &[&str] = &[
+    "lowercase",
+    "UPPERCASE",
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "kebab-case",
+    "SCREAMING-KEBAB-CASE",
+];
 
-    Ok(contract)
+/// Finds the function that a method name or signature refers to, where
+/// `option` names the builder option the method was specified for.
+fn resolve_method<'a>(
+    contract: &'a Contract,
+    method: &str,
+    option: &str,
+) -> Result<&'a ethcontract_common::abi::Function, GenerateError> {
+    let mut functions = contract.abi.functions().filter(|function| {
+        if method.contains('(') {
+            function.abi_signature() == method || function.signature() == method
+        } else {
+            function.name == method
+        }
+    });
+    match (functions.next(), functions.next()) {
+        (Some(function), None) => Ok(function),
+        (Some(_), Some(_)) => Err(GenerateError::InvalidOption(format!(
+            "method '{}' is overloaded, {} must be specified by signature",
+            method, option,
+        ))),
+        (None, _) => Err(GenerateError::InvalidOption(format!(
+            "{} for '{}' were specified but this method does not exist",
+            option, method,
+        ))),
+    }
 }
 
-fn expand_contract(cx: &Context) -> Result<TokenStream> {
+/// Finds the method, constructor or event that a parameter type substitution
+/// refers to, returning its signature.
+fn resolve_param(contract: &Contract, item: &str, param: &str) -> Result<String, GenerateError> {
+    let missing_param = || {
+        GenerateError::InvalidOption(format!(
+            "a type substitution for '{}.{}' was specified but '{}' has no parameter '{}'",
+            item, param, item, param,
+        ))
+    };
+
+    if item == "constructor" {
+        return match contract.abi.constructor() {
+            Some(constructor) if constructor.inputs.iter().any(|input| input.name == param) => {
+                Ok(item.to_owned())
+            }
+            Some(_) => Err(missing_param()),
+            None => Err(GenerateError::InvalidOption(format!(
+                "a type substitution for '{}.{}' was specified but the contract has no constructor",
+                item, param,
+            ))),
+        };
+    }
+
+    let by_signature = item.contains('(');
+    let mut functions = contract.abi.functions().filter(|function| {
+        if by_signature {
+            function.abi_signature() == item || function.signature() == item
+        } else {
+            function.name == item
+        }
+    });
+    let mut events = contract.abi.events().filter(|event| {
+        if by_signature {
+            event.abi_signature() == item
+        } else {
+            event.name == item
+        }
+    });
+    let (signature, found) = match (functions.next(), functions.next(), events.next()) {
+        (Some(function), None, _) => (
+            function.abi_signature(),
+            function
+                .inputs
+                .iter()
+                .chain(&function.outputs)
+                .any(|input| input.name == param),
+        ),
+        (None, _, Some(event)) if events.next().is_none() => (
+            event.abi_signature(),
+            event.inputs.iter().any(|input| input.name == param),
+        ),
+        (Some(_), Some(_), _) | (None, _, Some(_)) => {
+            return Err(GenerateError::InvalidOption(format!(
+                "'{}' is overloaded, type substitutions must be specified by signature",
+                item,
+            )))
+        }
+        (None, _, None) => {
+            return Err(GenerateError::InvalidOption(format!(
+                "a type substitution for '{}.{}' was specified but this method does not exist",
+                item, param,
+            )))
+        }
+    };
+    if !found {
+        return Err(missing_param());
+    }
+    Ok(signature)
+}
+
+pub(crate) fn expand(
+    contract: &Contract,
+    builder: ContractBuilder,
+) -> Result<(TokenStream, ContractReport), GenerateError> {
+    let merged = merge::merge(contract, &builder.merged_contracts)?;
+    let contract = filter::filter(&merged, &builder)?;
+    validate::validate(&contract, &builder)?;
+
+    let cx = Context::from_builder(&contract, builder)?;
+    let tokens = expand_contract(&cx)?;
+    let report = ContractReport::new(
+        &cx.contract_name.to_string(),
+        contract.abi.functions().count(),
+        contract.abi.events().count(),
+        &tokens,
+    );
+
+    Ok((tokens, report))
+}
+
+fn expand_contract(cx: &Context) -> Result<TokenStream, GenerateError> {
     let runtime_crate = &cx.runtime_crate;
     let vis = &cx.visibility;
     let contract_mod = &cx.contract_mod;
     let contract_name = &cx.contract_name;
 
     let common = common::expand(cx);
+    let erc20 = erc20::expand(cx)?;
+    let allowance = erc20::expand_allowance(cx);
+    let balances = erc20::expand_balances(cx);
+    let nft = nft::expand(cx);
+    let upgrade = upgrade::expand(cx);
     let deployment = deployment::expand(cx)?;
     let methods = methods::expand(cx)?;
     let events = events::expand(cx)?;
+    let storage = storage::expand(cx)?;
+
+    let contract_trait = match &cx.contract_trait {
+        Some(name) if cx.contract.abi.functions().next().is_some() => {
+            quote! { #vis use self::#contract_mod::#name; }
+        }
+        _ => quote! {},
+    };
 
     Ok(quote! {
         #[allow(dead_code)]
@@ -136,10 +442,25 @@ fn expand_contract(cx: &Context) -> Result<TokenStream> {
             use #runtime_crate as ethcontract;
 
             #common
+            #erc20
+            #allowance
+            #balances
+            #nft
+            #upgrade
             #deployment
             #methods
             #events
+            #storage
         }
         #vis use self::#contract_mod::Contract as #contract_name;
+        #contract_trait
     })
 }
+
+/// Creates a contract with the specified name and JSON ABI for tests.
+#[cfg(test)]
+pub(crate) fn test_contract(name: &str, abi: &str) -> Contract {
+    let mut contract = Contract::with_name(name);
+    contract.abi = ethcontract_common::Abi::load(abi.as_bytes()).unwrap();
+    contract
+}
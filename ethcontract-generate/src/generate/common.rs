@@ -1,9 +1,9 @@
 use crate::generate::Context;
 use crate::util::expand_doc;
-use ethcontract_common::artifact::truffle::TruffleLoader;
-use ethcontract_common::{Address, DeploymentInformation};
+use ethcontract_common::{Address, Contract, DeploymentInformation};
 use proc_macro2::{Literal, TokenStream};
 use quote::quote;
+use serde_json::Value;
 
 pub(crate) fn expand(cx: &Context) -> TokenStream {
     let contract_name = &cx.contract_name;
@@ -16,9 +16,11 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
         .unwrap_or("Generated by `ethcontract`");
     let doc = expand_doc(doc_str);
 
-    let contract_json = TruffleLoader::save_to_string(cx.contract).unwrap();
+    let contract_json = stable_json(cx.contract);
 
-    let deployments = cx.networks.iter().map(|(chain_id, network)| {
+    let mut networks = cx.networks.iter().collect::<Vec<_>>();
+    networks.sort_by_key(|(chain_id, _)| *chain_id);
+    let deployments = networks.into_iter().map(|(chain_id, network)| {
         let chain_id = Literal::string(chain_id);
         let address = expand_address(network.address);
         let deployment_information = expand_deployment_information(network.deployment_information);
@@ -69,14 +71,14 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
             /// Note that this does not verify that a contract with a matching
             /// `Abi` is actually deployed at the given address.
             pub fn at<F, B, T>(
-                web3: &self::ethcontract::web3::api::Web3<T>,
+                web3: &self::ethcontract::Web3<T>,
                 address: self::ethcontract::Address,
             ) -> Self
             where
                 F: std::future::Future<
                         Output = Result<
                             self::ethcontract::json::Value,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
@@ -85,15 +87,15 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
                             Vec<
                                 Result<
                                     self::ethcontract::json::Value,
-                                    self::ethcontract::web3::Error,
+                                    self::ethcontract::Web3Error,
                                 >,
                             >,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
-                T: self::ethcontract::web3::Transport<Out = F>
-                    + self::ethcontract::web3::BatchTransport<Batch = B>
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
                     + Send
                     + Sync
                     + 'static,
@@ -110,15 +112,15 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
             /// actually deployed at the given address nor that the transaction hash,
             /// when provided, is actually for this contract deployment.
             pub fn with_deployment_info<F, B, T>(
-                web3: &self::ethcontract::web3::api::Web3<T>,
+                web3: &self::ethcontract::Web3<T>,
                 address: self::ethcontract::Address,
-                deployment_information: Option<ethcontract::common::DeploymentInformation>,
+                deployment_information: Option<self::ethcontract::common::DeploymentInformation>,
             ) -> Self
             where
                 F: std::future::Future<
                         Output = Result<
                             self::ethcontract::json::Value,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
@@ -127,22 +129,22 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
                             Vec<
                                 Result<
                                     self::ethcontract::json::Value,
-                                    self::ethcontract::web3::Error,
+                                    self::ethcontract::Web3Error,
                                 >,
                             >,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
-                T: self::ethcontract::web3::Transport<Out = F>
-                    + self::ethcontract::web3::BatchTransport<Batch = B>
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
                     + Send
                     + Sync
                     + 'static,
             {
                 use self::ethcontract::Instance;
                 use self::ethcontract::transport::DynTransport;
-                use self::ethcontract::web3::api::Web3;
+                use self::ethcontract::Web3;
 
                 let transport = DynTransport::new(web3.transport().clone());
                 let web3 = Web3::new(transport);
@@ -152,6 +154,89 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
                 Contract::from_raw(instance)
             }
 
+            /// Creates a new contract instance like `at`, and verifies that the
+            /// `web3` provider is connected to the chain with the expected
+            /// chain ID, if specified, and that there is contract code at the
+            /// given `Address`.
+            pub async fn at_checked<F, B, T>(
+                web3: &self::ethcontract::Web3<T>,
+                address: self::ethcontract::Address,
+                chain_id: Option<u64>,
+            ) -> Result<Self, self::ethcontract::errors::DeployError>
+            where
+                F: std::future::Future<
+                        Output = Result<
+                            self::ethcontract::json::Value,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                B: std::future::Future<
+                        Output = Result<
+                            Vec<
+                                Result<
+                                    self::ethcontract::json::Value,
+                                    self::ethcontract::Web3Error,
+                                >,
+                            >,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
+                    + Send
+                    + Sync
+                    + 'static,
+            {
+                let contract = Contract::at(web3, address);
+                contract.raw_instance().verify(chain_id).await?;
+
+                Ok(contract)
+            }
+
+            /// Creates a copy of this contract instance that uses a different
+            /// `web3` provider, keeping its address, deployment information
+            /// and method defaults.
+            pub fn with_transport<F, B, T>(
+                &self,
+                web3: &self::ethcontract::Web3<T>,
+            ) -> Self
+            where
+                F: std::future::Future<
+                        Output = Result<
+                            self::ethcontract::json::Value,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                B: std::future::Future<
+                        Output = Result<
+                            Vec<
+                                Result<
+                                    self::ethcontract::json::Value,
+                                    self::ethcontract::Web3Error,
+                                >,
+                            >,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
+                    + Send
+                    + Sync
+                    + 'static,
+            {
+                use self::ethcontract::transport::DynTransport;
+                use self::ethcontract::Web3;
+
+                let transport = DynTransport::new(web3.transport().clone());
+                let instance = self.raw_instance().with_transport(Web3::new(transport));
+
+                Contract::from_raw(instance)
+            }
+
             /// Creates a contract from a raw instance.
             fn from_raw(instance: self::ethcontract::dyns::DynInstance) -> Self {
                 let methods = Methods { instance };
@@ -165,10 +250,30 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
 
             /// Returns the deployment information of the contract
             /// if it is known, `None` otherwise.
-            pub fn deployment_information(&self) -> Option<ethcontract::common::DeploymentInformation> {
+            pub fn deployment_information(&self) -> Option<self::ethcontract::common::DeploymentInformation> {
                 self.raw_instance().deployment_information()
             }
 
+            /// Returns whether the contract is an EIP-1167 minimal proxy, also
+            /// known as a clone, of the implementation contract at the given
+            /// `Address`.
+            pub async fn is_clone_of(
+                &self,
+                implementation: self::ethcontract::Address,
+            ) -> Result<bool, self::ethcontract::errors::ExecutionError> {
+                self.raw_instance().is_clone_of(implementation).await
+            }
+
+            /// Verifies that the `web3` provider is connected to the chain with
+            /// the expected chain ID, if specified, and that there is contract
+            /// code at the address of this instance.
+            pub async fn verify(
+                &self,
+                chain_id: Option<u64>,
+            ) -> Result<(), self::ethcontract::errors::DeployError> {
+                self.raw_instance().verify(chain_id).await
+            }
+
             /// Returns a reference to the default method options used by this
             /// contract.
             pub fn defaults(&self) -> &self::ethcontract::contract::MethodDefaults {
@@ -181,6 +286,16 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
                 &mut self.raw_instance_mut().defaults
             }
 
+            /// Sets the default method options that are applied to every
+            /// method of this contract.
+            pub fn with_defaults(
+                mut self,
+                defaults: self::ethcontract::contract::MethodDefaults,
+            ) -> Self {
+                *self.defaults_mut() = defaults;
+                self
+            }
+
             /// Returns a reference to the raw runtime instance used by this
             /// contract.
             pub fn raw_instance(&self) -> &self::ethcontract::dyns::DynInstance {
@@ -201,12 +316,47 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
                     .finish()
             }
         }
+
+        impl self::ethcontract::contract::TypedContract for Contract {
+            fn contract() -> &'static self::ethcontract::Contract {
+                Self::raw_contract()
+            }
+
+            fn from_instance(instance: self::ethcontract::dyns::DynInstance) -> Self {
+                Contract::from_raw(instance)
+            }
+        }
+
+        impl self::ethcontract::contract::AnyContract for Contract {
+            fn name(&self) -> &str {
+                stringify!(#contract_name)
+            }
+
+            fn raw_instance(&self) -> &self::ethcontract::dyns::DynInstance {
+                &self.methods.instance
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+        }
     }
 }
 
 /// Expands an `Address` into a literal representation that can be used with
 /// quasi-quoting for code generation.
-fn expand_address(address: Address) -> TokenStream {
+/// Serializes the contract artifact for embedding it into the bindings. Object
+/// keys and ABI items are sorted so that the same artifact always generates
+/// the same code.
+fn stable_json(contract: &Contract) -> String {
+    let mut json = serde_json::to_value(contract).unwrap();
+    if let Some(Value::Array(abi)) = json.get_mut("abi") {
+        abi.sort_by_cached_key(|item| item.to_string());
+    }
+    json.to_string()
+}
+
+pub(crate) fn expand_address(address: Address) -> TokenStream {
     let bytes = address
         .as_bytes()
         .iter()
@@ -223,21 +373,22 @@ fn expand_address(address: Address) -> TokenStream {
 fn expand_deployment_information(deployment: Option<DeploymentInformation>) -> TokenStream {
     match deployment {
         Some(DeploymentInformation::BlockNumber(block)) => quote! {
-            Some(ethcontract::common::DeploymentInformation::BlockNumber(#block))
+            Some(self::ethcontract::common::DeploymentInformation::BlockNumber(#block))
         },
         Some(DeploymentInformation::TransactionHash(hash)) => {
             let bytes = hash.as_bytes().iter().copied().map(Literal::u8_unsuffixed);
             quote! {
-                Some(ethcontract::common::DeploymentInformation::TransactionHash([#( #bytes ),*].into()))
+                Some(self::ethcontract::common::DeploymentInformation::TransactionHash([#( #bytes ),*].into()))
             }
         }
-        None => return quote! { None },
+        None => quote! { None },
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate::test_contract;
 
     #[test]
     #[rustfmt::skip]
@@ -265,17 +416,29 @@ mod tests {
         assert_quote!(
             expand_deployment_information(Some(DeploymentInformation::TransactionHash("000102030405060708090a0b0c0d0e0f10111213000000000000000000000000".parse().unwrap()))),
             {
-                Some(ethcontract::common::DeploymentInformation::TransactionHash([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0].into()))
+                Some(self::ethcontract::common::DeploymentInformation::TransactionHash([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0].into()))
             },
         );
 
         assert_quote!(
             expand_deployment_information(Some(DeploymentInformation::BlockNumber(42))),
             {
-                Some(ethcontract::common::DeploymentInformation::BlockNumber(42u64))
+                Some(self::ethcontract::common::DeploymentInformation::BlockNumber(42u64))
             },
         );
 
 
     }
+
+    #[test]
+    fn stable_json_sorts_abi() {
+        let contract = |abi: &str| test_contract("Contract", abi);
+        let foo = r#"{"type":"function","name":"foo","inputs":[],"outputs":[]}"#;
+        let bar = r#"{"type":"function","name":"bar","inputs":[],"outputs":[]}"#;
+
+        assert_eq!(
+            stable_json(&contract(&format!("[{},{}]", foo, bar))),
+            stable_json(&contract(&format!("[{},{}]", bar, foo))),
+        );
+    }
 }
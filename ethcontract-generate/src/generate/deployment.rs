@@ -1,14 +1,15 @@
+use crate::errors::GenerateError;
 use crate::generate::{methods, Context};
 use crate::util;
-use anyhow::{Context as _, Result};
+use ethcontract_common::Address;
 use inflector::Inflector;
 use proc_macro2::{Literal, TokenStream};
 use quote::quote;
+use std::collections::HashMap;
 
-pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream, GenerateError> {
     let deployed = expand_deployed(cx);
-    let deploy =
-        expand_deploy(cx).context("error generating contract `deploy` associated function")?;
+    let deploy = expand_deploy(cx)?;
 
     Ok(quote! {
         #deployed
@@ -29,13 +30,13 @@ fn expand_deployed(cx: &Context) -> TokenStream {
             /// Note that this does not verify that a contract with a matching
             /// `Abi` is actually deployed at the given address.
             pub async fn deployed<F, B, T>(
-                web3: &self::ethcontract::web3::api::Web3<T>,
+                web3: &self::ethcontract::Web3<T>,
             ) -> Result<Self, self::ethcontract::errors::DeployError>
             where
                 F: std::future::Future<
                         Output = Result<
                             self::ethcontract::json::Value,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
@@ -44,15 +45,15 @@ fn expand_deployed(cx: &Context) -> TokenStream {
                             Vec<
                                 Result<
                                     self::ethcontract::json::Value,
-                                    self::ethcontract::web3::Error,
+                                    self::ethcontract::Web3Error,
                                 >,
                             >,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
-                T: self::ethcontract::web3::Transport<Out = F>
-                    + self::ethcontract::web3::BatchTransport<Batch = B>
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
                     + Send
                     + Sync
                     + 'static,
@@ -66,11 +67,49 @@ fn expand_deployed(cx: &Context) -> TokenStream {
 
                 Ok(Contract::from_raw(instance))
             }
+
+            /// Locates a deployed contract like `deployed`, and verifies that
+            /// there is contract code at its address on the network reported
+            /// by the `web3` provider.
+            pub async fn deployed_checked<F, B, T>(
+                web3: &self::ethcontract::Web3<T>,
+            ) -> Result<Self, self::ethcontract::errors::DeployError>
+            where
+                F: std::future::Future<
+                        Output = Result<
+                            self::ethcontract::json::Value,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                B: std::future::Future<
+                        Output = Result<
+                            Vec<
+                                Result<
+                                    self::ethcontract::json::Value,
+                                    self::ethcontract::Web3Error,
+                                >,
+                            >,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
+                    + Send
+                    + Sync
+                    + 'static,
+            {
+                let contract = Contract::deployed(web3).await?;
+                contract.raw_instance().verify(None).await?;
+
+                Ok(contract)
+            }
         }
     }
 }
 
-fn expand_deploy(cx: &Context) -> Result<TokenStream> {
+fn expand_deploy(cx: &Context) -> Result<TokenStream, GenerateError> {
     if cx.contract.bytecode.is_empty() {
         // do not generate deploy method for contracts that have empty bytecode
         return Ok(quote! {});
@@ -82,7 +121,10 @@ fn expand_deploy(cx: &Context) -> Result<TokenStream> {
 
     let (input, arg) = match cx.contract.abi.constructor() {
         Some(constructor) => (
-            methods::expand_inputs(&constructor.inputs)?,
+            methods::expand_inputs(
+                &cx.type_substitutions.item("constructor"),
+                &constructor.inputs,
+            )?,
             methods::expand_inputs_call_arg(&constructor.inputs),
         ),
         None => (quote! {}, quote! {()}),
@@ -94,6 +136,21 @@ fn expand_deploy(cx: &Context) -> Result<TokenStream> {
         .undefined_libraries()
         .map(|name| (name, util::safe_ident(&name.to_snake_case())))
         .collect();
+
+    // NOTE: Check that the bytecode can be linked with the libraries so that
+    //   the generated `deploy` function does not panic when linking.
+    let mut fields = HashMap::new();
+    let mut bytecode = cx.contract.bytecode.clone();
+    for (name, field) in &libs {
+        if let Some(other) = fields.insert(field.to_string(), name) {
+            return Err(GenerateError::NameCollision(format!(
+                "libraries '{}' and '{}' both expand to field '{}'",
+                other, name, field,
+            )));
+        }
+        bytecode.link(name, Address::zero())?;
+    }
+    bytecode.to_bytes()?;
     let (lib_struct, lib_input, link) = if !libs.is_empty() {
         let lib_struct = {
             let lib_struct_fields = libs.iter().map(|(name, field)| {
@@ -140,13 +197,13 @@ fn expand_deploy(cx: &Context) -> Result<TokenStream> {
             #doc
             #[allow(clippy::too_many_arguments)]
             pub fn builder<F, B, T>(
-                web3: &self::ethcontract::web3::api::Web3<T> #lib_input #input ,
+                web3: &self::ethcontract::Web3<T> #lib_input #input ,
             ) -> self::ethcontract::dyns::DynDeployBuilder<Self>
             where
                 F: std::future::Future<
                         Output = Result<
                             self::ethcontract::json::Value,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
@@ -155,22 +212,22 @@ fn expand_deploy(cx: &Context) -> Result<TokenStream> {
                             Vec<
                                 Result<
                                     self::ethcontract::json::Value,
-                                    self::ethcontract::web3::Error,
+                                    self::ethcontract::Web3Error,
                                 >,
                             >,
-                            self::ethcontract::web3::Error,
+                            self::ethcontract::Web3Error,
                         >,
                     > + Send
                     + 'static,
-                T: self::ethcontract::web3::Transport<Out = F>
-                    + self::ethcontract::web3::BatchTransport<Batch = B>
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
                     + Send
                     + Sync
                     + 'static,
             {
                 use self::ethcontract::dyns::DynTransport;
                 use self::ethcontract::contract::DeployBuilder;
-                use self::ethcontract::web3::api::Web3;
+                use self::ethcontract::Web3;
 
                 let transport = DynTransport::new(web3.transport().clone());
                 let web3 = Web3::new(transport);
@@ -180,6 +237,53 @@ fn expand_deploy(cx: &Context) -> Result<TokenStream> {
 
                 DeployBuilder::new(web3, bytecode, #arg).expect("valid deployment args")
             }
+
+            /// Creates a deploy builder for an EIP-1167 minimal proxy, also
+            /// known as a clone, that delegates all calls to an existing
+            /// implementation of this contract at the given `Address`.
+            ///
+            /// Note that the constructor is not executed for clones, so they
+            /// are usually initialized with a separate transaction.
+            pub fn deploy_clone<F, B, T>(
+                web3: &self::ethcontract::Web3<T>,
+                implementation: self::ethcontract::Address,
+            ) -> self::ethcontract::dyns::DynDeployBuilder<Self>
+            where
+                F: std::future::Future<
+                        Output = Result<
+                            self::ethcontract::json::Value,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                B: std::future::Future<
+                        Output = Result<
+                            Vec<
+                                Result<
+                                    self::ethcontract::json::Value,
+                                    self::ethcontract::Web3Error,
+                                >,
+                            >,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
+                    + Send
+                    + Sync
+                    + 'static,
+            {
+                use self::ethcontract::dyns::DynTransport;
+                use self::ethcontract::contract::DeployBuilder;
+                use self::ethcontract::Web3;
+
+                let transport = DynTransport::new(web3.transport().clone());
+                let web3 = Web3::new(transport);
+
+                let bytecode = Self::raw_contract().bytecode.clone();
+                DeployBuilder::clone_of(web3, bytecode, implementation)
+            }
         }
 
         impl self::ethcontract::contract::Deploy<self::ethcontract::dyns::DynTransport> for Contract {
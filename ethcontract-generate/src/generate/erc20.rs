@@ -0,0 +1,316 @@
+//! Detection of ERC20 token contracts and expansion of ERC20 specific
+//! bindings.
+
+use crate::errors::GenerateError;
+use crate::generate::Context;
+use ethcontract_common::abi::Function;
+use ethcontract_common::abiext::FunctionExt;
+use ethcontract_common::Abi;
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+/// The maximum number of decimals of a token, matching
+/// `ethcontract::units::MAX_DECIMALS`. Token amounts with more decimals would
+/// overflow a 256-bit integer for any amount greater than zero.
+pub const MAX_TOKEN_DECIMALS: u32 = 77;
+
+/// Functions that a contract must implement to be considered an ERC20 token.
+const ERC20_FUNCTIONS: &[&str] = &[
+    "totalSupply()",
+    "balanceOf(address)",
+    "transfer(address,uint256)",
+    "transferFrom(address,address,uint256)",
+    "approve(address,uint256)",
+    "allowance(address,address)",
+];
+
+/// Returns `true` if the ABI implements the ERC20 token standard functions.
+pub(crate) fn is_erc20(abi: &Abi) -> bool {
+    ERC20_FUNCTIONS.iter().all(|signature| {
+        abi.functions()
+            .any(|function| function.abi_signature() == *signature)
+    })
+}
+
+/// The parameters of a function that represent token amounts.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct TokenAmounts {
+    /// Indices of input parameters that are token amounts.
+    pub inputs: &'static [usize],
+    /// Whether or not the function output is a token amount.
+    pub output: bool,
+}
+
+/// Returns the parameters of a standard ERC20 function that should be
+/// expanded to `TokenAmount`s. This is only the case when the bindings were
+/// configured with the number of decimals of the token.
+pub(crate) fn token_amounts(cx: &Context, function: &Function) -> TokenAmounts {
+    if cx.token_decimals.is_none() {
+        return TokenAmounts::default();
+    }
+
+    let (inputs, output): (&'static [usize], _) = match function.abi_signature().as_str() {
+        "totalSupply()" | "balanceOf(address)" | "allowance(address,address)" => (&[], true),
+        "transfer(address,uint256)"
+        | "approve(address,uint256)"
+        | "increaseAllowance(address,uint256)"
+        | "decreaseAllowance(address,uint256)" => (&[1], false),
+        "transferFrom(address,address,uint256)" => (&[2], false),
+        _ => (&[], false),
+    };
+    TokenAmounts { inputs, output }
+}
+
+/// Expands the type used for token amounts.
+pub(crate) fn expand_amount_type() -> TokenStream {
+    quote! { self::ethcontract::units::TokenAmount<TokenDecimals> }
+}
+
+/// Expands the token decimals marker type when the bindings were configured
+/// with the number of decimals of the token.
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream, GenerateError> {
+    let decimals = match cx.token_decimals {
+        Some(decimals) => decimals,
+        None => return Ok(quote! {}),
+    };
+    if decimals > MAX_TOKEN_DECIMALS {
+        return Err(GenerateError::InvalidOption(format!(
+            "token decimals must be at most {} but {} were specified",
+            MAX_TOKEN_DECIMALS, decimals,
+        )));
+    }
+    if !is_erc20(&cx.contract.abi) {
+        return Err(GenerateError::InvalidOption(
+            "token decimals were specified but the contract is not an ERC20 token".to_owned(),
+        ));
+    }
+
+    let decimals = Literal::u32_unsuffixed(decimals);
+    Ok(quote! {
+        /// Marker type for the number of decimals of the token, used for
+        /// typed token amounts.
+        #[derive(Clone, Copy, Debug)]
+        pub struct TokenDecimals;
+
+        impl self::ethcontract::units::Decimals for TokenDecimals {
+            const DECIMALS: u32 = #decimals;
+        }
+    })
+}
+
+/// Expands the allowance convenience methods for ERC20 token contracts.
+pub(crate) fn expand_allowance(cx: &Context) -> TokenStream {
+    if !is_erc20(&cx.contract.abi) {
+        return quote! {};
+    }
+
+    let (amount_type, min_amount) = match cx.token_decimals {
+        Some(_) => (expand_amount_type(), quote! { min_amount.into() }),
+        None => (quote! { self::ethcontract::U256 }, quote! { min_amount }),
+    };
+
+    quote! {
+        impl Contract {
+            /// Ensures that `spender` is allowed to spend at least `min_amount`
+            /// tokens of `owner`, only sending an approval when the current
+            /// allowance is insufficient. Returns the results of the approval
+            /// transactions that were sent.
+            pub async fn ensure_allowance(
+                &self,
+                owner: self::ethcontract::Address,
+                spender: self::ethcontract::Address,
+                min_amount: #amount_type,
+            ) -> Result<
+                Vec<self::ethcontract::transaction::TransactionResult>,
+                self::ethcontract::errors::MethodError,
+            > {
+                self.ensure_allowance_with(owner, spender, min_amount, Default::default())
+                    .await
+            }
+
+            /// Ensures that `spender` is allowed to spend at least `min_amount`
+            /// tokens of `owner` with the specified options, for example for
+            /// resetting the allowance to zero first for tokens that require
+            /// it.
+            pub async fn ensure_allowance_with(
+                &self,
+                owner: self::ethcontract::Address,
+                spender: self::ethcontract::Address,
+                min_amount: #amount_type,
+                options: self::ethcontract::contract::AllowanceOptions,
+            ) -> Result<
+                Vec<self::ethcontract::transaction::TransactionResult>,
+                self::ethcontract::errors::MethodError,
+            > {
+                self.raw_instance()
+                    .ensure_allowance(owner, spender, #min_amount, options)
+                    .await
+            }
+        }
+    }
+}
+
+/// Expands the batched balance convenience methods for ERC20 token contracts.
+pub(crate) fn expand_balances(cx: &Context) -> TokenStream {
+    if !is_erc20(&cx.contract.abi) {
+        return quote! {};
+    }
+
+    let (amount_type, balances) = match cx.token_decimals {
+        Some(_) => (
+            expand_amount_type(),
+            quote! { balances.into_iter().map(self::ethcontract::units::TokenAmount::from_raw).collect() },
+        ),
+        None => (quote! { self::ethcontract::U256 }, quote! { balances }),
+    };
+
+    quote! {
+        impl Contract {
+            /// Retrieves the token balances of multiple owners in the latest
+            /// block, or the block with the default block tag of the contract,
+            /// with batched `balanceOf` calls, in the order of the owners.
+            pub async fn balance_of_many(
+                &self,
+                owners: &[self::ethcontract::Address],
+            ) -> Result<Vec<#amount_type>, self::ethcontract::errors::MethodError> {
+                let balances = self.raw_instance().balance_of_many(owners).await?;
+                Ok(#balances)
+            }
+
+            /// Retrieves the token balances of multiple owners in the
+            /// specified block.
+            pub async fn balance_of_many_at(
+                &self,
+                owners: &[self::ethcontract::Address],
+                block: impl Into<self::ethcontract::BlockId>,
+            ) -> Result<Vec<#amount_type>, self::ethcontract::errors::MethodError> {
+                let balances = self
+                    .raw_instance()
+                    .balance_of_many_at(owners, block)
+                    .await?;
+                Ok(#balances)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+    use crate::ContractBuilder;
+    use ethcontract_common::Contract;
+
+    fn erc20() -> Contract {
+        let abi = ERC20_FUNCTIONS
+            .iter()
+            .map(|signature| {
+                let name = &signature[..signature.find('(').unwrap()];
+                let inputs = match *signature {
+                    "balanceOf(address)" => r#"[{"name":"owner","type":"address"}]"#,
+                    "transfer(address,uint256)" | "approve(address,uint256)" => {
+                        r#"[{"name":"to","type":"address"},{"name":"amount","type":"uint256"}]"#
+                    }
+                    "transferFrom(address,address,uint256)" => {
+                        r#"[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"amount","type":"uint256"}]"#
+                    }
+                    "allowance(address,address)" => {
+                        r#"[{"name":"owner","type":"address"},{"name":"spender","type":"address"}]"#
+                    }
+                    _ => "[]",
+                };
+                format!(
+                    r#"{{"type":"function","name":"{}","inputs":{},"outputs":[{{"name":"","type":"uint256"}}],"stateMutability":"view"}}"#,
+                    name, inputs,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        test_contract("Token", &format!("[{}]", abi))
+    }
+
+    #[test]
+    fn detects_erc20() {
+        assert!(is_erc20(&erc20().abi));
+        assert!(!is_erc20(&Contract::with_name("Empty").abi));
+    }
+
+    #[test]
+    fn expand_token_decimals() {
+        let contract = erc20();
+        let cx =
+            Context::from_builder(&contract, ContractBuilder::new().token_decimals(6)).unwrap();
+
+        assert_quote!(expand(&cx).unwrap(), {
+            /// Marker type for the number of decimals of the token, used for
+            /// typed token amounts.
+            #[derive(Clone, Copy, Debug)]
+            pub struct TokenDecimals;
+
+            impl self::ethcontract::units::Decimals for TokenDecimals {
+                const DECIMALS: u32 = 6;
+            }
+        });
+
+        let transfer = contract.abi.function("transfer").unwrap();
+        let amounts = token_amounts(&cx, transfer);
+        assert_eq!(amounts.inputs, &[1]);
+        assert!(!amounts.output);
+    }
+
+    #[test]
+    fn expand_without_token_decimals() {
+        let contract = erc20();
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+
+        assert_quote!(expand(&cx).unwrap(), {});
+        let balance_of = contract.abi.function("balanceOf").unwrap();
+        assert!(!token_amounts(&cx, balance_of).output);
+    }
+
+    #[test]
+    fn expand_allowance_only_for_erc20() {
+        let contract = erc20();
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert!(expand_allowance(&cx)
+            .to_string()
+            .contains("ensure_allowance"));
+
+        let contract = Contract::with_name("Empty");
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand_allowance(&cx), {});
+    }
+
+    #[test]
+    fn expand_balances_only_for_erc20() {
+        let contract = erc20();
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert!(expand_balances(&cx).to_string().contains("balance_of_many"));
+
+        let contract = Contract::with_name("Empty");
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand_balances(&cx), {});
+    }
+
+    #[test]
+    fn token_decimals_require_erc20() {
+        let contract = Contract::with_name("Empty");
+        let cx =
+            Context::from_builder(&contract, ContractBuilder::new().token_decimals(6)).unwrap();
+
+        assert!(matches!(expand(&cx), Err(GenerateError::InvalidOption(_))));
+    }
+
+    #[test]
+    fn token_decimals_are_limited() {
+        let contract = erc20();
+        let cx = Context::from_builder(
+            &contract,
+            ContractBuilder::new().token_decimals(MAX_TOKEN_DECIMALS + 1),
+        )
+        .unwrap();
+
+        assert!(matches!(expand(&cx), Err(GenerateError::InvalidOption(_))));
+    }
+}
@@ -1,6 +1,7 @@
-use crate::generate::{types, Context};
-use crate::util;
-use anyhow::Result;
+use crate::errors::GenerateError;
+use crate::generate::types::{ParamTypes, TypeSubstitutions};
+use crate::generate::Context;
+use crate::{util, AddressFormat, SerdeOptions, UintFormat};
 use ethcontract_common::abi::{Event, EventParam, Hash, ParamType};
 use ethcontract_common::abiext::EventExt;
 use inflector::Inflector;
@@ -8,7 +9,7 @@ use proc_macro2::{Literal, TokenStream};
 use quote::quote;
 use syn::Path;
 
-pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream, GenerateError> {
     let structs_mod = expand_structs_mod(cx)?;
     let filters = expand_filters(cx)?;
     let all_events = expand_all_events(cx);
@@ -20,14 +21,28 @@ pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
     })
 }
 
+/// Returns the events of the ABI sorted by signature, so that the same ABI
+/// always yields the same code since the events are stored in a `HashMap`.
+fn sorted_events<'a>(cx: &Context<'a>) -> Vec<&'a Event> {
+    let mut events = cx.contract.abi.events().collect::<Vec<_>>();
+    events.sort_by_cached_key(|event| event.abi_signature());
+    events
+}
+
 /// Expands into a module containing all the event data structures from the ABI.
-fn expand_structs_mod(cx: &Context) -> Result<TokenStream> {
-    let data_types = cx
-        .contract
-        .abi
-        .events()
-        .map(|event| expand_data_type(event, &cx.event_derives))
-        .collect::<Result<Vec<_>>>()?;
+fn expand_structs_mod(cx: &Context) -> Result<TokenStream, GenerateError> {
+    let data_types = sorted_events(cx)
+        .into_iter()
+        .map(|event| {
+            expand_data_type(
+                event,
+                &cx.type_substitutions,
+                &cx.event_derives,
+                cx.serde.as_ref(),
+                cx.arbitrary_impls,
+            )
+        })
+        .collect::<Result<Vec<_>, GenerateError>>()?;
     if data_types.is_empty() {
         return Ok(quote! {});
     }
@@ -47,10 +62,73 @@ fn expand_derives(derives: &[Path]) -> TokenStream {
     quote! {#(#derives),*}
 }
 
+/// Expands the `serde` derives and container attributes of an event type, or
+/// nothing if serde serialization is not derived.
+fn expand_serde_derives(serde: Option<&SerdeOptions>, rename_all: bool) -> TokenStream {
+    let serde = match serde {
+        Some(serde) => serde,
+        None => return quote! {},
+    };
+    let rename_all = match &serde.rename_all {
+        Some(case) if rename_all => {
+            let case = Literal::string(case);
+            quote! { #[serde(rename_all = #case)] }
+        }
+        _ => quote! {},
+    };
+
+    quote! {
+        #[derive(
+            self::ethcontract::private::serde::Serialize,
+            self::ethcontract::private::serde::Deserialize,
+        )]
+        #[serde(crate = "self::ethcontract::private::serde")]
+        #rename_all
+    }
+}
+
+/// Expands the `serde` attributes of the fields of an event data type for the
+/// configured representations. Fields with substituted types keep the
+/// representation of their type.
+fn expand_serde_field_attrs(
+    types: &ParamTypes,
+    event: &Event,
+    serde: Option<&SerdeOptions>,
+) -> Vec<TokenStream> {
+    let serde = match serde {
+        Some(serde) => serde,
+        None => return Vec::new(),
+    };
+
+    event
+        .inputs
+        .iter()
+        .map(|input| {
+            if types.substitute(&input.name, &input.kind).is_some() {
+                return quote! {};
+            }
+            let with = match (&input.kind, serde.uint_format, serde.address_format) {
+                (ParamType::Uint(n), UintFormat::Decimal, _) if n / 8 > 16 => "u256_decimal",
+                (ParamType::Int(n), UintFormat::Decimal, _) if n / 8 > 16 => "i256_decimal",
+                (ParamType::Address, _, AddressFormat::Checksummed) => "address_checksummed",
+                _ => return quote! {},
+            };
+            let with = Literal::string(&format!("self::ethcontract::serialization::{}", with));
+            quote! { #[serde(with = #with)] }
+        })
+        .collect()
+}
+
 /// Expands an ABI event into a single event data type. This can expand either
 /// into a structure or a tuple in the case where all event parameters (topics
 /// and data) are anonymous.
-fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream> {
+fn expand_data_type(
+    event: &Event,
+    types: &TypeSubstitutions,
+    event_derives: &[Path],
+    serde: Option<&SerdeOptions>,
+    arbitrary_impls: bool,
+) -> Result<TokenStream, GenerateError> {
     let event_name = expand_struct_name(event);
 
     let signature = expand_hash(event.signature());
@@ -58,21 +136,33 @@ fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream
     let abi_signature = event.abi_signature();
     let abi_signature_lit = Literal::string(&abi_signature);
     let abi_signature_doc = util::expand_doc(&format!("`{}`", abi_signature));
+    let constants = expand_constants(event);
 
-    let params = expand_params(event)?;
+    let types = types.item(&abi_signature);
+    let params = expand_params(&types, event)?;
     let param_names = params.iter().map(|param| &param.0);
+    let field_attrs = expand_serde_field_attrs(&types, event, serde);
 
     let all_anonymous_fields = event.inputs.iter().all(|input| input.name.is_empty());
     let (data_type_definition, data_type_construction) = if all_anonymous_fields {
-        expand_data_tuple(&event_name, &params)
+        expand_data_tuple(&event_name, &params, &field_attrs)
+    } else {
+        expand_data_struct(&event_name, &params, &field_attrs)
+    };
+    let arbitrary_impl = if arbitrary_impls {
+        expand_data_arbitrary(&event_name, &params, &data_type_construction)
     } else {
-        expand_data_struct(&event_name, &params)
+        quote! {}
     };
 
     let derives = expand_derives(event_derives);
+    let serde_derives = expand_serde_derives(serde, !all_anonymous_fields);
 
     Ok(quote! {
+        #constants
+
         #[derive(Clone, Debug, Default, Eq, PartialEq, #derives)]
+        #serde_derives
         pub #data_type_definition
 
         impl #event_name {
@@ -104,9 +194,47 @@ fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream
                 unimplemented!("events are only decoded, not encoded")
             }
         }
+
+        #arbitrary_impl
     })
 }
 
+/// Expands the constants for the ABI signature of an event and, for events
+/// that are not anonymous, its topic 0 hash.
+fn expand_constants(event: &Event) -> TokenStream {
+    let name = event.name.to_screaming_snake_case();
+
+    let abi_signature = event.abi_signature();
+    let signature_name = util::ident(&format!("{}_SIGNATURE", name));
+    let signature_doc = util::expand_doc(&format!(
+        "The ABI signature of the `{}` event.",
+        abi_signature
+    ));
+    let signature_lit = Literal::string(&abi_signature);
+
+    let topic = if event.anonymous {
+        quote! {}
+    } else {
+        let topic_name = util::ident(&format!("{}_TOPIC", name));
+        let topic_doc = util::expand_doc(&format!(
+            "The topic 0 of the `{}` event, which is the Keccak-256 hash of its ABI signature.",
+            abi_signature,
+        ));
+        let topic = expand_hash(event.signature());
+        quote! {
+            #topic_doc
+            pub const #topic_name: self::ethcontract::H256 = #topic;
+        }
+    };
+
+    quote! {
+        #signature_doc
+        pub const #signature_name: &str = #signature_lit;
+
+        #topic
+    }
+}
+
 /// Expands an ABI event into an identifier for its event data type.
 fn expand_struct_name(event: &Event) -> TokenStream {
     let event_name = util::ident(&event.name.to_pascal_case());
@@ -114,7 +242,10 @@ fn expand_struct_name(event: &Event) -> TokenStream {
 }
 
 /// Expands an ABI event into name-type pairs for each of its parameters.
-fn expand_params(event: &Event) -> Result<Vec<(TokenStream, TokenStream)>> {
+fn expand_params(
+    types: &ParamTypes,
+    event: &Event,
+) -> Result<Vec<(TokenStream, TokenStream)>, GenerateError> {
     event
         .inputs
         .iter()
@@ -122,23 +253,29 @@ fn expand_params(event: &Event) -> Result<Vec<(TokenStream, TokenStream)>> {
         .map(|(i, input)| {
             // NOTE: Events can contain nameless values.
             let name = util::expand_input_name(i, &input.name);
-            let ty = expand_input_type(input)?;
+            let ty = expand_input_type(types, input)?;
 
             Ok((name, ty))
         })
         .collect()
 }
 
-/// Expands an event data structure from its name-type parameter pairs. Returns
-/// a tuple with the type definition (i.e. the struct declaration) and
-/// construction (i.e. code for creating an instance of the event data).
+/// Expands an event data structure from its name-type parameter pairs and the
+/// attributes of its fields, if any. Returns a tuple with the type definition
+/// (i.e. the struct declaration) and construction (i.e. code for creating an
+/// instance of the event data).
 fn expand_data_struct(
     name: &TokenStream,
     params: &[(TokenStream, TokenStream)],
+    attrs: &[TokenStream],
 ) -> (TokenStream, TokenStream) {
     let fields = params
         .iter()
-        .map(|(name, ty)| quote! { pub #name: #ty })
+        .enumerate()
+        .map(|(i, (name, ty))| {
+            let attrs = attrs.get(i);
+            quote! { #attrs pub #name: #ty }
+        })
         .collect::<Vec<_>>();
 
     let param_names = params
@@ -153,15 +290,21 @@ fn expand_data_struct(
     (definition, construction)
 }
 
-/// Expands an event data named tuple from its name-type parameter pairs.
-/// Returns a tuple with the type definition and construction.
+/// Expands an event data named tuple from its name-type parameter pairs and
+/// the attributes of its fields, if any. Returns a tuple with the type
+/// definition and construction.
 fn expand_data_tuple(
     name: &TokenStream,
     params: &[(TokenStream, TokenStream)],
+    attrs: &[TokenStream],
 ) -> (TokenStream, TokenStream) {
     let fields = params
         .iter()
-        .map(|(_, ty)| quote! { pub #ty })
+        .enumerate()
+        .map(|(i, (_, ty))| {
+            let attrs = attrs.get(i);
+            quote! { #attrs pub #ty }
+        })
         .collect::<Vec<_>>();
 
     let param_names = params
@@ -176,13 +319,33 @@ fn expand_data_tuple(
     (definition, construction)
 }
 
+/// Expands an `Arbitrary` implementation for an event data type that generates
+/// each parameter in order.
+fn expand_data_arbitrary(
+    name: &TokenStream,
+    params: &[(TokenStream, TokenStream)],
+    construction: &TokenStream,
+) -> TokenStream {
+    let param_names = params.iter().map(|(name, _)| name);
+
+    quote! {
+        impl self::ethcontract::arbitrary::Arbitrary for #name {
+            fn arbitrary<R>(rng: &mut R) -> Self
+            where
+                R: self::ethcontract::arbitrary::Rng + ?Sized,
+            {
+                #( let #param_names = self::ethcontract::arbitrary::Arbitrary::arbitrary(rng); )*
+                #construction
+            }
+        }
+    }
+}
+
 /// Expands into an `Events` type with method definitions for creating event
 /// streams for all non-anonymous contract events in the ABI.
-fn expand_filters(cx: &Context) -> Result<TokenStream> {
-    let standard_events = cx
-        .contract
-        .abi
-        .events()
+fn expand_filters(cx: &Context) -> Result<TokenStream, GenerateError> {
+    let standard_events = sorted_events(cx)
+        .into_iter()
         .filter(|event| !event.anonymous)
         .collect::<Vec<_>>();
     if standard_events.is_empty() {
@@ -195,8 +358,8 @@ fn expand_filters(cx: &Context) -> Result<TokenStream> {
         .collect::<Vec<_>>();
     let builders = standard_events
         .iter()
-        .map(|event| expand_builder_type(event))
-        .collect::<Result<Vec<_>>>()?;
+        .map(|event| expand_builder_type(event, &cx.type_substitutions))
+        .collect::<Result<Vec<_>, GenerateError>>()?;
 
     Ok(quote! {
         impl Contract {
@@ -247,14 +410,17 @@ fn expand_filter(event: &Event) -> TokenStream {
 
 /// Expands an ABI event into a wrapped `EventBuilder` type with type-safe
 /// filter methods.
-fn expand_builder_type(event: &Event) -> Result<TokenStream> {
+fn expand_builder_type(
+    event: &Event,
+    types: &TypeSubstitutions,
+) -> Result<TokenStream, GenerateError> {
     let event_name = expand_struct_name(event);
     let builder_doc = util::expand_doc(&format!(
         "A builder for creating a filtered stream of `{}` events.",
         event_name
     ));
     let builder_name = expand_builder_name(event);
-    let topic_filters = expand_builder_topic_filters(event)?;
+    let topic_filters = expand_builder_topic_filters(&types.item(event.abi_signature()), event)?;
 
     Ok(quote! {
         #builder_doc
@@ -268,7 +434,7 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
             ///
             /// If left unset defaults to the latest block.
             #[allow(clippy::wrong_self_convention)]
-            pub fn from_block(mut self, block: self::ethcontract::BlockNumber) -> Self {
+            pub fn from_block(mut self, block: impl Into<self::ethcontract::BlockId>) -> Self {
                 self.0 = (self.0).from_block(block);
                 self
             }
@@ -277,7 +443,7 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
             ///
             /// If left unset defaults to the streaming until the end of days.
             #[allow(clippy::wrong_self_convention)]
-            pub fn to_block(mut self, block: self::ethcontract::BlockNumber) -> Self {
+            pub fn to_block(mut self, block: impl Into<self::ethcontract::BlockId>) -> Self {
                 self.0 = (self.0).to_block(block);
                 self
             }
@@ -290,6 +456,16 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
                 self
             }
 
+            /// Uses an adaptive block page size when querying past events, for
+            /// example the one learned by the transport.
+            pub fn adaptive_block_range(
+                mut self,
+                value: self::ethcontract::log::AdaptiveBlockRange,
+            ) -> Self {
+                self.0 = (self.0).adaptive_block_range(value);
+                self
+            }
+
             /// Sets the polling interval. This is used as the interval between
             /// consecutive `eth_getFilterChanges` calls to get filter updates.
             pub fn poll_interval(mut self, value: std::time::Duration) -> Self {
@@ -297,6 +473,13 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
                 self
             }
 
+            /// Streams events by polling blocks and checking their logs bloom
+            /// instead of using a node side log filter.
+            pub fn bloom_filter(mut self, enabled: bool) -> Self {
+                self.0 = (self.0).bloom_filter(enabled);
+                self
+            }
+
             #topic_filters
 
             /// Returns a future that resolves with a collection of all existing
@@ -317,19 +500,51 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
             > {
                 (self.0).stream()
             }
+
+            /// Creates an event stream from the current event builder that
+            /// shares an `eth_subscribe` subscription with other streams of
+            /// the specified multiplexer.
+            pub fn multiplexed_stream<M: self::ethcontract::DuplexTransport>(
+                self,
+                multiplexer: &self::ethcontract::log::LogMultiplexer<M>,
+            ) -> impl self::ethcontract::futures::stream::Stream<
+                Item = std::result::Result<
+                    self::ethcontract::StreamEvent<self::event_data::#event_name>,
+                    self::ethcontract::errors::EventError,
+                >,
+            > {
+                (self.0).multiplexed_stream(multiplexer)
+            }
+
+            /// Creates an event stream from the current event builder that
+            /// streams new events from the specified log source.
+            pub fn stream_from(
+                self,
+                source: &self::ethcontract::log::LogSource,
+            ) -> impl self::ethcontract::futures::stream::Stream<
+                Item = std::result::Result<
+                    self::ethcontract::StreamEvent<self::event_data::#event_name>,
+                    self::ethcontract::errors::EventError,
+                >,
+            > {
+                (self.0).stream_from(source)
+            }
         }
     })
 }
 
 /// Expands an ABI event into filter methods for its indexed parameters.
-fn expand_builder_topic_filters(event: &Event) -> Result<TokenStream> {
+fn expand_builder_topic_filters(
+    types: &ParamTypes,
+    event: &Event,
+) -> Result<TokenStream, GenerateError> {
     let topic_filters = event
         .inputs
         .iter()
         .filter(|input| input.indexed)
         .enumerate()
-        .map(|(topic_index, input)| expand_builder_topic_filter(topic_index, input))
-        .collect::<Result<Vec<_>>>()?;
+        .map(|(topic_index, input)| expand_builder_topic_filter(types, topic_index, input))
+        .collect::<Result<Vec<_>, GenerateError>>()?;
 
     Ok(quote! {
         #( #topic_filters )*
@@ -338,7 +553,11 @@ fn expand_builder_topic_filters(event: &Event) -> Result<TokenStream> {
 
 /// Expands a event parameter into an event builder filter method for the
 /// specified topic index.
-fn expand_builder_topic_filter(topic_index: usize, param: &EventParam) -> Result<TokenStream> {
+fn expand_builder_topic_filter(
+    types: &ParamTypes,
+    topic_index: usize,
+    param: &EventParam,
+) -> Result<TokenStream, GenerateError> {
     let doc = util::expand_doc(&format!(
         "Adds a filter for the {} event parameter.",
         param.name,
@@ -349,7 +568,7 @@ fn expand_builder_topic_filter(topic_index: usize, param: &EventParam) -> Result
     } else {
         util::safe_ident(&param.name.to_snake_case())
     };
-    let ty = expand_input_type(param)?;
+    let ty = expand_input_type(types, param)?;
 
     Ok(quote! {
         #doc
@@ -390,6 +609,22 @@ fn expand_all_events(cx: &Context) -> TokenStream {
 
         #event_enum
         #event_parse_log
+
+        impl Event {
+            /// Decodes logs into events in parallel, for example when
+            /// backfilling large numbers of historic events. The events are
+            /// ordered by block number and log index.
+            pub fn decode_logs_par(
+                logs: Vec<self::ethcontract::Log>,
+            ) -> Vec<
+                Result<
+                    self::ethcontract::contract::Event<Self>,
+                    self::ethcontract::errors::ExecutionError,
+                >,
+            > {
+                self::ethcontract::contract::decode_logs_par(logs)
+            }
+        }
     }
 }
 
@@ -416,10 +651,12 @@ fn expand_event_enum(cx: &Context) -> TokenStream {
     };
 
     let derives = expand_derives(&cx.event_derives);
+    let serde_derives = expand_serde_derives(cx.serde.as_ref(), false);
 
     quote! {
         /// A contract event.
         #[derive(Clone, Debug, Eq, PartialEq, #derives)]
+        #serde_derives
         pub enum Event {
             #( #variants, )*
         }
@@ -521,17 +758,19 @@ fn expand_event_parse_log(cx: &Context) -> TokenStream {
 ///
 /// Note that this is slightly different than an expanding a Solidity type as
 /// complex types like arrays and strings get emitted as hashes when they are
-/// indexed.
-fn expand_input_type(input: &EventParam) -> Result<TokenStream> {
+/// indexed. These are expanded to `ethcontract::tokens::Hash` of the type of
+/// the original value, so that they can be compared against candidate values.
+fn expand_input_type(types: &ParamTypes, input: &EventParam) -> Result<TokenStream, GenerateError> {
     Ok(match (&input.kind, input.indexed) {
         (ParamType::Array(..), true)
         | (ParamType::Bytes, true)
         | (ParamType::FixedArray(..), true)
         | (ParamType::String, true)
         | (ParamType::Tuple(..), true) => {
-            quote! { self::ethcontract::H256 }
+            let ty = types.expand(&input.name, &input.kind)?;
+            quote! { self::ethcontract::tokens::Hash<#ty> }
         }
-        (kind, _) => types::expand(kind)?,
+        (kind, _) => types.expand(&input.name, kind)?,
     })
 }
 
@@ -629,7 +868,7 @@ mod tests {
         };
 
         #[rustfmt::skip]
-        assert_quote!(expand_builder_topic_filters(&event).unwrap(), {
+        assert_quote!(expand_builder_topic_filters(&TypeSubstitutions::default().item(""), &event).unwrap(), {
             #[doc = "Adds a filter for the from event parameter."]
             pub fn from(mut self, topic: self::ethcontract::Topic<self::ethcontract::Address>) -> Self {
                 self.0 = (self.0).topic0(topic);
@@ -664,8 +903,8 @@ mod tests {
         };
 
         let name = expand_struct_name(&event);
-        let params = expand_params(&event).unwrap();
-        let (definition, construction) = expand_data_struct(&name, &params);
+        let params = expand_params(&TypeSubstitutions::default().item(""), &event).unwrap();
+        let (definition, construction) = expand_data_struct(&name, &params, &[]);
 
         assert_quote!(definition, {
             struct Foo {
@@ -676,6 +915,143 @@ mod tests {
         assert_quote!(construction, { Foo { a, p1 } });
     }
 
+    #[test]
+    fn expand_params_with_type_substitutions() {
+        let event = Event {
+            name: "Foo".into(),
+            inputs: vec![
+                EventParam {
+                    name: "a".into(),
+                    kind: ParamType::Uint(256),
+                    indexed: true,
+                },
+                EventParam {
+                    name: "b".into(),
+                    kind: ParamType::String,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "c".into(),
+                    kind: ParamType::Uint(256),
+                    indexed: false,
+                },
+            ],
+            anonymous: false,
+        };
+
+        let mut types = TypeSubstitutions::default();
+        types.types.insert("uint256".into(), quote! { crate::Wad });
+        for param in &["b", "c"] {
+            types.params.insert(
+                (event.abi_signature(), param.to_string()),
+                quote! { crate::Ray },
+            );
+        }
+
+        let name = expand_struct_name(&event);
+        let params = expand_params(&types.item(event.abi_signature()), &event).unwrap();
+        let (definition, _) = expand_data_struct(&name, &params, &[]);
+        assert_quote!(definition, {
+            struct Foo {
+                pub a: crate::Wad,
+                pub b: self::ethcontract::tokens::Hash<crate::Ray>,
+                pub c: crate::Ray,
+            }
+        });
+    }
+
+    #[test]
+    fn expand_data_struct_with_serde_representations() {
+        let event = Event {
+            name: "Foo".into(),
+            inputs: vec![
+                EventParam {
+                    name: "from".into(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "amount".into(),
+                    kind: ParamType::Uint(256),
+                    indexed: false,
+                },
+                EventParam {
+                    name: "delta".into(),
+                    kind: ParamType::Int(256),
+                    indexed: false,
+                },
+                EventParam {
+                    name: "small".into(),
+                    kind: ParamType::Uint(64),
+                    indexed: false,
+                },
+                EventParam {
+                    name: "wad".into(),
+                    kind: ParamType::Uint(256),
+                    indexed: false,
+                },
+            ],
+            anonymous: false,
+        };
+
+        let mut types = TypeSubstitutions::default();
+        types.params.insert(
+            (event.abi_signature(), "wad".to_owned()),
+            quote! { crate::Wad },
+        );
+        let types = types.item(event.abi_signature());
+        let serde = SerdeOptions {
+            uint_format: UintFormat::Decimal,
+            address_format: AddressFormat::Checksummed,
+            rename_all: Some("camelCase".into()),
+        };
+
+        let name = expand_struct_name(&event);
+        let params = expand_params(&types, &event).unwrap();
+        let attrs = expand_serde_field_attrs(&types, &event, Some(&serde));
+        let (definition, _) = expand_data_struct(&name, &params, &attrs);
+        assert_quote!(definition, {
+            struct Foo {
+                #[serde(with = "self::ethcontract::serialization::address_checksummed")]
+                pub from: self::ethcontract::Address,
+                #[serde(with = "self::ethcontract::serialization::u256_decimal")]
+                pub amount: self::ethcontract::U256,
+                #[serde(with = "self::ethcontract::serialization::i256_decimal")]
+                pub delta: self::ethcontract::I256,
+                pub small: u64,
+                pub wad: crate::Wad,
+            }
+        });
+
+        assert_quote!(expand_serde_derives(Some(&serde), true), {
+            #[derive(
+                self::ethcontract::private::serde::Serialize,
+                self::ethcontract::private::serde::Deserialize,
+            )]
+            #[serde(crate = "self::ethcontract::private::serde")]
+            #[serde(rename_all = "camelCase")]
+        });
+        assert!(
+            expand_serde_field_attrs(&types, &event, Some(&SerdeOptions::default()))
+                .iter()
+                .all(|attrs| attrs.is_empty())
+        );
+        assert!(expand_serde_derives(None, true).is_empty());
+    }
+
+    #[test]
+    fn invalid_serde_rename_convention() {
+        let contract = Contract::with_name("Contract");
+        let builder = ContractBuilder::new().serde(SerdeOptions {
+            rename_all: Some("camel".into()),
+            ..Default::default()
+        });
+        assert!(matches!(
+            Context::from_builder(&contract, builder),
+            Err(GenerateError::InvalidOption(_)),
+        ));
+    }
+
     #[test]
     fn expand_data_tuple_value() {
         let event = Event {
@@ -696,8 +1072,8 @@ mod tests {
         };
 
         let name = expand_struct_name(&event);
-        let params = expand_params(&event).unwrap();
-        let (definition, construction) = expand_data_tuple(&name, &params);
+        let params = expand_params(&TypeSubstitutions::default().item(""), &event).unwrap();
+        let (definition, construction) = expand_data_tuple(&name, &params, &[]);
 
         assert_quote!(definition, {
             struct Foo(pub bool, pub self::ethcontract::Address);
@@ -705,6 +1081,43 @@ mod tests {
         assert_quote!(construction, { Foo(p0, p1) });
     }
 
+    #[test]
+    fn expand_data_arbitrary_impl() {
+        let event = Event {
+            name: "Foo".into(),
+            inputs: vec![
+                EventParam {
+                    name: "a".into(),
+                    kind: ParamType::Bool,
+                    indexed: false,
+                },
+                EventParam {
+                    name: String::new(),
+                    kind: ParamType::Address,
+                    indexed: false,
+                },
+            ],
+            anonymous: false,
+        };
+
+        let name = expand_struct_name(&event);
+        let params = expand_params(&TypeSubstitutions::default().item(""), &event).unwrap();
+        let (_, construction) = expand_data_struct(&name, &params, &[]);
+
+        assert_quote!(expand_data_arbitrary(&name, &params, &construction), {
+            impl self::ethcontract::arbitrary::Arbitrary for Foo {
+                fn arbitrary<R>(rng: &mut R) -> Self
+                where
+                    R: self::ethcontract::arbitrary::Rng + ?Sized,
+                {
+                    let a = self::ethcontract::arbitrary::Arbitrary::arbitrary(rng);
+                    let p1 = self::ethcontract::arbitrary::Arbitrary::arbitrary(rng);
+                    Foo { a, p1 }
+                }
+            }
+        });
+    }
+
     #[test]
     fn expand_enum_for_all_events() {
         let mut contract = Contract::with_name("Contract");
@@ -824,6 +1237,35 @@ mod tests {
         });
     }
 
+    #[test]
+    fn expand_event_constants() {
+        let mut event = Event {
+            name: "OwnershipTransferred".into(),
+            inputs: vec![EventParam {
+                name: "owner".into(),
+                kind: ParamType::Address,
+                indexed: true,
+            }],
+            anonymous: false,
+        };
+
+        let topic = expand_hash(event.signature());
+        assert_quote!(expand_constants(&event), {
+            #[doc = "The ABI signature of the `OwnershipTransferred(address)` event."]
+            pub const OWNERSHIP_TRANSFERRED_SIGNATURE: &str = "OwnershipTransferred(address)";
+
+            #[doc = "The topic 0 of the `OwnershipTransferred(address)` event, which is the Keccak-256 hash of its ABI signature."]
+            pub const OWNERSHIP_TRANSFERRED_TOPIC: self::ethcontract::H256 = #topic;
+        });
+
+        event.anonymous = true;
+        assert_quote!(expand_constants(&event), {
+            #[doc = "The ABI signature of the `OwnershipTransferred(address) anonymous` event."]
+            pub const OWNERSHIP_TRANSFERRED_SIGNATURE: &str =
+                "OwnershipTransferred(address) anonymous";
+        });
+    }
+
     #[test]
     #[rustfmt::skip]
     fn expand_hash_value() {
@@ -0,0 +1,165 @@
+//! Filtering of the functions and events of a contract ABI, so that bindings
+//! are only generated for the items that are actually used.
+
+use crate::errors::GenerateError;
+use crate::util;
+use crate::ContractBuilder;
+use ethcontract_common::abiext::{EventExt, FunctionExt};
+use ethcontract_common::Contract;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Removes the functions and events that do not match the method and event
+/// filters of the builder from the contract ABI.
+///
+/// Patterns are matched against the item names and signatures. A pattern
+/// that does not match any item is an error, since it is most likely a typo.
+pub(crate) fn filter<'a>(
+    contract: &'a Contract,
+    builder: &ContractBuilder,
+) -> Result<Cow<'a, Contract>, GenerateError> {
+    if builder.method_filter.is_empty() && builder.event_filter.is_empty() {
+        return Ok(Cow::Borrowed(contract));
+    }
+
+    let mut contract = contract.clone();
+    if !builder.method_filter.is_empty() {
+        check_patterns(
+            "method",
+            &builder.method_filter,
+            contract
+                .abi
+                .functions()
+                .map(|function| (function.name.clone(), function.abi_signature())),
+        )?;
+        retain(&mut contract.abi.functions, |function| {
+            matches(
+                &builder.method_filter,
+                &function.name,
+                &function.abi_signature(),
+            )
+        });
+    }
+    if !builder.event_filter.is_empty() {
+        check_patterns(
+            "event",
+            &builder.event_filter,
+            contract
+                .abi
+                .events()
+                .map(|event| (event.name.clone(), event.abi_signature())),
+        )?;
+        retain(&mut contract.abi.events, |event| {
+            matches(&builder.event_filter, &event.name, &event.abi_signature())
+        });
+    }
+
+    Ok(Cow::Owned(contract))
+}
+
+fn matches(patterns: &[String], name: &str, signature: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| matches_pattern(pattern, name, signature))
+}
+
+fn matches_pattern(pattern: &str, name: &str, signature: &str) -> bool {
+    util::glob_match(pattern, name) || util::glob_match(pattern, signature)
+}
+
+fn check_patterns(
+    kind: &str,
+    patterns: &[String],
+    items: impl Iterator<Item = (String, String)>,
+) -> Result<(), GenerateError> {
+    let items = items.collect::<Vec<_>>();
+    for pattern in patterns {
+        if !items
+            .iter()
+            .any(|(name, signature)| matches_pattern(pattern, name, signature))
+        {
+            return Err(GenerateError::InvalidOption(format!(
+                "{} pattern '{}' does not match any {} of the contract",
+                kind, pattern, kind,
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn retain<T>(items: &mut HashMap<String, Vec<T>>, mut keep: impl FnMut(&T) -> bool) {
+    for overloads in items.values_mut() {
+        overloads.retain(|item| keep(item));
+    }
+    items.retain(|_, overloads| !overloads.is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+
+    fn contract() -> Contract {
+        test_contract(
+            "Pool",
+            r#"[
+                {"type":"function","name":"swap","inputs":[],"outputs":[]},
+                {"type":"function","name":"slot0","inputs":[],"outputs":[]},
+                {"type":"function","name":"observe","inputs":[],"outputs":[]},
+                {"type":"function","name":"observations","inputs":[],"outputs":[]},
+                {"type":"function","name":"mint","inputs":[{"name":"","type":"uint256"}],"outputs":[]},
+                {"type":"function","name":"mint","inputs":[],"outputs":[]},
+                {"type":"event","name":"Swap","inputs":[],"anonymous":false},
+                {"type":"event","name":"Mint","inputs":[],"anonymous":false}
+            ]"#,
+        )
+    }
+
+    fn names(items: impl Iterator<Item = String>) -> Vec<String> {
+        let mut names = items.collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn filter_methods_and_events() {
+        let contract = contract();
+        let mut builder = ContractBuilder::new();
+        builder.method_filter = vec!["swap".into(), "observ*".into(), "mint(uint256)".into()];
+        builder.event_filter = vec!["Swap".into()];
+
+        let filtered = filter(&contract, &builder).unwrap();
+        assert_eq!(
+            names(
+                filtered
+                    .abi
+                    .functions()
+                    .map(|function| function.abi_signature())
+            ),
+            ["mint(uint256)", "observations()", "observe()", "swap()"],
+        );
+        assert_eq!(
+            names(filtered.abi.events().map(|event| event.name.clone())),
+            ["Swap"],
+        );
+    }
+
+    #[test]
+    fn filter_without_patterns_keeps_everything() {
+        let contract = contract();
+        let filtered = filter(&contract, &ContractBuilder::new()).unwrap();
+        assert!(matches!(filtered, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn filter_pattern_matching_nothing() {
+        let contract = contract();
+        let mut builder = ContractBuilder::new();
+        builder.event_filter = vec!["Burn*".into()];
+
+        assert!(matches!(
+            filter(&contract, &builder),
+            Err(GenerateError::InvalidOption(_)),
+        ));
+    }
+}
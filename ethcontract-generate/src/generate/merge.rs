@@ -0,0 +1,191 @@
+//! Merging of the ABIs of multiple contracts into a single binding, for
+//! example when interfaces and their implementation are compiled into
+//! separate artifacts.
+
+use crate::errors::GenerateError;
+use ethcontract_common::abi::{Event, Function, Param};
+use ethcontract_common::abiext::{EventExt, FunctionExt};
+use ethcontract_common::Contract;
+use std::borrow::Cow;
+
+/// Merges the ABIs of the specified contracts into the ABI of the contract
+/// bindings are generated for, similarly to how Solidity inheritance composes
+/// interfaces.
+///
+/// Items with the same signature are only included once, and the name,
+/// bytecode, networks and documentation of the primary contract are kept.
+/// Items with the same signature whose definitions are incompatible, such as
+/// functions with different return types, are an error.
+pub(crate) fn merge<'a>(
+    contract: &'a Contract,
+    others: &[Contract],
+) -> Result<Cow<'a, Contract>, GenerateError> {
+    if others.is_empty() {
+        return Ok(Cow::Borrowed(contract));
+    }
+
+    let mut merged = contract.clone();
+    for other in others {
+        let origin = if other.name.is_empty() {
+            "merged contract".to_owned()
+        } else {
+            format!("contract '{}'", other.name)
+        };
+
+        match (&merged.abi.constructor, &other.abi.constructor) {
+            (Some(constructor), Some(other_constructor))
+                if kinds(&constructor.inputs) != kinds(&other_constructor.inputs) =>
+            {
+                return Err(GenerateError::AbiConflict(format!(
+                    "constructor of {} has different parameters",
+                    origin
+                )));
+            }
+            (None, Some(other_constructor)) => {
+                merged.abi.constructor = Some(other_constructor.clone())
+            }
+            _ => {}
+        }
+
+        for function in other.abi.functions() {
+            let overloads = merged
+                .abi
+                .functions
+                .entry(function.name.clone())
+                .or_default();
+            match overloads
+                .iter()
+                .find(|existing| existing.abi_signature() == function.abi_signature())
+            {
+                Some(existing) if !compatible_functions(existing, function) => {
+                    return Err(GenerateError::AbiConflict(format!(
+                        "function '{}' of {} has a different definition",
+                        function.abi_signature(),
+                        origin
+                    )));
+                }
+                Some(_) => {}
+                None => overloads.push(function.clone()),
+            }
+        }
+
+        for event in other.abi.events() {
+            let overloads = merged.abi.events.entry(event.name.clone()).or_default();
+            match overloads
+                .iter()
+                .find(|existing| existing.abi_signature() == event.abi_signature())
+            {
+                Some(existing) if !compatible_events(existing, event) => {
+                    return Err(GenerateError::AbiConflict(format!(
+                        "event '{}' of {} has a different definition",
+                        event.abi_signature(),
+                        origin
+                    )));
+                }
+                Some(_) => {}
+                None => overloads.push(event.clone()),
+            }
+        }
+
+        merged.abi.fallback |= other.abi.fallback;
+        merged.abi.receive |= other.abi.receive;
+    }
+
+    Ok(Cow::Owned(merged))
+}
+
+fn kinds(params: &[Param]) -> Vec<String> {
+    params.iter().map(|param| param.kind.to_string()).collect()
+}
+
+/// Functions with the same signature are compatible if they have the same
+/// return types and state mutability, parameter names may differ.
+fn compatible_functions(a: &Function, b: &Function) -> bool {
+    kinds(&a.outputs) == kinds(&b.outputs) && a.state_mutability == b.state_mutability
+}
+
+/// Events with the same signature are compatible if the same parameters are
+/// indexed, parameter names may differ.
+fn compatible_events(a: &Event, b: &Event) -> bool {
+    let indexed = |event: &Event| event.inputs.iter().map(|p| p.indexed).collect::<Vec<_>>();
+    indexed(a) == indexed(b) && a.anonymous == b.anonymous
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+
+    fn contract(name: &str, abi: &str) -> Contract {
+        test_contract(name, abi)
+    }
+
+    const INTERFACE: &str = r#"[
+        {"type":"function","name":"transfer","stateMutability":"nonpayable",
+         "inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"}],
+         "outputs":[{"name":"","type":"bool"}]},
+        {"type":"function","name":"totalSupply","stateMutability":"view",
+         "inputs":[],"outputs":[{"name":"","type":"uint256"}]},
+        {"type":"event","name":"Transfer","anonymous":false,"inputs":[
+         {"name":"from","type":"address","indexed":true},
+         {"name":"to","type":"address","indexed":true},
+         {"name":"value","type":"uint256","indexed":false}]}
+    ]"#;
+
+    #[test]
+    fn merge_deduplicates_identical_signatures() {
+        let implementation = contract(
+            "Token",
+            r#"[
+                {"type":"constructor","inputs":[{"name":"supply","type":"uint256"}]},
+                {"type":"function","name":"transfer","stateMutability":"nonpayable",
+                 "inputs":[{"name":"recipient","type":"address"},{"name":"amount","type":"uint256"}],
+                 "outputs":[{"name":"","type":"bool"}]},
+                {"type":"function","name":"transfer","stateMutability":"nonpayable",
+                 "inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"},
+                           {"name":"data","type":"bytes"}],
+                 "outputs":[{"name":"","type":"bool"}]}
+            ]"#,
+        );
+
+        let merged = merge(&implementation, &[contract("IERC20", INTERFACE)]).unwrap();
+        let mut signatures = merged
+            .abi
+            .functions()
+            .map(|function| function.abi_signature())
+            .collect::<Vec<_>>();
+        signatures.sort();
+
+        assert_eq!(merged.name, "Token");
+        assert!(merged.abi.constructor.is_some());
+        assert_eq!(
+            signatures,
+            [
+                "totalSupply()",
+                "transfer(address,uint256)",
+                "transfer(address,uint256,bytes)"
+            ],
+        );
+        assert_eq!(
+            merged.abi.functions["transfer"][0].inputs[0].name,
+            "recipient"
+        );
+        assert_eq!(merged.abi.events().count(), 1);
+    }
+
+    #[test]
+    fn merge_conflicting_definitions() {
+        let implementation = contract(
+            "Token",
+            r#"[
+                {"type":"function","name":"totalSupply","stateMutability":"nonpayable",
+                 "inputs":[],"outputs":[{"name":"","type":"uint256"}]}
+            ]"#,
+        );
+
+        assert!(matches!(
+            merge(&implementation, &[contract("IERC20", INTERFACE)]),
+            Err(GenerateError::AbiConflict(_)),
+        ));
+    }
+}
@@ -1,15 +1,18 @@
-use crate::generate::{types, Context};
+use crate::errors::GenerateError;
+use crate::generate::erc20::{self, TokenAmounts};
+use crate::generate::types::ParamTypes;
+use crate::generate::{common, Context};
 use crate::util;
-use anyhow::{anyhow, Context as _, Result};
-use ethcontract_common::abi::{Function, Param, StateMutability};
+use ethcontract_common::abi::{Function, Param, StateMutability, Uint};
 use ethcontract_common::abiext::FunctionExt;
 use ethcontract_common::hash::H32;
 use inflector::Inflector;
 use proc_macro2::{Literal, TokenStream};
 use quote::quote;
-use syn::Ident;
+use std::collections::{HashMap, HashSet};
+use syn::{Ident, Path};
 
-pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream, GenerateError> {
     let functions = expand_functions(cx)?;
     let fallback = expand_fallback(cx);
 
@@ -21,34 +24,55 @@ pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
 
 /// Expands a context into a method struct containing all the generated bindings
 /// to the Solidity contract methods.
-fn expand_functions(cx: &Context) -> Result<TokenStream> {
+fn expand_functions(cx: &Context) -> Result<TokenStream, GenerateError> {
     let mut aliases = cx.method_aliases.clone();
-    let functions = cx
-        .contract
-        .abi
-        .functions()
+    let mut output_structs = Vec::new();
+
+    // NOTE: We sort the functions by signature so that the same ABI always
+    //   yields the same code, since they are stored in a `HashMap`.
+    let mut functions = cx.contract.abi.functions().collect::<Vec<_>>();
+    functions.sort_by_cached_key(|function| function.abi_signature());
+    let functions = functions
+        .into_iter()
         .map(|function| {
             let signature = function.abi_signature();
 
             let alias = aliases.remove(&signature);
             let name = alias.unwrap_or_else(|| util::safe_ident(&function.name.to_snake_case()));
-            let signature = function.abi_signature();
             let selector = expand_selector(function.selector());
-            let inputs = expand_inputs(&function.inputs)
-                .with_context(|| format!("error expanding function '{}'", signature))?;
-            let input_types = expand_input_types(&function.inputs)
-                .with_context(|| format!("error expanding function '{}'", signature))?;
-            let outputs = expand_outputs(&function.outputs)
-                .with_context(|| format!("error expanding function '{}'", signature))?;
+            let amounts = erc20::token_amounts(cx, function);
+            let types = cx.type_substitutions.item(signature);
+            let inputs = expand_inputs_with_amounts(&types, &function.inputs, amounts)?;
+            let input_types = expand_input_types(&types, &function.inputs, amounts)?;
+            let outputs = match expand_output_struct(&types, function, &name)? {
+                Some((struct_name, output_struct)) => {
+                    output_structs.push(output_struct);
+                    quote! { self::outputs::#struct_name }
+                }
+                None => expand_outputs(&types, &function.outputs, amounts)?,
+            };
 
             Ok((function, name, selector, inputs, input_types, outputs))
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<Result<Vec<_>, GenerateError>>()?;
     if let Some(unused) = aliases.keys().next() {
-        return Err(anyhow!(
+        return Err(GenerateError::InvalidOption(format!(
             "a manual method alias for '{}' was specified but this method does not exist",
             unused,
-        ));
+        )));
+    }
+
+    let mut names = HashMap::new();
+    for (function, name, ..) in &functions {
+        if let Some(other) = names.insert(name.to_string(), function) {
+            return Err(GenerateError::NameCollision(format!(
+                "functions '{}' and '{}' both expand to method '{}', a method alias \
+                 must be specified for one of them",
+                other.abi_signature(),
+                function.abi_signature(),
+                name,
+            )));
+        }
     }
 
     let methods = functions
@@ -71,6 +95,22 @@ fn expand_functions(cx: &Context) -> Result<TokenStream> {
                 expand_signature_accessor(function, name, selector, input_types, outputs)
             });
 
+    let contract_trait = expand_trait(cx, &functions);
+    let examples = expand_examples(cx, &functions);
+    let outputs_mod = if output_structs.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            /// Module containing the generated return types for contract
+            /// methods with multiple named outputs.
+            pub mod outputs {
+                use super::ethcontract;
+
+                #( #output_structs )*
+            }
+        }
+    };
+
     let signatures_attrs = quote! { #[derive(Clone, Copy)] };
     let signatures_struct = quote! {
         struct Signatures;
@@ -128,9 +168,249 @@ fn expand_functions(cx: &Context) -> Result<TokenStream> {
                 &self.methods
             }
         }
+
+        #contract_trait
+        #outputs_mod
+        #examples
     })
 }
 
+/// Expands the generated function bindings into a trait mirroring the contract
+/// methods along with its implementation for the contract type, when enabled.
+/// View methods are called and other methods are sent as transactions.
+fn expand_trait(
+    cx: &Context,
+    functions: &[(
+        &Function,
+        Ident,
+        TokenStream,
+        TokenStream,
+        TokenStream,
+        TokenStream,
+    )],
+) -> TokenStream {
+    let trait_name = match &cx.contract_trait {
+        Some(name) => name,
+        None => return quote! {},
+    };
+
+    let (declarations, implementations): (Vec<_>, Vec<_>) = functions
+        .iter()
+        .map(|(function, name, _, inputs, _, outputs)| {
+            let (doc, output, action) = match function.state_mutability {
+                StateMutability::Pure | StateMutability::View => (
+                    format!("Calls the contract method `{}`.", function.signature()),
+                    quote! { #outputs },
+                    quote! { call },
+                ),
+                _ => (
+                    format!(
+                        "Sends a transaction to the contract method `{}`.",
+                        function.signature()
+                    ),
+                    quote! { self::ethcontract::transaction::TransactionResult },
+                    quote! { send },
+                ),
+            };
+            let doc = util::expand_doc(&doc);
+            let result = quote! {
+                self::ethcontract::futures::future::BoxFuture<
+                    '_,
+                    Result<#output, self::ethcontract::errors::MethodError>,
+                >
+            };
+            let arg = function
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(i, param)| util::expand_input_name(i, &param.name));
+
+            let declaration = quote! {
+                #doc
+                fn #name(&self #inputs) -> #result;
+            };
+            let implementation = quote! {
+                fn #name(&self #inputs) -> #result {
+                    self::ethcontract::futures::FutureExt::boxed(
+                        self.methods.#name(#( #arg ),*).#action()
+                    )
+                }
+            };
+
+            (declaration, implementation)
+        })
+        .unzip();
+
+    quote! {
+        /// Trait mirroring the methods of the contract, implemented by the
+        /// generated contract type. Application code can be written against
+        /// this trait so that it can be unit tested with mock implementations.
+        #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+        pub trait #trait_name {
+            #( #declarations )*
+        }
+
+        #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+        impl #trait_name for Contract {
+            #( #implementations )*
+        }
+    }
+}
+
+/// Expands the generated function bindings into a module of examples, when
+/// enabled, with a function for connecting to a node, one for creating a
+/// contract instance and one per method calling it with default arguments.
+fn expand_examples(
+    cx: &Context,
+    functions: &[(
+        &Function,
+        Ident,
+        TokenStream,
+        TokenStream,
+        TokenStream,
+        TokenStream,
+    )],
+) -> TokenStream {
+    if !cx.examples || functions.is_empty() {
+        return quote! {};
+    }
+
+    let deployed = !cx.contract.networks.is_empty() || !cx.networks.is_empty();
+    let (instance, instance_example) = if deployed {
+        (
+            quote! {
+                /// Creates an instance of the contract deployed on the network
+                /// of the node, as specified by the deployments of the artifact.
+                pub async fn instance(
+                    web3: &self::ethcontract::Web3<self::ethcontract::DynTransport>,
+                ) -> Result<super::Contract, self::ethcontract::errors::DeployError> {
+                    super::Contract::deployed(web3).await
+                }
+            },
+            "let instance = examples::instance(&web3).await?;",
+        )
+    } else {
+        (
+            quote! {
+                /// Creates an instance of the contract deployed at the
+                /// specified address.
+                pub fn instance(
+                    web3: &self::ethcontract::Web3<self::ethcontract::DynTransport>,
+                    address: self::ethcontract::Address,
+                ) -> super::Contract {
+                    super::Contract::at(web3, address)
+                }
+            },
+            "let instance = examples::instance(&web3, address);",
+        )
+    };
+
+    let (examples, names): (Vec<_>, Vec<_>) = functions
+        .iter()
+        .map(|(function, name, _, _, _, outputs)| {
+            let (doc, prefix, output, action) = match function.state_mutability {
+                StateMutability::Pure | StateMutability::View => (
+                    format!(
+                        "Calls the contract method `{}` with example arguments.",
+                        function.signature()
+                    ),
+                    "call",
+                    quote! { #outputs },
+                    quote! { call },
+                ),
+                _ => (
+                    format!(
+                        "Sends a transaction to the contract method `{}` with example \
+                         arguments.",
+                        function.signature()
+                    ),
+                    "send",
+                    quote! { self::ethcontract::transaction::TransactionResult },
+                    quote! { send },
+                ),
+            };
+            let doc = util::expand_doc(&doc);
+            let example = util::ident(&format!("{}_{}", prefix, name));
+            let args = function
+                .inputs
+                .iter()
+                .map(|_| quote! { Default::default() });
+
+            let tokens = quote! {
+                #doc
+                #[allow(clippy::type_complexity)]
+                pub async fn #example(
+                    instance: &super::Contract,
+                ) -> Result<#output, self::ethcontract::errors::MethodError> {
+                    instance.methods().#name(#( #args ),*).#action().await
+                }
+            };
+
+            (tokens, example)
+        })
+        .unzip();
+
+    let doc = util::expand_doc(&format!(
+        "Examples of using the contract bindings, with a function per contract \
+         method calling it with default arguments. The examples are compiled \
+         along with the bindings, so they are working starting points for using \
+         the contract:\n\n\
+         ```ignore\n\
+         let web3 = examples::connect(Http::new(\"http://localhost:8545\")?);\n\
+         {}\n\
+         let result = examples::{}(&instance).await?;\n\
+         ```",
+        instance_example, names[0],
+    ));
+    quote! {
+        #doc
+        pub mod examples {
+            // NOTE: Import everything from the contract module, since method
+            //   input and output types can refer to the `ethcontract` alias,
+            //   the `outputs` module and the `TokenDecimals` marker type.
+            use super::*;
+
+            /// Connects to a node with the specified transport, such as an
+            /// HTTP or WebSocket transport.
+            pub fn connect<F, B, T>(
+                transport: T,
+            ) -> self::ethcontract::Web3<self::ethcontract::DynTransport>
+            where
+                F: std::future::Future<
+                        Output = Result<
+                            self::ethcontract::json::Value,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                B: std::future::Future<
+                        Output = Result<
+                            Vec<
+                                Result<
+                                    self::ethcontract::json::Value,
+                                    self::ethcontract::Web3Error,
+                                >,
+                            >,
+                            self::ethcontract::Web3Error,
+                        >,
+                    > + Send
+                    + 'static,
+                T: self::ethcontract::Transport<Out = F>
+                    + self::ethcontract::BatchTransport<Batch = B>
+                    + Send
+                    + Sync
+                    + 'static,
+            {
+                self::ethcontract::Web3::new(self::ethcontract::DynTransport::new(transport))
+            }
+
+            #instance
+
+            #( #examples )*
+        }
+    }
+}
+
 fn expand_function(
     cx: &Context,
     function: &Function,
@@ -152,24 +432,110 @@ fn expand_function(
         .unwrap_or("Generated by `ethcontract`");
     let doc = util::expand_doc(doc_str);
 
-    let (method, result_type_name) = match function.state_mutability {
-        StateMutability::Pure | StateMutability::View => {
-            (quote! { view_method }, quote! { DynViewMethodBuilder })
+    // Only payable methods expose a `value` setter, so that sending ETH to a
+    // method that would revert is caught at compile time.
+    let (method, result, payability) = match function.state_mutability {
+        StateMutability::Pure | StateMutability::View => (
+            quote! { view_method },
+            quote! { self::ethcontract::dyns::DynViewMethodBuilder<#outputs> },
+            quote! {},
+        ),
+        StateMutability::NonPayable => (
+            quote! { method },
+            quote! {
+                self::ethcontract::dyns::DynMethodBuilder<
+                    #outputs,
+                    self::ethcontract::contract::NonPayable,
+                >
+            },
+            quote! { .non_payable() },
+        ),
+        StateMutability::Payable => (
+            quote! { method },
+            quote! { self::ethcontract::dyns::DynMethodBuilder<#outputs> },
+            quote! {},
+        ),
+    };
+    let codec = cx.method_codecs.get(&signature);
+    let call = match codec.and_then(|codec| codec.encode.as_ref()) {
+        Some(encode) => {
+            let method = match function.state_mutability {
+                StateMutability::Pure | StateMutability::View => quote! { view_method_with_data },
+                _ => quote! { method_with_data },
+            };
+            let names = function
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(i, param)| util::expand_input_name(i, &param.name));
+            quote! { #method(#selector, #encode(#( #names ),*)) }
+        }
+        None => {
+            let arg = expand_inputs_call_arg(&function.inputs);
+            quote! { #method(#selector, #arg) }
         }
-        _ => (quote! { method }, quote! { DynMethodBuilder }),
     };
-    let result = quote! { self::ethcontract::dyns::#result_type_name<#outputs> };
-    let arg = expand_inputs_call_arg(&function.inputs);
+    let decoder = codec
+        .and_then(|codec| codec.decode.as_ref())
+        .map(|decode| quote! { .output_decoder(#decode) });
+    let defaults = expand_method_defaults(cx, function);
 
     quote! {
         #doc
         pub fn #name(&self #inputs) -> #result {
-            self.instance.#method(#selector, #arg)
+            self.instance.#call
                 .expect("generated call")
+                #payability
+                #decoder
+                #defaults
         }
     }
 }
 
+/// Paths of the functions with which a method encodes its calldata and
+/// decodes its return data instead of using the standard ABI encoding.
+pub(crate) struct CustomCodec {
+    pub encode: Option<Path>,
+    pub decode: Option<Path>,
+}
+
+/// Expands the default options that were configured for a method into builder
+/// method calls.
+fn expand_method_defaults(cx: &Context, function: &Function) -> TokenStream {
+    let defaults = match cx.method_defaults.get(&function.abi_signature()) {
+        Some(defaults) => defaults,
+        None => return quote! {},
+    };
+    let view = matches!(
+        function.state_mutability,
+        StateMutability::Pure | StateMutability::View
+    );
+
+    let from = defaults.from.map(|from| {
+        let from = common::expand_address(from);
+        if view {
+            quote! { .from(#from) }
+        } else {
+            quote! { .from(self::ethcontract::Account::Local(#from, None)) }
+        }
+    });
+    let gas = defaults.gas.map(|gas| {
+        let gas = expand_u256(gas);
+        quote! { .gas(#gas) }
+    });
+    let value = defaults.value.map(|value| {
+        let value = expand_u256(value);
+        quote! { .value(#value) }
+    });
+
+    quote! { #from #gas #value }
+}
+
+fn expand_u256(value: Uint) -> TokenStream {
+    let limbs = value.0.iter().copied().map(Literal::u64_unsuffixed);
+    quote! { self::ethcontract::U256([#( #limbs ),*]) }
+}
+
 fn expand_signature_accessor(
     function: &Function,
     name: &Ident,
@@ -190,27 +556,58 @@ fn expand_signature_accessor(
     }
 }
 
-pub(crate) fn expand_inputs(inputs: &[Param]) -> Result<TokenStream> {
+pub(crate) fn expand_inputs(
+    types: &ParamTypes,
+    inputs: &[Param],
+) -> Result<TokenStream, GenerateError> {
+    expand_inputs_with_amounts(types, inputs, TokenAmounts::default())
+}
+
+fn expand_inputs_with_amounts(
+    types: &ParamTypes,
+    inputs: &[Param],
+    amounts: TokenAmounts,
+) -> Result<TokenStream, GenerateError> {
     let params = inputs
         .iter()
         .enumerate()
         .map(|(i, param)| {
             let name = util::expand_input_name(i, &param.name);
-            let kind = types::expand(&param.kind)?;
+            let kind = expand_type(types, param, amounts.inputs.contains(&i))?;
             Ok(quote! { #name: #kind })
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<Result<Vec<_>, GenerateError>>()?;
     Ok(quote! { #( , #params )* })
 }
 
-pub(crate) fn expand_input_types(inputs: &[Param]) -> Result<TokenStream> {
+fn expand_input_types(
+    types: &ParamTypes,
+    inputs: &[Param],
+    amounts: TokenAmounts,
+) -> Result<TokenStream, GenerateError> {
     let params = inputs
         .iter()
-        .map(|param| types::expand(&param.kind))
-        .collect::<Result<Vec<_>>>()?;
+        .enumerate()
+        .map(|(i, param)| expand_type(types, param, amounts.inputs.contains(&i)))
+        .collect::<Result<Vec<_>, GenerateError>>()?;
     Ok(quote! { ( #( #params ,)* ) })
 }
 
+/// Expands the Rust type for a function parameter, using a typed token amount
+/// for parameters that represent ERC20 token amounts unless a type was
+/// substituted for the parameter.
+fn expand_type(
+    types: &ParamTypes,
+    param: &Param,
+    amount: bool,
+) -> Result<TokenStream, GenerateError> {
+    if amount && types.substitute(&param.name, &param.kind).is_none() {
+        Ok(erc20::expand_amount_type())
+    } else {
+        types.expand(&param.name, &param.kind)
+    }
+}
+
 pub(crate) fn expand_inputs_call_arg(inputs: &[Param]) -> TokenStream {
     let names = inputs
         .iter()
@@ -219,20 +616,85 @@ pub(crate) fn expand_inputs_call_arg(inputs: &[Param]) -> TokenStream {
     quote! { ( #( #names ,)* ) }
 }
 
-fn expand_outputs(outputs: &[Param]) -> Result<TokenStream> {
+fn expand_outputs(
+    types: &ParamTypes,
+    outputs: &[Param],
+    amounts: TokenAmounts,
+) -> Result<TokenStream, GenerateError> {
     match outputs.len() {
         0 => Ok(quote! { () }),
-        1 => types::expand(&outputs[0].kind),
+        1 => expand_type(types, &outputs[0], amounts.output),
         _ => {
             let types = outputs
                 .iter()
-                .map(|param| types::expand(&param.kind))
-                .collect::<Result<Vec<_>>>()?;
+                .map(|param| types.expand(&param.name, &param.kind))
+                .collect::<Result<Vec<_>, GenerateError>>()?;
             Ok(quote! { (#( #types ),*) })
         }
     }
 }
 
+/// Expands the return type struct for a function with multiple named outputs,
+/// returning its name and definition. Returns `None` for functions with a
+/// single output, or with unnamed or conflicting output names, which return
+/// a tuple instead.
+fn expand_output_struct(
+    types: &ParamTypes,
+    function: &Function,
+    name: &Ident,
+) -> Result<Option<(Ident, TokenStream)>, GenerateError> {
+    let outputs = &function.outputs;
+    if outputs.len() < 2 || outputs.iter().any(|output| output.name.is_empty()) {
+        return Ok(None);
+    }
+
+    let fields = outputs
+        .iter()
+        .enumerate()
+        .map(|(i, output)| util::expand_input_name(i, &output.name))
+        .collect::<Vec<_>>();
+    let names = fields
+        .iter()
+        .map(ToString::to_string)
+        .collect::<HashSet<_>>();
+    if names.len() != fields.len() {
+        return Ok(None);
+    }
+
+    let types = outputs
+        .iter()
+        .map(|output| types.expand(&output.name, &output.kind))
+        .collect::<Result<Vec<_>, GenerateError>>()?;
+
+    let struct_name = util::ident(&format!("{}Output", name.to_string().to_pascal_case()));
+    let doc = util::expand_doc(&format!(
+        "Named outputs of the contract method `{}`.",
+        function.signature()
+    ));
+    let definition = quote! {
+        #doc
+        #[derive(Clone, Debug, Default, Eq, PartialEq)]
+        pub struct #struct_name {
+            #( pub #fields: #types, )*
+        }
+
+        impl self::ethcontract::tokens::Tokenize for #struct_name {
+            fn from_token(
+                token: self::ethcontract::common::abi::Token,
+            ) -> Result<Self, self::ethcontract::tokens::Error> {
+                let (#( #fields, )*) = self::ethcontract::tokens::Tokenize::from_token(token)?;
+                Ok(#struct_name { #( #fields ),* })
+            }
+
+            fn into_token(self) -> self::ethcontract::common::abi::Token {
+                self::ethcontract::tokens::Tokenize::into_token((#( self.#fields, )*))
+            }
+        }
+    };
+
+    Ok(Some((struct_name, definition)))
+}
+
 fn expand_selector(selector: H32) -> TokenStream {
     let bytes = selector.iter().copied().map(Literal::u8_unsuffixed);
     quote! { [#( #bytes ),*] }
@@ -263,18 +725,26 @@ fn expand_fallback(cx: &Context) -> TokenStream {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate::types::TypeSubstitutions;
+    use crate::{ContractBuilder, MethodCodec, MethodDefaults};
     use ethcontract_common::abi::ParamType;
+    use ethcontract_common::{Abi, Address, Contract};
 
     #[test]
     fn expand_inputs_empty() {
-        assert_quote!(expand_inputs(&[]).unwrap().to_string(), {},);
+        assert_quote!(
+            expand_inputs(&TypeSubstitutions::default().item(""), &[])
+                .unwrap()
+                .to_string(),
+            {},
+        );
     }
 
     #[test]
     fn expand_inputs_() {
         assert_quote!(
             expand_inputs(
-
+                &TypeSubstitutions::default().item("foo(bool,address)"),
                 &[
                     Param {
                         name: "a".to_string(),
@@ -293,16 +763,28 @@ mod tests {
 
     #[test]
     fn expand_outputs_empty() {
-        assert_quote!(expand_outputs(&[],).unwrap(), { () });
+        assert_quote!(
+            expand_outputs(
+                &TypeSubstitutions::default().item(""),
+                &[],
+                TokenAmounts::default()
+            )
+            .unwrap(),
+            { () }
+        );
     }
 
     #[test]
     fn expand_outputs_single() {
         assert_quote!(
-            expand_outputs(&[Param {
-                name: "a".to_string(),
-                kind: ParamType::Bool,
-            }])
+            expand_outputs(
+                &TypeSubstitutions::default().item(""),
+                &[Param {
+                    name: "a".to_string(),
+                    kind: ParamType::Bool,
+                }],
+                TokenAmounts::default()
+            )
             .unwrap(),
             { bool },
         );
@@ -311,18 +793,619 @@ mod tests {
     #[test]
     fn expand_outputs_multiple() {
         assert_quote!(
-            expand_outputs(&[
+            expand_outputs(
+                &TypeSubstitutions::default().item(""),
+                &[
+                    Param {
+                        name: "a".to_string(),
+                        kind: ParamType::Bool,
+                    },
+                    Param {
+                        name: "b".to_string(),
+                        kind: ParamType::Address,
+                    },
+                ],
+                TokenAmounts::default()
+            )
+            .unwrap(),
+            { (bool, self::ethcontract::Address) },
+        );
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_output_struct_for_named_outputs() {
+        #[allow(deprecated)]
+        let mut function = Function {
+            name: "getReserves".to_owned(),
+            inputs: Vec::new(),
+            outputs: vec![
                 Param {
-                    name: "a".to_string(),
-                    kind: ParamType::Bool,
+                    name: "reserve0".to_owned(),
+                    kind: ParamType::Uint(112),
                 },
                 Param {
-                    name: "b".to_string(),
-                    kind: ParamType::Address,
+                    name: "blockTimestampLast".to_owned(),
+                    kind: ParamType::Uint(32),
                 },
-            ],)
-            .unwrap(),
-            { (bool, self::ethcontract::Address) },
+            ],
+            constant: false,
+            state_mutability: StateMutability::View,
+        };
+
+        let (name, definition) = expand_output_struct(&TypeSubstitutions::default().item(""), &function, &util::ident("get_reserves"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(name, "GetReservesOutput");
+        assert_quote!(definition, {
+            #[doc = "Named outputs of the contract method `getReserves():(uint112,uint32)`."]
+            #[derive(Clone, Debug, Default, Eq, PartialEq)]
+            pub struct GetReservesOutput {
+                pub reserve_0: u128,
+                pub block_timestamp_last: u32,
+            }
+
+            impl self::ethcontract::tokens::Tokenize for GetReservesOutput {
+                fn from_token(
+                    token: self::ethcontract::common::abi::Token,
+                ) -> Result<Self, self::ethcontract::tokens::Error> {
+                    let (reserve_0, block_timestamp_last,) =
+                        self::ethcontract::tokens::Tokenize::from_token(token)?;
+                    Ok(GetReservesOutput { reserve_0, block_timestamp_last })
+                }
+
+                fn into_token(self) -> self::ethcontract::common::abi::Token {
+                    self::ethcontract::tokens::Tokenize::into_token((
+                        self.reserve_0,
+                        self.block_timestamp_last,
+                    ))
+                }
+            }
+        });
+
+        function.outputs[1].name.clear();
+        assert!(
+            expand_output_struct(&TypeSubstitutions::default().item(""), &function, &util::ident("get_reserves"))
+                .unwrap()
+                .is_none()
         );
     }
+
+    #[test]
+    fn expand_overloaded_functions_requires_alias() {
+        let mut contract = Contract::with_name("Contract");
+        contract.abi = Abi::load(
+            br#"[
+                {
+                    "type": "function",
+                    "name": "foo",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "view"
+                },
+                {
+                    "type": "function",
+                    "name": "foo",
+                    "inputs": [{ "name": "", "type": "bool" }],
+                    "outputs": [],
+                    "stateMutability": "view"
+                }
+            ]"#
+            .as_ref(),
+        )
+        .unwrap();
+
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        match expand_functions(&cx) {
+            Err(GenerateError::NameCollision(message)) => assert_eq!(
+                message,
+                "functions 'foo()' and 'foo(bool)' both expand to method 'foo', \
+                 a method alias must be specified for one of them",
+            ),
+            _ => panic!("expected a name collision"),
+        }
+
+        let cx = Context::from_builder(
+            &contract,
+            ContractBuilder::new().add_method_alias("foo(bool)", "foo_with_flag"),
+        )
+        .unwrap();
+        assert!(expand_functions(&cx).is_ok());
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_function_builders() {
+        let mut contract = Contract::with_name("Contract");
+        contract.abi = Abi::load(
+            br#"[
+                {
+                    "type": "function",
+                    "name": "get",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "view"
+                },
+                {
+                    "type": "function",
+                    "name": "set",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "nonpayable"
+                },
+                {
+                    "type": "function",
+                    "name": "deposit",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "payable"
+                }
+            ]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        let expand = |name: &str| {
+            let function = contract.abi.function(name).unwrap();
+            expand_function(
+                &cx,
+                function,
+                &util::ident(name),
+                &quote! { [0, 0, 0, 0] },
+                &quote! {},
+                &quote! { () },
+            )
+        };
+
+        assert_quote!(expand("get"), {
+            #[doc = "Generated by `ethcontract`"]
+            pub fn get(&self) -> self::ethcontract::dyns::DynViewMethodBuilder<()> {
+                self.instance.view_method([0, 0, 0, 0], ())
+                    .expect("generated call")
+            }
+        });
+        assert_quote!(expand("set"), {
+            #[doc = "Generated by `ethcontract`"]
+            pub fn set(&self) -> self::ethcontract::dyns::DynMethodBuilder<
+                (),
+                self::ethcontract::contract::NonPayable,
+            > {
+                self.instance.method([0, 0, 0, 0], ())
+                    .expect("generated call")
+                    .non_payable()
+            }
+        });
+        assert_quote!(expand("deposit"), {
+            #[doc = "Generated by `ethcontract`"]
+            pub fn deposit(&self) -> self::ethcontract::dyns::DynMethodBuilder<()> {
+                self.instance.method([0, 0, 0, 0], ())
+                    .expect("generated call")
+            }
+        });
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_custom_codecs() {
+        let mut contract = Contract::with_name("Contract");
+        contract.abi = Abi::load(
+            br#"[
+                {
+                    "type": "function",
+                    "name": "quote",
+                    "inputs": [{ "name": "amount", "type": "uint128" }],
+                    "outputs": [{ "name": "", "type": "uint128" }],
+                    "stateMutability": "view"
+                },
+                {
+                    "type": "function",
+                    "name": "swap",
+                    "inputs": [{ "name": "amount", "type": "uint128" }],
+                    "outputs": [],
+                    "stateMutability": "nonpayable"
+                }
+            ]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let builder = ContractBuilder::new()
+            .add_method_codec(
+                "quote",
+                MethodCodec {
+                    encode: Some("crate::codec::encode_quote".to_owned()),
+                    decode: Some("crate::codec::decode_quote".to_owned()),
+                },
+            )
+            .add_method_codec(
+                "swap(uint128)",
+                MethodCodec {
+                    encode: Some("encode_swap".to_owned()),
+                    decode: None,
+                },
+            );
+        let cx = Context::from_builder(&contract, builder).unwrap();
+        let expand = |name: &str| {
+            let function = contract.abi.function(name).unwrap();
+            expand_function(
+                &cx,
+                function,
+                &util::ident(name),
+                &quote! { [0, 0, 0, 0] },
+                &quote! { , amount: u128 },
+                &quote! { u128 },
+            )
+        };
+
+        assert_quote!(expand("quote"), {
+            #[doc = "Generated by `ethcontract`"]
+            pub fn quote(&self, amount: u128) -> self::ethcontract::dyns::DynViewMethodBuilder<u128> {
+                self.instance
+                    .view_method_with_data([0, 0, 0, 0], crate::codec::encode_quote(amount))
+                    .expect("generated call")
+                    .output_decoder(crate::codec::decode_quote)
+            }
+        });
+        assert_quote!(expand("swap"), {
+            #[doc = "Generated by `ethcontract`"]
+            pub fn swap(&self, amount: u128) -> self::ethcontract::dyns::DynMethodBuilder<
+                u128,
+                self::ethcontract::contract::NonPayable,
+            > {
+                self.instance
+                    .method_with_data([0, 0, 0, 0], encode_swap(amount))
+                    .expect("generated call")
+                    .non_payable()
+            }
+        });
+
+        for (method, codec, error) in vec![
+            (
+                "quote",
+                MethodCodec::default(),
+                "method codec for 'quote' has neither an encoder nor a decoder",
+            ),
+            (
+                "missing",
+                MethodCodec::default(),
+                "method codecs for 'missing' were specified but this method does not exist",
+            ),
+            (
+                "swap",
+                MethodCodec {
+                    encode: Some("not a path".to_owned()),
+                    decode: None,
+                },
+                "method codec function 'not a path': unexpected token",
+            ),
+        ] {
+            let builder = ContractBuilder::new().add_method_codec(method, codec);
+            match Context::from_builder(&contract, builder) {
+                Err(GenerateError::InvalidOption(message)) => assert_eq!(message, error),
+                _ => panic!("expected invalid method codec for '{}'", method),
+            }
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_method_defaults_() {
+        let mut contract = Contract::with_name("Contract");
+        contract.abi = Abi::load(
+            br#"[
+                {
+                    "type": "function",
+                    "name": "get",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "view"
+                },
+                {
+                    "type": "function",
+                    "name": "claim",
+                    "inputs": [],
+                    "outputs": [],
+                    "stateMutability": "payable"
+                },
+                {
+                    "type": "function",
+                    "name": "claim",
+                    "inputs": [{ "name": "", "type": "address" }],
+                    "outputs": [],
+                    "stateMutability": "nonpayable"
+                }
+            ]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let from = Address::repeat_byte(1);
+        let defaults = MethodDefaults {
+            gas: Some(300_000.into()),
+            value: Some(Uint::from(1) << 64),
+            from: Some(from),
+        };
+
+        let builder = ContractBuilder::new()
+            .add_method_defaults("claim()", defaults.clone())
+            .add_method_defaults("get", MethodDefaults {
+                from: Some(from),
+                ..Default::default()
+            });
+        let cx = Context::from_builder(&contract, builder).unwrap();
+        let address = common::expand_address(from);
+
+        let claim = contract.abi.functions_by_name("claim").unwrap();
+        assert_quote!(expand_method_defaults(&cx, &claim[0]), {
+            .from(self::ethcontract::Account::Local(#address, None))
+            .gas(self::ethcontract::U256([300000, 0, 0, 0]))
+            .value(self::ethcontract::U256([0, 1, 0, 0]))
+        });
+        assert!(expand_method_defaults(&cx, &claim[1]).is_empty());
+        assert_quote!(
+            expand_method_defaults(&cx, contract.abi.function("get").unwrap()),
+            { .from(#address) },
+        );
+
+        for (method, defaults, error) in vec![
+            (
+                "claim",
+                MethodDefaults::default(),
+                "method 'claim' is overloaded, method defaults must be specified by signature",
+            ),
+            (
+                "missing",
+                MethodDefaults::default(),
+                "method defaults for 'missing' were specified but this method does not exist",
+            ),
+            (
+                "claim(address)",
+                defaults,
+                "a default value was specified for method 'claim(address)' but it is not payable",
+            ),
+        ] {
+            let builder = ContractBuilder::new().add_method_defaults(method, defaults);
+            match Context::from_builder(&contract, builder) {
+                Err(GenerateError::InvalidOption(message)) => assert_eq!(message, error),
+                _ => panic!("expected invalid method defaults for '{}'", method),
+            }
+        }
+    }
+
+    #[test]
+    fn expand_type_substitutions() {
+        let mut contract = Contract::with_name("Contract");
+        contract.abi = Abi::load(
+            br#"[{
+                "type": "function",
+                "name": "setPrice",
+                "inputs": [
+                    { "name": "price", "type": "uint256" },
+                    { "name": "amounts", "type": "uint256[]" }
+                ],
+                "outputs": [{ "name": "", "type": "uint256" }],
+                "stateMutability": "nonpayable"
+            }]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let function = contract.abi.function("setPrice").unwrap();
+
+        let builder = ContractBuilder::new()
+            .add_type_substitution("uint", "crate::Wad")
+            .add_type_substitution("setPrice.price", "crate::Ray");
+        let cx = Context::from_builder(&contract, builder).unwrap();
+        let types = cx.type_substitutions.item(function.abi_signature());
+        assert_quote!(expand_inputs(&types, &function.inputs).unwrap(), {
+            , price: crate::Ray, amounts: Vec<crate::Wad>
+        });
+        assert_quote!(
+            expand_outputs(&types, &function.outputs, TokenAmounts::default()).unwrap(),
+            { crate::Wad },
+        );
+
+        for (target, error) in vec![
+            (
+                "setPrice.missing",
+                "a type substitution for 'setPrice.missing' was specified but 'setPrice' \
+                 has no parameter 'missing'",
+            ),
+            (
+                "missing.price",
+                "a type substitution for 'missing.price' was specified but this method does \
+                 not exist",
+            ),
+            (
+                "constructor.price",
+                "a type substitution for 'constructor.price' was specified but the contract \
+                 has no constructor",
+            ),
+        ] {
+            let builder = ContractBuilder::new().add_type_substitution(target, "crate::Wad");
+            match Context::from_builder(&contract, builder) {
+                Err(GenerateError::InvalidOption(message)) => assert_eq!(message, error),
+                _ => panic!("expected invalid type substitution for '{}'", target),
+            }
+        }
+        for (target, rust_type) in &[("uint256", "not a type"), ("foo", "crate::Wad")] {
+            let builder = ContractBuilder::new().add_type_substitution(*target, *rust_type);
+            assert!(matches!(
+                Context::from_builder(&contract, builder),
+                Err(GenerateError::InvalidOption(_))
+            ));
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_contract_trait() {
+        let mut contract = Contract::with_name("Token");
+        contract.abi = Abi::load(
+            br#"[{
+                "type": "function",
+                "name": "totalSupply",
+                "inputs": [],
+                "outputs": [{ "name": "", "type": "uint256" }],
+                "stateMutability": "view"
+            }]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let function = contract.abi.function("totalSupply").unwrap();
+        let functions = [(
+            function,
+            util::ident("total_supply"),
+            expand_selector(function.selector()),
+            expand_inputs(&TypeSubstitutions::default().item(""), &function.inputs).unwrap(),
+            quote! {},
+            quote! { self::ethcontract::U256 },
+        )];
+
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand_trait(&cx, &functions), {});
+
+        let cx =
+            Context::from_builder(&contract, ContractBuilder::new().contract_trait(true)).unwrap();
+        assert_quote!(expand_trait(&cx, &functions), {
+            /// Trait mirroring the methods of the contract, implemented by the
+            /// generated contract type. Application code can be written against
+            /// this trait so that it can be unit tested with mock implementations.
+            #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+            pub trait IToken {
+                #[doc = "Calls the contract method `totalSupply():(uint256)`."]
+                fn total_supply(&self) -> self::ethcontract::futures::future::BoxFuture<
+                    '_,
+                    Result<self::ethcontract::U256, self::ethcontract::errors::MethodError>,
+                >;
+            }
+
+            #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+            impl IToken for Contract {
+                fn total_supply(&self) -> self::ethcontract::futures::future::BoxFuture<
+                    '_,
+                    Result<self::ethcontract::U256, self::ethcontract::errors::MethodError>,
+                > {
+                    self::ethcontract::futures::FutureExt::boxed(self.methods.total_supply().call())
+                }
+            }
+        });
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_contract_examples() {
+        let mut contract = Contract::with_name("Token");
+        contract.abi = Abi::load(
+            br#"[{
+                "type": "function",
+                "name": "totalSupply",
+                "inputs": [],
+                "outputs": [{ "name": "", "type": "uint256" }],
+                "stateMutability": "view"
+            }, {
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    { "name": "to", "type": "address" },
+                    { "name": "value", "type": "uint256" }
+                ],
+                "outputs": [{ "name": "", "type": "bool" }],
+                "stateMutability": "nonpayable"
+            }]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let functions = ["totalSupply", "transfer"]
+            .iter()
+            .map(|name| {
+                let function = contract.abi.function(name).unwrap();
+                (
+                    function,
+                    util::ident(&name.to_snake_case()),
+                    expand_selector(function.selector()),
+                    expand_inputs(&TypeSubstitutions::default().item(""), &function.inputs)
+                        .unwrap(),
+                    quote! {},
+                    expand_outputs(
+                        &TypeSubstitutions::default().item(""),
+                        &function.outputs,
+                        TokenAmounts::default(),
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand_examples(&cx, &functions), {});
+
+        let cx =
+            Context::from_builder(&contract, ContractBuilder::new().examples(true)).unwrap();
+        assert_quote!(expand_examples(&cx, &functions), {
+            #[doc = "Examples of using the contract bindings, with a function per contract method calling it with default arguments. The examples are compiled along with the bindings, so they are working starting points for using the contract:\n\n```ignore\nlet web3 = examples::connect(Http::new(\"http://localhost:8545\")?);\nlet instance = examples::instance(&web3, address);\nlet result = examples::call_total_supply(&instance).await?;\n```"]
+            pub mod examples {
+                use super::*;
+
+                /// Connects to a node with the specified transport, such as an
+                /// HTTP or WebSocket transport.
+                pub fn connect<F, B, T>(
+                    transport: T,
+                ) -> self::ethcontract::Web3<self::ethcontract::DynTransport>
+                where
+                    F: std::future::Future<
+                            Output = Result<
+                                self::ethcontract::json::Value,
+                                self::ethcontract::Web3Error,
+                            >,
+                        > + Send
+                        + 'static,
+                    B: std::future::Future<
+                            Output = Result<
+                                Vec<
+                                    Result<
+                                        self::ethcontract::json::Value,
+                                        self::ethcontract::Web3Error,
+                                    >,
+                                >,
+                                self::ethcontract::Web3Error,
+                            >,
+                        > + Send
+                        + 'static,
+                    T: self::ethcontract::Transport<Out = F>
+                        + self::ethcontract::BatchTransport<Batch = B>
+                        + Send
+                        + Sync
+                        + 'static,
+                {
+                    self::ethcontract::Web3::new(self::ethcontract::DynTransport::new(transport))
+                }
+
+                /// Creates an instance of the contract deployed at the
+                /// specified address.
+                pub fn instance(
+                    web3: &self::ethcontract::Web3<self::ethcontract::DynTransport>,
+                    address: self::ethcontract::Address,
+                ) -> super::Contract {
+                    super::Contract::at(web3, address)
+                }
+
+                #[doc = "Calls the contract method `totalSupply():(uint256)` with example arguments."]
+                #[allow(clippy::type_complexity)]
+                pub async fn call_total_supply(
+                    instance: &super::Contract,
+                ) -> Result<self::ethcontract::U256, self::ethcontract::errors::MethodError> {
+                    instance.methods().total_supply().call().await
+                }
+
+                #[doc = "Sends a transaction to the contract method `transfer(address,uint256):(bool)` with example arguments."]
+                #[allow(clippy::type_complexity)]
+                pub async fn send_transfer(
+                    instance: &super::Contract,
+                ) -> Result<
+                    self::ethcontract::transaction::TransactionResult,
+                    self::ethcontract::errors::MethodError
+                > {
+                    instance.methods().transfer(Default::default(), Default::default()).send().await
+                }
+            }
+        });
+    }
 }
@@ -0,0 +1,87 @@
+//! Detection of ERC721 and ERC1155 token contracts with metadata extensions
+//! and expansion of token metadata helpers.
+
+use crate::generate::Context;
+use ethcontract_common::abiext::FunctionExt;
+use ethcontract_common::Abi;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Functions for retrieving token metadata URIs, `tokenURI` for ERC721 and
+/// `uri` for ERC1155 tokens.
+const METADATA_FUNCTIONS: &[&str] = &["tokenURI(uint256)", "uri(uint256)"];
+
+/// Returns `true` if the ABI implements the ERC721 or ERC1155 metadata URI
+/// functions.
+pub(crate) fn has_token_metadata(abi: &Abi) -> bool {
+    abi.functions().any(|function| {
+        METADATA_FUNCTIONS.contains(&function.abi_signature().as_str())
+            && function.outputs.len() == 1
+    })
+}
+
+/// Expands the token metadata helper for NFT contracts.
+pub(crate) fn expand(cx: &Context) -> TokenStream {
+    if !has_token_metadata(&cx.contract.abi) {
+        return quote! {};
+    }
+
+    quote! {
+        impl Contract {
+            /// Retrieves and deserializes the metadata of a token, resolving
+            /// `data:` URIs directly and retrieving all other URIs with the
+            /// specified content fetcher.
+            pub async fn token_metadata(
+                &self,
+                token_id: self::ethcontract::U256,
+                fetcher: &dyn self::ethcontract::nft::ContentFetcher,
+            ) -> Result<
+                self::ethcontract::nft::TokenMetadata,
+                self::ethcontract::errors::MetadataError,
+            > {
+                self.raw_instance().token_metadata(token_id, fetcher).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+    use crate::ContractBuilder;
+    use ethcontract_common::Contract;
+
+    fn contract(name: &str) -> Contract {
+        let abi = format!(
+            r#"[{{
+                "type": "function",
+                "name": "{}",
+                "inputs": [{{ "name": "id", "type": "uint256" }}],
+                "outputs": [{{ "name": "", "type": "string" }}],
+                "stateMutability": "view"
+            }}]"#,
+            name,
+        );
+
+        test_contract("Token", &abi)
+    }
+
+    #[test]
+    fn detects_token_metadata() {
+        assert!(has_token_metadata(&contract("tokenURI").abi));
+        assert!(has_token_metadata(&contract("uri").abi));
+        assert!(!has_token_metadata(&contract("name").abi));
+    }
+
+    #[test]
+    fn expand_token_metadata_only_for_nfts() {
+        let contract = contract("tokenURI");
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert!(expand(&cx).to_string().contains("token_metadata"));
+
+        let contract = Contract::with_name("Empty");
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand(&cx), {});
+    }
+}
@@ -0,0 +1,351 @@
+//! Expansion of typed storage slot accessors for the state variables of
+//! contracts with a storage layout.
+
+use crate::errors::GenerateError;
+use crate::generate::{types, Context};
+use crate::util;
+use ethcontract_common::abi::param_type::Reader;
+use ethcontract_common::abi::{ParamType, Uint as U256};
+use ethcontract_common::storage::{StorageLayout, StorageType, StorageVariable};
+use inflector::Inflector;
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+/// Expands the `storage` module with an accessor per state variable. State
+/// variables of types that are not stored in place, such as strings and
+/// structs, have no accessor.
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream, GenerateError> {
+    let layout = match &cx.contract.storage_layout {
+        Some(layout) if !layout.storage.is_empty() => layout,
+        _ => return Ok(quote! {}),
+    };
+
+    let mut accessors = Vec::new();
+    for variable in &layout.storage {
+        expand_variable(layout, variable, &mut accessors)?;
+    }
+
+    Ok(quote! {
+        /// Module containing typed accessors for the storage slots of the
+        /// contract's state variables, for reading them without getters and
+        /// for constructing state overrides.
+        pub mod storage {
+            use super::ethcontract;
+
+            #( #accessors )*
+        }
+    })
+}
+
+/// The parameters and slot computations of an accessor, accumulated while
+/// walking the type of a state variable.
+#[derive(Clone)]
+struct Accessor<'a> {
+    name: String,
+    label: &'a str,
+    params: Vec<TokenStream>,
+    steps: Vec<TokenStream>,
+    offset: Option<usize>,
+    has_length: bool,
+}
+
+fn expand_variable(
+    layout: &StorageLayout,
+    variable: &StorageVariable,
+    accessors: &mut Vec<TokenStream>,
+) -> Result<(), GenerateError> {
+    let slot = expand_uint(variable.slot);
+    let accessor = Accessor {
+        name: variable.label.to_snake_case(),
+        label: &variable.label,
+        params: Vec::new(),
+        steps: vec![quote! { let slot = #slot; }],
+        offset: Some(variable.offset),
+        has_length: false,
+    };
+    expand_type(layout, &variable.type_id, accessor, accessors)
+}
+
+fn expand_type(
+    layout: &StorageLayout,
+    type_id: &str,
+    mut accessor: Accessor,
+    accessors: &mut Vec<TokenStream>,
+) -> Result<(), GenerateError> {
+    let ty = match layout.types.get(type_id) {
+        Some(ty) => ty,
+        None => return Ok(()),
+    };
+
+    match (ty.encoding.as_str(), &ty.base) {
+        ("mapping", _) => {
+            let key = match ty.key.as_ref().and_then(|key| layout.types.get(key)) {
+                Some(key) => key,
+                None => return Ok(()),
+            };
+            let key_type = match key_type(&key.label)? {
+                Some(key_type) => key_type,
+                None => return Ok(()),
+            };
+            let value = match &ty.value {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+
+            let param = util::ident(&format!("key{}", accessor.params.len()));
+            accessor.params.push(quote! { #param: #key_type });
+            accessor.steps.push(quote! {
+                let slot = self::ethcontract::storage::mapping_slot(slot, #param);
+            });
+            accessor.offset = Some(0);
+            expand_type(layout, value, accessor, accessors)
+        }
+        ("dynamic_array", Some(base)) => {
+            if !accessor.has_length {
+                accessors.push(expand_length(&accessor));
+                accessor.has_length = true;
+            }
+            accessor.steps.push(quote! {
+                let slot = self::ethcontract::storage::array_slot(slot);
+            });
+            expand_element(layout, base, accessor, accessors)
+        }
+        ("inplace", Some(base)) => expand_element(layout, base, accessor, accessors),
+        ("inplace", None) if ty.members.is_empty() => {
+            if let Some(value_type) = value_type(&ty.label)? {
+                accessors.push(expand_accessor(&accessor, ty, value_type));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn expand_element(
+    layout: &StorageLayout,
+    base: &str,
+    mut accessor: Accessor,
+    accessors: &mut Vec<TokenStream>,
+) -> Result<(), GenerateError> {
+    let base_type = match layout.types.get(base) {
+        Some(base_type) => base_type,
+        None => return Ok(()),
+    };
+    if base_type.number_of_bytes > U256::from(u32::MAX) {
+        return Ok(());
+    }
+    let size = Literal::usize_unsuffixed(base_type.number_of_bytes.as_usize());
+
+    let param = util::ident(&format!("index{}", accessor.params.len()));
+    accessor
+        .params
+        .push(quote! { #param: self::ethcontract::U256 });
+    if is_value_type(base_type) {
+        accessor.steps.push(quote! {
+            let (slot, offset) = self::ethcontract::storage::element_slot(slot, #param, #size);
+        });
+        accessor.offset = None;
+    } else {
+        accessor.steps.push(quote! {
+            let (slot, _) = self::ethcontract::storage::element_slot(slot, #param, #size);
+        });
+        accessor.offset = Some(0);
+    }
+    expand_type(layout, base, accessor, accessors)
+}
+
+fn expand_accessor(accessor: &Accessor, ty: &StorageType, value_type: TokenStream) -> TokenStream {
+    let name = util::safe_ident(&accessor.name);
+    let doc = util::expand_doc(&format!(
+        "Returns the storage slot of the `{}` state variable{}.",
+        accessor.label,
+        if accessor.params.is_empty() {
+            ""
+        } else {
+            " for the specified keys and indices"
+        },
+    ));
+    let params = &accessor.params;
+    let steps = &accessor.steps;
+    let offset = match accessor.offset {
+        Some(offset) => {
+            let offset = Literal::usize_unsuffixed(offset);
+            quote! { #offset }
+        }
+        None => quote! { offset },
+    };
+    let size = Literal::usize_unsuffixed(ty.number_of_bytes.as_usize());
+    let label = Literal::string(&ty.label);
+
+    quote! {
+        #doc
+        pub fn #name(#( #params, )*) -> self::ethcontract::storage::StorageSlot<#value_type> {
+            #( #steps )*
+            self::ethcontract::storage::StorageSlot::new(slot, #offset, #size, #label)
+        }
+    }
+}
+
+fn expand_length(accessor: &Accessor) -> TokenStream {
+    let name = util::safe_ident(&format!("{}_len", accessor.name));
+    let doc = util::expand_doc(&format!(
+        "Returns the storage slot of the length of the `{}` array.",
+        accessor.label,
+    ));
+    let params = &accessor.params;
+    let steps = &accessor.steps;
+
+    quote! {
+        #doc
+        pub fn #name(#( #params, )*) -> self::ethcontract::storage::StorageSlot<self::ethcontract::U256> {
+            #( #steps )*
+            self::ethcontract::storage::StorageSlot::new(slot, 0, 32, "uint256")
+        }
+    }
+}
+
+/// Returns `true` if the type is a value type stored in place.
+fn is_value_type(ty: &StorageType) -> bool {
+    ty.encoding == "inplace" && ty.base.is_none() && ty.members.is_empty()
+}
+
+/// Expands the Rust type of a value type with the specified canonical name.
+fn value_type(label: &str) -> Result<Option<TokenStream>, GenerateError> {
+    if label == "address payable" || label.starts_with("contract ") {
+        return Ok(Some(quote! { self::ethcontract::Address }));
+    }
+    if label.starts_with("enum ") {
+        return Ok(Some(quote! { u8 }));
+    }
+    match Reader::read(label) {
+        Ok(kind)
+            if matches!(
+                kind,
+                ParamType::Address
+                    | ParamType::Bool
+                    | ParamType::Int(_)
+                    | ParamType::Uint(_)
+                    | ParamType::FixedBytes(_)
+            ) =>
+        {
+            types::expand(&kind).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Expands the Rust type of a mapping key with the specified canonical name.
+fn key_type(label: &str) -> Result<Option<TokenStream>, GenerateError> {
+    match label {
+        "string" => Ok(Some(quote! { String })),
+        "bytes" => Ok(Some(quote! { self::ethcontract::tokens::Bytes<Vec<u8>> })),
+        _ => value_type(label),
+    }
+}
+
+fn expand_uint(value: U256) -> TokenStream {
+    if value <= U256::from(u64::MAX) {
+        let value = Literal::u64_suffixed(value.as_u64());
+        quote! { self::ethcontract::U256::from(#value) }
+    } else {
+        let limbs = value.0.iter().map(|limb| Literal::u64_suffixed(*limb));
+        quote! { self::ethcontract::U256([#( #limbs ),*]) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContractBuilder;
+    use ethcontract_common::Contract;
+
+    const LAYOUT: &str = r#"{
+        "storage": [
+            { "label": "owner", "slot": "0", "offset": 0, "type": "t_address" },
+            { "label": "paused", "slot": "0", "offset": 20, "type": "t_bool" },
+            { "label": "balances", "slot": "1", "offset": 0, "type": "t_mapping(t_address,t_uint256)" },
+            { "label": "checkpoints", "slot": "2", "offset": 0, "type": "t_array(t_uint64)dyn_storage" },
+            { "label": "name", "slot": "3", "offset": 0, "type": "t_string_storage" }
+        ],
+        "types": {
+            "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+            "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+            "t_uint64": { "encoding": "inplace", "label": "uint64", "numberOfBytes": "8" },
+            "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" },
+            "t_string_storage": { "encoding": "bytes", "label": "string", "numberOfBytes": "32" },
+            "t_array(t_uint64)dyn_storage": {
+                "encoding": "dynamic_array",
+                "label": "uint64[]",
+                "numberOfBytes": "32",
+                "base": "t_uint64"
+            },
+            "t_mapping(t_address,t_uint256)": {
+                "encoding": "mapping",
+                "key": "t_address",
+                "label": "mapping(address => uint256)",
+                "numberOfBytes": "32",
+                "value": "t_uint256"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn expand_storage_accessors() {
+        let mut contract = Contract::with_name("Token");
+        contract.storage_layout = Some(StorageLayout::from_json(LAYOUT).unwrap());
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+
+        assert_quote!(expand(&cx).unwrap(), {
+            /// Module containing typed accessors for the storage slots of the
+            /// contract's state variables, for reading them without getters and
+            /// for constructing state overrides.
+            pub mod storage {
+                use super::ethcontract;
+
+                #[doc = "Returns the storage slot of the `owner` state variable."]
+                pub fn owner() -> self::ethcontract::storage::StorageSlot<self::ethcontract::Address>
+                {
+                    let slot = self::ethcontract::U256::from(0u64);
+                    self::ethcontract::storage::StorageSlot::new(slot, 0, 20, "address")
+                }
+                #[doc = "Returns the storage slot of the `paused` state variable."]
+                pub fn paused() -> self::ethcontract::storage::StorageSlot<bool> {
+                    let slot = self::ethcontract::U256::from(0u64);
+                    self::ethcontract::storage::StorageSlot::new(slot, 20, 1, "bool")
+                }
+                #[doc = "Returns the storage slot of the `balances` state variable for the specified keys and indices."]
+                pub fn balances(
+                    key0: self::ethcontract::Address,
+                ) -> self::ethcontract::storage::StorageSlot<self::ethcontract::U256>
+                {
+                    let slot = self::ethcontract::U256::from(1u64);
+                    let slot = self::ethcontract::storage::mapping_slot(slot, key0);
+                    self::ethcontract::storage::StorageSlot::new(slot, 0, 32, "uint256")
+                }
+                #[doc = "Returns the storage slot of the length of the `checkpoints` array."]
+                pub fn checkpoints_len(
+                ) -> self::ethcontract::storage::StorageSlot<self::ethcontract::U256>
+                {
+                    let slot = self::ethcontract::U256::from(2u64);
+                    self::ethcontract::storage::StorageSlot::new(slot, 0, 32, "uint256")
+                }
+                #[doc = "Returns the storage slot of the `checkpoints` state variable for the specified keys and indices."]
+                pub fn checkpoints(
+                    index0: self::ethcontract::U256,
+                ) -> self::ethcontract::storage::StorageSlot<u64> {
+                    let slot = self::ethcontract::U256::from(2u64);
+                    let slot = self::ethcontract::storage::array_slot(slot);
+                    let (slot, offset) = self::ethcontract::storage::element_slot(slot, index0, 8);
+                    self::ethcontract::storage::StorageSlot::new(slot, offset, 8, "uint64")
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn expand_without_storage_layout() {
+        let contract = Contract::with_name("Token");
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand(&cx).unwrap(), {});
+    }
+}
@@ -1,9 +1,74 @@
-use anyhow::{anyhow, Result};
+use crate::errors::GenerateError;
 use ethcontract_common::abi::ParamType;
 use proc_macro2::{Literal, TokenStream};
 use quote::quote;
+use std::collections::HashMap;
+
+/// Rust types substituted for Solidity types in the generated bindings.
+#[derive(Default)]
+pub(crate) struct TypeSubstitutions {
+    /// Substituted types by canonical Solidity type.
+    pub types: HashMap<String, TokenStream>,
+
+    /// Substituted types by method, constructor or event signature and
+    /// parameter name.
+    pub params: HashMap<(String, String), TokenStream>,
+}
+
+impl TypeSubstitutions {
+    /// Returns the substitutions for the parameters of the method, constructor
+    /// or event with the specified signature.
+    pub fn item(&self, signature: impl Into<String>) -> ParamTypes<'_> {
+        ParamTypes {
+            substitutions: self,
+            signature: signature.into(),
+        }
+    }
+
+    /// Expands a Solidity type, substituting Rust types for the Solidity type
+    /// and the types nested in it.
+    pub fn expand(&self, kind: &ParamType) -> Result<TokenStream, GenerateError> {
+        expand_with(kind, &|kind| self.types.get(&kind.to_string()).cloned())
+    }
+}
+
+/// The substitutions for the parameters of a method, constructor or event.
+pub(crate) struct ParamTypes<'a> {
+    substitutions: &'a TypeSubstitutions,
+    signature: String,
+}
+
+impl ParamTypes<'_> {
+    /// Returns the type substituted for a parameter as a whole, if any.
+    pub fn substitute(&self, name: &str, kind: &ParamType) -> Option<TokenStream> {
+        self.substitutions
+            .params
+            .get(&(self.signature.clone(), name.to_owned()))
+            .or_else(|| self.substitutions.types.get(&kind.to_string()))
+            .cloned()
+    }
+
+    /// Expands the type of a parameter.
+    pub fn expand(&self, name: &str, kind: &ParamType) -> Result<TokenStream, GenerateError> {
+        match self.substitute(name, kind) {
+            Some(ty) => Ok(ty),
+            None => self.substitutions.expand(kind),
+        }
+    }
+}
+
+pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream, GenerateError> {
+    expand_with(kind, &|_| None)
+}
+
+fn expand_with(
+    kind: &ParamType,
+    substitute: &dyn Fn(&ParamType) -> Option<TokenStream>,
+) -> Result<TokenStream, GenerateError> {
+    if let Some(ty) = substitute(kind) {
+        return Ok(ty);
+    }
 
-pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
     match kind {
         ParamType::Address => Ok(quote! { self::ethcontract::Address }),
         ParamType::Bytes => Ok(quote! { self::ethcontract::tokens::Bytes<Vec<u8>> }),
@@ -14,7 +79,10 @@ pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
             5..=8 => Ok(quote! { i64 }),
             9..=16 => Ok(quote! { i128 }),
             17..=32 => Ok(quote! { self::ethcontract::I256 }),
-            _ => Err(anyhow!("unsupported solidity type int{}", n)),
+            _ => Err(GenerateError::UnsupportedAbi(format!(
+                "solidity type int{}",
+                n
+            ))),
         },
         ParamType::Uint(n) => match n / 8 {
             1 => Ok(quote! { u8 }),
@@ -23,12 +91,15 @@ pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
             5..=8 => Ok(quote! { u64 }),
             9..=16 => Ok(quote! { u128 }),
             17..=32 => Ok(quote! { self::ethcontract::U256 }),
-            _ => Err(anyhow!("unsupported solidity type uint{}", n)),
+            _ => Err(GenerateError::UnsupportedAbi(format!(
+                "solidity type uint{}",
+                n
+            ))),
         },
         ParamType::Bool => Ok(quote! { bool }),
         ParamType::String => Ok(quote! { String }),
         ParamType::Array(t) => {
-            let inner = expand(t)?;
+            let inner = expand_with(t, substitute)?;
             Ok(quote! { Vec<#inner> })
         }
         ParamType::FixedBytes(n) => {
@@ -39,13 +110,37 @@ pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
         }
         ParamType::FixedArray(t, n) => {
             // TODO(nlordell): see above
-            let inner = expand(t)?;
+            let inner = expand_with(t, substitute)?;
             let size = Literal::usize_unsuffixed(*n);
             Ok(quote! { [#inner; #size] })
         }
         ParamType::Tuple(t) => {
-            let inner = t.iter().map(expand).collect::<Result<Vec<_>>>()?;
+            let inner = t
+                .iter()
+                .map(|t| expand_with(t, substitute))
+                .collect::<Result<Vec<_>, GenerateError>>()?;
             Ok(quote! { (#(#inner,)*) })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_fixed_arrays() {
+        assert_quote!(
+            expand(&ParamType::FixedArray(Box::new(ParamType::Uint(256)), 3)).unwrap(),
+            { [self::ethcontract::U256; 3] },
+        );
+        assert_quote!(
+            expand(&ParamType::Array(Box::new(ParamType::FixedArray(
+                Box::new(ParamType::Address),
+                2,
+            ))))
+            .unwrap(),
+            { Vec<[self::ethcontract::Address; 2]> },
+        );
+    }
+}
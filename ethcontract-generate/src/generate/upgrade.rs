@@ -0,0 +1,219 @@
+//! Detection of UUPS upgradeable implementations and upgradeable beacons and
+//! expansion of upgrade helpers.
+
+use crate::generate::Context;
+use ethcontract_common::abiext::FunctionExt;
+use ethcontract_common::Abi;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The signature of the UUPS and beacon `upgradeTo` function.
+const UPGRADE_TO: &str = "upgradeTo(address)";
+
+/// The signature of the UUPS `upgradeToAndCall` function.
+const UPGRADE_TO_AND_CALL: &str = "upgradeToAndCall(address,bytes)";
+
+/// The signature of the ERC1822 `proxiableUUID` function.
+const PROXIABLE_UUID: &str = "proxiableUUID()";
+
+/// The signature of the beacon `implementation` function.
+const IMPLEMENTATION: &str = "implementation()";
+
+/// Returns `true` if the ABI has a function with the specified signature.
+fn has_function(abi: &Abi, signature: &str) -> bool {
+    abi.functions()
+        .any(|function| function.abi_signature() == signature)
+}
+
+/// Returns `true` if the ABI is a UUPS upgradeable implementation, which is
+/// used through an EIP-1967 proxy.
+pub(crate) fn is_uups(abi: &Abi) -> bool {
+    has_function(abi, PROXIABLE_UUID)
+        && (has_function(abi, UPGRADE_TO) || has_function(abi, UPGRADE_TO_AND_CALL))
+}
+
+/// Returns `true` if the ABI is an upgradeable beacon.
+pub(crate) fn is_beacon(abi: &Abi) -> bool {
+    !has_function(abi, PROXIABLE_UUID)
+        && has_function(abi, UPGRADE_TO)
+        && abi.functions().any(|function| {
+            function.abi_signature() == IMPLEMENTATION && function.outputs.len() == 1
+        })
+}
+
+/// Expands the upgrade helpers for UUPS implementations and beacons.
+pub(crate) fn expand(cx: &Context) -> TokenStream {
+    let abi = &cx.contract.abi;
+    if is_uups(abi) {
+        expand_uups(abi)
+    } else if is_beacon(abi) {
+        expand_beacon()
+    } else {
+        quote! {}
+    }
+}
+
+fn expand_uups(abi: &Abi) -> TokenStream {
+    let upgrade_to = if has_function(abi, UPGRADE_TO) {
+        quote! {
+            /// Upgrades the proxy to a new implementation with `upgradeTo`,
+            /// after checking that the new implementation is a UUPS contract
+            /// with the expected code. The implementation slot of the proxy is
+            /// verified after the upgrade.
+            pub async fn upgrade_proxy(
+                &self,
+                implementation: self::ethcontract::Address,
+                options: self::ethcontract::contract::UpgradeOptions,
+            ) -> Result<
+                self::ethcontract::transaction::TransactionResult,
+                self::ethcontract::errors::UpgradeError,
+            > {
+                self.raw_instance().upgrade_proxy(implementation, options).await
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let upgrade_to_and_call = if has_function(abi, UPGRADE_TO_AND_CALL) {
+        quote! {
+            /// Upgrades the proxy to a new implementation with
+            /// `upgradeToAndCall`, calling the new implementation with `data`
+            /// in the context of the proxy. The same checks as for
+            /// `upgrade_proxy` are performed.
+            pub async fn upgrade_proxy_and_call(
+                &self,
+                implementation: self::ethcontract::Address,
+                data: Vec<u8>,
+                options: self::ethcontract::contract::UpgradeOptions,
+            ) -> Result<
+                self::ethcontract::transaction::TransactionResult,
+                self::ethcontract::errors::UpgradeError,
+            > {
+                self.raw_instance()
+                    .upgrade_proxy_and_call(implementation, data, options)
+                    .await
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        impl Contract {
+            /// Returns the current implementation of the proxy read from its
+            /// EIP-1967 storage slots, or `None` if the contract is not behind
+            /// an EIP-1967 proxy.
+            pub async fn proxy_implementation(
+                &self,
+            ) -> Result<
+                Option<self::ethcontract::Address>,
+                self::ethcontract::errors::ExecutionError,
+            > {
+                self.raw_instance().proxy_implementation().await
+            }
+
+            #upgrade_to
+            #upgrade_to_and_call
+        }
+    }
+}
+
+fn expand_beacon() -> TokenStream {
+    quote! {
+        impl Contract {
+            /// Returns the current implementation of the beacon.
+            pub async fn beacon_implementation(
+                &self,
+            ) -> Result<
+                self::ethcontract::Address,
+                self::ethcontract::errors::ExecutionError,
+            > {
+                self.raw_instance().beacon_implementation().await
+            }
+
+            /// Upgrades the beacon to a new implementation with `upgradeTo`,
+            /// after checking that the new implementation is a contract with
+            /// the expected code. The implementation of the beacon is verified
+            /// after the upgrade.
+            pub async fn upgrade_beacon(
+                &self,
+                implementation: self::ethcontract::Address,
+                options: self::ethcontract::contract::UpgradeOptions,
+            ) -> Result<
+                self::ethcontract::transaction::TransactionResult,
+                self::ethcontract::errors::UpgradeError,
+            > {
+                self.raw_instance().upgrade_beacon(implementation, options).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+    use crate::ContractBuilder;
+    use ethcontract_common::Contract;
+
+    fn contract(functions: &[(&str, &str, &str)]) -> Contract {
+        let functions = functions
+            .iter()
+            .map(|(name, inputs, outputs)| {
+                format!(
+                    r#"{{
+                        "type": "function",
+                        "name": "{}",
+                        "inputs": [{}],
+                        "outputs": [{}],
+                        "stateMutability": "nonpayable"
+                    }}"#,
+                    name, inputs, outputs,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        test_contract("Upgradeable", &format!("[{}]", functions.join(",")))
+    }
+
+    const ADDRESS: &str = r#"{ "name": "", "type": "address" }"#;
+    const BYTES: &str = r#"{ "name": "", "type": "bytes" }"#;
+    const BYTES32: &str = r#"{ "name": "", "type": "bytes32" }"#;
+
+    #[test]
+    fn detects_upgradeable_contracts() {
+        let upgrade_to_and_call = format!("{},{}", ADDRESS, BYTES);
+        let uups = contract(&[
+            ("upgradeToAndCall", &upgrade_to_and_call, ""),
+            ("proxiableUUID", "", BYTES32),
+        ]);
+        assert!(is_uups(&uups.abi));
+        assert!(!is_beacon(&uups.abi));
+
+        let beacon = contract(&[("upgradeTo", ADDRESS, ""), ("implementation", "", ADDRESS)]);
+        assert!(!is_uups(&beacon.abi));
+        assert!(is_beacon(&beacon.abi));
+
+        let transparent = contract(&[("upgradeTo", ADDRESS, "")]);
+        assert!(!is_uups(&transparent.abi));
+        assert!(!is_beacon(&transparent.abi));
+    }
+
+    #[test]
+    fn expand_upgrade_helpers_only_for_upgradeable_contracts() {
+        let uups = contract(&[("upgradeTo", ADDRESS, ""), ("proxiableUUID", "", BYTES32)]);
+        let cx = Context::from_builder(&uups, ContractBuilder::new()).unwrap();
+        let tokens = expand(&cx).to_string();
+        assert!(tokens.contains("upgrade_proxy"));
+        assert!(!tokens.contains("upgrade_proxy_and_call"));
+        assert!(!tokens.contains("upgrade_beacon"));
+
+        let beacon = contract(&[("upgradeTo", ADDRESS, ""), ("implementation", "", ADDRESS)]);
+        let cx = Context::from_builder(&beacon, ContractBuilder::new()).unwrap();
+        assert!(expand(&cx).to_string().contains("upgrade_beacon"));
+
+        let contract = Contract::with_name("Empty");
+        let cx = Context::from_builder(&contract, ContractBuilder::new()).unwrap();
+        assert_quote!(expand(&cx), {});
+    }
+}
@@ -0,0 +1,317 @@
+//! Validation of contract ABIs before expanding them into bindings.
+//!
+//! Code generation assumes that names in the ABI are valid identifiers and that
+//! all parameter types can be mapped to Rust types. Checking this upfront
+//! allows malformed or unsupported ABIs to be reported with the offending
+//! function, event or parameter instead of failing (or panicking) deep within
+//! code generation. All problems are collected so that they can be fixed at
+//! once instead of one rebuild at a time.
+
+use crate::errors::GenerateError;
+use crate::generate::types;
+use crate::ContractBuilder;
+use ethcontract_common::abi::ParamType;
+use ethcontract_common::abiext::{EventExt, FunctionExt};
+use ethcontract_common::Contract;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Error returned when generating bindings for a contract whose ABI cannot be
+/// expanded. It lists every problem found in the contract, not just the first.
+///
+/// This error is returned by
+/// [`ContractBuilder::generate`](crate::ContractBuilder::generate) as
+/// [`GenerateError::Validation`].
+#[derive(Clone, Debug)]
+pub struct ValidationError {
+    /// The origin of the artifact the contract was loaded from, if it was
+    /// specified with
+    /// [`ContractBuilder::artifact_origin`](crate::ContractBuilder::artifact_origin).
+    pub artifact: Option<String>,
+
+    /// The name of the contract.
+    pub contract: String,
+
+    /// The problems that were found.
+    pub problems: Vec<Problem>,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "found {} problem", self.problems.len())?;
+        if self.problems.len() != 1 {
+            f.write_str("s")?;
+        }
+        write!(f, " in contract '{}'", self.contract)?;
+        if let Some(artifact) = &self.artifact {
+            write!(f, " from artifact '{}'", artifact)?;
+        }
+        f.write_str(":")?;
+        for problem in &self.problems {
+            write!(f, "\n  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ValidationError {}
+
+/// A single problem found in a contract.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Problem {
+    /// The path to the offending value in the contract artifact.
+    ///
+    /// Paths look like JSON pointers, but ABI entries are identified by their
+    /// signature instead of their index in the ABI array, for example
+    /// `/abi/transfer(address,uint256)/inputs/1/type`, since the original
+    /// order of the ABI entries is not preserved when loading an artifact.
+    pub path: String,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Validates that the contract ABI can be expanded into bindings.
+pub(crate) fn validate(
+    contract: &Contract,
+    builder: &ContractBuilder,
+) -> Result<(), GenerateError> {
+    let name = builder
+        .contract_name_override
+        .as_deref()
+        .unwrap_or(&contract.name);
+
+    let mut validator = Validator::default();
+    if !name.is_empty() {
+        validator.check("/contractName".to_owned(), || {
+            check_name(name).map_err(context("invalid contract name"))
+        });
+    }
+
+    let abi = &contract.abi;
+    if let Some(constructor) = abi.constructor() {
+        for (i, param) in constructor.inputs.iter().enumerate() {
+            validator.check_param("constructor", "constructor", i, &param.name, &param.kind);
+        }
+    }
+
+    for function in abi.functions() {
+        let signature = function.abi_signature();
+        let item = format!("function '{}'", signature);
+        validator.check(path(&[&signature, "name"]), || {
+            check_name(&function.name).map_err(context(&item))
+        });
+        for (i, param) in function.inputs.iter().enumerate() {
+            validator.check_param(&signature, &item, i, &param.name, &param.kind);
+        }
+        for (i, param) in function.outputs.iter().enumerate() {
+            validator.check(
+                path(&[&signature, "outputs", &i.to_string(), "type"]),
+                || {
+                    check_type(&param.kind)
+                        .map_err(context(format!("output #{}", i)))
+                        .map_err(context(&item))
+                },
+            );
+        }
+    }
+
+    for event in abi.events() {
+        let signature = event.abi_signature();
+        let item = format!("event '{}'", signature);
+        validator.check(path(&[&signature, "name"]), || {
+            check_name(&event.name).map_err(context(&item))
+        });
+        for (i, param) in event.inputs.iter().enumerate() {
+            validator.check_param(&signature, &item, i, &param.name, &param.kind);
+        }
+    }
+
+    if validator.problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError {
+            artifact: builder.artifact_origin.clone(),
+            contract: name.to_owned(),
+            problems: validator.problems,
+        }
+        .into())
+    }
+}
+
+/// Checks that a name from the contract ABI can be used as a Rust identifier.
+fn check_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid identifier", name))
+    }
+}
+
+fn check_type(kind: &ParamType) -> Result<(), String> {
+    types::expand(kind).map(drop).map_err(|err| err.to_string())
+}
+
+/// Returns a function for adding context to a problem message.
+fn context(context: impl Display) -> impl FnOnce(String) -> String {
+    move |message| format!("{}: {}", context, message)
+}
+
+/// Helper for accumulating problems.
+#[derive(Default)]
+struct Validator {
+    problems: Vec<Problem>,
+}
+
+impl Validator {
+    fn check(&mut self, path: String, check: impl FnOnce() -> Result<(), String>) {
+        if let Err(message) = check() {
+            self.problems.push(Problem { path, message });
+        }
+    }
+
+    fn check_param(&mut self, root: &str, item: &str, index: usize, name: &str, kind: &ParamType) {
+        let describe = || match name {
+            "" => format!("parameter #{} of {}", index, item),
+            name => format!("parameter '{}' of {}", name, item),
+        };
+
+        let index = index.to_string();
+        // NOTE: Unnamed parameters get a generated name.
+        if !name.is_empty() {
+            self.check(path(&[root, "inputs", &index, "name"]), || {
+                check_name(name).map_err(context(describe()))
+            });
+        }
+        self.check(path(&[root, "inputs", &index, "type"]), || {
+            check_type(kind).map_err(context(describe()))
+        });
+    }
+}
+
+/// Creates a path to a value within the contract ABI.
+fn path(tokens: &[&str]) -> String {
+    let mut path = "/abi".to_owned();
+    for token in tokens {
+        path.push('/');
+        path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::test_contract;
+
+    fn contract(abi: &str) -> Contract {
+        test_contract("Contract", abi)
+    }
+
+    fn problems(contract: &Contract, builder: &ContractBuilder) -> ValidationError {
+        match validate(contract, builder) {
+            Err(GenerateError::Validation(err)) => err,
+            result => panic!("unexpected validation result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn valid_names() {
+        for name in &["foo", "_foo", "FooBar", "foo_1", "self"] {
+            assert!(check_name(name).is_ok(), "{}", name);
+        }
+        for name in &["", "1foo", "foo-bar", "$foo", "foo bar"] {
+            assert!(check_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn validate_valid_contract() {
+        let contract = contract(
+            r#"[{
+                "type": "function",
+                "name": "foo",
+                "inputs": [{ "name": "", "type": "uint256" }],
+                "outputs": [{ "name": "", "type": "bool" }],
+                "stateMutability": "view"
+            }]"#,
+        );
+
+        assert!(validate(&contract, &ContractBuilder::new()).is_ok());
+    }
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let contract = contract(
+            r#"[
+                {
+                    "type": "function",
+                    "name": "foo",
+                    "inputs": [
+                        { "name": "a", "type": "bool" },
+                        { "name": "b", "type": "uint264" }
+                    ],
+                    "outputs": [],
+                    "stateMutability": "view"
+                },
+                {
+                    "type": "event",
+                    "name": "Foo$",
+                    "inputs": [],
+                    "anonymous": false
+                }
+            ]"#,
+        );
+
+        let err = problems(&contract, &ContractBuilder::new().artifact_origin("a.json"));
+        assert_eq!(err.artifact.as_deref(), Some("a.json"));
+        assert_eq!(err.contract, "Contract");
+
+        let mut problems = err.problems;
+        problems.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            problems,
+            vec![
+                Problem {
+                    path: "/abi/Foo$()/name".to_owned(),
+                    message: "event 'Foo$()': 'Foo$' is not a valid identifier".to_owned(),
+                },
+                Problem {
+                    path: "/abi/foo(bool,uint264)/inputs/1/type".to_owned(),
+                    message: "parameter 'b' of function 'foo(bool,uint264)': \
+                              unsupported solidity type uint264"
+                        .to_owned(),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn validate_contract_name() {
+        let contract = Contract::with_name("My-Contract");
+
+        let err = problems(&contract, &ContractBuilder::new());
+        assert_eq!(
+            err.to_string(),
+            "found 1 problem in contract 'My-Contract':\n  \
+             /contractName: invalid contract name: 'My-Contract' is not a valid identifier",
+        );
+        assert!(validate(
+            &contract,
+            &ContractBuilder::new().contract_name_override("Ok")
+        )
+        .is_ok());
+    }
+}
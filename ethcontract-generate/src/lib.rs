@@ -10,12 +10,23 @@
 #[path = "test/macros.rs"]
 mod test_macros;
 
+pub mod diff;
+pub mod errors;
+pub mod report;
+pub mod rerun;
+pub mod snapshot;
 pub mod source;
 
+mod dir;
 mod generate;
 mod rustfmt;
 mod util;
+mod verify;
 
+pub use crate::diff::{diff, AbiDiff};
+pub use crate::errors::GenerateError;
+pub use crate::generate::{Problem, ValidationError, MAX_TOKEN_DECIMALS};
+pub use crate::report::{BindingsReport, ContractReport};
 pub use crate::source::Source;
 pub use crate::util::parse_address;
 
@@ -30,17 +41,18 @@ pub mod loaders {
     pub use ethcontract_common::artifact::truffle::TruffleLoader;
 }
 
-use anyhow::Result;
+use ethcontract_common::abi::{Address, Uint};
 use ethcontract_common::contract::Network;
 use ethcontract_common::Contract;
 use proc_macro2::TokenStream;
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
 /// Builder for generating contract code. Note that no code is generated until
 /// the builder is finalized with `generate` or `output`.
+#[derive(Clone)]
 pub struct ContractBuilder {
     /// The runtime crate name to use.
     pub runtime_crate_name: String,
@@ -61,9 +73,57 @@ pub struct ContractBuilder {
     /// Manually specified contract method aliases.
     pub method_aliases: HashMap<String, String>,
 
+    /// Default options for specific methods, by method name or signature.
+    pub method_defaults: HashMap<String, MethodDefaults>,
+
+    /// Custom calldata encoders and return data decoders for specific
+    /// methods, by method name or signature.
+    pub method_codecs: HashMap<String, MethodCodec>,
+
+    /// Rust types substituted for Solidity types in the generated bindings,
+    /// by Solidity type or by method or event parameter.
+    pub type_substitutions: HashMap<String, String>,
+
+    /// Contracts whose ABIs are merged into the ABI of the generated bindings,
+    /// for example interfaces compiled into separate artifacts.
+    pub merged_contracts: Vec<Contract>,
+
+    /// Glob patterns of the methods to generate bindings for, matched against
+    /// method names and signatures. Bindings are generated for all methods
+    /// when empty.
+    pub method_filter: Vec<String>,
+
+    /// Glob patterns of the events to generate bindings for, matched against
+    /// event names and signatures. Bindings are generated for all events when
+    /// empty.
+    pub event_filter: Vec<String>,
+
     /// Derives added to event structs and enums.
     pub event_derives: Vec<String>,
 
+    /// Serde representations of event structs and enums. When set, `serde`
+    /// serialization is derived for them.
+    pub serde: Option<SerdeOptions>,
+
+    /// The number of decimals of the token when generating bindings for an
+    /// ERC20 token. When set, token amounts of the standard ERC20 methods use
+    /// `TokenAmount` instead of raw `U256` values.
+    pub token_decimals: Option<u32>,
+
+    /// Generate a trait mirroring the contract methods, implemented by the
+    /// generated contract type.
+    pub contract_trait: bool,
+
+    /// Implement `ethcontract::arbitrary::Arbitrary` for event data types.
+    pub arbitrary_impls: bool,
+
+    /// Generate a module of examples calling each contract method.
+    pub examples: bool,
+
+    /// The origin of the artifact the contract was loaded from, such as its
+    /// path or URL. This is only used for error reporting.
+    pub artifact_origin: Option<String>,
+
     /// Format generated code sing locally installed copy of `rustfmt`.
     pub rustfmt: bool,
 }
@@ -78,7 +138,19 @@ impl ContractBuilder {
             contract_name_override: None,
             networks: Default::default(),
             method_aliases: Default::default(),
+            method_defaults: Default::default(),
+            method_codecs: Default::default(),
+            type_substitutions: Default::default(),
+            merged_contracts: vec![],
+            method_filter: vec![],
+            event_filter: vec![],
             event_derives: vec![],
+            serde: None,
+            token_decimals: None,
+            contract_trait: false,
+            arbitrary_impls: false,
+            examples: false,
+            artifact_origin: None,
             rustfmt: true,
         }
     }
@@ -152,6 +224,133 @@ impl ContractBuilder {
         self
     }
 
+    /// Sets default options for a method, that are emitted into the generated
+    /// method bindings. The method is specified either by name, or by
+    /// signature for overloaded methods.
+    ///
+    /// Options that are explicitly set on the method builder returned by the
+    /// bindings take precedence over these defaults, while these defaults take
+    /// precedence over the defaults of the contract instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ethcontract_generate::{ContractBuilder, MethodDefaults};
+    /// let builder = ContractBuilder::new().add_method_defaults(
+    ///     "claim",
+    ///     MethodDefaults {
+    ///         gas: Some(300_000.into()),
+    ///         ..Default::default()
+    ///     },
+    /// );
+    /// ```
+    pub fn add_method_defaults(
+        mut self,
+        method: impl Into<String>,
+        defaults: MethodDefaults,
+    ) -> Self {
+        self.method_defaults.insert(method.into(), defaults);
+        self
+    }
+
+    /// Sets a custom codec for a method, for contracts whose methods can not
+    /// be called with the standard ABI encoding, such as gas-optimized
+    /// contracts with packed parameters or fallback based dispatch. The
+    /// generated bindings keep the typed method signature, but encode the
+    /// calldata and decode the return data with the specified functions. The
+    /// method is specified either by name, or by signature for overloaded
+    /// methods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ethcontract_generate::{ContractBuilder, MethodCodec};
+    /// let builder = ContractBuilder::new().add_method_codec(
+    ///     "swap",
+    ///     MethodCodec {
+    ///         encode: Some("crate::codec::encode_swap".to_owned()),
+    ///         decode: Some("crate::codec::decode_swap".to_owned()),
+    ///     },
+    /// );
+    /// ```
+    pub fn add_method_codec(mut self, method: impl Into<String>, codec: MethodCodec) -> Self {
+        self.method_codecs.insert(method.into(), codec);
+        self
+    }
+
+    /// Substitutes a Rust type for a Solidity type in the generated bindings,
+    /// so that domain specific wrapper types can be used in method signatures
+    /// and event data types directly. The substituted type must implement
+    /// `Tokenize` for the Solidity type it replaces.
+    ///
+    /// The substitution is specified either for a Solidity type such as
+    /// `uint256`, which replaces the type in all parameters including nested
+    /// in arrays and tuples, or for a single parameter as `method.parameter`
+    /// where the method or event is specified by name or by signature for
+    /// overloaded methods, and `constructor` refers to the contract
+    /// constructor. Parameter substitutions apply to both inputs and named
+    /// outputs, and take precedence over Solidity type substitutions and typed
+    /// token amounts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ethcontract_generate::ContractBuilder;
+    /// let builder = ContractBuilder::new()
+    ///     .add_type_substitution("uint256", "crate::Wad")
+    ///     .add_type_substitution("setPrice.price", "crate::Ray");
+    /// ```
+    pub fn add_type_substitution(
+        mut self,
+        target: impl Into<String>,
+        rust_type: impl Into<String>,
+    ) -> Self {
+        self.type_substitutions
+            .insert(target.into(), rust_type.into());
+        self
+    }
+
+    /// Merges the ABI of another contract into the generated bindings, similarly
+    /// to how Solidity inheritance composes interfaces. This is useful when an
+    /// artifact only contains an interface such as `IERC20` and another one
+    /// contains the implementation.
+    ///
+    /// Functions and events with identical signatures are only generated once,
+    /// while incompatible definitions with the same signature, such as
+    /// functions with different return types, result in a
+    /// [`GenerateError::AbiConflict`] error. The name, bytecode and networks of
+    /// the contract passed to [`generate`](Self::generate) are used.
+    pub fn merge(mut self, contract: Contract) -> Self {
+        self.merged_contracts.push(contract);
+        self
+    }
+
+    /// Only generates bindings for the methods matching the specified glob
+    /// pattern, where `*` matches any sequence of characters and `?` matches
+    /// any single character. This can be called multiple times to include
+    /// multiple patterns, and keeps compile times and binary sizes down when
+    /// only a few methods of a large contract are used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ethcontract_generate::ContractBuilder;
+    /// let builder = ContractBuilder::new()
+    ///     .include_method("swap")
+    ///     .include_method("observe*");
+    /// ```
+    pub fn include_method(mut self, pattern: impl Into<String>) -> Self {
+        self.method_filter.push(pattern.into());
+        self
+    }
+
+    /// Only generates bindings for the events matching the specified glob
+    /// pattern. See [`include_method`](Self::include_method) for details.
+    pub fn include_event(mut self, pattern: impl Into<String>) -> Self {
+        self.event_filter.push(pattern.into());
+        self
+    }
+
     /// Specifies whether or not to format the code using a locally installed
     /// copy of `rustfmt`.
     ///
@@ -180,14 +379,146 @@ impl ContractBuilder {
         self
     }
 
+    /// Derives `serde::Serialize` and `serde::Deserialize` for event structs
+    /// and enums, with the specified representations of their fields. This
+    /// can be used instead of adding the serde derives with
+    /// [`add_event_derive`](Self::add_event_derive), so that JSON emitted by
+    /// services built on the bindings matches existing APIs, and it does not
+    /// require a dependency on `serde`.
+    ///
+    /// Note that field representations only apply to fields that are `U256`,
+    /// `I256` or address values, and not to values nested in arrays or
+    /// tuples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ethcontract_generate::{AddressFormat, ContractBuilder, SerdeOptions, UintFormat};
+    /// let builder = ContractBuilder::new().serde(SerdeOptions {
+    ///     uint_format: UintFormat::Decimal,
+    ///     address_format: AddressFormat::Checksummed,
+    ///     rename_all: Some("camelCase".to_owned()),
+    /// });
+    /// ```
+    pub fn serde(mut self, options: SerdeOptions) -> Self {
+        self.serde = Some(options);
+        self
+    }
+
+    /// Sets the number of decimals of the token for generating bindings to an
+    /// ERC20 token contract.
+    ///
+    /// This opts into typed token amounts, where the amount parameters and
+    /// return values of the standard ERC20 methods use
+    /// `ethcontract::units::TokenAmount` with a generated `TokenDecimals`
+    /// marker type instead of raw `U256` values. Generating bindings fails if
+    /// the contract is not an ERC20 token or if the number of decimals exceeds
+    /// [`MAX_TOKEN_DECIMALS`].
+    pub fn token_decimals(mut self, decimals: u32) -> Self {
+        self.token_decimals = Some(decimals);
+        self
+    }
+
+    /// Specifies whether or not to generate a trait mirroring the contract
+    /// methods.
+    ///
+    /// The trait is named after the contract prefixed with `I` (for example
+    /// `IErc20` for an `ERC20` contract) and is implemented by the generated
+    /// contract type. View methods are called and other methods are sent as
+    /// transactions, returning boxed futures. This allows application code to
+    /// be written against the trait and unit tested with hand-written mock
+    /// implementations.
+    pub fn contract_trait(mut self, contract_trait: bool) -> Self {
+        self.contract_trait = contract_trait;
+        self
+    }
+
+    /// Specifies whether or not to implement `ethcontract::arbitrary::Arbitrary`
+    /// for the generated event data types, so that property tests can generate
+    /// arbitrary events. The generated code requires the `test-helpers`
+    /// feature of the runtime crate, so these bindings are usually only
+    /// generated for tests.
+    pub fn arbitrary_impls(mut self, arbitrary_impls: bool) -> Self {
+        self.arbitrary_impls = arbitrary_impls;
+        self
+    }
+
+    /// Specifies whether or not to generate an `examples` module next to the
+    /// contract bindings, as starting points for using large contract APIs.
+    ///
+    /// The module contains a function connecting to a node, a function
+    /// creating a contract instance, using the deployments of the artifact if
+    /// it has any, and a function per contract method calling it with default
+    /// arguments. View methods are called and other methods are sent as
+    /// transactions. Since the examples are compiled with the bindings, types
+    /// substituted for method parameters must implement `Default`.
+    pub fn examples(mut self, examples: bool) -> Self {
+        self.examples = examples;
+        self
+    }
+
+    /// Sets the origin of the artifact the contract was loaded from, such as
+    /// its path or URL, used for reporting errors in the contract.
+    pub fn artifact_origin(mut self, origin: impl Into<String>) -> Self {
+        self.artifact_origin = Some(origin.into());
+        self
+    }
+
     /// Generates the contract bindings.
-    pub fn generate(self, contract: &Contract) -> Result<ContractBindings> {
+    ///
+    /// If the contract ABI cannot be expanded into bindings, a
+    /// [`GenerateError::Validation`] error is returned listing all problems
+    /// that were found in the contract.
+    pub fn generate(self, contract: &Contract) -> Result<ContractBindings, GenerateError> {
         let rustfmt = self.rustfmt;
+        let (tokens, report) = generate::expand(contract, self)?;
         Ok(ContractBindings {
-            tokens: generate::expand(contract, self)?,
+            tokens,
             rustfmt,
+            report,
         })
     }
+
+    /// Generates bindings for all Truffle artifacts with a `json` extension in
+    /// a directory, and writes them to the output directory. See
+    /// [`ContractBuilder::generate_artifact`] for more details.
+    ///
+    /// Build scripts should track the artifact directory with
+    /// [`RerunIfChanged::dir`](rerun::RerunIfChanged::dir), so that the
+    /// bindings are regenerated when artifacts are added or recompiled.
+    pub fn generate_dir(
+        self,
+        artifacts: impl AsRef<Path>,
+        out_dir: impl AsRef<Path>,
+    ) -> Result<BindingsReport, GenerateError> {
+        let artifact = dir::load_dir(artifacts.as_ref())?;
+        self.generate_artifact(&artifact, out_dir)
+    }
+
+    /// Generates bindings for all contracts of an artifact, and writes them to
+    /// the output directory with one file per contract named after the contract
+    /// module, for example `ierc20.rs` for the `IERC20` contract. A `mod.rs`
+    /// file including all contracts is also written, so that all bindings can
+    /// be included at once:
+    ///
+    /// ```ignore
+    /// mod contracts {
+    ///     include!(concat!(env!("OUT_DIR"), "/contracts/mod.rs"));
+    /// }
+    /// ```
+    ///
+    /// Contracts are expanded in parallel with the number of threads Cargo
+    /// allows build scripts to use, as specified by the `NUM_JOBS` environment
+    /// variable. The builder options apply to all contracts, so options that
+    /// name a single contract, such as a contract name override, should not be
+    /// used. Returns the size report of the generated bindings.
+    pub fn generate_artifact(
+        self,
+        artifact: &Artifact,
+        out_dir: impl AsRef<Path>,
+    ) -> Result<BindingsReport, GenerateError> {
+        dir::generate_artifact(self, artifact, out_dir.as_ref())
+    }
 }
 
 impl Default for ContractBuilder {
@@ -196,6 +527,80 @@ impl Default for ContractBuilder {
     }
 }
 
+/// Default options for a contract method that are emitted into the generated
+/// bindings. See [`ContractBuilder::add_method_defaults`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MethodDefaults {
+    /// The default gas limit for method transactions and calls.
+    pub gas: Option<Uint>,
+    /// The default amount of ETH to send with method transactions. This can
+    /// only be set for payable methods.
+    pub value: Option<Uint>,
+    /// The default sender of method transactions and calls. Transactions are
+    /// sent from an `Account::Local` account, that is signed by the node.
+    pub from: Option<Address>,
+}
+
+/// A custom encoding of the calldata and return data of a contract method.
+/// See [`ContractBuilder::add_method_codec`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MethodCodec {
+    /// The path of the function encoding the calldata of the method. It is
+    /// called with the method parameters and returns the complete calldata,
+    /// including any selector, as a value that converts into `Bytes`, such as
+    /// a `Vec<u8>`. The parameters are ABI encoded when no encoder is set.
+    pub encode: Option<String>,
+    /// The path of the function decoding the return data of the method, with
+    /// the signature `fn(&[u8]) -> Result<R, ethcontract::common::abi::Error>`
+    /// where `R` is the Rust output type of the method. The return data is ABI
+    /// decoded when no decoder is set.
+    pub decode: Option<String>,
+}
+
+/// Serde representations of generated event data types. See
+/// [`ContractBuilder::serde`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SerdeOptions {
+    /// The representation of `U256` and `I256` fields.
+    pub uint_format: UintFormat,
+    /// The representation of address fields.
+    pub address_format: AddressFormat,
+    /// The `serde` case convention that field names of event structs are
+    /// renamed to, such as `camelCase`. Field names are snake cased when no
+    /// convention is specified.
+    pub rename_all: Option<String>,
+}
+
+/// The representation of 256-bit integers in generated event data types.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UintFormat {
+    /// Hexadecimal strings, such as `"0x2a"`.
+    Hex,
+    /// Decimal strings, such as `"42"`.
+    Decimal,
+}
+
+impl Default for UintFormat {
+    fn default() -> Self {
+        UintFormat::Hex
+    }
+}
+
+/// The representation of addresses in generated event data types.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AddressFormat {
+    /// Lowercase hexadecimal strings.
+    Lowercase,
+    /// EIP-55 checksummed hexadecimal strings.
+    Checksummed,
+}
+
+impl Default for AddressFormat {
+    fn default() -> Self {
+        AddressFormat::Lowercase
+    }
+}
+
 /// Type-safe contract bindings generated by a `Builder`. This type can be
 /// either written to file or into a token stream for use in a procedural macro.
 pub struct ContractBindings {
@@ -204,6 +609,9 @@ pub struct ContractBindings {
 
     /// Format generated code using locally installed copy of `rustfmt`.
     pub rustfmt: bool,
+
+    /// The size of the generated bindings.
+    pub report: ContractReport,
 }
 
 impl ContractBindings {
@@ -218,28 +626,48 @@ impl ContractBindings {
     }
 
     /// Writes the bindings to a given `Write`.
-    pub fn write(&self, mut w: impl Write) -> Result<()> {
-        let source = {
-            let raw = self.tokens.to_string();
-
-            if self.rustfmt {
-                rustfmt::format(&raw).unwrap_or(raw)
-            } else {
-                raw
-            }
-        };
-
-        w.write_all(source.as_bytes())?;
-        Ok(())
+    pub fn write(&self, mut w: impl Write) -> Result<(), GenerateError> {
+        w.write_all(self.source().as_bytes())
+            .map_err(|err| GenerateError::Io("failed to write contract bindings".to_owned(), err))
     }
 
     /// Writes the bindings to the specified file.
-    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<()> {
-        let file = File::create(path)?;
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), GenerateError> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|err| {
+            GenerateError::Io(format!("failed to create file '{}'", path.display()), err)
+        })?;
         let writer = BufWriter::new(file);
         self.write(writer)
     }
 
+    /// Verifies that the bindings in the specified file are up to date, for
+    /// example to fail a build script when committed bindings drift from the
+    /// artifact they were generated from.
+    ///
+    /// The bindings are formatted the same way as when writing them, and then
+    /// compared on the token level, so that differences in whitespace, for
+    /// example from a different `rustfmt` version, are ignored.
+    pub fn verify_against_file(&self, path: impl AsRef<Path>) -> Result<(), GenerateError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|err| {
+            GenerateError::Io(format!("failed to read file '{}'", path.display()), err)
+        })?;
+        verify::verify(&self.source(), &source).map_err(|mismatch| {
+            GenerateError::OutdatedBindings(path.display().to_string(), mismatch)
+        })
+    }
+
+    fn source(&self) -> String {
+        let raw = self.tokens.to_string();
+
+        if self.rustfmt {
+            rustfmt::format(&raw).unwrap_or(raw)
+        } else {
+            raw
+        }
+    }
+
     /// Converts the bindings into its underlying token stream. This allows it
     /// to be used within a procedural macro.
     pub fn into_tokens(self) -> TokenStream {
@@ -0,0 +1,249 @@
+//! Size reports of generated bindings, for finding the contracts that
+//! contribute the most to the compile time of projects with many contracts.
+//!
+//! ```no_run
+//! # use ethcontract_generate::report::BindingsReport;
+//! # use ethcontract_generate::{Artifact, ContractBuilder};
+//! # fn build(artifact: Artifact) -> Result<(), ethcontract_generate::GenerateError> {
+//! let mut report = BindingsReport::default();
+//! for contract in artifact.iter() {
+//!     let bindings = ContractBuilder::new().generate(contract)?;
+//!     report.push(bindings.report.clone());
+//!     // write the bindings...
+//! }
+//! report.write_to_file("target/bindings-report.md")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::GenerateError;
+use proc_macro2::{TokenStream, TokenTree};
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// The size of the generated bindings of a contract.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ContractReport {
+    /// The name of the contract.
+    pub name: String,
+    /// The number of contract methods, after applying method filters.
+    pub methods: usize,
+    /// The number of contract events, after applying event filters.
+    pub events: usize,
+    /// The number of generated structs and enums, such as event data types
+    /// and method builders.
+    pub structs: usize,
+    /// The number of tokens of the generated code, which is a rough estimate
+    /// of the time it takes to compile the bindings.
+    pub tokens: usize,
+}
+
+impl ContractReport {
+    /// Creates a report for contract bindings, counting the structs and tokens
+    /// of the generated code.
+    pub(crate) fn new(name: &str, methods: usize, events: usize, tokens: &TokenStream) -> Self {
+        let mut report = ContractReport {
+            name: name.to_owned(),
+            methods,
+            events,
+            ..Default::default()
+        };
+        report.count(tokens.clone());
+        report
+    }
+
+    fn count(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    self.tokens += 2;
+                    self.count(group.stream());
+                }
+                TokenTree::Ident(ident) => {
+                    self.tokens += 1;
+                    if ident == "struct" || ident == "enum" {
+                        self.structs += 1;
+                    }
+                }
+                _ => self.tokens += 1,
+            }
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "methods": self.methods,
+            "events": self.events,
+            "structs": self.structs,
+            "tokens": self.tokens,
+        })
+    }
+}
+
+/// A size report of the generated bindings of multiple contracts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BindingsReport {
+    /// The reports of the contracts in the order they were added.
+    pub contracts: Vec<ContractReport>,
+}
+
+impl BindingsReport {
+    /// Adds the report of a contract.
+    pub fn push(&mut self, contract: ContractReport) {
+        self.contracts.push(contract);
+    }
+
+    /// Returns the total number of generated tokens.
+    pub fn total_tokens(&self) -> usize {
+        self.contracts.iter().map(|contract| contract.tokens).sum()
+    }
+
+    /// Returns the contract reports sorted by the number of generated tokens,
+    /// starting with the largest.
+    pub fn largest(&self) -> Vec<&ContractReport> {
+        let mut contracts = self.contracts.iter().collect::<Vec<_>>();
+        contracts.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.name.cmp(&b.name)));
+        contracts
+    }
+
+    /// Renders the report as JSON, with the contracts sorted from largest to
+    /// smallest.
+    pub fn to_json(&self) -> String {
+        let report = json!({
+            "contracts": self
+                .largest()
+                .into_iter()
+                .map(ContractReport::to_json)
+                .collect::<Vec<_>>(),
+            "totalTokens": self.total_tokens(),
+        });
+        serde_json::to_string_pretty(&report).expect("report serializes to JSON")
+    }
+
+    /// Renders the report as a Markdown table, with the contracts sorted from
+    /// largest to smallest and their share of the total generated tokens.
+    pub fn to_markdown(&self) -> String {
+        let total = self.total_tokens();
+        let mut markdown = String::new();
+        let _ = writeln!(
+            markdown,
+            "| Contract | Methods | Events | Structs | Tokens | Share |"
+        );
+        let _ = writeln!(markdown, "|---|---:|---:|---:|---:|---:|");
+        for contract in self.largest() {
+            let share = if total == 0 {
+                0.0
+            } else {
+                contract.tokens as f64 * 100.0 / total as f64
+            };
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {} | {} | {:.1}% |",
+                contract.name,
+                contract.methods,
+                contract.events,
+                contract.structs,
+                contract.tokens,
+                share,
+            );
+        }
+        let _ = writeln!(markdown, "| **Total** | | | | {} | |", total);
+        markdown
+    }
+
+    /// Writes the report to a file, as JSON if the file has a `json` extension
+    /// and as Markdown otherwise.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), GenerateError> {
+        let path = path.as_ref();
+        let report = match path.extension() {
+            Some(extension) if extension == "json" => self.to_json(),
+            _ => self.to_markdown(),
+        };
+        fs::write(path, report).map_err(|err| {
+            GenerateError::Io(format!("failed to write report '{}'", path.display()), err)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContractBuilder;
+    use ethcontract_common::abi::{Event, EventParam, Function, ParamType, StateMutability};
+    use ethcontract_common::Contract;
+
+    fn report(name: &str, tokens: usize) -> ContractReport {
+        ContractReport {
+            name: name.to_owned(),
+            methods: 1,
+            events: 2,
+            structs: 3,
+            tokens,
+        }
+    }
+
+    #[test]
+    fn report_counts_generated_items() {
+        let mut contract = Contract::with_name("MyContract");
+        #[allow(deprecated)]
+        let function = Function {
+            name: "foo".into(),
+            inputs: vec![],
+            outputs: vec![],
+            constant: false,
+            state_mutability: StateMutability::NonPayable,
+        };
+        contract.abi.functions.insert("foo".into(), vec![function]);
+        contract.abi.events.insert(
+            "Bar".into(),
+            vec![Event {
+                name: "Bar".into(),
+                inputs: vec![EventParam {
+                    name: "a".into(),
+                    kind: ParamType::Bool,
+                    indexed: false,
+                }],
+                anonymous: false,
+            }],
+        );
+
+        let bindings = ContractBuilder::new().generate(&contract).unwrap();
+        let report = &bindings.report;
+        assert_eq!(report.name, "MyContract");
+        assert_eq!((report.methods, report.events), (1, 1));
+        assert!(report.structs >= 3, "{:?}", report);
+
+        let mut tokens = ContractReport::default();
+        tokens.count(quote::quote! { struct A { b: (u8,) } });
+        assert_eq!((tokens.structs, tokens.tokens), (1, 10));
+    }
+
+    #[test]
+    fn render_reports() {
+        let report = BindingsReport {
+            contracts: vec![report("Small", 100), report("Large", 300)],
+        };
+
+        assert_eq!(
+            report.to_markdown(),
+            "| Contract | Methods | Events | Structs | Tokens | Share |\n\
+             |---|---:|---:|---:|---:|---:|\n\
+             | Large | 1 | 2 | 3 | 300 | 75.0% |\n\
+             | Small | 1 | 2 | 3 | 100 | 25.0% |\n\
+             | **Total** | | | | 400 | |\n"
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&report.to_json()).unwrap(),
+            json!({
+                "contracts": [
+                    { "name": "Large", "methods": 1, "events": 2, "structs": 3, "tokens": 300 },
+                    { "name": "Small", "methods": 1, "events": 2, "structs": 3, "tokens": 100 },
+                ],
+                "totalTokens": 400,
+            })
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Tracking of the files and environment variables that generated bindings
+//! depend on, so that build scripts rerun when contracts are recompiled.
+//!
+//! Without `rerun-if-changed` instructions, Cargo only reruns build scripts
+//! when files of the package change, which misses artifacts outside of the
+//! package such as the build output of a contracts workspace. Once a build
+//! script emits an instruction, only the listed files are watched, so every
+//! consumed artifact must be listed. Collecting them with [`RerunIfChanged`]
+//! avoids maintaining the list by hand:
+//!
+//! ```no_run
+//! # use ethcontract_generate::rerun::RerunIfChanged;
+//! # use ethcontract_generate::{ContractBuilder, Source};
+//! let mut rerun = RerunIfChanged::new();
+//!
+//! let source = Source::parse("../contracts/build/WETH9.json").unwrap();
+//! rerun.source(&source);
+//! // generate bindings for `source`...
+//!
+//! rerun.dir("../contracts/build/tokens");
+//! ContractBuilder::new()
+//!     .generate_dir("../contracts/build/tokens", "src/tokens")
+//!     .unwrap();
+//!
+//! rerun.emit();
+//! ```
+
+use crate::source::Source;
+use ethcontract_common::artifact::Artifact;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// The environment variables used when retrieving artifacts from Etherscan.
+const ETHERSCAN_VARS: &[&str] = &["ETHERSCAN_API_KEY"];
+
+/// The environment variables used when retrieving artifacts from IPFS.
+const IPFS_VARS: &[&str] = &["ETHCONTRACT_IPFS_GATEWAYS"];
+
+/// The files, directories and environment variables that bindings generated
+/// by a build script depend on.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RerunIfChanged {
+    paths: BTreeSet<PathBuf>,
+    env_vars: BTreeSet<String>,
+}
+
+impl RerunIfChanged {
+    /// Creates an empty set of dependencies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file that the bindings depend on.
+    pub fn file(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.paths.insert(path.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a directory that the bindings depend on. Cargo scans directories
+    /// for modified files, so this also covers artifacts that are added to the
+    /// directory later.
+    pub fn dir(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.file(path)
+    }
+
+    /// Adds an environment variable that the bindings depend on.
+    pub fn env(&mut self, name: impl Into<String>) -> &mut Self {
+        self.env_vars.insert(name.into());
+        self
+    }
+
+    /// Adds the dependencies of an artifact source. These are the artifact
+    /// file for local sources, and the environment variables configuring the
+    /// retrieval of remote sources, since remote artifacts can not be watched.
+    pub fn source(&mut self, source: &Source) -> &mut Self {
+        let vars = match source {
+            Source::Local(path) => {
+                self.file(path);
+                return self;
+            }
+            Source::Etherscan(_) => ETHERSCAN_VARS,
+            Source::Ipfs(_) => IPFS_VARS,
+            Source::Http(_) | Source::Npm(_) => &[],
+        };
+        for var in vars {
+            self.env(*var);
+        }
+        self
+    }
+
+    /// Adds the file or directory an artifact was loaded from, as reported by
+    /// its origin. Artifacts that were not loaded from the file system, for
+    /// example artifacts parsed from strings, are ignored.
+    pub fn artifact(&mut self, artifact: &Artifact) -> &mut Self {
+        let origin = Path::new(artifact.origin());
+        if origin.exists() {
+            self.file(origin);
+        }
+        self
+    }
+
+    /// Returns the Cargo build script instructions for the dependencies.
+    pub fn instructions(&self) -> Vec<String> {
+        self.paths
+            .iter()
+            .map(|path| format!("cargo:rerun-if-changed={}", path.display()))
+            .chain(
+                self.env_vars
+                    .iter()
+                    .map(|name| format!("cargo:rerun-if-env-changed={}", name)),
+            )
+            .collect()
+    }
+
+    /// Prints the Cargo build script instructions for the dependencies. This
+    /// should only be called from build scripts.
+    pub fn emit(&self) {
+        for instruction in self.instructions() {
+            println!("{}", instruction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instructions_for_dependencies() {
+        let mut rerun = RerunIfChanged::new();
+        rerun
+            .source(&Source::local("/build/WETH9.json"))
+            .source(&Source::etherscan("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap())
+            .source(&Source::npm(
+                "@openzeppelin/contracts/build/contracts/IERC20.json",
+            ))
+            .dir("/build/tokens")
+            .file("/build/WETH9.json")
+            .artifact(&Artifact::new())
+            .env("ARTIFACTS_DIR");
+
+        assert_eq!(
+            rerun.instructions(),
+            [
+                "cargo:rerun-if-changed=/build/WETH9.json",
+                "cargo:rerun-if-changed=/build/tokens",
+                "cargo:rerun-if-env-changed=ARTIFACTS_DIR",
+                "cargo:rerun-if-env-changed=ETHERSCAN_API_KEY",
+            ]
+        );
+    }
+
+    #[test]
+    fn artifacts_from_files_are_tracked() {
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let artifact = Artifact::with_origin(manifest);
+        assert_eq!(
+            RerunIfChanged::new().artifact(&artifact).instructions(),
+            [format!("cargo:rerun-if-changed={}", manifest)]
+        );
+    }
+}
@@ -1,34 +1,37 @@
 //! This module implements basic `rustfmt` code formatting.
 
-use anyhow::{anyhow, Result};
+use crate::errors::GenerateError;
 use std::io::Write;
 use std::process::{Command, Stdio};
 
 /// Formats the raw input source string and return formatted output.
-pub fn format(source: &str) -> Result<String> {
+pub fn format(source: &str) -> Result<String, GenerateError> {
     let mut rustfmt = Command::new("rustfmt")
         .args(&["--edition", "2018"])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .spawn()?;
+        .spawn()
+        .map_err(|err| GenerateError::Rustfmt(err.to_string()))?;
 
     {
-        let stdin = rustfmt
-            .stdin
-            .as_mut()
-            .ok_or_else(|| anyhow!("stdin was not created for `rustfmt` child process"))?;
-        stdin.write_all(source.as_bytes())?;
+        let stdin = rustfmt.stdin.as_mut().ok_or_else(|| {
+            GenerateError::Rustfmt("stdin was not created for `rustfmt` child process".to_owned())
+        })?;
+        stdin
+            .write_all(source.as_bytes())
+            .map_err(|err| GenerateError::Rustfmt(err.to_string()))?;
     }
 
-    let output = rustfmt.wait_with_output()?;
+    let output = rustfmt
+        .wait_with_output()
+        .map_err(|err| GenerateError::Rustfmt(err.to_string()))?;
     if !output.status.success() {
-        return Err(anyhow!(
+        return Err(GenerateError::Rustfmt(format!(
             "`rustfmt` exited with code {}:\n{}",
             output.status,
             String::from_utf8_lossy(&output.stderr),
-        ));
+        )));
     }
 
-    let stdout = String::from_utf8(output.stdout)?;
-    Ok(stdout)
+    String::from_utf8(output.stdout).map_err(|err| GenerateError::Rustfmt(err.to_string()))
 }
@@ -0,0 +1,363 @@
+//! Golden file snapshot tests of generated bindings.
+//!
+//! Snapshot tests expand contracts into bindings and compare the formatted
+//! source code with snapshots checked into the repository, so that changes to
+//! the generated code, for example from updating an artifact or the code
+//! generator, show up in code review. Unlike
+//! [`ContractBindings::verify_against_file`], snapshots are compared exactly
+//! and mismatches are reported with a line diff.
+//!
+//! When the `UPDATE_SNAPSHOTS` environment variable is set, snapshots are
+//! regenerated instead of compared:
+//!
+//! ```no_run
+//! # use ethcontract_generate::loaders::TruffleLoader;
+//! # use ethcontract_generate::snapshot::Snapshots;
+//! # use ethcontract_generate::ContractBuilder;
+//! #[test]
+//! fn bindings_snapshots() {
+//!     let artifact = TruffleLoader::new()
+//!         .load_from_file("build/contracts/IERC20.json")
+//!         .unwrap();
+//!     Snapshots::new("tests/snapshots")
+//!         .check_artifact(&artifact, &ContractBuilder::new())
+//!         .unwrap();
+//! }
+//! ```
+//!
+//! Running `UPDATE_SNAPSHOTS=1 cargo test` then writes the snapshots to
+//! `tests/snapshots/ierc20.rs`. Note that the bindings are formatted with the
+//! locally installed `rustfmt`, so snapshots should be checked with the same
+//! `rustfmt` version they were generated with.
+
+use crate::errors::{GenerateError, SnapshotMismatch};
+use crate::{ContractBindings, ContractBuilder};
+use ethcontract_common::artifact::Artifact;
+use inflector::Inflector;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The environment variable for regenerating snapshots.
+pub const UPDATE_SNAPSHOTS: &str = "UPDATE_SNAPSHOTS";
+
+/// The number of unchanged lines shown around changed lines in diffs.
+const CONTEXT_LINES: usize = 3;
+
+/// The maximum number of lines of a diff.
+const MAX_DIFF_LINES: usize = 200;
+
+/// The maximum size of the table used for computing diffs. Larger changes are
+/// shown as replacing all changed lines.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Snapshot tests of generated bindings, with snapshots stored in a directory.
+#[derive(Clone, Debug)]
+pub struct Snapshots {
+    dir: PathBuf,
+    update: bool,
+}
+
+impl Snapshots {
+    /// Creates snapshot tests with the snapshots in the specified directory.
+    /// Snapshots are regenerated instead of compared if the `UPDATE_SNAPSHOTS`
+    /// environment variable is set to a value other than `0`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let update = env::var_os(UPDATE_SNAPSHOTS)
+            .map(|value| !value.is_empty() && value != "0")
+            .unwrap_or(false);
+        Snapshots {
+            dir: dir.into(),
+            update,
+        }
+    }
+
+    /// Specifies whether or not to regenerate snapshots instead of comparing
+    /// them, overriding the `UPDATE_SNAPSHOTS` environment variable.
+    pub fn update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Returns the path of the snapshot with the specified name.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.rs", name))
+    }
+
+    /// Compares bindings with the snapshot with the specified name, or writes
+    /// them to the snapshot when updating snapshots.
+    pub fn check(&self, name: &str, bindings: &ContractBindings) -> Result<(), GenerateError> {
+        match self.compare(name, bindings)? {
+            Some(mismatch) => Err(GenerateError::Snapshots(vec![mismatch])),
+            None => Ok(()),
+        }
+    }
+
+    /// Generates bindings for all contracts of an artifact with the specified
+    /// builder and compares them with the snapshots named after the modules of
+    /// the contracts, for example `ierc20` for the `IERC20` contract. All
+    /// mismatched snapshots are reported together.
+    pub fn check_artifact(
+        &self,
+        artifact: &Artifact,
+        builder: &ContractBuilder,
+    ) -> Result<(), GenerateError> {
+        let mut mismatches = Vec::new();
+        for contract in artifact.iter() {
+            let name = builder
+                .contract_mod_override
+                .clone()
+                .unwrap_or_else(|| contract.name.to_snake_case());
+            let bindings = builder.clone().generate(contract)?;
+            mismatches.extend(self.compare(&name, &bindings)?);
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(GenerateError::Snapshots(mismatches))
+        }
+    }
+
+    fn compare(
+        &self,
+        name: &str,
+        bindings: &ContractBindings,
+    ) -> Result<Option<SnapshotMismatch>, GenerateError> {
+        let path = self.path(name);
+        let source = bindings.source();
+        if self.update {
+            write_snapshot(&path, &source)?;
+            return Ok(None);
+        }
+
+        let snapshot = match fs::read_to_string(&path) {
+            Ok(snapshot) => snapshot,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Some(SnapshotMismatch::Missing(path.display().to_string())));
+            }
+            Err(err) => {
+                return Err(GenerateError::Io(
+                    format!("failed to read snapshot '{}'", path.display()),
+                    err,
+                ))
+            }
+        };
+
+        let snapshot = snapshot.replace("\r\n", "\n");
+        if snapshot == source {
+            Ok(None)
+        } else {
+            Ok(Some(SnapshotMismatch::Changed(
+                path.display().to_string(),
+                diff(&snapshot, &source),
+            )))
+        }
+    }
+}
+
+fn write_snapshot(path: &Path, source: &str) -> Result<(), GenerateError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| {
+            GenerateError::Io(
+                format!("failed to create directory '{}'", dir.display()),
+                err,
+            )
+        })?;
+    }
+    fs::write(path, source).map_err(|err| {
+        GenerateError::Io(
+            format!("failed to write snapshot '{}'", path.display()),
+            err,
+        )
+    })
+}
+
+/// A line of a diff.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Line<'a> {
+    Unchanged(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Computes a line diff from the expected to the found source code, in a
+/// format similar to unified diffs.
+fn diff(expected: &str, found: &str) -> String {
+    let lines = diff_lines(
+        &expected.lines().collect::<Vec<_>>(),
+        &found.lines().collect::<Vec<_>>(),
+    );
+
+    // Only show unchanged lines close to changed lines.
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Unchanged(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let shown = |i: usize| {
+        changed
+            .iter()
+            .any(|&changed| i + CONTEXT_LINES >= changed && i <= changed + CONTEXT_LINES)
+    };
+
+    let mut output = String::new();
+    let (mut expected_line, mut found_line) = (1, 1);
+    let mut in_hunk = false;
+    let mut count = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if shown(i) {
+            if !in_hunk {
+                let _ = writeln!(output, "@@ -{} +{} @@", expected_line, found_line);
+                in_hunk = true;
+            }
+            if count == MAX_DIFF_LINES {
+                let _ = writeln!(output, "...");
+                break;
+            }
+            let _ = match line {
+                Line::Unchanged(line) => writeln!(output, " {}", line),
+                Line::Removed(line) => writeln!(output, "-{}", line),
+                Line::Added(line) => writeln!(output, "+{}", line),
+            };
+            count += 1;
+        } else {
+            in_hunk = false;
+        }
+
+        match line {
+            Line::Unchanged(_) => {
+                expected_line += 1;
+                found_line += 1;
+            }
+            Line::Removed(_) => expected_line += 1,
+            Line::Added(_) => found_line += 1,
+        }
+    }
+    output
+}
+
+/// Computes the lines of a diff using the longest common subsequence of the
+/// lines that differ after removing the common prefix and suffix.
+fn diff_lines<'a>(expected: &[&'a str], found: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = expected
+        .iter()
+        .zip(found)
+        .take_while(|(expected, found)| expected == found)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(found[prefix..].iter().rev())
+        .take_while(|(expected, found)| expected == found)
+        .count();
+    let removed = &expected[prefix..expected.len() - suffix];
+    let added = &found[prefix..found.len() - suffix];
+
+    let mut lines = expected[..prefix]
+        .iter()
+        .map(|line| Line::Unchanged(line))
+        .collect::<Vec<_>>();
+    if (removed.len() + 1) * (added.len() + 1) > MAX_DIFF_CELLS {
+        lines.extend(removed.iter().map(|line| Line::Removed(line)));
+        lines.extend(added.iter().map(|line| Line::Added(line)));
+    } else {
+        // `lengths[i][j]` is the length of the longest common subsequence of
+        // `removed[i..]` and `added[j..]`.
+        let width = added.len() + 1;
+        let mut lengths = vec![0usize; (removed.len() + 1) * width];
+        for i in (0..removed.len()).rev() {
+            for j in (0..added.len()).rev() {
+                lengths[i * width + j] = if removed[i] == added[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < removed.len() || j < added.len() {
+            if i < removed.len() && j < added.len() && removed[i] == added[j] {
+                lines.push(Line::Unchanged(removed[i]));
+                i += 1;
+                j += 1;
+            } else if j == added.len()
+                || (i < removed.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+            {
+                lines.push(Line::Removed(removed[i]));
+                i += 1;
+            } else {
+                lines.push(Line::Added(added[j]));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(
+        expected[expected.len() - suffix..]
+            .iter()
+            .map(|line| Line::Unchanged(line)),
+    );
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract_common::Contract;
+
+    fn snapshot_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "ethcontract-snapshots-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn diff_shows_changed_lines_with_context() {
+        let expected = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let found = "a\nb\nc\nd\ne\nF\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            diff(expected, found),
+            "@@ -3 +3 @@\n c\n d\n e\n-f\n+F\n g\n h\n i\n j\n+k\n"
+        );
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn snapshots_are_updated_and_compared() {
+        let dir = snapshot_dir("artifact");
+        let mut artifact = Artifact::new();
+        artifact.insert(Contract::with_name("MyContract"));
+        let builder = ContractBuilder::new().rustfmt(false);
+
+        assert!(matches!(
+            Snapshots::new(&dir).update(false).check_artifact(&artifact, &builder),
+            Err(GenerateError::Snapshots(mismatches))
+                if matches!(mismatches[..], [SnapshotMismatch::Missing(_)])
+        ));
+
+        let snapshots = Snapshots::new(&dir).update(true);
+        snapshots.check_artifact(&artifact, &builder).unwrap();
+        let path = snapshots.path("my_contract");
+        assert!(path.exists());
+
+        let snapshots = snapshots.update(false);
+        snapshots.check_artifact(&artifact, &builder).unwrap();
+
+        fs::write(&path, "// outdated\n").unwrap();
+        let err = snapshots
+            .check_artifact(&artifact, &builder)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("-// outdated"), "{}", err);
+        assert!(err.contains(UPDATE_SNAPSHOTS), "{}", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
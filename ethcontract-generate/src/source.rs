@@ -23,8 +23,10 @@
 //!     .expect("failed to load an artifact");
 //! ```
 
+use crate::errors::GenerateError;
 use crate::util;
-use anyhow::{anyhow, Context, Error, Result};
+use ethcontract_common::errors::IpfsError;
+use ethcontract_common::ipfs::{IpfsGateways, IpfsPath};
 use ethcontract_common::Address;
 use std::borrow::Cow;
 use std::env;
@@ -60,6 +62,17 @@ pub enum Source {
     ///
     /// [unpkg]: unpkg.io
     Npm(String),
+
+    /// A path to an artifact or ABI published to [IPFS], retrieved through
+    /// public IPFS gateways.
+    ///
+    /// The retrieved content is verified against its content identifier
+    /// when the path refers to a file directly. The gateways can be
+    /// configured with a comma separated list of gateway URLs in the
+    /// `ETHCONTRACT_IPFS_GATEWAYS` environment variable.
+    ///
+    /// [IPFS]: ipfs.io
+    Ipfs(IpfsPath),
 }
 
 impl Source {
@@ -86,37 +99,55 @@ impl Source {
     /// - a URL with `npm` scheme, NPM package name, an optional version
     ///   and a path (defaulting to the latest version and `index.js`).
     ///   For example `npm:@openzeppelin/contracts/build/contracts/IERC20.json`.
-    ///   The contract artifact or ABI will be retrieved through [`unpkg`].
+    ///   The contract artifact or ABI will be retrieved through [`unpkg`];
+    ///
+    /// - an `ipfs://` URI with a content identifier and an optional path, for
+    ///   example `ipfs://Qm.../artifact.json`. The contract artifact or ABI
+    ///   will be retrieved through IPFS gateways.
     ///
     /// [Etherscan]: etherscan.io
     /// [unpkg]: unpkg.io
-    pub fn parse(source: &str) -> Result<Self> {
-        let root = env::current_dir()?.canonicalize()?;
+    pub fn parse(source: &str) -> Result<Self, GenerateError> {
+        let root = env::current_dir()
+            .and_then(|cwd| cwd.canonicalize())
+            .map_err(|err| {
+                GenerateError::Io("failed to resolve current directory".to_owned(), err)
+            })?;
         Source::with_root(root, source)
     }
 
     /// Parses an artifact source from a string and uses the specified root
     /// directory for resolving relative paths. See [`parse`] for more details
     /// on supported source strings.
-    pub fn with_root(root: impl AsRef<Path>, source: &str) -> Result<Self> {
-        let base = Url::from_directory_path(root)
-            .map_err(|_| anyhow!("root path '{}' is not absolute"))?;
-        let url = base.join(source.as_ref())?;
+    pub fn with_root(root: impl AsRef<Path>, source: &str) -> Result<Self, GenerateError> {
+        let root = root.as_ref();
+        let base = Url::from_directory_path(root).map_err(|_| {
+            GenerateError::InvalidSource(format!("root path '{}' is not absolute", root.display()))
+        })?;
+        let url = base
+            .join(source.as_ref())
+            .map_err(|err| GenerateError::InvalidSource(format!("'{}': {}", source, err)))?;
 
         match url.scheme() {
             "file" => Ok(Source::local(url.path())),
             "http" | "https" => match url.host_str() {
-                Some("etherscan.io") => Source::etherscan(
-                    url.path()
-                        .rsplit('/')
-                        .next()
-                        .ok_or_else(|| anyhow!("HTTP URL does not have a path"))?,
-                ),
+                Some("etherscan.io") => {
+                    Source::etherscan(url.path().rsplit('/').next().ok_or_else(|| {
+                        GenerateError::InvalidSource(format!(
+                            "HTTP URL '{}' does not have a path",
+                            url
+                        ))
+                    })?)
+                }
                 _ => Ok(Source::Http(url)),
             },
             "etherscan" => Source::etherscan(url.path()),
             "npm" => Ok(Source::npm(url.path())),
-            _ => Err(anyhow!("unsupported URL '{}'", url)),
+            "ipfs" => Source::ipfs(url.as_str()),
+            _ => Err(GenerateError::InvalidSource(format!(
+                "unsupported URL '{}'",
+                url
+            ))),
         }
     }
 
@@ -126,16 +157,23 @@ impl Source {
     }
 
     /// Creates an HTTP source from a URL.
-    pub fn http(url: &str) -> Result<Self> {
-        Ok(Source::Http(Url::parse(url)?))
+    pub fn http(url: &str) -> Result<Self, GenerateError> {
+        let url = Url::parse(url)
+            .map_err(|err| GenerateError::InvalidSource(format!("'{}': {}", url, err)))?;
+        Ok(Source::Http(url))
     }
 
     /// Creates an [Etherscan] source from contract address on mainnet.
     ///
     /// [Etherscan]: etherscan.io
-    pub fn etherscan(address: &str) -> Result<Self> {
+    pub fn etherscan(address: &str) -> Result<Self, GenerateError> {
         util::parse_address(address)
-            .context("failed to parse address for Etherscan source")
+            .map_err(|err| {
+                GenerateError::InvalidSource(format!(
+                    "failed to parse address for Etherscan source: {}",
+                    err
+                ))
+            })
             .map(Source::Etherscan)
     }
 
@@ -144,6 +182,11 @@ impl Source {
         Source::Npm(package_path.into())
     }
 
+    /// Creates an IPFS source from an `ipfs://` URI.
+    pub fn ipfs(uri: &str) -> Result<Self, GenerateError> {
+        Ok(Source::Ipfs(uri.parse()?))
+    }
+
     /// Retrieves the source JSON of the artifact.
     ///
     /// This will either read the JSON from the file system or retrieve
@@ -153,33 +196,126 @@ impl Source {
     /// them using the [truffle loader].
     ///
     /// [truffle loader]: ethcontract_common::artifact::truffle::TruffleLoader
-    pub fn artifact_json(&self) -> Result<String> {
+    pub fn artifact_json(&self) -> Result<String, GenerateError> {
         match self {
             Source::Local(path) => get_local_contract(path),
             Source::Http(url) => get_http_contract(url),
             Source::Etherscan(address) => get_etherscan_contract(*address),
             Source::Npm(package) => get_npm_contract(package),
+            Source::Ipfs(path) => get_ipfs_contract(path),
+        }
+    }
+}
+
+/// Expands environment variables and the `{workspace_root}` placeholder in an
+/// artifact source string.
+///
+/// Environment variables are referenced as `$NAME` or `${NAME}`, and `$$`
+/// expands to a literal `$`. The `{workspace_root}` placeholder expands to the
+/// root directory of the Cargo workspace of the crate being built, which is
+/// found by searching the parent directories of `CARGO_MANIFEST_DIR` for a
+/// `Cargo.toml` with a `[workspace]` section.
+///
+/// ```
+/// # use ethcontract_generate::source::expand_source;
+/// std::env::set_var("ARTIFACTS_DIR", "/artifacts");
+/// assert_eq!(
+///     expand_source("${ARTIFACTS_DIR}/WETH9.json").unwrap(),
+///     "/artifacts/WETH9.json",
+/// );
+/// ```
+pub fn expand_source(source: &str) -> Result<String, GenerateError> {
+    expand_source_with(source, |name| env::var(name).ok())
+}
+
+fn expand_source_with(
+    source: &str,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<String, GenerateError> {
+    let lookup = |name: &str| {
+        var(name).ok_or_else(|| {
+            GenerateError::InvalidSource(format!(
+                "environment variable '{}' used in '{}' is not set",
+                name, source
+            ))
+        })
+    };
+
+    let mut expanded = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find(|c| c == '$' || c == '{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(remaining) = rest.strip_prefix("{workspace_root}") {
+            let manifest_dir = lookup("CARGO_MANIFEST_DIR")?;
+            expanded.push_str(&workspace_root(Path::new(&manifest_dir)).to_string_lossy());
+            rest = remaining;
+        } else if let Some(remaining) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = remaining;
+        } else if let Some(remaining) = rest.strip_prefix("${") {
+            let end = remaining.find('}').ok_or_else(|| {
+                GenerateError::InvalidSource(format!("unterminated variable in '{}'", source))
+            })?;
+            expanded.push_str(&lookup(&remaining[..end])?);
+            rest = &remaining[end + 1..];
+        } else if let Some(remaining) = rest.strip_prefix('$') {
+            let end = remaining
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(remaining.len());
+            if end == 0 {
+                return Err(GenerateError::InvalidSource(format!(
+                    "missing variable name after '$' in '{}'",
+                    source
+                )));
+            }
+            expanded.push_str(&lookup(&remaining[..end])?);
+            rest = &remaining[end..];
+        } else {
+            expanded.push('{');
+            rest = &rest[1..];
         }
     }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+/// Returns the root directory of the Cargo workspace containing the specified
+/// crate directory, or the crate directory itself if it is not part of a
+/// workspace.
+fn workspace_root(manifest_dir: &Path) -> &Path {
+    manifest_dir
+        .ancestors()
+        .find(|dir| {
+            fs::read_to_string(dir.join("Cargo.toml"))
+                .map(|manifest| manifest.lines().any(|line| line.trim() == "[workspace]"))
+                .unwrap_or(false)
+        })
+        .unwrap_or(manifest_dir)
 }
 
 impl FromStr for Source {
-    type Err = Error;
+    type Err = GenerateError;
 
-    fn from_str(s: &str) -> Result<Self> {
+    fn from_str(s: &str) -> Result<Self, GenerateError> {
         Source::parse(s)
     }
 }
 
-fn get_local_contract(path: &Path) -> Result<String> {
+fn get_local_contract(path: &Path) -> Result<String, GenerateError> {
     let path = if path.is_relative() {
-        let absolute_path = path.canonicalize().with_context(|| {
-            format!(
-                "unable to canonicalize file from working dir {} with path {}",
-                env::current_dir()
-                    .map(|cwd| cwd.display().to_string())
-                    .unwrap_or_else(|err| format!("??? ({})", err)),
-                path.display(),
+        let absolute_path = path.canonicalize().map_err(|err| {
+            GenerateError::Io(
+                format!(
+                    "unable to canonicalize file from working dir {} with path {}",
+                    env::current_dir()
+                        .map(|cwd| cwd.display().to_string())
+                        .unwrap_or_else(|err| format!("??? ({})", err)),
+                    path.display(),
+                ),
+                err,
             )
         })?;
         Cow::Owned(absolute_path)
@@ -187,17 +323,21 @@ fn get_local_contract(path: &Path) -> Result<String> {
         Cow::Borrowed(path)
     };
 
-    let json = fs::read_to_string(path).context("failed to read artifact JSON file")?;
+    let json = fs::read_to_string(&path).map_err(|err| {
+        GenerateError::Io(
+            format!("failed to read artifact JSON file '{}'", path.display()),
+            err,
+        )
+    })?;
     Ok(abi_or_artifact(json))
 }
 
-fn get_http_contract(url: &Url) -> Result<String> {
-    let json = util::http_get(url.as_str())
-        .with_context(|| format!("failed to retrieve JSON from {}", url))?;
+fn get_http_contract(url: &Url) -> Result<String, GenerateError> {
+    let json = util::http_get(url.as_str())?;
     Ok(abi_or_artifact(json))
 }
 
-fn get_etherscan_contract(address: Address) -> Result<String> {
+fn get_etherscan_contract(address: Address) -> Result<String, GenerateError> {
     // NOTE: We do not retrieve the bytecode since deploying contracts with the
     //   same bytecode is unreliable as the libraries have already linked and
     //   probably don't reference anything when deploying on other networks.
@@ -211,7 +351,13 @@ fn get_etherscan_contract(address: Address) -> Result<String> {
          ?module=contract&action=getabi&address={:?}&format=raw{}",
         address, api_key,
     );
-    let abi = util::http_get(&abi_url).context("failed to retrieve ABI from Etherscan.io")?;
+    // NOTE: Do not include the URL in errors, as it may contain an API key.
+    let abi = util::http_get(&abi_url).map_err(|err| match err {
+        GenerateError::Http(_, reason) => {
+            GenerateError::Http(format!("ABI for {:?} from Etherscan.io", address), reason)
+        }
+        err => err,
+    })?;
 
     // NOTE: Wrap the retrieved ABI in an empty contract, this is because
     //   currently, the code generation infrastructure depends on having an
@@ -224,14 +370,34 @@ fn get_etherscan_contract(address: Address) -> Result<String> {
     Ok(json)
 }
 
-fn get_npm_contract(package: &str) -> Result<String> {
+fn get_npm_contract(package: &str) -> Result<String, GenerateError> {
     let unpkg_url = format!("https://unpkg.com/{}", package);
-    let json = util::http_get(&unpkg_url)
-        .with_context(|| format!("failed to retrieve JSON from for npm package {}", package))?;
+    let json = util::http_get(&unpkg_url)?;
 
     Ok(abi_or_artifact(json))
 }
 
+fn get_ipfs_contract(path: &IpfsPath) -> Result<String, GenerateError> {
+    let gateways = match env::var("ETHCONTRACT_IPFS_GATEWAYS") {
+        Ok(gateways) => IpfsGateways::new(gateways.split(',').map(str::trim)),
+        Err(_) => IpfsGateways::default(),
+    };
+
+    let mut result = Err(IpfsError::NoGateways.into());
+    for url in gateways.urls(path) {
+        result = util::http_get_bytes(&url).and_then(|content| {
+            gateways.verify(path, &content)?;
+            String::from_utf8(content)
+                .map_err(|err| GenerateError::Http(url.clone(), err.to_string()))
+        });
+        if result.is_ok() {
+            break;
+        }
+    }
+
+    Ok(abi_or_artifact(result?))
+}
+
 /// A best-effort coercion of an ABI or an artifact JSON document into an
 /// artifact JSON document.
 ///
@@ -283,9 +449,52 @@ mod tests {
                 "npm:@openzeppelin/contracts@2.5.0/build/contracts/IERC20.json",
                 Source::npm("@openzeppelin/contracts@2.5.0/build/contracts/IERC20.json"),
             ),
+            (
+                "ipfs://QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o/build/IERC20.json",
+                Source::ipfs(
+                    "ipfs://QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o/build/IERC20.json",
+                )
+                .unwrap(),
+            ),
         ] {
             let source = Source::with_root(root, url).unwrap();
             assert_eq!(source, *expected);
         }
     }
+
+    #[test]
+    fn expand_source_variables() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let var = |name: &str| match name {
+            "CARGO_MANIFEST_DIR" => Some(manifest_dir.to_owned()),
+            "ARTIFACTS" => Some("/artifacts".to_owned()),
+            _ => None,
+        };
+        let workspace_root = Path::new(manifest_dir).parent().unwrap().display();
+
+        for (source, expected) in &[
+            (
+                "$ARTIFACTS/Contract.json",
+                "/artifacts/Contract.json".to_owned(),
+            ),
+            (
+                "${ARTIFACTS}_v2/Contract.json",
+                "/artifacts_v2/Contract.json".to_owned(),
+            ),
+            (
+                "{workspace_root}/build/Contract.json",
+                format!("{}/build/Contract.json", workspace_root),
+            ),
+            ("npm:pkg/{name}$$.json", "npm:pkg/{name}$.json".to_owned()),
+        ] {
+            assert_eq!(expand_source_with(source, var).unwrap(), *expected);
+        }
+
+        for source in &["$MISSING/Contract.json", "${ARTIFACTS", "$/Contract.json"] {
+            assert!(matches!(
+                expand_source_with(source, var),
+                Err(GenerateError::InvalidSource(_)),
+            ));
+        }
+    }
 }
@@ -1,9 +1,11 @@
-use anyhow::{anyhow, Result};
+use crate::errors::GenerateError;
 use curl::easy::Easy;
-use ethcontract_common::Address;
+use ethcontract_common::errors::ParseAddressError;
+use ethcontract_common::{address, Address};
 use inflector::Inflector;
 use proc_macro2::{Ident, Literal, Span, TokenStream};
 use quote::quote;
+use std::fmt::Display;
 use syn::Ident as SynIdent;
 
 /// Expands a identifier string into an token.
@@ -41,34 +43,82 @@ pub fn expand_doc(s: &str) -> TokenStream {
     }
 }
 
-/// Parses the given address string
-pub fn parse_address<S>(address_str: S) -> Result<Address>
+/// Parses the given address string. Mixed-case addresses must have a valid
+/// EIP-55 checksum.
+pub fn parse_address<S>(address_str: S) -> Result<Address, ParseAddressError>
 where
     S: AsRef<str>,
 {
-    let address_str = address_str.as_ref();
-    if !address_str.starts_with("0x") {
-        return Err(anyhow!("address must start with '0x'"));
-    }
-    Ok(address_str[2..].parse()?)
+    address::parse_checksummed(address_str.as_ref())
 }
 
 /// Performs an HTTP GET request and return the contents of the response.
-pub fn http_get(url: &str) -> Result<String> {
+pub fn http_get(url: &str) -> Result<String, GenerateError> {
     let mut buffer = Vec::new();
+    transfer(url, false, &mut buffer).map_err(|err| http_error(url, err))?;
+
+    let buffer = String::from_utf8(buffer).map_err(|err| http_error(url, err))?;
+    Ok(buffer)
+}
+
+/// Performs an HTTP GET request and return the raw contents of the response,
+/// failing if the server responds with an error status.
+pub fn http_get_bytes(url: &str) -> Result<Vec<u8>, GenerateError> {
+    let mut buffer = Vec::new();
+    transfer(url, true, &mut buffer).map_err(|err| http_error(url, err))?;
+
+    Ok(buffer)
+}
+
+fn transfer(url: &str, fail_on_error: bool, buffer: &mut Vec<u8>) -> Result<(), curl::Error> {
     let mut handle = Easy::new();
     handle.url(url)?;
-    {
-        let mut transfer = handle.transfer();
-        transfer.write_function(|data| {
-            buffer.extend_from_slice(data);
-            Ok(data.len())
-        })?;
-        transfer.perform()?;
+    handle.fail_on_error(fail_on_error)?;
+
+    let mut transfer = handle.transfer();
+    transfer.write_function(|data| {
+        buffer.extend_from_slice(data);
+        Ok(data.len())
+    })?;
+    transfer.perform()
+}
+
+fn http_error(url: &str, err: impl Display) -> GenerateError {
+    GenerateError::Http(url.to_owned(), err.to_string())
+}
+
+/// Matches a name against a glob pattern, where `*` matches any sequence of
+/// characters and `?` matches any single character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // NOTE: Backtrack to the last `*` on mismatch, which is enough since each
+    //   `*` only needs to be extended as far as the next one allows.
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
     }
 
-    let buffer = String::from_utf8(buffer)?;
-    Ok(buffer)
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 #[cfg(test)]
@@ -90,6 +140,23 @@ mod tests {
         assert_quote!(expand_input_name(0, "CamelCase1"), { camel_case_1 });
     }
 
+    #[test]
+    fn glob_patterns() {
+        for (pattern, name, expected) in &[
+            ("swap", "swap", true),
+            ("swap", "swapExact", false),
+            ("observe*", "observe", true),
+            ("observe*", "observations", false),
+            ("*Swap*", "ExactSwapInput", true),
+            ("slot?", "slot0", true),
+            ("slot?", "slot", false),
+            ("a*b*c", "a-b-b-c", true),
+            ("a*b*c", "a-c-b", false),
+        ] {
+            assert_eq!(glob_match(pattern, name), *expected, "{} {}", pattern, name);
+        }
+    }
+
     #[test]
     fn parse_address_missing_prefix() {
         if parse_address("0000000000000000000000000000000000000000").is_ok() {
@@ -104,6 +171,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_address_invalid_checksum() {
+        if parse_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_ok() {
+            panic!("parsing address with invalid checksum should fail");
+        }
+    }
+
     #[test]
     fn parse_address_ok() {
         let expected = Address::from([
@@ -0,0 +1,156 @@
+//! Token level comparison of generated bindings with previously generated
+//! source code, ignoring formatting differences.
+
+use crate::errors::BindingsMismatch;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::Lit;
+
+/// The number of tokens preceding a mismatch included in its context.
+const CONTEXT_TOKENS: usize = 8;
+
+/// Compares the source code of generated bindings with previously generated
+/// source code.
+pub(crate) fn verify(expected: &str, found: &str) -> Result<(), BindingsMismatch> {
+    let expected = flatten(
+        expected
+            .parse()
+            .expect("generated bindings are valid tokens"),
+    );
+    let found = flatten(
+        found
+            .parse()
+            .map_err(|err| BindingsMismatch::Unparsable(format!("{:?}", err)))?,
+    );
+
+    let position = expected
+        .iter()
+        .zip(&found)
+        .position(|(expected, found)| expected != found)
+        .or_else(|| {
+            if expected.len() != found.len() {
+                Some(expected.len().min(found.len()))
+            } else {
+                None
+            }
+        });
+
+    match position {
+        Some(position) => {
+            let token = |tokens: &[String]| {
+                tokens
+                    .get(position)
+                    .map(|token| format!("`{}`", token))
+                    .unwrap_or_else(|| "end of file".to_owned())
+            };
+            Err(BindingsMismatch::Token {
+                context: expected[position.saturating_sub(CONTEXT_TOKENS)..position].join(" "),
+                expected: token(&expected),
+                found: token(&found),
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+/// Flattens a token stream into the string representations of its tokens,
+/// so that differences in spacing and string literal syntax are ignored.
+/// Trailing commas are removed since `rustfmt` adds them to items split over
+/// multiple lines.
+fn flatten(tokens: TokenStream) -> Vec<String> {
+    let mut flattened = Vec::new();
+    flatten_into(tokens, &mut flattened);
+
+    let mut tokens = flattened.into_iter().peekable();
+    let mut normalized = Vec::new();
+    while let Some(token) = tokens.next() {
+        let trailing = match tokens.peek() {
+            Some(next) => [")", "]", "}", ">"].contains(&next.as_str()),
+            None => false,
+        };
+        if token != "," || !trailing {
+            normalized.push(token);
+        }
+    }
+    normalized
+}
+
+fn flatten_into(tokens: TokenStream, flattened: &mut Vec<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                if !open.is_empty() {
+                    flattened.push(open.to_owned());
+                }
+                flatten_into(group.stream(), flattened);
+                if !close.is_empty() {
+                    flattened.push(close.to_owned());
+                }
+            }
+            TokenTree::Punct(punct) => flattened.push(punct.as_char().to_string()),
+            // doc comments are parsed into string literals, while quoted doc
+            // attributes use raw string literals
+            TokenTree::Literal(literal) => match Lit::new(literal) {
+                Lit::Str(string) => flattened.push(format!("{:?}", string.value())),
+                literal => flattened.push(literal.to_token_stream().to_string()),
+            },
+            token => flattened.push(token.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn verify_ignores_formatting() {
+        let expected = quote! {
+            /// Documentation.
+            pub fn foo(a: Vec<Vec<u8>>) -> u32 { a.len() as u32 }
+        };
+        let source = "
+            /// Documentation.
+            pub fn foo(
+                a: Vec<Vec<u8> >,
+            ) -> u32 {
+                a.len() as u32
+            }
+        ";
+
+        assert_eq!(verify(&expected.to_string(), source), Ok(()));
+    }
+
+    #[test]
+    fn verify_reports_mismatch() {
+        let expected = quote! { pub fn foo() -> u32 { 42 } };
+
+        assert_eq!(
+            verify(&expected.to_string(), "pub fn foo() -> u64 { 42 }"),
+            Err(BindingsMismatch::Token {
+                context: "pub fn foo ( ) - >".to_owned(),
+                expected: "`u32`".to_owned(),
+                found: "`u64`".to_owned(),
+            }),
+        );
+        assert_eq!(
+            verify(&expected.to_string(), "pub fn foo() -> u32"),
+            Err(BindingsMismatch::Token {
+                context: "pub fn foo ( ) - > u32".to_owned(),
+                expected: "`{`".to_owned(),
+                found: "end of file".to_owned(),
+            }),
+        );
+        assert!(matches!(
+            verify(&expected.to_string(), "pub fn foo() -> u32 {"),
+            Err(BindingsMismatch::Unparsable(_)),
+        ));
+    }
+}
@@ -211,7 +211,7 @@ impl MockTransport {
         signature: H32,
         index: usize,
         generation: usize,
-        pred: Box<dyn Fn(&CallContext, &P) -> bool + Send>,
+        pred: TxPredicateFn<P>,
     ) {
         let mut state = self.state.lock().unwrap();
         let expectation = state.expectation::<P, R>(address, signature, index, generation);
@@ -279,7 +279,7 @@ impl MockTransport {
         signature: H32,
         index: usize,
         generation: usize,
-        returns: Box<dyn Fn(&CallContext, P) -> Result<R, String> + Send>,
+        returns: TxReturnsFn<P, R>,
     ) {
         let mut state = self.state.lock().unwrap();
         let expectation = state.expectation::<P, R>(address, signature, index, generation);
@@ -443,14 +443,14 @@ impl MockTransport {
         args.done();
 
         let state = self.state.lock().unwrap();
-        Self::ok(&U64::from(state.block))
+        Self::ok(U64::from(state.block))
     }
 
     fn chain_id(&self, args: Parser) -> Result<Value, Error> {
         args.done();
 
         let state = self.state.lock().unwrap();
-        Self::ok(&U256::from(state.chain_id))
+        Self::ok(U256::from(state.chain_id))
     }
 
     fn transaction_count(&self, mut args: Parser) -> Result<Value, Error> {
@@ -468,14 +468,14 @@ impl MockTransport {
             }
             _ => state.nonce.get(&address).copied().unwrap_or(0),
         };
-        Self::ok(&U256::from(transaction_count))
+        Self::ok(U256::from(transaction_count))
     }
 
     fn gas_price(&self, args: Parser) -> Result<Value, Error> {
         args.done();
 
         let state = self.state.lock().unwrap();
-        Self::ok(&U256::from(state.gas_price))
+        Self::ok(U256::from(state.gas_price))
     }
 
     fn estimate_gas(&self, mut args: Parser) -> Result<Value, Error> {
@@ -521,7 +521,7 @@ impl MockTransport {
         // When estimating gas, we'll check all expectation as if we're
         // executing a method, but we won't mark any expectation as fulfilled.
 
-        Self::ok(&U256::from(1))
+        Self::ok(U256::from(1))
     }
 
     fn call(&self, mut args: Parser) -> Result<Value, Error> {
@@ -982,11 +982,18 @@ impl<P: Tokenize + Send + 'static, R: Tokenize + Send + 'static> ExpectationApi
     }
 }
 
+/// Predicate that has access to the transaction context.
+type TxPredicateFn<P> = Box<dyn Fn(&CallContext, &P) -> bool + Send>;
+
+/// Return value generator that has access to the transaction context.
+type TxReturnsFn<P, R> = Box<dyn Fn(&CallContext, P) -> Result<R, String> + Send>;
+
+#[allow(clippy::enum_variant_names)]
 enum Predicate<P: Tokenize + Send + 'static> {
     None,
     Predicate(Box<dyn predicates::Predicate<P> + Send>),
     Function(Box<dyn Fn(&P) -> bool + Send>),
-    TxFunction(Box<dyn Fn(&CallContext, &P) -> bool + Send>),
+    TxFunction(TxPredicateFn<P>),
 }
 
 impl<P: Tokenize + Send + 'static> Predicate<P> {
@@ -1005,7 +1012,7 @@ enum Returns<P: Tokenize + Send + 'static, R: Tokenize + Send + 'static> {
     Error(String),
     Const(Token),
     Function(Box<dyn Fn(P) -> Result<R, String> + Send>),
-    TxFunction(Box<dyn Fn(&CallContext, P) -> Result<R, String> + Send>),
+    TxFunction(TxReturnsFn<P, R>),
 }
 
 impl<P: Tokenize + Send + 'static, R: Tokenize + Send + 'static> Returns<P, R> {
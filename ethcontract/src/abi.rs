@@ -0,0 +1,751 @@
+//! Utilities for encoding and decoding ABI data from human readable function
+//! and event signatures, without needing a full contract ABI.
+//!
+//! Functions are described by signatures such as `transfer(address,uint256)`,
+//! optionally followed by their outputs as in `balanceOf(address)(uint256)`.
+//! Events are described by signatures that mark indexed parameters, such as
+//! `Transfer(address indexed,address indexed,uint256)`. See the
+//! [`ethcontract_common::signature`] module for more details.
+//!
+//! Values packed with Solidity's `abi.encodePacked`, which many contracts hash
+//! to verify signatures, can be encoded with [`encode_packed`] or with the
+//! [`Packed`] builder, which specifies the type of every value explicitly:
+//!
+//! ```
+//! # use ethcontract::abi::Packed;
+//! # use ethcontract::Address;
+//! # let (owner, nonce) = (Address::zero(), 42.into());
+//! let hash = Packed::new()
+//!     .address(owner)
+//!     .uint(256, nonce)
+//!     .string("claim")
+//!     .keccak256()
+//!     .unwrap();
+//! ```
+//!
+//! Data from untrusted sources, such as the return data of arbitrary
+//! contracts, can be decoded with [`try_decode`], which never panics on
+//! malformed data.
+//!
+//! The [`SignatureDecoder`] can additionally be used to decode calldata and
+//! logs for unknown selectors and topics by looking up their signatures in a
+//! signature database.
+
+pub(crate) mod decoder;
+mod signatures;
+
+pub use self::decoder::DecodeOptions;
+use self::decoder::Decoder;
+#[cfg(feature = "signature-db")]
+pub use self::signatures::RemoteSignatureDatabase;
+pub use self::signatures::{
+    DecodedCall, DecodedEvent, LocalSignatureDatabase, SignatureDatabase, SignatureDecoder,
+};
+use crate::contract::{BorrowedToken, RawLog};
+use crate::errors::{AbiCodecError, DecodeError};
+use crate::int::I256;
+pub use ethcontract_common::abi::{decode, encode, ParamType, Token};
+use ethcontract_common::abi::{Function, Param};
+use ethcontract_common::abiext::FunctionExt;
+pub use ethcontract_common::hash::{function_selector, keccak256, H32};
+pub use ethcontract_common::signature::{parse_event, parse_function, parse_types};
+use std::slice;
+use web3::types::{Address, H256, U256};
+
+/// Computes the topic of an event with the specified human readable
+/// signature. Parameter names and `indexed` markers are ignored.
+pub fn event_topic(signature: &str) -> Result<H256, AbiCodecError> {
+    Ok(parse_event(signature)?.signature())
+}
+
+/// Encodes calldata for calling a function with the specified signature.
+pub fn encode_function_call(signature: &str, tokens: &[Token]) -> Result<Vec<u8>, AbiCodecError> {
+    let function = parse_function(signature)?;
+    let kinds = param_kinds(&function.inputs);
+    check_tokens(&kinds, tokens)?;
+
+    Ok(encode_call(function.selector(), tokens))
+}
+
+/// Encodes calldata for a function selector and parameters, without checking
+/// the parameters against the function inputs.
+///
+/// Calldata for parameters that only contain value types, which is most
+/// calls, is encoded with a single allocation of the exact calldata size.
+pub fn encode_call(selector: H32, tokens: &[Token]) -> Vec<u8> {
+    let mut calldata = Vec::new();
+    encode_call_into(selector, tokens, &mut calldata);
+    calldata
+}
+
+/// Encodes calldata for a function selector and parameters into a buffer,
+/// replacing its contents. Reusing the buffer avoids allocating calldata when
+/// encoding many calls in a loop where the calldata is only borrowed, for
+/// example for hashing or comparing it. Method builders own their calldata
+/// until it is sent, so they always allocate it with [`encode_call`]:
+///
+/// ```
+/// # use ethcontract::abi::{self, Token};
+/// # use ethcontract::Address;
+/// # let owners = vec![Address::zero(); 3];
+/// let selector = abi::function_selector("balanceOf(address)");
+/// let mut calldata = Vec::new();
+/// for owner in owners {
+///     abi::encode_call_into(selector, &[Token::Address(owner)], &mut calldata);
+///     // use `calldata`...
+/// }
+/// ```
+pub fn encode_call_into(selector: H32, tokens: &[Token], out: &mut Vec<u8>) {
+    out.clear();
+    out.extend_from_slice(&selector);
+    match tokens.iter().map(static_len).sum::<Option<usize>>() {
+        Some(len) => {
+            out.reserve(len);
+            for token in tokens {
+                encode_static(token, out);
+            }
+        }
+        None => out.extend_from_slice(&encode(tokens)),
+    }
+}
+
+/// Decodes the parameters from calldata for a function with the specified
+/// signature. Returns an error if the calldata selector does not match the
+/// function signature.
+pub fn decode_function_call(signature: &str, data: &[u8]) -> Result<Vec<Token>, AbiCodecError> {
+    let function = parse_function(signature)?;
+    let selector = function.selector();
+    if !data.starts_with(&selector) {
+        return Err(AbiCodecError::SelectorMismatch(selector));
+    }
+
+    Ok(try_decode(&param_kinds(&function.inputs), &data[4..])?)
+}
+
+/// Decodes the return data of a function call with the specified signature.
+/// The signature must specify the function outputs, for example
+/// `balanceOf(address)(uint256)`.
+pub fn decode_function_output(signature: &str, data: &[u8]) -> Result<Vec<Token>, AbiCodecError> {
+    let function = parse_function(signature)?;
+    Ok(decode_outputs(&function, data)?)
+}
+
+/// Encodes the topics and data of a log emitted by the specified event
+/// signature. Tokens are specified in declaration order.
+///
+/// Indexed dynamic parameters (strings, bytes, arrays and tuples) are
+/// encoded as the Keccak256 hash of their value as per the ABI
+/// specification.
+pub fn encode_event_data(signature: &str, tokens: &[Token]) -> Result<RawLog, AbiCodecError> {
+    let event = parse_event(signature)?;
+    let kinds = event
+        .inputs
+        .iter()
+        .map(|input| input.kind.clone())
+        .collect::<Vec<_>>();
+    check_tokens(&kinds, tokens)?;
+
+    let mut topics = Vec::new();
+    if !event.anonymous {
+        topics.push(event.signature());
+    }
+
+    let mut data = Vec::new();
+    for (input, token) in event.inputs.iter().zip(tokens) {
+        if input.indexed {
+            topics.push(encode_topic(token));
+        } else {
+            data.push(token.clone());
+        }
+    }
+
+    Ok(RawLog {
+        topics,
+        data: encode(&data),
+    })
+}
+
+/// Decodes the parameters of a log emitted by the specified event signature.
+/// Tokens are returned in declaration order.
+///
+/// Since indexed dynamic parameters are only available as hashes of their
+/// actual value, they are decoded as `Token::FixedBytes` of the hash.
+pub fn decode_event_data(signature: &str, log: RawLog) -> Result<Vec<Token>, AbiCodecError> {
+    let event = parse_event(signature)?;
+    if !event.anonymous {
+        let topic = event.signature();
+        if log.topics.first() != Some(&topic) {
+            return Err(AbiCodecError::TopicMismatch(topic));
+        }
+    }
+
+    let tokens = decoder::decode_log(&event, &log.topics, &log.data, DecodeOptions::default())?;
+    Ok(tokens.into_iter().map(BorrowedToken::into_token).collect())
+}
+
+/// Decodes ABI encoded data like [`decode`], but never panics on malformed
+/// data and returns errors describing where the data is malformed. This
+/// should be used for data from untrusted sources, such as the return data
+/// of arbitrary contracts.
+pub fn try_decode(kinds: &[ParamType], data: &[u8]) -> Result<Vec<Token>, DecodeError> {
+    try_decode_with(kinds, data, DecodeOptions::default())
+}
+
+/// Decodes ABI encoded data like [`try_decode`] with the specified options,
+/// for example to reject strings that are not valid UTF-8.
+pub fn try_decode_with(
+    kinds: &[ParamType],
+    data: &[u8],
+    options: DecodeOptions,
+) -> Result<Vec<Token>, DecodeError> {
+    let (tokens, _) = Decoder::new(data, options).decode_params(kinds, 0, 0)?;
+    Ok(tokens.into_iter().map(BorrowedToken::into_token).collect())
+}
+
+/// Decodes the inputs of a function call from the calldata following the
+/// selector.
+pub(crate) fn decode_inputs(function: &Function, data: &[u8]) -> Result<Vec<Token>, DecodeError> {
+    try_decode(&param_kinds(&function.inputs), data)
+}
+
+/// Decodes the outputs of a function call.
+pub(crate) fn decode_outputs(function: &Function, data: &[u8]) -> Result<Vec<Token>, DecodeError> {
+    try_decode(&param_kinds(&function.outputs), data)
+}
+
+/// Encodes tokens following the non-standard packed mode used by Solidity's
+/// `abi.encodePacked`.
+///
+/// Value types are encoded using the minimal number of bytes for their type,
+/// strings and bytes are encoded in place without their length, and array
+/// elements are padded to 32 bytes. Tuples and nested arrays are not
+/// supported in packed mode.
+pub fn encode_packed(kinds: &[ParamType], tokens: &[Token]) -> Result<Vec<u8>, AbiCodecError> {
+    check_tokens(kinds, tokens)?;
+
+    let mut packed = Vec::new();
+    for (kind, token) in kinds.iter().zip(tokens) {
+        encode_packed_token(kind, token, false, &mut packed)?;
+    }
+    Ok(packed)
+}
+
+/// Computes the Keccak256 hash of tokens encoded in packed mode. This is the
+/// equivalent of Solidity's `keccak256(abi.encodePacked(...))`.
+pub fn keccak256_packed(kinds: &[ParamType], tokens: &[Token]) -> Result<H256, AbiCodecError> {
+    Ok(H256(keccak256(encode_packed(kinds, tokens)?)))
+}
+
+/// Builder for values encoded in packed mode, equivalent to the arguments of
+/// Solidity's `abi.encodePacked`.
+///
+/// Specifying the Solidity type of every value avoids the most common packing
+/// mistakes, such as encoding a `uint8` as a full 32-byte word.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Packed {
+    kinds: Vec<ParamType>,
+    tokens: Vec<Token>,
+}
+
+impl Packed {
+    /// Creates a new empty packed encoding.
+    pub fn new() -> Self {
+        Packed::default()
+    }
+
+    /// Appends a value of the specified Solidity type.
+    pub fn token(mut self, kind: ParamType, token: Token) -> Self {
+        self.kinds.push(kind);
+        self.tokens.push(token);
+        self
+    }
+
+    /// Appends an `address`.
+    pub fn address(self, value: Address) -> Self {
+        self.token(ParamType::Address, Token::Address(value))
+    }
+
+    /// Appends a `bool`.
+    pub fn bool(self, value: bool) -> Self {
+        self.token(ParamType::Bool, Token::Bool(value))
+    }
+
+    /// Appends an unsigned integer of the specified bit size, for example
+    /// `uint(8, value)` for a `uint8`.
+    pub fn uint(self, bits: usize, value: U256) -> Self {
+        self.token(ParamType::Uint(bits), Token::Uint(value))
+    }
+
+    /// Appends a signed integer of the specified bit size.
+    pub fn int(self, bits: usize, value: I256) -> Self {
+        self.token(ParamType::Int(bits), Token::Int(value.into_raw()))
+    }
+
+    /// Appends fixed size bytes, the size of the `bytesN` type is the length
+    /// of the value.
+    pub fn fixed_bytes(self, value: impl AsRef<[u8]>) -> Self {
+        let value = value.as_ref().to_vec();
+        self.token(ParamType::FixedBytes(value.len()), Token::FixedBytes(value))
+    }
+
+    /// Appends dynamic `bytes`.
+    pub fn bytes(self, value: impl Into<Vec<u8>>) -> Self {
+        self.token(ParamType::Bytes, Token::Bytes(value.into()))
+    }
+
+    /// Appends a `string`.
+    pub fn string(self, value: impl Into<String>) -> Self {
+        self.token(ParamType::String, Token::String(value.into()))
+    }
+
+    /// Encodes the values in packed mode.
+    pub fn encode(&self) -> Result<Vec<u8>, AbiCodecError> {
+        encode_packed(&self.kinds, &self.tokens)
+    }
+
+    /// Computes the Keccak256 hash of the values encoded in packed mode.
+    pub fn keccak256(&self) -> Result<H256, AbiCodecError> {
+        keccak256_packed(&self.kinds, &self.tokens)
+    }
+}
+
+/// Computes the Keccak256 hash of the concatenation of byte strings. This is
+/// the equivalent of `keccak256(abi.encodePacked(a, b, ...))` for values that
+/// are already encoded, such as other hashes.
+pub fn keccak256_concat(parts: &[&[u8]]) -> H256 {
+    H256(keccak256(parts.concat()))
+}
+
+/// Computes the EIP-191 hash of a message signed with `eth_sign` or
+/// `personal_sign`, which is the Keccak256 hash of
+/// `"\x19Ethereum Signed Message:\n" || len(message) || message`.
+///
+/// Contracts verifying a signature of a hash usually recompute this hash of
+/// the 32 bytes of the signed hash.
+pub fn hash_message(message: impl AsRef<[u8]>) -> H256 {
+    let message = message.as_ref();
+    keccak256_concat(&[
+        format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes(),
+        message,
+    ])
+}
+
+/// Returns the encoded size of a token that only contains value types, which
+/// are encoded in place, or `None` for tokens containing dynamic values.
+fn static_len(token: &Token) -> Option<usize> {
+    match token {
+        Token::Address(_) | Token::Int(_) | Token::Uint(_) | Token::Bool(_) => Some(32),
+        Token::FixedBytes(bytes) => Some((bytes.len() + 31) / 32 * 32),
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => tokens.iter().map(static_len).sum(),
+        Token::Bytes(_) | Token::String(_) | Token::Array(_) => None,
+    }
+}
+
+/// Encodes a token that only contains value types in place.
+fn encode_static(token: &Token, out: &mut Vec<u8>) {
+    match token {
+        Token::Address(address) => {
+            out.extend_from_slice(&[0; 12]);
+            out.extend_from_slice(address.as_bytes());
+        }
+        Token::Int(value) | Token::Uint(value) => {
+            let mut word = [0; 32];
+            value.to_big_endian(&mut word);
+            out.extend_from_slice(&word);
+        }
+        Token::Bool(value) => {
+            out.extend_from_slice(&[0; 31]);
+            out.push(*value as u8);
+        }
+        Token::FixedBytes(bytes) => pad_right(bytes, out),
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            for token in tokens {
+                encode_static(token, out);
+            }
+        }
+        Token::Bytes(_) | Token::String(_) | Token::Array(_) => {
+            unreachable!("dynamic tokens are not encoded in place")
+        }
+    }
+}
+
+fn param_kinds(params: &[Param]) -> Vec<ParamType> {
+    params.iter().map(|param| param.kind.clone()).collect()
+}
+
+/// Verifies that tokens match their corresponding parameter types.
+fn check_tokens(kinds: &[ParamType], tokens: &[Token]) -> Result<(), AbiCodecError> {
+    if kinds.len() != tokens.len() {
+        return Err(AbiCodecError::TokenCount {
+            expected: kinds.len(),
+            actual: tokens.len(),
+        });
+    }
+    for (kind, token) in kinds.iter().zip(tokens) {
+        if !token.type_check(kind) {
+            return Err(AbiCodecError::TypeMismatch {
+                kind: kind.clone(),
+                token: token.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes an indexed event parameter as a topic.
+fn encode_topic(token: &Token) -> H256 {
+    match token {
+        Token::String(value) => H256(keccak256(value)),
+        Token::Bytes(value) => H256(keccak256(value)),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            let mut preimage = Vec::new();
+            for token in tokens {
+                encode_topic_preimage(token, &mut preimage);
+            }
+            H256(keccak256(preimage))
+        }
+        _ => H256::from_slice(&encode(slice::from_ref(token))),
+    }
+}
+
+/// Encodes a value nested in an indexed array or tuple parameter. Nested
+/// values are encoded in place and padded to a multiple of 32 bytes.
+fn encode_topic_preimage(token: &Token, out: &mut Vec<u8>) {
+    match token {
+        Token::String(value) => pad_right(value.as_bytes(), out),
+        Token::Bytes(value) => pad_right(value, out),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            for token in tokens {
+                encode_topic_preimage(token, out);
+            }
+        }
+        _ => out.extend_from_slice(&encode(slice::from_ref(token))),
+    }
+}
+
+fn pad_right(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(bytes);
+    let remainder = bytes.len() % 32;
+    if remainder != 0 {
+        out.resize(out.len() + 32 - remainder, 0);
+    }
+}
+
+/// Encodes a single token in packed mode. Array elements are always padded
+/// to 32 bytes.
+fn encode_packed_token(
+    kind: &ParamType,
+    token: &Token,
+    in_array: bool,
+    out: &mut Vec<u8>,
+) -> Result<(), AbiCodecError> {
+    let unsupported = || AbiCodecError::UnsupportedPackedType(kind.clone());
+    let word = |value: U256| {
+        let mut word = [0u8; 32];
+        value.to_big_endian(&mut word);
+        word
+    };
+
+    match (kind, token) {
+        (ParamType::Address, Token::Address(address)) => {
+            if in_array {
+                out.extend_from_slice(&[0u8; 12]);
+            }
+            out.extend_from_slice(address.as_bytes());
+        }
+        (ParamType::Bool, Token::Bool(value)) => {
+            if in_array {
+                out.extend_from_slice(&[0u8; 31]);
+            }
+            out.push(*value as u8);
+        }
+        (ParamType::Uint(bits), Token::Uint(value)) => {
+            if value.bits() > *bits {
+                return Err(AbiCodecError::TypeMismatch {
+                    kind: kind.clone(),
+                    token: token.clone(),
+                });
+            }
+            let size = if in_array { 32 } else { bits / 8 };
+            out.extend_from_slice(&word(*value)[32 - size..]);
+        }
+        (ParamType::Int(bits), Token::Int(value)) => {
+            let word = word(*value);
+            let size = bits / 8;
+            let sign = if word[32 - size] & 0x80 != 0 { 0xff } else { 0 };
+            if word[..32 - size].iter().any(|byte| *byte != sign) {
+                return Err(AbiCodecError::TypeMismatch {
+                    kind: kind.clone(),
+                    token: token.clone(),
+                });
+            }
+            let size = if in_array { 32 } else { size };
+            out.extend_from_slice(&word[32 - size..]);
+        }
+        (ParamType::FixedBytes(_), Token::FixedBytes(bytes)) => {
+            if in_array {
+                pad_right(bytes, out);
+            } else {
+                out.extend_from_slice(bytes);
+            }
+        }
+        (ParamType::Bytes, Token::Bytes(bytes)) if !in_array => out.extend_from_slice(bytes),
+        (ParamType::String, Token::String(value)) if !in_array => {
+            out.extend_from_slice(value.as_bytes())
+        }
+        (ParamType::Array(element), Token::Array(tokens))
+        | (ParamType::FixedArray(element, _), Token::FixedArray(tokens))
+            if !in_array =>
+        {
+            for token in tokens {
+                encode_packed_token(element, token, true, out)?;
+            }
+        }
+        _ => return Err(unsupported()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, I256};
+    use hex_literal::hex;
+
+    #[test]
+    fn encode_and_decode_function_call() {
+        let tokens = [
+            Token::Address(addr!("0x0123456789012345678901234567890123456789")),
+            Token::Uint(1_000.into()),
+        ];
+        let calldata = encode_function_call("transfer(address,uint256)", &tokens).unwrap();
+        assert_eq!(
+            calldata,
+            hex!(
+                "a9059cbb
+                 0000000000000000000000000123456789012345678901234567890123456789
+                 00000000000000000000000000000000000000000000000000000000000003e8"
+            )
+            .to_vec()
+        );
+        assert_eq!(
+            decode_function_call("transfer(address,uint256)", &calldata).unwrap(),
+            tokens
+        );
+        assert!(matches!(
+            decode_function_call("approve(address,uint256)", &calldata),
+            Err(AbiCodecError::SelectorMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn encode_function_call_checks_tokens() {
+        assert!(matches!(
+            encode_function_call("foo(uint256)", &[]),
+            Err(AbiCodecError::TokenCount {
+                expected: 1,
+                actual: 0
+            })
+        ));
+        assert!(matches!(
+            encode_function_call("foo(uint256)", &[Token::Bool(true)]),
+            Err(AbiCodecError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn encode_call_in_place() {
+        let selector = [1, 2, 3, 4];
+        let mut calldata = Vec::new();
+        for tokens in vec![
+            vec![],
+            vec![
+                Token::Address(Address::repeat_byte(0x11)),
+                Token::Int(I256::minus_one().into_raw()),
+                Token::Bool(true),
+                Token::FixedBytes(vec![0xff; 4]),
+                Token::Tuple(vec![
+                    Token::Uint(42.into()),
+                    Token::FixedArray(vec![Token::Bool(false); 2]),
+                ]),
+            ],
+            vec![
+                Token::Uint(1.into()),
+                Token::Tuple(vec![Token::String("dynamic".into())]),
+                Token::Array(vec![Token::Bytes(vec![1, 2, 3])]),
+            ],
+        ] {
+            let expected = [&selector[..], &encode(&tokens)].concat();
+            assert_eq!(encode_call(selector, &tokens), expected);
+
+            encode_call_into(selector, &tokens, &mut calldata);
+            assert_eq!(calldata, expected);
+        }
+    }
+
+    #[test]
+    fn decode_output() {
+        let data = encode(&[Token::Uint(42.into()), Token::Bool(true)]);
+        assert_eq!(
+            decode_function_output("foo()(uint256,bool)", &data).unwrap(),
+            vec![Token::Uint(42.into()), Token::Bool(true)]
+        );
+    }
+
+    #[test]
+    fn event_topic_ignores_indexed() {
+        assert_eq!(
+            event_topic("Transfer(address indexed,address indexed,uint256)").unwrap(),
+            H256(hex!(
+                "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+            ))
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_event_data() {
+        let signature = "Foo(address indexed,string indexed,uint256)";
+        let tokens = vec![
+            Token::Address(addr!("0x0123456789012345678901234567890123456789")),
+            Token::String("hello".into()),
+            Token::Uint(42.into()),
+        ];
+
+        let log = encode_event_data(signature, &tokens).unwrap();
+        assert_eq!(
+            log.topics,
+            vec![
+                event_topic(signature).unwrap(),
+                H256(hex!(
+                    "0000000000000000000000000123456789012345678901234567890123456789"
+                )),
+                H256(keccak256("hello")),
+            ]
+        );
+        assert_eq!(log.data, encode(&[Token::Uint(42.into())]));
+
+        assert_eq!(
+            decode_event_data(signature, log.clone()).unwrap(),
+            vec![
+                tokens[0].clone(),
+                Token::FixedBytes(keccak256("hello").to_vec()),
+                tokens[2].clone(),
+            ]
+        );
+        assert!(matches!(
+            decode_event_data("Bar(address indexed,string indexed,uint256)", log),
+            Err(AbiCodecError::TopicMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn encode_anonymous_event_data() {
+        let log =
+            encode_event_data("Foo(uint256 indexed) anonymous", &[Token::Uint(1.into())]).unwrap();
+        assert_eq!(log.topics, vec![H256::from_low_u64_be(1)]);
+        assert!(log.data.is_empty());
+    }
+
+    #[test]
+    fn encode_packed_values() {
+        // test vector from the Solidity documentation on non-standard packed
+        // mode: `abi.encodePacked(int16(-1), bytes1(0x42), uint16(0x03), "Hello, world!")`
+        let packed = encode_packed(
+            &parse_types("int16,bytes1,uint16,string").unwrap(),
+            &[
+                Token::Int(I256::from(-1).into_raw()),
+                Token::FixedBytes(vec![0x42]),
+                Token::Uint(3.into()),
+                Token::String("Hello, world!".into()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            packed,
+            hex!("ffff42000348656c6c6f2c20776f726c6421").to_vec()
+        );
+    }
+
+    #[test]
+    fn encode_packed_arrays_are_padded() {
+        let packed = encode_packed(
+            &[ParamType::Array(Box::new(ParamType::Uint(8)))],
+            &[Token::Array(vec![
+                Token::Uint(1.into()),
+                Token::Uint(2.into()),
+            ])],
+        )
+        .unwrap();
+        assert_eq!(packed.len(), 64);
+        assert_eq!(packed[31], 1);
+        assert_eq!(packed[63], 2);
+    }
+
+    #[test]
+    fn encode_packed_errors() {
+        assert!(matches!(
+            encode_packed(&[ParamType::Uint(8)], &[Token::Uint(256.into())]),
+            Err(AbiCodecError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            encode_packed(
+                &[ParamType::Tuple(vec![ParamType::Bool])],
+                &[Token::Tuple(vec![Token::Bool(true)])]
+            ),
+            Err(AbiCodecError::UnsupportedPackedType(_))
+        ));
+        assert!(matches!(
+            encode_packed(
+                &[ParamType::Array(Box::new(ParamType::String))],
+                &[Token::Array(vec![Token::String("a".into())])]
+            ),
+            Err(AbiCodecError::UnsupportedPackedType(_))
+        ));
+    }
+
+    #[test]
+    fn packed_builder() {
+        let packed = Packed::new()
+            .int(16, I256::from(-1))
+            .fixed_bytes([0x42])
+            .uint(16, 3.into())
+            .string("Hello, world!");
+        assert_eq!(
+            packed.encode().unwrap(),
+            hex!("ffff42000348656c6c6f2c20776f726c6421").to_vec()
+        );
+        assert_eq!(
+            packed.keccak256().unwrap(),
+            H256(keccak256(packed.encode().unwrap()))
+        );
+
+        assert!(matches!(
+            Packed::new().uint(8, 256.into()).encode(),
+            Err(AbiCodecError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn signed_message_hash() {
+        assert_eq!(
+            keccak256_concat(&[b"hello", b" ", b"world"]),
+            H256(keccak256("hello world"))
+        );
+        assert_eq!(
+            hash_message("Hello World"),
+            H256(hex!(
+                "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+            ))
+        );
+    }
+
+    #[test]
+    fn packed_hash() {
+        assert_eq!(
+            keccak256_packed(&[ParamType::String], &[Token::String("hello".into())]).unwrap(),
+            H256(keccak256("hello"))
+        );
+    }
+}
@@ -0,0 +1,493 @@
+//! Bounds checked decoding of ABI encoded data that never panics on malformed
+//! data, for example data returned by adversarial contracts.
+//!
+//! Values are decoded into tokens borrowing byte arrays and strings from the
+//! data, which are converted into owned tokens when needed.
+
+use crate::contract::BorrowedToken;
+use crate::errors::DecodeError;
+use ethcontract_common::abi::{Event as AbiEvent, ParamType};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::str;
+use web3::types::{Address, H256, U256};
+
+/// Options for decoding ABI encoded data.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DecodeOptions {
+    /// Reject strings that are not valid UTF-8 instead of replacing invalid
+    /// sequences with `U+FFFD` like `ethabi` does.
+    pub strict_utf8: bool,
+}
+
+impl DecodeOptions {
+    /// Sets whether strings that are not valid UTF-8 are rejected.
+    pub fn strict_utf8(mut self, value: bool) -> Self {
+        self.strict_utf8 = value;
+        self
+    }
+}
+
+/// Decodes the topics and data of a log for an event, returning the values of
+/// the event parameters in the order of the event ABI.
+pub(crate) fn decode_log<'a>(
+    event: &AbiEvent,
+    topics: &'a [H256],
+    data: &'a [u8],
+    options: DecodeOptions,
+) -> Result<Vec<BorrowedToken<'a>>, DecodeError> {
+    let topics = if event.anonymous {
+        topics
+    } else {
+        match topics.split_first() {
+            Some((signature, topics)) if *signature == event.signature() => topics,
+            _ => return Err(DecodeError::InvalidTopics),
+        }
+    };
+    if topics.len() != event.inputs.iter().filter(|input| input.indexed).count() {
+        return Err(DecodeError::InvalidTopics);
+    }
+
+    let decoder = Decoder::new(data, options);
+    let mut topics = topics.iter();
+    let mut offset = 0;
+    event
+        .inputs
+        .iter()
+        .map(|input| {
+            if input.indexed {
+                let topic = topics.next().ok_or(DecodeError::InvalidTopics)?.as_bytes();
+                match input.kind {
+                    ParamType::String
+                    | ParamType::Bytes
+                    | ParamType::Array(_)
+                    | ParamType::FixedArray(..)
+                    | ParamType::Tuple(_) => Ok(BorrowedToken::FixedBytes(topic)),
+                    _ => Ok(Decoder::new(topic, options)
+                        .decode_param(&input.kind, 0, 0)?
+                        .0),
+                }
+            } else {
+                let (token, next) = decoder.decode_param(&input.kind, 0, offset)?;
+                offset = next;
+                Ok(token)
+            }
+        })
+        .collect()
+}
+
+/// A decoder for ABI encoded data.
+///
+/// All offsets are absolute offsets in the data. The offsets of dynamic values
+/// are encoded relative to the start of their enclosing tuple or array, which
+/// is passed as the `base` offset.
+///
+/// Dynamic arrays can share their encoding, for example the elements of a
+/// `uint256[][]` can all point to the same inner array, so the number of
+/// decoded array elements is limited to the number of words of the data,
+/// which bounds the size of the decoded tokens by the size of the data.
+pub(crate) struct Decoder<'a> {
+    data: &'a [u8],
+    options: DecodeOptions,
+    /// The number of array elements decoded so far.
+    elements: Cell<usize>,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a new decoder for the specified data.
+    pub(crate) fn new(data: &'a [u8], options: DecodeOptions) -> Self {
+        Decoder {
+            data,
+            options,
+            elements: Cell::new(0),
+        }
+    }
+
+    /// Decodes consecutive parameters of the specified types, returning the
+    /// tokens and the offset following the last parameter.
+    pub(crate) fn decode_params(
+        &self,
+        kinds: &[ParamType],
+        base: usize,
+        mut offset: usize,
+    ) -> Result<(Vec<BorrowedToken<'a>>, usize), DecodeError> {
+        let mut tokens = Vec::with_capacity(kinds.len());
+        for kind in kinds {
+            let (token, next) = self.decode_param(kind, base, offset)?;
+            tokens.push(token);
+            offset = next;
+        }
+        Ok((tokens, offset))
+    }
+
+    /// Decodes a parameter at an offset of the data, returning the token and
+    /// the offset of the next parameter.
+    pub(crate) fn decode_param(
+        &self,
+        kind: &ParamType,
+        base: usize,
+        offset: usize,
+    ) -> Result<(BorrowedToken<'a>, usize), DecodeError> {
+        let next = offset.saturating_add(32);
+        let token = match kind {
+            ParamType::Address => {
+                BorrowedToken::Address(Address::from_slice(&self.take(offset, 32)?[12..]))
+            }
+            ParamType::Int(_) => BorrowedToken::Int(U256::from_big_endian(self.take(offset, 32)?)),
+            ParamType::Uint(_) => {
+                BorrowedToken::Uint(U256::from_big_endian(self.take(offset, 32)?))
+            }
+            ParamType::Bool => {
+                let word = self.take(offset, 32)?;
+                if word[..31].iter().any(|byte| *byte != 0) {
+                    return Err(DecodeError::InvalidBool(offset));
+                }
+                BorrowedToken::Bool(word[31] == 1)
+            }
+            ParamType::FixedBytes(len) => BorrowedToken::FixedBytes(self.take(offset, *len)?),
+            ParamType::Bytes => BorrowedToken::Bytes(self.take_dynamic(base, offset)?.1),
+            ParamType::String => {
+                let (start, bytes) = self.take_dynamic(base, offset)?;
+                BorrowedToken::String(if self.options.strict_utf8 {
+                    Cow::Borrowed(
+                        str::from_utf8(bytes).map_err(|_| DecodeError::InvalidUtf8(start))?,
+                    )
+                } else {
+                    String::from_utf8_lossy(bytes)
+                })
+            }
+            ParamType::Array(kind) => {
+                let start = self.take_offset(base, offset)?;
+                let len = self.take_usize(start)?;
+                let tail = start.saturating_add(32);
+                // NOTE: Every element takes at least one word, so reject
+                //   malicious lengths that exceed the available data instead
+                //   of looping over them.
+                if len > self.data.len().saturating_sub(tail) / 32 {
+                    return Err(DecodeError::InvalidLength(start));
+                }
+                self.count_elements(start, len)?;
+                BorrowedToken::Array(self.decode_elements(kind, len, tail, tail)?.0)
+            }
+            ParamType::FixedArray(kind, len) => {
+                if kind.is_dynamic() {
+                    let start = self.take_offset(base, offset)?;
+                    self.count_elements(start, *len)?;
+                    BorrowedToken::FixedArray(self.decode_elements(kind, *len, start, start)?.0)
+                } else {
+                    let (tokens, next) = self.decode_elements(kind, *len, base, offset)?;
+                    return Ok((BorrowedToken::FixedArray(tokens), next));
+                }
+            }
+            ParamType::Tuple(kinds) => {
+                if kind.is_dynamic() {
+                    let start = self.take_offset(base, offset)?;
+                    BorrowedToken::Tuple(self.decode_params(kinds, start, start)?.0)
+                } else {
+                    let (tokens, next) = self.decode_params(kinds, base, offset)?;
+                    return Ok((BorrowedToken::Tuple(tokens), next));
+                }
+            }
+        };
+        Ok((token, next))
+    }
+
+    /// Decodes consecutive parameters of the same type, returning the tokens
+    /// and the offset following the last parameter.
+    fn decode_elements(
+        &self,
+        kind: &ParamType,
+        len: usize,
+        base: usize,
+        mut offset: usize,
+    ) -> Result<(Vec<BorrowedToken<'a>>, usize), DecodeError> {
+        let mut tokens = Vec::with_capacity(len.min(self.data.len() / 32));
+        for _ in 0..len {
+            let (token, next) = self.decode_param(kind, base, offset)?;
+            tokens.push(token);
+            offset = next;
+        }
+        Ok((tokens, offset))
+    }
+
+    /// Counts the elements of an array at an offset, failing if the arrays of
+    /// the data have more elements in total than the data has words.
+    fn count_elements(&self, offset: usize, len: usize) -> Result<(), DecodeError> {
+        let elements = self.elements.get().saturating_add(len);
+        if elements > self.data.len() / 32 {
+            return Err(DecodeError::TooManyElements(offset));
+        }
+        self.elements.set(elements);
+        Ok(())
+    }
+
+    /// Returns `len` bytes of the data at an offset.
+    fn take(&self, offset: usize, len: usize) -> Result<&'a [u8], DecodeError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(DecodeError::OutOfBounds {
+                offset,
+                len,
+                size: self.data.len(),
+            })
+    }
+
+    /// Decodes a 32-byte word at an offset as an offset or length.
+    fn take_usize(&self, offset: usize) -> Result<usize, DecodeError> {
+        let word = self.take(offset, 32)?;
+        if word[..28].iter().any(|byte| *byte != 0) {
+            return Err(DecodeError::InvalidLength(offset));
+        }
+        Ok(word[28..]
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as usize))
+    }
+
+    /// Decodes the absolute offset of a dynamic value whose relative offset is
+    /// at the specified offset.
+    fn take_offset(&self, base: usize, offset: usize) -> Result<usize, DecodeError> {
+        Ok(base.saturating_add(self.take_usize(offset)?))
+    }
+
+    /// Decodes the bytes of a dynamic byte array or string whose relative
+    /// offset is at the specified offset, returning the offset of the value
+    /// along with its bytes.
+    fn take_dynamic(&self, base: usize, offset: usize) -> Result<(usize, &'a [u8]), DecodeError> {
+        let start = self.take_offset(base, offset)?;
+        let len = self.take_usize(start)?;
+        Ok((start, self.take(start.saturating_add(32), len)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract_common::abi::{encode, EventParam, RawLog, Token};
+
+    fn event(inputs: Vec<(ParamType, bool)>) -> AbiEvent {
+        AbiEvent {
+            name: "Foo".into(),
+            inputs: inputs
+                .into_iter()
+                .enumerate()
+                .map(|(i, (kind, indexed))| EventParam {
+                    name: format!("p{}", i),
+                    kind,
+                    indexed,
+                })
+                .collect(),
+            anonymous: false,
+        }
+    }
+
+    #[test]
+    fn decodes_like_ethabi() {
+        let event = event(vec![
+            (ParamType::Address, true),
+            (ParamType::String, true),
+            (ParamType::Bytes, false),
+            (ParamType::Array(Box::new(ParamType::String)), false),
+            (
+                ParamType::Tuple(vec![ParamType::Uint(256), ParamType::Bytes]),
+                false,
+            ),
+            (ParamType::FixedArray(Box::new(ParamType::Int(8)), 2), false),
+            (ParamType::FixedBytes(4), false),
+            (ParamType::Bool, false),
+        ]);
+        let topics = vec![
+            event.signature(),
+            H256::from_low_u64_be(42),
+            H256::repeat_byte(1),
+        ];
+        let data = encode(&[
+            Token::Bytes(b"bytes".to_vec()),
+            Token::Array(vec![
+                Token::String("a".into()),
+                Token::String("\u{1f980}".into()),
+            ]),
+            Token::Tuple(vec![Token::Uint(7.into()), Token::Bytes(vec![1, 2, 3])]),
+            Token::FixedArray(vec![Token::Int(U256::MAX), Token::Int(1.into())]),
+            Token::FixedBytes(vec![0xde, 0xad, 0xbe, 0xef]),
+            Token::Bool(true),
+        ]);
+
+        let tokens = decode_log(&event, &topics, &data, DecodeOptions::default()).unwrap();
+        assert_eq!(tokens[1].as_bytes(), Some(topics[2].as_bytes()));
+        assert_eq!(tokens[2].as_bytes(), Some(&b"bytes"[..]));
+        assert!(matches!(&tokens[3], BorrowedToken::Array(strings)
+            if matches!(&strings[1], BorrowedToken::String(Cow::Borrowed("\u{1f980}")))));
+
+        let expected = event
+            .parse_log(RawLog {
+                topics: topics.clone(),
+                data: data.clone(),
+            })
+            .unwrap()
+            .params
+            .into_iter()
+            .map(|param| param.value)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens
+                .into_iter()
+                .map(BorrowedToken::into_token)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn invalid_strings_are_decoded_lossily() {
+        let event = event(vec![(ParamType::String, false)]);
+        let data = encode(&[Token::Bytes(vec![b'a', 0xff])]);
+        let topics = [event.signature()];
+        let tokens = decode_log(&event, &topics, &data, DecodeOptions::default()).unwrap();
+        assert_eq!(tokens[0].as_str(), Some("a\u{fffd}"));
+    }
+
+    #[test]
+    fn malformed_logs_are_rejected() {
+        let event = event(vec![(ParamType::Address, true), (ParamType::Bytes, false)]);
+        let signature = event.signature();
+        let topic = H256::zero();
+        let data = encode(&[Token::Bytes(vec![1; 40])]);
+
+        for (topics, data) in vec![
+            (vec![signature, topic], &data[..96]),
+            (vec![signature], &data[..]),
+            (vec![H256::zero(), topic], &data[..]),
+            (vec![signature, topic], &[0xff; 64][..]),
+        ] {
+            assert!(decode_log(&event, &topics, data, DecodeOptions::default()).is_err());
+        }
+    }
+    #[test]
+    fn decodes_values_like_ethabi() {
+        let kinds = [
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::String,
+            ]))),
+            ParamType::FixedArray(Box::new(ParamType::Bytes), 2),
+            ParamType::Tuple(vec![ParamType::Bool, ParamType::FixedBytes(2)]),
+        ];
+        let tokens = vec![
+            Token::Uint(42.into()),
+            Token::Array(vec![
+                Token::Tuple(vec![
+                    Token::Address(Address::repeat_byte(1)),
+                    Token::String("a".into()),
+                ]),
+                Token::Tuple(vec![
+                    Token::Address(Address::repeat_byte(2)),
+                    Token::String("b".into()),
+                ]),
+            ]),
+            Token::FixedArray(vec![Token::Bytes(vec![1]), Token::Bytes(vec![2, 3])]),
+            Token::Tuple(vec![Token::Bool(true), Token::FixedBytes(vec![4, 5])]),
+        ];
+        let data = encode(&tokens);
+
+        assert_eq!(crate::abi::try_decode(&kinds, &data).unwrap(), tokens);
+        assert_eq!(
+            ethcontract_common::abi::decode(&kinds, &data).unwrap(),
+            tokens
+        );
+    }
+
+    #[test]
+    fn malformed_data_is_rejected() {
+        let decode = |kinds: &[ParamType], data: &[u8]| crate::abi::try_decode(kinds, data);
+        let word = |value: u64| H256::from_low_u64_be(value).as_bytes().to_vec();
+
+        assert_eq!(
+            decode(&[ParamType::Uint(256), ParamType::Uint(256)], &word(1)[..]),
+            Err(DecodeError::OutOfBounds {
+                offset: 32,
+                len: 32,
+                size: 32,
+            })
+        );
+        assert_eq!(
+            decode(&[ParamType::Bool], &word(2)[..]),
+            Ok(vec![Token::Bool(false)])
+        );
+        assert_eq!(
+            decode(&[ParamType::Bool], &[0xff; 32]),
+            Err(DecodeError::InvalidBool(0))
+        );
+        assert_eq!(
+            decode(&[ParamType::Bytes], &[0xff; 32]),
+            Err(DecodeError::InvalidLength(0))
+        );
+
+        // NOTE: `ethabi` panics on out of bounds offsets of dynamic fixed
+        //   size arrays.
+        assert_eq!(
+            decode(
+                &[ParamType::FixedArray(Box::new(ParamType::String), 1)],
+                &word(0x1000)
+            ),
+            Err(DecodeError::OutOfBounds {
+                offset: 0x1000,
+                len: 32,
+                size: 32,
+            })
+        );
+
+        // an array length that exceeds the data is rejected without looping
+        // over its elements
+        let data = [word(0x20), word(u32::MAX as _)].concat();
+        assert_eq!(
+            decode(
+                &[ParamType::Array(Box::new(ParamType::FixedArray(
+                    Box::new(ParamType::Uint(256)),
+                    0
+                )))],
+                &data
+            ),
+            Err(DecodeError::InvalidLength(0x20))
+        );
+
+        // nested arrays whose elements all point to the same inner array are
+        // rejected instead of decoding quadratically many tokens
+        let len = 64;
+        let data = [
+            word(0x20),
+            word(len),
+            (0..len).flat_map(|_| word(len * 32)).collect(),
+            word(len),
+            (0..len).flat_map(word).collect(),
+        ]
+        .concat();
+        assert_eq!(
+            decode(
+                &[ParamType::Array(Box::new(ParamType::Array(Box::new(
+                    ParamType::Uint(256)
+                ))))],
+                &data
+            ),
+            Err(DecodeError::TooManyElements(0x40 + len as usize * 32))
+        );
+    }
+
+    #[test]
+    fn strict_utf8_strings() {
+        let data = encode(&[Token::Bytes(vec![b'a', 0xff])]);
+        assert_eq!(
+            crate::abi::try_decode(&[ParamType::String], &data).unwrap(),
+            vec![Token::String("a\u{fffd}".into())]
+        );
+        assert_eq!(
+            crate::abi::try_decode_with(
+                &[ParamType::String],
+                &data,
+                DecodeOptions::default().strict_utf8(true)
+            ),
+            Err(DecodeError::InvalidUtf8(0x20))
+        );
+    }
+}
@@ -0,0 +1,666 @@
+//! Best-effort decoding of calldata and event logs for unknown function
+//! selectors and event topics using signature databases such as
+//! [4byte](https://www.4byte.directory) and
+//! [openchain](https://openchain.xyz/signatures).
+//!
+//! Since selectors and topics are hashes, a signature database may return
+//! several candidate signatures for the same selector, and events signatures
+//! in these databases do not specify which parameters are indexed. Decoding
+//! therefore returns every candidate that is consistent with the data.
+
+use crate::contract::RawLog;
+use crate::errors::{ParseSignatureError, SignatureLookupError};
+use ethcontract_common::abi::{decode, encode, Event, ParamType, Token};
+use ethcontract_common::abiext::FunctionExt;
+use ethcontract_common::hash::H32;
+use ethcontract_common::signature::{parse_event, parse_function};
+use ethcontract_common::Abi;
+use futures::future::{self, BoxFuture, FutureExt};
+use std::collections::HashMap;
+use std::slice;
+use web3::types::H256;
+
+/// The maximum number of event parameters for which indexed parameters are
+/// guessed when the signature does not specify them.
+const MAX_GUESSED_EVENT_PARAMS: usize = 12;
+
+/// A database of human readable function and event signatures, indexed by
+/// function selector and event topic.
+pub trait SignatureDatabase: Send + Sync {
+    /// Returns the function signatures matching a selector.
+    fn functions(&self, selector: H32) -> BoxFuture<'_, Result<Vec<String>, SignatureLookupError>>;
+
+    /// Returns the event signatures matching a topic.
+    fn events(&self, topic: H256) -> BoxFuture<'_, Result<Vec<String>, SignatureLookupError>>;
+}
+
+/// A signature database that is kept in memory.
+#[derive(Clone, Debug, Default)]
+pub struct LocalSignatureDatabase {
+    functions: HashMap<H32, Vec<String>>,
+    events: HashMap<H256, Vec<String>>,
+}
+
+impl LocalSignatureDatabase {
+    /// Creates a new empty signature database.
+    pub fn new() -> Self {
+        LocalSignatureDatabase::default()
+    }
+
+    /// Adds a function signature to the database, for example
+    /// `transfer(address,uint256)`.
+    pub fn add_function(&mut self, signature: &str) -> Result<(), ParseSignatureError> {
+        let selector = parse_function(signature)?.selector();
+        insert(self.functions.entry(selector).or_default(), signature);
+        Ok(())
+    }
+
+    /// Adds an event signature to the database. Indexed parameters may
+    /// optionally be specified, for example
+    /// `Transfer(address indexed,address indexed,uint256)`. Anonymous events
+    /// are ignored since they don't have a topic.
+    pub fn add_event(&mut self, signature: &str) -> Result<(), ParseSignatureError> {
+        let event = parse_event(signature)?;
+        if !event.anonymous {
+            insert(self.events.entry(event.signature()).or_default(), signature);
+        }
+        Ok(())
+    }
+
+    /// Adds all function and event signatures from a contract ABI.
+    pub fn add_abi(&mut self, abi: &Abi) {
+        for function in abi.functions() {
+            insert(
+                self.functions.entry(function.selector()).or_default(),
+                &function.abi_signature(),
+            );
+        }
+        for event in abi.events().filter(|event| !event.anonymous) {
+            insert(
+                self.events.entry(event.signature()).or_default(),
+                &event_signature(event),
+            );
+        }
+    }
+}
+
+fn insert(signatures: &mut Vec<String>, signature: &str) {
+    if !signatures.iter().any(|existing| existing == signature) {
+        signatures.push(signature.to_owned());
+    }
+}
+
+impl SignatureDatabase for LocalSignatureDatabase {
+    fn functions(&self, selector: H32) -> BoxFuture<'_, Result<Vec<String>, SignatureLookupError>> {
+        let signatures = self.functions.get(&selector).cloned().unwrap_or_default();
+        future::ready(Ok(signatures)).boxed()
+    }
+
+    fn events(&self, topic: H256) -> BoxFuture<'_, Result<Vec<String>, SignatureLookupError>> {
+        let signatures = self.events.get(&topic).cloned().unwrap_or_default();
+        future::ready(Ok(signatures)).boxed()
+    }
+}
+
+/// A decoded function call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedCall {
+    /// The signature of the function.
+    pub signature: String,
+    /// The decoded function parameters.
+    pub params: Vec<Token>,
+}
+
+/// A decoded event log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedEvent {
+    /// The signature of the event, with indexed parameters marked.
+    pub signature: String,
+    /// The decoded event parameters in declaration order. Indexed dynamic
+    /// parameters are decoded as `Token::FixedBytes` of their hash.
+    pub params: Vec<Token>,
+}
+
+/// Decodes calldata and event logs with signatures from a signature database.
+#[derive(Clone, Debug)]
+pub struct SignatureDecoder<D> {
+    database: D,
+}
+
+impl<D: SignatureDatabase> SignatureDecoder<D> {
+    /// Creates a new decoder using the specified signature database.
+    pub fn new(database: D) -> Self {
+        SignatureDecoder { database }
+    }
+
+    /// Returns a reference to the underlying signature database.
+    pub fn database(&self) -> &D {
+        &self.database
+    }
+
+    /// Decodes function calldata, returning all signatures from the database
+    /// that the calldata can be decoded with.
+    pub async fn decode_call(
+        &self,
+        calldata: &[u8],
+    ) -> Result<Vec<DecodedCall>, SignatureLookupError> {
+        if calldata.len() < 4 {
+            return Ok(Vec::new());
+        }
+
+        let mut selector = H32::default();
+        selector.copy_from_slice(&calldata[..4]);
+        let signatures = self.database.functions(selector).await?;
+
+        Ok(signatures
+            .into_iter()
+            .filter_map(|signature| {
+                let function = parse_function(&signature).ok()?;
+                let kinds = function
+                    .inputs
+                    .iter()
+                    .map(|input| input.kind.clone())
+                    .collect::<Vec<_>>();
+                let params = decode_exact(&kinds, &calldata[4..])?;
+                Some(DecodedCall { signature, params })
+            })
+            .collect())
+    }
+
+    /// Decodes an event log, returning all signatures from the database that
+    /// the log can be decoded with.
+    ///
+    /// When a signature does not specify indexed parameters, every possible
+    /// assignment of indexed parameters that is consistent with the log is
+    /// returned.
+    pub async fn decode_event(
+        &self,
+        log: &RawLog,
+    ) -> Result<Vec<DecodedEvent>, SignatureLookupError> {
+        let topic = match log.topics.first() {
+            Some(topic) => *topic,
+            None => return Ok(Vec::new()),
+        };
+        let signatures = self.database.events(topic).await?;
+
+        let mut decoded = Vec::new();
+        for signature in signatures {
+            let event = match parse_event(&signature) {
+                Ok(event) if !event.anonymous => event,
+                _ => continue,
+            };
+
+            if event.inputs.iter().any(|input| input.indexed) {
+                decoded.extend(decode_log(&event, log));
+            } else {
+                decoded.extend(
+                    guess_indexed(&event, log.topics.len() - 1)
+                        .filter_map(|event| decode_log(&event, log)),
+                );
+            }
+        }
+
+        Ok(decoded)
+    }
+}
+
+/// Returns all variations of an event with the specified number of indexed
+/// parameters.
+fn guess_indexed(event: &Event, indexed: usize) -> impl Iterator<Item = Event> + '_ {
+    let len = event.inputs.len();
+    let masks = if len <= MAX_GUESSED_EVENT_PARAMS && indexed <= len {
+        0..(1u32 << len)
+    } else {
+        0..0
+    };
+
+    masks
+        .filter(move |mask| mask.count_ones() as usize == indexed)
+        .map(move |mask| {
+            let mut event = event.clone();
+            for (i, input) in event.inputs.iter_mut().enumerate() {
+                input.indexed = mask & (1 << i) != 0;
+            }
+            event
+        })
+}
+
+/// Decodes a log with an event, verifying that the log is consistent with the
+/// parameter types of the event.
+fn decode_log(event: &Event, log: &RawLog) -> Option<DecodedEvent> {
+    let indexed = event.inputs.iter().filter(|input| input.indexed);
+    if indexed.clone().count() != log.topics.len() - 1 {
+        return None;
+    }
+
+    let data_kinds = event
+        .inputs
+        .iter()
+        .filter(|input| !input.indexed)
+        .map(|input| input.kind.clone())
+        .collect::<Vec<_>>();
+    let mut data = decode_exact(&data_kinds, &log.data)?.into_iter();
+    let mut topics = log.topics[1..].iter();
+
+    let params = event
+        .inputs
+        .iter()
+        .map(|input| {
+            if !input.indexed {
+                return data.next();
+            }
+
+            let topic = topics.next()?;
+            match input.kind {
+                ParamType::String
+                | ParamType::Bytes
+                | ParamType::Array(_)
+                | ParamType::FixedArray(..)
+                | ParamType::Tuple(_) => Some(Token::FixedBytes(topic.as_bytes().to_vec())),
+                _ => decode_exact(slice::from_ref(&input.kind), topic.as_bytes())?.pop(),
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(DecodedEvent {
+        signature: event_signature(event),
+        params,
+    })
+}
+
+/// Decodes data, only succeeding if the data is the canonical encoding of the
+/// decoded tokens. This filters out signatures that happen to decode without
+/// errors but don't actually match the data.
+fn decode_exact(kinds: &[ParamType], data: &[u8]) -> Option<Vec<Token>> {
+    let tokens = decode(kinds, data).ok()?;
+    if encode(&tokens) == data {
+        Some(tokens)
+    } else {
+        None
+    }
+}
+
+/// Formats an event signature with indexed parameters marked.
+fn event_signature(event: &Event) -> String {
+    let inputs = event
+        .inputs
+        .iter()
+        .map(|input| {
+            if input.indexed {
+                format!("{} indexed", input.kind)
+            } else {
+                input.kind.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    format!("{}({})", event.name, inputs.join(","))
+}
+
+#[cfg(feature = "signature-db")]
+pub use self::remote::RemoteSignatureDatabase;
+
+#[cfg(feature = "signature-db")]
+mod remote {
+    use super::SignatureDatabase;
+    use crate::errors::SignatureLookupError;
+    use ethcontract_common::hash::H32;
+    use futures::future::{BoxFuture, FutureExt};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use web3::types::H256;
+
+    const OPENCHAIN_URL: &str = "https://api.openchain.xyz/signature-database/v1";
+    const FOUR_BYTE_URL: &str = "https://www.4byte.directory/api/v1";
+
+    /// A signature database that is queried over HTTP. Both the
+    /// [openchain](https://openchain.xyz/signatures) and
+    /// [4byte](https://www.4byte.directory) APIs are supported.
+    #[derive(Clone, Debug)]
+    pub struct RemoteSignatureDatabase {
+        client: reqwest::Client,
+        api: Api,
+        url: String,
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum Api {
+        OpenChain,
+        FourByte,
+    }
+
+    impl RemoteSignatureDatabase {
+        /// Creates a signature database using the openchain API.
+        pub fn openchain() -> Self {
+            RemoteSignatureDatabase::new(Api::OpenChain, OPENCHAIN_URL)
+        }
+
+        /// Creates a signature database using the 4byte API.
+        pub fn four_byte() -> Self {
+            RemoteSignatureDatabase::new(Api::FourByte, FOUR_BYTE_URL)
+        }
+
+        fn new(api: Api, url: &str) -> Self {
+            RemoteSignatureDatabase {
+                client: reqwest::Client::new(),
+                api,
+                url: url.to_owned(),
+            }
+        }
+
+        /// Sets the base URL of the API, for example for self-hosted
+        /// instances.
+        pub fn with_url(mut self, url: impl Into<String>) -> Self {
+            self.url = url.into();
+            self
+        }
+
+        /// Sets the HTTP client used for making requests.
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.client = client;
+            self
+        }
+
+        async fn lookup(
+            &self,
+            kind: Kind,
+            hash: &[u8],
+        ) -> Result<Vec<String>, SignatureLookupError> {
+            let hash = format!("0x{}", hex::encode(hash));
+            let (url, query) = match (self.api, kind) {
+                (Api::OpenChain, _) => (format!("{}/lookup", self.url), kind.openchain_key()),
+                (Api::FourByte, Kind::Function) => {
+                    (format!("{}/signatures/", self.url), "hex_signature")
+                }
+                (Api::FourByte, Kind::Event) => {
+                    (format!("{}/event-signatures/", self.url), "hex_signature")
+                }
+            };
+
+            let response = self
+                .client
+                .get(&url)
+                .query(&[(query, &hash)])
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+
+            match self.api {
+                Api::OpenChain => parse_openchain(&response, kind, &hash),
+                Api::FourByte => parse_four_byte(&response),
+            }
+        }
+    }
+
+    impl SignatureDatabase for RemoteSignatureDatabase {
+        fn functions(
+            &self,
+            selector: H32,
+        ) -> BoxFuture<'_, Result<Vec<String>, SignatureLookupError>> {
+            async move { self.lookup(Kind::Function, &selector).await }.boxed()
+        }
+
+        fn events(&self, topic: H256) -> BoxFuture<'_, Result<Vec<String>, SignatureLookupError>> {
+            async move { self.lookup(Kind::Event, topic.as_bytes()).await }.boxed()
+        }
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    enum Kind {
+        Function,
+        Event,
+    }
+
+    impl Kind {
+        fn openchain_key(self) -> &'static str {
+            match self {
+                Kind::Function => "function",
+                Kind::Event => "event",
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct OpenChainResponse {
+        ok: bool,
+        #[serde(default)]
+        error: Option<String>,
+        #[serde(default)]
+        result: HashMap<String, HashMap<String, Option<Vec<OpenChainSignature>>>>,
+    }
+
+    #[derive(Deserialize)]
+    struct OpenChainSignature {
+        name: String,
+    }
+
+    fn parse_openchain(
+        response: &str,
+        kind: Kind,
+        hash: &str,
+    ) -> Result<Vec<String>, SignatureLookupError> {
+        let mut response = serde_json::from_str::<OpenChainResponse>(response)
+            .map_err(|err| SignatureLookupError::InvalidResponse(err.to_string()))?;
+        if !response.ok {
+            return Err(SignatureLookupError::InvalidResponse(
+                response.error.unwrap_or_default(),
+            ));
+        }
+
+        Ok(response
+            .result
+            .remove(kind.openchain_key())
+            .and_then(|mut signatures| signatures.remove(hash))
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|signature| signature.name)
+            .collect())
+    }
+
+    #[derive(Deserialize)]
+    struct FourByteResponse {
+        results: Vec<FourByteSignature>,
+    }
+
+    #[derive(Deserialize)]
+    struct FourByteSignature {
+        id: u64,
+        text_signature: String,
+    }
+
+    fn parse_four_byte(response: &str) -> Result<Vec<String>, SignatureLookupError> {
+        let mut response = serde_json::from_str::<FourByteResponse>(response)
+            .map_err(|err| SignatureLookupError::InvalidResponse(err.to_string()))?;
+
+        // NOTE: Signatures are submitted by anyone, so colliding signatures
+        //   are often spam submitted after the original, sort them so that
+        //   the oldest ones come first.
+        response.results.sort_by_key(|signature| signature.id);
+        Ok(response
+            .results
+            .into_iter()
+            .map(|signature| signature.text_signature)
+            .collect())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parse_openchain_response() {
+            let response = r#"{
+                "ok": true,
+                "result": {
+                    "event": {},
+                    "function": {
+                        "0xa9059cbb": [
+                            { "name": "transfer(address,uint256)", "filtered": false }
+                        ],
+                        "0x00000000": null
+                    }
+                }
+            }"#;
+
+            assert_eq!(
+                parse_openchain(response, Kind::Function, "0xa9059cbb").unwrap(),
+                vec!["transfer(address,uint256)"],
+            );
+            assert!(parse_openchain(response, Kind::Function, "0x00000000")
+                .unwrap()
+                .is_empty());
+            assert!(parse_openchain(response, Kind::Event, "0xa9059cbb")
+                .unwrap()
+                .is_empty());
+            assert!(parse_openchain(
+                r#"{ "ok": false, "error": "invalid hash" }"#,
+                Kind::Function,
+                "0x00"
+            )
+            .is_err());
+        }
+
+        #[test]
+        fn parse_four_byte_response() {
+            let response = r#"{
+                "count": 2,
+                "results": [
+                    { "id": 2, "text_signature": "many_msg_babbage(bytes1)" },
+                    { "id": 1, "text_signature": "transfer(address,uint256)" }
+                ]
+            }"#;
+
+            assert_eq!(
+                parse_four_byte(response).unwrap(),
+                vec!["transfer(address,uint256)", "many_msg_babbage(bytes1)"],
+            );
+            assert!(parse_four_byte("{}").is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::{encode_event_data, encode_function_call};
+    use futures::executor::block_on;
+    use web3::types::{Address, U256};
+
+    fn decoder() -> SignatureDecoder<LocalSignatureDatabase> {
+        let mut database = LocalSignatureDatabase::new();
+        database.add_function("transfer(address,uint256)").unwrap();
+        database.add_function("transfer(address,uint256)").unwrap();
+        database
+            .add_event("Transfer(address,address,uint256)")
+            .unwrap();
+        database
+            .add_event("Approval(address indexed,address indexed,uint256)")
+            .unwrap();
+        SignatureDecoder::new(database)
+    }
+
+    #[test]
+    fn decode_call() {
+        let decoder = decoder();
+        let params = vec![
+            Token::Address(Address::repeat_byte(1)),
+            Token::Uint(42.into()),
+        ];
+        let calldata = encode_function_call("transfer(address,uint256)", &params).unwrap();
+
+        assert_eq!(
+            block_on(decoder.decode_call(&calldata)).unwrap(),
+            vec![DecodedCall {
+                signature: "transfer(address,uint256)".to_owned(),
+                params,
+            }],
+        );
+
+        // unknown selector, truncated and malformed calldata
+        assert!(block_on(decoder.decode_call(&[0; 36])).unwrap().is_empty());
+        assert!(block_on(decoder.decode_call(&calldata[..3]))
+            .unwrap()
+            .is_empty());
+        assert!(block_on(decoder.decode_call(&calldata[..32]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn decode_event_with_indexed_params() {
+        let decoder = decoder();
+        let params = vec![
+            Token::Address(Address::repeat_byte(1)),
+            Token::Address(Address::repeat_byte(2)),
+            Token::Uint(42.into()),
+        ];
+        let signature = "Approval(address indexed,address indexed,uint256)";
+        let log = encode_event_data(signature, &params).unwrap();
+
+        assert_eq!(
+            block_on(decoder.decode_event(&log)).unwrap(),
+            vec![DecodedEvent {
+                signature: signature.to_owned(),
+                params,
+            }],
+        );
+    }
+
+    #[test]
+    fn decode_event_guesses_indexed_params() {
+        let decoder = decoder();
+        let params = vec![
+            Token::Address(Address::repeat_byte(1)),
+            Token::Address(Address::repeat_byte(2)),
+            Token::Uint(U256::MAX),
+        ];
+        let log = encode_event_data("Transfer(address indexed,address indexed,uint256)", &params)
+            .unwrap();
+
+        // NOTE: The `uint256` value cannot be an address, so the only
+        //   consistent assignment of indexed parameters is the correct one.
+        assert_eq!(
+            block_on(decoder.decode_event(&log)).unwrap(),
+            vec![DecodedEvent {
+                signature: "Transfer(address indexed,address indexed,uint256)".to_owned(),
+                params,
+            }],
+        );
+
+        assert!(block_on(decoder.decode_event(&RawLog {
+            topics: vec![H256::zero()],
+            data: vec![],
+        }))
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn local_database_from_abi() {
+        let abi = Abi::load(
+            r#"[{
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "indexed": false }
+                ],
+                "anonymous": false
+            }]"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut database = LocalSignatureDatabase::new();
+        database.add_abi(&abi);
+        let topic = abi.event("Transfer").unwrap().signature();
+
+        assert_eq!(
+            block_on(database.events(topic)).unwrap(),
+            vec!["Transfer(address indexed,address indexed,uint256)"],
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! Deterministic generators of arbitrary ABI values for property tests and
+//! fuzzing of contract interaction logic.
+//!
+//! Values are generated from a seeded random number generator, so failing
+//! property tests can be reproduced from their seed. Integers are biased
+//! towards edge cases such as zero and the minimum and maximum values of their
+//! ABI type, since these tend to uncover overflow and rounding bugs:
+//!
+//! ```
+//! use ethcontract::arbitrary::{self, Arbitrary};
+//! use ethcontract::{Address, U256};
+//!
+//! let mut rng = arbitrary::rng(42);
+//! for _ in 0..100 {
+//!     let (to, amount) = <(Address, U256)>::arbitrary(&mut rng);
+//!     // check the contract interaction logic with `to` and `amount`...
+//! #   let _ = (to, amount);
+//! }
+//! ```
+//!
+//! Event data types of generated bindings implement [`Arbitrary`] when the
+//! bindings are generated with the `arbitrary_impls` option.
+//!
+//! Custom [`Tokenize`] implementations can be checked for conformance with
+//! the ABI encoding with [`check_roundtrip`], which verifies that arbitrary
+//! values survive encoding and decoding:
+//!
+//! ```
+//! use ethcontract::arbitrary;
+//! use ethcontract::common::abi::ParamType;
+//! use ethcontract::Address;
+//!
+//! let kind = ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(64)]);
+//! arbitrary::check_roundtrip::<(Address, u64)>(&kind, 42, 100);
+//! ```
+//!
+//! The [`fuzz_decode`] and [`fuzz_roundtrip`] functions are fuzz targets for
+//! the ABI encoder and decoder that can be driven by a fuzzer such as
+//! `cargo fuzz` with arbitrary input bytes.
+//!
+//! This module is only available with the `test-helpers` feature.
+
+use crate::abi::{self, try_decode};
+use crate::tokens::{Bytes, Hash, Tokenize};
+use crate::I256;
+use arrayvec::ArrayVec;
+use ethcontract_common::abi::{ParamType, Token};
+use rand::rngs::StdRng;
+use std::fmt::Debug;
+use std::slice;
+use web3::types::{Address, H256, U256};
+
+pub use rand::{self, Rng, SeedableRng};
+
+/// The maximum length of generated dynamic arrays.
+const MAX_ARRAY_LEN: usize = 8;
+
+/// The maximum length of generated byte arrays and strings.
+const MAX_BYTES_LEN: usize = 64;
+
+/// The probability of generating an edge case value for integers.
+const EDGE_CASE_PROBABILITY: f64 = 0.125;
+
+/// Creates a deterministic random number generator from a seed.
+///
+/// Note that the generated values for a seed may change between versions of
+/// the `rand` crate.
+pub fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// A type of which arbitrary well-formed ABI values can be generated.
+pub trait Arbitrary: Sized {
+    /// Generates an arbitrary value.
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self;
+}
+
+/// Generates an arbitrary token of the specified ABI type, for example for
+/// encoding arbitrary inputs of a function only known at runtime.
+pub fn arbitrary_token<R: Rng + ?Sized>(rng: &mut R, kind: &ParamType) -> Token {
+    match kind {
+        ParamType::Address => Token::Address(Address::arbitrary(rng)),
+        ParamType::Bytes => Token::Bytes(arbitrary_bytes(rng)),
+        ParamType::Int(bits) => Token::Int(arbitrary_int(rng, *bits)),
+        ParamType::Uint(bits) => Token::Uint(arbitrary_uint(rng, *bits)),
+        ParamType::Bool => Token::Bool(rng.gen()),
+        ParamType::String => Token::String(String::arbitrary(rng)),
+        ParamType::Array(kind) => {
+            let len = rng.gen_range(0..=MAX_ARRAY_LEN);
+            Token::Array((0..len).map(|_| arbitrary_token(rng, kind)).collect())
+        }
+        ParamType::FixedBytes(len) => {
+            let mut bytes = vec![0; *len];
+            rng.fill_bytes(&mut bytes);
+            Token::FixedBytes(bytes)
+        }
+        ParamType::FixedArray(kind, len) => {
+            Token::FixedArray((0..*len).map(|_| arbitrary_token(rng, kind)).collect())
+        }
+        ParamType::Tuple(kinds) => Token::Tuple(
+            kinds
+                .iter()
+                .map(|kind| arbitrary_token(rng, kind))
+                .collect(),
+        ),
+    }
+}
+
+/// Generates an unsigned integer with the specified number of bits.
+fn arbitrary_uint<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> U256 {
+    let max = U256::MAX >> (256 - bits);
+    if rng.gen_bool(EDGE_CASE_PROBABILITY) {
+        return [U256::zero(), U256::one(), max][rng.gen_range(0..3)];
+    }
+
+    // Choose the bit length first so that small and large values are equally
+    // likely to be generated.
+    let len = rng.gen_range(0..=bits);
+    U256(rng.gen()) & (max >> (bits - len))
+}
+
+/// Generates a two's complement signed integer with the specified number of
+/// bits.
+fn arbitrary_int<R: Rng + ?Sized>(rng: &mut R, bits: usize) -> U256 {
+    let max = U256::MAX >> (257 - bits);
+    if rng.gen_bool(EDGE_CASE_PROBABILITY) {
+        return [U256::zero(), U256::one(), U256::MAX, max, !max][rng.gen_range(0..5)];
+    }
+
+    let value = arbitrary_uint(rng, bits - 1);
+    if rng.gen() {
+        !value
+    } else {
+        value
+    }
+}
+
+fn arbitrary_bytes<R: Rng + ?Sized>(rng: &mut R) -> Vec<u8> {
+    let mut bytes = vec![0; rng.gen_range(0..=MAX_BYTES_LEN)];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+impl Arbitrary for U256 {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        arbitrary_uint(rng, 256)
+    }
+}
+
+impl Arbitrary for I256 {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        I256::from_raw(arbitrary_int(rng, 256))
+    }
+}
+
+macro_rules! impl_arbitrary_for_integer {
+    ($($generate:ident => $($int:ty),*;)*) => {$($(
+        impl Arbitrary for $int {
+            fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+                $generate(rng, std::mem::size_of::<$int>() * 8).low_u128() as _
+            }
+        }
+    )*)*};
+}
+
+impl_arbitrary_for_integer! {
+    arbitrary_uint => u8, u16, u32, u64, u128;
+    arbitrary_int => i8, i16, i32, i64, i128;
+}
+
+impl Arbitrary for bool {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        rng.gen()
+    }
+}
+
+impl Arbitrary for Address {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        // The zero address is a common edge case for contracts, for example
+        // when minting or burning tokens.
+        if rng.gen_bool(EDGE_CASE_PROBABILITY) {
+            return Address::zero();
+        }
+        Address::from(rng.gen::<[u8; 20]>())
+    }
+}
+
+impl Arbitrary for H256 {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        H256::from(rng.gen::<[u8; 32]>())
+    }
+}
+
+impl<T> Arbitrary for Hash<T> {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Hash::from_hash(H256::arbitrary(rng))
+    }
+}
+
+impl Arbitrary for String {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let len = rng.gen_range(0..=MAX_BYTES_LEN);
+        (0..len).map(|_| rng.gen::<char>()).collect()
+    }
+}
+
+impl Arbitrary for Bytes<Vec<u8>> {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Bytes(arbitrary_bytes(rng))
+    }
+}
+
+impl<const N: usize> Arbitrary for Bytes<[u8; N]> {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut bytes = [0; N];
+        rng.fill_bytes(&mut bytes);
+        Bytes(bytes)
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let len = rng.gen_range(0..=MAX_ARRAY_LEN);
+        (0..len).map(|_| T::arbitrary(rng)).collect()
+    }
+}
+
+impl<T: Arbitrary, const N: usize> Arbitrary for [T; N] {
+    fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        (0..N)
+            .map(|_| T::arbitrary(rng))
+            .collect::<ArrayVec<T, N>>()
+            .into_inner()
+            .unwrap_or_else(|_| unreachable!("array has exactly N elements"))
+    }
+}
+
+macro_rules! impl_arbitrary_for_tuple {
+    ($($ty:ident),*) => {
+        impl<$($ty: Arbitrary),*> Arbitrary for ($($ty,)*) {
+            #[allow(clippy::unused_unit, unused_variables)]
+            fn arbitrary<R: Rng + ?Sized>(rng: &mut R) -> Self {
+                ($($ty::arbitrary(rng),)*)
+            }
+        }
+    };
+}
+
+impl_arbitrary_for_tuple!();
+impl_arbitrary_for_tuple!(A);
+impl_arbitrary_for_tuple!(A, B);
+impl_arbitrary_for_tuple!(A, B, C);
+impl_arbitrary_for_tuple!(A, B, C, D);
+impl_arbitrary_for_tuple!(A, B, C, D, E);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F, G);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F, G, H);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F, G, H, I);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_arbitrary_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Asserts that a value round trips through its token and ABI encoding as a
+/// parameter of the specified type. The value must tokenize into a token of
+/// the type that decodes back into the value, and its encoding must decode
+/// back into the same token.
+///
+/// # Panics
+///
+/// Panics with a description of the failed check if the value does not round
+/// trip.
+pub fn assert_roundtrip<T>(kind: &ParamType, value: T)
+where
+    T: Tokenize + Clone + Debug + PartialEq,
+{
+    if let Err(message) = roundtrip(kind, value) {
+        panic!("{}", message);
+    }
+}
+
+/// Asserts that a token round trips through the ABI encoding as a parameter of
+/// the specified type. Its encoding must decode back into the same token with
+/// both the bounds checked decoder and `ethabi`, and re-encoding the decoded
+/// token must yield the same data.
+///
+/// # Panics
+///
+/// Panics with a description of the failed check if the token does not round
+/// trip.
+pub fn assert_token_roundtrip(kind: &ParamType, token: &Token) {
+    if let Err(message) = token_roundtrip(kind, token) {
+        panic!("{}", message);
+    }
+}
+
+/// Checks that arbitrary values of a type round trip through their token and
+/// ABI encoding like [`assert_roundtrip`] does. The values are generated from
+/// the seed, so failures can be reproduced.
+///
+/// # Panics
+///
+/// Panics with the seed, iteration and a description of the failed check if
+/// a value does not round trip.
+pub fn check_roundtrip<T>(kind: &ParamType, seed: u64, iterations: usize)
+where
+    T: Arbitrary + Tokenize + Clone + Debug + PartialEq,
+{
+    let mut rng = rng(seed);
+    for iteration in 0..iterations {
+        if let Err(message) = roundtrip(kind, T::arbitrary(&mut rng)) {
+            panic!(
+                "round trip failed for seed {} at iteration {}: {}",
+                seed, iteration, message,
+            );
+        }
+    }
+}
+
+fn roundtrip<T>(kind: &ParamType, value: T) -> Result<(), String>
+where
+    T: Tokenize + Clone + Debug + PartialEq,
+{
+    let token = value.clone().into_token();
+    if !token.type_check(kind) {
+        return Err(format!(
+            "{:?} tokenizes into {:?}, which is not a {}",
+            value, token, kind,
+        ));
+    }
+    token_roundtrip(kind, &token)?;
+
+    match T::from_token(token.clone()) {
+        Ok(decoded) if decoded == value => Ok(()),
+        Ok(decoded) => Err(format!(
+            "{:?} detokenizes into {:?} instead of {:?}",
+            token, decoded, value,
+        )),
+        Err(err) => Err(format!("detokenizing {:?} failed: {}", token, err)),
+    }
+}
+
+fn token_roundtrip(kind: &ParamType, token: &Token) -> Result<(), String> {
+    let (kinds, tokens) = (slice::from_ref(kind), slice::from_ref(token));
+    let data = abi::encode(tokens);
+
+    let decoded = try_decode(kinds, &data)
+        .map_err(|err| format!("decoding {:?} as {} failed: {}", token, kind, err))?;
+    if decoded != tokens {
+        return Err(format!("{:?} decodes into {:?}", token, decoded));
+    }
+    if abi::decode(kinds, &data).ok().as_deref() != Some(tokens) {
+        return Err(format!("ethabi decodes {:?} differently", token));
+    }
+    if abi::encode(&decoded) != data {
+        return Err(format!("{:?} re-encodes differently", token));
+    }
+    Ok(())
+}
+
+/// The maximum nesting depth of parameter types generated by fuzz targets.
+const MAX_FUZZ_DEPTH: usize = 3;
+
+/// Reads parameter types from the first bytes of fuzz input, returning the
+/// types and the remaining input.
+fn fuzz_kinds(input: &[u8]) -> (Vec<ParamType>, &[u8]) {
+    fn kind(bytes: &mut slice::Iter<u8>, depth: usize) -> ParamType {
+        let byte = bytes.next().copied().unwrap_or_default();
+        let len = (byte >> 4) as usize;
+        match byte % if depth < MAX_FUZZ_DEPTH { 11 } else { 8 } {
+            0 => ParamType::Address,
+            1 => ParamType::Bool,
+            2 => ParamType::Uint((len + 1) * 16),
+            3 => ParamType::Int((len + 1) * 16),
+            4 => ParamType::FixedBytes(len % 32 + 1),
+            5 => ParamType::Bytes,
+            6 => ParamType::String,
+            7 => ParamType::Uint(256),
+            8 => ParamType::Array(Box::new(kind(bytes, depth + 1))),
+            9 => ParamType::FixedArray(Box::new(kind(bytes, depth + 1)), len % 4 + 1),
+            _ => ParamType::Tuple((0..len % 4 + 1).map(|_| kind(bytes, depth + 1)).collect()),
+        }
+    }
+
+    let mut bytes = input.iter();
+    let count = bytes.next().copied().unwrap_or_default() % 4 + 1;
+    let kinds = (0..count).map(|_| kind(&mut bytes, 0)).collect();
+    (kinds, bytes.as_slice())
+}
+
+/// Fuzz target for the bounds checked ABI decoder. The first bytes of the
+/// input select parameter types and the remaining bytes are decoded as
+/// parameters of these types.
+///
+/// # Panics
+///
+/// Decoding must not panic on any input, and data that is decoded
+/// successfully must round trip through encoding. Panics otherwise.
+pub fn fuzz_decode(input: &[u8]) {
+    let (kinds, data) = fuzz_kinds(input);
+    if let Ok(tokens) = try_decode(&kinds, data) {
+        let encoded = abi::encode(&tokens);
+        assert_eq!(
+            try_decode(&kinds, &encoded).as_ref(),
+            Ok(&tokens),
+            "decoded tokens do not round trip",
+        );
+    }
+}
+
+/// Fuzz target for the ABI encoder. The first bytes of the input select a
+/// parameter type and the remaining bytes seed the generation of an
+/// arbitrary token of this type, which must round trip with
+/// [`assert_token_roundtrip`].
+///
+/// # Panics
+///
+/// Panics if the generated token does not round trip.
+pub fn fuzz_roundtrip(input: &[u8]) {
+    let (kinds, seed) = fuzz_kinds(input);
+    let seed = seed
+        .iter()
+        .fold(0u64, |seed, byte| seed.rotate_left(8) ^ *byte as u64);
+    let kind = ParamType::Tuple(kinds);
+    assert_token_roundtrip(&kind, &arbitrary_token(&mut rng(seed), &kind));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate<T: Arbitrary>(seed: u64) -> Vec<T> {
+        let mut rng = rng(seed);
+        (0..1000).map(|_| T::arbitrary(&mut rng)).collect()
+    }
+
+    #[test]
+    fn values_are_deterministic() {
+        assert_eq!(
+            generate::<(U256, Address)>(1),
+            generate::<(U256, Address)>(1)
+        );
+        assert_ne!(
+            generate::<(U256, Address)>(1),
+            generate::<(U256, Address)>(2)
+        );
+    }
+
+    #[test]
+    fn integers_include_edge_cases() {
+        let values = generate::<U256>(0);
+        assert!(values.contains(&U256::zero()));
+        assert!(values.contains(&U256::MAX));
+        assert!(values.iter().any(|value| value.bits() <= 64));
+
+        let values = generate::<I256>(0);
+        assert!(values.contains(&I256::MIN));
+        assert!(values.contains(&I256::MAX));
+        assert!(values.contains(&I256::minus_one()));
+
+        let values = generate::<i8>(0);
+        assert!(values.contains(&i8::MIN));
+        assert!(values.contains(&i8::MAX));
+    }
+
+    #[test]
+    fn tokens_are_well_formed() {
+        let mut rng = rng(0);
+        for kind in &[
+            ParamType::Uint(24),
+            ParamType::Int(40),
+            ParamType::FixedBytes(4),
+            ParamType::Array(Box::new(ParamType::String)),
+            ParamType::FixedArray(Box::new(ParamType::Address), 3),
+            ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes]),
+        ] {
+            for _ in 0..100 {
+                let token = arbitrary_token(&mut rng, kind);
+                assert!(token.type_check(kind), "{:?} is not a {}", token, kind);
+                match &token {
+                    Token::Uint(value) => assert!(value.bits() <= 24),
+                    Token::Int(_) => {
+                        let value = i64::from_token(token.clone()).unwrap();
+                        assert!((-(1 << 39)..(1 << 39)).contains(&value));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    #[test]
+    fn builtin_types_roundtrip() {
+        check_roundtrip::<(Address, U256, I256, bool)>(
+            &ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Int(256),
+                ParamType::Bool,
+            ]),
+            0,
+            100,
+        );
+        check_roundtrip::<Vec<(u8, String)>>(
+            &ParamType::Array(Box::new(ParamType::Tuple(vec![
+                ParamType::Uint(8),
+                ParamType::String,
+            ]))),
+            0,
+            100,
+        );
+        check_roundtrip::<[Bytes<[u8; 4]>; 2]>(
+            &ParamType::FixedArray(Box::new(ParamType::FixedBytes(4)), 2),
+            0,
+            100,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "round trip failed for seed 0 at iteration 0")]
+    fn mismatched_types_fail_roundtrip() {
+        check_roundtrip::<u64>(&ParamType::Bool, 0, 1);
+    }
+
+    #[test]
+    fn fuzz_targets() {
+        let mut rng = rng(0);
+        for _ in 0..1000 {
+            let input = (0..rng.gen_range(0..256))
+                .map(|_| rng.gen())
+                .collect::<Vec<u8>>();
+            fuzz_decode(&input);
+            fuzz_roundtrip(&input);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! into a single request to the Node.
 
 use futures::channel::oneshot::{channel, Sender};
+use serde_json::Value;
 use web3::{
     error::Error as Web3Error,
     helpers::{self},
@@ -15,7 +16,7 @@ pub struct CallBatch<T: Web3BatchTransport> {
     requests: Vec<(Request, CompletionHandler)>,
 }
 
-type Request = (CallRequest, Option<BlockId>);
+type Request = (CallRequest, Vec<Value>);
 type CompletionHandler = Sender<Result<Bytes, Web3Error>>;
 
 impl<T: Web3BatchTransport> CallBatch<T> {
@@ -37,9 +38,21 @@ impl<T: Web3BatchTransport> CallBatch<T> {
         &mut self,
         call: CallRequest,
         block: Option<BlockId>,
+    ) -> impl std::future::Future<Output = Result<Bytes, Web3Error>> {
+        let block = helpers::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
+        self.push_with_params(call, vec![block])
+    }
+
+    /// Adds a call request to the current batch with the already serialized
+    /// parameters following the call request, for example a block tag and
+    /// state overrides.
+    pub(crate) fn push_with_params(
+        &mut self,
+        call: CallRequest,
+        params: Vec<Value>,
     ) -> impl std::future::Future<Output = Result<Bytes, Web3Error>> {
         let (tx, rx) = channel();
-        self.requests.push(((call, block), tx));
+        self.requests.push(((call, params), tx));
         async move {
             rx.await.unwrap_or_else(|_| {
                 Err(Web3Error::Transport(
@@ -59,11 +72,10 @@ impl<T: Web3BatchTransport> CallBatch<T> {
 
             // Send requests in a single call
             let batch_result = inner
-                .send_batch(requests.iter().map(|(request, block)| {
-                    let req = helpers::serialize(request);
-                    let block =
-                        helpers::serialize(&block.unwrap_or_else(|| BlockNumber::Latest.into()));
-                    let (id, request) = inner.prepare("eth_call", vec![req, block]);
+                .send_batch(requests.iter().map(|(request, params)| {
+                    let mut req = vec![helpers::serialize(request)];
+                    req.extend(params.iter().cloned());
+                    let (id, request) = inner.prepare("eth_call", req);
                     (id, request)
                 }))
                 .await;
@@ -0,0 +1,278 @@
+//! Block identifiers and tags for selecting the chain state that contract
+//! calls, balance reads and nonce lookups are made against, and the block
+//! ranges of log queries.
+//!
+//! APIs that read state at a block accept anything that converts into a
+//! [`BlockId`], so blocks can be specified by number, by hash or by tag
+//! without conversions at the call site:
+//!
+//! ```no_run
+//! # async fn run(instance: ethcontract::Instance<ethcontract::Http>) {
+//! # let owners = [ethcontract::Address::zero()];
+//! use ethcontract::block::BlockTag;
+//!
+//! instance.balance_of_many_at(&owners, 12_345_678).await;
+//! instance.balance_of_many_at(&owners, BlockTag::Finalized).await;
+//! # }
+//! ```
+//!
+//! Block tags can also be set as defaults on contract instances with
+//! [`MethodDefaults::block_tag`](crate::contract::MethodDefaults::block_tag),
+//! or used for pinning a [`ReadSession`](crate::session::ReadSession) to the
+//! block with the tag, so that applications can for example only ever read
+//! finalized state without specifying a block for every call.
+
+use crate::errors::ExecutionError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+use web3::api::Web3;
+use web3::types::{self as web3_types, Block, BlockNumber, H256, U64};
+use web3::{helpers, Transport};
+
+/// Identifies a block by number, hash or tag.
+///
+/// This is a superset of the `web3` `BlockId` and `BlockNumber` types, which
+/// can not express the `safe` and `finalized` tags, and converts from both of
+/// them. Blocks specified by hash are serialized as EIP-1898 block parameters.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BlockId {
+    /// A block by number.
+    Number(U64),
+    /// A block by hash.
+    Hash(H256),
+    /// The block that currently has the tag.
+    Tag(BlockTag),
+}
+
+impl BlockId {
+    /// Returns the block as a `web3` block number, or `None` for blocks
+    /// specified by hash or by a tag that `web3` does not support.
+    pub fn block_number(self) -> Option<BlockNumber> {
+        match self {
+            BlockId::Number(number) => Some(BlockNumber::Number(number)),
+            BlockId::Hash(_) => None,
+            BlockId::Tag(BlockTag::Earliest) => Some(BlockNumber::Earliest),
+            BlockId::Tag(BlockTag::Latest) => Some(BlockNumber::Latest),
+            BlockId::Tag(BlockTag::Pending) => Some(BlockNumber::Pending),
+            BlockId::Tag(BlockTag::Safe) | BlockId::Tag(BlockTag::Finalized) => None,
+        }
+    }
+
+    /// Returns the block as a `web3` block number, looking up the number of
+    /// blocks specified by hash or by a tag that `web3` does not support.
+    pub async fn resolve_block_number<T: Transport>(
+        self,
+        web3: &Web3<T>,
+    ) -> Result<BlockNumber, ExecutionError> {
+        if let Some(number) = self.block_number() {
+            return Ok(number);
+        }
+        let number = match self {
+            BlockId::Number(number) => number,
+            BlockId::Hash(hash) => web3
+                .eth()
+                .block(web3_types::BlockId::Hash(hash))
+                .await?
+                .and_then(|block| block.number)
+                .ok_or(ExecutionError::MissingBlock)?,
+            BlockId::Tag(tag) => tag.resolve(web3).await?.0,
+        };
+        Ok(BlockNumber::Number(number))
+    }
+}
+
+impl Default for BlockId {
+    fn default() -> Self {
+        BlockId::Tag(BlockTag::Latest)
+    }
+}
+
+impl From<u64> for BlockId {
+    fn from(number: u64) -> Self {
+        BlockId::Number(number.into())
+    }
+}
+
+impl From<U64> for BlockId {
+    fn from(number: U64) -> Self {
+        BlockId::Number(number)
+    }
+}
+
+impl From<H256> for BlockId {
+    fn from(hash: H256) -> Self {
+        BlockId::Hash(hash)
+    }
+}
+
+impl From<BlockTag> for BlockId {
+    fn from(tag: BlockTag) -> Self {
+        BlockId::Tag(tag)
+    }
+}
+
+impl From<BlockNumber> for BlockId {
+    fn from(number: BlockNumber) -> Self {
+        match number {
+            BlockNumber::Number(number) => BlockId::Number(number),
+            BlockNumber::Earliest => BlockId::Tag(BlockTag::Earliest),
+            BlockNumber::Latest => BlockId::Tag(BlockTag::Latest),
+            BlockNumber::Pending => BlockId::Tag(BlockTag::Pending),
+        }
+    }
+}
+
+impl From<web3_types::BlockId> for BlockId {
+    fn from(block: web3_types::BlockId) -> Self {
+        match block {
+            web3_types::BlockId::Hash(hash) => BlockId::Hash(hash),
+            web3_types::BlockId::Number(number) => number.into(),
+        }
+    }
+}
+
+impl Serialize for BlockId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BlockId::Number(number) => serializer.serialize_str(&format!("{:#x}", number)),
+            BlockId::Hash(hash) => {
+                let mut block = serializer.serialize_struct("BlockIdEip1898", 1)?;
+                block.serialize_field("blockHash", hash)?;
+                block.end()
+            }
+            BlockId::Tag(tag) => tag.serialize(serializer),
+        }
+    }
+}
+
+/// A block tag as specified in the Ethereum JSON RPC specification.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BlockTag {
+    /// The genesis block.
+    Earliest,
+    /// The latest block of the canonical chain.
+    Latest,
+    /// The latest block that is safe from re-orgs under honest majority and
+    /// certain synchronicity assumptions.
+    Safe,
+    /// The latest block that was finalized by the consensus layer, and can not
+    /// be re-orged without burning a large amount of stake.
+    Finalized,
+    /// The pending state, including the transactions in the node's
+    /// transaction pool.
+    Pending,
+}
+
+impl BlockTag {
+    /// Returns the JSON RPC name of the block tag.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BlockTag::Earliest => "earliest",
+            BlockTag::Latest => "latest",
+            BlockTag::Safe => "safe",
+            BlockTag::Finalized => "finalized",
+            BlockTag::Pending => "pending",
+        }
+    }
+
+    /// Resolves the number and hash of the block that currently has this tag.
+    ///
+    /// Note that nodes that do not support the `safe` and `finalized` tags, for
+    /// example nodes of chains without proof-of-stake finality, generally
+    /// return an error for them.
+    pub async fn resolve<T: Transport>(
+        self,
+        web3: &Web3<T>,
+    ) -> Result<(U64, H256), ExecutionError> {
+        let block = web3
+            .transport()
+            .execute(
+                "eth_getBlockByNumber",
+                vec![helpers::serialize(&self), helpers::serialize(&false)],
+            )
+            .await?;
+        let block = helpers::decode::<Option<Block<H256>>>(block)?;
+        match block {
+            Some(Block {
+                number: Some(number),
+                hash: Some(hash),
+                ..
+            }) => Ok((number, hash)),
+            _ => Err(ExecutionError::MissingBlock),
+        }
+    }
+}
+
+impl Default for BlockTag {
+    fn default() -> Self {
+        BlockTag::Latest
+    }
+}
+
+impl Display for BlockTag {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for BlockTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    #[test]
+    fn resolve_block_tags() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!(Block::<H256> {
+            hash: Some(H256::repeat_byte(1)),
+            number: Some(42.into()),
+            ..Default::default()
+        }));
+        let block = BlockTag::Finalized.resolve(&web3).immediate().unwrap();
+        assert_eq!(block, (42.into(), H256::repeat_byte(1)));
+        transport.assert_request("eth_getBlockByNumber", &[json!("finalized"), json!(false)]);
+
+        transport.add_response(json!(null));
+        assert!(matches!(
+            BlockTag::Safe.resolve(&web3).immediate(),
+            Err(ExecutionError::MissingBlock)
+        ));
+        transport.assert_request("eth_getBlockByNumber", &[json!("safe"), json!(false)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_id_conversions() {
+        let hash = H256::repeat_byte(1);
+        for (block, expected) in vec![
+            (BlockId::from(42), json!("0x2a")),
+            (U64::from(42).into(), json!("0x2a")),
+            (BlockNumber::Number(42.into()).into(), json!("0x2a")),
+            (BlockNumber::Pending.into(), json!("pending")),
+            (BlockTag::Finalized.into(), json!("finalized")),
+            (hash.into(), json!({ "blockHash": hash })),
+            (
+                web3_types::BlockId::Hash(hash).into(),
+                json!({ "blockHash": hash }),
+            ),
+        ] {
+            assert_eq!(json!(block), expected);
+        }
+
+        assert_eq!(
+            BlockId::from(BlockNumber::Earliest).block_number(),
+            Some(BlockNumber::Earliest)
+        );
+        assert_eq!(BlockId::from(BlockTag::Safe).block_number(), None);
+        assert_eq!(BlockId::from(hash).block_number(), None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Helpers for messaging between Ethereum and L2 rollups.
+//!
+//! The [`optimism`] module supports OP-stack chains such as Optimism and Base,
+//! with deposits through the `L1StandardBridge` and `OptimismPortal`
+//! contracts and withdrawals through the `L2ToL1MessagePasser`, including the
+//! storage proofs for proving withdrawals and tracking them until they are
+//! finalized. The [`arbitrum`] module supports Arbitrum chains, with ETH
+//! deposits and retryable tickets through the `Inbox`, redeeming tickets with
+//! the `ArbRetryableTx` precompile, and withdrawals through the `ArbSys`
+//! precompile and the `Outbox`.
+//!
+//! The bridge contracts are wrapped like generated contract bindings, and
+//! functions that send transactions return method builders with the value
+//! of the call already set:
+//!
+//! ```ignore
+//! let bridge = optimism::L1StandardBridge::at(&web3, bridge_address);
+//! bridge
+//!     .deposit_eth_to(recipient, amount, 200_000, Vec::new())
+//!     .from(account)
+//!     .send()
+//!     .await?;
+//! ```
+
+pub mod arbitrum;
+pub mod optimism;
+
+use crate::contract::{Instance, MethodBuilder};
+use crate::errors::MethodError;
+use crate::tokens::Tokenize;
+use ethcontract_common::hash::function_selector;
+use ethcontract_common::Abi;
+use web3::api::Web3;
+use web3::types::{Address, H160, U256};
+use web3::Transport;
+
+/// The offset that is added to the address of an L1 contract sending a
+/// message to L2 by both OP-stack and Arbitrum chains, so that it can't
+/// impersonate the L2 contract at the same address.
+pub const ALIAS_OFFSET: Address = H160([
+    0x11, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x11, 0x11,
+]);
+
+/// Returns the aliased address that is the sender of messages from an L1
+/// contract on L2.
+pub fn apply_l1_to_l2_alias(address: Address) -> Address {
+    from_u256(to_u256(address).overflowing_add(to_u256(ALIAS_OFFSET)).0)
+}
+
+/// Returns the address of the L1 contract that sent a message with the
+/// specified aliased sender on L2.
+pub fn undo_l1_to_l2_alias(address: Address) -> Address {
+    from_u256(to_u256(address).overflowing_sub(to_u256(ALIAS_OFFSET)).0)
+}
+
+fn to_u256(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+/// Converts the low 160 bits of a value into an address.
+fn from_u256(value: U256) -> Address {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+/// Creates an instance of a bridge contract with its embedded ABI.
+fn instance<T: Transport>(web3: &Web3<T>, abi: &str, address: Address) -> Instance<T> {
+    let abi = Abi::load(abi.as_bytes()).expect("bridge contract ABIs are valid");
+    Instance::at(web3.clone(), abi, address)
+}
+
+/// Returns a method builder for a function of a bridge contract.
+fn method<T, P, R>(instance: &Instance<T>, signature: &str, params: P) -> MethodBuilder<T, R>
+where
+    T: Transport,
+    P: Tokenize,
+    R: Tokenize,
+{
+    instance
+        .method(function_selector(signature), params)
+        .expect("bridge contract functions match their ABIs")
+}
+
+/// Calls a view function of a bridge contract.
+async fn call<T, P, R>(instance: &Instance<T>, signature: &str, params: P) -> Result<R, MethodError>
+where
+    T: Transport,
+    P: Tokenize,
+    R: Tokenize,
+{
+    instance
+        .view_method(function_selector(signature), params)
+        .expect("bridge contract functions match their ABIs")
+        .call()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn l1_to_l2_alias() {
+        let address = Address::repeat_byte(0x42);
+        let aliased = apply_l1_to_l2_alias(address);
+        assert_eq!(
+            aliased,
+            "0x5353424242424242424242424242424242425353"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(undo_l1_to_l2_alias(aliased), address);
+
+        // Aliasing wraps around at 160 bits.
+        let aliased = apply_l1_to_l2_alias(Address::repeat_byte(0xff));
+        assert_eq!(
+            aliased,
+            "0x1111000000000000000000000000000000001110"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(undo_l1_to_l2_alias(aliased), Address::repeat_byte(0xff));
+    }
+}
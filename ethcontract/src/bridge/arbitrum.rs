@@ -0,0 +1,754 @@
+//! Deposits, retryable tickets and withdrawals for Arbitrum chains.
+//!
+//! Messages from L1 are sent through the `Inbox` as retryable tickets, which
+//! are executed automatically on L2 when enough gas was paid for, and can
+//! otherwise be redeemed manually with [`RetryableTx::redeem`] until they
+//! expire.
+//!
+//! Messages from L2 are sent through the `ArbSys` precompile and can be
+//! executed on L1 through the `Outbox` once the assertion containing them is
+//! confirmed, with the proof retrieved from the `NodeInterface`.
+
+use super::{call, instance, method};
+use crate::contract::{Instance, MethodBuilder, RawLog};
+use crate::errors::{ExecutionError, MethodError, RpcError};
+use crate::tokens::Bytes as TokenBytes;
+use web3::api::Web3;
+use web3::types::{Address, TransactionReceipt, H160, H256, U256};
+use web3::Transport;
+
+/// The address of the `ArbSys` precompile.
+pub const ARB_SYS: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x64,
+]);
+
+/// The address of the `ArbRetryableTx` precompile.
+pub const ARB_RETRYABLE_TX: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x6e,
+]);
+
+/// The address of the `NodeInterface`, which is only available through
+/// `eth_call` and `eth_estimateGas`.
+pub const NODE_INTERFACE: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0xc8,
+]);
+
+const INBOX_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "depositEth",
+        "inputs": [],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "function",
+        "name": "createRetryableTicket",
+        "inputs": [
+            { "name": "to", "type": "address" },
+            { "name": "l2CallValue", "type": "uint256" },
+            { "name": "maxSubmissionCost", "type": "uint256" },
+            { "name": "excessFeeRefundAddress", "type": "address" },
+            { "name": "callValueRefundAddress", "type": "address" },
+            { "name": "gasLimit", "type": "uint256" },
+            { "name": "maxFeePerGas", "type": "uint256" },
+            { "name": "data", "type": "bytes" }
+        ],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "function",
+        "name": "calculateRetryableSubmissionFee",
+        "inputs": [
+            { "name": "dataLength", "type": "uint256" },
+            { "name": "baseFee", "type": "uint256" }
+        ],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "view"
+    }
+]"#;
+
+const ARB_RETRYABLE_TX_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "redeem",
+        "inputs": [{ "name": "ticketId", "type": "bytes32" }],
+        "outputs": [{ "name": "", "type": "bytes32" }],
+        "stateMutability": "nonpayable"
+    },
+    {
+        "type": "function",
+        "name": "keepalive",
+        "inputs": [{ "name": "ticketId", "type": "bytes32" }],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "nonpayable"
+    },
+    {
+        "type": "function",
+        "name": "cancel",
+        "inputs": [{ "name": "ticketId", "type": "bytes32" }],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    },
+    {
+        "type": "function",
+        "name": "getTimeout",
+        "inputs": [{ "name": "ticketId", "type": "bytes32" }],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "view"
+    }
+]"#;
+
+const ARB_SYS_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "withdrawEth",
+        "inputs": [{ "name": "destination", "type": "address" }],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "function",
+        "name": "sendTxToL1",
+        "inputs": [
+            { "name": "destination", "type": "address" },
+            { "name": "data", "type": "bytes" }
+        ],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "event",
+        "name": "L2ToL1Tx",
+        "inputs": [
+            { "name": "caller", "type": "address", "indexed": false },
+            { "name": "destination", "type": "address", "indexed": true },
+            { "name": "hash", "type": "uint256", "indexed": true },
+            { "name": "position", "type": "uint256", "indexed": true },
+            { "name": "arbBlockNum", "type": "uint256", "indexed": false },
+            { "name": "ethBlockNum", "type": "uint256", "indexed": false },
+            { "name": "timestamp", "type": "uint256", "indexed": false },
+            { "name": "callvalue", "type": "uint256", "indexed": false },
+            { "name": "data", "type": "bytes", "indexed": false }
+        ],
+        "anonymous": false
+    }
+]"#;
+
+const OUTBOX_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "isSpent",
+        "inputs": [{ "name": "index", "type": "uint256" }],
+        "outputs": [{ "name": "", "type": "bool" }],
+        "stateMutability": "view"
+    },
+    {
+        "type": "function",
+        "name": "executeTransaction",
+        "inputs": [
+            { "name": "proof", "type": "bytes32[]" },
+            { "name": "index", "type": "uint256" },
+            { "name": "l2Sender", "type": "address" },
+            { "name": "to", "type": "address" },
+            { "name": "l2Block", "type": "uint256" },
+            { "name": "l1Block", "type": "uint256" },
+            { "name": "l2Timestamp", "type": "uint256" },
+            { "name": "value", "type": "uint256" },
+            { "name": "data", "type": "bytes" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }
+]"#;
+
+const NODE_INTERFACE_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "constructOutboxProof",
+        "inputs": [
+            { "name": "size", "type": "uint64" },
+            { "name": "leaf", "type": "uint64" }
+        ],
+        "outputs": [
+            { "name": "send", "type": "bytes32" },
+            { "name": "root", "type": "bytes32" },
+            { "name": "proof", "type": "bytes32[]" }
+        ],
+        "stateMutability": "view"
+    }
+]"#;
+
+/// The decoded data of an `L2ToL1Tx` event.
+type L2ToL1TxData = (
+    Address,
+    Address,
+    U256,
+    U256,
+    U256,
+    U256,
+    U256,
+    U256,
+    TokenBytes<Vec<u8>>,
+);
+
+/// The ABI tuple returned by `constructOutboxProof`.
+type OutboxProofTuple = (
+    TokenBytes<[u8; 32]>,
+    TokenBytes<[u8; 32]>,
+    Vec<TokenBytes<[u8; 32]>>,
+);
+
+/// The parameters of a retryable ticket sent from L1.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetryableTicket {
+    /// The account that is called on L2.
+    pub to: Address,
+    /// The amount of ETH sent with the call on L2.
+    pub l2_call_value: U256,
+    /// The maximum fee for submitting the ticket, which should be at least
+    /// the fee returned by [`Inbox::submission_fee`].
+    pub max_submission_cost: U256,
+    /// The L2 account that is refunded the unused fees.
+    pub excess_fee_refund_address: Address,
+    /// The L2 account that receives the call value if the ticket expires or
+    /// is cancelled.
+    pub call_value_refund_address: Address,
+    /// The gas limit for executing the ticket on L2. The ticket is only
+    /// executed automatically if the gas limit and fee are sufficient.
+    pub gas_limit: U256,
+    /// The maximum fee per gas for executing the ticket on L2.
+    pub max_fee_per_gas: U256,
+    /// The calldata of the call on L2.
+    pub data: Vec<u8>,
+}
+
+impl RetryableTicket {
+    /// Returns the ETH that needs to be sent with the ticket to cover the
+    /// call value and the maximum fees.
+    pub fn deposit(&self) -> U256 {
+        self.l2_call_value + self.max_submission_cost + self.gas_limit * self.max_fee_per_gas
+    }
+}
+
+/// The `Inbox` contract for sending messages from L1.
+#[derive(Clone, Debug)]
+pub struct Inbox<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> Inbox<T> {
+    /// Creates an inbox instance at the specified address.
+    pub fn at(web3: &Web3<T>, address: Address) -> Self {
+        Inbox {
+            instance: instance(web3, INBOX_ABI, address),
+        }
+    }
+
+    /// Returns the underlying contract instance.
+    pub fn raw_instance(&self) -> &Instance<T> {
+        &self.instance
+    }
+
+    /// Deposits ETH to the sender on L2, or to its aliased address for
+    /// contracts.
+    pub fn deposit_eth(&self, amount: U256) -> MethodBuilder<T, U256> {
+        method::<_, _, U256>(&self.instance, "depositEth()", ()).value(amount)
+    }
+
+    /// Creates a retryable ticket, sending the deposit of the ticket with
+    /// the transaction.
+    pub fn create_retryable_ticket(&self, ticket: &RetryableTicket) -> MethodBuilder<T, U256> {
+        method::<_, _, U256>(
+            &self.instance,
+            "createRetryableTicket(address,uint256,uint256,address,address,uint256,uint256,bytes)",
+            (
+                ticket.to,
+                ticket.l2_call_value,
+                ticket.max_submission_cost,
+                ticket.excess_fee_refund_address,
+                ticket.call_value_refund_address,
+                ticket.gas_limit,
+                ticket.max_fee_per_gas,
+                TokenBytes(ticket.data.clone()),
+            ),
+        )
+        .value(ticket.deposit())
+    }
+
+    /// Returns the fee for submitting a ticket with the specified calldata
+    /// length at the specified L1 base fee.
+    pub async fn submission_fee(
+        &self,
+        data_length: usize,
+        base_fee: U256,
+    ) -> Result<U256, MethodError> {
+        call(
+            &self.instance,
+            "calculateRetryableSubmissionFee(uint256,uint256)",
+            (U256::from(data_length), base_fee),
+        )
+        .await
+    }
+}
+
+/// The status of a retryable ticket on L2.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RetryableStatus {
+    /// The ticket can still be redeemed until the specified timestamp.
+    Pending {
+        /// The timestamp at which the ticket expires.
+        timeout: u64,
+    },
+    /// The ticket was redeemed, cancelled or expired.
+    Closed,
+}
+
+/// The `ArbRetryableTx` precompile for managing retryable tickets on L2.
+#[derive(Clone, Debug)]
+pub struct RetryableTx<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> RetryableTx<T> {
+    /// Creates an instance of the precompile.
+    pub fn new(web3: &Web3<T>) -> Self {
+        RetryableTx {
+            instance: instance(web3, ARB_RETRYABLE_TX_ABI, ARB_RETRYABLE_TX),
+        }
+    }
+
+    /// Redeems a ticket that was not executed automatically, returning the
+    /// hash of the redeem transaction.
+    pub fn redeem(&self, ticket_id: H256) -> MethodBuilder<T, TokenBytes<[u8; 32]>> {
+        method(
+            &self.instance,
+            "redeem(bytes32)",
+            (TokenBytes(ticket_id.0),),
+        )
+    }
+
+    /// Extends the lifetime of a ticket, returning its new timeout.
+    pub fn keepalive(&self, ticket_id: H256) -> MethodBuilder<T, U256> {
+        method(
+            &self.instance,
+            "keepalive(bytes32)",
+            (TokenBytes(ticket_id.0),),
+        )
+    }
+
+    /// Cancels a ticket, which can only be done by its beneficiary.
+    pub fn cancel(&self, ticket_id: H256) -> MethodBuilder<T, ()> {
+        method(
+            &self.instance,
+            "cancel(bytes32)",
+            (TokenBytes(ticket_id.0),),
+        )
+    }
+
+    /// Returns the status of a ticket. The precompile reverts for tickets
+    /// that no longer exist, which are reported as closed.
+    pub async fn status(&self, ticket_id: H256) -> Result<RetryableStatus, MethodError> {
+        let timeout: Result<U256, _> = call(
+            &self.instance,
+            "getTimeout(bytes32)",
+            (TokenBytes(ticket_id.0),),
+        )
+        .await;
+        match timeout {
+            Ok(timeout) => Ok(RetryableStatus::Pending {
+                timeout: timeout.low_u64(),
+            }),
+            Err(err) => match err.rpc_error() {
+                Some(RpcError::ExecutionReverted { .. }) => Ok(RetryableStatus::Closed),
+                _ => Err(err),
+            },
+        }
+    }
+}
+
+/// The `ArbSys` precompile for sending messages from L2.
+#[derive(Clone, Debug)]
+pub struct ArbSys<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> ArbSys<T> {
+    /// Creates an instance of the precompile.
+    pub fn new(web3: &Web3<T>) -> Self {
+        ArbSys {
+            instance: instance(web3, ARB_SYS_ABI, ARB_SYS),
+        }
+    }
+
+    /// Withdraws ETH to an account on L1.
+    pub fn withdraw_eth(&self, destination: Address, amount: U256) -> MethodBuilder<T, U256> {
+        method::<_, _, U256>(&self.instance, "withdrawEth(address)", (destination,)).value(amount)
+    }
+
+    /// Sends a message that calls the destination on L1 with the specified
+    /// value.
+    pub fn send_tx_to_l1(
+        &self,
+        destination: Address,
+        data: Vec<u8>,
+        value: U256,
+    ) -> MethodBuilder<T, U256> {
+        method::<_, _, U256>(
+            &self.instance,
+            "sendTxToL1(address,bytes)",
+            (destination, TokenBytes(data)),
+        )
+        .value(value)
+    }
+}
+
+/// A message sent from L2 to L1.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct L2ToL1Message {
+    /// The account that sent the message on L2.
+    pub caller: Address,
+    /// The account that is called on L1.
+    pub destination: Address,
+    /// The hash of the message.
+    pub hash: U256,
+    /// The position of the message in the outbox, which is its index when
+    /// executing it.
+    pub position: U256,
+    /// The L2 block of the message.
+    pub arb_block_num: U256,
+    /// The L1 block of the message.
+    pub eth_block_num: U256,
+    /// The L2 timestamp of the message.
+    pub timestamp: U256,
+    /// The amount of ETH sent with the call.
+    pub callvalue: U256,
+    /// The calldata of the call.
+    pub data: Vec<u8>,
+}
+
+impl L2ToL1Message {
+    /// Returns the messages sent by an L2 transaction, which are decoded from
+    /// the `L2ToL1Tx` events of its receipt.
+    pub fn from_receipt(receipt: &TransactionReceipt) -> Result<Vec<Self>, ExecutionError> {
+        let abi = ethcontract_common::Abi::load(ARB_SYS_ABI.as_bytes())
+            .expect("bridge contract ABIs are valid");
+        let event = abi
+            .event("L2ToL1Tx")
+            .expect("bridge contract events match their ABIs");
+        receipt
+            .logs
+            .iter()
+            .filter(|log| log.address == ARB_SYS && log.topics.first() == Some(&event.signature()))
+            .map(|log| {
+                let (
+                    caller,
+                    destination,
+                    hash,
+                    position,
+                    arb_block_num,
+                    eth_block_num,
+                    timestamp,
+                    callvalue,
+                    data,
+                ): L2ToL1TxData = RawLog::from(log.clone()).decode(event)?;
+                Ok(L2ToL1Message {
+                    caller,
+                    destination,
+                    hash,
+                    position,
+                    arb_block_num,
+                    eth_block_num,
+                    timestamp,
+                    callvalue,
+                    data: data.0,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A proof of an L2 to L1 message against an outbox root.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OutboxProof {
+    /// The hash of the message.
+    pub send: H256,
+    /// The outbox root the message is proven against.
+    pub root: H256,
+    /// The merkle proof of the message.
+    pub proof: Vec<H256>,
+}
+
+/// The `NodeInterface` for retrieving outbox proofs on L2.
+#[derive(Clone, Debug)]
+pub struct NodeInterface<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> NodeInterface<T> {
+    /// Creates an instance of the node interface.
+    pub fn new(web3: &Web3<T>) -> Self {
+        NodeInterface {
+            instance: instance(web3, NODE_INTERFACE_ABI, NODE_INTERFACE),
+        }
+    }
+
+    /// Returns the proof of the message at the specified position in an
+    /// outbox with the specified number of messages, which is the send count
+    /// of the confirmed assertion the message is executed against.
+    pub async fn outbox_proof(&self, size: u64, position: u64) -> Result<OutboxProof, MethodError> {
+        let (send, root, proof): OutboxProofTuple = call(
+            &self.instance,
+            "constructOutboxProof(uint64,uint64)",
+            (size, position),
+        )
+        .await?;
+        Ok(OutboxProof {
+            send: H256(send.0),
+            root: H256(root.0),
+            proof: proof.into_iter().map(|node| H256(node.0)).collect(),
+        })
+    }
+}
+
+/// The `Outbox` contract for executing L2 to L1 messages.
+#[derive(Clone, Debug)]
+pub struct Outbox<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> Outbox<T> {
+    /// Creates an outbox instance at the specified address.
+    pub fn at(web3: &Web3<T>, address: Address) -> Self {
+        Outbox {
+            instance: instance(web3, OUTBOX_ABI, address),
+        }
+    }
+
+    /// Returns the underlying contract instance.
+    pub fn raw_instance(&self) -> &Instance<T> {
+        &self.instance
+    }
+
+    /// Returns whether the message at the specified position was executed.
+    pub async fn is_spent(&self, position: U256) -> Result<bool, MethodError> {
+        call(&self.instance, "isSpent(uint256)", (position,)).await
+    }
+
+    /// Executes a message with its proof from
+    /// [`NodeInterface::outbox_proof`].
+    pub fn execute_transaction(
+        &self,
+        message: &L2ToL1Message,
+        proof: &OutboxProof,
+    ) -> MethodBuilder<T, ()> {
+        let proof = proof
+            .proof
+            .iter()
+            .map(|node| TokenBytes(node.0))
+            .collect::<Vec<_>>();
+        method(
+            &self.instance,
+            "executeTransaction(\
+                bytes32[],uint256,address,address,uint256,uint256,uint256,uint256,bytes\
+            )",
+            (
+                proof,
+                message.position,
+                message.caller,
+                message.destination,
+                message.arb_block_num,
+                message.eth_block_num,
+                message.timestamp,
+                message.callvalue,
+                TokenBytes(message.data.clone()),
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use ethcontract_common::abi::{self, Token};
+    use ethcontract_common::hash::{function_selector, keccak256};
+    use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
+    use web3::error::Error as Web3Error;
+    use web3::types::{Bytes, Log, H2048};
+
+    fn ticket() -> RetryableTicket {
+        RetryableTicket {
+            to: Address::repeat_byte(0x42),
+            l2_call_value: 1_000.into(),
+            max_submission_cost: 100.into(),
+            excess_fee_refund_address: Address::repeat_byte(1),
+            call_value_refund_address: Address::repeat_byte(2),
+            gas_limit: 50_000.into(),
+            max_fee_per_gas: 2.into(),
+            data: vec![0xde, 0xad],
+        }
+    }
+
+    #[test]
+    fn retryable_ticket() {
+        let web3 = Web3::new(TestTransport::new());
+        let inbox = Inbox::at(&web3, Address::repeat_byte(9));
+
+        let ticket = ticket();
+        assert_eq!(ticket.deposit(), U256::from(101_100));
+
+        let tx = inbox.create_retryable_ticket(&ticket).tx;
+        assert_eq!(tx.to, Some(Address::repeat_byte(9)));
+        assert_eq!(tx.value, Some(101_100.into()));
+        assert_eq!(
+            tx.data.unwrap().0,
+            [
+                &function_selector(
+                    "createRetryableTicket(\
+                        address,uint256,uint256,address,address,uint256,uint256,bytes\
+                    )"
+                )[..],
+                &abi::encode(&[
+                    Token::Address(ticket.to),
+                    Token::Uint(ticket.l2_call_value),
+                    Token::Uint(ticket.max_submission_cost),
+                    Token::Address(ticket.excess_fee_refund_address),
+                    Token::Address(ticket.call_value_refund_address),
+                    Token::Uint(ticket.gas_limit),
+                    Token::Uint(ticket.max_fee_per_gas),
+                    Token::Bytes(ticket.data.clone()),
+                ]),
+            ]
+            .concat(),
+        );
+
+        let tx = inbox.deposit_eth(5.into()).tx;
+        assert_eq!(tx.value, Some(5.into()));
+        assert_eq!(tx.data.unwrap().0, function_selector("depositEth()"));
+    }
+
+    #[test]
+    fn retryable_status() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let retryables = RetryableTx::new(&web3);
+        let ticket_id = H256::repeat_byte(7);
+
+        transport.add_response(json!(format!("0x{:064x}", 1_000)));
+        let status = retryables.status(ticket_id).immediate().unwrap();
+        assert_eq!(status, RetryableStatus::Pending { timeout: 1_000 });
+
+        transport.add_error(Web3Error::Rpc(JsonrpcError {
+            code: ErrorCode::ServerError(3),
+            message: "execution reverted".to_owned(),
+            data: None,
+        }));
+        let status = retryables.status(ticket_id).immediate().unwrap();
+        assert_eq!(status, RetryableStatus::Closed);
+
+        transport.add_error(Web3Error::Rpc(JsonrpcError::internal_error()));
+        assert!(retryables.status(ticket_id).immediate().is_err());
+    }
+
+    #[test]
+    fn executes_l2_to_l1_messages() {
+        let message = L2ToL1Message {
+            caller: Address::repeat_byte(1),
+            destination: Address::repeat_byte(2),
+            hash: 3.into(),
+            position: 4.into(),
+            arb_block_num: 5.into(),
+            eth_block_num: 6.into(),
+            timestamp: 7.into(),
+            callvalue: 8.into(),
+            data: vec![9],
+        };
+        let log = Log {
+            address: ARB_SYS,
+            topics: vec![
+                H256(keccak256(
+                    "L2ToL1Tx(address,address,uint256,uint256,uint256,uint256,uint256,uint256,bytes)",
+                )),
+                message.destination.into(),
+                H256::from_low_u64_be(3),
+                H256::from_low_u64_be(4),
+            ],
+            data: Bytes(abi::encode(&[
+                Token::Address(message.caller),
+                Token::Uint(message.arb_block_num),
+                Token::Uint(message.eth_block_num),
+                Token::Uint(message.timestamp),
+                Token::Uint(message.callvalue),
+                Token::Bytes(message.data.clone()),
+            ])),
+            ..serde_json::from_value(json!({
+                "address": Address::zero(),
+                "topics": [],
+                "data": "0x",
+            }))
+            .unwrap()
+        };
+        let receipt = TransactionReceipt {
+            logs: vec![log],
+            ..serde_json::from_value(json!({
+                "transactionHash": H256::zero(),
+                "transactionIndex": "0x0",
+                "cumulativeGasUsed": "0x0",
+                "logs": [],
+                "logsBloom": H2048::zero(),
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            L2ToL1Message::from_receipt(&receipt).unwrap(),
+            vec![message.clone()],
+        );
+
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        transport.add_response(json!(format!(
+            "0x{}",
+            hex::encode(abi::encode(&[
+                Token::FixedBytes(vec![1; 32]),
+                Token::FixedBytes(vec![2; 32]),
+                Token::Array(vec![Token::FixedBytes(vec![3; 32])]),
+            ])),
+        )));
+        let proof = NodeInterface::new(&web3)
+            .outbox_proof(10, 4)
+            .immediate()
+            .unwrap();
+        assert_eq!(
+            proof,
+            OutboxProof {
+                send: H256::repeat_byte(1),
+                root: H256::repeat_byte(2),
+                proof: vec![H256::repeat_byte(3)],
+            },
+        );
+
+        let tx = Outbox::at(&web3, Address::repeat_byte(0xaa))
+            .execute_transaction(&message, &proof)
+            .tx;
+        assert_eq!(tx.to, Some(Address::repeat_byte(0xaa)));
+        assert_eq!(
+            tx.data.unwrap().0[4..],
+            abi::encode(&[
+                Token::Array(vec![Token::FixedBytes(vec![3; 32])]),
+                Token::Uint(4.into()),
+                Token::Address(message.caller),
+                Token::Address(message.destination),
+                Token::Uint(5.into()),
+                Token::Uint(6.into()),
+                Token::Uint(7.into()),
+                Token::Uint(8.into()),
+                Token::Bytes(vec![9]),
+            ])[..],
+        );
+    }
+}
@@ -0,0 +1,1027 @@
+//! Deposits and withdrawals for OP-stack chains.
+//!
+//! Deposits are sent on L1 through the `L1StandardBridge` for ETH and tokens,
+//! or through the `OptimismPortal` for arbitrary L2 transactions.
+//!
+//! Withdrawals are initiated on L2 through the `L2ToL1MessagePasser` or the
+//! `L2StandardBridge`, and then need to be proven and finalized on L1:
+//!
+//! 1. Once an output containing the L2 block of the withdrawal is proposed
+//!    on L1, the withdrawal is proven with [`OptimismPortal::prove_withdrawal`]
+//!    using the proofs retrieved from L2 with [`withdrawal_proof`].
+//! 2. Once the proof is older than the finalization period, the withdrawal
+//!    is finalized with [`OptimismPortal::finalize_withdrawal`].
+//!
+//! The status of a withdrawal can be tracked with
+//! [`OptimismPortal::withdrawal_status`].
+
+use super::{call, instance, method};
+use crate::contract::{Instance, MethodBuilder, RawLog};
+use crate::errors::{ExecutionError, MethodError};
+use crate::rpc;
+use crate::tokens::Bytes as TokenBytes;
+use ethcontract_common::abi::{self, Token};
+use ethcontract_common::hash::keccak256;
+use serde::Deserialize;
+use web3::api::Web3;
+use web3::types::{Address, BlockId, Bytes, TransactionReceipt, H160, H256, U256};
+use web3::Transport;
+
+/// The address of the `L2ToL1MessagePasser` predeploy.
+pub const L2_TO_L1_MESSAGE_PASSER: Address = H160([
+    0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x16,
+]);
+
+/// The address of the `L2StandardBridge` predeploy.
+pub const L2_STANDARD_BRIDGE: Address = H160([
+    0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x10,
+]);
+
+/// The address representing ETH for withdrawals through the
+/// `L2StandardBridge`.
+pub const LEGACY_ERC20_ETH: Address = H160([
+    0xde, 0xad, 0xde, 0xad, 0xde, 0xad, 0xde, 0xad, 0xde, 0xad, 0xde, 0xad, 0xde, 0xad, 0xde, 0xad,
+    0xde, 0xad, 0x00, 0x00,
+]);
+
+const L1_STANDARD_BRIDGE_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "depositETHTo",
+        "inputs": [
+            { "name": "_to", "type": "address" },
+            { "name": "_minGasLimit", "type": "uint32" },
+            { "name": "_extraData", "type": "bytes" }
+        ],
+        "outputs": [],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "function",
+        "name": "depositERC20To",
+        "inputs": [
+            { "name": "_l1Token", "type": "address" },
+            { "name": "_l2Token", "type": "address" },
+            { "name": "_to", "type": "address" },
+            { "name": "_amount", "type": "uint256" },
+            { "name": "_minGasLimit", "type": "uint32" },
+            { "name": "_extraData", "type": "bytes" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }
+]"#;
+
+const OPTIMISM_PORTAL_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "depositTransaction",
+        "inputs": [
+            { "name": "_to", "type": "address" },
+            { "name": "_value", "type": "uint256" },
+            { "name": "_gasLimit", "type": "uint64" },
+            { "name": "_isCreation", "type": "bool" },
+            { "name": "_data", "type": "bytes" }
+        ],
+        "outputs": [],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "function",
+        "name": "proveWithdrawalTransaction",
+        "inputs": [
+            {
+                "name": "_tx",
+                "type": "tuple",
+                "components": [
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "sender", "type": "address" },
+                    { "name": "target", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "gasLimit", "type": "uint256" },
+                    { "name": "data", "type": "bytes" }
+                ]
+            },
+            { "name": "_l2OutputIndex", "type": "uint256" },
+            {
+                "name": "_outputRootProof",
+                "type": "tuple",
+                "components": [
+                    { "name": "version", "type": "bytes32" },
+                    { "name": "stateRoot", "type": "bytes32" },
+                    { "name": "messagePasserStorageRoot", "type": "bytes32" },
+                    { "name": "latestBlockhash", "type": "bytes32" }
+                ]
+            },
+            { "name": "_withdrawalProof", "type": "bytes[]" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    },
+    {
+        "type": "function",
+        "name": "finalizeWithdrawalTransaction",
+        "inputs": [
+            {
+                "name": "_tx",
+                "type": "tuple",
+                "components": [
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "sender", "type": "address" },
+                    { "name": "target", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "gasLimit", "type": "uint256" },
+                    { "name": "data", "type": "bytes" }
+                ]
+            }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    },
+    {
+        "type": "function",
+        "name": "finalizedWithdrawals",
+        "inputs": [{ "name": "", "type": "bytes32" }],
+        "outputs": [{ "name": "", "type": "bool" }],
+        "stateMutability": "view"
+    },
+    {
+        "type": "function",
+        "name": "provenWithdrawals",
+        "inputs": [{ "name": "", "type": "bytes32" }],
+        "outputs": [
+            { "name": "outputRoot", "type": "bytes32" },
+            { "name": "timestamp", "type": "uint128" },
+            { "name": "l2OutputIndex", "type": "uint128" }
+        ],
+        "stateMutability": "view"
+    },
+    {
+        "type": "function",
+        "name": "provenWithdrawals",
+        "inputs": [
+            { "name": "", "type": "bytes32" },
+            { "name": "", "type": "address" }
+        ],
+        "outputs": [
+            { "name": "disputeGameProxy", "type": "address" },
+            { "name": "timestamp", "type": "uint64" }
+        ],
+        "stateMutability": "view"
+    }
+]"#;
+
+const L2_OUTPUT_ORACLE_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "getL2OutputIndexAfter",
+        "inputs": [{ "name": "_l2BlockNumber", "type": "uint256" }],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "view"
+    },
+    {
+        "type": "function",
+        "name": "getL2Output",
+        "inputs": [{ "name": "_l2OutputIndex", "type": "uint256" }],
+        "outputs": [
+            {
+                "name": "",
+                "type": "tuple",
+                "components": [
+                    { "name": "outputRoot", "type": "bytes32" },
+                    { "name": "timestamp", "type": "uint128" },
+                    { "name": "l2BlockNumber", "type": "uint128" }
+                ]
+            }
+        ],
+        "stateMutability": "view"
+    },
+    {
+        "type": "function",
+        "name": "FINALIZATION_PERIOD_SECONDS",
+        "inputs": [],
+        "outputs": [{ "name": "", "type": "uint256" }],
+        "stateMutability": "view"
+    }
+]"#;
+
+const L2_BRIDGE_ABI: &str = r#"[
+    {
+        "type": "function",
+        "name": "initiateWithdrawal",
+        "inputs": [
+            { "name": "_target", "type": "address" },
+            { "name": "_gasLimit", "type": "uint256" },
+            { "name": "_data", "type": "bytes" }
+        ],
+        "outputs": [],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "function",
+        "name": "withdrawTo",
+        "inputs": [
+            { "name": "_l2Token", "type": "address" },
+            { "name": "_to", "type": "address" },
+            { "name": "_amount", "type": "uint256" },
+            { "name": "_minGasLimit", "type": "uint32" },
+            { "name": "_extraData", "type": "bytes" }
+        ],
+        "outputs": [],
+        "stateMutability": "payable"
+    },
+    {
+        "type": "event",
+        "name": "MessagePassed",
+        "inputs": [
+            { "name": "nonce", "type": "uint256", "indexed": true },
+            { "name": "sender", "type": "address", "indexed": true },
+            { "name": "target", "type": "address", "indexed": true },
+            { "name": "value", "type": "uint256", "indexed": false },
+            { "name": "gasLimit", "type": "uint256", "indexed": false },
+            { "name": "data", "type": "bytes", "indexed": false },
+            { "name": "withdrawalHash", "type": "bytes32", "indexed": false }
+        ],
+        "anonymous": false
+    }
+]"#;
+
+/// The ABI tuple of a withdrawal transaction.
+type WithdrawalTuple = (U256, Address, Address, U256, U256, TokenBytes<Vec<u8>>);
+
+/// The decoded data of a `MessagePassed` event.
+type MessagePassedData = (
+    U256,
+    Address,
+    Address,
+    U256,
+    U256,
+    TokenBytes<Vec<u8>>,
+    TokenBytes<[u8; 32]>,
+);
+
+/// The ABI tuple of an output root proof.
+type OutputRootProofTuple = (
+    TokenBytes<[u8; 32]>,
+    TokenBytes<[u8; 32]>,
+    TokenBytes<[u8; 32]>,
+    TokenBytes<[u8; 32]>,
+);
+
+/// The `L1StandardBridge` contract for depositing ETH and tokens from L1.
+#[derive(Clone, Debug)]
+pub struct L1StandardBridge<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> L1StandardBridge<T> {
+    /// Creates a bridge instance at the specified address.
+    pub fn at(web3: &Web3<T>, address: Address) -> Self {
+        L1StandardBridge {
+            instance: instance(web3, L1_STANDARD_BRIDGE_ABI, address),
+        }
+    }
+
+    /// Returns the underlying contract instance.
+    pub fn raw_instance(&self) -> &Instance<T> {
+        &self.instance
+    }
+
+    /// Deposits ETH to an account on L2. The minimum gas limit is the gas
+    /// provided for executing the deposit on L2.
+    pub fn deposit_eth_to(
+        &self,
+        to: Address,
+        amount: U256,
+        min_gas_limit: u32,
+        extra_data: Vec<u8>,
+    ) -> MethodBuilder<T, ()> {
+        method::<_, _, ()>(
+            &self.instance,
+            "depositETHTo(address,uint32,bytes)",
+            (to, min_gas_limit, TokenBytes(extra_data)),
+        )
+        .value(amount)
+    }
+
+    /// Deposits an ERC20 token to an account on L2, which must be approved
+    /// for the bridge first.
+    pub fn deposit_erc20_to(
+        &self,
+        l1_token: Address,
+        l2_token: Address,
+        to: Address,
+        amount: U256,
+        min_gas_limit: u32,
+        extra_data: Vec<u8>,
+    ) -> MethodBuilder<T, ()> {
+        method(
+            &self.instance,
+            "depositERC20To(address,address,address,uint256,uint32,bytes)",
+            (
+                l1_token,
+                l2_token,
+                to,
+                amount,
+                min_gas_limit,
+                TokenBytes(extra_data),
+            ),
+        )
+    }
+}
+
+/// The `OptimismPortal` contract for sending deposit transactions and for
+/// proving and finalizing withdrawals on L1.
+#[derive(Clone, Debug)]
+pub struct OptimismPortal<T: Transport> {
+    instance: Instance<T>,
+}
+
+/// The status of a withdrawal on L1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WithdrawalStatus {
+    /// The withdrawal was not proven yet.
+    Initiated,
+    /// The withdrawal was proven at the specified timestamp, and can be
+    /// finalized once the finalization period has passed.
+    Proven {
+        /// The timestamp at which the withdrawal was proven.
+        timestamp: u64,
+    },
+    /// The withdrawal was finalized.
+    Finalized,
+}
+
+impl WithdrawalStatus {
+    /// Returns whether a withdrawal can be finalized at the specified time
+    /// with the finalization period of the chain in seconds.
+    pub fn is_ready_to_finalize(self, finalization_period: u64, now: u64) -> bool {
+        match self {
+            WithdrawalStatus::Proven { timestamp } => {
+                now >= timestamp.saturating_add(finalization_period)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<T: Transport> OptimismPortal<T> {
+    /// Creates a portal instance at the specified address.
+    pub fn at(web3: &Web3<T>, address: Address) -> Self {
+        OptimismPortal {
+            instance: instance(web3, OPTIMISM_PORTAL_ABI, address),
+        }
+    }
+
+    /// Returns the underlying contract instance.
+    pub fn raw_instance(&self) -> &Instance<T> {
+        &self.instance
+    }
+
+    /// Sends a deposit transaction that is executed on L2 with the specified
+    /// gas limit, with the sender being the aliased address for contracts.
+    /// The value is sent from L1 and minted on L2.
+    pub fn deposit_transaction(
+        &self,
+        to: Address,
+        value: U256,
+        gas_limit: u64,
+        data: Vec<u8>,
+    ) -> MethodBuilder<T, ()> {
+        method::<_, _, ()>(
+            &self.instance,
+            "depositTransaction(address,uint256,uint64,bool,bytes)",
+            (to, value, gas_limit, false, TokenBytes(data)),
+        )
+        .value(value)
+    }
+
+    /// Proves a withdrawal against the output with the specified index, or
+    /// against the dispute game with the specified index for portals with
+    /// fault proofs.
+    pub fn prove_withdrawal(
+        &self,
+        withdrawal: &Withdrawal,
+        l2_output_index: U256,
+        proof: WithdrawalProof,
+    ) -> MethodBuilder<T, ()> {
+        let storage_proof = proof
+            .storage_proof
+            .into_iter()
+            .map(|node| TokenBytes(node.0))
+            .collect::<Vec<_>>();
+        method(
+            &self.instance,
+            "proveWithdrawalTransaction(\
+                (uint256,address,address,uint256,uint256,bytes),\
+                uint256,\
+                (bytes32,bytes32,bytes32,bytes32),\
+                bytes[]\
+            )",
+            (
+                withdrawal.clone().into_tuple(),
+                l2_output_index,
+                proof.output_root_proof.into_tuple(),
+                storage_proof,
+            ),
+        )
+    }
+
+    /// Finalizes a proven withdrawal after the finalization period.
+    pub fn finalize_withdrawal(&self, withdrawal: &Withdrawal) -> MethodBuilder<T, ()> {
+        method(
+            &self.instance,
+            "finalizeWithdrawalTransaction((uint256,address,address,uint256,uint256,bytes))",
+            (withdrawal.clone().into_tuple(),),
+        )
+    }
+
+    /// Returns the status of a withdrawal. For portals with fault proofs,
+    /// proofs are tracked per account that submitted them, which must be
+    /// specified as the prover.
+    pub async fn withdrawal_status(
+        &self,
+        withdrawal_hash: H256,
+        prover: Option<Address>,
+    ) -> Result<WithdrawalStatus, MethodError> {
+        let hash = TokenBytes(withdrawal_hash.0);
+        let finalized: bool =
+            call(&self.instance, "finalizedWithdrawals(bytes32)", (hash,)).await?;
+        if finalized {
+            return Ok(WithdrawalStatus::Finalized);
+        }
+
+        let timestamp = match prover {
+            Some(prover) => {
+                let (_, timestamp): (Address, u64) = call(
+                    &self.instance,
+                    "provenWithdrawals(bytes32,address)",
+                    (hash, prover),
+                )
+                .await?;
+                timestamp
+            }
+            None => {
+                let (_, timestamp, _): (TokenBytes<[u8; 32]>, u128, u128) =
+                    call(&self.instance, "provenWithdrawals(bytes32)", (hash,)).await?;
+                timestamp as u64
+            }
+        };
+        Ok(match timestamp {
+            0 => WithdrawalStatus::Initiated,
+            timestamp => WithdrawalStatus::Proven { timestamp },
+        })
+    }
+}
+
+/// An output proposed to the `L2OutputOracle`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct L2Output {
+    /// The output root.
+    pub output_root: H256,
+    /// The timestamp at which the output was proposed.
+    pub timestamp: u64,
+    /// The L2 block of the output.
+    pub l2_block_number: u64,
+}
+
+/// The `L2OutputOracle` contract storing the L2 outputs proposed on L1, for
+/// finding the output to prove a withdrawal against on chains without fault
+/// proofs.
+#[derive(Clone, Debug)]
+pub struct L2OutputOracle<T: Transport> {
+    instance: Instance<T>,
+}
+
+impl<T: Transport> L2OutputOracle<T> {
+    /// Creates an oracle instance at the specified address.
+    pub fn at(web3: &Web3<T>, address: Address) -> Self {
+        L2OutputOracle {
+            instance: instance(web3, L2_OUTPUT_ORACLE_ABI, address),
+        }
+    }
+
+    /// Returns the underlying contract instance.
+    pub fn raw_instance(&self) -> &Instance<T> {
+        &self.instance
+    }
+
+    /// Returns the index of the first output that contains the specified L2
+    /// block. The call reverts if no such output was proposed yet.
+    pub async fn output_index_after(&self, l2_block_number: u64) -> Result<U256, MethodError> {
+        call(
+            &self.instance,
+            "getL2OutputIndexAfter(uint256)",
+            (U256::from(l2_block_number),),
+        )
+        .await
+    }
+
+    /// Returns the output with the specified index.
+    pub async fn output(&self, index: U256) -> Result<L2Output, MethodError> {
+        let (output_root, timestamp, l2_block_number): (TokenBytes<[u8; 32]>, u128, u128) =
+            call(&self.instance, "getL2Output(uint256)", (index,)).await?;
+        Ok(L2Output {
+            output_root: H256(output_root.0),
+            timestamp: timestamp as u64,
+            l2_block_number: l2_block_number as u64,
+        })
+    }
+
+    /// Returns the finalization period of withdrawals in seconds.
+    pub async fn finalization_period(&self) -> Result<u64, MethodError> {
+        let period: U256 = call(&self.instance, "FINALIZATION_PERIOD_SECONDS()", ()).await?;
+        Ok(period.low_u64())
+    }
+}
+
+/// The `L2ToL1MessagePasser` and `L2StandardBridge` predeploys for
+/// initiating withdrawals on L2.
+#[derive(Clone, Debug)]
+pub struct L2Bridge<T: Transport> {
+    message_passer: Instance<T>,
+    standard_bridge: Instance<T>,
+}
+
+impl<T: Transport> L2Bridge<T> {
+    /// Creates an instance of the predeploys at their standard addresses.
+    pub fn new(web3: &Web3<T>) -> Self {
+        L2Bridge {
+            message_passer: instance(web3, L2_BRIDGE_ABI, L2_TO_L1_MESSAGE_PASSER),
+            standard_bridge: instance(web3, L2_BRIDGE_ABI, L2_STANDARD_BRIDGE),
+        }
+    }
+
+    /// Initiates a withdrawal that calls the target on L1 with the specified
+    /// gas limit and value.
+    pub fn initiate_withdrawal(
+        &self,
+        target: Address,
+        value: U256,
+        gas_limit: U256,
+        data: Vec<u8>,
+    ) -> MethodBuilder<T, ()> {
+        method::<_, _, ()>(
+            &self.message_passer,
+            "initiateWithdrawal(address,uint256,bytes)",
+            (target, gas_limit, TokenBytes(data)),
+        )
+        .value(value)
+    }
+
+    /// Withdraws ETH to an account on L1.
+    pub fn withdraw_eth_to(
+        &self,
+        to: Address,
+        amount: U256,
+        min_gas_limit: u32,
+        extra_data: Vec<u8>,
+    ) -> MethodBuilder<T, ()> {
+        self.withdraw_to(LEGACY_ERC20_ETH, to, amount, min_gas_limit, extra_data)
+            .value(amount)
+    }
+
+    /// Withdraws a bridged token to an account on L1.
+    pub fn withdraw_to(
+        &self,
+        l2_token: Address,
+        to: Address,
+        amount: U256,
+        min_gas_limit: u32,
+        extra_data: Vec<u8>,
+    ) -> MethodBuilder<T, ()> {
+        method(
+            &self.standard_bridge,
+            "withdrawTo(address,address,uint256,uint32,bytes)",
+            (l2_token, to, amount, min_gas_limit, TokenBytes(extra_data)),
+        )
+    }
+}
+
+/// A withdrawal initiated on L2.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Withdrawal {
+    /// The nonce of the withdrawal.
+    pub nonce: U256,
+    /// The account that initiated the withdrawal on L2.
+    pub sender: Address,
+    /// The account that is called on L1.
+    pub target: Address,
+    /// The amount of ETH sent with the call.
+    pub value: U256,
+    /// The gas limit of the call.
+    pub gas_limit: U256,
+    /// The calldata of the call.
+    pub data: Bytes,
+}
+
+impl Withdrawal {
+    /// Returns the withdrawals initiated by an L2 transaction, which are
+    /// decoded from the `MessagePassed` events of its receipt.
+    pub fn from_receipt(receipt: &TransactionReceipt) -> Result<Vec<Self>, ExecutionError> {
+        let abi = ethcontract_common::Abi::load(L2_BRIDGE_ABI.as_bytes())
+            .expect("bridge contract ABIs are valid");
+        let event = abi
+            .event("MessagePassed")
+            .expect("bridge contract events match their ABIs");
+        receipt
+            .logs
+            .iter()
+            .filter(|log| {
+                log.address == L2_TO_L1_MESSAGE_PASSER
+                    && log.topics.first() == Some(&event.signature())
+            })
+            .map(|log| {
+                let (nonce, sender, target, value, gas_limit, data, _): MessagePassedData =
+                    RawLog::from(log.clone()).decode(event)?;
+                Ok(Withdrawal {
+                    nonce,
+                    sender,
+                    target,
+                    value,
+                    gas_limit,
+                    data: Bytes(data.0),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the hash identifying the withdrawal, which is
+    /// `keccak256(abi.encode(nonce, sender, target, value, gasLimit, data))`.
+    pub fn hash(&self) -> H256 {
+        H256(keccak256(abi::encode(&[
+            Token::Uint(self.nonce),
+            Token::Address(self.sender),
+            Token::Address(self.target),
+            Token::Uint(self.value),
+            Token::Uint(self.gas_limit),
+            Token::Bytes(self.data.0.clone()),
+        ])))
+    }
+
+    /// Returns the storage slot of the `L2ToL1MessagePasser` recording that
+    /// the withdrawal was initiated, which is proven on L1.
+    pub fn storage_slot(&self) -> H256 {
+        H256(keccak256(abi::encode(&[
+            Token::FixedBytes(self.hash().as_bytes().to_vec()),
+            Token::Uint(U256::zero()),
+        ])))
+    }
+
+    fn into_tuple(self) -> WithdrawalTuple {
+        (
+            self.nonce,
+            self.sender,
+            self.target,
+            self.value,
+            self.gas_limit,
+            TokenBytes(self.data.0),
+        )
+    }
+}
+
+/// The preimage of an L2 output root.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OutputRootProof {
+    /// The version of the output root.
+    pub version: H256,
+    /// The state root of the L2 block.
+    pub state_root: H256,
+    /// The storage root of the `L2ToL1MessagePasser` at the L2 block.
+    pub message_passer_storage_root: H256,
+    /// The hash of the L2 block.
+    pub latest_blockhash: H256,
+}
+
+impl OutputRootProof {
+    /// Returns the output root, which is
+    /// `keccak256(abi.encode(version, stateRoot, messagePasserStorageRoot, latestBlockhash))`.
+    pub fn output_root(&self) -> H256 {
+        let words = [
+            self.version.as_bytes(),
+            self.state_root.as_bytes(),
+            self.message_passer_storage_root.as_bytes(),
+            self.latest_blockhash.as_bytes(),
+        ];
+        H256(keccak256(words.concat()))
+    }
+
+    fn into_tuple(self) -> OutputRootProofTuple {
+        (
+            TokenBytes(self.version.0),
+            TokenBytes(self.state_root.0),
+            TokenBytes(self.message_passer_storage_root.0),
+            TokenBytes(self.latest_blockhash.0),
+        )
+    }
+}
+
+/// The proofs for proving a withdrawal on L1.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WithdrawalProof {
+    /// The preimage of the output root of the L2 block.
+    pub output_root_proof: OutputRootProof,
+    /// The storage proof of the withdrawal in the `L2ToL1MessagePasser`.
+    pub storage_proof: Vec<Bytes>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountProof {
+    storage_hash: H256,
+    storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Deserialize)]
+struct StorageProof {
+    proof: Vec<Bytes>,
+}
+
+/// Retrieves the proofs for proving a withdrawal against the output of the
+/// specified L2 block with `eth_getProof`. The block must be the L2 block of
+/// the output or dispute game that the withdrawal is proven against.
+pub async fn withdrawal_proof<T: Transport>(
+    l2: &Web3<T>,
+    withdrawal: &Withdrawal,
+    l2_block_number: u64,
+) -> Result<WithdrawalProof, ExecutionError> {
+    let block = l2
+        .eth()
+        .block(BlockId::Number(l2_block_number.into()))
+        .await?
+        .ok_or(ExecutionError::MissingBlock)?;
+    let proof: AccountProof = rpc::call(
+        l2,
+        "eth_getProof",
+        (
+            L2_TO_L1_MESSAGE_PASSER,
+            [withdrawal.storage_slot()],
+            format!("{:#x}", l2_block_number),
+        ),
+    )
+    .await?;
+
+    Ok(WithdrawalProof {
+        output_root_proof: OutputRootProof {
+            version: H256::zero(),
+            state_root: block.state_root,
+            message_passer_storage_root: proof.storage_hash,
+            latest_blockhash: block.hash.ok_or(ExecutionError::MissingBlock)?,
+        },
+        storage_proof: proof
+            .storage_proof
+            .into_iter()
+            .next()
+            .map(|storage| storage.proof)
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use ethcontract_common::hash::function_selector;
+    use web3::types::{Log, H2048, U64};
+
+    fn withdrawal() -> Withdrawal {
+        Withdrawal {
+            nonce: U256::from(1) << 240,
+            sender: Address::repeat_byte(0x11),
+            target: Address::repeat_byte(0x22),
+            value: 1_000.into(),
+            gas_limit: 100_000.into(),
+            data: Bytes(vec![0xde, 0xad]),
+        }
+    }
+
+    fn word(value: impl Into<U256>) -> String {
+        format!("{:064x}", value.into())
+    }
+
+    fn address(address: Address) -> String {
+        hex::encode(H256::from(address))
+    }
+
+    #[test]
+    fn withdrawal_hash_and_slot() {
+        let encoded = [
+            word(U256::from(1) << 240),
+            address(Address::repeat_byte(0x11)),
+            address(Address::repeat_byte(0x22)),
+            word(1_000),
+            word(100_000),
+            word(0xc0),
+            word(2),
+            format!("dead{}", "0".repeat(60)),
+        ]
+        .concat();
+        let hash = H256(keccak256(hex::decode(encoded).unwrap()));
+        assert_eq!(withdrawal().hash(), hash);
+
+        let slot = H256(keccak256([hash.as_bytes(), &[0; 32]].concat()));
+        assert_eq!(withdrawal().storage_slot(), slot);
+    }
+
+    #[test]
+    fn withdrawals_from_receipt() {
+        let withdrawal = withdrawal();
+        let log = Log {
+            address: L2_TO_L1_MESSAGE_PASSER,
+            topics: vec![
+                H256(keccak256(
+                    "MessagePassed(uint256,address,address,uint256,uint256,bytes,bytes32)",
+                )),
+                H256::from_slice(&hex::decode(word(withdrawal.nonce)).unwrap()),
+                withdrawal.sender.into(),
+                withdrawal.target.into(),
+            ],
+            data: Bytes(
+                abi::encode(&[
+                    Token::Uint(withdrawal.value),
+                    Token::Uint(withdrawal.gas_limit),
+                    Token::Bytes(withdrawal.data.0.clone()),
+                    Token::FixedBytes(withdrawal.hash().as_bytes().to_vec()),
+                ])
+                .to_vec(),
+            ),
+            ..serde_json::from_value(json!({
+                "address": Address::zero(),
+                "topics": [],
+                "data": "0x",
+            }))
+            .unwrap()
+        };
+        let other = Log {
+            address: Address::repeat_byte(1),
+            ..log.clone()
+        };
+        let receipt = TransactionReceipt {
+            logs: vec![other, log],
+            ..serde_json::from_value(json!({
+                "transactionHash": H256::zero(),
+                "transactionIndex": "0x0",
+                "cumulativeGasUsed": "0x0",
+                "logs": [],
+                "logsBloom": H2048::zero(),
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            Withdrawal::from_receipt(&receipt).unwrap(),
+            vec![withdrawal]
+        );
+    }
+
+    #[test]
+    fn deposits_and_withdrawals() {
+        let web3 = Web3::new(TestTransport::new());
+        let to = Address::repeat_byte(0x42);
+
+        let deposit = L1StandardBridge::at(&web3, Address::repeat_byte(1))
+            .deposit_eth_to(to, 1_000.into(), 200_000, vec![])
+            .tx;
+        assert_eq!(deposit.to, Some(Address::repeat_byte(1)));
+        assert_eq!(deposit.value, Some(1_000.into()));
+        assert_eq!(
+            deposit.data.unwrap().0,
+            [
+                &function_selector("depositETHTo(address,uint32,bytes)")[..],
+                &hex::decode([address(to), word(200_000), word(0x60), word(0)].concat()).unwrap(),
+            ]
+            .concat(),
+        );
+
+        let withdrawal = L2Bridge::new(&web3)
+            .withdraw_eth_to(to, 1_000.into(), 0, vec![])
+            .tx;
+        assert_eq!(withdrawal.to, Some(L2_STANDARD_BRIDGE));
+        assert_eq!(withdrawal.value, Some(1_000.into()));
+        assert_eq!(
+            &withdrawal.data.unwrap().0[4..36],
+            H256::from(LEGACY_ERC20_ETH).as_bytes(),
+        );
+
+        let portal = OptimismPortal::at(&web3, Address::repeat_byte(2));
+        let prove = portal
+            .prove_withdrawal(&self::withdrawal(), 3.into(), WithdrawalProof::default())
+            .tx;
+        assert_eq!(
+            prove.data.unwrap().0[..4],
+            function_selector(
+                "proveWithdrawalTransaction(\
+                    (uint256,address,address,uint256,uint256,bytes),\
+                    uint256,\
+                    (bytes32,bytes32,bytes32,bytes32),\
+                    bytes[]\
+                )"
+            ),
+        );
+    }
+
+    #[test]
+    fn withdrawal_status() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let portal = OptimismPortal::at(&web3, Address::repeat_byte(2));
+        let hash = H256::repeat_byte(0x33);
+
+        transport.add_response(json!(format!("0x{}", word(0))));
+        transport.add_response(json!(format!("0x{}{}{}", word(1), word(1_000), word(0))));
+        let status = portal.withdrawal_status(hash, None).immediate().unwrap();
+        assert_eq!(status, WithdrawalStatus::Proven { timestamp: 1_000 });
+        assert!(!status.is_ready_to_finalize(100, 1_099));
+        assert!(status.is_ready_to_finalize(100, 1_100));
+
+        transport.add_response(json!(format!("0x{}", word(0))));
+        transport.add_response(json!(format!("0x{}{}", word(0), word(0))));
+        let status = portal
+            .withdrawal_status(hash, Some(Address::repeat_byte(3)))
+            .immediate()
+            .unwrap();
+        assert_eq!(status, WithdrawalStatus::Initiated);
+
+        transport.add_response(json!(format!("0x{}", word(1))));
+        let status = portal.withdrawal_status(hash, None).immediate().unwrap();
+        assert_eq!(status, WithdrawalStatus::Finalized);
+        assert!(!status.is_ready_to_finalize(0, u64::MAX));
+    }
+
+    #[test]
+    fn retrieves_withdrawal_proof() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!({
+            "hash": H256::repeat_byte(5),
+            "parentHash": H256::zero(),
+            "sha3Uncles": H256::zero(),
+            "miner": Address::zero(),
+            "stateRoot": H256::repeat_byte(6),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": U64::from(42),
+            "gasUsed": "0x0",
+            "gasLimit": "0x0",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x0",
+            "difficulty": "0x0",
+            "uncles": [],
+            "transactions": [],
+        }));
+        transport.add_response(json!({
+            "address": L2_TO_L1_MESSAGE_PASSER,
+            "accountProof": [],
+            "balance": "0x0",
+            "codeHash": H256::zero(),
+            "nonce": "0x0",
+            "storageHash": H256::repeat_byte(7),
+            "storageProof": [{
+                "key": withdrawal().storage_slot(),
+                "value": "0x1",
+                "proof": ["0x01", "0x02"],
+            }],
+        }));
+
+        let proof = withdrawal_proof(&web3, &withdrawal(), 42)
+            .immediate()
+            .unwrap();
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x2a"), json!(false)]);
+        transport.assert_request(
+            "eth_getProof",
+            &[
+                json!(L2_TO_L1_MESSAGE_PASSER),
+                json!([withdrawal().storage_slot()]),
+                json!("0x2a"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        let output_root_proof = OutputRootProof {
+            version: H256::zero(),
+            state_root: H256::repeat_byte(6),
+            message_passer_storage_root: H256::repeat_byte(7),
+            latest_blockhash: H256::repeat_byte(5),
+        };
+        assert_eq!(
+            proof,
+            WithdrawalProof {
+                output_root_proof,
+                storage_proof: vec![Bytes(vec![1]), Bytes(vec![2])],
+            },
+        );
+        assert_eq!(
+            output_root_proof.output_root(),
+            H256(keccak256(abi::encode(&[
+                Token::FixedBytes(vec![0; 32]),
+                Token::FixedBytes(vec![6; 32]),
+                Token::FixedBytes(vec![7; 32]),
+                Token::FixedBytes(vec![5; 32]),
+            ]))),
+        );
+    }
+}
@@ -0,0 +1,775 @@
+//! Utilities for analyzing the code of deployed contracts, for example for
+//! tooling that identifies unknown contracts.
+//!
+//! Deployed code can be disassembled into its instructions, and the function
+//! selectors of the dispatcher generated by the Solidity compiler can be
+//! extracted from it. Additionally, the metadata appended to the code by the
+//! Solidity compiler can be decoded, and EIP-1167 minimal proxies and EIP-1967
+//! proxies can be detected.
+
+use crate::errors::ExecutionError;
+use ethcontract_common::hash::H32;
+use ethcontract_common::ipfs::Cid;
+use futures::future;
+use std::fmt::{self, Display, Formatter};
+use web3::api::Web3;
+use web3::types::{Address, BlockNumber, H256, U256};
+use web3::Transport;
+
+/// The EIP-1967 storage slot of the implementation address of a proxy, which
+/// is `keccak256("eip1967.proxy.implementation") - 1`.
+pub const IMPLEMENTATION_SLOT: H256 = H256([
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+]);
+
+/// The EIP-1967 storage slot of the admin address of a proxy, which is
+/// `keccak256("eip1967.proxy.admin") - 1`.
+pub const ADMIN_SLOT: H256 = H256([
+    0xb5, 0x31, 0x27, 0x68, 0x4a, 0x56, 0x8b, 0x31, 0x73, 0xae, 0x13, 0xb9, 0xf8, 0xa6, 0x01, 0x6e,
+    0x24, 0x3e, 0x63, 0xb6, 0xe8, 0xee, 0x11, 0x78, 0xd6, 0xa7, 0x17, 0x85, 0x0b, 0x5d, 0x61, 0x03,
+]);
+
+/// The EIP-1967 storage slot of the beacon address of a beacon proxy, which
+/// is `keccak256("eip1967.proxy.beacon") - 1`.
+pub const BEACON_SLOT: H256 = H256([
+    0xa3, 0xf0, 0xad, 0x74, 0xe5, 0x42, 0x3a, 0xeb, 0xfd, 0x80, 0xd3, 0xef, 0x43, 0x46, 0x57, 0x83,
+    0x35, 0xa9, 0xa7, 0x2a, 0xea, 0xee, 0x59, 0xff, 0x6c, 0xb3, 0x58, 0x2b, 0x35, 0x13, 0x3d, 0x50,
+]);
+
+/// The code of an EIP-1167 minimal proxy before the implementation address.
+const MINIMAL_PROXY_PREFIX: &[u8] = &[0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+
+/// The code of an EIP-1167 minimal proxy after the implementation address.
+const MINIMAL_PROXY_SUFFIX: &[u8] = &[
+    0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3,
+];
+
+/// The creation code of an EIP-1167 minimal proxy, which returns the 45 bytes
+/// of runtime code that follow it.
+const MINIMAL_PROXY_CONSTRUCTOR: &[u8] =
+    &[0x3d, 0x60, 0x2d, 0x80, 0x60, 0x0a, 0x3d, 0x39, 0x81, 0xf3];
+
+/// The prefix of the code of accounts delegating to a contract with EIP-7702.
+const DELEGATION_PREFIX: &[u8] = &[0xef, 0x01, 0x00];
+
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+const DUP1: u8 = 0x80;
+const DUP16: u8 = 0x8f;
+const EQ: u8 = 0x14;
+const JUMPI: u8 = 0x57;
+const JUMPDEST: u8 = 0x5b;
+
+/// An instruction of disassembled code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Instruction<'a> {
+    /// The offset of the instruction in the code.
+    pub offset: usize,
+    /// The opcode of the instruction.
+    pub opcode: u8,
+    /// The immediate data pushed by `PUSHn` instructions. This is shorter
+    /// than `n` bytes if the code ends before the end of the data.
+    pub immediate: &'a [u8],
+}
+
+impl Instruction<'_> {
+    /// Returns the mnemonic of the instruction, or `None` if its opcode is
+    /// not defined.
+    pub fn mnemonic(&self) -> Option<&'static str> {
+        mnemonic(self.opcode)
+    }
+
+    /// Returns the value pushed onto the stack by `PUSHn` instructions.
+    pub fn push_value(&self) -> Option<U256> {
+        if (PUSH1..=PUSH32).contains(&self.opcode) {
+            Some(U256::from_big_endian(self.immediate))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Instruction<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.mnemonic() {
+            Some(mnemonic) => f.write_str(mnemonic)?,
+            None => write!(f, "UNKNOWN(0x{:02x})", self.opcode)?,
+        }
+        if !self.immediate.is_empty() {
+            write!(f, " 0x{}", hex::encode(self.immediate))?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the instructions of code, created with
+/// [`instructions`].
+#[derive(Clone, Debug)]
+pub struct Instructions<'a> {
+    code: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Instruction<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opcode = *self.code.get(self.offset)?;
+        let start = self.offset + 1;
+        let end = start + push_size(opcode);
+        let instruction = Instruction {
+            offset: self.offset,
+            opcode,
+            immediate: &self.code[start..end.min(self.code.len())],
+        };
+        self.offset = end;
+        Some(instruction)
+    }
+}
+
+/// Returns an iterator over the instructions of code.
+///
+/// Note that the metadata appended to code by the Solidity compiler is not
+/// code, use [`split_metadata`] to remove it before disassembling.
+pub fn instructions(code: &[u8]) -> Instructions<'_> {
+    Instructions { code, offset: 0 }
+}
+
+/// Disassembles code into a listing with one instruction per line, prefixed
+/// with its hexadecimal offset, for example `0000: PUSH1 0x80`.
+pub fn disassemble(code: &[u8]) -> String {
+    instructions(code)
+        .map(|instruction| format!("{:04x}: {}\n", instruction.offset, instruction))
+        .collect()
+}
+
+/// An entry of the function dispatch table of a contract.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DispatchEntry {
+    /// The function selector.
+    pub selector: H32,
+    /// The offset of the code that the dispatcher jumps to for the selector.
+    pub destination: usize,
+}
+
+/// Extracts the function dispatch table from code, in the order in which the
+/// dispatcher checks the selectors.
+///
+/// The dispatcher generated by the Solidity compiler compares the selector of
+/// the call data with each function selector with a sequence of instructions
+/// like `DUP1 PUSH4 <selector> EQ PUSH2 <destination> JUMPI`. Dispatchers
+/// generated by other compilers, as well as selectors that are pushed with
+/// fewer than 4 bytes, are not detected.
+pub fn dispatch_table(code: &[u8]) -> Vec<DispatchEntry> {
+    let instructions = instructions(code).collect::<Vec<_>>();
+    instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, push)| push.opcode == PUSH4 && push.immediate.len() == 4)
+        .filter_map(|(index, push)| {
+            let mut rest = instructions[index + 1..]
+                .iter()
+                .skip_while(|instruction| (DUP1..=DUP16).contains(&instruction.opcode))
+                .take(3);
+            let (eq, destination, jumpi) = (rest.next()?, rest.next()?, rest.next()?);
+            if eq.opcode != EQ || jumpi.opcode != JUMPI {
+                return None;
+            }
+            let destination = destination.push_value()?;
+            if destination >= U256::from(code.len()) || code[destination.as_usize()] != JUMPDEST {
+                return None;
+            }
+
+            let mut selector = [0; 4];
+            selector.copy_from_slice(push.immediate);
+            Some(DispatchEntry {
+                selector,
+                destination: destination.as_usize(),
+            })
+        })
+        .collect()
+}
+
+/// Extracts the sorted function selectors from code. See [`dispatch_table`]
+/// for which dispatchers are supported.
+pub fn selectors(code: &[u8]) -> Vec<H32> {
+    let mut selectors = dispatch_table(code)
+        .into_iter()
+        .map(|entry| entry.selector)
+        .collect::<Vec<_>>();
+    selectors.sort_unstable();
+    selectors.dedup();
+    selectors
+}
+
+/// The metadata appended to deployed code by the Solidity compiler.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Metadata {
+    /// The IPFS CID of the metadata file of the contract.
+    pub ipfs: Option<Cid>,
+    /// The Swarm hash of the metadata file with the legacy `bzzr0` format.
+    pub bzzr0: Option<H256>,
+    /// The Swarm hash of the metadata file with the `bzzr1` format.
+    pub bzzr1: Option<H256>,
+    /// The version of the compiler, for example `0.8.0`.
+    pub solc: Option<String>,
+    /// Whether experimental compiler features were used.
+    pub experimental: bool,
+}
+
+/// Splits code into the code itself and the metadata appended by the
+/// Solidity compiler. The metadata is CBOR encoded and followed by its length
+/// as a 2 byte big endian integer.
+///
+/// Returns the unmodified code and `None` if the code does not end with
+/// metadata in a supported format.
+pub fn split_metadata(code: &[u8]) -> (&[u8], Option<Metadata>) {
+    let decode = || {
+        let length = code.len().checked_sub(2)?;
+        let metadata_length = u16::from_be_bytes([code[length], code[length + 1]]) as usize;
+        let start = length.checked_sub(metadata_length)?;
+        let metadata = decode_metadata(&code[start..length])?;
+        Some((&code[..start], metadata))
+    };
+
+    match decode() {
+        Some((code, metadata)) => (code, Some(metadata)),
+        None => (code, None),
+    }
+}
+
+/// A decoded CBOR value, limited to the types used for the metadata.
+enum CborValue<'a> {
+    Bytes(&'a [u8]),
+    Text(&'a str),
+    Bool(bool),
+}
+
+/// Decodes CBOR encoded metadata, which must be a map with text keys.
+fn decode_metadata(mut cbor: &[u8]) -> Option<Metadata> {
+    let (major, entries) = cbor_header(&mut cbor)?;
+    if major != 5 {
+        return None;
+    }
+
+    let mut metadata = Metadata::default();
+    for _ in 0..entries {
+        let key = match cbor_value(&mut cbor)? {
+            CborValue::Text(key) => key,
+            _ => return None,
+        };
+        match (key, cbor_value(&mut cbor)?) {
+            ("ipfs", CborValue::Bytes([0x12, 0x20, digest @ ..])) if digest.len() == 32 => {
+                let mut bytes = [0; 32];
+                bytes.copy_from_slice(digest);
+                metadata.ipfs = Some(Cid::v0(bytes));
+            }
+            ("bzzr0", CborValue::Bytes(hash)) if hash.len() == 32 => {
+                metadata.bzzr0 = Some(H256::from_slice(hash));
+            }
+            ("bzzr1", CborValue::Bytes(hash)) if hash.len() == 32 => {
+                metadata.bzzr1 = Some(H256::from_slice(hash));
+            }
+            ("solc", CborValue::Bytes([major, minor, patch])) => {
+                metadata.solc = Some(format!("{}.{}.{}", major, minor, patch));
+            }
+            ("solc", CborValue::Text(version)) => metadata.solc = Some(version.to_owned()),
+            ("experimental", CborValue::Bool(experimental)) => {
+                metadata.experimental = experimental;
+            }
+            _ => {}
+        }
+    }
+
+    if cbor.is_empty() {
+        Some(metadata)
+    } else {
+        None
+    }
+}
+
+/// Decodes the header of a CBOR data item, returning its major type and
+/// argument.
+fn cbor_header(cbor: &mut &[u8]) -> Option<(u8, usize)> {
+    let (first, rest) = cbor.split_first()?;
+    let (argument, size) = match first & 0x1f {
+        argument @ 0..=23 => (argument as usize, 0),
+        24 => (*rest.first()? as usize, 1),
+        25 => (
+            u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize,
+            2,
+        ),
+        _ => return None,
+    };
+    *cbor = &rest[size..];
+    Some((first >> 5, argument))
+}
+
+fn cbor_value<'a>(cbor: &mut &'a [u8]) -> Option<CborValue<'a>> {
+    let (major, argument) = cbor_header(cbor)?;
+    let mut bytes = || {
+        if cbor.len() < argument {
+            return None;
+        }
+        let (bytes, rest) = cbor.split_at(argument);
+        *cbor = rest;
+        Some(bytes)
+    };
+
+    match (major, argument) {
+        (2, _) => Some(CborValue::Bytes(bytes()?)),
+        (3, _) => Some(CborValue::Text(std::str::from_utf8(bytes()?).ok()?)),
+        (7, 20) => Some(CborValue::Bool(false)),
+        (7, 21) => Some(CborValue::Bool(true)),
+        _ => None,
+    }
+}
+
+/// A proxy contract that delegates calls to an implementation contract.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Proxy {
+    /// An EIP-1167 minimal proxy with the implementation address in its code.
+    Minimal(Address),
+    /// An EIP-1967 proxy with the implementation address in its
+    /// implementation storage slot.
+    Eip1967 {
+        /// The address of the implementation.
+        implementation: Address,
+        /// The address of the admin that can upgrade the proxy, if it is
+        /// stored in the admin storage slot.
+        admin: Option<Address>,
+    },
+    /// An EIP-1967 beacon proxy with the address of a beacon, which provides
+    /// the implementation address, in its beacon storage slot.
+    Beacon(Address),
+}
+
+impl Proxy {
+    /// Returns the implementation address of the proxy, or `None` for beacon
+    /// proxies whose implementation is provided by their beacon.
+    pub fn implementation(&self) -> Option<Address> {
+        match self {
+            Proxy::Minimal(implementation) => Some(*implementation),
+            Proxy::Eip1967 { implementation, .. } => Some(*implementation),
+            Proxy::Beacon(_) => None,
+        }
+    }
+}
+
+/// Returns the code of an account that delegates to a contract with an
+/// EIP-7702 authorization.
+pub fn delegation_code(address: Address) -> Vec<u8> {
+    let mut code = DELEGATION_PREFIX.to_vec();
+    code.extend_from_slice(address.as_bytes());
+    code
+}
+
+/// Returns the address of the contract an account delegates to with an
+/// EIP-7702 authorization, or `None` if the code is not a delegation
+/// designator.
+pub fn delegation_address(code: &[u8]) -> Option<Address> {
+    if code.len() != DELEGATION_PREFIX.len() + 20 || !code.starts_with(DELEGATION_PREFIX) {
+        return None;
+    }
+    Some(Address::from_slice(&code[DELEGATION_PREFIX.len()..]))
+}
+
+/// Returns the code of an EIP-1167 minimal proxy that delegates all calls to
+/// an implementation contract.
+pub fn minimal_proxy_code(implementation: Address) -> Vec<u8> {
+    let mut code = MINIMAL_PROXY_PREFIX.to_vec();
+    code.extend_from_slice(implementation.as_bytes());
+    code.extend_from_slice(MINIMAL_PROXY_SUFFIX);
+    code
+}
+
+/// Returns the creation code for deploying an EIP-1167 minimal proxy that
+/// delegates all calls to an implementation contract.
+pub fn minimal_proxy_creation_code(implementation: Address) -> Vec<u8> {
+    let mut code = MINIMAL_PROXY_CONSTRUCTOR.to_vec();
+    code.extend(minimal_proxy_code(implementation));
+    code
+}
+
+/// Returns the implementation address of an EIP-1167 minimal proxy, or `None`
+/// if the code is not the code of a minimal proxy.
+pub fn minimal_proxy_implementation(code: &[u8]) -> Option<Address> {
+    let prefix = MINIMAL_PROXY_PREFIX.len();
+    if code.len() != prefix + 20 + MINIMAL_PROXY_SUFFIX.len()
+        || !code.starts_with(MINIMAL_PROXY_PREFIX)
+        || !code.ends_with(MINIMAL_PROXY_SUFFIX)
+    {
+        return None;
+    }
+    Some(Address::from_slice(&code[prefix..prefix + 20]))
+}
+
+/// Detects whether the contract at an address is an EIP-1167 minimal proxy
+/// or an EIP-1967 proxy at the specified block, or the latest block if none
+/// is specified.
+///
+/// Note that EIP-1967 proxies are detected by their storage slots, so proxies
+/// that were not yet initialized are not detected.
+pub async fn detect_proxy<T: Transport>(
+    web3: &Web3<T>,
+    address: Address,
+    block: Option<BlockNumber>,
+) -> Result<Option<Proxy>, ExecutionError> {
+    let eth = web3.eth();
+    let slot = |slot: H256| eth.storage(address, U256::from_big_endian(slot.as_bytes()), block);
+    let (code, implementation, admin, beacon) = future::try_join4(
+        eth.code(address, block),
+        slot(IMPLEMENTATION_SLOT),
+        slot(ADMIN_SLOT),
+        slot(BEACON_SLOT),
+    )
+    .await?;
+
+    if let Some(implementation) = minimal_proxy_implementation(&code.0) {
+        return Ok(Some(Proxy::Minimal(implementation)));
+    }
+    if let Some(implementation) = slot_address(implementation) {
+        return Ok(Some(Proxy::Eip1967 {
+            implementation,
+            admin: slot_address(admin),
+        }));
+    }
+    Ok(slot_address(beacon).map(Proxy::Beacon))
+}
+
+/// Returns the address stored in a storage slot, or `None` if it is zero.
+pub(crate) fn slot_address(value: H256) -> Option<Address> {
+    let address = Address::from_slice(&value[12..]);
+    if address.is_zero() {
+        None
+    } else {
+        Some(address)
+    }
+}
+
+/// Returns the number of immediate bytes of an instruction.
+fn push_size(opcode: u8) -> usize {
+    if (PUSH1..=PUSH32).contains(&opcode) {
+        (opcode - PUSH1) as usize + 1
+    } else {
+        0
+    }
+}
+
+fn mnemonic(opcode: u8) -> Option<&'static str> {
+    const PUSH: [&str; 32] = [
+        "PUSH1", "PUSH2", "PUSH3", "PUSH4", "PUSH5", "PUSH6", "PUSH7", "PUSH8", "PUSH9", "PUSH10",
+        "PUSH11", "PUSH12", "PUSH13", "PUSH14", "PUSH15", "PUSH16", "PUSH17", "PUSH18", "PUSH19",
+        "PUSH20", "PUSH21", "PUSH22", "PUSH23", "PUSH24", "PUSH25", "PUSH26", "PUSH27", "PUSH28",
+        "PUSH29", "PUSH30", "PUSH31", "PUSH32",
+    ];
+    const DUP: [&str; 16] = [
+        "DUP1", "DUP2", "DUP3", "DUP4", "DUP5", "DUP6", "DUP7", "DUP8", "DUP9", "DUP10", "DUP11",
+        "DUP12", "DUP13", "DUP14", "DUP15", "DUP16",
+    ];
+    const SWAP: [&str; 16] = [
+        "SWAP1", "SWAP2", "SWAP3", "SWAP4", "SWAP5", "SWAP6", "SWAP7", "SWAP8", "SWAP9", "SWAP10",
+        "SWAP11", "SWAP12", "SWAP13", "SWAP14", "SWAP15", "SWAP16",
+    ];
+    const LOG: [&str; 5] = ["LOG0", "LOG1", "LOG2", "LOG3", "LOG4"];
+
+    let mnemonic = match opcode {
+        0x00 => "STOP",
+        0x01 => "ADD",
+        0x02 => "MUL",
+        0x03 => "SUB",
+        0x04 => "DIV",
+        0x05 => "SDIV",
+        0x06 => "MOD",
+        0x07 => "SMOD",
+        0x08 => "ADDMOD",
+        0x09 => "MULMOD",
+        0x0a => "EXP",
+        0x0b => "SIGNEXTEND",
+        0x10 => "LT",
+        0x11 => "GT",
+        0x12 => "SLT",
+        0x13 => "SGT",
+        0x14 => "EQ",
+        0x15 => "ISZERO",
+        0x16 => "AND",
+        0x17 => "OR",
+        0x18 => "XOR",
+        0x19 => "NOT",
+        0x1a => "BYTE",
+        0x1b => "SHL",
+        0x1c => "SHR",
+        0x1d => "SAR",
+        0x20 => "KECCAK256",
+        0x30 => "ADDRESS",
+        0x31 => "BALANCE",
+        0x32 => "ORIGIN",
+        0x33 => "CALLER",
+        0x34 => "CALLVALUE",
+        0x35 => "CALLDATALOAD",
+        0x36 => "CALLDATASIZE",
+        0x37 => "CALLDATACOPY",
+        0x38 => "CODESIZE",
+        0x39 => "CODECOPY",
+        0x3a => "GASPRICE",
+        0x3b => "EXTCODESIZE",
+        0x3c => "EXTCODECOPY",
+        0x3d => "RETURNDATASIZE",
+        0x3e => "RETURNDATACOPY",
+        0x3f => "EXTCODEHASH",
+        0x40 => "BLOCKHASH",
+        0x41 => "COINBASE",
+        0x42 => "TIMESTAMP",
+        0x43 => "NUMBER",
+        0x44 => "PREVRANDAO",
+        0x45 => "GASLIMIT",
+        0x46 => "CHAINID",
+        0x47 => "SELFBALANCE",
+        0x48 => "BASEFEE",
+        0x49 => "BLOBHASH",
+        0x4a => "BLOBBASEFEE",
+        0x50 => "POP",
+        0x51 => "MLOAD",
+        0x52 => "MSTORE",
+        0x53 => "MSTORE8",
+        0x54 => "SLOAD",
+        0x55 => "SSTORE",
+        0x56 => "JUMP",
+        0x57 => "JUMPI",
+        0x58 => "PC",
+        0x59 => "MSIZE",
+        0x5a => "GAS",
+        0x5b => "JUMPDEST",
+        0x5c => "TLOAD",
+        0x5d => "TSTORE",
+        0x5e => "MCOPY",
+        0x5f => "PUSH0",
+        0x60..=0x7f => PUSH[(opcode - 0x60) as usize],
+        0x80..=0x8f => DUP[(opcode - 0x80) as usize],
+        0x90..=0x9f => SWAP[(opcode - 0x90) as usize],
+        0xa0..=0xa4 => LOG[(opcode - 0xa0) as usize],
+        0xf0 => "CREATE",
+        0xf1 => "CALL",
+        0xf2 => "CALLCODE",
+        0xf3 => "RETURN",
+        0xf4 => "DELEGATECALL",
+        0xf5 => "CREATE2",
+        0xfa => "STATICCALL",
+        0xfd => "REVERT",
+        0xfe => "INVALID",
+        0xff => "SELFDESTRUCT",
+        _ => return None,
+    };
+    Some(mnemonic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::contracts::COUNTER;
+    use crate::test::prelude::*;
+    use ethcontract_common::hash::{function_selector, keccak256};
+
+    /// Returns the runtime code of the counter contract, which is copied from
+    /// offset `0x20` of the creation code by its constructor.
+    fn counter_runtime() -> Vec<u8> {
+        hex::decode(COUNTER).unwrap()[0x20..].to_vec()
+    }
+
+    #[test]
+    fn eip1967_slots() {
+        for (slot, name) in &[
+            (IMPLEMENTATION_SLOT, "eip1967.proxy.implementation"),
+            (ADMIN_SLOT, "eip1967.proxy.admin"),
+            (BEACON_SLOT, "eip1967.proxy.beacon"),
+        ] {
+            let hash = U256::from_big_endian(&keccak256(name));
+            assert_eq!(U256::from_big_endian(slot.as_bytes()), hash - 1);
+        }
+    }
+
+    #[test]
+    fn disassemble_code() {
+        assert_eq!(
+            disassemble(&[0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x0c, 0x61, 0x01]),
+            "0000: PUSH1 0x80\n\
+             0002: PUSH1 0x40\n\
+             0004: MSTORE\n\
+             0005: PUSH0\n\
+             0006: UNKNOWN(0x0c)\n\
+             0007: PUSH2 0x01\n",
+        );
+
+        // NOTE: Immediate data of push instructions is skipped, even if it
+        //   contains bytes that look like opcodes.
+        let offsets = instructions(&[0x62, 0x5b, 0x5b, 0x5b, 0x5b])
+            .map(|instruction| instruction.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0, 4]);
+    }
+
+    #[test]
+    fn extract_dispatch_table() {
+        let runtime = counter_runtime();
+        let (code, _) = split_metadata(&runtime);
+        assert_eq!(
+            dispatch_table(code),
+            vec![
+                DispatchEntry {
+                    selector: function_selector("value()"),
+                    destination: 0x3b,
+                },
+                DispatchEntry {
+                    selector: function_selector("increment()"),
+                    destination: 0x59,
+                },
+            ],
+        );
+        assert_eq!(
+            selectors(code),
+            vec![
+                function_selector("value()"),
+                function_selector("increment()"),
+            ],
+        );
+    }
+
+    #[test]
+    fn decode_metadata() {
+        let runtime = counter_runtime();
+        let (code, metadata) = split_metadata(&runtime);
+        assert_eq!(code.len(), runtime.len() - 0x35);
+        assert_eq!(
+            metadata,
+            Some(Metadata {
+                ipfs: Some(
+                    "QmYeoYAii9z2e8yXEFdugmLLLmKWZ3DHt7Dwtqy9X4JZok"
+                        .parse()
+                        .unwrap()
+                ),
+                solc: Some("0.8.0".to_owned()),
+                ..Default::default()
+            }),
+        );
+
+        // Legacy Swarm metadata with a prerelease compiler version.
+        let mut code = vec![0x00];
+        code.extend(
+            hex::decode(
+                "a265627a7a72305820\
+                 1111111111111111111111111111111111111111111111111111111111111111\
+                 64736f6c63\
+                 6b302e352e302d6e69676874\
+                 003a",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            split_metadata(&code),
+            (
+                &[0x00][..],
+                Some(Metadata {
+                    bzzr0: Some(H256::repeat_byte(0x11)),
+                    solc: Some("0.5.0-night".to_owned()),
+                    ..Default::default()
+                })
+            ),
+        );
+
+        for code in &[&[][..], &[0x00, 0x01][..], &[0xa1, 0x00, 0x01][..]] {
+            assert_eq!(split_metadata(code), (*code, None));
+        }
+    }
+
+    #[test]
+    fn delegation_designator() {
+        let delegate = Address::repeat_byte(0x42);
+        let code = delegation_code(delegate);
+        assert_eq!(
+            hex::encode(&code),
+            "ef01004242424242424242424242424242424242424242",
+        );
+        assert_eq!(delegation_address(&code), Some(delegate));
+        assert_eq!(delegation_address(&code[1..]), None);
+        assert_eq!(delegation_address(&minimal_proxy_code(delegate)), None);
+    }
+
+    #[test]
+    fn detect_minimal_proxy() {
+        let implementation = Address::repeat_byte(0x42);
+        let code = minimal_proxy_code(implementation);
+        assert_eq!(
+            hex::encode(&code),
+            "363d3d373d3d3d363d73\
+             4242424242424242424242424242424242424242\
+             5af43d82803e903d91602b57fd5bf3",
+        );
+        assert_eq!(minimal_proxy_implementation(&code), Some(implementation));
+        assert_eq!(minimal_proxy_implementation(&code[1..]), None);
+
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let proxy = Address::repeat_byte(1);
+
+        transport.add_response(json!(web3::types::Bytes(code)));
+        transport.add_response(json!(H256::zero()));
+        transport.add_response(json!(H256::zero()));
+        transport.add_response(json!(H256::zero()));
+        let detected = detect_proxy(&web3, proxy, None).immediate().unwrap();
+        assert_eq!(detected, Some(Proxy::Minimal(implementation)));
+        assert_eq!(detected.unwrap().implementation(), Some(implementation));
+
+        transport.assert_request("eth_getCode", &[json!(proxy), json!("latest")]);
+        for slot in &[IMPLEMENTATION_SLOT, ADMIN_SLOT, BEACON_SLOT] {
+            transport.assert_request(
+                "eth_getStorageAt",
+                &[json!(proxy), json!(slot), json!("latest")],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn detect_eip1967_proxies() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (implementation, admin, beacon) = (
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+            Address::repeat_byte(4),
+        );
+
+        for (slots, expected) in vec![
+            (
+                [implementation, admin, Address::zero()],
+                Some(Proxy::Eip1967 {
+                    implementation,
+                    admin: Some(admin),
+                }),
+            ),
+            (
+                [implementation, Address::zero(), Address::zero()],
+                Some(Proxy::Eip1967 {
+                    implementation,
+                    admin: None,
+                }),
+            ),
+            (
+                [Address::zero(), Address::zero(), beacon],
+                Some(Proxy::Beacon(beacon)),
+            ),
+            ([Address::zero(); 3], None),
+        ] {
+            transport.add_response(json!("0x6000"));
+            for address in &slots {
+                transport.add_response(json!(H256::from(*address)));
+            }
+            let detected = detect_proxy(&web3, Address::repeat_byte(1), None)
+                .immediate()
+                .unwrap();
+            assert_eq!(detected, expected);
+        }
+    }
+}
@@ -0,0 +1,469 @@
+//! Support for [EIP-3668](https://eips.ethereum.org/EIPS/eip-3668) offchain
+//! lookups, also known as CCIP-Read.
+//!
+//! Contracts supporting offchain lookups revert calls with an `OffchainLookup`
+//! error specifying gateway URLs to retrieve data from and a callback function
+//! to call with the retrieved data, which then verifies it and returns the
+//! result of the original call. Contract calls handle these lookups
+//! automatically, with the lookup options specified with
+//! [`ViewMethodBuilder::ccip_read`](crate::contract::ViewMethodBuilder::ccip_read)
+//! or with the method defaults of a contract instance. Offchain lookups are
+//! not supported for batched calls.
+//!
+//! When the `http` feature is enabled, gateways are queried over HTTP by
+//! default. Lookups can be disabled with [`CcipRead::disabled`], in which
+//! case calls fail with the `OffchainLookup` revert.
+
+use crate::abi;
+use crate::errors::{CcipError, RpcError};
+use ethcontract_common::abi::{ParamType, Token};
+use ethcontract_common::hash::H32;
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::sync::Arc;
+use web3::error::Error as Web3Error;
+use web3::types::Address;
+
+/// The selector of the
+/// `OffchainLookup(address,string[],bytes,bytes4,bytes)` error.
+pub const OFFCHAIN_LOOKUP_SELECTOR: H32 = [0x55, 0x6f, 0x18, 0x30];
+
+/// The default maximum number of offchain lookups for a single call.
+pub const DEFAULT_MAX_LOOKUPS: usize = 4;
+
+/// A request to an offchain lookup gateway.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GatewayRequest {
+    /// The URL to send the request to.
+    pub url: String,
+    /// The JSON body for `POST` requests. Requests without a body are sent as
+    /// `GET` requests.
+    pub body: Option<Value>,
+}
+
+impl GatewayRequest {
+    /// Creates the request for a gateway URL template. URLs containing a
+    /// `{data}` parameter are requested with `GET` with the parameters
+    /// substituted, and other URLs are requested with `POST` with the sender
+    /// and data in the body.
+    pub fn new(url: &str, sender: Address, data: &[u8]) -> Self {
+        let sender = format!("{:?}", sender);
+        let data = format!("0x{}", hex::encode(data));
+        if url.contains("{data}") {
+            GatewayRequest {
+                url: url.replace("{sender}", &sender).replace("{data}", &data),
+                body: None,
+            }
+        } else {
+            GatewayRequest {
+                url: url.replace("{sender}", &sender),
+                body: Some(json!({ "data": data, "sender": sender })),
+            }
+        }
+    }
+}
+
+/// A client for sending requests to offchain lookup gateways.
+pub trait Gateway: Debug + Send + Sync {
+    /// Sends a request to a gateway and returns the data of its response.
+    /// Errors for which [`CcipError::is_retryable`] is `true` cause the next
+    /// gateway URL to be tried.
+    fn fetch<'a>(
+        &'a self,
+        request: &'a GatewayRequest,
+    ) -> BoxFuture<'a, Result<Vec<u8>, CcipError>>;
+}
+
+/// An offchain lookup requested by a contract.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OffchainLookup {
+    /// The contract that requested the lookup.
+    pub sender: Address,
+    /// The gateway URL templates.
+    pub urls: Vec<String>,
+    /// The data to send to the gateway.
+    pub call_data: Vec<u8>,
+    /// The selector of the function to call with the gateway response.
+    pub callback_function: H32,
+    /// The data to pass to the callback function.
+    pub extra_data: Vec<u8>,
+}
+
+impl OffchainLookup {
+    /// Decodes an offchain lookup from revert data. Returns `None` if the
+    /// revert data is not a valid `OffchainLookup` error.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 || data[..4] != OFFCHAIN_LOOKUP_SELECTOR {
+            return None;
+        }
+        let kinds = [
+            ParamType::Address,
+            ParamType::Array(Box::new(ParamType::String)),
+            ParamType::Bytes,
+            ParamType::FixedBytes(4),
+            ParamType::Bytes,
+        ];
+        let mut tokens = abi::try_decode(&kinds, &data[4..]).ok()?.into_iter();
+        let sender = tokens.next()?.into_address()?;
+        let urls = tokens
+            .next()?
+            .into_array()?
+            .into_iter()
+            .map(Token::into_string)
+            .collect::<Option<_>>()?;
+        let call_data = tokens.next()?.into_bytes()?;
+        let mut callback_function = H32::default();
+        callback_function.copy_from_slice(&tokens.next()?.into_fixed_bytes()?);
+        let extra_data = tokens.next()?.into_bytes()?;
+        Some(OffchainLookup {
+            sender,
+            urls,
+            call_data,
+            callback_function,
+            extra_data,
+        })
+    }
+
+    /// Decodes an offchain lookup from the error of a reverted call.
+    pub fn from_error(err: &Web3Error) -> Option<Self> {
+        match RpcError::from_web3(err)? {
+            RpcError::ExecutionReverted {
+                data: Some(data), ..
+            } => Self::decode(&data.0),
+            _ => None,
+        }
+    }
+
+    /// Returns the calldata for calling the callback function with a gateway
+    /// response.
+    pub fn callback_data(&self, response: Vec<u8>) -> Vec<u8> {
+        abi::encode_call(
+            self.callback_function,
+            &[
+                Token::Bytes(response),
+                Token::Bytes(self.extra_data.clone()),
+            ],
+        )
+    }
+}
+
+/// Options for handling offchain lookups in contract calls.
+#[derive(Clone, Debug)]
+pub struct CcipRead {
+    gateway: Option<Arc<dyn Gateway>>,
+    max_lookups: usize,
+}
+
+impl CcipRead {
+    /// Creates options for handling offchain lookups with the specified
+    /// gateway client.
+    pub fn new(gateway: Arc<dyn Gateway>) -> Self {
+        CcipRead {
+            gateway: Some(gateway),
+            max_lookups: DEFAULT_MAX_LOOKUPS,
+        }
+    }
+
+    /// Creates options that disable offchain lookups.
+    pub fn disabled() -> Self {
+        CcipRead {
+            gateway: None,
+            max_lookups: 0,
+        }
+    }
+
+    /// Sets the maximum number of offchain lookups for a single call, as the
+    /// callback of a lookup can request another lookup. Calls exceeding the
+    /// limit fail with `CcipError::TooManyLookups`.
+    pub fn max_lookups(mut self, value: usize) -> Self {
+        self.max_lookups = value;
+        self
+    }
+
+    /// Returns the maximum number of offchain lookups for a single call, which
+    /// is zero if offchain lookups are disabled.
+    pub fn lookup_limit(&self) -> usize {
+        match self.gateway {
+            Some(_) => self.max_lookups,
+            None => 0,
+        }
+    }
+
+    /// Performs an offchain lookup, trying its gateway URLs in order until one
+    /// of them returns a response or fails with an error that is not
+    /// retryable. Returns the last error if all gateways fail.
+    pub async fn fetch(&self, lookup: &OffchainLookup) -> Result<Vec<u8>, CcipError> {
+        let gateway = self.gateway.as_ref().ok_or(CcipError::Disabled)?;
+        let mut result = Err(CcipError::NoGateways);
+        for url in &lookup.urls {
+            let request = GatewayRequest::new(url, lookup.sender, &lookup.call_data);
+            result = gateway.fetch(&request).await;
+            match &result {
+                Err(err) if err.is_retryable() => continue,
+                _ => break,
+            }
+        }
+        result
+    }
+}
+
+impl Default for CcipRead {
+    /// Returns options that query gateways over HTTP when the `http` feature
+    /// is enabled, and that disable offchain lookups otherwise.
+    fn default() -> Self {
+        #[cfg(feature = "http")]
+        {
+            CcipRead::new(http::DEFAULT_GATEWAY.clone())
+        }
+        #[cfg(not(feature = "http"))]
+        {
+            CcipRead::disabled()
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+pub use self::http::HttpGateway;
+
+#[cfg(feature = "http")]
+mod http {
+    use super::{Gateway, GatewayRequest};
+    use crate::errors::CcipError;
+    use futures::future::{BoxFuture, FutureExt};
+    use lazy_static::lazy_static;
+    use serde::Deserialize;
+    use std::sync::Arc;
+    use web3::types::Bytes;
+
+    /// The maximum number of HTTP redirects followed for gateway requests.
+    const MAX_REDIRECTS: usize = 4;
+
+    lazy_static! {
+        pub(super) static ref DEFAULT_GATEWAY: Arc<dyn Gateway> = Arc::new(HttpGateway::new());
+    }
+
+    /// A gateway client that sends requests over HTTP.
+    #[derive(Clone, Debug)]
+    pub struct HttpGateway {
+        client: reqwest::Client,
+    }
+
+    #[derive(Deserialize)]
+    struct Response {
+        data: Bytes,
+    }
+
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        message: String,
+    }
+
+    impl HttpGateway {
+        /// Creates a new HTTP gateway client, which follows a limited number
+        /// of redirects.
+        pub fn new() -> Self {
+            let client = reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+                .build()
+                .unwrap_or_default();
+            HttpGateway { client }
+        }
+
+        /// Creates a new HTTP gateway client with the specified HTTP client.
+        pub fn with_client(client: reqwest::Client) -> Self {
+            HttpGateway { client }
+        }
+    }
+
+    impl Default for HttpGateway {
+        fn default() -> Self {
+            HttpGateway::new()
+        }
+    }
+
+    impl Gateway for HttpGateway {
+        fn fetch<'a>(
+            &'a self,
+            request: &'a GatewayRequest,
+        ) -> BoxFuture<'a, Result<Vec<u8>, CcipError>> {
+            async move {
+                let response = match &request.body {
+                    Some(body) => self.client.post(&request.url).json(body),
+                    None => self.client.get(&request.url),
+                }
+                .send()
+                .await?;
+
+                let status = response.status();
+                let body = response.bytes().await?;
+                if !status.is_success() {
+                    let message = serde_json::from_slice::<ErrorResponse>(&body)
+                        .map(|response| response.message)
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+                    return Err(CcipError::Gateway {
+                        status: status.as_u16(),
+                        message,
+                    });
+                }
+                let response = serde_json::from_slice::<Response>(&body)
+                    .map_err(|err| CcipError::InvalidResponse(err.to_string()))?;
+                Ok(response.data.0)
+            }
+            .boxed()
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use futures::future::FutureExt;
+    use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// A gateway with static responses for each URL, recording the requests.
+    #[derive(Debug, Default)]
+    pub struct StaticGateway {
+        pub responses: HashMap<String, Result<Vec<u8>, u16>>,
+        pub requests: Mutex<Vec<GatewayRequest>>,
+    }
+
+    impl Gateway for StaticGateway {
+        fn fetch<'a>(
+            &'a self,
+            request: &'a GatewayRequest,
+        ) -> BoxFuture<'a, Result<Vec<u8>, CcipError>> {
+            self.requests.lock().unwrap().push(request.clone());
+            let result = match self.responses.get(&request.url) {
+                Some(Ok(data)) => Ok(data.clone()),
+                Some(Err(status)) => Err(CcipError::Gateway {
+                    status: *status,
+                    message: "error".to_owned(),
+                }),
+                None => Err(CcipError::Gateway {
+                    status: 404,
+                    message: "not found".to_owned(),
+                }),
+            };
+            async move { result }.boxed()
+        }
+    }
+
+    pub fn lookup(sender: Address, urls: &[&str]) -> OffchainLookup {
+        OffchainLookup {
+            sender,
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            call_data: vec![1, 2, 3],
+            callback_function: [0xca, 0x11, 0xba, 0xcc],
+            extra_data: vec![4, 5],
+        }
+    }
+
+    pub fn encode_lookup(lookup: &OffchainLookup) -> Vec<u8> {
+        abi::encode_call(
+            OFFCHAIN_LOOKUP_SELECTOR,
+            &[
+                Token::Address(lookup.sender),
+                Token::Array(lookup.urls.iter().cloned().map(Token::String).collect()),
+                Token::Bytes(lookup.call_data.clone()),
+                Token::FixedBytes(lookup.callback_function.to_vec()),
+                Token::Bytes(lookup.extra_data.clone()),
+            ],
+        )
+    }
+
+    #[test]
+    fn selector() {
+        assert_eq!(
+            ethcontract_common::hash::function_selector(
+                "OffchainLookup(address,string[],bytes,bytes4,bytes)"
+            ),
+            OFFCHAIN_LOOKUP_SELECTOR,
+        );
+    }
+
+    #[test]
+    fn decode_lookup() {
+        let lookup = lookup(Address::repeat_byte(1), &["https://a/{sender}/{data}.json"]);
+        let data = encode_lookup(&lookup);
+        assert_eq!(OffchainLookup::decode(&data), Some(lookup.clone()));
+        assert_eq!(OffchainLookup::decode(&data[..data.len() - 32]), None);
+        assert_eq!(OffchainLookup::decode(&data[4..]), None);
+
+        let err = Web3Error::Rpc(JsonrpcError {
+            code: ErrorCode::ServerError(3),
+            message: "execution reverted".to_owned(),
+            data: Some(json!(format!("0x{}", hex::encode(&data)))),
+        });
+        assert_eq!(OffchainLookup::from_error(&err), Some(lookup.clone()));
+
+        assert_eq!(
+            lookup.callback_data(vec![6]),
+            abi::encode_call(
+                [0xca, 0x11, 0xba, 0xcc],
+                &[Token::Bytes(vec![6]), Token::Bytes(vec![4, 5])],
+            ),
+        );
+    }
+
+    #[test]
+    fn gateway_requests() {
+        let sender = Address::repeat_byte(0xab);
+        assert_eq!(
+            GatewayRequest::new("https://a/{sender}/{data}.json", sender, &[1, 2]),
+            GatewayRequest {
+                url: format!("https://a/0x{}/0x0102.json", "ab".repeat(20)),
+                body: None,
+            },
+        );
+        assert_eq!(
+            GatewayRequest::new("https://a/{sender}", sender, &[1, 2]),
+            GatewayRequest {
+                url: format!("https://a/0x{}", "ab".repeat(20)),
+                body: Some(json!({
+                    "data": "0x0102",
+                    "sender": format!("0x{}", "ab".repeat(20)),
+                })),
+            },
+        );
+    }
+
+    #[test]
+    fn fetch_tries_gateways_in_order() {
+        let gateway = Arc::new(StaticGateway {
+            responses: vec![
+                ("https://a".to_owned(), Err(502)),
+                ("https://b".to_owned(), Ok(vec![42])),
+                ("https://c".to_owned(), Err(400)),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        });
+        let ccip = CcipRead::new(gateway.clone());
+        let sender = Address::repeat_byte(1);
+
+        let lookup = lookup(sender, &["https://a", "https://b", "https://c"]);
+        assert_eq!(ccip.fetch(&lookup).now_or_never().unwrap().unwrap(), [42]);
+        assert_eq!(gateway.requests.lock().unwrap().len(), 2);
+
+        // Client errors are not retried.
+        let lookup = self::lookup(sender, &["https://c", "https://b"]);
+        assert!(matches!(
+            ccip.fetch(&lookup).now_or_never().unwrap(),
+            Err(CcipError::Gateway { status: 400, .. })
+        ));
+
+        let lookup = self::lookup(sender, &[]);
+        assert!(matches!(
+            ccip.fetch(&lookup).now_or_never().unwrap(),
+            Err(CcipError::NoGateways)
+        ));
+        assert!(matches!(
+            CcipRead::disabled().fetch(&lookup).now_or_never().unwrap(),
+            Err(CcipError::Disabled)
+        ));
+    }
+}
@@ -0,0 +1,549 @@
+//! Configuration presets for major networks, with the native currency, public
+//! RPC and block explorer of each chain, and hints for choosing gas prices and
+//! confirmation parameters for transactions.
+//!
+//! Presets can be retrieved by chain ID with [`preset`], and can be extended
+//! or overridden with user-defined chains using [`Chains`]:
+//!
+//! ```
+//! # use ethcontract::chains::{self, ChainConfig, Chains};
+//! let chains = Chains::presets().chain(
+//!     ChainConfig::new(31337, "Devnet").rpc_url("http://localhost:8545"),
+//! );
+//!
+//! assert_eq!(chains.get(1), Some(&chains::MAINNET));
+//! assert_eq!(chains.get(31337).unwrap().name, "Devnet");
+//! ```
+
+use crate::contract::{CodeSizeLimits, MethodDefaults};
+use crate::errors::ChainAddressError;
+use crate::transaction::confirm::ConfirmParams;
+use crate::transaction::gas_price::{FeeUrgency, GasPrice};
+use crate::transaction::ResolveCondition;
+use ethcontract_common::address;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::Duration;
+use web3::types::{Address, H256};
+
+/// The native currency of a chain, used for paying transaction fees.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NativeCurrency {
+    /// The symbol of the currency, for example `ETH`.
+    pub symbol: Cow<'static, str>,
+    /// The number of decimals of the currency.
+    pub decimals: u32,
+}
+
+impl NativeCurrency {
+    /// Creates a new native currency with the specified symbol and decimals.
+    pub fn new(symbol: impl Into<Cow<'static, str>>, decimals: u32) -> Self {
+        NativeCurrency {
+            symbol: symbol.into(),
+            decimals,
+        }
+    }
+}
+
+/// The configuration of a chain.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainConfig {
+    /// The chain ID.
+    pub id: u64,
+    /// The human readable name of the chain.
+    pub name: Cow<'static, str>,
+    /// The ERC-3770 short name of the chain used for chain-prefixed
+    /// addresses, for example `eth` for `eth:0x...` addresses on mainnet.
+    pub short_name: Option<Cow<'static, str>>,
+    /// The native currency of the chain.
+    pub currency: NativeCurrency,
+    /// The URL of a public RPC endpoint of the chain. Public endpoints are
+    /// usually rate limited and should not be relied on in production.
+    pub rpc_url: Option<Cow<'static, str>>,
+    /// The base URL of a block explorer for the chain, without a trailing
+    /// slash.
+    pub explorer_url: Option<Cow<'static, str>>,
+    /// Whether the chain supports EIP-1559 transactions and fee markets.
+    pub eip1559: bool,
+    /// The average time between blocks.
+    pub block_time: Duration,
+    /// The number of confirmations after which transactions are unlikely to
+    /// be reverted by a reorganization of the chain. This is a hint and not a
+    /// finality guarantee.
+    pub confirmations: usize,
+    /// The code size limits of contract deployments.
+    pub code_size_limits: CodeSizeLimits,
+}
+
+macro_rules! preset {
+    (
+        $(#[$attr:meta])*
+        $name:ident = {
+            id: $id:expr,
+            name: $chain:expr,
+            short_name: $short_name:expr,
+            currency: ($symbol:expr, $decimals:expr),
+            rpc_url: $rpc:expr,
+            explorer_url: $explorer:expr,
+            eip1559: $eip1559:expr,
+            block_time: $block_time:expr,
+            confirmations: $confirmations:expr,
+        }
+    ) => {
+        $(#[$attr])*
+        pub const $name: ChainConfig = ChainConfig {
+            id: $id,
+            name: Cow::Borrowed($chain),
+            short_name: Some(Cow::Borrowed($short_name)),
+            currency: NativeCurrency {
+                symbol: Cow::Borrowed($symbol),
+                decimals: $decimals,
+            },
+            rpc_url: Some(Cow::Borrowed($rpc)),
+            explorer_url: Some(Cow::Borrowed($explorer)),
+            eip1559: $eip1559,
+            block_time: $block_time,
+            confirmations: $confirmations,
+            code_size_limits: CodeSizeLimits::STANDARD,
+        };
+    };
+}
+
+preset! {
+    /// Ethereum mainnet.
+    MAINNET = {
+        id: 1,
+        name: "Ethereum",
+        short_name: "eth",
+        currency: ("ETH", 18),
+        rpc_url: "https://cloudflare-eth.com",
+        explorer_url: "https://etherscan.io",
+        eip1559: true,
+        block_time: Duration::from_secs(12),
+        confirmations: 12,
+    }
+}
+
+preset! {
+    /// The Sepolia Ethereum test network.
+    SEPOLIA = {
+        id: 11_155_111,
+        name: "Sepolia",
+        short_name: "sep",
+        currency: ("ETH", 18),
+        rpc_url: "https://rpc.sepolia.org",
+        explorer_url: "https://sepolia.etherscan.io",
+        eip1559: true,
+        block_time: Duration::from_secs(12),
+        confirmations: 2,
+    }
+}
+
+preset! {
+    /// The Optimism mainnet.
+    OPTIMISM = {
+        id: 10,
+        name: "Optimism",
+        short_name: "oeth",
+        currency: ("ETH", 18),
+        rpc_url: "https://mainnet.optimism.io",
+        explorer_url: "https://optimistic.etherscan.io",
+        eip1559: true,
+        block_time: Duration::from_secs(2),
+        confirmations: 1,
+    }
+}
+
+preset! {
+    /// The BNB Smart Chain mainnet.
+    BSC = {
+        id: 56,
+        name: "BNB Smart Chain",
+        short_name: "bnb",
+        currency: ("BNB", 18),
+        rpc_url: "https://bsc-dataseed.binance.org",
+        explorer_url: "https://bscscan.com",
+        eip1559: false,
+        block_time: Duration::from_secs(3),
+        confirmations: 15,
+    }
+}
+
+preset! {
+    /// The Gnosis chain.
+    GNOSIS = {
+        id: 100,
+        name: "Gnosis",
+        short_name: "gno",
+        currency: ("xDAI", 18),
+        rpc_url: "https://rpc.gnosischain.com",
+        explorer_url: "https://gnosisscan.io",
+        eip1559: true,
+        block_time: Duration::from_secs(5),
+        confirmations: 12,
+    }
+}
+
+preset! {
+    /// The Polygon PoS mainnet.
+    POLYGON = {
+        id: 137,
+        name: "Polygon",
+        short_name: "pol",
+        currency: ("POL", 18),
+        rpc_url: "https://polygon-rpc.com",
+        explorer_url: "https://polygonscan.com",
+        eip1559: true,
+        block_time: Duration::from_secs(2),
+        confirmations: 64,
+    }
+}
+
+preset! {
+    /// The Base mainnet.
+    BASE = {
+        id: 8453,
+        name: "Base",
+        short_name: "base",
+        currency: ("ETH", 18),
+        rpc_url: "https://mainnet.base.org",
+        explorer_url: "https://basescan.org",
+        eip1559: true,
+        block_time: Duration::from_secs(2),
+        confirmations: 1,
+    }
+}
+
+preset! {
+    /// The Arbitrum One mainnet.
+    ARBITRUM = {
+        id: 42_161,
+        name: "Arbitrum One",
+        short_name: "arb1",
+        currency: ("ETH", 18),
+        rpc_url: "https://arb1.arbitrum.io/rpc",
+        explorer_url: "https://arbiscan.io",
+        eip1559: true,
+        block_time: Duration::from_millis(250),
+        confirmations: 1,
+    }
+}
+
+preset! {
+    /// The Avalanche C-Chain mainnet.
+    AVALANCHE = {
+        id: 43_114,
+        name: "Avalanche C-Chain",
+        short_name: "avax",
+        currency: ("AVAX", 18),
+        rpc_url: "https://api.avax.network/ext/bc/C/rpc",
+        explorer_url: "https://snowtrace.io",
+        eip1559: true,
+        block_time: Duration::from_secs(2),
+        confirmations: 1,
+    }
+}
+
+/// All chain configuration presets.
+pub const PRESETS: &[ChainConfig] = &[
+    MAINNET, SEPOLIA, OPTIMISM, BSC, GNOSIS, POLYGON, BASE, ARBITRUM, AVALANCHE,
+];
+
+/// Returns the configuration preset for the chain with the specified ID.
+pub fn preset(id: u64) -> Option<&'static ChainConfig> {
+    PRESETS.iter().find(|chain| chain.id == id)
+}
+
+impl ChainConfig {
+    /// Creates a new configuration for a user-defined chain. The chain uses
+    /// ETH with 18 decimals as native currency, supports EIP-1559, and has
+    /// 12 second blocks by default.
+    pub fn new(id: u64, name: impl Into<Cow<'static, str>>) -> Self {
+        ChainConfig {
+            id,
+            name: name.into(),
+            short_name: None,
+            currency: NativeCurrency::new("ETH", 18),
+            rpc_url: None,
+            explorer_url: None,
+            eip1559: true,
+            block_time: Duration::from_secs(12),
+            confirmations: 0,
+            code_size_limits: CodeSizeLimits::STANDARD,
+        }
+    }
+
+    /// Sets the ERC-3770 short name of the chain.
+    pub fn short_name(mut self, short_name: impl Into<Cow<'static, str>>) -> Self {
+        self.short_name = Some(short_name.into());
+        self
+    }
+
+    /// Sets the native currency of the chain.
+    pub fn currency(mut self, currency: NativeCurrency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// Sets the URL of a public RPC endpoint of the chain.
+    pub fn rpc_url(mut self, url: impl Into<Cow<'static, str>>) -> Self {
+        self.rpc_url = Some(url.into());
+        self
+    }
+
+    /// Sets the base URL of a block explorer for the chain.
+    pub fn explorer_url(mut self, url: impl Into<Cow<'static, str>>) -> Self {
+        let url = url.into();
+        self.explorer_url = Some(match url {
+            Cow::Borrowed(url) => Cow::Borrowed(url.trim_end_matches('/')),
+            Cow::Owned(url) => Cow::Owned(url.trim_end_matches('/').to_owned()),
+        });
+        self
+    }
+
+    /// Sets whether the chain supports EIP-1559 transactions.
+    pub fn eip1559(mut self, value: bool) -> Self {
+        self.eip1559 = value;
+        self
+    }
+
+    /// Sets the average time between blocks.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.block_time = value;
+        self
+    }
+
+    /// Sets the number of confirmations after which transactions are unlikely
+    /// to be reverted.
+    pub fn confirmations(mut self, value: usize) -> Self {
+        self.confirmations = value;
+        self
+    }
+
+    /// Sets the code size limits of contract deployments, for chains that
+    /// raise the EIP-170 and EIP-3860 limits.
+    pub fn code_size_limits(mut self, value: CodeSizeLimits) -> Self {
+        self.code_size_limits = value;
+        self
+    }
+
+    /// Returns the gas price to use for transactions on the chain with the
+    /// specified urgency. This forecasts fees from the fee history of recent
+    /// blocks on chains supporting EIP-1559, and scales the gas price
+    /// estimated by the node otherwise.
+    pub fn gas_price(&self, urgency: FeeUrgency) -> GasPrice {
+        match (self.eip1559, urgency) {
+            (true, urgency) => GasPrice::Forecast(urgency),
+            (false, FeeUrgency::Slow) => GasPrice::low(),
+            (false, FeeUrgency::Standard) => GasPrice::Standard,
+            (false, FeeUrgency::Fast) => GasPrice::Scaled(1.5),
+        }
+    }
+
+    /// Returns confirmation parameters that wait for the recommended number
+    /// of confirmations of the chain, polling the node at most once per block.
+    pub fn confirm_params(&self) -> ConfirmParams {
+        ConfirmParams::with_confirmations(self.confirmations).block_time(self.block_time)
+    }
+
+    /// Returns method defaults for contract instances on the chain, that wait
+    /// for the recommended number of confirmations and derive the poll
+    /// intervals for confirming transactions and streaming events from the
+    /// block time of the chain.
+    pub fn method_defaults(&self) -> MethodDefaults {
+        MethodDefaults {
+            resolve: Some(ResolveCondition::Confirmed(self.confirm_params())),
+            block_time: Some(self.block_time),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the block explorer URL of a transaction, or `None` if the chain
+    /// has no block explorer.
+    pub fn tx_url(&self, hash: H256) -> Option<String> {
+        self.explorer_link("tx", hash)
+    }
+
+    /// Returns the block explorer URL of an account or contract, or `None` if
+    /// the chain has no block explorer.
+    pub fn address_url(&self, address: Address) -> Option<String> {
+        self.explorer_link("address", address)
+    }
+
+    /// Formats an address with the ERC-3770 short name of the chain, or
+    /// returns `None` if the chain has no short name.
+    pub fn format_address(&self, address: Address) -> Option<String> {
+        let short_name = self.short_name.as_ref()?;
+        Some(address::to_chain_prefixed(short_name, &address))
+    }
+
+    /// Parses an address for the chain. Addresses with an ERC-3770 chain
+    /// prefix are only accepted if the prefix is the short name of the chain,
+    /// which catches addresses that are copied from the configuration of a
+    /// different chain. Addresses without a prefix are accepted as is. EIP-55
+    /// checksums are verified in both cases.
+    pub fn parse_address(&self, s: &str) -> Result<Address, ChainAddressError> {
+        if !s.contains(':') {
+            return Ok(address::parse_checksummed(s)?);
+        }
+
+        let (short_name, address) = address::parse_chain_prefixed(s)?;
+        if self.short_name.as_deref() != Some(short_name) {
+            return Err(ChainAddressError::ChainMismatch {
+                chain_id: self.id,
+                short_name: short_name.to_owned(),
+            });
+        }
+        Ok(address)
+    }
+
+    fn explorer_link(&self, kind: &str, item: impl std::fmt::Debug) -> Option<String> {
+        let explorer = self.explorer_url.as_ref()?;
+        Some(format!("{}/{}/{:?}", explorer, kind, item))
+    }
+}
+
+/// A collection of chain configurations keyed by chain ID, allowing presets
+/// to be extended or overridden with user-defined chains.
+#[derive(Clone, Debug, Default)]
+pub struct Chains {
+    chains: HashMap<u64, ChainConfig>,
+}
+
+impl Chains {
+    /// Creates a new empty collection of chain configurations.
+    pub fn new() -> Self {
+        Chains::default()
+    }
+
+    /// Creates a new collection containing all chain configuration presets.
+    pub fn presets() -> Self {
+        PRESETS.iter().cloned().fold(Chains::new(), Chains::chain)
+    }
+
+    /// Adds a chain configuration, replacing any existing configuration for
+    /// the same chain ID.
+    pub fn chain(mut self, chain: ChainConfig) -> Self {
+        self.chains.insert(chain.id, chain);
+        self
+    }
+
+    /// Returns the configuration of the chain with the specified ID.
+    pub fn get(&self, id: u64) -> Option<&ChainConfig> {
+        self.chains.get(&id)
+    }
+
+    /// Returns the configuration of the chain with the specified ERC-3770
+    /// short name.
+    pub fn by_short_name(&self, short_name: &str) -> Option<&ChainConfig> {
+        self.iter()
+            .find(|chain| chain.short_name.as_deref() == Some(short_name))
+    }
+
+    /// Parses an address for the chain with the specified ID, verifying that
+    /// the ERC-3770 chain prefix of the address matches the chain. See
+    /// [`ChainConfig::parse_address`].
+    pub fn parse_address(&self, s: &str, chain_id: u64) -> Result<Address, ChainAddressError> {
+        self.get(chain_id)
+            .ok_or(ChainAddressError::UnknownChain(chain_id))?
+            .parse_address(s)
+    }
+
+    /// Returns an iterator over the chain configurations in the collection.
+    pub fn iter(&self) -> impl Iterator<Item = &ChainConfig> + '_ {
+        self.chains.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_have_unique_ids() {
+        let chains = Chains::presets();
+        assert_eq!(chains.iter().count(), PRESETS.len());
+        for chain in PRESETS {
+            assert_eq!(preset(chain.id), Some(chain));
+            assert_eq!(
+                chains.by_short_name(chain.short_name.as_deref().unwrap()),
+                Some(chain),
+            );
+        }
+        assert_eq!(preset(31337), None);
+    }
+
+    #[test]
+    fn user_defined_chains_override_presets() {
+        let chains = Chains::presets()
+            .chain(ChainConfig::new(1, "Fork").explorer_url("http://localhost:4000/"))
+            .chain(ChainConfig::new(31337, "Devnet").eip1559(false));
+
+        let fork = chains.get(1).unwrap();
+        assert_eq!(fork.name, "Fork");
+        assert_eq!(fork.explorer_url.as_deref(), Some("http://localhost:4000"));
+        assert_eq!(chains.get(31337).unwrap().rpc_url, None);
+        assert_eq!(chains.get(137), Some(&POLYGON));
+    }
+
+    #[test]
+    fn explorer_links() {
+        assert_eq!(
+            MAINNET.tx_url(H256::repeat_byte(0x42)).unwrap(),
+            "https://etherscan.io/tx/\
+             0x4242424242424242424242424242424242424242424242424242424242424242",
+        );
+        assert_eq!(
+            GNOSIS.address_url(Address::repeat_byte(0xab)).unwrap(),
+            "https://gnosisscan.io/address/0xabababababababababababababababababababab",
+        );
+        assert_eq!(ChainConfig::new(31337, "Devnet").tx_url(H256::zero()), None);
+    }
+
+    #[test]
+    fn chain_prefixed_addresses() {
+        let address = Address::repeat_byte(0xab);
+        let prefixed = OPTIMISM.format_address(address).unwrap();
+        assert_eq!(prefixed, "oeth:0xABaBaBaBABabABabAbAbABAbABabababaBaBABaB",);
+        assert_eq!(OPTIMISM.parse_address(&prefixed).unwrap(), address);
+        assert!(matches!(
+            MAINNET.parse_address(&prefixed),
+            Err(ChainAddressError::ChainMismatch { chain_id: 1, short_name })
+                if short_name == "oeth"
+        ));
+        assert_eq!(MAINNET.parse_address(&prefixed[5..]).unwrap(), address,);
+
+        let chains = Chains::presets();
+        assert_eq!(chains.parse_address(&prefixed, 10).unwrap(), address);
+        assert!(matches!(
+            chains.parse_address(&prefixed, 31337),
+            Err(ChainAddressError::UnknownChain(31337))
+        ));
+
+        let devnet = ChainConfig::new(31337, "Devnet");
+        assert_eq!(devnet.format_address(address), None);
+        assert!(devnet.parse_address(&prefixed).is_err());
+    }
+
+    #[test]
+    fn fee_and_confirmation_hints() {
+        assert_eq!(
+            MAINNET.gas_price(FeeUrgency::Fast),
+            GasPrice::Forecast(FeeUrgency::Fast),
+        );
+        assert_eq!(BSC.gas_price(FeeUrgency::Standard), GasPrice::Standard);
+
+        let params = POLYGON.confirm_params();
+        assert_eq!(params.confirmations, 64);
+        assert_eq!(params.poll_interval_min, Duration::from_millis(500));
+        assert_eq!(params.poll_interval_max, Duration::from_secs(2));
+
+        let defaults = ARBITRUM.method_defaults();
+        assert_eq!(defaults.block_time, Some(Duration::from_millis(250)));
+        assert!(matches!(
+            defaults.resolve,
+            Some(ResolveCondition::Confirmed(params))
+                if params.poll_interval_max == Duration::from_millis(250)
+        ));
+    }
+}
@@ -2,15 +2,22 @@
 //! for sending transactions to contracts as well as querying current contract
 //! state.
 
+mod allowance;
+mod any;
+mod balance;
 mod deploy;
+mod dynamic;
 mod event;
 mod method;
+mod registry;
+mod upgrade;
 
 use crate::{
-    errors::{DeployError, LinkError},
+    bytecode,
+    errors::{DeployError, ExecutionError, LinkError},
     tokens::Tokenize,
 };
-use ethcontract_common::abi::{Error as AbiError, Result as AbiResult};
+use ethcontract_common::abi::{Error as AbiError, Function, Result as AbiResult, Token};
 use ethcontract_common::abiext::FunctionExt;
 use ethcontract_common::hash::H32;
 use ethcontract_common::{Abi, Bytecode, Contract, DeploymentInformation};
@@ -20,12 +27,23 @@ use web3::api::Web3;
 use web3::types::{Address, Bytes, H256};
 use web3::Transport;
 
-pub use self::deploy::{Deploy, DeployBuilder};
+pub use self::allowance::AllowanceOptions;
+pub use self::any::{AnyContract, TypedContract};
+pub use self::deploy::{
+    CodeSizeLimits, Deploy, DeployBuilder, DryRun, MAX_CODE_SIZE, MAX_INIT_CODE_SIZE,
+};
+pub use self::dynamic::{DynContract, DynEvent};
 pub use self::event::{
-    AllEventsBuilder, Event, EventBuilder, EventMetadata, EventStatus, ParseLog, RawLog,
-    StreamEvent, Topic,
+    decode_logs_par, decode_logs_par_with, AllEventsBuilder, BorrowedToken, Event, EventBuilder,
+    EventId, EventMetadata, EventStatus, ParseLog, RawLog, StreamEvent, Topic,
+    DEFAULT_DECODE_THREADS,
+};
+pub use self::method::{
+    CallLimits, MethodBuilder, MethodDefaults, NonPayable, OutputDecoder, Payable,
+    ViewMethodBuilder,
 };
-pub use self::method::{MethodBuilder, MethodDefaults, ViewMethodBuilder};
+pub use self::registry::ChainRegistry;
+pub use self::upgrade::UpgradeOptions;
 use std::marker::PhantomData;
 
 /// Method signature with additional info about method's input and output types.
@@ -134,6 +152,49 @@ impl<T: Transport> Instance<T> {
         ))
     }
 
+    /// Creates a new contract instance like [`Instance::at`], and verifies
+    /// that the `web3` provider is connected to the chain with the expected
+    /// chain ID, if specified, and that there is contract code at the given
+    /// `Address`. This fails fast with a clear error when the provider is
+    /// connected to the wrong network, instead of every later call reverting
+    /// or returning empty data.
+    pub async fn at_checked(
+        web3: Web3<T>,
+        abi: Abi,
+        address: Address,
+        chain_id: Option<u64>,
+    ) -> Result<Self, DeployError> {
+        let instance = Instance::at(web3, abi, address);
+        instance.verify(chain_id).await?;
+        Ok(instance)
+    }
+
+    /// Locates a deployed contract like [`Instance::deployed`], and verifies
+    /// that there is contract code at its address on the network reported by
+    /// the `web3` provider.
+    pub async fn deployed_checked(web3: Web3<T>, contract: Contract) -> Result<Self, DeployError> {
+        let instance = Instance::deployed(web3, contract).await?;
+        instance.verify(None).await?;
+        Ok(instance)
+    }
+
+    /// Verifies that the `web3` provider is connected to the chain with the
+    /// expected chain ID, if specified, and that there is contract code at the
+    /// address of the instance in the latest block.
+    pub async fn verify(&self, chain_id: Option<u64>) -> Result<(), DeployError> {
+        if let Some(expected) = chain_id {
+            let actual = self.web3.eth().chain_id().await?.as_u64();
+            if actual != expected {
+                return Err(DeployError::ChainMismatch { expected, actual });
+            }
+        }
+        let code = self.web3.eth().code(self.address, None).await?;
+        if code.0.is_empty() {
+            return Err(DeployError::NoCode(self.address));
+        }
+        Ok(())
+    }
+
     /// Creates a contract builder with the specified `web3` provider and the
     /// given `Contract` byte code. This allows the contract deployment
     /// transaction to be configured before deploying the contract.
@@ -148,6 +209,22 @@ impl<T: Transport> Instance<T> {
         Linker::new(contract).deploy(web3, params)
     }
 
+    /// Creates a deploy builder for an EIP-1167 minimal proxy, also known as a
+    /// clone, that delegates all calls to the implementation contract at the
+    /// given `Address`. The deployed instance uses the given `Abi`, which is
+    /// usually the ABI of the implementation.
+    pub fn deploy_clone(
+        web3: Web3<T>,
+        abi: Abi,
+        implementation: Address,
+    ) -> DeployBuilder<T, Self> {
+        let linker = Linker {
+            abi,
+            bytecode: Bytecode::default(),
+        };
+        DeployBuilder::clone_of(web3, linker, implementation)
+    }
+
     /// Deploys a contract with the specified `web3` provider with the given
     /// `Contract` byte code and linking libraries.
     pub fn link_and_deploy<'a, P, I>(
@@ -168,6 +245,31 @@ impl<T: Transport> Instance<T> {
         linker.deploy(web3, params)
     }
 
+    /// Sets the default method options that are applied to every method
+    /// builder created from this instance, for example the sender of all
+    /// transactions. Options that are explicitly set on a method builder take
+    /// precedence over the defaults.
+    pub fn with_defaults(mut self, defaults: MethodDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Creates a copy of this contract instance that uses a different `web3`
+    /// provider, for example for sending transactions through a different
+    /// node than the one used for calls. The ABI, address, deployment
+    /// information and method defaults are kept.
+    pub fn with_transport<U: Transport>(&self, web3: Web3<U>) -> Instance<U> {
+        Instance {
+            web3,
+            abi: self.abi.clone(),
+            address: self.address,
+            deployment_information: self.deployment_information,
+            defaults: self.defaults.clone(),
+            methods: self.methods.clone(),
+            events: self.events.clone(),
+        }
+    }
+
     /// Retrieve the underlying web3 provider used by this contract instance.
     pub fn web3(&self) -> Web3<T> {
         self.web3.clone()
@@ -189,6 +291,22 @@ impl<T: Transport> Instance<T> {
         self.deployment_information
     }
 
+    /// Returns whether the contract is an EIP-1167 minimal proxy, also known
+    /// as a clone, of the implementation contract at the given `Address`.
+    pub async fn is_clone_of(&self, implementation: Address) -> Result<bool, ExecutionError> {
+        let code = self.web3.eth().code(self.address, None).await?;
+        Ok(bytecode::minimal_proxy_implementation(&code.0) == Some(implementation))
+    }
+
+    /// Returns the address of the contract the instance delegates to if its
+    /// address is an externally owned account with EIP-7702 delegated code,
+    /// `None` otherwise. Bindings can be used at such addresses to call the
+    /// delegated code in the context of the account.
+    pub async fn delegation(&self) -> Result<Option<Address>, ExecutionError> {
+        let code = self.web3.eth().code(self.address, None).await?;
+        Ok(bytecode::delegation_address(&code.0))
+    }
+
     /// Returns a method builder to setup a call or transaction on a smart
     /// contract method. Note that calls just get evaluated on a node but do not
     /// actually commit anything to the block chain.
@@ -202,17 +320,25 @@ impl<T: Transport> Instance<T> {
         R: Tokenize,
     {
         let signature = signature.into().into_inner();
-        let signature = signature.as_ref();
-        let function = self
-            .methods
-            .get(signature)
-            .map(|(name, index)| &self.abi.functions[name][*index])
-            .ok_or_else(|| AbiError::InvalidName(hex::encode(signature)))?;
+        let function = self.function(signature)?;
         let tokens = match params.into_token() {
             ethcontract_common::abi::Token::Tuple(tokens) => tokens,
             _ => unreachable!("function arguments are always tuples"),
         };
-        let data = function.encode_input(&tokens)?;
+        let types_check = tokens.len() == function.inputs.len()
+            && tokens
+                .iter()
+                .zip(&function.inputs)
+                .all(|(token, input)| token.type_check(&input.kind));
+        if !types_check {
+            return Err(AbiError::InvalidData);
+        }
+        // NOTE: The method signature is the function selector, so use it
+        //   directly instead of hashing the function signature again. The
+        //   calldata is moved into the call or transaction request, so there
+        //   is no buffer for the builder to reuse and `encode_call` allocates
+        //   it once with its exact size instead.
+        let data = crate::abi::encode_call(signature, &tokens);
 
         // take ownership here as it greatly simplifies dealing with futures
         // lifetime as it would require the contract Instance to live until
@@ -241,6 +367,93 @@ impl<T: Transport> Instance<T> {
         Ok(self.method(signature, params)?.view())
     }
 
+    /// Returns a method builder for a contract method with calldata that was
+    /// encoded by the caller, for contracts that do not use the standard ABI
+    /// encoding for their methods, such as gas-optimized contracts with packed
+    /// parameters or custom dispatch. The method identified by the selector is
+    /// only used for decoding return data and for error reporting, and the
+    /// calldata is not decoded for transaction hooks, guards and previews.
+    pub fn method_with_data<R>(
+        &self,
+        selector: H32,
+        data: impl Into<Bytes>,
+    ) -> AbiResult<MethodBuilder<T, R>>
+    where
+        R: Tokenize,
+    {
+        let function = self.function(selector)?.clone();
+        let mut builder = MethodBuilder::new(self.web3(), function, self.address, data.into())
+            .with_defaults(&self.defaults);
+        builder.tx.function = None;
+        Ok(builder)
+    }
+
+    /// Returns a view method builder for a call to a contract method with
+    /// calldata that was encoded by the caller. See
+    /// [`Instance::method_with_data`].
+    pub fn view_method_with_data<R>(
+        &self,
+        selector: H32,
+        data: impl Into<Bytes>,
+    ) -> AbiResult<ViewMethodBuilder<T, R>>
+    where
+        R: Tokenize,
+    {
+        Ok(self.method_with_data(selector, data)?.view())
+    }
+
+    /// Returns a method builder for a contract method identified by its name
+    /// or full ABI signature (for example `transfer(address,uint256)`), with
+    /// dynamically typed parameters and return value.
+    ///
+    /// A method name can only be used if the method is not overloaded.
+    pub fn method_by_name(
+        &self,
+        name: &str,
+        params: Vec<Token>,
+    ) -> AbiResult<MethodBuilder<T, Token>> {
+        let selector = self.find_selector(name)?;
+        self.method(selector, Token::Tuple(params))
+    }
+
+    /// Returns a view method builder for a contract method identified by its
+    /// name or full ABI signature, with dynamically typed parameters and return
+    /// value. See [`Instance::method_by_name`] for more details.
+    pub fn view_method_by_name(
+        &self,
+        name: &str,
+        params: Vec<Token>,
+    ) -> AbiResult<ViewMethodBuilder<T, Token>> {
+        Ok(self.method_by_name(name, params)?.view())
+    }
+
+    /// Finds a method by its selector.
+    fn function(&self, selector: H32) -> AbiResult<&Function> {
+        self.methods
+            .get(&selector)
+            .map(|(name, index)| &self.abi.functions[name][*index])
+            .ok_or_else(|| AbiError::InvalidName(hex::encode(selector)))
+    }
+
+    /// Finds the selector of a method by its name or full ABI signature.
+    fn find_selector(&self, name: &str) -> AbiResult<H32> {
+        let invalid_name = || AbiError::InvalidName(name.to_owned());
+        if name.contains('(') {
+            return self
+                .abi
+                .functions()
+                .find(|function| function.abi_signature() == name)
+                .map(|function| function.selector())
+                .ok_or_else(invalid_name);
+        }
+
+        match self.abi.functions.get(name).map(Vec::as_slice) {
+            Some([function]) => Ok(function.selector()),
+            // NOTE: Overloaded methods must be identified by their signature.
+            _ => Err(invalid_name()),
+        }
+    }
+
     /// Returns a method builder to setup a call to a smart contract's fallback
     /// function.
     ///
@@ -254,11 +467,10 @@ impl<T: Transport> Instance<T> {
             return Err(AbiError::InvalidName("fallback".into()));
         }
 
-        Ok(MethodBuilder::fallback(
-            self.web3(),
-            self.address,
-            Bytes(data.into()),
-        ))
+        Ok(
+            MethodBuilder::fallback(self.web3(), self.address, Bytes(data.into()))
+                .with_defaults(&self.defaults),
+        )
     }
 
     /// Returns a event builder to setup an event stream for a smart contract
@@ -271,19 +483,24 @@ impl<T: Transport> Instance<T> {
             .events
             .get(&signature)
             .map(|(name, index)| &self.abi.events[name][*index])
-            .ok_or_else(|| AbiError::InvalidName(hex::encode(&signature)))?;
+            .ok_or_else(|| AbiError::InvalidName(hex::encode(signature)))?;
 
-        Ok(EventBuilder::new(
-            self.web3(),
-            event.clone(),
-            self.address(),
-        ))
+        let builder = EventBuilder::new(self.web3(), event.clone(), self.address());
+        Ok(match self.defaults.block_time {
+            Some(block_time) => builder.block_time(block_time),
+            None => builder,
+        })
     }
 
     /// Returns a log stream that emits a log for every new event emitted after
     /// the stream was created for this contract instance.
     pub fn all_events(&self) -> AllEventsBuilder<T, RawLog> {
-        AllEventsBuilder::new(self.web3(), self.address(), self.deployment_information())
+        let builder =
+            AllEventsBuilder::new(self.web3(), self.address(), self.deployment_information());
+        match self.defaults.block_time {
+            Some(block_time) => builder.block_time(block_time),
+            None => builder,
+        }
     }
 }
 
@@ -388,6 +605,8 @@ where
 mod tests {
     use super::*;
     use crate::test::prelude::*;
+    use crate::transaction::confirm::ConfirmParams;
+    use crate::transaction::{Account, GasPrice, ResolveCondition};
     use ethcontract_common::contract::Network;
     use ethcontract_common::Contract;
     use web3::types::H256;
@@ -428,6 +647,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn with_transport() {
+        let transport = TestTransport::new();
+        let mut other = TestTransport::new();
+
+        let address = addr!("0x0102030405060708091011121314151617181920");
+        let mut instance = Instance::at(Web3::new(transport.clone()), Abi::default(), address);
+        instance.defaults.gas = Some(42.into());
+        let retargeted = instance.with_transport(Web3::new(other.clone()));
+
+        assert_eq!(retargeted.address(), address);
+        assert_eq!(retargeted.defaults.gas, Some(42.into()));
+
+        other.add_response(json!("0x1"));
+        retargeted.web3().eth().block_number().immediate().unwrap();
+        other.assert_request("eth_blockNumber", &[]);
+        other.assert_no_more_requests();
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn defaults_are_applied_to_method_builders() {
+        let transport = TestTransport::new();
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let mut abi = Abi::load(
+            br#"[{
+                "type": "function",
+                "name": "claim",
+                "inputs": [],
+                "outputs": [],
+                "stateMutability": "nonpayable"
+            }]"#
+            .as_ref(),
+        )
+        .unwrap();
+        abi.fallback = true;
+        let instance = Instance::at(Web3::new(transport.clone()), abi, Address::zero())
+            .with_defaults(MethodDefaults {
+                from: Some(Account::Local(from, None)),
+                gas_price: Some(GasPrice::Scaled(1.5)),
+                resolve: Some(ResolveCondition::Confirmed(
+                    ConfirmParams::with_confirmations(3),
+                )),
+                ..Default::default()
+            });
+
+        let method = instance
+            .method_by_name("claim", Vec::new())
+            .unwrap()
+            .into_inner();
+        let fallback = instance.fallback(vec![]).unwrap().into_inner();
+        for tx in &[method, fallback] {
+            assert_eq!(tx.from.as_ref().map(Account::address), Some(from));
+            assert_eq!(tx.gas_price, Some(GasPrice::Scaled(1.5)));
+            assert!(matches!(
+                &tx.resolve,
+                Some(ResolveCondition::Confirmed(params)) if params.confirmations == 3
+            ));
+        }
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn deployed_not_found() {
         let mut transport = TestTransport::new();
@@ -453,4 +734,170 @@ mod tests {
             err
         );
     }
+
+    #[test]
+    fn at_checked() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let address = addr!("0x0102030405060708091011121314151617181920");
+
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!("0x6080"));
+        let instance = Instance::at_checked(web3.clone(), Abi::default(), address, Some(1))
+            .immediate()
+            .expect("failed to verify instance");
+        assert_eq!(instance.address(), address);
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_request("eth_getCode", &[json!(address), json!("latest")]);
+
+        transport.add_response(json!("0x5"));
+        let err = Instance::at_checked(web3.clone(), Abi::default(), address, Some(1))
+            .immediate()
+            .expect_err("unexpected success on wrong chain");
+        assert!(matches!(
+            err,
+            DeployError::ChainMismatch {
+                expected: 1,
+                actual: 5,
+            }
+        ));
+        transport.assert_request("eth_chainId", &[]);
+
+        transport.add_response(json!("0x"));
+        let err = Instance::at_checked(web3, Abi::default(), address, None)
+            .immediate()
+            .expect_err("unexpected success without code");
+        assert!(matches!(err, DeployError::NoCode(a) if a == address));
+        transport.assert_request("eth_getCode", &[json!(address), json!("latest")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn method_with_custom_codec() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let address = addr!("0x0102030405060708091011121314151617181920");
+        let abi = Abi::load(
+            br#"[{
+                "type": "function",
+                "name": "price",
+                "inputs": [{ "name": "id", "type": "uint8" }],
+                "outputs": [{ "name": "", "type": "uint16" }],
+                "stateMutability": "view"
+            }]"#
+            .as_ref(),
+        )
+        .unwrap();
+        let instance = Instance::at(web3, abi, address);
+        let selector = instance.abi().function("price").unwrap().selector();
+
+        fn decode_price(data: &[u8]) -> Result<u16, AbiError> {
+            match data {
+                [high, low] => Ok(u16::from_be_bytes([*high, *low])),
+                _ => Err(AbiError::InvalidData),
+            }
+        }
+
+        transport.add_response(json!("0x0102"));
+        let price = instance
+            .view_method_with_data(selector, vec![0x01, 0x07])
+            .unwrap()
+            .output_decoder(decode_price)
+            .call()
+            .immediate()
+            .expect("call error");
+        assert_eq!(price, 0x0102);
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "to": address, "data": "0x0107" }), json!("latest")],
+        );
+
+        transport.add_response(json!("0x01"));
+        let err = instance
+            .view_method_with_data(selector, vec![0x01, 0x07])
+            .unwrap()
+            .output_decoder(decode_price)
+            .call()
+            .immediate()
+            .expect_err("unexpected success decoding invalid data");
+        assert!(matches!(err.inner, ExecutionError::AbiDecode(_)));
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "to": address, "data": "0x0107" }), json!("latest")],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn delegated_account() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let address = Address::repeat_byte(0x01);
+        let delegate = Address::repeat_byte(0x42);
+        let instance = Instance::at(web3, Abi::default(), address);
+
+        transport.add_response(json!(Bytes(bytecode::delegation_code(delegate))));
+        transport.add_response(json!("0x"));
+        assert_eq!(instance.delegation().immediate().unwrap(), Some(delegate));
+        assert_eq!(instance.delegation().immediate().unwrap(), None);
+
+        for _ in 0..2 {
+            transport.assert_request("eth_getCode", &[json!(address), json!("latest")]);
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn deploy_clone() {
+        use crate::dev;
+        use crate::test::contracts::{COUNTER, COUNTER_ABI};
+
+        futures::executor::block_on(async {
+            let node = dev::spawn();
+            let abi = Abi::load(COUNTER_ABI.as_bytes()).unwrap();
+            let contract = Contract {
+                abi: abi.clone(),
+                bytecode: Bytecode::from_hex_str(COUNTER).unwrap(),
+                ..Contract::empty()
+            };
+            let implementation = Instance::builder(node.web3(), contract, ())
+                .unwrap()
+                .from(node.account(0))
+                .deploy()
+                .await
+                .unwrap();
+
+            let clone = Instance::deploy_clone(node.web3(), abi, implementation.address())
+                .from(node.account(0))
+                .deploy()
+                .await
+                .unwrap();
+            assert_ne!(clone.address(), implementation.address());
+
+            clone
+                .method_by_name("increment", vec![])
+                .unwrap()
+                .from(node.account(0))
+                .send()
+                .await
+                .unwrap();
+            for (instance, value) in &[(&clone, 1), (&implementation, 0)] {
+                let result = instance
+                    .view_method_by_name("value", vec![])
+                    .unwrap()
+                    .from(node.accounts()[0])
+                    .call()
+                    .await
+                    .unwrap();
+                assert_eq!(result, Token::Uint((*value).into()));
+            }
+
+            assert!(clone.is_clone_of(implementation.address()).await.unwrap());
+            assert!(!clone.is_clone_of(clone.address()).await.unwrap());
+            assert!(!implementation
+                .is_clone_of(implementation.address())
+                .await
+                .unwrap());
+        });
+    }
 }
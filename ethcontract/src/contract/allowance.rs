@@ -0,0 +1,234 @@
+//! Convenience flows for managing ERC20 token allowances.
+
+use crate::contract::Instance;
+use crate::errors::MethodError;
+use crate::transaction::{Account, TransactionResult};
+use ethcontract_common::hash::function_selector;
+use web3::types::{Address, U256};
+use web3::Transport;
+
+/// The signature of the ERC20 `allowance` function.
+const ALLOWANCE: &str = "allowance(address,address)";
+
+/// The signature of the ERC20 `approve` function.
+const APPROVE: &str = "approve(address,uint256)";
+
+/// Options for ensuring an ERC20 token allowance.
+#[derive(Clone, Debug, Default)]
+pub struct AllowanceOptions {
+    /// The allowance to approve when the current allowance is insufficient,
+    /// for example `U256::MAX` for an unlimited approval. Defaults to the
+    /// minimum required allowance.
+    pub approve_amount: Option<U256>,
+    /// Reset a non-zero allowance to zero before approving the new allowance.
+    /// This is required by tokens such as USDT that do not allow changing an
+    /// allowance from one non-zero value to another.
+    pub reset_to_zero: bool,
+}
+
+impl AllowanceOptions {
+    /// Specify the allowance to approve when the current allowance is
+    /// insufficient.
+    pub fn approve_amount(mut self, value: U256) -> Self {
+        self.approve_amount = Some(value);
+        self
+    }
+
+    /// Specify whether or not to reset a non-zero allowance to zero before
+    /// approving the new allowance.
+    pub fn reset_to_zero(mut self, value: bool) -> Self {
+        self.reset_to_zero = value;
+        self
+    }
+}
+
+impl<T: Transport> Instance<T> {
+    /// Ensures that `spender` is allowed to spend at least `min_amount` tokens
+    /// of `owner` for an ERC20 token contract. The current allowance is read
+    /// first, and an approval is only sent when it is insufficient. Returns
+    /// the results of the approval transactions that were sent, which is
+    /// empty if the allowance was already sufficient.
+    ///
+    /// Approvals are signed with the default account of the instance if it is
+    /// the owner's account, and signed by the node otherwise.
+    pub async fn ensure_allowance(
+        &self,
+        owner: Address,
+        spender: Address,
+        min_amount: U256,
+        options: AllowanceOptions,
+    ) -> Result<Vec<TransactionResult>, MethodError> {
+        let allowance = self
+            .view_method::<_, U256>(function_selector(ALLOWANCE), (owner, spender))
+            .map_err(|err| MethodError::from_parts(ALLOWANCE.to_owned(), err.into()))?
+            .call()
+            .await?;
+        if allowance >= min_amount {
+            return Ok(Vec::new());
+        }
+
+        let from = match &self.defaults.from {
+            Some(account) if account.address() == owner => account.clone(),
+            _ => Account::Local(owner, None),
+        };
+        let approve = |amount: U256| {
+            self.method::<_, ()>(function_selector(APPROVE), (spender, amount))
+                .map(|method| method.from(from.clone()))
+                .map_err(|err| MethodError::from_parts(APPROVE.to_owned(), err.into()))
+        };
+
+        let mut results = Vec::new();
+        if options.reset_to_zero && !allowance.is_zero() {
+            results.push(approve(U256::zero())?.send().await?);
+        }
+        let amount = options.approve_amount.unwrap_or_default().max(min_amount);
+        results.push(approve(amount)?.send().await?);
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use ethcontract_common::Abi;
+    use serde_json::Value;
+    use web3::api::Web3;
+    use web3::types::{H2048, H256};
+
+    const ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "allowance",
+            "inputs": [
+                { "name": "owner", "type": "address" },
+                { "name": "spender", "type": "address" }
+            ],
+            "outputs": [{ "name": "", "type": "uint256" }],
+            "stateMutability": "view"
+        },
+        {
+            "type": "function",
+            "name": "approve",
+            "inputs": [
+                { "name": "spender", "type": "address" },
+                { "name": "amount", "type": "uint256" }
+            ],
+            "outputs": [],
+            "stateMutability": "nonpayable"
+        }
+    ]"#;
+
+    fn instance(transport: &TestTransport) -> Instance<TestTransport> {
+        Instance::at(
+            Web3::new(transport.clone()),
+            Abi::load(ABI.as_bytes()).unwrap(),
+            Address::repeat_byte(0x42),
+        )
+    }
+
+    fn allowance_call(owner: Address, spender: Address) -> Value {
+        json!({
+            "to": Address::repeat_byte(0x42),
+            "data": format!(
+                "0xdd62ed3e000000000000000000000000{}000000000000000000000000{}",
+                hex::encode(owner),
+                hex::encode(spender),
+            ),
+        })
+    }
+
+    fn add_sent_transaction(transport: &mut TestTransport, hash: H256) {
+        transport.add_response(json!("0x1")); // gas limit
+        transport.add_response(json!(hash));
+        transport.add_response(json!("0x1")); // block number
+        transport.add_response(json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": "0x1",
+            "blockHash": H256::repeat_byte(1),
+            "cumulativeGasUsed": "0x1",
+            "gasUsed": "0x1",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x1",
+        }));
+    }
+
+    fn approve_data(spender: Address, amount: u64) -> String {
+        format!(
+            "0x095ea7b3000000000000000000000000{}{:064x}",
+            hex::encode(spender),
+            amount,
+        )
+    }
+
+    #[test]
+    fn sufficient_allowance() {
+        let mut transport = TestTransport::new();
+        let (owner, spender) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        transport.add_response(json!(H256::from_low_u64_be(100)));
+        let results = instance(&transport)
+            .ensure_allowance(owner, spender, 100.into(), Default::default())
+            .immediate()
+            .unwrap();
+
+        assert!(results.is_empty());
+        transport.assert_request(
+            "eth_call",
+            &[allowance_call(owner, spender), json!("latest")],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn insufficient_allowance_with_reset() {
+        let mut transport = TestTransport::new();
+        let (owner, spender) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        transport.add_response(json!(H256::from_low_u64_be(1)));
+        add_sent_transaction(&mut transport, H256::repeat_byte(3));
+        add_sent_transaction(&mut transport, H256::repeat_byte(4));
+        let results = instance(&transport)
+            .ensure_allowance(
+                owner,
+                spender,
+                100.into(),
+                AllowanceOptions::default()
+                    .approve_amount(1000.into())
+                    .reset_to_zero(true),
+            )
+            .immediate()
+            .unwrap();
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.hash())
+                .collect::<Vec<_>>(),
+            vec![H256::repeat_byte(3), H256::repeat_byte(4)],
+        );
+        transport.assert_request(
+            "eth_call",
+            &[allowance_call(owner, spender), json!("latest")],
+        );
+        for (amount, hash) in [(0, 3), (1000, 4)].iter().copied() {
+            let mut tx = json!({
+                "from": owner,
+                "to": Address::repeat_byte(0x42),
+                "data": approve_data(spender, amount),
+            });
+            transport.assert_request("eth_estimateGas", std::slice::from_ref(&tx));
+            tx["gas"] = json!("0x1");
+            transport.assert_request("eth_sendTransaction", &[tx]);
+            transport.assert_request("eth_blockNumber", &[]);
+            transport.assert_request(
+                "eth_getTransactionReceipt",
+                &[json!(H256::repeat_byte(hash))],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+}
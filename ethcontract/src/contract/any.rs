@@ -0,0 +1,196 @@
+//! Type-erased contract instances, allowing contracts of different types to be
+//! stored and used uniformly.
+
+use crate::contract::{MethodBuilder, ViewMethodBuilder};
+use crate::dyns::{DynInstance, DynTransport};
+use ethcontract_common::abi::{Result as AbiResult, Token};
+use ethcontract_common::{Abi, Contract};
+use std::any::Any;
+use web3::types::Address;
+
+/// A type-erased contract instance, implemented by all generated contract
+/// types.
+///
+/// This allows contracts of different types to be stored together, for
+/// example in a registry of tokens, as `Box<dyn AnyContract>`. Methods can be
+/// called by name with dynamically typed parameters and return values, and the
+/// instance can be downcast back to its typed bindings when needed.
+pub trait AnyContract: Any + Send + Sync {
+    /// Returns the name of the contract.
+    fn name(&self) -> &str;
+
+    /// Returns a reference to the raw runtime instance used by this contract.
+    fn raw_instance(&self) -> &DynInstance;
+
+    /// Returns this contract as `Any`, so that it can be downcast to its
+    /// concrete type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns the contract address.
+    fn address(&self) -> Address {
+        self.raw_instance().address()
+    }
+
+    /// Returns the contract ABI.
+    fn abi(&self) -> &Abi {
+        self.raw_instance().abi()
+    }
+
+    /// Returns a method builder for a contract method identified by its name or
+    /// full ABI signature. See [`Instance::method_by_name`] for more details.
+    ///
+    /// [`Instance::method_by_name`]: crate::contract::Instance::method_by_name
+    fn method(
+        &self,
+        name: &str,
+        params: Vec<Token>,
+    ) -> AbiResult<MethodBuilder<DynTransport, Token>> {
+        self.raw_instance().method_by_name(name, params)
+    }
+
+    /// Returns a view method builder for a contract method identified by its
+    /// name or full ABI signature.
+    fn view_method(
+        &self,
+        name: &str,
+        params: Vec<Token>,
+    ) -> AbiResult<ViewMethodBuilder<DynTransport, Token>> {
+        self.raw_instance().view_method_by_name(name, params)
+    }
+}
+
+impl dyn AnyContract {
+    /// Returns `true` if the contract is of type `C`.
+    pub fn is<C: AnyContract>(&self) -> bool {
+        self.as_any().is::<C>()
+    }
+
+    /// Returns a reference to the typed contract if it is of type `C`.
+    pub fn downcast_ref<C: AnyContract>(&self) -> Option<&C> {
+        self.as_any().downcast_ref()
+    }
+}
+
+/// A typed contract that can be created from a raw runtime instance,
+/// implemented by all generated contract types.
+///
+/// This allows generic code, such as the [`ChainRegistry`], to create
+/// instances of generated contract types.
+///
+/// [`ChainRegistry`]: crate::contract::ChainRegistry
+pub trait TypedContract: AnyContract + Sized {
+    /// Returns the contract artifact used to generate the contract type.
+    fn contract() -> &'static Contract;
+
+    /// Creates a typed contract from a raw runtime instance. Note that this
+    /// does not verify that the instance ABI matches the contract type.
+    fn from_instance(instance: DynInstance) -> Self;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transport::DynTransport;
+    use crate::Instance;
+    use ethcontract_common::abi::{Function, Param, ParamType, StateMutability};
+    use web3::Web3;
+
+    struct Token0(DynInstance);
+    struct Token1(DynInstance);
+
+    impl AnyContract for Token0 {
+        fn name(&self) -> &str {
+            "Token0"
+        }
+        fn raw_instance(&self) -> &DynInstance {
+            &self.0
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl AnyContract for Token1 {
+        fn name(&self) -> &str {
+            "Token1"
+        }
+        fn raw_instance(&self) -> &DynInstance {
+            &self.0
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    fn instance(transport: &TestTransport, address: Address) -> DynInstance {
+        let mut abi = Abi {
+            constructor: None,
+            functions: Default::default(),
+            events: Default::default(),
+            fallback: false,
+            receive: false,
+        };
+        #[allow(deprecated)]
+        let function = Function {
+            name: "balanceOf".to_owned(),
+            inputs: vec![Param {
+                name: "owner".to_owned(),
+                kind: ParamType::Address,
+            }],
+            outputs: vec![Param {
+                name: "".to_owned(),
+                kind: ParamType::Uint(256),
+            }],
+            constant: false,
+            state_mutability: StateMutability::View,
+        };
+        abi.functions.insert(function.name.clone(), vec![function]);
+
+        let web3 = Web3::new(DynTransport::new(transport.clone()));
+        Instance::at(web3, abi, address)
+    }
+
+    #[test]
+    fn heterogeneous_contracts() {
+        let mut transport = TestTransport::new();
+        let owner = addr!("0x0101010101010101010101010101010101010101");
+        let contracts: Vec<Box<dyn AnyContract>> = vec![
+            Box::new(Token0(instance(&transport, Address::repeat_byte(0)))),
+            Box::new(Token1(instance(&transport, Address::repeat_byte(1)))),
+        ];
+
+        assert_eq!(contracts[1].name(), "Token1");
+        assert_eq!(contracts[1].address(), Address::repeat_byte(1));
+        assert!(contracts[0].is::<Token0>());
+        assert!(contracts[0].downcast_ref::<Token1>().is_none());
+        assert!(contracts[1].downcast_ref::<Token1>().is_some());
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        let balance = contracts[0]
+            .view_method("balanceOf", vec![Token::Address(owner)])
+            .unwrap()
+            .call()
+            .immediate()
+            .unwrap();
+        assert_eq!(balance, Token::Uint(42.into()));
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": Address::repeat_byte(0),
+                    "data": "0x70a082310000000000000000000000000101010101010101010101010101010101010101",
+                }),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert!(contracts[0].method("transfer", vec![]).is_err());
+        assert!(contracts[0]
+            .method("balanceOf(address)", vec![Token::Address(owner)])
+            .is_ok());
+    }
+}
@@ -0,0 +1,110 @@
+//! Convenience reads of ERC20 token balances.
+
+use crate::batch::CallBatch;
+use crate::block::BlockId;
+use crate::contract::Instance;
+use crate::errors::MethodError;
+use ethcontract_common::hash::function_selector;
+use futures::future;
+use web3::types::{Address, U256};
+use web3::BatchTransport;
+
+/// The signature of the ERC20 `balanceOf` function.
+const BALANCE_OF: &str = "balanceOf(address)";
+
+/// The maximum number of balance calls per batch request.
+const BATCH_SIZE: usize = 100;
+
+impl<T: BatchTransport> Instance<T> {
+    /// Retrieves the ERC20 token balances of multiple owners in the latest
+    /// block, or the block with the default block tag of the instance, with
+    /// batched `balanceOf` calls. The balances are returned in the order of the
+    /// owners.
+    pub async fn balance_of_many(&self, owners: &[Address]) -> Result<Vec<U256>, MethodError> {
+        self.batched_balances(owners, None).await
+    }
+
+    /// Retrieves the ERC20 token balances of multiple owners in the specified
+    /// block.
+    pub async fn balance_of_many_at(
+        &self,
+        owners: &[Address],
+        block: impl Into<BlockId>,
+    ) -> Result<Vec<U256>, MethodError> {
+        self.batched_balances(owners, Some(block.into())).await
+    }
+
+    async fn batched_balances(
+        &self,
+        owners: &[Address],
+        block: Option<BlockId>,
+    ) -> Result<Vec<U256>, MethodError> {
+        let mut batch = CallBatch::new(self.web3().transport().clone());
+        let calls = owners
+            .iter()
+            .map(|owner| {
+                let mut call = self
+                    .view_method::<_, U256>(function_selector(BALANCE_OF), (*owner,))
+                    .map_err(|err| MethodError::from_parts(BALANCE_OF.to_owned(), err.into()))?;
+                if let Some(block) = block {
+                    call = call.block(block);
+                }
+                Ok(call.batch_call(&mut batch))
+            })
+            .collect::<Result<Vec<_>, MethodError>>()?;
+
+        batch.execute_all(BATCH_SIZE).await;
+        future::try_join_all(calls).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use ethcontract_common::Abi;
+    use web3::api::Web3;
+    use web3::types::H256;
+
+    const ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "balanceOf",
+            "inputs": [{ "name": "owner", "type": "address" }],
+            "outputs": [{ "name": "", "type": "uint256" }],
+            "stateMutability": "view"
+        }
+    ]"#;
+
+    #[test]
+    fn batched_balances() {
+        let mut transport = TestTransport::new();
+        let instance = Instance::at(
+            Web3::new(transport.clone()),
+            Abi::load(ABI.as_bytes()).unwrap(),
+            Address::repeat_byte(0x42),
+        );
+        let owners = [Address::repeat_byte(1), Address::repeat_byte(2)];
+
+        transport.add_response(json!([
+            H256::from_low_u64_be(10),
+            H256::from_low_u64_be(20)
+        ]));
+        let balances = instance.balance_of_many_at(&owners, 7).immediate().unwrap();
+
+        assert_eq!(balances, vec![10.into(), 20.into()]);
+        for owner in &owners {
+            transport.assert_request(
+                "eth_call",
+                &[
+                    json!({
+                        "to": Address::repeat_byte(0x42),
+                        "data": format!("0x70a08231000000000000000000000000{}", hex::encode(owner)),
+                    }),
+                    json!("0x7"),
+                ],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+}
@@ -1,14 +1,21 @@
 //! Implementation for creating instances for deployed contracts and deploying
 //! new contracts.
 
+use crate::bytecode;
 use crate::errors::{DeployError, ExecutionError};
 use crate::tokens::Tokenize;
-use crate::transaction::{Account, GasPrice, TransactionBuilder, TransactionResult};
+use crate::transaction::{
+    Account, GasPrice, TransactionBuilder, TransactionExtension, TransactionGuard, TransactionHook,
+    TransactionResult,
+};
 use ethcontract_common::abi::Error as AbiError;
+use ethcontract_common::address;
 use ethcontract_common::{Abi, Bytecode};
 use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
 use web3::api::Web3;
-use web3::types::{Address, Bytes, H256, U256};
+use web3::types::{Address, BlockNumber, Bytes, CallRequest, H256, U256};
 use web3::Transport;
 
 /// a factory trait for deployable contract instances. this traits provides
@@ -36,6 +43,55 @@ pub trait Deploy<T: Transport>: Sized {
     ) -> Self;
 }
 
+/// The maximum runtime code size of a contract, as specified by EIP-170.
+pub const MAX_CODE_SIZE: usize = 0x6000;
+
+/// The maximum init code size of a contract creation transaction, as
+/// specified by EIP-3860.
+pub const MAX_INIT_CODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// The code size limits of contract deployments on a chain.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CodeSizeLimits {
+    /// The maximum runtime code size of a contract.
+    pub max_code_size: usize,
+    /// The maximum init code size of a contract creation transaction.
+    pub max_init_code_size: usize,
+}
+
+impl CodeSizeLimits {
+    /// The code size limits of EIP-170 and EIP-3860, which are used by
+    /// Ethereum mainnet and most other chains.
+    pub const STANDARD: CodeSizeLimits = CodeSizeLimits {
+        max_code_size: MAX_CODE_SIZE,
+        max_init_code_size: MAX_INIT_CODE_SIZE,
+    };
+}
+
+impl Default for CodeSizeLimits {
+    fn default() -> Self {
+        CodeSizeLimits::STANDARD
+    }
+}
+
+/// The predicted result of a contract deployment, see
+/// [`DeployBuilder::dry_run`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DryRun {
+    /// The account that would deploy the contract.
+    pub from: Address,
+    /// The nonce of the deployment transaction.
+    pub nonce: U256,
+    /// The gas limit of the deployment transaction, which is the estimated gas
+    /// if no gas limit was specified.
+    pub gas: U256,
+    /// The predicted address of the deployed contract.
+    pub contract_address: Address,
+    /// The runtime code of the deployed contract returned by simulating the
+    /// constructor.
+    pub code: Bytes,
+}
+
 /// Builder for specifying options for deploying a linked contract.
 #[derive(Debug, Clone)]
 #[must_use = "deploy builers do nothing unless you `.deploy()` them"]
@@ -50,6 +106,8 @@ where
     context: I::Context,
     /// The underlying transaction used t
     tx: TransactionBuilder<T>,
+    /// The code size limits to check the deployment against.
+    code_size_limits: Option<CodeSizeLimits>,
     _instance: PhantomData<I>,
 }
 
@@ -88,10 +146,30 @@ where
             web3: web3.clone(),
             context,
             tx: TransactionBuilder::new(web3).data(data).confirmations(0),
+            code_size_limits: Some(CodeSizeLimits::STANDARD),
             _instance: PhantomData,
         })
     }
 
+    /// Create a new deploy builder for an EIP-1167 minimal proxy, also known
+    /// as a clone, that delegates all calls to an existing implementation
+    /// contract. The deployed instance uses the ABI of the contract data.
+    ///
+    /// Note that the constructor of the contract is not executed for clones,
+    /// so they are usually initialized with a separate transaction.
+    pub fn clone_of(web3: Web3<T>, context: I::Context, implementation: Address) -> Self {
+        let code = bytecode::minimal_proxy_creation_code(implementation);
+        DeployBuilder {
+            web3: web3.clone(),
+            context,
+            tx: TransactionBuilder::new(web3)
+                .data(Bytes(code))
+                .confirmations(0),
+            code_size_limits: Some(CodeSizeLimits::STANDARD),
+            _instance: PhantomData,
+        }
+    }
+
     /// Specify the signing method to use for the transaction, if not specified
     /// the the transaction will be locally signed with the default user.
     pub fn from(mut self, value: Account) -> Self {
@@ -135,15 +213,149 @@ where
         self
     }
 
+    /// Specify a timeout for deploying the contract, if not specified there is
+    /// no timeout. See [`TransactionBuilder::timeout`] for more details.
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.tx = self.tx.timeout(value);
+        self
+    }
+
+    /// Specify the expected block time of the chain for deriving the poll
+    /// intervals used for confirming the deployment transaction. See
+    /// [`TransactionBuilder::block_time`] for more details.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.tx = self.tx.block_time(value);
+        self
+    }
+
+    /// Specify a hook that is notified of the deployment transaction. See
+    /// [`TransactionBuilder::hook`] for more details.
+    pub fn hook(mut self, value: Arc<dyn TransactionHook>) -> Self {
+        self.tx = self.tx.hook(value);
+        self
+    }
+
+    /// Specify a guard that is consulted before signing the deployment
+    /// transaction. See [`TransactionBuilder::guard`] for more details.
+    pub fn guard(mut self, value: Arc<dyn TransactionGuard>) -> Self {
+        self.tx = self.tx.guard(value);
+        self
+    }
+
+    /// Specify whether to check the deployment transaction against the latest
+    /// block before it is signed. See [`TransactionBuilder::preflight`] for
+    /// more details.
+    pub fn preflight(mut self, value: bool) -> Self {
+        self.tx = self.tx.preflight(value);
+        self
+    }
+
+    /// Specify whether to check that the sender can pay for the deployment
+    /// transaction before it is signed. See
+    /// [`TransactionBuilder::check_balance`] for more details.
+    pub fn check_balance(mut self, value: bool) -> Self {
+        self.tx = self.tx.check_balance(value);
+        self
+    }
+
+    /// Specify an extension that adds nonstandard fields to the deployment
+    /// transaction. See [`TransactionBuilder::extension`] for more details.
+    pub fn extension(mut self, value: impl TransactionExtension + 'static) -> Self {
+        self.tx = self.tx.extension(value);
+        self
+    }
+
+    /// Specify the ERC20 token to pay transaction fees with on chains that
+    /// support the Celo `feeCurrency` transaction field. See
+    /// [`TransactionBuilder::fee_currency`] for more details.
+    pub fn fee_currency(mut self, token: Address) -> Self {
+        self.tx = self.tx.fee_currency(token);
+        self
+    }
+
+    /// Specify the code size limits to check the deployment against, or `None`
+    /// to skip the checks, which defaults to the EIP-170 and EIP-3860 limits.
+    /// Use the `code_size_limits` of the chain configuration for chains that
+    /// raise the limits.
+    ///
+    /// The init code size is always checked before deploying. The runtime
+    /// code size is checked by dry runs, and by deployments with preflight
+    /// checks, which simulate the constructor to retrieve the runtime code.
+    pub fn code_size_limits(mut self, value: Option<CodeSizeLimits>) -> Self {
+        self.code_size_limits = value;
+        self
+    }
+
     /// Extract inner `TransactionBuilder` from this `DeployBuilder`. This
     /// exposes `TransactionBuilder` only APIs.
     pub fn into_inner(self) -> TransactionBuilder<T> {
         self.tx
     }
 
+    /// Predicts the `CREATE2` address of the contract when deployed by the
+    /// specified factory with the specified salt instead of by a deployment
+    /// transaction.
+    pub fn create2_address(&self, factory: Address, salt: H256) -> Address {
+        let init_code = self.tx.data.as_ref().map(|data| &data.0[..]).unwrap_or(&[]);
+        address::create2_address(&factory, salt.0, init_code)
+    }
+
+    /// Simulates the deployment without sending a transaction. This estimates
+    /// the deployment gas, predicts the address of the deployed contract from
+    /// the deployer nonce, and simulates the constructor with an `eth_call` to
+    /// retrieve the code of the deployed contract.
+    ///
+    /// Reverting constructors fail the dry run with the revert error.
+    pub async fn dry_run(self) -> Result<DryRun, DeployError> {
+        self.check_init_code_size()?;
+
+        let eth = self.web3.eth();
+        let from = match &self.tx.from {
+            Some(account) => account.address(),
+            None => *eth
+                .accounts()
+                .await?
+                .first()
+                .ok_or(ExecutionError::NoLocalAccounts)?,
+        };
+        let nonce = match self.tx.nonce {
+            Some(nonce) => nonce,
+            None => {
+                eth.transaction_count(from, Some(BlockNumber::Pending))
+                    .await?
+            }
+        };
+        let code = self.simulate(Some(from)).await?;
+        self.check_code_size(&code.0)?;
+        let gas = match self.tx.gas {
+            Some(gas) => gas,
+            None => {
+                self.tx
+                    .from(Account::Local(from, None))
+                    .estimate_gas()
+                    .await?
+            }
+        };
+
+        Ok(DryRun {
+            from,
+            nonce,
+            gas,
+            contract_address: address::create_address(&from, nonce.as_u64()),
+            code,
+        })
+    }
+
     /// Sign (if required) and execute the transaction. Returns the transaction
     /// hash that can be used to retrieve transaction information.
     pub async fn deploy(self) -> Result<I, DeployError> {
+        self.check_init_code_size()?;
+        if self.tx.preflight && self.code_size_limits.is_some() {
+            let from = self.tx.from.as_ref().map(Account::address);
+            let code = self.simulate(from).await?;
+            self.check_code_size(&code.0)?;
+        }
+
         let tx = match self.tx.send().await? {
             TransactionResult::Receipt(tx) => tx,
             TransactionResult::Hash(tx) => return Err(DeployError::Pending(tx)),
@@ -161,6 +373,47 @@ where
             self.context,
         ))
     }
+
+    /// Simulates the constructor with an `eth_call`, returning the runtime
+    /// code of the deployed contract.
+    async fn simulate(&self, from: Option<Address>) -> Result<Bytes, ExecutionError> {
+        let call = CallRequest {
+            from,
+            to: None,
+            gas: self.tx.gas,
+            gas_price: self.tx.gas_price.and_then(|gas_price| gas_price.value()),
+            value: self.tx.value,
+            data: self.tx.data.clone(),
+            transaction_type: None,
+            access_list: None,
+        };
+        Ok(self.web3.eth().call(call, None).await?)
+    }
+
+    fn check_init_code_size(&self) -> Result<(), DeployError> {
+        let size = self.tx.data.as_ref().map(|data| data.0.len()).unwrap_or(0);
+        match self.code_size_limits {
+            Some(limits) if size > limits.max_init_code_size => {
+                Err(DeployError::InitCodeSizeExceeded {
+                    size,
+                    limit: limits.max_init_code_size,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_code_size(&self, code: &[u8]) -> Result<(), DeployError> {
+        match self.code_size_limits {
+            Some(limits) if code.len() > limits.max_code_size => {
+                Err(DeployError::CodeSizeExceeded {
+                    size: code.len(),
+                    limit: limits.max_code_size,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -209,6 +462,104 @@ mod tests {
         //   on github
     }
 
+    #[test]
+    fn deploy_dry_run() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        let contract = Contract {
+            bytecode: Bytecode::from_hex_str("0x42").unwrap(),
+            ..Contract::empty()
+        };
+        let builder = InstanceDeployBuilder::new(web3, Linker::new(contract), ())
+            .expect("error creating deploy builder")
+            .from(Account::Local(from, None));
+
+        assert_eq!(
+            builder.create2_address(Address::zero(), H256::zero()),
+            ethcontract_common::address::create2_address(&Address::zero(), [0; 32], &[0x42]),
+        );
+
+        transport.add_response(json!("0x2")); // nonce
+        transport.add_response(json!("0x1337")); // runtime code
+        transport.add_response(json!("0x5208")); // gas estimate
+        let dry_run = builder.dry_run().immediate().expect("dry run failed");
+
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "from": from, "data": "0x42" }), json!("latest")],
+        );
+        transport.assert_request(
+            "eth_estimateGas",
+            &[json!({ "from": from, "data": "0x42" })],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(
+            dry_run,
+            DryRun {
+                from,
+                nonce: 2.into(),
+                gas: 0x5208.into(),
+                contract_address: addr!("0xf778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+                code: Bytes(vec![0x13, 0x37]),
+            }
+        );
+    }
+
+    #[test]
+    fn deploy_checks_code_size_limits() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        let contract = Contract {
+            bytecode: Bytecode::from_hex_str(&format!("0x{}", "00".repeat(MAX_INIT_CODE_SIZE + 1)))
+                .unwrap(),
+            ..Contract::empty()
+        };
+        let builder = InstanceDeployBuilder::new(web3.clone(), Linker::new(contract), ())
+            .expect("error creating deploy builder")
+            .from(Account::Local(from, None));
+        assert!(matches!(
+            builder.clone().deploy().immediate(),
+            Err(DeployError::InitCodeSizeExceeded {
+                size,
+                limit: MAX_INIT_CODE_SIZE,
+            }) if size == MAX_INIT_CODE_SIZE + 1
+        ));
+        assert!(matches!(
+            builder.dry_run().immediate(),
+            Err(DeployError::InitCodeSizeExceeded { .. })
+        ));
+        transport.assert_no_more_requests();
+
+        let contract = Contract {
+            bytecode: Bytecode::from_hex_str("0x42").unwrap(),
+            ..Contract::empty()
+        };
+        let builder = InstanceDeployBuilder::new(web3, Linker::new(contract), ())
+            .expect("error creating deploy builder")
+            .from(Account::Local(from, None))
+            .preflight(true)
+            .code_size_limits(Some(CodeSizeLimits {
+                max_code_size: 1,
+                ..Default::default()
+            }));
+        transport.add_response(json!("0x1337")); // runtime code
+        assert!(matches!(
+            builder.deploy().immediate(),
+            Err(DeployError::CodeSizeExceeded { size: 2, limit: 1 })
+        ));
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "from": from, "data": "0x42" }), json!("latest")],
+        );
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn deploy_fails_on_empty_bytecode() {
         let transport = TestTransport::new();
@@ -0,0 +1,258 @@
+//! Contract instances with an ABI that is only known at runtime.
+
+use crate::abi::{decoder, DecodeOptions};
+use crate::contract::{AnyContract, Instance, MethodBuilder, RawLog, ViewMethodBuilder};
+use crate::dyns::{DynInstance, DynTransport};
+use crate::errors::ExecutionError;
+use ethcontract_common::abi::{Error as AbiError, LogParam, Result as AbiResult, Token};
+use ethcontract_common::Abi;
+use jsonrpc_core::Value;
+use std::any::Any;
+use std::future::Future;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::Address;
+use web3::{BatchTransport, Transport};
+
+/// A contract instance created from an ABI that is only known at runtime, with
+/// dynamically typed method calls and event decoding.
+///
+/// This is useful for tools such as block explorers or generic transaction
+/// builders where type-safe bindings cannot be generated ahead of time.
+/// Parameters and return values are represented as `Token`s.
+#[derive(Clone, Debug)]
+pub struct DynContract {
+    name: String,
+    instance: DynInstance,
+}
+
+/// An event decoded by a `DynContract`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynEvent {
+    /// The name of the event.
+    pub name: String,
+    /// The decoded event parameters.
+    pub params: Vec<LogParam>,
+}
+
+impl DynContract {
+    /// Creates a new contract instance at the given `Address` from its JSON
+    /// ABI.
+    ///
+    /// Note that this does not verify that a contract with a matching ABI is
+    /// actually deployed at the given address.
+    pub fn from_abi<F, B, T>(
+        web3: &Web3<T>,
+        abi_json: &str,
+        address: Address,
+    ) -> Result<Self, AbiError>
+    where
+        F: Future<Output = Result<Value, Web3Error>> + Send + 'static,
+        B: Future<Output = Result<Vec<Result<Value, Web3Error>>, Web3Error>> + Send + 'static,
+        T: Transport<Out = F> + BatchTransport<Batch = B> + Send + Sync + 'static,
+    {
+        let abi = Abi::load(abi_json.as_bytes())?;
+        let web3 = Web3::new(DynTransport::new(web3.transport().clone()));
+        Ok(DynContract::from_instance(Instance::at(web3, abi, address)))
+    }
+
+    /// Creates a new contract from a raw instance.
+    pub fn from_instance(instance: DynInstance) -> Self {
+        DynContract {
+            name: String::new(),
+            instance,
+        }
+    }
+
+    /// Sets the name of the contract. Contracts created from an ABI are
+    /// unnamed by default.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Returns the contract address.
+    pub fn address(&self) -> Address {
+        self.instance.address()
+    }
+
+    /// Returns the contract ABI.
+    pub fn abi(&self) -> &Abi {
+        self.instance.abi()
+    }
+
+    /// Returns a method builder for a contract method identified by its name or
+    /// full ABI signature. See [`Instance::method_by_name`] for more details.
+    pub fn method(
+        &self,
+        name: &str,
+        params: Vec<Token>,
+    ) -> AbiResult<MethodBuilder<DynTransport, Token>> {
+        self.instance.method_by_name(name, params)
+    }
+
+    /// Returns a view method builder for a contract method identified by its
+    /// name or full ABI signature.
+    pub fn view_method(
+        &self,
+        name: &str,
+        params: Vec<Token>,
+    ) -> AbiResult<ViewMethodBuilder<DynTransport, Token>> {
+        self.instance.view_method_by_name(name, params)
+    }
+
+    /// Decodes a log emitted by this contract, finding the matching event by
+    /// its topic. Note that anonymous events cannot be decoded since they have
+    /// no topic identifying them.
+    pub fn decode_event(&self, log: RawLog) -> Result<DynEvent, ExecutionError> {
+        let topic = log
+            .topics
+            .first()
+            .copied()
+            .ok_or_else(|| AbiError::InvalidData)?;
+        let event = self
+            .abi()
+            .events()
+            .find(|event| !event.anonymous && event.signature() == topic)
+            .ok_or_else(|| AbiError::InvalidName(format!("{:?}", topic)))?;
+
+        let tokens = decoder::decode_log(event, &log.topics, &log.data, DecodeOptions::default())?;
+
+        Ok(DynEvent {
+            name: event.name.clone(),
+            params: event
+                .inputs
+                .iter()
+                .zip(tokens)
+                .map(|(input, token)| LogParam {
+                    name: input.name.clone(),
+                    value: token.into_token(),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl AnyContract for DynContract {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn raw_instance(&self) -> &DynInstance {
+        &self.instance
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use web3::types::H256;
+
+    const ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "balanceOf",
+            "inputs": [{ "name": "owner", "type": "address" }],
+            "outputs": [{ "name": "", "type": "uint256" }],
+            "stateMutability": "view"
+        },
+        {
+            "type": "event",
+            "name": "Transfer",
+            "inputs": [
+                { "name": "from", "type": "address", "indexed": true },
+                { "name": "to", "type": "address", "indexed": true },
+                { "name": "value", "type": "uint256", "indexed": false }
+            ],
+            "anonymous": false
+        }
+    ]"#;
+
+    fn contract(transport: &TestTransport) -> DynContract {
+        let web3 = Web3::new(transport.clone());
+        DynContract::from_abi(
+            &web3,
+            ABI,
+            addr!("0x0202020202020202020202020202020202020202"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn call_method() {
+        let mut transport = TestTransport::new();
+        let contract = contract(&transport);
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        let balance = contract
+            .view_method("balanceOf", vec![Token::Address(Address::repeat_byte(1))])
+            .unwrap()
+            .call()
+            .immediate()
+            .unwrap();
+
+        assert_eq!(balance, Token::Uint(42.into()));
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": contract.address(),
+                    "data": "0x70a082310000000000000000000000000101010101010101010101010101010101010101",
+                }),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn invalid_abi() {
+        let web3 = Web3::new(TestTransport::new());
+        assert!(DynContract::from_abi(&web3, "{}", Address::zero()).is_err());
+    }
+
+    #[test]
+    fn decode_event() {
+        let contract = contract(&TestTransport::new());
+        let topic = contract.abi().event("Transfer").unwrap().signature();
+
+        let event = contract
+            .decode_event(RawLog {
+                topics: vec![
+                    topic,
+                    H256::from(Address::repeat_byte(1)),
+                    H256::from(Address::repeat_byte(2)),
+                ],
+                data: H256::from_low_u64_be(42).as_bytes().to_vec(),
+            })
+            .unwrap();
+
+        assert_eq!(event.name, "Transfer");
+        assert_eq!(
+            event
+                .params
+                .into_iter()
+                .map(|param| (param.name, param.value))
+                .collect::<Vec<_>>(),
+            vec![
+                ("from".to_owned(), Token::Address(Address::repeat_byte(1))),
+                ("to".to_owned(), Token::Address(Address::repeat_byte(2))),
+                ("value".to_owned(), Token::Uint(42.into())),
+            ],
+        );
+
+        assert!(contract
+            .decode_event(RawLog {
+                topics: vec![H256::zero()],
+                data: vec![],
+            })
+            .is_err());
+    }
+}
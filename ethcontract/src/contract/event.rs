@@ -1,12 +1,17 @@
 //! Module implements type-safe event streams from an ABI event definition with
 //! detokenization of the data included in the log.
 
+mod borrowed;
 mod data;
+mod parallel;
 
-pub use self::data::{Event, EventMetadata, EventStatus, ParseLog, RawLog, StreamEvent};
+pub use self::borrowed::BorrowedToken;
+pub use self::data::{Event, EventId, EventMetadata, EventStatus, ParseLog, RawLog, StreamEvent};
+pub use self::parallel::{decode_logs_par, decode_logs_par_with, DEFAULT_DECODE_THREADS};
+use crate::block::{BlockId, BlockTag};
 use crate::errors::{EventError, ExecutionError};
-use crate::log::LogFilterBuilder;
-use crate::tokens::Tokenize;
+use crate::log::{AdaptiveBlockRange, LogFilterBuilder, LogMultiplexer, LogSource};
+use crate::tokens::{Lenient, Tokenize};
 pub use ethcontract_common::abi::Topic;
 use ethcontract_common::{
     abi::{Event as AbiEvent, RawTopicFilter, Token},
@@ -18,15 +23,13 @@ use std::cmp;
 use std::marker::PhantomData;
 use std::time::Duration;
 use web3::api::Web3;
-use web3::types::{Address, BlockNumber, H256};
-use web3::Transport;
+use web3::types::{Address, Log, H256};
+use web3::{DuplexTransport, Transport};
 
 /// A builder for creating a filtered stream of contract events that are
 #[derive(Debug)]
 #[must_use = "event builders do nothing unless you stream them"]
 pub struct EventBuilder<T: Transport, E: Tokenize> {
-    /// The underlying web3 instance.
-    web3: Web3<T>,
     /// The event ABI data for encoding topic filters and decoding logs.
     event: AbiEvent,
     /// The web3 filter builder used for creating a log filter.
@@ -41,7 +44,6 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
     /// and address.
     pub fn new(web3: Web3<T>, event: AbiEvent, address: Address) -> Self {
         EventBuilder {
-            web3: web3.clone(),
             event,
             filter: LogFilterBuilder::new(web3).address(vec![address]),
             topics: RawTopicFilter::default(),
@@ -53,7 +55,7 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
     ///
     /// If left unset defaults to the latest block.
     #[allow(clippy::wrong_self_convention)]
-    pub fn from_block(mut self, block: BlockNumber) -> Self {
+    pub fn from_block(mut self, block: impl Into<BlockId>) -> Self {
         self.filter = self.filter.from_block(block);
         self
     }
@@ -62,11 +64,18 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
     ///
     /// If left unset defaults to the streaming until the end of days.
     #[allow(clippy::wrong_self_convention)]
-    pub fn to_block(mut self, block: BlockNumber) -> Self {
+    pub fn to_block(mut self, block: impl Into<BlockId>) -> Self {
         self.filter = self.filter.to_block(block);
         self
     }
 
+    /// Restricts the events to the block with the specified hash. See
+    /// [`LogFilterBuilder::block_hash`] for more details.
+    pub fn block_hash(mut self, hash: H256) -> Self {
+        self.filter = self.filter.block_hash(hash);
+        self
+    }
+
     /// Adds a filter for the first indexed topic.
     ///
     /// This corresponds to the first indexed property, which for anonymous
@@ -106,6 +115,13 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
         self
     }
 
+    /// Uses an adaptive block page size when querying past events. See
+    /// [`AdaptiveBlockRange`] for more details.
+    pub fn adaptive_block_range(mut self, value: AdaptiveBlockRange) -> Self {
+        self.filter = self.filter.adaptive_block_range(value);
+        self
+    }
+
     /// The polling interval. This is used as the interval between consecutive
     /// `eth_getFilterChanges` calls to get filter updates.
     pub fn poll_interval(mut self, value: Duration) -> Self {
@@ -113,6 +129,22 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
         self
     }
 
+    /// The expected block time of the chain, used as the polling interval if
+    /// none is specified. See [`LogFilterBuilder::block_time`] for more
+    /// details.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.filter = self.filter.block_time(value);
+        self
+    }
+
+    /// Stream events by polling blocks and checking their logs bloom instead
+    /// of using a node side log filter. See
+    /// [`LogFilterBuilder::bloom_filter`] for more details.
+    pub fn bloom_filter(mut self, enabled: bool) -> Self {
+        self.filter = self.filter.bloom_filter(enabled);
+        self
+    }
+
     /// Returns a `LogFilterBuilder` instance for the current builder.
     pub fn into_inner(self) -> Result<(AbiEvent, LogFilterBuilder<T>), EventError> {
         let EventBuilder {
@@ -132,6 +164,57 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
     /// Returns a future that resolves with a collection of all existing logs
     /// matching the builder parameters.
     pub async fn query(self) -> Result<Vec<Event<E>>, EventError> {
+        self.query_with(RawLog::decode).await
+    }
+
+    /// Returns a future that resolves with a collection of all existing logs
+    /// matching the builder parameters, decoded in lenient mode. See
+    /// [`RawLog::decode_lenient`] for more details.
+    pub async fn query_lenient(self) -> Result<Vec<Event<Lenient<E>>>, EventError> {
+        self.query_with(RawLog::decode_lenient).await
+    }
+
+    /// Creates an event stream from the current event builder that emits new
+    /// events.
+    pub fn stream(self) -> impl Stream<Item = Result<StreamEvent<E>, EventError>> {
+        self.stream_with(RawLog::decode, LogFilterBuilder::stream)
+    }
+
+    /// Creates an event stream from the current event builder that shares an
+    /// `eth_subscribe` subscription with other streams of the specified
+    /// multiplexer. See [`LogMultiplexer`] for more details.
+    pub fn multiplexed_stream<M: DuplexTransport>(
+        self,
+        multiplexer: &LogMultiplexer<M>,
+    ) -> impl Stream<Item = Result<StreamEvent<E>, EventError>> {
+        let multiplexer = multiplexer.clone();
+        self.stream_with(RawLog::decode, move |filter| {
+            filter.multiplexed_stream(&multiplexer)
+        })
+    }
+
+    /// Creates an event stream from the current event builder that streams
+    /// new events from the specified log source, independently of whether it
+    /// uses subscriptions or polling. See [`LogSource`] for more details.
+    pub fn stream_from(
+        self,
+        source: &LogSource,
+    ) -> impl Stream<Item = Result<StreamEvent<E>, EventError>> {
+        let source = source.clone();
+        self.stream_with(RawLog::decode, move |filter| source.stream(filter))
+    }
+
+    /// Creates an event stream from the current event builder that emits new
+    /// events decoded in lenient mode. See [`RawLog::decode_lenient`] for more
+    /// details.
+    pub fn stream_lenient(self) -> impl Stream<Item = Result<StreamEvent<Lenient<E>>, EventError>> {
+        self.stream_with(RawLog::decode_lenient, LogFilterBuilder::stream)
+    }
+
+    async fn query_with<D, F>(self, decode: F) -> Result<Vec<Event<D>>, EventError>
+    where
+        F: Fn(RawLog, &AbiEvent) -> Result<D, ExecutionError>,
+    {
         let (event, filter) = self.into_inner()?;
         filter
             .past_logs()
@@ -139,18 +222,25 @@ impl<T: Transport, E: Tokenize> EventBuilder<T, E> {
             .map_err(|err| EventError::new(&event, err))?
             .into_iter()
             .map(|log| {
-                Event::from_past_log(log, |raw| raw.decode(&event))
+                Event::from_past_log(log, |raw| decode(raw, &event))
                     .map_err(|err| EventError::new(&event, err))
             })
             .collect()
     }
 
-    /// Creates an event stream from the current event builder that emits new
-    /// events.
-    pub fn stream(self) -> impl Stream<Item = Result<StreamEvent<E>, EventError>> {
+    fn stream_with<D, F, S, L>(
+        self,
+        decode: F,
+        stream: S,
+    ) -> impl Stream<Item = Result<StreamEvent<D>, EventError>>
+    where
+        F: Fn(RawLog, &AbiEvent) -> Result<D, ExecutionError>,
+        S: FnOnce(LogFilterBuilder<T>) -> L,
+        L: Stream<Item = Result<Log, ExecutionError>>,
+    {
         future::ready(self.into_inner().map(|(event, filter)| {
-            filter.stream().map(move |log| {
-                log.and_then(|log| Event::from_streamed_log(log, |raw| raw.decode(&event)))
+            stream(filter).map(move |log| {
+                log.and_then(|log| Event::from_streamed_log(log, |raw| decode(raw, &event)))
                     .map_err(|err| EventError::new(&event, err))
             })
         }))
@@ -202,7 +292,7 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
     ///
     /// If left unset defaults to the latest block.
     #[allow(clippy::wrong_self_convention)]
-    pub fn from_block(mut self, block: BlockNumber) -> Self {
+    pub fn from_block(mut self, block: impl Into<BlockId>) -> Self {
         self.filter = self.filter.from_block(block);
         self
     }
@@ -211,7 +301,7 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
     ///
     /// If left unset defaults to the streaming until the end of days.
     #[allow(clippy::wrong_self_convention)]
-    pub fn to_block(mut self, block: BlockNumber) -> Self {
+    pub fn to_block(mut self, block: impl Into<BlockId>) -> Self {
         self.filter = self.filter.to_block(block);
         self
     }
@@ -251,6 +341,13 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
         self
     }
 
+    /// Restricts the events to the block with the specified hash. See
+    /// [`LogFilterBuilder::block_hash`] for more details.
+    pub fn block_hash(mut self, hash: H256) -> Self {
+        self.filter = self.filter.block_hash(hash);
+        self
+    }
+
     /// The page size in blocks to use when doing a paginated query on past
     /// events.
     pub fn block_page_size(mut self, value: u64) -> Self {
@@ -258,6 +355,13 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
         self
     }
 
+    /// Uses an adaptive block page size when doing a paginated query on past
+    /// events. See [`AdaptiveBlockRange`] for more details.
+    pub fn adaptive_block_range(mut self, value: AdaptiveBlockRange) -> Self {
+        self.filter = self.filter.adaptive_block_range(value);
+        self
+    }
+
     /// The polling interval. This is used as the interval between consecutive
     /// `eth_getLogs` calls to get log updates.
     pub fn poll_interval(mut self, value: Duration) -> Self {
@@ -265,6 +369,22 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
         self
     }
 
+    /// The expected block time of the chain, used as the polling interval if
+    /// none is specified. See [`LogFilterBuilder::block_time`] for more
+    /// details.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.filter = self.filter.block_time(value);
+        self
+    }
+
+    /// Stream events by polling blocks and checking their logs bloom instead
+    /// of using a node side log filter. See
+    /// [`LogFilterBuilder::bloom_filter`] for more details.
+    pub fn bloom_filter(mut self, enabled: bool) -> Self {
+        self.filter = self.filter.bloom_filter(enabled);
+        self
+    }
+
     /// Returns a future that resolves into a collection of events matching the
     /// event builder's parameters.
     pub async fn query(self) -> Result<Vec<Event<E>>, ExecutionError> {
@@ -295,12 +415,12 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
             None => None,
         };
         let filter = match (self.filter.from_block, deployment_block) {
-            (Some(BlockNumber::Earliest), Some(deployment_block)) => {
-                self.filter.from_block(deployment_block.into())
+            (Some(BlockId::Tag(BlockTag::Earliest)), Some(deployment_block)) => {
+                self.filter.from_block(deployment_block)
             }
-            (Some(BlockNumber::Number(from_block)), Some(deployment_block)) => {
+            (Some(BlockId::Number(from_block)), Some(deployment_block)) => {
                 let from_block = cmp::max(from_block.as_u64(), deployment_block);
-                self.filter.from_block(from_block.into())
+                self.filter.from_block(from_block)
             }
             _ => self.filter,
         };
@@ -319,6 +439,30 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
             .stream()
             .and_then(|log| async { Event::from_streamed_log(log, E::parse_log) })
     }
+
+    /// Creates an event stream from the current event builder that shares an
+    /// `eth_subscribe` subscription with other streams of the specified
+    /// multiplexer. See [`LogMultiplexer`] for more details.
+    pub fn multiplexed_stream<M: DuplexTransport>(
+        self,
+        multiplexer: &LogMultiplexer<M>,
+    ) -> impl Stream<Item = Result<StreamEvent<E>, ExecutionError>> {
+        self.filter
+            .multiplexed_stream(multiplexer)
+            .and_then(|log| async { Event::from_streamed_log(log, E::parse_log) })
+    }
+
+    /// Creates an event stream from the current event builder that streams
+    /// new events from the specified log source, independently of whether it
+    /// uses subscriptions or polling. See [`LogSource`] for more details.
+    pub fn stream_from(
+        self,
+        source: &LogSource,
+    ) -> impl Stream<Item = Result<StreamEvent<E>, ExecutionError>> {
+        source
+            .stream(self.filter)
+            .and_then(|log| async { Event::from_streamed_log(log, E::parse_log) })
+    }
 }
 
 /// Retrieves a block number for the specified transaction hash.
@@ -344,7 +488,7 @@ mod tests {
     use ethcontract_common::abi::{EventParam, ParamType};
     use futures::stream::StreamExt;
     use serde_json::Value;
-    use web3::types::{Address, H2048, H256, U256, U64};
+    use web3::types::{Address, BlockNumber, H2048, H256, U256, U64};
 
     fn test_abi_event() -> (AbiEvent, Value) {
         let event = AbiEvent {
@@ -401,7 +545,7 @@ mod tests {
         let address = Address::repeat_byte(0x01);
         let signature = event.signature();
         let events = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, address)
-            .to_block(99.into())
+            .to_block(99)
             .topic1(Topic::OneOf(vec![
                 Address::repeat_byte(0x70),
                 Address::repeat_byte(0x80),
@@ -429,12 +573,40 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn event_query_lenient() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, mut log) = test_abi_event();
+
+        // the log of a newer contract version with an additional event field
+        log["data"] = json!(format!(
+            "0x{}{}",
+            hex::encode(H256::from_low_u64_be(42)),
+            hex::encode(H256::from_low_u64_be(1337)),
+        ));
+        transport.add_response(json!([log]));
+
+        let events = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, Address::zero())
+            .query_lenient()
+            .immediate()
+            .expect("failed to get logs");
+
+        assert_eq!(events[0].data.value.2, U256::from(42));
+        assert_eq!(
+            events[0].data.remainder,
+            H256::from_low_u64_be(1337).as_bytes(),
+        );
+    }
+
     #[test]
     fn event_stream_next_event() {
         let mut transport = TestTransport::new();
         let web3 = Web3::new(transport.clone());
         let (event, log) = test_abi_event();
 
+        // latest block
+        transport.add_response(json!("0x1"));
         // filter created
         transport.add_response(json!("0xf0"));
         // get logs filter
@@ -443,7 +615,7 @@ mod tests {
         let address = Address::repeat_byte(0x01);
         let signature = event.signature();
         let event = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, address)
-            .to_block(99.into())
+            .to_block(99)
             .topic1(Topic::OneOf(vec![
                 Address::repeat_byte(0x70),
                 Address::repeat_byte(0x80),
@@ -457,6 +629,7 @@ mod tests {
 
         assert!(event.is_added());
         assert_eq!(event.inner_data().2, U256::from(42));
+        transport.assert_request("eth_blockNumber", &[]);
         transport.assert_request(
             "eth_newFilter",
             &[json!({
@@ -488,7 +661,7 @@ mod tests {
         let address = Address::repeat_byte(0x01);
         let signature = event.signature();
         let raw_events = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
-            .to_block(99.into())
+            .to_block(99)
             .topic0(Topic::This(signature))
             .topic2(Topic::OneOf(vec![
                 Address::repeat_byte(0x70).into(),
@@ -560,7 +733,7 @@ mod tests {
         transport.add_response(json!([log, log]));
 
         let raw_events = AllEventsBuilder::<_, RawLog>::new(web3, address, Some(deployment.into()))
-            .from_block(5.into())
+            .from_block(5)
             .to_block(BlockNumber::Pending)
             .topic0(Topic::This(signature))
             .block_page_size(5)
@@ -610,6 +783,8 @@ mod tests {
         let web3 = Web3::new(transport.clone());
         let (event, log) = test_abi_event();
 
+        // latest block
+        transport.add_response(json!("0x1"));
         // filter created
         transport.add_response(json!("0xf0"));
         // get logs filter
@@ -618,7 +793,7 @@ mod tests {
         let address = Address::repeat_byte(0x01);
         let signature = event.signature();
         let raw_event = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
-            .to_block(99.into())
+            .to_block(99)
             .topic0(Topic::This(signature))
             .topic2(Topic::OneOf(vec![
                 Address::repeat_byte(0x70).into(),
@@ -647,6 +822,7 @@ mod tests {
                 },
             },
         );
+        transport.assert_request("eth_blockNumber", &[]);
         transport.assert_request(
             "eth_newFilter",
             &[json!({
@@ -0,0 +1,73 @@
+//! Tokens for event data that borrow dynamic byte arrays and strings from the
+//! raw log instead of copying them, for decoding large numbers of logs with
+//! few allocations.
+
+use ethcontract_common::abi::Token;
+use std::borrow::Cow;
+use web3::types::{Address, U256};
+
+/// An ABI value decoded from a raw log, borrowing byte arrays and strings from
+/// the log data and topics.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BorrowedToken<'a> {
+    /// An address.
+    Address(Address),
+    /// A fixed size byte array, or the hash of an indexed dynamic value.
+    FixedBytes(&'a [u8]),
+    /// A dynamic byte array.
+    Bytes(&'a [u8]),
+    /// A two's complement signed integer.
+    Int(U256),
+    /// An unsigned integer.
+    Uint(U256),
+    /// A boolean.
+    Bool(bool),
+    /// A string. Unless strict UTF-8 decoding is enabled, strings are decoded
+    /// with lossy UTF-8 decoding like `ethabi` does, so only strings
+    /// containing invalid UTF-8 are copied.
+    String(Cow<'a, str>),
+    /// A fixed size array.
+    FixedArray(Vec<BorrowedToken<'a>>),
+    /// A dynamic array.
+    Array(Vec<BorrowedToken<'a>>),
+    /// A tuple.
+    Tuple(Vec<BorrowedToken<'a>>),
+}
+
+impl<'a> BorrowedToken<'a> {
+    /// Returns the borrowed bytes of a fixed size or dynamic byte array.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            BorrowedToken::FixedBytes(bytes) | BorrowedToken::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the string value of a string token.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedToken::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Converts the token into an owned token, for example for detokenizing
+    /// the values of logs that are kept.
+    pub fn into_token(self) -> Token {
+        let into_tokens = |tokens: Vec<BorrowedToken>| {
+            tokens.into_iter().map(BorrowedToken::into_token).collect()
+        };
+        match self {
+            BorrowedToken::Address(address) => Token::Address(address),
+            BorrowedToken::FixedBytes(bytes) => Token::FixedBytes(bytes.to_vec()),
+            BorrowedToken::Bytes(bytes) => Token::Bytes(bytes.to_vec()),
+            BorrowedToken::Int(value) => Token::Int(value),
+            BorrowedToken::Uint(value) => Token::Uint(value),
+            BorrowedToken::Bool(value) => Token::Bool(value),
+            BorrowedToken::String(value) => Token::String(value.into_owned()),
+            BorrowedToken::FixedArray(tokens) => Token::FixedArray(into_tokens(tokens)),
+            BorrowedToken::Array(tokens) => Token::Array(into_tokens(tokens)),
+            BorrowedToken::Tuple(tokens) => Token::Tuple(into_tokens(tokens)),
+        }
+    }
+}
@@ -1,6 +1,12 @@
 //! Module contains code for parsing and manipulating event data.
-use crate::{errors::ExecutionError, tokens::Tokenize};
-use ethcontract_common::abi::{Event as AbiEvent, RawLog as AbiRawLog, Token};
+use super::borrowed::BorrowedToken;
+use crate::abi::{decoder, DecodeOptions};
+use crate::errors::ExecutionError;
+use crate::tokens::{Lenient, Tokenize};
+use ethcontract_common::abi::{Event as AbiEvent, Token};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
 use web3::types::{Log, H256};
 
 /// A contract event
@@ -32,6 +38,12 @@ pub enum EventStatus<T> {
 }
 
 impl<T> Event<T> {
+    /// Returns the identifier of the event, or `None` if the event is pending
+    /// and has no metadata.
+    pub fn id(&self) -> Option<EventId> {
+        self.meta.as_ref().map(EventMetadata::id)
+    }
+
     /// Creates an event from a log given a mapping function.
     pub(crate) fn from_past_log<E, F>(log: Log, f: F) -> Result<Self, ExecutionError>
     where
@@ -144,6 +156,16 @@ pub struct EventMetadata {
 }
 
 impl EventMetadata {
+    /// Returns the identifier of the event.
+    pub fn id(&self) -> EventId {
+        EventId {
+            block_number: self.block_number,
+            block_hash: self.block_hash,
+            transaction_index: self.transaction_index,
+            log_index: self.log_index,
+        }
+    }
+
     fn from_log(log: &Log) -> Option<Self> {
         Some(EventMetadata {
             block_hash: log.block_hash?,
@@ -157,6 +179,78 @@ impl EventMetadata {
     }
 }
 
+/// A stable identifier of a mined event, for recording which events were
+/// processed, for example by indexers that need to process every event
+/// exactly once and resume processing after a restart.
+///
+/// Identifiers are ordered by the position of the event in the chain, that is
+/// by block number, then transaction index and log index. Events of different
+/// blocks at the same height, which can happen during re-orgs, have the same
+/// position but different block hashes, and are ordered by block hash so that
+/// the ordering is consistent with equality.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventId {
+    /// The number of the block where the log was produced.
+    pub block_number: u64,
+    /// The hash of the block where the log was produced.
+    pub block_hash: H256,
+    /// The block index of the transaction this log belongs to.
+    pub transaction_index: usize,
+    /// The index of the log in the block.
+    pub log_index: usize,
+}
+
+impl EventId {
+    /// Returns the identifier of the event of a log, or `None` if the log is
+    /// pending.
+    pub fn from_log(log: &Log) -> Option<Self> {
+        Some(EventId {
+            block_number: log.block_number?.as_u64(),
+            block_hash: log.block_hash?,
+            transaction_index: log.transaction_index?.as_usize(),
+            log_index: log.log_index?.as_usize(),
+        })
+    }
+
+    /// Returns the position of the event in the chain, ignoring the block
+    /// hash.
+    pub fn position(&self) -> (u64, usize, usize) {
+        (self.block_number, self.transaction_index, self.log_index)
+    }
+
+    /// Returns `true` if the event follows the other event in the chain. This
+    /// is useful for skipping events that were already processed when
+    /// resuming from a recorded event, regardless of the block hashes.
+    pub fn is_after(&self, other: &EventId) -> bool {
+        self.position() > other.position()
+    }
+}
+
+impl Ord for EventId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.position()
+            .cmp(&other.position())
+            .then_with(|| self.block_hash.cmp(&other.block_hash))
+    }
+}
+
+impl PartialOrd for EventId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for EventId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{} ({:?})",
+            self.block_number, self.transaction_index, self.log_index, self.block_hash,
+        )
+    }
+}
+
 /// Raw log topics and data for a contract event.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct RawLog {
@@ -172,19 +266,77 @@ impl RawLog {
     where
         D: Tokenize,
     {
-        let event_log = event.parse_log(AbiRawLog {
-            topics: self.topics,
-            data: self.data,
-        })?;
+        Ok(self.decode_lenient(event)?.value)
+    }
+
+    /// Decode raw log data into a tokenizable for a matching event ABI entry,
+    /// tolerating additional non-indexed data following the event fields, for
+    /// example from a newer version of the contract that added fields to the
+    /// event. The additional data is returned as the remainder.
+    pub fn decode_lenient<D>(self, event: &AbiEvent) -> Result<Lenient<D>, ExecutionError>
+    where
+        D: Tokenize,
+    {
+        self.decode_lenient_with(event, DecodeOptions::default())
+    }
 
-        let tokens = event_log
-            .params
+    /// Decode raw log data into a tokenizable for a matching event ABI entry
+    /// with the specified decoding options, for example to reject strings that
+    /// are not valid UTF-8.
+    pub fn decode_with<D>(
+        self,
+        event: &AbiEvent,
+        options: DecodeOptions,
+    ) -> Result<D, ExecutionError>
+    where
+        D: Tokenize,
+    {
+        Ok(self.decode_lenient_with(event, options)?.value)
+    }
+
+    fn decode_lenient_with<D>(
+        self,
+        event: &AbiEvent,
+        options: DecodeOptions,
+    ) -> Result<Lenient<D>, ExecutionError>
+    where
+        D: Tokenize,
+    {
+        let tokens = decoder::decode_log(event, &self.topics, &self.data, options)?
             .into_iter()
-            .map(|param| param.value)
+            .map(BorrowedToken::into_token)
+            .collect::<Vec<_>>();
+        let data_tokens = event
+            .inputs
+            .iter()
+            .zip(&tokens)
+            .filter(|(input, _)| !input.indexed)
+            .map(|(_, token)| token.clone())
             .collect::<Vec<_>>();
         let data = D::from_token(Token::Tuple(tokens))?;
 
-        Ok(data)
+        Ok(Lenient::from_decoded(data, &data_tokens, &self.data))
+    }
+
+    /// Decode raw log data for a matching event ABI entry into tokens that
+    /// borrow byte arrays and strings from the log, in the order of the event
+    /// parameters.
+    ///
+    /// Unlike [`RawLog::decode`], this does not copy the log data or allocate
+    /// byte arrays and strings, which makes up a large part of the time spent
+    /// decoding when scanning many logs for a few fields, for example during
+    /// large backfills. Tokens can be converted into owned tokens for the logs
+    /// that are kept.
+    pub fn decode_borrowed(
+        &self,
+        event: &AbiEvent,
+    ) -> Result<Vec<BorrowedToken<'_>>, ExecutionError> {
+        Ok(decoder::decode_log(
+            event,
+            &self.topics,
+            &self.data,
+            DecodeOptions::default(),
+        )?)
     }
 }
 
@@ -209,3 +361,75 @@ impl ParseLog for RawLog {
         Ok(log)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn id(block_number: u64, transaction_index: usize, log_index: usize, hash: u8) -> EventId {
+        EventId {
+            block_number,
+            block_hash: H256::repeat_byte(hash),
+            transaction_index,
+            log_index,
+        }
+    }
+
+    #[test]
+    fn event_id_ordering() {
+        let mut ids = vec![
+            id(2, 0, 3, 1),
+            id(1, 1, 2, 1),
+            id(2, 0, 3, 0),
+            id(1, 0, 1, 1),
+        ];
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                id(1, 0, 1, 1),
+                id(1, 1, 2, 1),
+                id(2, 0, 3, 0),
+                id(2, 0, 3, 1),
+            ],
+        );
+
+        assert!(id(2, 0, 0, 0).is_after(&id(1, 5, 5, 0)));
+        assert!(!id(2, 0, 3, 1).is_after(&id(2, 0, 3, 0)));
+    }
+
+    #[test]
+    fn event_id_from_log() {
+        let log: Log = serde_json::from_value(json!({
+            "address": "0x0000000000000000000000000000000000000000",
+            "topics": [],
+            "data": "0x",
+            "blockHash": H256::repeat_byte(1),
+            "blockNumber": "0x10",
+            "transactionHash": H256::repeat_byte(2),
+            "transactionIndex": "0x2",
+            "logIndex": "0x5",
+        }))
+        .unwrap();
+
+        let event_id = EventId::from_log(&log).unwrap();
+        assert_eq!(event_id, id(16, 2, 5, 1));
+        assert_eq!(EventMetadata::from_log(&log).unwrap().id(), event_id);
+        assert_eq!(
+            serde_json::to_value(event_id).unwrap(),
+            json!({
+                "blockNumber": 16,
+                "blockHash": H256::repeat_byte(1),
+                "transactionIndex": 2,
+                "logIndex": 5,
+            }),
+        );
+
+        let pending = Log {
+            block_number: None,
+            ..log
+        };
+        assert_eq!(EventId::from_log(&pending), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Parallel decoding of large batches of logs, for example when backfilling
+//! historic events, where decoding is CPU bound.
+
+use super::data::{Event, ParseLog};
+use crate::errors::ExecutionError;
+use std::panic;
+use std::thread;
+use web3::types::Log;
+
+/// The default number of threads used for decoding logs.
+pub const DEFAULT_DECODE_THREADS: usize = 4;
+
+/// The minimum number of logs decoded by a single thread, so that small
+/// batches are not split over threads that take longer to spawn than to
+/// decode the logs.
+const MIN_CHUNK_SIZE: usize = 1024;
+
+/// Decodes logs into events in parallel using the default number of threads.
+/// See [`decode_logs_par_with`] for more details.
+pub fn decode_logs_par<E>(logs: Vec<Log>) -> Vec<Result<Event<E>, ExecutionError>>
+where
+    E: ParseLog + Send + 'static,
+{
+    decode_logs_par_with(logs, DEFAULT_DECODE_THREADS)
+}
+
+/// Decodes logs into events in parallel using up to the specified number of
+/// threads.
+///
+/// The decoded events are ordered by block number and log index, regardless
+/// of the order of the logs, with pending logs that are not part of a block
+/// yet ordered last. Each log is decoded independently, so a log that cannot
+/// be decoded results in an error at its position without affecting the
+/// other logs.
+pub fn decode_logs_par_with<E>(
+    mut logs: Vec<Log>,
+    threads: usize,
+) -> Vec<Result<Event<E>, ExecutionError>>
+where
+    E: ParseLog + Send + 'static,
+{
+    logs.sort_by_key(|log| match (log.block_number, log.log_index) {
+        (Some(block_number), Some(log_index)) => (false, block_number.as_u64(), log_index),
+        _ => (true, 0, Default::default()),
+    });
+
+    let chunk_size = MIN_CHUNK_SIZE.max((logs.len() + threads.max(1) - 1) / threads.max(1));
+    if logs.len() <= chunk_size {
+        return decode(logs);
+    }
+
+    // NOTE: Split the chunks off the end of the logs to avoid moving the
+    //   remaining logs for every chunk, and reverse them afterwards.
+    let mut handles = Vec::new();
+    while !logs.is_empty() {
+        let chunk = logs.split_off(logs.len().saturating_sub(chunk_size));
+        handles.push(thread::spawn(move || decode::<E>(chunk)));
+    }
+
+    handles
+        .into_iter()
+        .rev()
+        .flat_map(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err))
+        })
+        .collect()
+}
+
+fn decode<E: ParseLog>(logs: Vec<Log>) -> Vec<Result<Event<E>, ExecutionError>> {
+    logs.into_iter()
+        .map(|log| Event::from_past_log(log, E::parse_log))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::RawLog;
+    use web3::types::{Bytes, H256};
+
+    fn log(block_number: Option<u64>, log_index: u64) -> Log {
+        Log {
+            address: Default::default(),
+            topics: vec![H256::from_low_u64_be(log_index)],
+            data: Bytes(block_number.unwrap_or_default().to_be_bytes().to_vec()),
+            block_hash: block_number.map(|_| H256::zero()),
+            block_number: block_number.map(Into::into),
+            transaction_hash: block_number.map(|_| H256::zero()),
+            transaction_index: block_number.map(|_| 0.into()),
+            log_index: Some(log_index.into()),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    fn order(events: Vec<Result<Event<RawLog>, ExecutionError>>) -> Vec<(u64, u64)> {
+        events
+            .into_iter()
+            .map(|event| event.unwrap().meta.unwrap())
+            .map(|meta| (meta.block_number, meta.log_index as u64))
+            .collect()
+    }
+
+    #[test]
+    fn decode_logs_par_preserves_order() {
+        let mut logs = Vec::new();
+        for block in (0..10).rev() {
+            for index in 0..1000 {
+                logs.push(log(Some(block), index));
+            }
+        }
+
+        let mut expected = Vec::new();
+        for block in 0..10 {
+            for index in 0..1000 {
+                expected.push((block, index));
+            }
+        }
+
+        assert_eq!(order(decode_logs_par_with(logs.clone(), 3)), expected);
+        assert_eq!(order(decode_logs_par_with(logs, 1)), expected);
+    }
+
+    #[test]
+    fn decode_logs_par_orders_pending_logs_last() {
+        let events =
+            decode_logs_par::<RawLog>(vec![log(None, 0), log(Some(1), 2), log(Some(1), 1)]);
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0].as_ref().unwrap().meta.as_ref().unwrap().log_index,
+            1
+        );
+        assert_eq!(
+            events[1].as_ref().unwrap().meta.as_ref().unwrap().log_index,
+            2
+        );
+        assert!(events[2].as_ref().unwrap().meta.is_none());
+    }
+
+    #[test]
+    fn decode_logs_par_reports_removed_logs() {
+        let removed = Log {
+            removed: Some(true),
+            ..log(Some(1), 0)
+        };
+        let events = decode_logs_par::<RawLog>(vec![removed, log(Some(1), 1)]);
+
+        assert!(matches!(events[0], Err(ExecutionError::RemovedLog(_))));
+        assert!(events[1].is_ok());
+    }
+}
@@ -2,13 +2,30 @@
 //! intended to be used directly but to be used by a contract `Instance` with
 //! [Instance::method](ethcontract::contract::Instance::method).
 
-use crate::transaction::{Account, GasPrice, TransactionBuilder, TransactionResult};
-use crate::{batch::CallBatch, errors::MethodError, tokens::Tokenize};
-use ethcontract_common::abi::{Function, Token};
+use crate::abi;
+use crate::block::{BlockId, BlockTag};
+use crate::ccip::{CcipRead, OffchainLookup};
+use crate::contract::ParseLog;
+use crate::errors::{CcipError, ExecutionError};
+use crate::rpc::TraceBackend;
+use crate::tokens::{Lenient, Tokenize};
+use crate::transaction::{
+    self, Account, AccountOverride, GasPrice, PendingTransaction, ResolveCondition,
+    SignedAuthorization, StateChanges, StateOverrides, TransactionBuilder, TransactionExtension,
+    TransactionGuard, TransactionHook, TransactionPreview, TransactionResult, TxJournal,
+};
+use crate::{batch::CallBatch, errors::MethodError};
+use ethcontract_common::abi::{Error as AbiError, Function, Token};
+use futures::stream::{self, Stream};
+use futures_timer::Delay;
+use serde_json::Value;
+use std::fmt::{self, Debug, Formatter};
 use std::marker::PhantomData;
-use web3::types::{Address, BlockId, Bytes, CallRequest, U256};
-use web3::Transport;
+use std::sync::Arc;
+use std::time::Duration;
+use web3::types::{Address, BlockNumber, Bytes, CallRequest, TransactionReceipt, H256, U256};
 use web3::{api::Web3, BatchTransport};
+use web3::{helpers, Transport};
 
 /// Default options to be applied to `MethodBuilder` or `ViewMethodBuilder`.
 #[derive(Clone, Debug, Default)]
@@ -17,21 +34,133 @@ pub struct MethodDefaults {
     pub from: Option<Account>,
     /// Default gas amount to use for transaction.
     pub gas: Option<U256>,
-    /// Default gas price to use for transaction.
+    /// Default gas price strategy to use for transaction.
     pub gas_price: Option<GasPrice>,
+    /// Default condition on which sent transactions are resolved, for example
+    /// the number of confirmations to wait for.
+    pub resolve: Option<ResolveCondition>,
+    /// Default timeout for sending transactions and for calls.
+    pub timeout: Option<Duration>,
+    /// Expected block time of the chain, from which the default poll
+    /// intervals for confirming transactions and streaming events are derived.
+    pub block_time: Option<Duration>,
+    /// Default hook to notify of outgoing transactions.
+    pub hook: Option<Arc<dyn TransactionHook>>,
+    /// Default guard to consult before signing transactions.
+    pub guard: Option<Arc<dyn TransactionGuard>>,
+    /// Default journal for recording sent transactions.
+    pub journal: Option<TxJournal>,
+    /// Default extensions with additional transaction fields, applied before
+    /// the extensions of the method builder.
+    pub extensions: Vec<Arc<dyn TransactionExtension>>,
+    /// Default limits for contract calls.
+    pub call_limits: CallLimits,
+    /// Default block tag for contract calls and for looking up the nonce of
+    /// transaction senders, used when no block or nonce is specified. Note
+    /// that looking up nonces in the `safe` or `finalized` state can result in
+    /// reusing the nonces of recent transactions.
+    pub block_tag: Option<BlockTag>,
+    /// Default options for EIP-3668 offchain lookups requested by contract
+    /// calls. If not specified, the default options of [`CcipRead`] are used.
+    pub ccip_read: Option<CcipRead>,
+}
+
+/// Limits for contract calls that protect against contracts running unbounded
+/// loops or returning excessive amounts of data on calls that cost nothing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CallLimits {
+    /// The maximum amount of gas to provide for the call. Calls with a larger
+    /// gas amount are capped to this value.
+    pub gas: Option<U256>,
+    /// The maximum size in bytes of the data returned by the call. Calls that
+    /// return more data fail with `ExecutionError::ReturnDataTooLarge`.
+    pub max_return_data: Option<usize>,
+}
+
+impl CallLimits {
+    /// Merges these limits with default limits, used for the limits that are
+    /// not already set.
+    fn or(self, defaults: CallLimits) -> Self {
+        CallLimits {
+            gas: self.gas.or(defaults.gas),
+            max_return_data: self.max_return_data.or(defaults.max_return_data),
+        }
+    }
+
+    /// Caps the specified gas amount.
+    fn cap_gas(&self, gas: Option<U256>) -> Option<U256> {
+        match (gas, self.gas) {
+            (Some(gas), Some(cap)) => Some(gas.min(cap)),
+            (gas, cap) => gas.or(cap),
+        }
+    }
+
+    /// Checks the size of the data returned by a call.
+    fn check_return_data(&self, data: &[u8]) -> Result<(), ExecutionError> {
+        match self.max_return_data {
+            Some(limit) if data.len() > limit => {
+                Err(ExecutionError::ReturnDataTooLarge(data.len(), limit))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Marker type for methods that accept ETH, allowing an amount of ETH to be
+/// sent with the method transaction.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Payable;
+
+/// Marker type for methods that do not accept ETH. Method builders for these
+/// methods do not allow specifying an amount of ETH to send, as the
+/// transaction would always revert.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NonPayable;
+
+/// A custom decoder for the return data of a contract method, for contracts
+/// that do not ABI encode their return values, such as gas-optimized contracts
+/// returning packed data. See [`MethodBuilder::output_decoder`].
+pub struct OutputDecoder<R>(pub fn(&[u8]) -> Result<R, AbiError>);
+
+impl<R> OutputDecoder<R> {
+    fn decode(self, function: &Function, data: &[u8]) -> Result<R, MethodError> {
+        (self.0)(data).map_err(|err| MethodError::new(function, err))
+    }
+}
+
+impl<R> Clone for OutputDecoder<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for OutputDecoder<R> {}
+
+impl<R> Debug for OutputDecoder<R> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("OutputDecoder").finish()
+    }
 }
 
 /// Data used for building a contract method call or transaction. The method
 /// builder can be demoted into a `CallBuilder` to not allow sending of
 /// transactions. This is useful when dealing with view functions.
+///
+/// The `P` type parameter is either [`Payable`] or [`NonPayable`], and only
+/// builders for payable methods allow specifying an amount of ETH to send.
 #[derive(Debug, Clone)]
 #[must_use = "methods do nothing unless you `.call()` or `.send()` them"]
-pub struct MethodBuilder<T: Transport, R: Tokenize> {
+pub struct MethodBuilder<T: Transport, R: Tokenize, P = Payable> {
     web3: Web3<T>,
     function: Function,
     /// transaction parameters
     pub tx: TransactionBuilder<T>,
+    call_limits: CallLimits,
+    block_tag: Option<BlockTag>,
+    ccip_read: Option<CcipRead>,
+    decoder: Option<OutputDecoder<R>>,
     _result: PhantomData<R>,
+    _payability: PhantomData<P>,
 }
 
 impl<T: Transport> MethodBuilder<T, ()> {
@@ -48,26 +177,75 @@ impl<T: Transport> MethodBuilder<T, ()> {
             constant: false,
             state_mutability: Default::default(),
         };
-        MethodBuilder::new(web3, function, address, data)
+        let mut builder = MethodBuilder::new(web3, function, address, data);
+        builder.tx.function = None;
+        builder
     }
 }
 
 impl<T: Transport, R: Tokenize> MethodBuilder<T, R> {
     /// Creates a new builder for a transaction.
     pub fn new(web3: Web3<T>, function: Function, address: Address, data: Bytes) -> Self {
+        let mut tx = TransactionBuilder::new(web3.clone()).to(address).data(data);
+        tx.function = Some(function.clone());
         MethodBuilder {
-            web3: web3.clone(),
+            web3,
             function,
-            tx: TransactionBuilder::new(web3).to(address).data(data),
+            tx,
+            call_limits: CallLimits::default(),
+            block_tag: None,
+            ccip_read: None,
+            decoder: None,
             _result: PhantomData,
+            _payability: PhantomData,
         }
     }
 
+    /// Specify what how much ETH to transfer with the transaction, if not
+    /// specified then no ETH will be sent.
+    pub fn value(mut self, value: U256) -> Self {
+        self.tx = self.tx.value(value);
+        self
+    }
+
+    /// Marks the method as non-payable, so that no ETH can be sent with the
+    /// method transaction. Any previously specified amount of ETH is cleared.
+    pub fn non_payable(mut self) -> MethodBuilder<T, R, NonPayable> {
+        self.tx.value = None;
+        self.with_payability()
+    }
+}
+
+impl<T: Transport, R: Tokenize, P> MethodBuilder<T, R, P> {
     /// Apply method defaults to this builder.
     pub fn with_defaults(mut self, defaults: &MethodDefaults) -> Self {
         self.tx.from = self.tx.from.or_else(|| defaults.from.clone());
         self.tx.gas = self.tx.gas.or(defaults.gas);
         self.tx.gas_price = self.tx.gas_price.or(defaults.gas_price);
+        self.tx.resolve = self.tx.resolve.or_else(|| defaults.resolve.clone());
+        self.tx.timeout = self.tx.timeout.or(defaults.timeout);
+        self.tx.block_time = self.tx.block_time.or(defaults.block_time);
+        self.tx.hook = self.tx.hook.or_else(|| defaults.hook.clone());
+        self.tx.guard = self.tx.guard.or_else(|| defaults.guard.clone());
+        self.tx.journal = self.tx.journal.or_else(|| defaults.journal.clone());
+        if !defaults.extensions.is_empty() {
+            let extensions = std::mem::take(&mut self.tx.extensions);
+            self.tx.extensions = defaults.extensions.clone();
+            self.tx.extensions.extend(extensions);
+        }
+        self.call_limits = self.call_limits.or(defaults.call_limits);
+        self.block_tag = self.block_tag.or(defaults.block_tag);
+        self.tx.nonce_tag = self.tx.nonce_tag.or(defaults.block_tag);
+        self.ccip_read = self.ccip_read.or_else(|| defaults.ccip_read.clone());
+        self
+    }
+
+    /// Specify a custom decoder for the data returned by calls to the method,
+    /// which is used instead of decoding the ABI outputs of the method. This
+    /// allows typed bindings for contracts that return non-standard encoded
+    /// data, and is set by bindings generated with custom method codecs.
+    pub fn output_decoder(mut self, decoder: fn(&[u8]) -> Result<R, AbiError>) -> Self {
+        self.decoder = Some(OutputDecoder(decoder));
         self
     }
 
@@ -92,13 +270,6 @@ impl<T: Transport, R: Tokenize> MethodBuilder<T, R> {
         self
     }
 
-    /// Specify what how much ETH to transfer with the transaction, if not
-    /// specified then no ETH will be sent.
-    pub fn value(mut self, value: U256) -> Self {
-        self.tx = self.tx.value(value);
-        self
-    }
-
     /// Specify the nonce for the transation, if not specified will use the
     /// current transaction count for the signing account.
     pub fn nonce(mut self, value: U256) -> Self {
@@ -114,6 +285,82 @@ impl<T: Transport, R: Tokenize> MethodBuilder<T, R> {
         self
     }
 
+    /// Specify a timeout for calling the method or sending the method
+    /// transaction, if not specified there is no timeout. See
+    /// [`TransactionBuilder::timeout`] for more details.
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.tx = self.tx.timeout(value);
+        self
+    }
+
+    /// Specify the expected block time of the chain for deriving the poll
+    /// intervals used for confirming the method transaction. See
+    /// [`TransactionBuilder::block_time`] for more details.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.tx = self.tx.block_time(value);
+        self
+    }
+
+    /// Specify a hook that is notified of the method transaction. See
+    /// [`TransactionBuilder::hook`] for more details.
+    pub fn hook(mut self, value: Arc<dyn TransactionHook>) -> Self {
+        self.tx = self.tx.hook(value);
+        self
+    }
+
+    /// Specify a guard that is consulted before signing the method
+    /// transaction. See [`TransactionBuilder::guard`] for more details.
+    pub fn guard(mut self, value: Arc<dyn TransactionGuard>) -> Self {
+        self.tx = self.tx.guard(value);
+        self
+    }
+
+    /// Specify a journal that records the method transaction. See
+    /// [`TransactionBuilder::journal`] for more details.
+    pub fn journal(mut self, value: TxJournal) -> Self {
+        self.tx = self.tx.journal(value);
+        self
+    }
+
+    /// Specify whether to check the method transaction against the latest
+    /// block before it is signed. See [`TransactionBuilder::preflight`] for
+    /// more details.
+    pub fn preflight(mut self, value: bool) -> Self {
+        self.tx = self.tx.preflight(value);
+        self
+    }
+
+    /// Specify whether to check that the sender can pay for the method
+    /// transaction before it is signed. See
+    /// [`TransactionBuilder::check_balance`] for more details.
+    pub fn check_balance(mut self, value: bool) -> Self {
+        self.tx = self.tx.check_balance(value);
+        self
+    }
+
+    /// Specify an extension that adds nonstandard fields to the method
+    /// transaction. See [`TransactionBuilder::extension`] for more details.
+    pub fn extension(mut self, value: impl TransactionExtension + 'static) -> Self {
+        self.tx = self.tx.extension(value);
+        self
+    }
+
+    /// Specify a signed EIP-7702 authorization to include in the method
+    /// transaction. See [`TransactionBuilder::authorization`] for more
+    /// details.
+    pub fn authorization(mut self, value: SignedAuthorization) -> Self {
+        self.tx = self.tx.authorization(value);
+        self
+    }
+
+    /// Specify the ERC20 token to pay transaction fees with on chains that
+    /// support the Celo `feeCurrency` transaction field. See
+    /// [`TransactionBuilder::fee_currency`] for more details.
+    pub fn fee_currency(mut self, token: Address) -> Self {
+        self.tx = self.tx.fee_currency(token);
+        self
+    }
+
     /// Extract inner `TransactionBuilder` from this `SendBuilder`. This exposes
     /// `TransactionBuilder` only APIs.
     pub fn into_inner(self) -> TransactionBuilder<T> {
@@ -128,19 +375,122 @@ impl<T: Transport, R: Tokenize> MethodBuilder<T, R> {
             .map_err(|err| MethodError::new(&function, err))
     }
 
+    /// Sign (if required) and send the method call transaction, wait for its
+    /// confirmation and parse the logs emitted by the contract into events,
+    /// typically the `Event` enum generated for the contract.
+    ///
+    /// The transaction is confirmed with the configured confirmation options,
+    /// or with the default ones if it was configured to resolve while pending.
+    /// Logs emitted by other contracts during the transaction are ignored.
+    pub async fn send_and_confirm<E: ParseLog>(
+        mut self,
+    ) -> Result<(TransactionReceipt, Vec<E>), MethodError> {
+        if let Some(ResolveCondition::Pending) = self.tx.resolve {
+            self.tx.resolve = None;
+        }
+
+        let address = self.tx.to;
+        let Self { function, tx, .. } = self;
+        let receipt = match tx.send().await {
+            Ok(TransactionResult::Receipt(receipt)) => receipt,
+            Ok(TransactionResult::Hash(hash)) => {
+                return Err(MethodError::new(
+                    &function,
+                    ExecutionError::PendingTransaction(hash),
+                ))
+            }
+            Err(err) => return Err(MethodError::new(&function, err)),
+        };
+        let events = receipt
+            .logs
+            .iter()
+            .filter(|log| Some(log.address) == address)
+            .map(|log| E::parse_log(log.clone().into()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| MethodError::new(&function, err))?;
+
+        Ok((receipt, events))
+    }
+
+    /// Sign (if required) and broadcast the method call transaction, returning
+    /// its hash without waiting for confirmation. See
+    /// [`TransactionBuilder::send_nowait`] for more details.
+    pub async fn send_nowait(self) -> Result<H256, MethodError> {
+        let Self { function, tx, .. } = self;
+        tx.send_nowait()
+            .await
+            .map_err(|err| MethodError::new(&function, err))
+    }
+
+    /// Sign (if required) and broadcast the method call transaction without
+    /// waiting for confirmation, returning a resumable handle to the pending
+    /// transaction.
+    pub async fn broadcast(self) -> Result<PendingTransaction, MethodError> {
+        let Self { function, tx, .. } = self;
+        tx.broadcast()
+            .await
+            .map_err(|err| MethodError::new(&function, err))
+    }
+
+    /// Simulate the method transaction and return the state changes it would
+    /// make without sending it. See
+    /// [`TransactionBuilder::simulate_state_changes`] for more details.
+    pub async fn simulate_state_changes(
+        self,
+        backend: TraceBackend,
+        block: Option<BlockNumber>,
+    ) -> Result<StateChanges, MethodError> {
+        let Self { function, tx, .. } = self;
+        tx.simulate_state_changes(backend, block)
+            .await
+            .map_err(|err| MethodError::new(&function, err))
+    }
+
+    /// Resolve the parameters of the method transaction without signing or
+    /// sending it. See [`TransactionBuilder::preview`] for more details.
+    pub async fn preview(self) -> Result<TransactionPreview, MethodError> {
+        let Self { function, tx, .. } = self;
+        tx.preview()
+            .await
+            .map_err(|err| MethodError::new(&function, err))
+    }
+
     /// Demotes a `MethodBuilder` into a `ViewMethodBuilder` which has a more
     /// restricted API and cannot actually send transactions.
     pub fn view(self) -> ViewMethodBuilder<T, R> {
-        ViewMethodBuilder::from_method(self)
+        ViewMethodBuilder::from_method(self.with_payability())
     }
 
-    /// Call a contract method. Contract calls do not modify the blockchain and
-    /// as such do not require gas or signing. Note that doing a call with a
-    /// block number requires first demoting the `MethodBuilder` into a
-    /// `ViewMethodBuilder` and setting the block number for the call.
+    /// Demotes a `MethodBuilder` into a `ViewMethodBuilder` for simulating the
+    /// method as if it was called by the specified address, which can be any
+    /// account including a contract. See [`ViewMethodBuilder::simulate_as`].
+    pub fn simulate_as(self, address: Address) -> ViewMethodBuilder<T, R> {
+        self.view().simulate_as(address)
+    }
+
+    /// Simulate the method transaction with a contract call and return its
+    /// result. Contract calls do not modify the blockchain and as such do not
+    /// require gas or signing, so any state changes are discarded; use
+    /// [`MethodBuilder::send`] to execute the method on chain. Note that doing
+    /// a call with a block number requires first demoting the `MethodBuilder`
+    /// into a `ViewMethodBuilder` and setting the block number for the call.
     pub async fn call(self) -> Result<R, MethodError> {
         self.view().call().await
     }
+
+    fn with_payability<Q>(self) -> MethodBuilder<T, R, Q> {
+        MethodBuilder {
+            web3: self.web3,
+            function: self.function,
+            tx: self.tx,
+            call_limits: self.call_limits,
+            block_tag: self.block_tag,
+            ccip_read: self.ccip_read,
+            decoder: self.decoder,
+            _result: PhantomData,
+            _payability: PhantomData,
+        }
+    }
 }
 
 /// Data used for building a contract method call. The view method builder can't
@@ -150,22 +500,41 @@ impl<T: Transport, R: Tokenize> MethodBuilder<T, R> {
 pub struct ViewMethodBuilder<T: Transport, R: Tokenize> {
     /// method parameters
     pub m: MethodBuilder<T, R>,
-    /// optional block number
+    /// optional block number, hash or tag
     pub block: Option<BlockId>,
+    /// call gas and return data limits
+    pub limits: CallLimits,
+    /// optional default block tag, used if no block is specified
+    pub block_tag: Option<BlockTag>,
+    /// optional state overrides for the call
+    pub overrides: Option<StateOverrides>,
+    /// optional options for offchain lookups, the defaults are used if not
+    /// specified
+    pub ccip_read: Option<CcipRead>,
 }
 
 impl<T: Transport, R: Tokenize> ViewMethodBuilder<T, R> {
     /// Create a new `ViewMethodBuilder` by demoting a `MethodBuilder`.
     pub fn from_method(method: MethodBuilder<T, R>) -> Self {
+        let limits = method.call_limits;
+        let block_tag = method.block_tag;
+        let ccip_read = method.ccip_read.clone();
         ViewMethodBuilder {
             m: method,
             block: None,
+            limits,
+            block_tag,
+            overrides: None,
+            ccip_read,
         }
     }
 
     /// Apply method defaults to this builder.
     pub fn with_defaults(mut self, defaults: &MethodDefaults) -> Self {
         self.m = self.m.with_defaults(defaults);
+        self.limits = self.limits.or(defaults.call_limits);
+        self.block_tag = self.block_tag.or(defaults.block_tag);
+        self.ccip_read = self.ccip_read.or_else(|| defaults.ccip_read.clone());
         self
     }
 
@@ -182,24 +551,81 @@ impl<T: Transport, R: Tokenize> ViewMethodBuilder<T, R> {
         self
     }
 
-    /// Specify the gas price to use, if not specified then the estimated gas
-    /// price will be used.
-    pub fn gas_price(mut self, value: GasPrice) -> Self {
-        self.m = self.m.gas_price(value);
+    /// Specify the block for the call by number, hash or tag, for example to
+    /// only read finalized state. If not specified, the default block tag or
+    /// the latest block is used.
+    pub fn block(mut self, value: impl Into<BlockId>) -> Self {
+        self.block = Some(value.into());
         self
     }
 
-    /// Specify what how much ETH to transfer with the transaction, if not
-    /// specified then no ETH will be sent.
-    pub fn value(mut self, value: U256) -> Self {
-        self.m = self.m.value(value);
+    /// Specify state overrides for the call, for example to simulate the call
+    /// against a pending token approval. Note that state overrides for calls
+    /// are not supported by all nodes.
+    pub fn state_overrides(mut self, value: StateOverrides) -> Self {
+        self.overrides = Some(value);
         self
     }
 
-    /// Specify the nonce for the transation, if not specified will use the
-    /// current transaction count for the signing account.
-    pub fn block(mut self, value: BlockId) -> Self {
-        self.block = Some(value);
+    /// Simulate the call as if it was made by the specified address, which can
+    /// be any account including a contract, for example to check whether a
+    /// token holder could execute a method.
+    pub fn simulate_as(self, address: Address) -> Self {
+        self.from(address)
+    }
+
+    /// Simulate the call as if it was made by the specified address with the
+    /// specified balance, which is set with a state override. This allows
+    /// simulating payable calls from accounts that do not own enough ether.
+    pub fn simulate_as_with_balance(mut self, address: Address, balance: U256) -> Self {
+        let overrides = self.overrides.get_or_insert_with(Default::default);
+        let account = overrides.remove(&address).unwrap_or_default();
+        overrides.insert(
+            address,
+            AccountOverride {
+                balance: Some(balance),
+                ..account
+            },
+        );
+        self.from(address)
+    }
+
+    /// Specify a timeout for the call, if not specified there is no timeout.
+    /// Note that the timeout does not apply to batched calls.
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.m = self.m.timeout(value);
+        self
+    }
+
+    /// Specify a custom decoder for the data returned by the call, which is
+    /// used instead of decoding the ABI outputs of the method.
+    pub fn output_decoder(mut self, decoder: fn(&[u8]) -> Result<R, AbiError>) -> Self {
+        self.m = self.m.output_decoder(decoder);
+        self
+    }
+
+    /// Specify the maximum amount of gas to provide for the call. The gas
+    /// amount of the call is capped to this value, and it is used as the gas
+    /// amount if none is specified.
+    pub fn gas_cap(mut self, value: U256) -> Self {
+        self.limits.gas = Some(value);
+        self
+    }
+
+    /// Specify the maximum size in bytes of the data returned by the call.
+    /// Calls returning more data fail without decoding it. Note that the limit
+    /// is checked once the node response is received, so transports should
+    /// also limit response sizes to bound memory usage.
+    pub fn max_return_data(mut self, value: usize) -> Self {
+        self.limits.max_return_data = Some(value);
+        self
+    }
+
+    /// Specify how EIP-3668 offchain lookups requested by the contract are
+    /// handled, for example to use a custom gateway client or to disable
+    /// them with [`CcipRead::disabled`].
+    pub fn ccip_read(mut self, value: CcipRead) -> Self {
+        self.ccip_read = Some(value);
         self
     }
 }
@@ -208,10 +634,44 @@ impl<T: Transport, R: Tokenize> ViewMethodBuilder<T, R> {
     /// Call a contract method. Contract calls do not modify the blockchain and
     /// as such do not require gas or signing.
     pub async fn call(self) -> Result<R, MethodError> {
-        let eth = &self.m.web3.eth();
-        let (function, call, block) = self.decompose();
-        let future = eth.call(call, block);
-        convert_response::<_, R>(future, function).await
+        let web3 = self.m.web3.clone();
+        let timeout = self.m.tx.timeout;
+        let limits = self.limits;
+        let ccip = self.ccip_read.clone().unwrap_or_default();
+        let decoder = self.m.decoder;
+        let (function, call, params) = self.decompose();
+        let future = transaction::with_timeout(timeout, eth_call(&web3, call, params, &ccip));
+        convert_response::<_, _, R>(future, function, limits, decoder).await
+    }
+
+    /// Call a contract method and decode its result in lenient mode,
+    /// tolerating additional trailing outputs, for example from a newer
+    /// version of the contract. The data following the outputs is returned as
+    /// the remainder instead of being silently ignored.
+    ///
+    /// Methods with a custom output decoder decode all of the returned data
+    /// with the decoder, so their remainder is always empty.
+    pub async fn call_lenient(self) -> Result<Lenient<R>, MethodError> {
+        let web3 = self.m.web3.clone();
+        let timeout = self.m.tx.timeout;
+        let limits = self.limits;
+        let ccip = self.ccip_read.clone().unwrap_or_default();
+        let decoder = self.m.decoder;
+        let (function, call, params) = self.decompose();
+        let future = transaction::with_timeout(timeout, eth_call(&web3, call, params, &ccip));
+        let bytes = receive_response(future, &function, limits).await?;
+        if let Some(decoder) = decoder {
+            let value = decoder.decode(&function, &bytes.0)?;
+            return Ok(Lenient {
+                value,
+                remainder: Vec::new(),
+            });
+        }
+        let tokens = abi::decode_outputs(&function, &bytes.0)
+            .map_err(|err| MethodError::new(&function, err))?;
+        let remainder = Lenient::from_decoded((), &tokens, &bytes.0).remainder;
+        let value = detokenize_output(&function, tokens)?;
+        Ok(Lenient { value, remainder })
     }
 
     /// Adds this view method to a batch. Allows execution with other contract calls in one roundtrip
@@ -221,42 +681,197 @@ impl<T: Transport, R: Tokenize> ViewMethodBuilder<T, R> {
         self,
         batch: &mut CallBatch<B>,
     ) -> impl std::future::Future<Output = Result<R, MethodError>> {
-        let (function, call, block) = self.decompose();
-        let future = batch.push(call, block);
-        async move { convert_response::<_, R>(future, function).await }
+        let limits = self.limits;
+        let decoder = self.m.decoder;
+        let (function, call, params) = self.decompose();
+        let future = batch.push_with_params(call, params);
+        async move { convert_response::<_, _, R>(future, function, limits, decoder).await }
     }
 
-    fn decompose(self) -> (Function, CallRequest, Option<BlockId>) {
+    /// Calls the method at each of the specified blocks, for example for
+    /// reading the history of an on-chain value for charting. The calls are
+    /// sent in batches of `batch_size` requests, each in a single roundtrip,
+    /// and the results are returned in the order of the blocks.
+    ///
+    /// Note that calls at historic blocks require an archive node, and that
+    /// the timeout does not apply to batched calls.
+    pub async fn call_at_blocks(
+        self,
+        blocks: impl IntoIterator<Item = BlockNumber>,
+        batch_size: usize,
+    ) -> Result<Vec<(BlockNumber, R)>, MethodError>
+    where
+        T: BatchTransport,
+    {
+        let mut batch = CallBatch::new(self.m.web3.transport().clone());
+        let limits = self.limits;
+        let decoder = self.m.decoder;
+        let (function, call, params) = self.decompose();
+        let calls = blocks
+            .into_iter()
+            .map(|block| {
+                let mut params = params.clone();
+                params[0] = helpers::serialize(&block);
+                (block, batch.push_with_params(call.clone(), params))
+            })
+            .collect::<Vec<_>>();
+        batch.execute_all(batch_size.max(1)).await;
+
+        let mut results = Vec::with_capacity(calls.len());
+        for (block, call) in calls {
+            let result =
+                convert_response::<_, _, R>(call, function.clone(), limits, decoder).await?;
+            results.push((block, result));
+        }
+        Ok(results)
+    }
+
+    /// Watches the result of the call by calling it at each new block, polling
+    /// for new blocks every `interval`. Returns a stream of the results with
+    /// the numbers of the blocks they were read at, which only yields results
+    /// that differ from the previous result, starting with the result at the
+    /// latest block. This is useful for monitoring prices, oracle rounds or
+    /// configuration flags.
+    ///
+    /// The block specified for the call is ignored, and the stream ends at the
+    /// first error.
+    pub fn watch_call(self, interval: Duration) -> impl Stream<Item = Result<(u64, R), MethodError>>
+    where
+        R: Clone + PartialEq,
+    {
+        stream::try_unfold((self, None), move |(call, mut last)| async move {
+            loop {
+                let block = call
+                    .m
+                    .web3
+                    .eth()
+                    .block_number()
+                    .await
+                    .map_err(|err| MethodError::new(&call.m.function, err))?
+                    .as_u64();
+                if last.as_ref().map_or(false, |(number, _)| block <= *number) {
+                    Delay::new(interval).await;
+                    continue;
+                }
+
+                let value = call.clone().block(block).call().await?;
+                let changed = last
+                    .as_ref()
+                    .map_or(true, |(_, previous)| *previous != value);
+                last = Some((block, value.clone()));
+                if changed {
+                    return Ok(Some(((block, value), (call, last))));
+                }
+                Delay::new(interval).await;
+            }
+        })
+    }
+
+    fn decompose(self) -> (Function, CallRequest, Vec<Value>) {
+        let mut params = vec![match (self.block, self.block_tag) {
+            (Some(block), _) => helpers::serialize(&block),
+            (None, Some(tag)) => helpers::serialize(&tag),
+            (None, None) => helpers::serialize(&BlockNumber::Latest),
+        }];
+        if let Some(overrides) = &self.overrides {
+            params.push(helpers::serialize(overrides));
+        }
         (
             self.m.function,
             CallRequest {
                 from: self.m.tx.from.map(|account| account.address()),
                 to: Some(self.m.tx.to.unwrap_or_default()),
-                gas: self.m.tx.gas,
+                gas: self.limits.cap_gas(self.m.tx.gas),
                 gas_price: self.m.tx.gas_price.and_then(|gas_price| gas_price.value()),
                 value: self.m.tx.value,
                 data: self.m.tx.data,
                 transaction_type: None,
                 access_list: None,
             },
-            self.block,
+            params,
         )
     }
 }
 
+/// Executes an `eth_call` request with the serialized parameters following the
+/// call request, that is the block parameter, which unlike `BlockId` can also
+/// be a `safe` or `finalized` block tag, and optional state overrides.
+///
+/// Offchain lookups requested by the called contract are performed and the
+/// call is repeated with the callback, up to the lookup limit.
+async fn eth_call<T: Transport>(
+    web3: &Web3<T>,
+    mut call: CallRequest,
+    params: Vec<Value>,
+    ccip: &CcipRead,
+) -> Result<Bytes, ExecutionError> {
+    let mut lookups = 0;
+    loop {
+        let mut request = vec![helpers::serialize(&call)];
+        request.extend(params.iter().cloned());
+        let err = match web3.transport().execute("eth_call", request).await {
+            Ok(result) => return Ok(helpers::decode(result)?),
+            Err(err) => err,
+        };
+
+        let lookup = match OffchainLookup::from_error(&err) {
+            Some(lookup) if ccip.lookup_limit() > 0 => lookup,
+            _ => return Err(err.into()),
+        };
+        if Some(lookup.sender) != call.to {
+            return Err(CcipError::SenderMismatch(lookup.sender).into());
+        }
+        if lookups == ccip.lookup_limit() {
+            return Err(CcipError::TooManyLookups(lookups).into());
+        }
+        lookups += 1;
+
+        let response = ccip.fetch(&lookup).await?;
+        call.data = Some(Bytes(lookup.callback_data(response)));
+    }
+}
+
 async fn convert_response<
-    F: std::future::Future<Output = Result<Bytes, web3::Error>>,
+    F: std::future::Future<Output = Result<Bytes, E>>,
+    E: Into<ExecutionError>,
     R: Tokenize,
 >(
     future: F,
     function: Function,
+    limits: CallLimits,
+    decoder: Option<OutputDecoder<R>>,
 ) -> Result<R, MethodError> {
+    let bytes = receive_response(future, &function, limits).await?;
+    if let Some(decoder) = decoder {
+        return decoder.decode(&function, &bytes.0);
+    }
+    let tokens =
+        abi::decode_outputs(&function, &bytes.0).map_err(|err| MethodError::new(&function, err))?;
+    detokenize_output(&function, tokens)
+}
+
+async fn receive_response<F, E>(
+    future: F,
+    function: &Function,
+    limits: CallLimits,
+) -> Result<Bytes, MethodError>
+where
+    F: std::future::Future<Output = Result<Bytes, E>>,
+    E: Into<ExecutionError>,
+{
     let bytes = future
         .await
-        .map_err(|err| MethodError::new(&function, err))?;
-    let tokens = function
-        .decode_output(&bytes.0)
-        .map_err(|err| MethodError::new(&function, err))?;
+        .map_err(|err| MethodError::new(function, err))?;
+    limits
+        .check_return_data(&bytes.0)
+        .map_err(|err| MethodError::new(function, err))?;
+    Ok(bytes)
+}
+
+fn detokenize_output<R: Tokenize>(
+    function: &Function,
+    tokens: Vec<Token>,
+) -> Result<R, MethodError> {
     let token = match tokens.len() {
         0 => Token::Tuple(Vec::new()),
         1 => tokens.into_iter().next().unwrap(),
@@ -265,15 +880,18 @@ async fn convert_response<
         // accept this too.
         _ => Token::Tuple(tokens),
     };
-    let result = R::from_token(token).map_err(|err| MethodError::new(&function, err))?;
+    let result = R::from_token(token).map_err(|err| MethodError::new(function, err))?;
     Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::contract::RawLog;
     use crate::test::prelude::*;
     use ethcontract_common::abi::{Param, ParamType};
+    use futures::stream::{StreamExt as _, TryStreamExt as _};
+    use web3::types::{H2048, H256};
 
     fn test_abi_function() -> (Function, Bytes) {
         #[allow(deprecated)]
@@ -320,6 +938,94 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn method_send_and_confirm() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let hash = H256::repeat_byte(0xff);
+        let (function, data) = test_abi_function();
+        let log = |address: Address, topic: u8| {
+            json!({
+                "address": address,
+                "topics": [H256::repeat_byte(topic)],
+                "data": "0x",
+            })
+        };
+
+        transport.add_response(json!(hash)); // transaction hash
+        transport.add_response(json!("0x1")); // block number
+        transport.add_response(json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": "0x1",
+            "blockHash": H256::zero(),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [log(address, 1), log(from, 2), log(address, 3)],
+            "status": "0x1",
+        }));
+        let mut builder = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .from(Account::Local(from, None))
+            .gas(1.into())
+            .gas_price(2.into());
+        builder.tx.resolve = Some(ResolveCondition::Pending);
+        let (receipt, events) = builder
+            .send_and_confirm::<RawLog>()
+            .immediate()
+            .expect("send and confirm error");
+
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": address,
+                "gas": "0x1",
+                "gasPrice": "0x2",
+                "data": data,
+            })],
+        );
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        transport.assert_no_more_requests();
+
+        assert_eq!(receipt.transaction_hash, hash);
+        assert_eq!(
+            events,
+            vec![
+                RawLog {
+                    topics: vec![H256::repeat_byte(1)],
+                    data: Vec::new(),
+                },
+                RawLog {
+                    topics: vec![H256::repeat_byte(3)],
+                    data: Vec::new(),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn non_payable_method_clears_value() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data)
+            .value(28.into())
+            .non_payable()
+            .gas(1.into())
+            .into_inner();
+
+        assert_eq!(tx.value, None);
+        assert_eq!(tx.gas, Some(1.into()));
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn view_method_call() {
         let mut transport = TestTransport::new();
@@ -328,17 +1034,14 @@ mod tests {
         let address = addr!("0x0123456789012345678901234567890123456789");
         let from = addr!("0x9876543210987654321098765432109876543210");
         let (function, data) = test_abi_function();
-        let tx = ViewMethodBuilder::<_, U256>::from_method(MethodBuilder::new(
-            web3,
-            function,
-            address,
-            data.clone(),
-        ))
+        let tx = ViewMethodBuilder::<_, U256>::from_method(
+            MethodBuilder::new(web3, function, address, data.clone())
+                .gas_price(2.into())
+                .value(28.into()),
+        )
         .from(from)
         .gas(1.into())
-        .gas_price(2.into())
-        .value(28.into())
-        .block(BlockId::Number(100.into()));
+        .block(100);
 
         transport.add_response(json!(
             "0x000000000000000000000000000000000000000000000000000000000000002a"
@@ -363,6 +1066,125 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn view_method_call_at_blocks() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = ViewMethodBuilder::<_, U256>::from_method(MethodBuilder::new(
+            web3,
+            function,
+            address,
+            data.clone(),
+        ))
+        .block(100);
+
+        transport.add_response(json!([
+            "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "0x0000000000000000000000000000000000000000000000000000000000000002",
+        ]));
+        transport.add_response(json!([
+            "0x0000000000000000000000000000000000000000000000000000000000000003",
+        ]));
+        let blocks = vec![
+            BlockNumber::Number(1.into()),
+            BlockNumber::Number(2.into()),
+            BlockNumber::Latest,
+        ];
+        let result = tx
+            .call_at_blocks(blocks.clone(), 2)
+            .immediate()
+            .expect("call error");
+
+        assert_eq!(
+            result,
+            blocks
+                .into_iter()
+                .zip(vec![1.into(), 2.into(), 3.into()])
+                .collect::<Vec<(_, U256)>>(),
+        );
+        for block in &["0x1", "0x2", "latest"] {
+            transport.assert_request(
+                "eth_call",
+                &[json!({ "to": address, "data": data }), json!(block)],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn view_method_watch_call() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = ViewMethodBuilder::<_, U256>::from_method(MethodBuilder::new(
+            web3,
+            function,
+            address,
+            data.clone(),
+        ));
+
+        let value = |value: u64| json!(format!("0x{:064x}", value));
+        transport.add_response(json!("0x1"));
+        transport.add_response(value(5));
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!("0x2"));
+        transport.add_response(value(5));
+        transport.add_response(json!("0x3"));
+        transport.add_response(value(7));
+        let results = tx
+            .watch_call(Duration::from_millis(1))
+            .take(2)
+            .try_collect::<Vec<_>>()
+            .wait()
+            .expect("call error");
+
+        assert_eq!(results, vec![(1, 5.into()), (3, 7.into())]);
+        let call = |block: &str| {
+            let params = [json!({ "to": address, "data": data }), json!(block)];
+            ("eth_call", params.to_vec())
+        };
+        for (method, params) in vec![
+            ("eth_blockNumber", vec![]),
+            call("0x1"),
+            ("eth_blockNumber", vec![]),
+            ("eth_blockNumber", vec![]),
+            call("0x2"),
+            ("eth_blockNumber", vec![]),
+            call("0x3"),
+        ] {
+            transport.assert_request(method, &params);
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn view_method_call_lenient() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = ViewMethodBuilder::<_, U256>::from_method(MethodBuilder::new(
+            web3, function, address, data,
+        ));
+
+        // the return data of a newer contract version with an additional output
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a\
+               0000000000000000000000000000000000000000000000000000000000000539"
+        ));
+        let result = tx.call_lenient().immediate().expect("call error");
+
+        assert_eq!(result.value, 42.into());
+        assert_eq!(result.remainder, H256::from_low_u64_be(1337).as_bytes());
+        assert!(!result.is_exact());
+    }
+
     #[test]
     fn method_to_view_method_preserves_options() {
         let mut transport = TestTransport::new();
@@ -393,6 +1215,271 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn view_method_call_limits() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .gas(0x1000.into())
+            .with_defaults(&MethodDefaults {
+                call_limits: CallLimits {
+                    gas: Some(0x100.into()),
+                    max_return_data: None,
+                },
+                ..Default::default()
+            })
+            .view()
+            .max_return_data(32);
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        let result = tx.clone().call().immediate().expect("call error");
+        assert_eq!(result, 42.into());
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": address,
+                    "gas": "0x100",
+                    "data": data,
+                }),
+                json!("latest"),
+            ],
+        );
+
+        transport.add_response(json!(format!("0x{}", "00".repeat(64))));
+        let err = tx.call().immediate().expect_err("unexpected success");
+        assert!(matches!(
+            err.inner,
+            ExecutionError::ReturnDataTooLarge(64, 32)
+        ));
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": address,
+                    "gas": "0x100",
+                    "data": data,
+                }),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn simulate_calls_as_other_accounts() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let whale = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let method =
+            MethodBuilder::<_, U256>::new(web3, function, address, data.clone()).value(1.into());
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        method
+            .clone()
+            .simulate_as(whale)
+            .call()
+            .immediate()
+            .expect("call error");
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({ "from": whale, "to": address, "value": "0x1", "data": data }),
+                json!("latest"),
+            ],
+        );
+
+        let mut batch = CallBatch::new(transport.clone());
+        let call = method
+            .view()
+            .state_overrides(
+                vec![(address, AccountOverride::default().nonce(1.into()))]
+                    .into_iter()
+                    .collect(),
+            )
+            .simulate_as_with_balance(whale, 2.into())
+            .simulate_as_with_balance(address, 3.into())
+            .simulate_as(whale)
+            .batch_call(&mut batch);
+        transport.add_response(json!([
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ]));
+        batch.execute_all(1).immediate();
+        assert_eq!(call.immediate().expect("call error"), 42.into());
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({ "from": whale, "to": address, "value": "0x1", "data": data }),
+                json!("latest"),
+                json!({
+                    "0x0123456789012345678901234567890123456789": {
+                        "balance": "0x3",
+                        "nonce": "0x1",
+                    },
+                    "0x9876543210987654321098765432109876543210": { "balance": "0x2" },
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn default_block_tag_is_used_for_calls_and_nonces() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let defaults = MethodDefaults {
+            block_tag: Some(BlockTag::Finalized),
+            ..Default::default()
+        };
+        let method = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .with_defaults(&defaults);
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        method.clone().call().immediate().expect("call error");
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "to": address, "data": data }), json!("finalized")],
+        );
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        method
+            .clone()
+            .view()
+            .block(7)
+            .call()
+            .immediate()
+            .expect("call error");
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "to": address, "data": data }), json!("0x7")],
+        );
+
+        transport.add_response(json!("0x5"));
+        transport.add_response(json!(H256::repeat_byte(1)));
+        let mut method = method
+            .from(Account::Local(from, None))
+            .gas(1.into())
+            .gas_price(1.into());
+        method.tx.resolve = Some(ResolveCondition::Pending);
+        method.send().immediate().expect("send error");
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(from), json!("finalized")],
+        );
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": address,
+                "gas": "0x1",
+                "gasPrice": "0x1",
+                "data": data,
+                "nonce": "0x5",
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn view_method_offchain_lookups() {
+        use crate::ccip::tests::{encode_lookup, lookup as offchain_lookup, StaticGateway};
+        use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
+        use web3::error::Error as Web3Error;
+
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let lookup = offchain_lookup(address, &["https://gateway/{data}"]);
+        let revert = |lookup: &OffchainLookup| {
+            Web3Error::Rpc(JsonrpcError {
+                code: ErrorCode::ServerError(3),
+                message: "execution reverted".to_owned(),
+                data: Some(json!(Bytes(encode_lookup(lookup)))),
+            })
+        };
+        let gateway = Arc::new(StaticGateway {
+            responses: vec![("https://gateway/0x010203".to_owned(), Ok(vec![42]))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        });
+        let method = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .view()
+            .ccip_read(CcipRead::new(gateway.clone()));
+
+        transport.add_error(revert(&lookup));
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        let result = method.clone().call().immediate().expect("call error");
+        assert_eq!(result, 42.into());
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "to": address, "data": data }), json!("latest")],
+        );
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({ "to": address, "data": Bytes(lookup.callback_data(vec![42])) }),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(gateway.requests.lock().unwrap().len(), 1);
+
+        // Lookups that are requested by other contracts are rejected.
+        let other = offchain_lookup(Address::repeat_byte(1), &["https://gateway/{data}"]);
+        transport.add_error(revert(&other));
+        let err = method.clone().call().immediate().unwrap_err();
+        assert!(matches!(
+            err.inner,
+            ExecutionError::Ccip(CcipError::SenderMismatch(sender)) if sender == other.sender
+        ));
+
+        // Callbacks requesting further lookups are limited.
+        transport.add_error(revert(&lookup));
+        transport.add_error(revert(&lookup));
+        let err = method
+            .clone()
+            .ccip_read(CcipRead::new(gateway).max_lookups(1))
+            .call()
+            .immediate()
+            .unwrap_err();
+        assert!(matches!(
+            err.inner,
+            ExecutionError::Ccip(CcipError::TooManyLookups(1))
+        ));
+
+        // Reverts are returned as is when lookups are disabled.
+        transport.add_error(revert(&lookup));
+        let err = method
+            .ccip_read(CcipRead::disabled())
+            .call()
+            .immediate()
+            .unwrap_err();
+        assert!(matches!(err.inner, ExecutionError::Revert(None)));
+    }
+
     #[test]
     fn method_defaults_are_applied() {
         let transport = TestTransport::new();
@@ -406,12 +1493,25 @@ mod tests {
                 from: Some(Account::Local(from, None)),
                 gas: Some(1.into()),
                 gas_price: Some(2.into()),
+                resolve: Some(ResolveCondition::Pending),
+                timeout: Some(Duration::from_secs(3)),
+                block_time: Some(Duration::from_secs(2)),
+                hook: None,
+                guard: None,
+                journal: None,
+                extensions: Vec::new(),
+                call_limits: CallLimits::default(),
+                block_tag: None,
+                ccip_read: None,
             })
             .into_inner();
 
         assert_eq!(tx.from.map(|a| a.address()), Some(from));
         assert_eq!(tx.gas, Some(1.into()));
         assert_eq!(tx.gas_price, Some(2.into()));
+        assert!(matches!(tx.resolve, Some(ResolveCondition::Pending)));
+        assert_eq!(tx.timeout, Some(Duration::from_secs(3)));
+        assert_eq!(tx.block_time, Some(Duration::from_secs(2)));
         transport.assert_no_more_requests();
     }
 }
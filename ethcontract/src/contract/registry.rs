@@ -0,0 +1,237 @@
+//! Implementation of a registry of contract deployments across multiple
+//! chains.
+
+use crate::contract::{Instance, TypedContract};
+use crate::dyns::DynWeb3;
+use crate::errors::{DeployError, RegistryError};
+use crate::transport::DynTransport;
+use futures::Future;
+use jsonrpc_core::Value;
+use std::collections::HashMap;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::Address;
+use web3::{BatchTransport, Transport};
+
+/// A registry of providers and named contract addresses keyed by chain ID.
+///
+/// This allows applications that interact with the same contract types on
+/// many chains to retrieve ready-to-use typed contract instances:
+///
+/// ```no_run
+/// # use ethcontract::contract::{ChainRegistry, TypedContract};
+/// # use ethcontract::{Address, Http, Web3};
+/// # fn usdc<Erc20: TypedContract>(mainnet: Web3<Http>, polygon: Web3<Http>) {
+/// let registry = ChainRegistry::new()
+///     .chain(1, &mainnet)
+///     .chain(137, &polygon)
+///     .contract(1, "USDC", Address::repeat_byte(1))
+///     .contract(137, "USDC", Address::repeat_byte(2));
+///
+/// let usdc = registry.get::<Erc20>(137, "USDC").unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ChainRegistry {
+    chains: HashMap<u64, DynWeb3>,
+    contracts: HashMap<u64, HashMap<String, Address>>,
+}
+
+impl ChainRegistry {
+    /// Creates a new empty registry.
+    pub fn new() -> Self {
+        ChainRegistry::default()
+    }
+
+    /// Registers the provider for a chain, replacing any previously registered
+    /// provider for the chain.
+    pub fn chain<F, B, T>(mut self, chain_id: u64, web3: &Web3<T>) -> Self
+    where
+        F: Future<Output = Result<Value, Web3Error>> + Send + 'static,
+        B: Future<Output = Result<Vec<Result<Value, Web3Error>>, Web3Error>> + Send + 'static,
+        T: Transport<Out = F> + BatchTransport<Batch = B> + Send + Sync + 'static,
+    {
+        let transport = DynTransport::new(web3.transport().clone());
+        self.chains.insert(chain_id, Web3::new(transport));
+        self
+    }
+
+    /// Registers the address of a named contract on a chain.
+    pub fn contract(mut self, chain_id: u64, name: impl Into<String>, address: Address) -> Self {
+        self.contracts
+            .entry(chain_id)
+            .or_default()
+            .insert(name.into(), address);
+        self
+    }
+
+    /// Returns the IDs of the chains with registered providers in ascending
+    /// order.
+    pub fn chain_ids(&self) -> Vec<u64> {
+        let mut chain_ids = self.chains.keys().copied().collect::<Vec<_>>();
+        chain_ids.sort_unstable();
+        chain_ids
+    }
+
+    /// Returns the provider registered for a chain.
+    pub fn web3(&self, chain_id: u64) -> Option<&DynWeb3> {
+        self.chains.get(&chain_id)
+    }
+
+    /// Returns the address of a named contract on a chain.
+    pub fn address(&self, chain_id: u64, name: &str) -> Option<Address> {
+        self.contracts.get(&chain_id)?.get(name).copied()
+    }
+
+    /// Returns a typed instance of a named contract on a chain.
+    ///
+    /// Note that this does not verify that a contract of the requested type
+    /// is actually deployed at the registered address.
+    pub fn get<C: TypedContract>(&self, chain_id: u64, name: &str) -> Result<C, RegistryError> {
+        let web3 = self.provider(chain_id)?;
+        let address = self
+            .address(chain_id, name)
+            .ok_or_else(|| RegistryError::UnknownContract(chain_id, name.to_owned()))?;
+        let instance = Instance::at(web3, C::contract().abi.clone(), address);
+
+        Ok(C::from_instance(instance))
+    }
+
+    /// Returns a typed instance of a contract on a chain using the deployment
+    /// from the networks of its artifact.
+    ///
+    /// Note that artifact networks are keyed by network ID, which matches the
+    /// chain ID for most but not all networks.
+    pub fn deployed<C: TypedContract>(&self, chain_id: u64) -> Result<C, DeployError> {
+        let web3 = self
+            .provider(chain_id)
+            .map_err(|_| DeployError::NotFound(chain_id.to_string()))?;
+        let contract = C::contract();
+        let network = contract
+            .networks
+            .get(&chain_id.to_string())
+            .ok_or_else(|| DeployError::NotFound(chain_id.to_string()))?;
+        let instance = Instance::with_deployment_info(
+            web3,
+            contract.abi.clone(),
+            network.address,
+            network.deployment_information,
+        );
+
+        Ok(C::from_instance(instance))
+    }
+
+    /// Returns typed instances of a named contract on all chains where both
+    /// a provider and an address are registered, ordered by chain ID.
+    pub fn all<C: TypedContract>(&self, name: &str) -> Vec<(u64, C)> {
+        self.chain_ids()
+            .into_iter()
+            .filter_map(|chain_id| Some((chain_id, self.get(chain_id, name).ok()?)))
+            .collect()
+    }
+
+    fn provider(&self, chain_id: u64) -> Result<DynWeb3, RegistryError> {
+        self.chains
+            .get(&chain_id)
+            .cloned()
+            .ok_or(RegistryError::UnknownChain(chain_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::AnyContract;
+    use crate::dyns::DynInstance;
+    use crate::test::prelude::*;
+    use ethcontract_common::contract::Network;
+    use ethcontract_common::Contract;
+    use lazy_static::lazy_static;
+    use std::any::Any;
+
+    struct Token(DynInstance);
+
+    impl AnyContract for Token {
+        fn name(&self) -> &str {
+            "Token"
+        }
+        fn raw_instance(&self) -> &DynInstance {
+            &self.0
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl TypedContract for Token {
+        fn contract() -> &'static Contract {
+            lazy_static! {
+                static ref CONTRACT: Contract = {
+                    let mut contract = Contract::empty();
+                    contract.networks.insert(
+                        "1".to_owned(),
+                        Network {
+                            address: Address::repeat_byte(0x11),
+                            deployment_information: None,
+                        },
+                    );
+                    contract
+                };
+            }
+            &CONTRACT
+        }
+
+        fn from_instance(instance: DynInstance) -> Self {
+            Token(instance)
+        }
+    }
+
+    #[test]
+    fn registry_contracts() {
+        let mainnet = Web3::new(TestTransport::new());
+        let mut polygon_transport = TestTransport::new();
+        let polygon = Web3::new(polygon_transport.clone());
+        let registry = ChainRegistry::new()
+            .chain(137, &polygon)
+            .chain(1, &mainnet)
+            .contract(1, "USDC", Address::repeat_byte(1))
+            .contract(137, "USDC", Address::repeat_byte(2))
+            .contract(10, "USDC", Address::repeat_byte(3));
+
+        assert_eq!(registry.chain_ids(), vec![1, 137]);
+        assert_eq!(registry.address(10, "USDC"), Some(Address::repeat_byte(3)));
+
+        let usdc = registry.get::<Token>(137, "USDC").unwrap();
+        assert_eq!(usdc.address(), Address::repeat_byte(2));
+        polygon_transport.add_response(json!("0x89"));
+        usdc.raw_instance()
+            .web3()
+            .eth()
+            .chain_id()
+            .immediate()
+            .unwrap();
+        polygon_transport.assert_request("eth_chainId", &[]);
+        polygon_transport.assert_no_more_requests();
+
+        let all = registry.all::<Token>("USDC");
+        assert_eq!(
+            all.iter()
+                .map(|(chain_id, token)| (*chain_id, token.address()))
+                .collect::<Vec<_>>(),
+            vec![(1, Address::repeat_byte(1)), (137, Address::repeat_byte(2))],
+        );
+
+        assert!(matches!(
+            registry.get::<Token>(10, "USDC"),
+            Err(RegistryError::UnknownChain(10))
+        ));
+        assert!(matches!(
+            registry.get::<Token>(1, "DAI"),
+            Err(RegistryError::UnknownContract(1, name)) if name == "DAI"
+        ));
+
+        let deployed = registry.deployed::<Token>(1).unwrap();
+        assert_eq!(deployed.address(), Address::repeat_byte(0x11));
+        assert!(registry.deployed::<Token>(137).is_err());
+    }
+}
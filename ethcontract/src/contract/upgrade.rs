@@ -0,0 +1,433 @@
+//! Helpers for upgrading EIP-1967 UUPS proxies and upgradeable beacons.
+
+use crate::abi;
+use crate::bytecode::{self, BEACON_SLOT, IMPLEMENTATION_SLOT};
+use crate::contract::Instance;
+use crate::errors::{ExecutionError, MethodError, UpgradeError};
+use crate::tokens::Bytes as TokenBytes;
+use crate::tokens::Tokenize;
+use crate::transaction::TransactionResult;
+use ethcontract_common::abi::ParamType;
+use ethcontract_common::hash::function_selector;
+use web3::api::Web3;
+use web3::types::{Address, Bytes, CallRequest, H256, U256};
+use web3::Transport;
+
+/// The signature of the UUPS and beacon `upgradeTo` function.
+const UPGRADE_TO: &str = "upgradeTo(address)";
+
+/// The signature of the UUPS `upgradeToAndCall` function.
+const UPGRADE_TO_AND_CALL: &str = "upgradeToAndCall(address,bytes)";
+
+/// The signature of the ERC1822 `proxiableUUID` function.
+const PROXIABLE_UUID: &str = "proxiableUUID()";
+
+/// The signature of the beacon `implementation` function.
+const IMPLEMENTATION: &str = "implementation()";
+
+/// Options for checking a new implementation before upgrading to it.
+#[derive(Clone, Debug, Default)]
+pub struct UpgradeOptions {
+    /// The expected runtime code of the new implementation. The upgrade is
+    /// aborted if the deployed code differs from it, ignoring the Solidity
+    /// metadata appended to both.
+    pub expected_code: Option<Bytes>,
+}
+
+impl UpgradeOptions {
+    /// Specify the expected runtime code of the new implementation.
+    pub fn expected_code(mut self, code: impl Into<Bytes>) -> Self {
+        self.expected_code = Some(code.into());
+        self
+    }
+}
+
+impl<T: Transport> Instance<T> {
+    /// Returns the implementation of an EIP-1967 proxy, which is either read
+    /// from its implementation slot, or retrieved from its beacon for beacon
+    /// proxies. Returns `None` if the contract is not an EIP-1967 proxy.
+    pub async fn proxy_implementation(&self) -> Result<Option<Address>, ExecutionError> {
+        if let Some(implementation) = self.slot_address(IMPLEMENTATION_SLOT).await? {
+            return Ok(Some(implementation));
+        }
+        match self.slot_address(BEACON_SLOT).await? {
+            Some(beacon) => beacon_implementation(&self.web3, beacon).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the implementation of an upgradeable beacon contract.
+    pub async fn beacon_implementation(&self) -> Result<Address, ExecutionError> {
+        beacon_implementation(&self.web3, self.address).await
+    }
+
+    /// Upgrades a UUPS proxy to a new implementation with `upgradeTo`.
+    ///
+    /// Before the upgrade transaction is sent, this checks that the contract
+    /// is an EIP-1967 proxy, that the new implementation is a contract with
+    /// the expected code, and that it is a UUPS implementation itself, so that
+    /// the proxy stays upgradeable. The implementation slot is read again
+    /// after the upgrade to verify that it was updated.
+    pub async fn upgrade_proxy(
+        &self,
+        implementation: Address,
+        options: UpgradeOptions,
+    ) -> Result<TransactionResult, UpgradeError> {
+        self.check_proxy_upgrade(implementation, &options).await?;
+        let result = self
+            .method::<_, ()>(function_selector(UPGRADE_TO), (implementation,))
+            .map_err(|err| MethodError::from_parts(UPGRADE_TO.to_owned(), err.into()))?
+            .send()
+            .await?;
+        self.check_upgraded(IMPLEMENTATION_SLOT, implementation)
+            .await?;
+        Ok(result)
+    }
+
+    /// Upgrades a UUPS proxy to a new implementation with `upgradeToAndCall`,
+    /// calling the new implementation with `data` in the context of the proxy,
+    /// for example to initialize or migrate its storage. The same checks as
+    /// for `upgrade_proxy` are performed.
+    pub async fn upgrade_proxy_and_call(
+        &self,
+        implementation: Address,
+        data: Vec<u8>,
+        options: UpgradeOptions,
+    ) -> Result<TransactionResult, UpgradeError> {
+        self.check_proxy_upgrade(implementation, &options).await?;
+        let result = self
+            .method::<_, ()>(
+                function_selector(UPGRADE_TO_AND_CALL),
+                (implementation, TokenBytes(data)),
+            )
+            .map_err(|err| MethodError::from_parts(UPGRADE_TO_AND_CALL.to_owned(), err.into()))?
+            .send()
+            .await?;
+        self.check_upgraded(IMPLEMENTATION_SLOT, implementation)
+            .await?;
+        Ok(result)
+    }
+
+    /// Upgrades an upgradeable beacon to a new implementation with
+    /// `upgradeTo`, which upgrades all beacon proxies using it.
+    ///
+    /// Before the upgrade transaction is sent, this checks that the new
+    /// implementation is a contract with the expected code. The beacon's
+    /// implementation is read again after the upgrade to verify that it was
+    /// updated.
+    pub async fn upgrade_beacon(
+        &self,
+        implementation: Address,
+        options: UpgradeOptions,
+    ) -> Result<TransactionResult, UpgradeError> {
+        self.check_code(implementation, &options).await?;
+        let result = self
+            .method::<_, ()>(function_selector(UPGRADE_TO), (implementation,))
+            .map_err(|err| MethodError::from_parts(UPGRADE_TO.to_owned(), err.into()))?
+            .send()
+            .await?;
+        let actual = self.beacon_implementation().await?;
+        if actual != implementation {
+            return Err(UpgradeError::NotUpgraded {
+                expected: implementation,
+                actual,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Checks that the contract is a proxy and that it can be upgraded to the
+    /// new UUPS implementation.
+    async fn check_proxy_upgrade(
+        &self,
+        implementation: Address,
+        options: &UpgradeOptions,
+    ) -> Result<(), UpgradeError> {
+        if self.slot_address(IMPLEMENTATION_SLOT).await?.is_none() {
+            return Err(UpgradeError::NotProxy(self.address));
+        }
+        self.check_code(implementation, options).await?;
+
+        // Implementations without `proxiableUUID` either revert or fall back
+        // to returning nothing, so both mean the proxy would no longer be
+        // upgradeable after the upgrade.
+        let uuid = match call(&self.web3, implementation, PROXIABLE_UUID).await {
+            Ok(output) if output.len() >= 32 => H256::from_slice(&output[..32]),
+            Ok(_) | Err(ExecutionError::Revert(_)) | Err(ExecutionError::InvalidOpcode) => {
+                return Err(UpgradeError::NotProxiable(implementation))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if uuid != IMPLEMENTATION_SLOT {
+            return Err(UpgradeError::NotProxiable(implementation));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the new implementation is a contract with the expected
+    /// code.
+    async fn check_code(
+        &self,
+        implementation: Address,
+        options: &UpgradeOptions,
+    ) -> Result<(), UpgradeError> {
+        let code = self
+            .web3
+            .eth()
+            .code(implementation, None)
+            .await
+            .map_err(ExecutionError::from)?;
+        if code.0.is_empty() {
+            return Err(UpgradeError::NotContract(implementation));
+        }
+        if let Some(expected) = &options.expected_code {
+            if bytecode::split_metadata(&code.0).0 != bytecode::split_metadata(&expected.0).0 {
+                return Err(UpgradeError::CodeMismatch(implementation));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the address in an EIP-1967 storage slot is the new
+    /// implementation after an upgrade.
+    async fn check_upgraded(
+        &self,
+        slot: H256,
+        implementation: Address,
+    ) -> Result<(), UpgradeError> {
+        let actual = self.slot_address(slot).await?.unwrap_or_default();
+        if actual != implementation {
+            return Err(UpgradeError::NotUpgraded {
+                expected: implementation,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reads the non-zero address stored in an EIP-1967 storage slot of the
+    /// contract.
+    async fn slot_address(&self, slot: H256) -> Result<Option<Address>, ExecutionError> {
+        let value = self
+            .web3
+            .eth()
+            .storage(self.address, U256::from_big_endian(slot.as_bytes()), None)
+            .await?;
+        Ok(bytecode::slot_address(value))
+    }
+}
+
+/// Retrieves the implementation of a beacon by calling `implementation()`.
+async fn beacon_implementation<T: Transport>(
+    web3: &Web3<T>,
+    beacon: Address,
+) -> Result<Address, ExecutionError> {
+    let output = call(web3, beacon, IMPLEMENTATION).await?;
+    let tokens = abi::try_decode(&[ParamType::Address], &output)?;
+    Ok(Address::from_token(tokens.into_iter().next().unwrap())?)
+}
+
+/// Calls a function without parameters on a contract and returns the raw
+/// output.
+async fn call<T: Transport>(
+    web3: &Web3<T>,
+    to: Address,
+    signature: &str,
+) -> Result<Vec<u8>, ExecutionError> {
+    let request = CallRequest {
+        to: Some(to),
+        data: Some(Bytes(function_selector(signature).to_vec())),
+        ..Default::default()
+    };
+    let output = web3.eth().call(request, None).await?;
+    Ok(output.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::Account;
+    use ethcontract_common::Abi;
+    use serde_json::Value;
+    use web3::types::H2048;
+
+    const ABI: &str = r#"[
+        {
+            "type": "function",
+            "name": "upgradeTo",
+            "inputs": [{ "name": "implementation", "type": "address" }],
+            "outputs": [],
+            "stateMutability": "nonpayable"
+        },
+        {
+            "type": "function",
+            "name": "upgradeToAndCall",
+            "inputs": [
+                { "name": "implementation", "type": "address" },
+                { "name": "data", "type": "bytes" }
+            ],
+            "outputs": [],
+            "stateMutability": "payable"
+        }
+    ]"#;
+
+    const OWNER: Address = Address::repeat_byte(0x01);
+    const PROXY: Address = Address::repeat_byte(0x42);
+    const OLD: Address = Address::repeat_byte(0x10);
+    const NEW: Address = Address::repeat_byte(0x11);
+
+    fn instance(transport: &TestTransport) -> Instance<TestTransport> {
+        let mut instance = Instance::at(
+            Web3::new(transport.clone()),
+            Abi::load(ABI.as_bytes()).unwrap(),
+            PROXY,
+        );
+        instance.defaults.from = Some(Account::Local(OWNER, None));
+        instance
+    }
+
+    fn slot(slot: H256) -> Value {
+        json!(U256::from_big_endian(slot.as_bytes()))
+    }
+
+    fn word(address: Address) -> Value {
+        json!(H256::from(address))
+    }
+
+    fn call_request(to: Address, signature: &str) -> Value {
+        json!({
+            "to": to,
+            "data": format!("0x{}", hex::encode(function_selector(signature))),
+        })
+    }
+
+    fn add_sent_transaction(transport: &mut TestTransport, hash: H256) {
+        transport.add_response(json!("0x1")); // gas limit
+        transport.add_response(json!(hash));
+        transport.add_response(json!("0x1")); // block number
+        transport.add_response(json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": "0x1",
+            "blockHash": H256::repeat_byte(1),
+            "cumulativeGasUsed": "0x1",
+            "gasUsed": "0x1",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x1",
+        }));
+    }
+
+    #[test]
+    fn upgrade_uups_proxy() {
+        let mut transport = TestTransport::new();
+        let code = "0x6080604052";
+
+        transport.add_response(word(OLD));
+        transport.add_response(json!(code));
+        transport.add_response(json!(IMPLEMENTATION_SLOT));
+        add_sent_transaction(&mut transport, H256::repeat_byte(3));
+        transport.add_response(word(NEW));
+        let result = instance(&transport)
+            .upgrade_proxy(
+                NEW,
+                UpgradeOptions::default().expected_code(hex::decode(&code[2..]).unwrap()),
+            )
+            .immediate()
+            .unwrap();
+
+        assert_eq!(result.hash(), H256::repeat_byte(3));
+        transport.assert_request(
+            "eth_getStorageAt",
+            &[json!(PROXY), slot(IMPLEMENTATION_SLOT), json!("latest")],
+        );
+        transport.assert_request("eth_getCode", &[json!(NEW), json!("latest")]);
+        transport.assert_request(
+            "eth_call",
+            &[call_request(NEW, PROXIABLE_UUID), json!("latest")],
+        );
+        let mut tx = json!({
+            "from": OWNER,
+            "to": PROXY,
+            "data": format!("0x3659cfe6{}", hex::encode(H256::from(NEW))),
+        });
+        transport.assert_request("eth_estimateGas", std::slice::from_ref(&tx));
+        tx["gas"] = json!("0x1");
+        transport.assert_request("eth_sendTransaction", &[tx]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(H256::repeat_byte(3))]);
+        transport.assert_request(
+            "eth_getStorageAt",
+            &[json!(PROXY), slot(IMPLEMENTATION_SLOT), json!("latest")],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn upgrade_rejects_invalid_implementations() {
+        let mut transport = TestTransport::new();
+        transport.add_response(word(Address::zero()));
+        let result = instance(&transport)
+            .upgrade_proxy(NEW, Default::default())
+            .immediate();
+        assert!(matches!(result, Err(UpgradeError::NotProxy(address)) if address == PROXY));
+
+        transport.add_response(word(OLD));
+        transport.add_response(json!("0x"));
+        let result = instance(&transport)
+            .upgrade_proxy(NEW, Default::default())
+            .immediate();
+        assert!(matches!(result, Err(UpgradeError::NotContract(address)) if address == NEW));
+
+        transport.add_response(word(OLD));
+        transport.add_response(json!("0x6080604052"));
+        let result = instance(&transport)
+            .upgrade_proxy(
+                NEW,
+                UpgradeOptions::default().expected_code(vec![0x60, 0x80, 0x60, 0x40, 0x53]),
+            )
+            .immediate();
+        assert!(matches!(result, Err(UpgradeError::CodeMismatch(address)) if address == NEW));
+
+        transport.add_response(word(OLD));
+        transport.add_response(json!("0x6080604052"));
+        transport.add_response(json!("0x"));
+        let result = instance(&transport)
+            .upgrade_proxy(NEW, Default::default())
+            .immediate();
+        assert!(matches!(result, Err(UpgradeError::NotProxiable(address)) if address == NEW));
+    }
+
+    #[test]
+    fn implementation_of_beacon_proxy() {
+        let mut transport = TestTransport::new();
+        let beacon = Address::repeat_byte(0x20);
+
+        transport.add_response(word(Address::zero()));
+        transport.add_response(word(beacon));
+        transport.add_response(word(NEW));
+        let implementation = instance(&transport)
+            .proxy_implementation()
+            .immediate()
+            .unwrap();
+
+        assert_eq!(implementation, Some(NEW));
+        transport.assert_request(
+            "eth_getStorageAt",
+            &[json!(PROXY), slot(IMPLEMENTATION_SLOT), json!("latest")],
+        );
+        transport.assert_request(
+            "eth_getStorageAt",
+            &[json!(PROXY), slot(BEACON_SLOT), json!("latest")],
+        );
+        transport.assert_request(
+            "eth_call",
+            &[call_request(beacon, IMPLEMENTATION), json!("latest")],
+        );
+        transport.assert_no_more_requests();
+    }
+}
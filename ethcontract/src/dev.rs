@@ -0,0 +1,421 @@
+//! An embedded development node that executes transactions with an in-process
+//! EVM, so that tests of contract logic do not require an external node such
+//! as Ganache or Hardhat.
+//!
+//! The node mines a block for every transaction as soon as it is sent and
+//! starts out with funded accounts, which can be used as unlocked accounts or
+//! for offline signing:
+//!
+//! ```
+//! # use ethcontract::dev;
+//! # use ethcontract::transaction::TransactionBuilder;
+//! # async fn test() -> Result<(), Box<dyn std::error::Error>> {
+//! let node = dev::spawn();
+//! let web3 = node.web3();
+//!
+//! let receipt = TransactionBuilder::new(web3.clone())
+//!     .from(node.account(0))
+//!     .to(node.accounts()[1])
+//!     .value(1_000.into())
+//!     .send()
+//!     .await?;
+//! assert!(receipt.is_receipt());
+//! # Ok(())
+//! # }
+//! # futures::executor::block_on(test()).unwrap();
+//! ```
+//!
+//! Besides the standard `eth_*` methods used by the runtime, the node
+//! supports the `evm_mine`, `evm_increaseTime`, `evm_setNextBlockTimestamp`,
+//! `evm_snapshot` and `evm_revert` methods known from Ganache and Hardhat.
+//!
+//! The node only keeps the state of the latest block, so state queries for
+//! previous blocks fail. The base fee is constant, and only the `ecrecover`
+//! and `identity` precompiled contracts are supported.
+
+mod node;
+mod transaction;
+
+use self::node::Node;
+use crate::secret::PrivateKey;
+use crate::transaction::Account;
+use futures::channel::oneshot;
+use futures::future::{self, BoxFuture, FutureExt as _};
+use jsonrpc_core::types::{Call, MethodCall, Params};
+use jsonrpc_core::Error as JsonrpcError;
+use serde_json::{json, Value};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::signing::keccak256;
+use web3::types::{Address, U256};
+use web3::{helpers, BatchTransport, RequestId, Transport};
+
+/// The stack size of the node thread, which needs to fit the nested frames of
+/// calls up to the maximum call depth.
+const STACK_SIZE: usize = 256 << 20;
+
+/// Options for configuring a development node.
+#[derive(Clone, Debug)]
+pub struct DevOptions {
+    /// The chain ID of the node.
+    pub chain_id: u64,
+    /// The number of funded accounts.
+    pub accounts: usize,
+    /// The initial balance of each funded account.
+    pub balance: U256,
+    /// The gas limit of blocks.
+    pub block_gas_limit: u64,
+    /// The base fee of blocks.
+    pub base_fee: U256,
+}
+
+impl Default for DevOptions {
+    fn default() -> Self {
+        DevOptions {
+            chain_id: 1337,
+            accounts: 10,
+            balance: U256::exp10(22),
+            block_gas_limit: 30_000_000,
+            base_fee: 1_000_000_000.into(),
+        }
+    }
+}
+
+/// Starts a development node with the default options: 10 accounts that are
+/// funded with 10000 ETH each on chain 1337.
+pub fn spawn() -> DevNode {
+    spawn_with(DevOptions::default())
+}
+
+/// Starts a development node with the specified options.
+///
+/// The node runs on its own thread, which stops once the node and all of its
+/// transports are dropped.
+pub fn spawn_with(options: DevOptions) -> DevNode {
+    let keys = (0..options.accounts).map(dev_key).collect::<Vec<_>>();
+    let mut node = Node::new(options.clone(), keys.clone());
+
+    let (sender, receiver) = mpsc::channel::<Request>();
+    thread::Builder::new()
+        .name("ethcontract-dev".to_owned())
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            for (method, params, reply) in receiver {
+                let _ = reply.send(node.handle(&method, &params));
+            }
+        })
+        .expect("failed to spawn development node thread");
+
+    DevNode {
+        transport: DevTransport {
+            sender: Arc::new(Mutex::new(sender)),
+            id: Default::default(),
+        },
+        keys,
+        chain_id: options.chain_id,
+    }
+}
+
+/// Returns the private key of a funded account. Keys are derived from the
+/// account index, so accounts are the same for every node.
+fn dev_key(index: usize) -> PrivateKey {
+    (0u64..)
+        .find_map(|attempt| {
+            let seed = format!("ethcontract-dev/{}/{}", index, attempt);
+            PrivateKey::from_raw(keccak256(seed.as_bytes())).ok()
+        })
+        .expect("a valid key is eventually derived")
+}
+
+/// A running development node.
+#[derive(Clone, Debug)]
+pub struct DevNode {
+    transport: DevTransport,
+    keys: Vec<PrivateKey>,
+    chain_id: u64,
+}
+
+impl DevNode {
+    /// Returns the transport for sending requests to the node.
+    pub fn transport(&self) -> &DevTransport {
+        &self.transport
+    }
+
+    /// Returns a `Web3` instance for the node.
+    pub fn web3(&self) -> Web3<DevTransport> {
+        Web3::new(self.transport.clone())
+    }
+
+    /// Returns the chain ID of the node.
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Returns the addresses of the funded accounts.
+    pub fn accounts(&self) -> Vec<Address> {
+        self.keys.iter().map(PrivateKey::public_address).collect()
+    }
+
+    /// Returns the private key of a funded account.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is not less than the number of accounts.
+    pub fn private_key(&self, index: usize) -> &PrivateKey {
+        &self.keys[index]
+    }
+
+    /// Returns a funded account for sending transactions, which are signed by
+    /// the node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is not less than the number of accounts.
+    pub fn account(&self, index: usize) -> Account {
+        Account::Local(self.keys[index].public_address(), None)
+    }
+
+    /// Mines an empty block.
+    pub async fn mine(&self) -> Result<(), Web3Error> {
+        self.transport.execute("evm_mine", vec![]).await?;
+        Ok(())
+    }
+
+    /// Advances the time of the following blocks by a number of seconds.
+    pub async fn increase_time(&self, seconds: u64) -> Result<(), Web3Error> {
+        self.transport
+            .execute("evm_increaseTime", vec![json!(seconds)])
+            .await?;
+        Ok(())
+    }
+
+    /// Takes a snapshot of the chain state, returning its ID.
+    pub async fn snapshot(&self) -> Result<U256, Web3Error> {
+        helpers::decode(self.transport.execute("evm_snapshot", vec![]).await?)
+    }
+
+    /// Reverts the chain state to a snapshot, returning whether the snapshot
+    /// existed. Reverting removes the snapshot and all later snapshots.
+    pub async fn revert(&self, snapshot: U256) -> Result<bool, Web3Error> {
+        let reverted = self
+            .transport
+            .execute("evm_revert", vec![json!(snapshot)])
+            .await?;
+        helpers::decode(reverted)
+    }
+}
+
+/// A request to the node thread, along with the channel for the response.
+type Request = (
+    String,
+    Vec<Value>,
+    oneshot::Sender<Result<Value, JsonrpcError>>,
+);
+
+/// A transport that sends requests to a development node.
+#[derive(Clone)]
+pub struct DevTransport {
+    sender: Arc<Mutex<mpsc::Sender<Request>>>,
+    id: Arc<AtomicUsize>,
+}
+
+impl Debug for DevTransport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("DevTransport").finish()
+    }
+}
+
+impl Transport for DevTransport {
+    type Out = BoxFuture<'static, Result<Value, Web3Error>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.id.fetch_add(1, Ordering::AcqRel);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, _: RequestId, call: Call) -> Self::Out {
+        let (method, params) = match call {
+            Call::MethodCall(MethodCall { method, params, .. }) => {
+                let params = match params {
+                    Params::Array(params) => params,
+                    Params::Map(params) => vec![Value::Object(params)],
+                    Params::None => Vec::new(),
+                };
+                (method, params)
+            }
+            _ => {
+                return future::err(Web3Error::Transport(
+                    "the development node only supports method calls".to_owned(),
+                ))
+                .boxed()
+            }
+        };
+
+        let (reply, response) = oneshot::channel();
+        let sent = self
+            .sender
+            .lock()
+            .expect("development node transport mutex poisoned")
+            .send((method, params, reply));
+        if sent.is_err() {
+            return future::err(stopped()).boxed();
+        }
+        async move {
+            match response.await {
+                Ok(result) => result.map_err(Web3Error::Rpc),
+                Err(_) => Err(stopped()),
+            }
+        }
+        .boxed()
+    }
+}
+
+impl BatchTransport for DevTransport {
+    type Batch = BoxFuture<'static, Result<Vec<Result<Value, Web3Error>>, Web3Error>>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests
+            .into_iter()
+            .map(|(id, call)| self.send(id, call))
+            .collect::<Vec<_>>();
+        Box::pin(async move { Ok(future::join_all(requests).await) })
+    }
+}
+
+fn stopped() -> Web3Error {
+    Web3Error::Transport("the development node stopped".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{ExecutionError, MethodError};
+    use crate::test::contracts::{COUNTER, COUNTER_ABI};
+    use crate::transaction::TransactionBuilder;
+    use crate::Instance;
+    use ethcontract_common::abi::{Contract as Abi, Token};
+    use web3::types::{Bytes, U64};
+
+    async fn deploy(node: &DevNode, code: &str) -> Address {
+        let receipt = TransactionBuilder::new(node.web3())
+            .from(node.account(0))
+            .data(Bytes(hex::decode(code).unwrap()))
+            .send()
+            .await
+            .unwrap();
+        receipt.as_receipt().unwrap().contract_address.unwrap()
+    }
+
+    #[test]
+    fn deploy_and_call_contract() {
+        futures::executor::block_on(async {
+            let node = spawn();
+            let address = deploy(&node, COUNTER).await;
+            let abi = Abi::load(COUNTER_ABI.as_bytes()).unwrap();
+            let counter = Instance::at(node.web3(), abi, address);
+
+            for account in 0..2 {
+                counter
+                    .method_by_name("increment", vec![])
+                    .unwrap()
+                    .from(node.account(account))
+                    .send()
+                    .await
+                    .unwrap();
+            }
+            let account = Account::Offline(node.private_key(0).clone(), None);
+            counter
+                .method_by_name("increment", vec![])
+                .unwrap()
+                .from(account)
+                .send()
+                .await
+                .unwrap();
+
+            for (account, value) in [(0, 2), (1, 1), (2, 0)].iter() {
+                let result = counter
+                    .view_method_by_name("value", vec![])
+                    .unwrap()
+                    .from(node.accounts()[*account])
+                    .call()
+                    .await
+                    .unwrap();
+                assert_eq!(result, Token::Uint((*value).into()));
+            }
+
+            let web3 = node.web3();
+            assert_eq!(web3.eth().block_number().await.unwrap(), U64::from(4));
+            let balance = web3.eth().balance(node.accounts()[0], None).await.unwrap();
+            assert!(balance < DevOptions::default().balance);
+        });
+    }
+
+    #[test]
+    fn reverts_are_decoded() {
+        futures::executor::block_on(async {
+            let node = spawn();
+            // A contract whose runtime code reverts with `Error("no")`.
+            let runtime = "6308c379a060e01b600052602060045260026024526\
+                           16e6f60f01b60445260646000fd";
+            let code = format!("61{:04x}80600c6000396000f3{}", runtime.len() / 2, runtime);
+            let address = deploy(&node, &code).await;
+            let abi = Abi::load(COUNTER_ABI.as_bytes()).unwrap();
+            let contract = Instance::at(node.web3(), abi, address);
+
+            let err = contract
+                .view_method_by_name("value", vec![])
+                .unwrap()
+                .call()
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    MethodError { inner: ExecutionError::Revert(Some(reason)), .. }
+                        if reason == "no"
+                ),
+                "{:?}",
+                err
+            );
+        });
+    }
+
+    #[test]
+    fn snapshots_revert_state() {
+        futures::executor::block_on(async {
+            let node = spawn_with(DevOptions {
+                accounts: 2,
+                ..Default::default()
+            });
+            let web3 = node.web3();
+            assert_eq!(web3.eth().accounts().await.unwrap(), node.accounts());
+
+            let snapshot = node.snapshot().await.unwrap();
+            TransactionBuilder::new(web3.clone())
+                .from(node.account(0))
+                .to(node.accounts()[1])
+                .value(1.into())
+                .send()
+                .await
+                .unwrap();
+            node.mine().await.unwrap();
+            assert_eq!(web3.eth().block_number().await.unwrap(), U64::from(2));
+
+            assert!(node.revert(snapshot).await.unwrap());
+            assert!(!node.revert(snapshot).await.unwrap());
+            assert_eq!(web3.eth().block_number().await.unwrap(), U64::from(0));
+            assert_eq!(
+                web3.eth().balance(node.accounts()[1], None).await.unwrap(),
+                DevOptions::default().balance
+            );
+        });
+    }
+}
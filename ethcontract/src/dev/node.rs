@@ -0,0 +1,970 @@
+//! The chain state of the development node and its JSON RPC methods.
+
+use super::transaction::{self, Fees, SignedTransaction, UnsignedTransaction};
+use super::DevOptions;
+use crate::evm::{self, AccountInfo, Database, Env, Outcome};
+use crate::log::Bloom;
+use crate::secret::PrivateKey;
+use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use web3::signing::keccak256;
+use web3::types::{AccessList, Address, Bytes, H2048, H256, U256, U64};
+
+/// The accounts of the chain state.
+#[derive(Clone, Debug, Default)]
+struct State {
+    accounts: HashMap<Address, Account>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Account {
+    info: AccountInfo,
+    storage: HashMap<U256, U256>,
+}
+
+/// The chain state as seen by the EVM when executing a transaction.
+struct StateDb<'a> {
+    state: &'a State,
+    blocks: &'a [Block],
+}
+
+impl Database for StateDb<'_> {
+    fn account(&mut self, address: Address) -> AccountInfo {
+        self.state
+            .accounts
+            .get(&address)
+            .map(|account| account.info.clone())
+            .unwrap_or_default()
+    }
+
+    fn storage(&mut self, address: Address, slot: U256) -> U256 {
+        self.state
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&slot))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn block_hash(&mut self, number: u64) -> H256 {
+        self.blocks
+            .get(number as usize)
+            .map(|block| block.hash)
+            .unwrap_or_default()
+    }
+}
+
+/// A mined block.
+#[derive(Clone, Debug)]
+struct Block {
+    number: u64,
+    hash: H256,
+    parent_hash: H256,
+    timestamp: u64,
+    base_fee: U256,
+    gas_limit: u64,
+    gas_used: u64,
+    transactions: Vec<MinedTransaction>,
+}
+
+/// A transaction along with the result of its execution.
+#[derive(Clone, Debug)]
+struct MinedTransaction {
+    signed: SignedTransaction,
+    gas_price: U256,
+    success: bool,
+    gas_used: u64,
+    cumulative_gas_used: u64,
+    contract_address: Option<Address>,
+    logs: Vec<evm::Log>,
+}
+
+/// A filter created with one of the `eth_new*Filter` methods.
+#[derive(Clone, Debug)]
+enum Filter {
+    Blocks { next: u64 },
+    PendingTransactions,
+    Logs { filter: LogFilter, next: u64 },
+}
+
+/// The criteria of a log filter.
+#[derive(Clone, Debug, Default)]
+struct LogFilter {
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    block_hash: Option<H256>,
+    addresses: Vec<Address>,
+    topics: Vec<Vec<H256>>,
+}
+
+impl LogFilter {
+    fn matches(&self, log: &evm::Log) -> bool {
+        (self.addresses.is_empty() || self.addresses.contains(&log.address))
+            && self.topics.iter().enumerate().all(|(i, topics)| {
+                topics.is_empty() || log.topics.get(i).map_or(false, |t| topics.contains(t))
+            })
+    }
+}
+
+/// The parameters of `eth_call`, `eth_estimateGas` and `eth_sendTransaction`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionRequest {
+    from: Option<Address>,
+    to: Option<Address>,
+    gas: Option<U256>,
+    gas_price: Option<U256>,
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+    value: Option<U256>,
+    data: Option<Bytes>,
+    input: Option<Bytes>,
+    nonce: Option<U256>,
+    access_list: Option<AccessList>,
+}
+
+impl TransactionRequest {
+    fn data(&self) -> Vec<u8> {
+        self.input
+            .as_ref()
+            .or(self.data.as_ref())
+            .map(|data| data.0.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// An error returned from a JSON RPC method.
+fn error(code: i64, message: impl Into<String>) -> JsonrpcError {
+    JsonrpcError {
+        code: ErrorCode::ServerError(code),
+        message: message.into(),
+        data: None,
+    }
+}
+
+fn invalid_params(message: impl Into<String>) -> JsonrpcError {
+    JsonrpcError {
+        code: ErrorCode::InvalidParams,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// Returns the error of a reverted call, in the same format as Geth so that
+/// the revert reason is decoded by [`crate::errors::ExecutionError`].
+fn revert_error(output: &[u8]) -> JsonrpcError {
+    let message = match crate::errors::revert::decode_reason(output) {
+        Some(reason) => format!("execution reverted: {}", reason),
+        None => "execution reverted".to_owned(),
+    };
+    JsonrpcError {
+        code: ErrorCode::ServerError(3),
+        message,
+        data: Some(json!(Bytes(output.to_vec()))),
+    }
+}
+
+fn outcome_error(outcome: &Outcome) -> Option<JsonrpcError> {
+    match outcome {
+        Outcome::Success(_) => None,
+        Outcome::Revert(output) => Some(revert_error(output)),
+        Outcome::Halt(halt) => Some(error(-32000, halt.to_string())),
+    }
+}
+
+/// Parses the parameter at an index.
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, JsonrpcError> {
+    serde_json::from_value(params.get(index).cloned().unwrap_or(Value::Null))
+        .map_err(|err| invalid_params(format!("invalid parameter {}: {}", index, err)))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// The state of the development node.
+#[derive(Clone, Debug)]
+pub(crate) struct Node {
+    options: DevOptions,
+    keys: Vec<(Address, PrivateKey)>,
+    state: State,
+    blocks: Vec<Block>,
+    transactions: HashMap<H256, (u64, usize)>,
+    filters: HashMap<U256, Filter>,
+    next_filter: U256,
+    snapshots: HashMap<U256, Box<Node>>,
+    next_snapshot: U256,
+    time_offset: u64,
+    next_timestamp: Option<u64>,
+}
+
+impl Node {
+    pub fn new(options: DevOptions, keys: Vec<PrivateKey>) -> Self {
+        let mut state = State::default();
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                let address = key.public_address();
+                state.accounts.entry(address).or_default().info.balance = options.balance;
+                (address, key)
+            })
+            .collect();
+
+        let mut node = Node {
+            options,
+            keys,
+            state,
+            blocks: Vec::new(),
+            transactions: HashMap::new(),
+            filters: HashMap::new(),
+            next_filter: U256::one(),
+            snapshots: HashMap::new(),
+            next_snapshot: U256::one(),
+            time_offset: 0,
+            next_timestamp: None,
+        };
+        node.mine(0, now(), Vec::new());
+        node
+    }
+
+    fn head(&self) -> &Block {
+        self.blocks.last().expect("the genesis block always exists")
+    }
+
+    /// Returns the execution environment of the next block.
+    fn pending_env(&self) -> Env {
+        let head = self.head();
+        Env {
+            chain_id: self.options.chain_id,
+            number: head.number + 1,
+            timestamp: self.next_timestamp().max(head.timestamp + 1),
+            coinbase: Address::zero(),
+            gas_limit: self.options.block_gas_limit,
+            base_fee: self.options.base_fee,
+            prevrandao: head.hash,
+        }
+    }
+
+    fn next_timestamp(&self) -> u64 {
+        self.next_timestamp
+            .unwrap_or_else(|| now() + self.time_offset)
+    }
+
+    /// Mines a block containing the specified transactions.
+    fn mine(&mut self, number: u64, timestamp: u64, transactions: Vec<MinedTransaction>) {
+        let parent_hash = self.blocks.last().map_or_else(H256::zero, |head| head.hash);
+        self.next_timestamp = None;
+
+        // NOTE: Block hashes are not the hashes of Ethereum block headers,
+        //   but they uniquely identify the blocks of the node.
+        let mut header = vec![
+            transaction::encode_bytes(parent_hash.as_bytes()),
+            transaction::encode_u64(number),
+            transaction::encode_u64(timestamp),
+        ];
+        header.extend(
+            transactions
+                .iter()
+                .map(|tx| transaction::encode_bytes(tx.signed.hash.as_bytes())),
+        );
+        let hash = H256(keccak256(&transaction::encode_list(header)));
+
+        for (index, tx) in transactions.iter().enumerate() {
+            self.transactions.insert(tx.signed.hash, (number, index));
+        }
+        self.blocks.push(Block {
+            number,
+            hash,
+            parent_hash,
+            timestamp,
+            base_fee: self.options.base_fee,
+            gas_limit: self.options.block_gas_limit,
+            gas_used: transactions.last().map_or(0, |tx| tx.cumulative_gas_used),
+            transactions,
+        });
+    }
+
+    /// Handles a JSON RPC request.
+    pub fn handle(&mut self, method: &str, params: &[Value]) -> Result<Value, JsonrpcError> {
+        let result = match method {
+            "web3_clientVersion" => json!(concat!("ethcontract-dev/v", env!("CARGO_PKG_VERSION"))),
+            "net_version" => json!(self.options.chain_id.to_string()),
+            "net_listening" => json!(true),
+            "eth_chainId" => json!(U64::from(self.options.chain_id)),
+            "eth_syncing" | "eth_mining" => json!(false),
+            "eth_coinbase" => json!(Address::zero()),
+            "eth_accounts" => json!(self
+                .keys
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>()),
+            "eth_blockNumber" => json!(U64::from(self.head().number)),
+            "eth_gasPrice" => json!(self.options.base_fee),
+            "eth_maxPriorityFeePerGas" => json!(U256::zero()),
+            "eth_feeHistory" => self.fee_history(params)?,
+            "eth_getBalance" => {
+                self.check_latest(params.get(1))?;
+                json!(self.account(param(params, 0)?).balance)
+            }
+            "eth_getTransactionCount" => {
+                self.check_latest(params.get(1))?;
+                json!(U256::from(self.account(param(params, 0)?).nonce))
+            }
+            "eth_getCode" => {
+                self.check_latest(params.get(1))?;
+                json!(Bytes(self.account(param(params, 0)?).code.to_vec()))
+            }
+            "eth_getStorageAt" => {
+                self.check_latest(params.get(2))?;
+                let address = param(params, 0)?;
+                let slot: U256 = param(params, 1)?;
+                let value = StateDb {
+                    state: &self.state,
+                    blocks: &self.blocks,
+                }
+                .storage(address, slot);
+                let mut word = H256::zero();
+                value.to_big_endian(word.as_bytes_mut());
+                json!(word)
+            }
+            "eth_call" => {
+                self.check_latest(params.get(1))?;
+                let request = param(params, 0)?;
+                let result = self.execute(&request, None)?;
+                if let Some(err) = outcome_error(&result.outcome) {
+                    return Err(err);
+                }
+                json!(Bytes(result.outcome.output().to_vec()))
+            }
+            "eth_estimateGas" => json!(U256::from(self.estimate_gas(&param(params, 0)?)?)),
+            "eth_sendTransaction" => json!(self.send_transaction(param(params, 0)?)?),
+            "eth_sendRawTransaction" => {
+                let raw: Bytes = param(params, 0)?;
+                json!(self.send_raw_transaction(&raw.0)?)
+            }
+            "eth_getTransactionByHash" => self
+                .find_transaction(param(params, 0)?)
+                .map_or(Value::Null, |(block, index)| transaction_json(block, index)),
+            "eth_getTransactionReceipt" => self
+                .find_transaction(param(params, 0)?)
+                .map_or(Value::Null, |(block, index)| receipt_json(block, index)),
+            "eth_getBlockByNumber" => {
+                let full = param::<Option<bool>>(params, 1)?.unwrap_or(false);
+                match self.block_number(params.first())? {
+                    Some(number) => block_json(&self.blocks[number as usize], full),
+                    None => Value::Null,
+                }
+            }
+            "eth_getBlockByHash" => {
+                let full = param::<Option<bool>>(params, 1)?.unwrap_or(false);
+                let hash: H256 = param(params, 0)?;
+                self.blocks
+                    .iter()
+                    .find(|block| block.hash == hash)
+                    .map_or(Value::Null, |block| block_json(block, full))
+            }
+            "eth_getBlockReceipts" => match self.block_number(params.first())? {
+                Some(number) => {
+                    let block = &self.blocks[number as usize];
+                    json!((0..block.transactions.len())
+                        .map(|index| receipt_json(block, index))
+                        .collect::<Vec<_>>())
+                }
+                None => Value::Null,
+            },
+            "eth_getLogs" => {
+                let filter = self.log_filter(params.first())?;
+                json!(self.logs(&filter, None))
+            }
+            "eth_newFilter" => {
+                let filter = self.log_filter(params.first())?;
+                let next = self.head().number + 1;
+                self.add_filter(Filter::Logs { filter, next })
+            }
+            "eth_newBlockFilter" => {
+                let next = self.head().number + 1;
+                self.add_filter(Filter::Blocks { next })
+            }
+            "eth_newPendingTransactionFilter" => self.add_filter(Filter::PendingTransactions),
+            "eth_getFilterChanges" => self.filter_changes(param(params, 0)?)?,
+            "eth_getFilterLogs" => match self.filters.get(&param(params, 0)?) {
+                Some(Filter::Logs { filter, .. }) => json!(self.logs(filter, None)),
+                _ => return Err(error(-32000, "filter not found")),
+            },
+            "eth_uninstallFilter" => json!(self.filters.remove(&param(params, 0)?).is_some()),
+            "evm_mine" => {
+                if let Some(timestamp) = param::<Option<U256>>(params, 0)? {
+                    self.next_timestamp = Some(timestamp.low_u64());
+                }
+                let env = self.pending_env();
+                self.mine(env.number, env.timestamp, Vec::new());
+                json!("0x0")
+            }
+            "evm_increaseTime" => {
+                let seconds: U256 = param(params, 0)?;
+                self.time_offset += seconds.low_u64();
+                json!(self.time_offset)
+            }
+            "evm_setNextBlockTimestamp" => {
+                let timestamp: U256 = param(params, 0)?;
+                self.next_timestamp = Some(timestamp.low_u64());
+                Value::Null
+            }
+            "evm_snapshot" => {
+                let id = self.next_snapshot;
+                self.next_snapshot += U256::one();
+                let mut snapshot = self.clone();
+                snapshot.snapshots.clear();
+                self.snapshots.insert(id, Box::new(snapshot));
+                json!(id)
+            }
+            "evm_revert" => {
+                let id: U256 = param(params, 0)?;
+                match self.snapshots.remove(&id) {
+                    Some(snapshot) => {
+                        // NOTE: Like Ganache, reverting to a snapshot also
+                        //   removes all snapshots taken after it.
+                        let mut snapshots = std::mem::take(&mut self.snapshots);
+                        snapshots.retain(|other, _| *other < id);
+                        let next_snapshot = self.next_snapshot;
+                        *self = *snapshot;
+                        self.snapshots = snapshots;
+                        self.next_snapshot = next_snapshot;
+                        json!(true)
+                    }
+                    None => json!(false),
+                }
+            }
+            _ => {
+                return Err(JsonrpcError {
+                    code: ErrorCode::MethodNotFound,
+                    message: format!("the method {} does not exist/is not available", method),
+                    data: None,
+                })
+            }
+        };
+        Ok(result)
+    }
+
+    fn account(&self, address: Address) -> AccountInfo {
+        StateDb {
+            state: &self.state,
+            blocks: &self.blocks,
+        }
+        .account(address)
+    }
+
+    /// Parses a block number or tag, returning `None` for unknown blocks.
+    fn block_number(&self, block: Option<&Value>) -> Result<Option<u64>, JsonrpcError> {
+        let head = self.head().number;
+        let number = match block {
+            None | Some(Value::Null) => head,
+            Some(Value::String(tag)) => match tag.as_str() {
+                "latest" | "pending" | "safe" | "finalized" => head,
+                "earliest" => 0,
+                number => serde_json::from_value::<U64>(json!(number))
+                    .map_err(|_| invalid_params(format!("invalid block number {}", number)))?
+                    .as_u64(),
+            },
+            Some(Value::Object(object)) => {
+                if let Some(hash) = object.get("blockHash") {
+                    let hash: H256 = serde_json::from_value(hash.clone())
+                        .map_err(|_| invalid_params("invalid block hash"))?;
+                    match self.blocks.iter().find(|block| block.hash == hash) {
+                        Some(block) => block.number,
+                        None => return Ok(None),
+                    }
+                } else {
+                    return self.block_number(object.get("blockNumber"));
+                }
+            }
+            Some(_) => return Err(invalid_params("invalid block number")),
+        };
+        Ok(if number <= head { Some(number) } else { None })
+    }
+
+    /// Checks that a block parameter refers to the latest block, since the
+    /// node does not keep the state of previous blocks.
+    fn check_latest(&self, block: Option<&Value>) -> Result<(), JsonrpcError> {
+        match self.block_number(block)? {
+            Some(number) if number == self.head().number => Ok(()),
+            Some(number) => Err(error(
+                -32000,
+                format!("state of block {} is not available", number),
+            )),
+            None => Err(error(-32000, "header not found")),
+        }
+    }
+
+    /// Executes a call on top of the latest state.
+    fn execute(
+        &self,
+        request: &TransactionRequest,
+        gas: Option<u64>,
+    ) -> Result<evm::ExecutionResult, JsonrpcError> {
+        let env = self.pending_env();
+        let gas_price = request
+            .gas_price
+            .or(request.max_fee_per_gas)
+            .unwrap_or_default();
+        let tx = evm::Transaction {
+            from: request.from.unwrap_or_default(),
+            to: request.to,
+            value: request.value.unwrap_or_default(),
+            data: request.data(),
+            gas_limit: gas
+                .or_else(|| request.gas.map(|gas| gas.low_u64()))
+                .unwrap_or(env.gas_limit),
+            gas_price,
+            nonce: None,
+            access_list: access_list(request.access_list.as_ref()),
+        };
+        let mut db = StateDb {
+            state: &self.state,
+            blocks: &self.blocks,
+        };
+        evm::transact(&mut db, &env, &tx).map_err(|err| error(-32000, err.to_string()))
+    }
+
+    /// Estimates the gas of a transaction by searching for the lowest gas
+    /// limit for which it succeeds.
+    fn estimate_gas(&self, request: &TransactionRequest) -> Result<u64, JsonrpcError> {
+        let mut high = request
+            .gas
+            .map(|gas| gas.low_u64())
+            .unwrap_or(self.options.block_gas_limit);
+        let result = self.execute(request, Some(high))?;
+        if let Some(err) = outcome_error(&result.outcome) {
+            return Err(err);
+        }
+
+        let mut low = result.gas_used - 1;
+        while low + 1 < high {
+            let mid = low + (high - low) / 2;
+            match self.execute(request, Some(mid)) {
+                Ok(result) if result.outcome.is_success() => high = mid,
+                _ => low = mid,
+            }
+        }
+        Ok(high)
+    }
+
+    fn send_transaction(&mut self, request: TransactionRequest) -> Result<H256, JsonrpcError> {
+        let from = request
+            .from
+            .ok_or_else(|| invalid_params("missing transaction sender"))?;
+        let key = self
+            .keys
+            .iter()
+            .find(|(address, _)| *address == from)
+            .map(|(_, key)| key.clone())
+            .ok_or_else(|| error(-32000, format!("unknown account {:?}", from)))?;
+
+        let gas = match request.gas {
+            Some(gas) => gas.low_u64(),
+            None => self.estimate_gas(&request)?,
+        };
+        let fees = match request.gas_price {
+            Some(gas_price) => Fees::Legacy { gas_price },
+            None => {
+                let max_priority_fee_per_gas = request.max_priority_fee_per_gas.unwrap_or_default();
+                Fees::Eip1559 {
+                    max_fee_per_gas: request
+                        .max_fee_per_gas
+                        .unwrap_or(self.options.base_fee + max_priority_fee_per_gas),
+                    max_priority_fee_per_gas,
+                }
+            }
+        };
+        let transaction = UnsignedTransaction {
+            chain_id: Some(self.options.chain_id),
+            nonce: match request.nonce {
+                Some(nonce) => nonce.low_u64(),
+                None => self.account(from).nonce,
+            },
+            fees,
+            gas,
+            to: request.to,
+            value: request.value.unwrap_or_default(),
+            data: request.data(),
+            access_list: request.access_list.clone().unwrap_or_default(),
+        };
+        self.send_raw_transaction(&transaction.sign(&key))
+    }
+
+    /// Executes a signed transaction and mines a block containing it.
+    fn send_raw_transaction(&mut self, raw: &[u8]) -> Result<H256, JsonrpcError> {
+        let signed = transaction::decode(raw).map_err(|err| error(-32000, err))?;
+        if self.transactions.contains_key(&signed.hash) {
+            return Err(error(-32000, "already known"));
+        }
+        let tx = &signed.transaction;
+        if let Some(chain_id) = tx.chain_id {
+            if chain_id != self.options.chain_id {
+                return Err(error(-32000, "invalid chain id for signer"));
+            }
+        }
+
+        let env = self.pending_env();
+        let gas_price = match tx.fees {
+            Fees::Legacy { gas_price } => {
+                if gas_price < env.base_fee {
+                    return Err(error(-32000, "gas price less than block base fee"));
+                }
+                gas_price
+            }
+            Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                if max_fee_per_gas < env.base_fee {
+                    return Err(error(-32000, "max fee per gas less than block base fee"));
+                }
+                if max_priority_fee_per_gas > max_fee_per_gas {
+                    return Err(error(
+                        -32000,
+                        "max priority fee per gas higher than max fee per gas",
+                    ));
+                }
+                max_fee_per_gas.min(env.base_fee + max_priority_fee_per_gas)
+            }
+        };
+
+        let evm_tx = evm::Transaction {
+            from: signed.from,
+            to: tx.to,
+            value: tx.value,
+            data: tx.data.clone(),
+            gas_limit: tx.gas,
+            gas_price,
+            nonce: Some(tx.nonce),
+            access_list: access_list(Some(&tx.access_list)),
+        };
+        let mut db = StateDb {
+            state: &self.state,
+            blocks: &self.blocks,
+        };
+        let result =
+            evm::transact(&mut db, &env, &evm_tx).map_err(|err| error(-32000, err.to_string()))?;
+
+        for (address, change) in result.changes {
+            if change.destroyed {
+                self.state.accounts.remove(&address);
+                continue;
+            }
+            let account = self.state.accounts.entry(address).or_default();
+            if change.created {
+                account.storage.clear();
+            }
+            account.info = change.info;
+            for (slot, value) in change.storage {
+                if value.is_zero() {
+                    account.storage.remove(&slot);
+                } else {
+                    account.storage.insert(slot, value);
+                }
+            }
+        }
+
+        let hash = signed.hash;
+        let mined = MinedTransaction {
+            signed,
+            gas_price,
+            success: result.outcome.is_success(),
+            gas_used: result.gas_used,
+            cumulative_gas_used: result.gas_used,
+            contract_address: result.contract_address,
+            logs: result.logs,
+        };
+        self.mine(env.number, env.timestamp, vec![mined]);
+        Ok(hash)
+    }
+
+    fn find_transaction(&self, hash: H256) -> Option<(&Block, usize)> {
+        let (number, index) = self.transactions.get(&hash)?;
+        Some((&self.blocks[*number as usize], *index))
+    }
+
+    fn fee_history(&self, params: &[Value]) -> Result<Value, JsonrpcError> {
+        let count: U256 = param(params, 0)?;
+        let newest = self
+            .block_number(params.get(1))?
+            .ok_or_else(|| error(-32000, "header not found"))?;
+        let percentiles = param::<Option<Vec<f64>>>(params, 2)?.unwrap_or_default();
+        let count = count.low_u64().min(newest + 1).max(1);
+        let blocks = &self.blocks[(newest + 1 - count) as usize..=newest as usize];
+
+        let mut base_fees = blocks
+            .iter()
+            .map(|block| block.base_fee)
+            .collect::<Vec<_>>();
+        base_fees.push(self.options.base_fee);
+        let gas_used_ratio = blocks
+            .iter()
+            .map(|block| block.gas_used as f64 / block.gas_limit as f64)
+            .collect::<Vec<_>>();
+        let reward = blocks
+            .iter()
+            .map(|_| vec![U256::zero(); percentiles.len()])
+            .collect::<Vec<_>>();
+        Ok(json!({
+            "oldestBlock": U64::from(newest + 1 - count),
+            "baseFeePerGas": base_fees,
+            "gasUsedRatio": gas_used_ratio,
+            "reward": reward,
+        }))
+    }
+
+    fn log_filter(&self, filter: Option<&Value>) -> Result<LogFilter, JsonrpcError> {
+        let filter = match filter {
+            Some(Value::Object(filter)) => filter,
+            None | Some(Value::Null) => return Ok(LogFilter::default()),
+            Some(_) => return Err(invalid_params("invalid filter")),
+        };
+
+        let block = |name: &str| match filter.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => Ok(Some(self.block_number(Some(value))?.unwrap_or(u64::MAX))),
+        };
+        let list = |value: Option<&Value>| -> Result<Vec<Value>, JsonrpcError> {
+            Ok(match value {
+                None | Some(Value::Null) => Vec::new(),
+                Some(Value::Array(values)) => values.clone(),
+                Some(value) => vec![value.clone()],
+            })
+        };
+        fn parse<T: DeserializeOwned>(value: Value) -> Result<T, JsonrpcError> {
+            serde_json::from_value(value).map_err(|err| invalid_params(err.to_string()))
+        }
+
+        Ok(LogFilter {
+            from_block: block("fromBlock")?,
+            to_block: block("toBlock")?,
+            block_hash: match filter.get("blockHash") {
+                None | Some(Value::Null) => None,
+                Some(hash) => Some(parse(hash.clone())?),
+            },
+            addresses: list(filter.get("address"))?
+                .into_iter()
+                .map(parse)
+                .collect::<Result<_, _>>()?,
+            topics: list(filter.get("topics"))?
+                .into_iter()
+                .map(|topics| list(Some(&topics))?.into_iter().map(parse).collect())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns the JSON of all logs matching a filter, starting with the
+    /// specified block if any.
+    fn logs(&self, filter: &LogFilter, from: Option<u64>) -> Vec<Value> {
+        let head = self.head().number;
+        let (from, to) = match filter.block_hash {
+            Some(hash) => match self.blocks.iter().find(|block| block.hash == hash) {
+                Some(block) => (block.number, block.number),
+                None => return Vec::new(),
+            },
+            None => (
+                from.or(filter.from_block).unwrap_or(head),
+                filter.to_block.unwrap_or(head).min(head),
+            ),
+        };
+
+        let mut logs = Vec::new();
+        for block in self
+            .blocks
+            .iter()
+            .take(to.saturating_add(1) as usize)
+            .skip(from as usize)
+        {
+            let mut log_index = 0;
+            for (index, tx) in block.transactions.iter().enumerate() {
+                for log in &tx.logs {
+                    if filter.matches(log) {
+                        logs.push(log_json(block, index, log_index, log));
+                    }
+                    log_index += 1;
+                }
+            }
+        }
+        logs
+    }
+
+    fn add_filter(&mut self, filter: Filter) -> Value {
+        let id = self.next_filter;
+        self.next_filter += U256::one();
+        self.filters.insert(id, filter);
+        json!(id)
+    }
+
+    fn filter_changes(&mut self, id: U256) -> Result<Value, JsonrpcError> {
+        let head = self.head().number;
+        let filter = self
+            .filters
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| error(-32000, "filter not found"))?;
+        let (changes, filter) = match filter {
+            Filter::Blocks { next } => {
+                let hashes = self
+                    .blocks
+                    .iter()
+                    .skip(next as usize)
+                    .map(|block| json!(block.hash))
+                    .collect::<Vec<_>>();
+                (hashes, Filter::Blocks { next: head + 1 })
+            }
+            Filter::PendingTransactions => (Vec::new(), Filter::PendingTransactions),
+            Filter::Logs { filter, next } => {
+                let logs = if next <= head {
+                    self.logs(&filter, Some(next))
+                } else {
+                    Vec::new()
+                };
+                let next = next.max(head + 1);
+                (logs, Filter::Logs { filter, next })
+            }
+        };
+        self.filters.insert(id, filter);
+        Ok(json!(changes))
+    }
+}
+
+fn access_list(access_list: Option<&AccessList>) -> Vec<(Address, Vec<H256>)> {
+    access_list
+        .into_iter()
+        .flatten()
+        .map(|item| (item.address, item.storage_keys.clone()))
+        .collect()
+}
+
+fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a evm::Log>) -> H2048 {
+    let mut bloom = Bloom::default();
+    for log in logs {
+        bloom.accrue(log.address.as_bytes());
+        for topic in &log.topics {
+            bloom.accrue(topic.as_bytes());
+        }
+    }
+    bloom.0
+}
+
+fn log_json(block: &Block, index: usize, log_index: usize, log: &evm::Log) -> Value {
+    json!({
+        "address": log.address,
+        "topics": log.topics,
+        "data": Bytes(log.data.clone()),
+        "blockHash": block.hash,
+        "blockNumber": U64::from(block.number),
+        "transactionHash": block.transactions[index].signed.hash,
+        "transactionIndex": U64::from(index),
+        "logIndex": U256::from(log_index),
+        "removed": false,
+    })
+}
+
+fn transaction_json(block: &Block, index: usize) -> Value {
+    let mined = &block.transactions[index];
+    let tx = &mined.signed.transaction;
+    let mut json = json!({
+        "hash": mined.signed.hash,
+        "nonce": U256::from(tx.nonce),
+        "blockHash": block.hash,
+        "blockNumber": U64::from(block.number),
+        "transactionIndex": U64::from(index),
+        "from": mined.signed.from,
+        "to": tx.to,
+        "value": tx.value,
+        "gas": U256::from(tx.gas),
+        "gasPrice": mined.gas_price,
+        "input": Bytes(tx.data.clone()),
+        "v": U64::from(mined.signed.v),
+        "r": U256::from_big_endian(mined.signed.r.as_bytes()),
+        "s": U256::from_big_endian(mined.signed.s.as_bytes()),
+        "type": U64::from(tx.transaction_type()),
+    });
+    if tx.transaction_type() != 0 {
+        json["chainId"] = json!(U64::from(tx.chain_id.unwrap_or_default()));
+        json["accessList"] = json!(tx.access_list);
+    }
+    if let Fees::Eip1559 {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    } = tx.fees
+    {
+        json["maxFeePerGas"] = json!(max_fee_per_gas);
+        json["maxPriorityFeePerGas"] = json!(max_priority_fee_per_gas);
+    }
+    json
+}
+
+fn receipt_json(block: &Block, index: usize) -> Value {
+    let mined = &block.transactions[index];
+    let first_log = block.transactions[..index]
+        .iter()
+        .map(|tx| tx.logs.len())
+        .sum::<usize>();
+    json!({
+        "transactionHash": mined.signed.hash,
+        "transactionIndex": U64::from(index),
+        "blockHash": block.hash,
+        "blockNumber": U64::from(block.number),
+        "from": mined.signed.from,
+        "to": mined.signed.transaction.to,
+        "cumulativeGasUsed": U256::from(mined.cumulative_gas_used),
+        "gasUsed": U256::from(mined.gas_used),
+        "effectiveGasPrice": mined.gas_price,
+        "contractAddress": mined.contract_address,
+        "logs": mined
+            .logs
+            .iter()
+            .enumerate()
+            .map(|(i, log)| log_json(block, index, first_log + i, log))
+            .collect::<Vec<_>>(),
+        "logsBloom": logs_bloom(&mined.logs),
+        "status": U64::from(mined.success as u64),
+        "type": U64::from(mined.signed.transaction.transaction_type()),
+    })
+}
+
+fn block_json(block: &Block, full: bool) -> Value {
+    let transactions = (0..block.transactions.len())
+        .map(|index| {
+            if full {
+                transaction_json(block, index)
+            } else {
+                json!(block.transactions[index].signed.hash)
+            }
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "number": U64::from(block.number),
+        "hash": block.hash,
+        "parentHash": block.parent_hash,
+        "nonce": "0x0000000000000000",
+        "mixHash": H256::zero(),
+        "sha3Uncles": H256(keccak256(&[0xc0])),
+        "logsBloom": logs_bloom(block.transactions.iter().flat_map(|tx| &tx.logs)),
+        "transactionsRoot": H256::zero(),
+        "stateRoot": H256::zero(),
+        "receiptsRoot": H256::zero(),
+        "miner": Address::zero(),
+        "difficulty": U256::zero(),
+        "totalDifficulty": U256::zero(),
+        "extraData": Bytes::default(),
+        "size": U256::zero(),
+        "gasLimit": U256::from(block.gas_limit),
+        "gasUsed": U256::from(block.gas_used),
+        "timestamp": U256::from(block.timestamp),
+        "baseFeePerGas": block.base_fee,
+        "transactions": transactions,
+        "uncles": [],
+    })
+}
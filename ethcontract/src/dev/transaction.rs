@@ -0,0 +1,465 @@
+//! Encoding, signing and decoding of the raw transactions processed by the
+//! development node.
+
+use crate::secret::PrivateKey;
+use web3::signing::{self, keccak256, Key};
+use web3::types::{AccessList, AccessListItem, Address, H256, U256};
+
+/// The fee parameters of a transaction, which also determine its type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Fees {
+    /// A legacy transaction, or an EIP-2930 transaction if it has an access
+    /// list.
+    Legacy { gas_price: U256 },
+    /// An EIP-1559 transaction.
+    Eip1559 {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+/// A transaction before signing.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct UnsignedTransaction {
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    pub fees: Fees,
+    pub gas: u64,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: AccessList,
+}
+
+/// A signed transaction along with its sender.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SignedTransaction {
+    pub transaction: UnsignedTransaction,
+    pub from: Address,
+    pub hash: H256,
+    pub v: u64,
+    pub r: H256,
+    pub s: H256,
+}
+
+impl UnsignedTransaction {
+    /// Returns the EIP-2718 transaction type.
+    pub fn transaction_type(&self) -> u8 {
+        match self.fees {
+            Fees::Legacy { .. } if self.access_list.is_empty() => 0,
+            Fees::Legacy { .. } => 1,
+            Fees::Eip1559 { .. } => 2,
+        }
+    }
+
+    /// Encodes the fields of the transaction, followed by the signature
+    /// fields if any.
+    fn encode(&self, signature: Option<(u64, H256, H256)>) -> Vec<u8> {
+        let kind = self.transaction_type();
+        let mut fields = Vec::with_capacity(12);
+        if kind != 0 {
+            fields.push(encode_u64(self.chain_id.unwrap_or_default()));
+        }
+        fields.push(encode_u64(self.nonce));
+        match self.fees {
+            Fees::Legacy { gas_price } => fields.push(encode_uint(gas_price)),
+            Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                fields.push(encode_uint(max_priority_fee_per_gas));
+                fields.push(encode_uint(max_fee_per_gas));
+            }
+        }
+        fields.push(encode_u64(self.gas));
+        fields.push(encode_bytes(
+            self.to.as_ref().map_or(&[][..], |to| to.as_bytes()),
+        ));
+        fields.push(encode_uint(self.value));
+        fields.push(encode_bytes(&self.data));
+        if kind != 0 {
+            let items = self
+                .access_list
+                .iter()
+                .map(|item| {
+                    let keys = item
+                        .storage_keys
+                        .iter()
+                        .map(|key| encode_bytes(key.as_bytes()))
+                        .collect();
+                    encode_list(vec![
+                        encode_bytes(item.address.as_bytes()),
+                        encode_list(keys),
+                    ])
+                })
+                .collect();
+            fields.push(encode_list(items));
+        }
+        match signature {
+            Some((v, r, s)) => {
+                fields.push(encode_u64(v));
+                fields.push(encode_uint(U256::from_big_endian(r.as_bytes())));
+                fields.push(encode_uint(U256::from_big_endian(s.as_bytes())));
+            }
+            None if kind == 0 => {
+                if let Some(chain_id) = self.chain_id {
+                    fields.push(encode_u64(chain_id));
+                    fields.push(encode_u64(0));
+                    fields.push(encode_u64(0));
+                }
+            }
+            None => {}
+        }
+
+        let list = encode_list(fields);
+        if kind == 0 {
+            list
+        } else {
+            let mut encoded = Vec::with_capacity(list.len() + 1);
+            encoded.push(kind);
+            encoded.extend_from_slice(&list);
+            encoded
+        }
+    }
+
+    /// Returns the hash that is signed by the sender.
+    fn signing_hash(&self) -> [u8; 32] {
+        keccak256(&self.encode(None))
+    }
+
+    /// Signs the transaction, returning the raw signed transaction.
+    pub fn sign(&self, key: &PrivateKey) -> Vec<u8> {
+        let legacy_chain_id = if self.transaction_type() == 0 {
+            self.chain_id
+        } else {
+            None
+        };
+        let signature = key
+            .sign(&self.signing_hash(), legacy_chain_id)
+            .expect("signing a hash never fails");
+        let v = if self.transaction_type() == 0 {
+            signature.v
+        } else {
+            signature.v - 27
+        };
+        self.encode(Some((v, signature.r, signature.s)))
+    }
+}
+
+/// Decodes and recovers the sender of a raw signed transaction.
+pub(crate) fn decode(raw: &[u8]) -> Result<SignedTransaction, String> {
+    let kind = match raw.first() {
+        Some(kind @ 1..=2) => *kind,
+        Some(0xc0..=0xff) => 0,
+        _ => return Err("unsupported transaction type".to_owned()),
+    };
+    let payload = if kind == 0 { raw } else { &raw[1..] };
+    let fields = match decode_item(payload) {
+        Some((Item::List(fields), [])) => fields,
+        _ => return Err("invalid transaction encoding".to_owned()),
+    };
+    let expected = match kind {
+        0 => 9,
+        1 => 11,
+        _ => 12,
+    };
+    if fields.len() != expected {
+        return Err("invalid transaction encoding".to_owned());
+    }
+
+    let invalid = || "invalid transaction encoding".to_owned();
+    let mut fields = fields.into_iter();
+    let mut next = || fields.next().ok_or_else(invalid);
+    let chain_id = if kind == 0 {
+        None
+    } else {
+        Some(next()?.u64().ok_or_else(invalid)?)
+    };
+    let nonce = next()?.u64().ok_or_else(invalid)?;
+    let fees = if kind == 2 {
+        let max_priority_fee_per_gas = next()?.uint().ok_or_else(invalid)?;
+        Fees::Eip1559 {
+            max_priority_fee_per_gas,
+            max_fee_per_gas: next()?.uint().ok_or_else(invalid)?,
+        }
+    } else {
+        Fees::Legacy {
+            gas_price: next()?.uint().ok_or_else(invalid)?,
+        }
+    };
+    let gas = next()?.u64().ok_or_else(invalid)?;
+    let to = match next()?.bytes().ok_or_else(invalid)? {
+        [] => None,
+        to if to.len() == 20 => Some(Address::from_slice(to)),
+        _ => return Err(invalid()),
+    };
+    let value = next()?.uint().ok_or_else(invalid)?;
+    let data = next()?.bytes().ok_or_else(invalid)?.to_vec();
+    let access_list = if kind == 0 {
+        Vec::new()
+    } else {
+        decode_access_list(next()?).ok_or_else(invalid)?
+    };
+    let v = next()?.u64().ok_or_else(invalid)?;
+    let r = next()?.uint().ok_or_else(invalid)?;
+    let s = next()?.uint().ok_or_else(invalid)?;
+
+    let (chain_id, recovery_id) = match kind {
+        0 if v == 27 || v == 28 => (None, v - 27),
+        0 if v >= 35 => (Some((v - 35) / 2), (v - 35) % 2),
+        0 => return Err("invalid signature".to_owned()),
+        _ if v <= 1 => (chain_id, v),
+        _ => return Err("invalid signature".to_owned()),
+    };
+    let transaction = UnsignedTransaction {
+        chain_id,
+        nonce,
+        fees,
+        gas,
+        to,
+        value,
+        data,
+        access_list,
+    };
+
+    let (mut r_bytes, mut s_bytes) = (H256::zero(), H256::zero());
+    r.to_big_endian(r_bytes.as_bytes_mut());
+    s.to_big_endian(s_bytes.as_bytes_mut());
+    let mut signature = [0; 64];
+    signature[..32].copy_from_slice(r_bytes.as_bytes());
+    signature[32..].copy_from_slice(s_bytes.as_bytes());
+    let from = signing::recover(&transaction.signing_hash(), &signature, recovery_id as i32)
+        .map_err(|_| "invalid signature".to_owned())?;
+
+    Ok(SignedTransaction {
+        transaction,
+        from,
+        hash: H256(keccak256(raw)),
+        v,
+        r: r_bytes,
+        s: s_bytes,
+    })
+}
+
+fn decode_access_list(item: Item) -> Option<AccessList> {
+    match item {
+        Item::List(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Item::List(fields) if fields.len() == 2 => {
+                    let mut fields = fields.into_iter();
+                    let address = match fields.next()?.bytes()? {
+                        address if address.len() == 20 => Address::from_slice(address),
+                        _ => return None,
+                    };
+                    let storage_keys = match fields.next()? {
+                        Item::List(keys) => keys
+                            .into_iter()
+                            .map(|key| match key.bytes()? {
+                                key if key.len() == 32 => Some(H256::from_slice(key)),
+                                _ => None,
+                            })
+                            .collect::<Option<_>>()?,
+                        Item::Bytes(_) => return None,
+                    };
+                    Some(AccessListItem {
+                        address,
+                        storage_keys,
+                    })
+                }
+                _ => None,
+            })
+            .collect(),
+        Item::Bytes(_) => None,
+    }
+}
+
+/// A decoded RLP item.
+#[derive(Debug)]
+enum Item<'a> {
+    Bytes(&'a [u8]),
+    List(Vec<Item<'a>>),
+}
+
+impl<'a> Item<'a> {
+    fn bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Item::Bytes(bytes) => Some(bytes),
+            Item::List(_) => None,
+        }
+    }
+
+    /// Decodes a scalar, rejecting leading zeros.
+    fn uint(&self) -> Option<U256> {
+        let bytes = self.bytes()?;
+        if bytes.len() > 32 || bytes.first() == Some(&0) {
+            return None;
+        }
+        Some(U256::from_big_endian(bytes))
+    }
+
+    fn u64(&self) -> Option<u64> {
+        let value = self.uint()?;
+        if value > U256::from(u64::MAX) {
+            return None;
+        }
+        Some(value.as_u64())
+    }
+}
+
+/// Decodes an RLP item, returning the item and the remaining data.
+fn decode_item(data: &[u8]) -> Option<(Item<'_>, &[u8])> {
+    let prefix = *data.first()?;
+    let (list, offset, len) = match prefix {
+        0x00..=0x7f => return Some((Item::Bytes(&data[..1]), &data[1..])),
+        0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+        0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+        _ => {
+            let (list, len_of_len) = if prefix < 0xc0 {
+                (false, (prefix - 0xb7) as usize)
+            } else {
+                (true, (prefix - 0xf7) as usize)
+            };
+            if len_of_len > 8 {
+                return None;
+            }
+            let len = data
+                .get(1..1 + len_of_len)?
+                .iter()
+                .fold(0u64, |len, byte| (len << 8) | *byte as u64);
+            (list, 1 + len_of_len, len as usize)
+        }
+    };
+
+    let end = offset.checked_add(len)?;
+    let payload = data.get(offset..end)?;
+    let item = if list {
+        let mut items = Vec::new();
+        let mut rest = payload;
+        while !rest.is_empty() {
+            let (item, next) = decode_item(rest)?;
+            items.push(item);
+            rest = next;
+        }
+        Item::List(items)
+    } else {
+        Item::Bytes(payload)
+    };
+    Some((item, &data[end..]))
+}
+
+fn encode_length(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        vec![offset + len as u8]
+    } else {
+        let len = (len as u64).to_be_bytes();
+        let len = &len[len.iter().take_while(|byte| **byte == 0).count()..];
+        let mut encoded = vec![offset + 55 + len.len() as u8];
+        encoded.extend_from_slice(len);
+        encoded
+    }
+}
+
+pub(crate) fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if let [byte] = bytes {
+        if *byte < 0x80 {
+            return vec![*byte];
+        }
+    }
+    let mut encoded = encode_length(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+pub(crate) fn encode_uint(value: U256) -> Vec<u8> {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    encode_bytes(&bytes[bytes.iter().take_while(|byte| **byte == 0).count()..])
+}
+
+pub(crate) fn encode_u64(value: u64) -> Vec<u8> {
+    encode_uint(value.into())
+}
+
+pub(crate) fn encode_list(items: Vec<Vec<u8>>) -> Vec<u8> {
+    let len = items.iter().map(Vec::len).sum();
+    let mut encoded = encode_length(len, 0xc0);
+    for item in items {
+        encoded.extend_from_slice(&item);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_transactions_roundtrip() {
+        let key = PrivateKey::from_raw([1; 32]).unwrap();
+        let legacy = UnsignedTransaction {
+            chain_id: Some(1337),
+            nonce: 1,
+            fees: Fees::Legacy {
+                gas_price: 1_000_000_000.into(),
+            },
+            gas: 21_000,
+            to: Some(Address::repeat_byte(2)),
+            value: 1.into(),
+            data: vec![1, 2, 3],
+            access_list: Vec::new(),
+        };
+        let typed = UnsignedTransaction {
+            fees: Fees::Eip1559 {
+                max_fee_per_gas: 2.into(),
+                max_priority_fee_per_gas: 1.into(),
+            },
+            to: None,
+            data: vec![0xff; 100],
+            access_list: vec![AccessListItem {
+                address: Address::repeat_byte(3),
+                storage_keys: vec![H256::repeat_byte(4)],
+            }],
+            ..legacy.clone()
+        };
+
+        for transaction in vec![legacy, typed] {
+            let raw = transaction.sign(&key);
+            let signed = decode(&raw).unwrap();
+            assert_eq!(signed.transaction, transaction);
+            assert_eq!(signed.from, key.public_address());
+            assert_eq!(signed.hash, H256(keccak256(&raw)));
+        }
+    }
+
+    #[test]
+    fn decodes_web3_signed_transactions() {
+        // Signed with `Accounts::sign_transaction` using the key `[1; 32]`.
+        let key = PrivateKey::from_raw([1; 32]).unwrap();
+        let transaction = web3::types::TransactionParameters {
+            nonce: Some(0.into()),
+            to: Some(Address::repeat_byte(2)),
+            gas: 21_000.into(),
+            gas_price: Some(1.into()),
+            value: 1.into(),
+            chain_id: Some(1337),
+            ..Default::default()
+        };
+        let web3 = web3::Web3::new(crate::test::prelude::TestTransport::new());
+        let signed =
+            futures::executor::block_on(web3.accounts().sign_transaction(transaction, &key))
+                .unwrap();
+
+        let decoded = decode(&signed.raw_transaction.0).unwrap();
+        assert_eq!(decoded.from, key.public_address());
+        assert_eq!(decoded.hash, signed.transaction_hash);
+        assert_eq!(decoded.transaction.chain_id, Some(1337));
+    }
+
+    #[test]
+    fn invalid_transactions_are_rejected() {
+        assert!(decode(&[]).is_err());
+        assert!(decode(&[0x03, 0xc0]).is_err());
+        assert!(decode(&encode_list(vec![encode_u64(1)])).is_err());
+    }
+}
@@ -4,17 +4,22 @@ mod ganache;
 mod geth;
 mod parity;
 pub(crate) mod revert;
+mod rpc;
+
+pub use self::rpc::RpcError;
 
 use crate::transaction::TransactionResult;
-use ethcontract_common::abi::{Error as AbiError, Event, Function};
+use ethcontract_common::abi::{Error as AbiError, Event, Function, ParamType, Token};
 use ethcontract_common::abiext::EventExt;
 pub use ethcontract_common::errors::*;
+use ethcontract_common::hash::H32;
 use secp256k1::Error as Secp256k1Error;
 use std::num::ParseIntError;
+use std::time::Duration;
 use thiserror::Error;
 use uint::FromDecStrErr;
 use web3::error::Error as Web3Error;
-use web3::types::{Log, TransactionReceipt, H256};
+use web3::types::{Address, Log, TransactionReceipt, H256, U256};
 
 /// Error that can occur while locating a deployed contract.
 #[derive(Debug, Error)]
@@ -49,6 +54,75 @@ pub enum DeployError {
     /// address cannot be determined.
     #[error("contract deployment transaction pending: {0}")]
     Pending(H256),
+
+    /// The runtime code of the contract exceeds the maximum code size of the
+    /// chain, which is 24576 bytes as specified by EIP-170 on most chains.
+    #[error("contract code size {size} exceeds the limit of {limit} bytes")]
+    CodeSizeExceeded {
+        /// The runtime code size of the contract.
+        size: usize,
+        /// The maximum runtime code size.
+        limit: usize,
+    },
+
+    /// The init code of the deployment transaction, which is the contract
+    /// bytecode followed by the constructor parameters, exceeds the maximum
+    /// init code size of the chain, which is 49152 bytes as specified by
+    /// EIP-3860 on most chains.
+    #[error("contract init code size {size} exceeds the limit of {limit} bytes")]
+    InitCodeSizeExceeded {
+        /// The init code size of the deployment transaction.
+        size: usize,
+        /// The maximum init code size.
+        limit: usize,
+    },
+
+    /// The chain ID reported by the `web3` provider does not match the chain
+    /// the contract was expected on.
+    #[error("expected contract on chain {expected} but connected to chain {actual}")]
+    ChainMismatch {
+        /// The expected chain ID.
+        expected: u64,
+        /// The chain ID reported by the `web3` provider.
+        actual: u64,
+    },
+
+    /// There is no contract code at the address of the contract.
+    #[error("no contract code at address {0:?}")]
+    NoCode(Address),
+}
+
+/// Error that can occur when retrieving a contract from a chain registry.
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    /// No provider is registered for the chain.
+    #[error("no provider registered for chain {0}")]
+    UnknownChain(u64),
+
+    /// No address is registered for the named contract on the chain.
+    #[error("contract '{1}' is not registered on chain {0}")]
+    UnknownContract(u64, String),
+}
+
+/// Error that can occur when parsing an address for a chain.
+#[derive(Clone, Debug, Error)]
+pub enum ChainAddressError {
+    /// The address is invalid.
+    #[error(transparent)]
+    Address(#[from] ParseAddressError),
+
+    /// No configuration is available for the chain.
+    #[error("no configuration for chain {0}")]
+    UnknownChain(u64),
+
+    /// The ERC-3770 chain prefix of the address does not match the chain.
+    #[error("address with chain prefix '{short_name}' can not be used on chain {chain_id}")]
+    ChainMismatch {
+        /// The ID of the chain the address is used on.
+        chain_id: u64,
+        /// The short name in the chain prefix of the address.
+        short_name: String,
+    },
 }
 
 /// Error that can occur while executing a contract call or transaction.
@@ -63,6 +137,10 @@ pub enum ExecutionError {
     #[error("abi decode error: {0}")]
     AbiDecode(#[from] AbiError),
 
+    /// The result of a contract method call or a log is malformed.
+    #[error("decode error: {0}")]
+    Decode(#[from] DecodeError),
+
     /// An error occured while parsing chain ID received from a Web3 call.
     #[error("parse chain ID error: {0}")]
     Parse(#[from] ParseIntError),
@@ -97,6 +175,10 @@ pub enum ExecutionError {
     #[error("pending transaction {0:?}, not yet part of a block")]
     PendingTransaction(H256),
 
+    /// The node did not return a mined latest block.
+    #[error("latest block not found")]
+    MissingBlock,
+
     /// A removed log was received when querying past logs.
     #[error("unexepected removed log when querying past logs")]
     RemovedLog(Box<Log>),
@@ -109,9 +191,73 @@ pub enum ExecutionError {
     #[error("tokenization error: {0}")]
     Tokenization(#[from] crate::tokens::Error),
 
+    /// An operation did not complete within the specified timeout.
+    #[error("operation timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// A transaction was rejected by its `TransactionGuard` before it was
+    /// signed.
+    #[error("transaction rejected by guard: {0}")]
+    Rejected(String),
+
+    /// An error occured while signing a transaction with an external signer.
+    #[error("signer error: {0}")]
+    Signer(#[from] SignerError),
+
+    /// The transaction has extension fields but is signed offline or with an
+    /// external signer, which only support standard transaction envelopes.
+    #[error("transaction extensions are only supported for transactions signed by the node or a remote signer")]
+    UnsupportedExtensions,
+
+    /// A contract call returned more data than the maximum return data size
+    /// configured for the call.
+    #[error("call returned {0} bytes of data which exceeds the limit of {1} bytes")]
+    ReturnDataTooLarge(usize, usize),
+
     /// Unexpected transaction hash
     #[error("transaction hash returned from node when sending raw transaction does not match expected hash")]
     UnexpectedTransactionHash,
+
+    /// The transaction failed its preflight checks against the latest block.
+    #[error("transaction preflight check failed: {0}")]
+    Preflight(#[from] PreflightError),
+
+    /// A locally simulated transaction is invalid and could not be executed,
+    /// for example because its sender cannot pay for it.
+    #[error("invalid transaction: {0}")]
+    InvalidTransaction(String),
+
+    /// The account cannot sign EIP-712 typed data. This is the case for
+    /// locked accounts, since nodes only sign typed data for unlocked
+    /// accounts.
+    #[error("account {0:?} cannot sign typed data")]
+    TypedDataUnsupported(Address),
+
+    /// The account cannot sign EIP-7702 authorizations. Only accounts with a
+    /// private key or an external signer can sign authorizations.
+    #[error("account {0:?} cannot sign authorizations")]
+    AuthorizationUnsupported(Address),
+
+    /// A set code transaction has no receiver. Set code transactions cannot
+    /// be used to deploy contracts.
+    #[error("set code transaction has no receiver")]
+    MissingReceiver,
+
+    /// A blob transaction or its sidecar is invalid.
+    #[error("invalid blob transaction: {0}")]
+    Blob(#[from] BlobError),
+
+    /// An offchain lookup requested by a contract call failed.
+    #[error("offchain lookup failed: {0}")]
+    Ccip(#[from] CcipError),
+
+    /// Recording a transaction in its journal failed.
+    #[error("transaction journal error: {0}")]
+    Journal(#[from] JournalError),
+
+    /// Collecting signatures for a multi-signature failed.
+    #[error("signature collection error: {0}")]
+    Multisig(#[from] MultisigError),
 }
 
 impl From<Web3Error> for ExecutionError {
@@ -132,6 +278,25 @@ impl From<Web3Error> for ExecutionError {
     }
 }
 
+impl ExecutionError {
+    /// Returns the cause of the error if it was caused by a failed JSON RPC
+    /// request with a known cause, such as a rate limited request or a
+    /// transaction with a stale nonce.
+    ///
+    /// Reverts that were decoded into `ExecutionError::Revert` are reported as
+    /// `RpcError::ExecutionReverted` without revert data.
+    pub fn rpc_error(&self) -> Option<RpcError> {
+        match self {
+            ExecutionError::Web3(err) => RpcError::from_web3(err),
+            ExecutionError::Revert(reason) => Some(RpcError::ExecutionReverted {
+                reason: reason.clone(),
+                data: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
 /// Error that can occur while executing a contract call or transaction.
 #[derive(Debug, Error)]
 #[error("method '{signature}' failure: {inner}")]
@@ -155,6 +320,12 @@ impl MethodError {
     pub fn from_parts(signature: String, inner: ExecutionError) -> Self {
         MethodError { signature, inner }
     }
+
+    /// Returns the cause of the error if it was caused by a failed JSON RPC
+    /// request with a known cause. See [`ExecutionError::rpc_error`].
+    pub fn rpc_error(&self) -> Option<RpcError> {
+        self.inner.rpc_error()
+    }
 }
 
 /// Error that can occur while streaming contract events.
@@ -182,6 +353,455 @@ impl EventError {
     }
 }
 
+/// Error that can occur while encoding or decoding data with the signature
+/// based utilities from the `abi` module.
+#[derive(Debug, Error)]
+pub enum AbiCodecError {
+    /// The function or event signature could not be parsed.
+    #[error(transparent)]
+    Signature(#[from] ParseSignatureError),
+
+    /// The number of tokens does not match the number of parameters.
+    #[error("expected {expected} tokens but got {actual}")]
+    TokenCount {
+        /// The number of parameters in the signature.
+        expected: usize,
+        /// The number of tokens that were provided.
+        actual: usize,
+    },
+
+    /// A token does not match its corresponding parameter type.
+    #[error("token {token} does not match parameter type '{kind}'")]
+    TypeMismatch {
+        /// The expected parameter type.
+        kind: ParamType,
+        /// The token that was provided.
+        token: Token,
+    },
+
+    /// Calldata does not start with the selector of the function signature.
+    #[error("calldata does not match selector 0x{}", hex::encode(.0))]
+    SelectorMismatch(H32),
+
+    /// An event log does not have the topic of the event signature.
+    #[error("log does not match event topic {0:?}")]
+    TopicMismatch(H256),
+
+    /// A parameter type cannot be used with `abi.encodePacked`.
+    #[error("type '{0}' is not supported in packed encoding")]
+    UnsupportedPackedType(ParamType),
+
+    /// An error occured while ABI encoding or decoding parameters.
+    #[error("abi error: {0}")]
+    Abi(#[from] AbiError),
+
+    /// The decoded data is malformed.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Error that can occur while decoding malformed ABI encoded data. Offsets are
+/// byte offsets in the decoded data.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum DecodeError {
+    /// A value extends past the end of the data, for example because the
+    /// data is truncated.
+    #[error("{len} bytes at offset {offset} are out of bounds of {size} bytes of data")]
+    OutOfBounds {
+        /// The offset of the value.
+        offset: usize,
+        /// The length of the value.
+        len: usize,
+        /// The size of the data.
+        size: usize,
+    },
+
+    /// An offset or length is too large for the data.
+    #[error("invalid offset or length at offset {0}")]
+    InvalidLength(usize),
+
+    /// A boolean is not encoded as a 0 or 1 word.
+    #[error("invalid boolean at offset {0}")]
+    InvalidBool(usize),
+
+    /// A string is not valid UTF-8 with strict UTF-8 decoding.
+    #[error("invalid UTF-8 string at offset {0}")]
+    InvalidUtf8(usize),
+
+    /// The arrays of the data have more elements in total than the data has
+    /// words, which is only possible if arrays share their encoding.
+    #[error("array at offset {0} has more elements than the data can encode")]
+    TooManyElements(usize),
+
+    /// The topics of a log do not match the event signature and indexed
+    /// parameters.
+    #[error("log topics do not match the event")]
+    InvalidTopics,
+}
+
+/// Error that can occur while looking up signatures in a signature database.
+#[derive(Debug, Error)]
+pub enum SignatureLookupError {
+    /// The request to a remote signature database failed.
+    #[cfg(feature = "signature-db")]
+    #[error("signature database request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    /// The signature database returned an unexpected response.
+    #[error("invalid signature database response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Error that can occur while querying the GraphQL API of a node.
+#[derive(Debug, Error)]
+pub enum GraphQlError {
+    /// The HTTP request to the GraphQL endpoint failed.
+    #[cfg(feature = "http")]
+    #[error("GraphQL request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    /// The node does not expose a GraphQL API.
+    #[error("node does not support GraphQL")]
+    Unsupported,
+
+    /// The query failed with the specified errors.
+    #[error("GraphQL query failed: {0}")]
+    Query(String),
+
+    /// The node returned an invalid GraphQL response.
+    #[error("invalid GraphQL response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Error that can occur while creating EIP-4844 blob transactions.
+#[derive(Debug, Error)]
+pub enum BlobError {
+    /// The raw bytes of a blob do not have the size of a blob.
+    #[error("blob has {0} bytes instead of 131072 bytes")]
+    InvalidBlobSize(usize),
+
+    /// A blob sidecar does not contain any blobs.
+    #[error("blob transactions require at least one blob")]
+    NoBlobs,
+
+    /// The number of commitments or proofs of a blob sidecar does not match
+    /// the number of blobs.
+    #[error("sidecar has {blobs} blobs but {commitments} commitments and {proofs} proofs")]
+    SidecarMismatch {
+        /// The number of blobs.
+        blobs: usize,
+        /// The number of commitments.
+        commitments: usize,
+        /// The number of proofs.
+        proofs: usize,
+    },
+
+    /// The versioned hashes of a transaction do not match its sidecar.
+    #[error("blob versioned hashes do not match the sidecar")]
+    VersionedHashMismatch,
+
+    /// The KZG implementation failed to compute a commitment or proof.
+    #[error("KZG computation failed: {0}")]
+    Kzg(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Error that can occur while recording or recovering journaled transactions.
+#[derive(Debug, Error)]
+pub enum JournalError {
+    /// Reading or writing the journal file failed.
+    #[error("failed to access journal file: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A journal entry could not be serialized or deserialized.
+    #[error("failed to serialize journal entry: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    /// The journal entry with the specified one-based index is malformed.
+    #[error("journal entry {0} is malformed")]
+    Corrupt(usize),
+
+    /// The journal database failed.
+    #[error("journal database error: {0}")]
+    Database(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The journaled transaction has no known nonce, so it can't be replaced.
+    #[error("journaled transaction {0:?} has no known nonce")]
+    UnknownNonce(H256),
+
+    /// The replacement of a journaled transaction is not sent by the sender
+    /// of the transaction.
+    #[error("replacement must be sent by {0:?}")]
+    SenderMismatch(Address),
+}
+
+/// Error that can occur while collecting and packing the signatures of a
+/// multi-signature.
+#[derive(Debug, Error)]
+pub enum MultisigError {
+    /// The signature is not a 65-byte `r || s || v` signature.
+    #[error("signature is {0} bytes long instead of 65 bytes")]
+    InvalidLength(usize),
+
+    /// The signer could not be recovered from the signature.
+    #[error("signer could not be recovered from signature")]
+    InvalidSignature,
+
+    /// The recovered signer is not the expected signer.
+    #[error("signature is from {recovered:?} instead of {expected:?}")]
+    UnexpectedSigner {
+        /// The expected signer.
+        expected: Address,
+        /// The signer recovered from the signature.
+        recovered: Address,
+    },
+
+    /// The signer already signed the message.
+    #[error("duplicate signature from {0:?}")]
+    DuplicateSigner(Address),
+
+    /// The signatures of two sets are for different messages.
+    #[error("signatures are for different messages")]
+    HashMismatch,
+
+    /// There are fewer signatures than required.
+    #[error("{signatures} signatures do not meet the threshold of {threshold}")]
+    BelowThreshold {
+        /// The number of collected signatures.
+        signatures: usize,
+        /// The number of required signatures.
+        threshold: usize,
+    },
+}
+
+/// Error that can occur while performing an EIP-3668 offchain lookup.
+#[derive(Debug, Error)]
+pub enum CcipError {
+    /// The HTTP request to the gateway failed.
+    #[cfg(feature = "http")]
+    #[error("gateway request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    /// The gateway responded with an error status.
+    #[error("gateway responded with status {status}: {message}")]
+    Gateway {
+        /// The HTTP status of the response.
+        status: u16,
+        /// The error message of the response.
+        message: String,
+    },
+
+    /// The gateway returned an invalid response.
+    #[error("invalid gateway response: {0}")]
+    InvalidResponse(String),
+
+    /// The lookup was requested by a different contract than the one that was
+    /// called, which is not allowed as the callback is called on the sender.
+    #[error("offchain lookup sender {0:?} does not match the called contract")]
+    SenderMismatch(Address),
+
+    /// The call requested more offchain lookups than allowed.
+    #[error("call exceeded the limit of {0} offchain lookups")]
+    TooManyLookups(usize),
+
+    /// The lookup does not specify any gateway URLs.
+    #[error("offchain lookup does not specify any gateways")]
+    NoGateways,
+
+    /// Offchain lookups are disabled.
+    #[error("offchain lookups are disabled")]
+    Disabled,
+}
+
+impl CcipError {
+    /// Returns `true` if the next gateway should be tried after this error,
+    /// which is the case for failed requests and server errors. Gateways
+    /// responding with client errors are not retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(feature = "http")]
+            CcipError::Request(_) => true,
+            CcipError::Gateway { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// Error that can occur while saving or loading transport fixtures.
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    /// The fixture file could not be read or written.
+    #[error("failed to access fixture file: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The fixture is not valid JSON.
+    #[error("invalid fixture: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Error that can occur while retrieving token metadata.
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    /// The call to retrieve the token URI failed.
+    #[error(transparent)]
+    Method(#[from] MethodError),
+
+    /// The contract does not implement `tokenURI` or `uri`.
+    #[error("contract does not implement `tokenURI(uint256)` or `uri(uint256)`")]
+    Unsupported,
+
+    /// The token URI is invalid.
+    #[error("invalid token URI '{0}'")]
+    InvalidUri(String),
+
+    /// The content fetcher failed to retrieve the metadata.
+    #[error("failed to fetch token metadata: {0}")]
+    Fetch(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The metadata could not be retrieved from IPFS.
+    #[error(transparent)]
+    Ipfs(#[from] IpfsError),
+
+    /// The HTTP request for the metadata failed.
+    #[cfg(feature = "nft-metadata")]
+    #[error("token metadata request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    /// The metadata is not valid JSON.
+    #[error("invalid token metadata: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Error that can occur while writing events to a sink.
+#[cfg(feature = "sinks")]
+#[derive(Debug, Error)]
+pub enum SinkError {
+    /// The event stream failed.
+    #[error(transparent)]
+    Event(#[from] EventError),
+
+    /// The event data could not be serialized to columns.
+    #[error("failed to serialize event data: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    /// The event is pending and can't be mapped to a row since it has no
+    /// identifier.
+    #[error("pending events can't be written to sinks")]
+    Pending,
+
+    /// The sink failed to write rows.
+    #[error("failed to write rows to sink: {0}")]
+    Write(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Error that can occur while upgrading a proxy or beacon.
+#[derive(Debug, Error)]
+pub enum UpgradeError {
+    /// A call or transaction for the upgrade failed.
+    #[error(transparent)]
+    Method(#[from] MethodError),
+
+    /// Reading the state of the proxy or implementation failed.
+    #[error(transparent)]
+    Execution(#[from] ExecutionError),
+
+    /// The contract is not an EIP-1967 proxy, since its implementation slot is
+    /// empty.
+    #[error("contract {0:?} is not an EIP-1967 proxy")]
+    NotProxy(Address),
+
+    /// The new implementation address has no code.
+    #[error("implementation {0:?} is not a contract")]
+    NotContract(Address),
+
+    /// The code of the new implementation does not match the expected code.
+    #[error("code of implementation {0:?} does not match the expected code")]
+    CodeMismatch(Address),
+
+    /// The new implementation of a UUPS proxy does not return the EIP-1967
+    /// implementation slot from `proxiableUUID()`, so upgrading to it would
+    /// fail or leave the proxy without a way to upgrade.
+    #[error("implementation {0:?} is not a UUPS implementation")]
+    NotProxiable(Address),
+
+    /// The implementation was not updated by the upgrade transaction.
+    #[error("implementation is {actual:?} after the upgrade instead of {expected:?}")]
+    NotUpgraded {
+        /// The new implementation.
+        expected: Address,
+        /// The implementation after the upgrade.
+        actual: Address,
+    },
+}
+
+/// Error that can occur while signing with an external signer.
+#[derive(Debug, Error)]
+pub enum SignerError {
+    /// The signing service failed to sign.
+    #[error("signing service error: {0}")]
+    Service(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The signing service returned an invalid signature, or a signature that
+    /// does not match the signing account.
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    /// The public key of the signing account is invalid.
+    #[error("invalid public key")]
+    InvalidPublicKey,
+}
+
+/// Error that can occur when checking a transaction against the latest block
+/// before sending it.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum PreflightError {
+    /// The gas limit of the transaction exceeds the gas limit of the latest
+    /// block, so the transaction can never be mined.
+    #[error("gas {gas} exceeds the block gas limit {block_gas_limit}")]
+    ExceedsBlockGasLimit {
+        /// The gas limit of the transaction.
+        gas: U256,
+        /// The gas limit of the latest block.
+        block_gas_limit: U256,
+    },
+
+    /// The maximum fee per gas of the transaction is below the base fee of the
+    /// next block, so the transaction cannot be included in it.
+    #[error("max fee per gas {max_fee} is below the base fee {base_fee}")]
+    FeeBelowBaseFee {
+        /// The maximum fee per gas of the transaction.
+        max_fee: U256,
+        /// The base fee per gas of the next block.
+        base_fee: U256,
+    },
+
+    /// The balance of the sender does not cover the value and the maximum
+    /// fees of the transaction.
+    #[error("insufficient funds: transaction requires {required} but sender only has {available}")]
+    InsufficientFunds {
+        /// The value plus maximum fees of the transaction.
+        required: U256,
+        /// The balance of the sender in the latest block.
+        available: U256,
+    },
+}
+
+/// Error that can occur while parsing a decimal token amount.
+#[derive(Clone, Debug, Error)]
+pub enum ParseUnitsError {
+    /// The amount is not a valid decimal number.
+    #[error("'{0}' is not a valid decimal amount")]
+    InvalidNumber(String),
+
+    /// The amount has more fractional digits than the number of decimals,
+    /// and would lose precision.
+    #[error("amount has more than {0} decimals")]
+    TooManyDecimals(u32),
+
+    /// The amount does not fit in a 256-bit integer.
+    #[error("amount does not fit in 256-bit integer")]
+    Overflow,
+}
+
 /// An error indicating an invalid private key. Private keys for secp256k1 must
 /// be exactly 32 bytes and fall within the range `[1, n)` where `n` is the
 /// order of the generator point of the curve.
@@ -260,6 +880,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rpc_error_of_execution_errors() {
+        let web3_err = Web3Error::Rpc(jsonrpc_core::Error {
+            code: (-32000).into(),
+            message: "nonce too low".to_owned(),
+            data: None,
+        });
+        let err = MethodError::from_parts("foo()".to_owned(), ExecutionError::from(web3_err));
+        assert_eq!(err.rpc_error(), Some(RpcError::NonceTooLow));
+
+        let err = ExecutionError::from(Web3Error::Rpc(geth_revert("execution reverted: failed")));
+        assert_eq!(
+            err.rpc_error(),
+            Some(RpcError::ExecutionReverted {
+                reason: Some("failed".to_owned()),
+                data: None,
+            })
+        );
+        assert_eq!(ExecutionError::InvalidOpcode.rpc_error(), None);
+    }
+
+    fn geth_revert(message: &str) -> jsonrpc_core::Error {
+        jsonrpc_core::Error {
+            code: 3.into(),
+            message: message.to_owned(),
+            data: None,
+        }
+    }
+
     #[test]
     fn all_errors_are_boxable_errors() {
         fn assert_boxable_error<T: Error + Send + Sync + 'static>() {}
@@ -1,6 +1,7 @@
 //! Module implements decoding ABI encoded revert reasons.
 
-use ethcontract_common::abi::{self, ParamType};
+use crate::abi;
+use ethcontract_common::abi::ParamType;
 use ethcontract_common::hash::{self, H32};
 use lazy_static::lazy_static;
 
@@ -22,10 +23,9 @@ pub fn decode_reason(bytes: &[u8]) -> Option<String> {
         return None;
     }
 
-    let reason = abi::decode(&[ParamType::String], &bytes[4..])
+    let reason = abi::try_decode(&[ParamType::String], &bytes[4..])
         .ok()?
-        .pop()
-        .expect("decoded single parameter will yield single token")
+        .pop()?
         .to_string();
 
     Some(reason)
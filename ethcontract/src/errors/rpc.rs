@@ -0,0 +1,338 @@
+//! This module implements a node agnostic classification of JSON RPC errors
+//! based on their error codes and messages, so that applications can branch on
+//! the cause of a failure without matching on error strings.
+
+use crate::errors::revert;
+use jsonrpc_core::Error as JsonrpcError;
+use serde_json::Value;
+use thiserror::Error;
+use web3::error::Error as Web3Error;
+use web3::types::Bytes;
+
+/// JSON RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON RPC error code used by Geth for reverted calls.
+const EXECUTION_ERROR: i64 = 3;
+/// EIP-1193 error code for requests that were rejected by the user.
+const USER_REJECTED: i64 = 4001;
+/// EIP-1193 error code for methods that are not supported by the provider.
+const UNSUPPORTED_METHOD: i64 = 4200;
+
+/// The cause of a failed JSON RPC request, as reported by the node or the
+/// provider.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum RpcError {
+    /// The provider rejected the request because a rate or usage limit was
+    /// exceeded. The request can be retried later.
+    #[error("request was rate limited")]
+    RateLimited,
+
+    /// The node does not support the requested method.
+    #[error("method not found")]
+    MethodNotFound,
+
+    /// The node side filter does not exist, for example because it expired.
+    #[error("filter not found")]
+    FilterNotFound,
+
+    /// The user rejected the request in their wallet.
+    #[error("request rejected by the user")]
+    UserRejected,
+
+    /// The call or transaction reverted, with the decoded revert reason and
+    /// the raw revert data when provided by the node.
+    #[error("execution reverted with reason {reason:?}")]
+    ExecutionReverted {
+        /// The revert reason, if any.
+        reason: Option<String>,
+        /// The raw revert data, for example an ABI encoded custom error.
+        data: Option<Bytes>,
+    },
+
+    /// The transaction nonce is lower than the account nonce, usually because
+    /// another transaction with the same nonce was already mined.
+    #[error("nonce too low")]
+    NonceTooLow,
+
+    /// The transaction nonce is too far ahead of the account nonce.
+    #[error("nonce too high")]
+    NonceTooHigh,
+
+    /// A pending transaction with the same nonce exists and the new
+    /// transaction does not pay enough to replace it.
+    #[error("replacement transaction underpriced")]
+    ReplacementUnderpriced,
+
+    /// The transaction is already in the transaction pool.
+    #[error("transaction already known")]
+    AlreadyKnown,
+
+    /// The account can not pay for the value and fees of the transaction.
+    #[error("insufficient funds")]
+    InsufficientFunds,
+
+    /// The gas price or fees of the transaction are below the minimum accepted
+    /// by the node or the current base fee.
+    #[error("transaction fees too low")]
+    FeeTooLow,
+
+    /// The gas limit of the transaction is lower than its intrinsic gas.
+    #[error("intrinsic gas too low")]
+    IntrinsicGasTooLow,
+
+    /// The gas limit of the transaction exceeds the block gas limit.
+    #[error("gas limit exceeds block gas limit")]
+    GasLimitExceeded,
+}
+
+/// Message fragments of rate limiting errors from common providers.
+const RATE_LIMITED: &[&str] = &[
+    "rate limit",
+    "too many requests",
+    "request rate exceeded",
+    "request count exceeded",
+    "exceeded its compute units",
+    "capacity exceeded",
+];
+
+impl RpcError {
+    /// Classifies a JSON RPC error. Returns `None` when the cause of the error
+    /// can not be determined.
+    pub fn from_jsonrpc(err: &JsonrpcError) -> Option<Self> {
+        let message = err.message.to_lowercase();
+        let contains = |fragments: &[&str]| fragments.iter().any(|f| message.contains(f));
+
+        let error = if err.code.code() == METHOD_NOT_FOUND
+            || err.code.code() == UNSUPPORTED_METHOD
+            || (message.contains("method")
+                && contains(&[
+                    "not found",
+                    "not supported",
+                    "does not exist",
+                    "not available",
+                ])) {
+            RpcError::MethodNotFound
+        } else if message.contains("filter") && contains(&["not found", "does not exist"]) {
+            RpcError::FilterNotFound
+        } else if err.code.code() == USER_REJECTED || contains(&["user rejected", "user denied"]) {
+            RpcError::UserRejected
+        } else if contains(RATE_LIMITED) {
+            RpcError::RateLimited
+        } else if err.code.code() == EXECUTION_ERROR || message.starts_with("execution reverted") {
+            revert_error(err)
+        } else if contains(&[
+            "nonce too low",
+            "nonce is too low",
+            "nonce has already been used",
+        ]) {
+            RpcError::NonceTooLow
+        } else if contains(&["nonce too high", "nonce is too high"]) {
+            RpcError::NonceTooHigh
+        } else if contains(&[
+            "replacement transaction underpriced",
+            "another transaction with same nonce",
+        ]) {
+            RpcError::ReplacementUnderpriced
+        } else if contains(&[
+            "already known",
+            "known transaction",
+            "transaction already imported",
+        ]) {
+            RpcError::AlreadyKnown
+        } else if contains(&["insufficient funds"]) {
+            RpcError::InsufficientFunds
+        } else if contains(&["intrinsic gas too low"]) {
+            RpcError::IntrinsicGasTooLow
+        } else if contains(&["exceeds block gas limit"]) {
+            RpcError::GasLimitExceeded
+        } else if contains(&[
+            "transaction underpriced",
+            "less than block base fee",
+            "gas price too low",
+            "gas price supplied is too low",
+            "fee too low",
+        ]) {
+            RpcError::FeeTooLow
+        } else {
+            return None;
+        };
+        Some(error)
+    }
+
+    /// Classifies a `web3` error. In addition to JSON RPC errors, this detects
+    /// HTTP 429 responses of rate limiting providers.
+    pub fn from_web3(err: &Web3Error) -> Option<Self> {
+        match err {
+            Web3Error::Rpc(err) => RpcError::from_jsonrpc(err),
+            Web3Error::Transport(message) => {
+                let message = message.to_lowercase();
+                if message.contains("429") || RATE_LIMITED.iter().any(|f| message.contains(f)) {
+                    Some(RpcError::RateLimited)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the request may succeed when retried later without
+    /// any changes.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, RpcError::RateLimited)
+    }
+}
+
+/// Creates a revert error from a JSON RPC error, decoding the revert reason
+/// from the revert data or the error message.
+fn revert_error(err: &JsonrpcError) -> RpcError {
+    let data = err
+        .data
+        .as_ref()
+        .and_then(|data| match data {
+            Value::Object(object) => object.get("data"),
+            data => Some(data),
+        })
+        .and_then(Value::as_str)
+        .and_then(|data| hex::decode(data.strip_prefix("0x").unwrap_or(data)).ok())
+        .map(Bytes);
+    let reason = data
+        .as_ref()
+        .and_then(|data| revert::decode_reason(&data.0))
+        .or_else(|| {
+            err.message
+                .strip_prefix("execution reverted: ")
+                .map(ToString::to_string)
+        });
+    RpcError::ExecutionReverted { reason, data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    fn rpc_error(code: i64, message: &str) -> JsonrpcError {
+        JsonrpcError {
+            code: code.into(),
+            message: message.to_owned(),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn classify_errors() {
+        for (code, message, expected) in vec![
+            (
+                -32601,
+                "the method eth_foo does not exist/is not available",
+                Some(RpcError::MethodNotFound),
+            ),
+            (
+                -32000,
+                "method not supported",
+                Some(RpcError::MethodNotFound),
+            ),
+            (-32000, "filter not found", Some(RpcError::FilterNotFound)),
+            (
+                4001,
+                "User rejected the request.",
+                Some(RpcError::UserRejected),
+            ),
+            (
+                -32603,
+                "MetaMask Tx Signature: User denied transaction signature.",
+                Some(RpcError::UserRejected),
+            ),
+            (
+                -32005,
+                "project ID request rate exceeded",
+                Some(RpcError::RateLimited),
+            ),
+            (
+                429,
+                "Your app has exceeded its compute units per second capacity",
+                Some(RpcError::RateLimited),
+            ),
+            (-32000, "nonce too low", Some(RpcError::NonceTooLow)),
+            (
+                -32010,
+                "Transaction nonce is too low. Try incrementing the nonce.",
+                Some(RpcError::NonceTooLow),
+            ),
+            (-32000, "nonce too high", Some(RpcError::NonceTooHigh)),
+            (
+                -32000,
+                "replacement transaction underpriced",
+                Some(RpcError::ReplacementUnderpriced),
+            ),
+            (-32000, "already known", Some(RpcError::AlreadyKnown)),
+            (
+                -32000,
+                "insufficient funds for gas * price + value",
+                Some(RpcError::InsufficientFunds),
+            ),
+            (-32000, "transaction underpriced", Some(RpcError::FeeTooLow)),
+            (
+                -32000,
+                "max fee per gas less than block base fee",
+                Some(RpcError::FeeTooLow),
+            ),
+            (
+                -32000,
+                "intrinsic gas too low",
+                Some(RpcError::IntrinsicGasTooLow),
+            ),
+            (
+                -32000,
+                "exceeds block gas limit",
+                Some(RpcError::GasLimitExceeded),
+            ),
+            (-32000, "header not found", None),
+        ] {
+            assert_eq!(
+                RpcError::from_jsonrpc(&rpc_error(code, message)),
+                expected,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn classify_reverts() {
+        let data = revert::encode_reason("not enough balance");
+        let mut err = rpc_error(3, "execution reverted: not enough balance");
+        err.data = Some(json!(format!("0x{}", hex::encode(&data))));
+        assert_eq!(
+            RpcError::from_jsonrpc(&err),
+            Some(RpcError::ExecutionReverted {
+                reason: Some("not enough balance".to_owned()),
+                data: Some(Bytes(data)),
+            })
+        );
+
+        let mut err = rpc_error(-32603, "execution reverted");
+        err.data = Some(json!({ "message": "reverted", "data": "0xdeadbeef" }));
+        assert_eq!(
+            RpcError::from_jsonrpc(&err),
+            Some(RpcError::ExecutionReverted {
+                reason: None,
+                data: Some(Bytes(vec![0xde, 0xad, 0xbe, 0xef])),
+            })
+        );
+    }
+
+    #[test]
+    fn classify_http_rate_limits() {
+        let err = Web3Error::Transport(
+            "response status code is not success: 429 Too Many Requests".to_owned(),
+        );
+        assert_eq!(RpcError::from_web3(&err), Some(RpcError::RateLimited));
+        assert!(RpcError::RateLimited.is_retryable());
+        assert_eq!(
+            RpcError::from_web3(&Web3Error::Transport("connection refused".to_owned())),
+            None
+        );
+    }
+}
@@ -0,0 +1,688 @@
+//! A minimal EVM interpreter for executing transactions in process, used by
+//! the development node in [`crate::dev`].
+//!
+//! The interpreter implements the instruction set and gas schedule of the
+//! Cancun hard fork, with the exception of blob transactions. Of the
+//! precompiled contracts, only `ecrecover` and `identity` are supported; calls
+//! to the other precompiles fail.
+
+mod interpreter;
+mod state;
+
+pub(crate) use self::state::{AccountChange, AccountInfo, Database, Log};
+
+use self::interpreter::Frame;
+use self::state::Journal;
+use primitive_types::U512;
+use std::sync::Arc;
+use web3::signing::{self, keccak256};
+use web3::types::{Address, H256, U256};
+
+/// The maximum call depth.
+const MAX_DEPTH: usize = 1024;
+
+/// The maximum size of deployed code, as defined by EIP-170.
+const MAX_CODE_SIZE: usize = 0x6000;
+
+/// The maximum size of init code, as defined by EIP-3860.
+const MAX_INIT_CODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// The number of precompiled contracts, which are at the addresses `1`
+/// through `10`.
+const PRECOMPILES: u64 = 10;
+
+/// The block and transaction context of an execution.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Env {
+    pub chain_id: u64,
+    pub number: u64,
+    pub timestamp: u64,
+    pub coinbase: Address,
+    pub gas_limit: u64,
+    pub base_fee: U256,
+    pub prevrandao: H256,
+}
+
+/// A transaction to execute.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Transaction {
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub gas_limit: u64,
+    /// The price paid per unit of gas, including the priority fee.
+    pub gas_price: U256,
+    /// The nonce of the transaction, or `None` to skip nonce checks for calls.
+    pub nonce: Option<u64>,
+    pub access_list: Vec<(Address, Vec<H256>)>,
+}
+
+/// Reasons for which a transaction cannot be included in a block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum InvalidTransaction {
+    NonceTooLow(u64, u64),
+    NonceTooHigh(u64, u64),
+    InsufficientFunds,
+    IntrinsicGasTooLow(u64),
+    GasLimitExceeded,
+    InitCodeTooLarge,
+}
+
+impl std::fmt::Display for InvalidTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InvalidTransaction::NonceTooLow(nonce, expected) => {
+                write!(f, "nonce too low: nonce {}, expected {}", nonce, expected)
+            }
+            InvalidTransaction::NonceTooHigh(nonce, expected) => {
+                write!(f, "nonce too high: nonce {}, expected {}", nonce, expected)
+            }
+            InvalidTransaction::InsufficientFunds => {
+                f.write_str("insufficient funds for gas * price + value")
+            }
+            InvalidTransaction::IntrinsicGasTooLow(gas) => {
+                write!(f, "intrinsic gas too low: requires {}", gas)
+            }
+            InvalidTransaction::GasLimitExceeded => f.write_str("exceeds block gas limit"),
+            InvalidTransaction::InitCodeTooLarge => f.write_str("max initcode size exceeded"),
+        }
+    }
+}
+
+/// Reasons for which execution halts exceptionally, consuming all gas.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Halt {
+    OutOfGas,
+    StackUnderflow,
+    StackOverflow,
+    InvalidJump,
+    InvalidOpcode(u8),
+    StaticCallViolation,
+    ReturnDataOutOfBounds,
+    CreateCollision,
+    CodeSizeLimit,
+    InvalidCode,
+    UnsupportedPrecompile(u64),
+}
+
+impl std::fmt::Display for Halt {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Halt::OutOfGas => f.write_str("out of gas"),
+            Halt::StackUnderflow => f.write_str("stack underflow"),
+            Halt::StackOverflow => f.write_str("stack limit reached"),
+            Halt::InvalidJump => f.write_str("invalid jump destination"),
+            Halt::InvalidOpcode(opcode) => write!(f, "invalid opcode: 0x{:02x}", opcode),
+            Halt::StaticCallViolation => f.write_str("write protection"),
+            Halt::ReturnDataOutOfBounds => f.write_str("return data out of bounds"),
+            Halt::CreateCollision => f.write_str("contract address collision"),
+            Halt::CodeSizeLimit => f.write_str("max code size exceeded"),
+            Halt::InvalidCode => f.write_str("invalid code: must not begin with 0xef"),
+            Halt::UnsupportedPrecompile(address) => {
+                write!(f, "precompile 0x{:02x} is not supported", address)
+            }
+        }
+    }
+}
+
+/// How a call or transaction ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Outcome {
+    Success(Vec<u8>),
+    Revert(Vec<u8>),
+    Halt(Halt),
+}
+
+impl Outcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, Outcome::Success(_))
+    }
+
+    /// Returns the return or revert data of the execution.
+    pub fn output(&self) -> &[u8] {
+        match self {
+            Outcome::Success(data) | Outcome::Revert(data) => data,
+            Outcome::Halt(_) => &[],
+        }
+    }
+}
+
+/// The result of executing a transaction.
+#[derive(Clone, Debug)]
+pub(crate) struct ExecutionResult {
+    pub outcome: Outcome,
+    /// The gas used by the transaction after refunds.
+    pub gas_used: u64,
+    pub logs: Vec<Log>,
+    pub contract_address: Option<Address>,
+    /// The state of all accounts touched by the transaction.
+    pub changes: Vec<(Address, AccountChange)>,
+}
+
+/// The result of a call or contract creation frame.
+#[derive(Debug)]
+pub(crate) struct FrameResult {
+    pub outcome: Outcome,
+    pub gas_left: u64,
+    pub refund: i64,
+}
+
+impl FrameResult {
+    fn failure(gas_left: u64) -> Self {
+        FrameResult {
+            outcome: Outcome::Revert(Vec::new()),
+            gas_left,
+            refund: 0,
+        }
+    }
+}
+
+/// A message call.
+#[derive(Clone, Debug)]
+pub(crate) struct Call {
+    pub caller: Address,
+    /// The account whose storage and balance the code operates on.
+    pub address: Address,
+    /// The account whose code is executed.
+    pub code_address: Address,
+    pub value: U256,
+    /// Whether the value is transferred from the caller, which is not the
+    /// case for `DELEGATECALL`.
+    pub transfer: bool,
+    pub input: Vec<u8>,
+    pub gas: u64,
+    pub is_static: bool,
+}
+
+/// Returns the address of a contract created with `CREATE`.
+pub(crate) fn create_address(sender: Address, nonce: u64) -> Address {
+    let nonce = nonce.to_be_bytes();
+    let nonce = &nonce[nonce.iter().take_while(|byte| **byte == 0).count()..];
+    let mut rlp = vec![0; 22];
+    rlp[1] = 0x94;
+    rlp[2..].copy_from_slice(sender.as_bytes());
+    match nonce {
+        [] => rlp.push(0x80),
+        [byte] if *byte < 0x80 => rlp.push(*byte),
+        _ => {
+            rlp.push(0x80 + nonce.len() as u8);
+            rlp.extend_from_slice(nonce);
+        }
+    }
+    rlp[0] = 0xc0 + (rlp.len() - 1) as u8;
+    Address::from_slice(&keccak256(&rlp)[12..])
+}
+
+/// Returns the address of a contract created with `CREATE2`.
+pub(crate) fn create2_address(sender: Address, salt: H256, init_code: &[u8]) -> Address {
+    let mut buffer = Vec::with_capacity(85);
+    buffer.push(0xff);
+    buffer.extend_from_slice(sender.as_bytes());
+    buffer.extend_from_slice(salt.as_bytes());
+    buffer.extend_from_slice(&keccak256(init_code));
+    Address::from_slice(&keccak256(&buffer)[12..])
+}
+
+/// Returns the intrinsic gas of a transaction.
+pub(crate) fn intrinsic_gas(tx: &Transaction) -> u64 {
+    let zeros = tx.data.iter().filter(|byte| **byte == 0).count() as u64;
+    let data = zeros * 4 + (tx.data.len() as u64 - zeros) * 16;
+    let create = if tx.to.is_none() {
+        32_000 + 2 * words(tx.data.len())
+    } else {
+        0
+    };
+    let access_list = tx
+        .access_list
+        .iter()
+        .map(|(_, slots)| 2400 + 1900 * slots.len() as u64)
+        .sum::<u64>();
+    21_000 + data + create + access_list
+}
+
+/// Returns the number of 32-byte words needed for a number of bytes.
+fn words(len: usize) -> u64 {
+    (len as u64 + 31) / 32
+}
+
+/// Returns the address of a precompiled contract.
+fn precompile(address: Address) -> Option<u64> {
+    let bytes = address.as_bytes();
+    if bytes[..12].iter().all(|byte| *byte == 0) {
+        let index = u64::from_be_bytes([0, 0, 0, 0, bytes[16], bytes[17], bytes[18], bytes[19]]);
+        if bytes[12..16].iter().all(|byte| *byte == 0) && (1..=PRECOMPILES).contains(&index) {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Executes a transaction against a database, returning the result along with
+/// the resulting state of the touched accounts. The database is not modified.
+pub(crate) fn transact<D: Database>(
+    db: &mut D,
+    env: &Env,
+    tx: &Transaction,
+) -> Result<ExecutionResult, InvalidTransaction> {
+    let intrinsic = intrinsic_gas(tx);
+    if tx.gas_limit < intrinsic {
+        return Err(InvalidTransaction::IntrinsicGasTooLow(intrinsic));
+    }
+    if tx.gas_limit > env.gas_limit {
+        return Err(InvalidTransaction::GasLimitExceeded);
+    }
+    if tx.to.is_none() && tx.data.len() > MAX_INIT_CODE_SIZE {
+        return Err(InvalidTransaction::InitCodeTooLarge);
+    }
+
+    let mut evm = Evm {
+        env,
+        origin: tx.from,
+        gas_price: tx.gas_price,
+        state: Journal::new(db),
+    };
+    let sender = evm.state.account(tx.from).clone();
+    if let Some(nonce) = tx.nonce {
+        if nonce < sender.nonce {
+            return Err(InvalidTransaction::NonceTooLow(nonce, sender.nonce));
+        }
+        if nonce > sender.nonce {
+            return Err(InvalidTransaction::NonceTooHigh(nonce, sender.nonce));
+        }
+    }
+    let fee = U256::from(tx.gas_limit).full_mul(tx.gas_price);
+    let cost = fee + U512::from(tx.value);
+    if cost > U512::from(sender.balance) {
+        return Err(InvalidTransaction::InsufficientFunds);
+    }
+    let fee = U256::from(tx.gas_limit) * tx.gas_price;
+
+    evm.state.set_balance(tx.from, sender.balance - fee);
+    evm.state.set_nonce(tx.from, sender.nonce.saturating_add(1));
+    evm.state.warm_account(tx.from);
+    evm.state.warm_account(env.coinbase);
+    for index in 1..=PRECOMPILES {
+        evm.state.warm_account(Address::from_low_u64_be(index));
+    }
+    for (address, slots) in &tx.access_list {
+        evm.state.warm_account(*address);
+        for slot in slots {
+            evm.state
+                .warm_slot(*address, U256::from_big_endian(slot.as_bytes()));
+        }
+    }
+
+    let gas = tx.gas_limit - intrinsic;
+    let (result, contract_address) = match tx.to {
+        Some(to) => {
+            evm.state.warm_account(to);
+            let call = Call {
+                caller: tx.from,
+                address: to,
+                code_address: to,
+                value: tx.value,
+                transfer: true,
+                input: tx.data.clone(),
+                gas,
+                is_static: false,
+            };
+            (evm.call(call, 0), None)
+        }
+        None => {
+            let address = create_address(tx.from, sender.nonce);
+            let result = evm.create_at(tx.from, address, tx.value, tx.data.clone().into(), gas, 0);
+            let created = if result.outcome.is_success() {
+                Some(address)
+            } else {
+                None
+            };
+            (result, created)
+        }
+    };
+
+    let gas_used = tx.gas_limit - result.gas_left;
+    let refund = if result.outcome.is_success() {
+        (result.refund.max(0) as u64).min(gas_used / 5)
+    } else {
+        0
+    };
+    let gas_used = gas_used - refund;
+
+    let balance = evm.state.account(tx.from).balance;
+    let remaining = U256::from(tx.gas_limit - gas_used) * tx.gas_price;
+    evm.state
+        .set_balance(tx.from, balance.saturating_add(remaining));
+    let tip = tx.gas_price.saturating_sub(env.base_fee);
+    if !tip.is_zero() {
+        let balance = evm.state.account(env.coinbase).balance;
+        evm.state.set_balance(
+            env.coinbase,
+            balance.saturating_add(U256::from(gas_used) * tip),
+        );
+    }
+
+    let (logs, changes) = evm.state.finish();
+    Ok(ExecutionResult {
+        outcome: result.outcome,
+        gas_used,
+        logs,
+        contract_address,
+        changes,
+    })
+}
+
+/// The execution context of a transaction.
+pub(crate) struct Evm<'a, D> {
+    env: &'a Env,
+    origin: Address,
+    gas_price: U256,
+    state: Journal<'a, D>,
+}
+
+impl<'a, D: Database> Evm<'a, D> {
+    /// Executes a message call.
+    fn call(&mut self, call: Call, depth: usize) -> FrameResult {
+        if depth > MAX_DEPTH {
+            return FrameResult::failure(call.gas);
+        }
+
+        let checkpoint = self.state.checkpoint();
+        if call.transfer && !self.state.transfer(call.caller, call.address, call.value) {
+            return FrameResult::failure(call.gas);
+        }
+
+        let result = match precompile(call.code_address) {
+            Some(index) => run_precompile(index, &call.input, call.gas),
+            None => {
+                let code = self.state.account(call.code_address).code.clone();
+                if code.is_empty() {
+                    return FrameResult {
+                        outcome: Outcome::Success(Vec::new()),
+                        gas_left: call.gas,
+                        refund: 0,
+                    };
+                }
+                let mut frame = Frame::new(code, call, depth);
+                let outcome = frame.run(self);
+                frame.finish(outcome)
+            }
+        };
+
+        if !result.outcome.is_success() {
+            self.state.revert(checkpoint);
+        }
+        result
+    }
+
+    /// Executes the init code of a contract created with `CREATE` or
+    /// `CREATE2`, checking the call depth and balance and incrementing the
+    /// nonce of the creator.
+    fn create(
+        &mut self,
+        caller: Address,
+        value: U256,
+        init_code: Arc<[u8]>,
+        salt: Option<H256>,
+        gas: u64,
+        depth: usize,
+    ) -> (FrameResult, Option<Address>) {
+        let creator = self.state.account(caller).clone();
+        if depth > MAX_DEPTH || creator.balance < value || creator.nonce == u64::MAX {
+            return (FrameResult::failure(gas), None);
+        }
+        self.state.set_nonce(caller, creator.nonce + 1);
+
+        let address = match salt {
+            Some(salt) => create2_address(caller, salt, &init_code),
+            None => create_address(caller, creator.nonce),
+        };
+        let result = self.create_at(caller, address, value, init_code, gas, depth);
+        let created = if result.outcome.is_success() {
+            Some(address)
+        } else {
+            None
+        };
+        (result, created)
+    }
+
+    /// Deploys a contract at an address.
+    fn create_at(
+        &mut self,
+        caller: Address,
+        address: Address,
+        value: U256,
+        init_code: Arc<[u8]>,
+        gas: u64,
+        depth: usize,
+    ) -> FrameResult {
+        self.state.warm_account(address);
+        let target = self.state.account(address);
+        if target.nonce != 0 || !target.code.is_empty() {
+            return FrameResult {
+                outcome: Outcome::Halt(Halt::CreateCollision),
+                gas_left: 0,
+                refund: 0,
+            };
+        }
+
+        let checkpoint = self.state.checkpoint();
+        self.state.mark_created(address);
+        self.state.set_nonce(address, 1);
+        if !self.state.transfer(caller, address, value) {
+            self.state.revert(checkpoint);
+            return FrameResult::failure(gas);
+        }
+
+        let call = Call {
+            caller,
+            address,
+            code_address: address,
+            value,
+            transfer: true,
+            input: Vec::new(),
+            gas,
+            is_static: false,
+        };
+        let mut frame = Frame::new(init_code, call, depth);
+        let outcome = match frame.run(self) {
+            Outcome::Success(code) => {
+                let deposit = 200 * code.len() as u64;
+                if code.len() > MAX_CODE_SIZE {
+                    Outcome::Halt(Halt::CodeSizeLimit)
+                } else if code.first() == Some(&0xef) {
+                    Outcome::Halt(Halt::InvalidCode)
+                } else if frame.gas.charge(deposit).is_err() {
+                    Outcome::Halt(Halt::OutOfGas)
+                } else {
+                    self.state.set_code(address, code.into());
+                    Outcome::Success(Vec::new())
+                }
+            }
+            outcome => outcome,
+        };
+        let result = frame.finish(outcome);
+
+        if !result.outcome.is_success() {
+            self.state.revert(checkpoint);
+        }
+        result
+    }
+}
+
+/// Executes a precompiled contract.
+fn run_precompile(index: u64, input: &[u8], gas: u64) -> FrameResult {
+    let (cost, output) = match index {
+        1 => (3000, ecrecover(input)),
+        4 => (15 + 3 * words(input.len()), input.to_vec()),
+        _ => {
+            return FrameResult {
+                outcome: Outcome::Halt(Halt::UnsupportedPrecompile(index)),
+                gas_left: 0,
+                refund: 0,
+            }
+        }
+    };
+    match gas.checked_sub(cost) {
+        Some(gas_left) => FrameResult {
+            outcome: Outcome::Success(output),
+            gas_left,
+            refund: 0,
+        },
+        None => FrameResult {
+            outcome: Outcome::Halt(Halt::OutOfGas),
+            gas_left: 0,
+            refund: 0,
+        },
+    }
+}
+
+/// Recovers the signer of a message hash, returning an empty output for
+/// invalid signatures.
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let mut padded = [0; 128];
+    let len = input.len().min(128);
+    padded[..len].copy_from_slice(&input[..len]);
+
+    let v = U256::from_big_endian(&padded[32..64]);
+    if v != 27.into() && v != 28.into() {
+        return Vec::new();
+    }
+    match signing::recover(&padded[..32], &padded[64..128], v.as_u32() as i32 - 27) {
+        Ok(address) => {
+            let mut output = vec![0; 32];
+            output[12..].copy_from_slice(address.as_bytes());
+            output
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemoryDb {
+        accounts: HashMap<Address, AccountInfo>,
+    }
+
+    impl Database for MemoryDb {
+        fn account(&mut self, address: Address) -> AccountInfo {
+            self.accounts.get(&address).cloned().unwrap_or_default()
+        }
+
+        fn storage(&mut self, _: Address, _: U256) -> U256 {
+            U256::zero()
+        }
+
+        fn block_hash(&mut self, _: u64) -> H256 {
+            H256::zero()
+        }
+    }
+
+    #[test]
+    fn contract_addresses() {
+        let sender: Address = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            create_address(sender, 0),
+            "0xcd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(
+            create_address(sender, 1),
+            "0x343c43a37d37dff08ae8c4a11544c718abb4fcf8"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(
+            create2_address(Address::zero(), H256::zero(), &[0]),
+            "0x4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn transfers_value_and_charges_gas() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let mut db = MemoryDb::default();
+        db.accounts.insert(
+            from,
+            AccountInfo {
+                balance: 1_000_000.into(),
+                ..Default::default()
+            },
+        );
+        let env = Env {
+            gas_limit: 30_000_000,
+            ..Default::default()
+        };
+        let tx = Transaction {
+            from,
+            to: Some(to),
+            value: 1000.into(),
+            gas_limit: 30_000,
+            gas_price: 2.into(),
+            nonce: Some(0),
+            ..Default::default()
+        };
+
+        let result = transact(&mut db, &env, &tx).unwrap();
+        assert_eq!(result.outcome, Outcome::Success(Vec::new()));
+        assert_eq!(result.gas_used, 21_000);
+        let changes = result.changes.into_iter().collect::<HashMap<_, _>>();
+        assert_eq!(
+            changes[&from].info.balance,
+            (1_000_000 - 1000 - 42_000).into()
+        );
+        assert_eq!(changes[&from].info.nonce, 1);
+        assert_eq!(changes[&to].info.balance, 1000.into());
+
+        assert_eq!(
+            transact(
+                &mut db,
+                &env,
+                &Transaction {
+                    nonce: Some(1),
+                    ..tx.clone()
+                }
+            )
+            .unwrap_err(),
+            InvalidTransaction::NonceTooHigh(1, 0)
+        );
+        assert_eq!(
+            transact(
+                &mut db,
+                &env,
+                &Transaction {
+                    value: 1_000_000.into(),
+                    ..tx
+                }
+            )
+            .unwrap_err(),
+            InvalidTransaction::InsufficientFunds
+        );
+    }
+
+    #[test]
+    fn recovers_signers() {
+        let key = crate::secret::PrivateKey::from_raw([1; 32]).unwrap();
+        let hash = [42; 32];
+        let signature = signing::Key::sign(&&key, &hash, None).unwrap();
+        let mut input = hash.to_vec();
+        input.extend_from_slice(H256::from_low_u64_be(signature.v).as_bytes());
+        input.extend_from_slice(signature.r.as_bytes());
+        input.extend_from_slice(signature.s.as_bytes());
+
+        let output = ecrecover(&input);
+        assert_eq!(Address::from_slice(&output[12..]), key.public_address());
+        input[63] = 29;
+        assert!(ecrecover(&input).is_empty());
+    }
+}
@@ -0,0 +1,1063 @@
+//! The bytecode interpreter executing a single call frame.
+
+use super::state::{Database, Log};
+use super::{words, Call, Evm, FrameResult, Halt, Outcome};
+use crate::int::I256;
+use primitive_types::U512;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use web3::signing::keccak256;
+use web3::types::{Address, H256, U256};
+
+/// The maximum number of items on the stack.
+const STACK_LIMIT: usize = 1024;
+
+/// The maximum amount of memory a frame can use. Expanding memory beyond this
+/// costs more gas than a block can contain, so it is treated as running out of
+/// gas.
+const MEMORY_LIMIT: u64 = 1 << 32;
+
+/// The gas remaining in a frame and the accumulated gas refund.
+#[derive(Debug)]
+pub(crate) struct Gas {
+    pub remaining: u64,
+    pub refund: i64,
+}
+
+impl Gas {
+    pub fn charge(&mut self, cost: u64) -> Result<(), Halt> {
+        self.remaining = self.remaining.checked_sub(cost).ok_or(Halt::OutOfGas)?;
+        Ok(())
+    }
+}
+
+/// An executing call frame.
+pub(crate) struct Frame {
+    code: Arc<[u8]>,
+    jumpdests: Vec<bool>,
+    pc: usize,
+    stack: Vec<U256>,
+    memory: Vec<u8>,
+    pub gas: Gas,
+    call: Call,
+    return_data: Vec<u8>,
+    depth: usize,
+}
+
+/// Returns the addresses of the `JUMPDEST` instructions of code, skipping the
+/// immediate data of `PUSH` instructions.
+fn analyze(code: &[u8]) -> Vec<bool> {
+    let mut jumpdests = vec![false; code.len()];
+    let mut pc = 0;
+    while pc < code.len() {
+        match code[pc] {
+            0x5b => jumpdests[pc] = true,
+            opcode @ 0x60..=0x7f => pc += (opcode - 0x5f) as usize,
+            _ => {}
+        }
+        pc += 1;
+    }
+    jumpdests
+}
+
+/// Converts a value to `usize`, saturating for values that do not fit.
+fn saturating_usize(value: U256) -> usize {
+    if value > U256::from(usize::MAX) {
+        usize::MAX
+    } else {
+        value.as_usize()
+    }
+}
+
+/// Converts a value to `u64`, saturating for values that do not fit.
+fn saturating_u64(value: U256) -> u64 {
+    if value > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        value.as_u64()
+    }
+}
+
+fn address_from_word(value: U256) -> Address {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+fn address_to_word(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+fn bool_to_word(value: bool) -> U256 {
+    if value {
+        U256::one()
+    } else {
+        U256::zero()
+    }
+}
+
+/// Copies a range of a buffer into a destination, padding with zeros past
+/// the end of the buffer.
+fn copy_padded(dest: &mut [u8], source: &[u8], offset: usize) {
+    let available = source.get(offset..).unwrap_or_default();
+    let len = available.len().min(dest.len());
+    dest[..len].copy_from_slice(&available[..len]);
+    for byte in &mut dest[len..] {
+        *byte = 0;
+    }
+}
+
+/// Returns the gas cost of memory of a number of words.
+fn memory_cost(words: u64) -> u64 {
+    3 * words + words * words / 512
+}
+
+/// Returns the gas cost of accessing an account or storage slot.
+fn access_cost(cold: bool, cold_cost: u64) -> u64 {
+    if cold {
+        cold_cost
+    } else {
+        100
+    }
+}
+
+fn sar(shift: U256, value: U256) -> U256 {
+    let negative = value.bit(255);
+    if shift >= U256::from(256) {
+        if negative {
+            U256::MAX
+        } else {
+            U256::zero()
+        }
+    } else if negative {
+        !(!value >> shift.as_usize())
+    } else {
+        value >> shift.as_usize()
+    }
+}
+
+fn signextend(size: U256, value: U256) -> U256 {
+    if size >= U256::from(31) {
+        return value;
+    }
+    let bit = size.as_usize() * 8 + 7;
+    let mask = (U256::one() << bit) - 1;
+    if value.bit(bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
+
+impl Frame {
+    pub fn new(code: Arc<[u8]>, call: Call, depth: usize) -> Self {
+        Frame {
+            jumpdests: analyze(&code),
+            code,
+            pc: 0,
+            stack: Vec::with_capacity(16),
+            memory: Vec::new(),
+            gas: Gas {
+                remaining: call.gas,
+                refund: 0,
+            },
+            call,
+            return_data: Vec::new(),
+            depth,
+        }
+    }
+
+    /// Returns the result of the frame for an outcome of its execution.
+    pub fn finish(self, outcome: Outcome) -> FrameResult {
+        match outcome {
+            Outcome::Success(_) => FrameResult {
+                outcome,
+                gas_left: self.gas.remaining,
+                refund: self.gas.refund,
+            },
+            Outcome::Revert(_) => FrameResult {
+                outcome,
+                gas_left: self.gas.remaining,
+                refund: 0,
+            },
+            Outcome::Halt(_) => FrameResult {
+                outcome,
+                gas_left: 0,
+                refund: 0,
+            },
+        }
+    }
+
+    /// Executes the code of the frame.
+    pub fn run<D: Database>(&mut self, evm: &mut Evm<D>) -> Outcome {
+        loop {
+            let opcode = match self.code.get(self.pc) {
+                Some(opcode) => *opcode,
+                None => return Outcome::Success(Vec::new()),
+            };
+            self.pc += 1;
+            match self.step(evm, opcode) {
+                Ok(None) => {}
+                Ok(Some(outcome)) => return outcome,
+                Err(halt) => return Outcome::Halt(halt),
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Result<U256, Halt> {
+        self.stack.pop().ok_or(Halt::StackUnderflow)
+    }
+
+    fn pop_address(&mut self) -> Result<Address, Halt> {
+        Ok(address_from_word(self.pop()?))
+    }
+
+    fn push(&mut self, value: U256) -> Result<(), Halt> {
+        if self.stack.len() == STACK_LIMIT {
+            return Err(Halt::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn binary(&mut self, op: impl FnOnce(U256, U256) -> U256) -> Result<(), Halt> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.push(op(a, b))
+    }
+
+    /// Expands memory to include a range, charging the expansion cost, and
+    /// returns the offset of the range. Empty ranges do not expand memory.
+    fn expand(&mut self, offset: U256, len: U256) -> Result<usize, Halt> {
+        if len.is_zero() {
+            return Ok(0);
+        }
+        let end = offset
+            .checked_add(len)
+            .filter(|end| *end <= U256::from(MEMORY_LIMIT))
+            .ok_or(Halt::OutOfGas)?
+            .as_u64();
+        let old = words(self.memory.len());
+        let new = (end + 31) / 32;
+        if new > old {
+            self.gas.charge(memory_cost(new) - memory_cost(old))?;
+            self.memory.resize(new as usize * 32, 0);
+        }
+        Ok(offset.as_usize())
+    }
+
+    /// Pops an offset and a length and returns the corresponding memory
+    /// range, expanding memory as needed.
+    fn memory_range(&mut self) -> Result<(usize, usize), Halt> {
+        let offset = self.pop()?;
+        let len = self.pop()?;
+        let start = self.expand(offset, len)?;
+        Ok((start, len.low_u64() as usize))
+    }
+
+    fn memory_slice(&self, offset: usize, len: usize) -> Vec<u8> {
+        if len == 0 {
+            Vec::new()
+        } else {
+            self.memory[offset..offset + len].to_vec()
+        }
+    }
+
+    /// Implements the `*COPY` instructions copying from a buffer to memory.
+    fn copy_to_memory(&mut self, source: &[u8]) -> Result<(), Halt> {
+        let dest = self.pop()?;
+        let offset = self.pop()?;
+        let len = self.pop()?;
+        self.copy_range(source, dest, offset, len)
+    }
+
+    fn copy_range(
+        &mut self,
+        source: &[u8],
+        dest: U256,
+        offset: U256,
+        len: U256,
+    ) -> Result<(), Halt> {
+        let dest = self.expand(dest, len)?;
+        let len = len.low_u64() as usize;
+        self.gas.charge(3 * words(len))?;
+        if len > 0 {
+            copy_padded(
+                &mut self.memory[dest..dest + len],
+                source,
+                saturating_usize(offset),
+            );
+        }
+        Ok(())
+    }
+
+    fn check_static(&self) -> Result<(), Halt> {
+        if self.call.is_static {
+            Err(Halt::StaticCallViolation)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn step<D: Database>(&mut self, evm: &mut Evm<D>, opcode: u8) -> Result<Option<Outcome>, Halt> {
+        match opcode {
+            // STOP
+            0x00 => return Ok(Some(Outcome::Success(Vec::new()))),
+            // ADD
+            0x01 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| a.overflowing_add(b).0)?;
+            }
+            // MUL
+            0x02 => {
+                self.gas.charge(5)?;
+                self.binary(|a, b| a.overflowing_mul(b).0)?;
+            }
+            // SUB
+            0x03 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| a.overflowing_sub(b).0)?;
+            }
+            // DIV
+            0x04 => {
+                self.gas.charge(5)?;
+                self.binary(|a, b| if b.is_zero() { b } else { a / b })?;
+            }
+            // SDIV
+            0x05 => {
+                self.gas.charge(5)?;
+                self.binary(|a, b| {
+                    if b.is_zero() {
+                        b
+                    } else {
+                        I256::from_raw(a).wrapping_div(I256::from_raw(b)).into_raw()
+                    }
+                })?;
+            }
+            // MOD
+            0x06 => {
+                self.gas.charge(5)?;
+                self.binary(|a, b| if b.is_zero() { b } else { a % b })?;
+            }
+            // SMOD
+            0x07 => {
+                self.gas.charge(5)?;
+                self.binary(|a, b| {
+                    if b.is_zero() {
+                        b
+                    } else {
+                        I256::from_raw(a).wrapping_rem(I256::from_raw(b)).into_raw()
+                    }
+                })?;
+            }
+            // ADDMOD, MULMOD
+            0x08 | 0x09 => {
+                self.gas.charge(8)?;
+                let a = self.pop()?;
+                let b = self.pop()?;
+                let n = self.pop()?;
+                let result = if n.is_zero() {
+                    n
+                } else {
+                    let value = if opcode == 0x08 {
+                        U512::from(a) + U512::from(b)
+                    } else {
+                        a.full_mul(b)
+                    };
+                    U256::try_from(value % U512::from(n)).expect("remainder fits into U256")
+                };
+                self.push(result)?;
+            }
+            // EXP
+            0x0a => {
+                let base = self.pop()?;
+                let exponent = self.pop()?;
+                self.gas
+                    .charge(10 + 50 * ((exponent.bits() as u64 + 7) / 8))?;
+                self.push(base.overflowing_pow(exponent).0)?;
+            }
+            // SIGNEXTEND
+            0x0b => {
+                self.gas.charge(5)?;
+                self.binary(signextend)?;
+            }
+            // LT, GT, SLT, SGT, EQ
+            0x10 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| bool_to_word(a < b))?;
+            }
+            0x11 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| bool_to_word(a > b))?;
+            }
+            0x12 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| bool_to_word(I256::from_raw(a) < I256::from_raw(b)))?;
+            }
+            0x13 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| bool_to_word(I256::from_raw(a) > I256::from_raw(b)))?;
+            }
+            0x14 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| bool_to_word(a == b))?;
+            }
+            // ISZERO
+            0x15 => {
+                self.gas.charge(3)?;
+                let a = self.pop()?;
+                self.push(bool_to_word(a.is_zero()))?;
+            }
+            // AND, OR, XOR
+            0x16 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| a & b)?;
+            }
+            0x17 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| a | b)?;
+            }
+            0x18 => {
+                self.gas.charge(3)?;
+                self.binary(|a, b| a ^ b)?;
+            }
+            // NOT
+            0x19 => {
+                self.gas.charge(3)?;
+                let a = self.pop()?;
+                self.push(!a)?;
+            }
+            // BYTE
+            0x1a => {
+                self.gas.charge(3)?;
+                self.binary(|i, x| {
+                    if i < U256::from(32) {
+                        U256::from(x.byte(31 - i.as_usize()))
+                    } else {
+                        U256::zero()
+                    }
+                })?;
+            }
+            // SHL, SHR
+            0x1b | 0x1c => {
+                self.gas.charge(3)?;
+                self.binary(|shift, value| {
+                    if shift >= U256::from(256) {
+                        U256::zero()
+                    } else if opcode == 0x1b {
+                        value << shift.as_usize()
+                    } else {
+                        value >> shift.as_usize()
+                    }
+                })?;
+            }
+            // SAR
+            0x1d => {
+                self.gas.charge(3)?;
+                self.binary(sar)?;
+            }
+            // KECCAK256
+            0x20 => {
+                self.gas.charge(30)?;
+                let (offset, len) = self.memory_range()?;
+                self.gas.charge(6 * words(len))?;
+                let hash = keccak256(&self.memory_slice(offset, len));
+                self.push(U256::from_big_endian(&hash))?;
+            }
+            // ADDRESS
+            0x30 => {
+                self.gas.charge(2)?;
+                self.push(address_to_word(self.call.address))?;
+            }
+            // BALANCE
+            0x31 => {
+                let address = self.pop_address()?;
+                let cold = evm.state.warm_account(address);
+                self.gas.charge(access_cost(cold, 2600))?;
+                let balance = evm.state.account(address).balance;
+                self.push(balance)?;
+            }
+            // ORIGIN
+            0x32 => {
+                self.gas.charge(2)?;
+                self.push(address_to_word(evm.origin))?;
+            }
+            // CALLER
+            0x33 => {
+                self.gas.charge(2)?;
+                self.push(address_to_word(self.call.caller))?;
+            }
+            // CALLVALUE
+            0x34 => {
+                self.gas.charge(2)?;
+                self.push(self.call.value)?;
+            }
+            // CALLDATALOAD
+            0x35 => {
+                self.gas.charge(3)?;
+                let offset = self.pop()?;
+                let mut word = [0; 32];
+                copy_padded(&mut word, &self.call.input, saturating_usize(offset));
+                self.push(U256::from_big_endian(&word))?;
+            }
+            // CALLDATASIZE
+            0x36 => {
+                self.gas.charge(2)?;
+                self.push(self.call.input.len().into())?;
+            }
+            // CALLDATACOPY
+            0x37 => {
+                self.gas.charge(3)?;
+                let input = std::mem::take(&mut self.call.input);
+                let result = self.copy_to_memory(&input);
+                self.call.input = input;
+                result?;
+            }
+            // CODESIZE
+            0x38 => {
+                self.gas.charge(2)?;
+                self.push(self.code.len().into())?;
+            }
+            // CODECOPY
+            0x39 => {
+                self.gas.charge(3)?;
+                let code = self.code.clone();
+                self.copy_to_memory(&code)?;
+            }
+            // GASPRICE
+            0x3a => {
+                self.gas.charge(2)?;
+                self.push(evm.gas_price)?;
+            }
+            // EXTCODESIZE
+            0x3b => {
+                let address = self.pop_address()?;
+                let cold = evm.state.warm_account(address);
+                self.gas.charge(access_cost(cold, 2600))?;
+                let len = evm.state.account(address).code.len();
+                self.push(len.into())?;
+            }
+            // EXTCODECOPY
+            0x3c => {
+                let address = self.pop_address()?;
+                let cold = evm.state.warm_account(address);
+                self.gas.charge(access_cost(cold, 2600))?;
+                let code = evm.state.account(address).code.clone();
+                self.copy_to_memory(&code)?;
+            }
+            // RETURNDATASIZE
+            0x3d => {
+                self.gas.charge(2)?;
+                self.push(self.return_data.len().into())?;
+            }
+            // RETURNDATACOPY
+            0x3e => {
+                self.gas.charge(3)?;
+                let dest = self.pop()?;
+                let offset = self.pop()?;
+                let len = self.pop()?;
+                let end = offset.checked_add(len);
+                if end.map_or(true, |end| end > self.return_data.len().into()) {
+                    return Err(Halt::ReturnDataOutOfBounds);
+                }
+                let data = std::mem::take(&mut self.return_data);
+                let result = self.copy_range(&data, dest, offset, len);
+                self.return_data = data;
+                result?;
+            }
+            // EXTCODEHASH
+            0x3f => {
+                let address = self.pop_address()?;
+                let cold = evm.state.warm_account(address);
+                self.gas.charge(access_cost(cold, 2600))?;
+                let hash = evm.state.code_hash(address);
+                self.push(U256::from_big_endian(hash.as_bytes()))?;
+            }
+            // BLOCKHASH
+            0x40 => {
+                self.gas.charge(20)?;
+                let number = self.pop()?;
+                let current = evm.env.number;
+                let hash = if number < current.into() && number + 256 >= current.into() {
+                    evm.state.block_hash(number.as_u64())
+                } else {
+                    H256::zero()
+                };
+                self.push(U256::from_big_endian(hash.as_bytes()))?;
+            }
+            // COINBASE
+            0x41 => {
+                self.gas.charge(2)?;
+                self.push(address_to_word(evm.env.coinbase))?;
+            }
+            // TIMESTAMP
+            0x42 => {
+                self.gas.charge(2)?;
+                self.push(evm.env.timestamp.into())?;
+            }
+            // NUMBER
+            0x43 => {
+                self.gas.charge(2)?;
+                self.push(evm.env.number.into())?;
+            }
+            // PREVRANDAO
+            0x44 => {
+                self.gas.charge(2)?;
+                self.push(U256::from_big_endian(evm.env.prevrandao.as_bytes()))?;
+            }
+            // GASLIMIT
+            0x45 => {
+                self.gas.charge(2)?;
+                self.push(evm.env.gas_limit.into())?;
+            }
+            // CHAINID
+            0x46 => {
+                self.gas.charge(2)?;
+                self.push(evm.env.chain_id.into())?;
+            }
+            // SELFBALANCE
+            0x47 => {
+                self.gas.charge(5)?;
+                let balance = evm.state.account(self.call.address).balance;
+                self.push(balance)?;
+            }
+            // BASEFEE
+            0x48 => {
+                self.gas.charge(2)?;
+                self.push(evm.env.base_fee)?;
+            }
+            // BLOBHASH, as there are no blob transactions
+            0x49 => {
+                self.gas.charge(3)?;
+                self.pop()?;
+                self.push(U256::zero())?;
+            }
+            // BLOBBASEFEE
+            0x4a => {
+                self.gas.charge(2)?;
+                self.push(U256::one())?;
+            }
+            // POP
+            0x50 => {
+                self.gas.charge(2)?;
+                self.pop()?;
+            }
+            // MLOAD
+            0x51 => {
+                self.gas.charge(3)?;
+                let offset = self.pop()?;
+                let offset = self.expand(offset, 32.into())?;
+                let word = U256::from_big_endian(&self.memory[offset..offset + 32]);
+                self.push(word)?;
+            }
+            // MSTORE
+            0x52 => {
+                self.gas.charge(3)?;
+                let offset = self.pop()?;
+                let value = self.pop()?;
+                let offset = self.expand(offset, 32.into())?;
+                value.to_big_endian(&mut self.memory[offset..offset + 32]);
+            }
+            // MSTORE8
+            0x53 => {
+                self.gas.charge(3)?;
+                let offset = self.pop()?;
+                let value = self.pop()?;
+                let offset = self.expand(offset, 1.into())?;
+                self.memory[offset] = value.byte(0);
+            }
+            // SLOAD
+            0x54 => {
+                let slot = self.pop()?;
+                let cold = evm.state.warm_slot(self.call.address, slot);
+                self.gas.charge(access_cost(cold, 2100))?;
+                let value = evm.state.slot(self.call.address, slot).current;
+                self.push(value)?;
+            }
+            // SSTORE
+            0x55 => {
+                self.check_static()?;
+                if self.gas.remaining <= 2300 {
+                    return Err(Halt::OutOfGas);
+                }
+                let slot = self.pop()?;
+                let value = self.pop()?;
+                self.sstore(evm, slot, value)?;
+            }
+            // JUMP
+            0x56 => {
+                self.gas.charge(8)?;
+                let dest = self.pop()?;
+                self.jump(dest)?;
+            }
+            // JUMPI
+            0x57 => {
+                self.gas.charge(10)?;
+                let dest = self.pop()?;
+                let condition = self.pop()?;
+                if !condition.is_zero() {
+                    self.jump(dest)?;
+                }
+            }
+            // PC
+            0x58 => {
+                self.gas.charge(2)?;
+                self.push((self.pc - 1).into())?;
+            }
+            // MSIZE
+            0x59 => {
+                self.gas.charge(2)?;
+                self.push(self.memory.len().into())?;
+            }
+            // GAS
+            0x5a => {
+                self.gas.charge(2)?;
+                self.push(self.gas.remaining.into())?;
+            }
+            // JUMPDEST
+            0x5b => self.gas.charge(1)?,
+            // TLOAD
+            0x5c => {
+                self.gas.charge(100)?;
+                let slot = self.pop()?;
+                let value = evm.state.tload(self.call.address, slot);
+                self.push(value)?;
+            }
+            // TSTORE
+            0x5d => {
+                self.check_static()?;
+                self.gas.charge(100)?;
+                let slot = self.pop()?;
+                let value = self.pop()?;
+                evm.state.tstore(self.call.address, slot, value);
+            }
+            // MCOPY
+            0x5e => {
+                self.gas.charge(3)?;
+                let dest = self.pop()?;
+                let source = self.pop()?;
+                let len = self.pop()?;
+                let end = source.max(dest);
+                self.expand(end, len)?;
+                self.gas.charge(3 * words(len.low_u64() as usize))?;
+                if !len.is_zero() {
+                    let (source, dest, len) = (source.as_usize(), dest.as_usize(), len.as_usize());
+                    self.memory.copy_within(source..source + len, dest);
+                }
+            }
+            // PUSH0
+            0x5f => {
+                self.gas.charge(2)?;
+                self.push(U256::zero())?;
+            }
+            // PUSH1 - PUSH32
+            0x60..=0x7f => {
+                self.gas.charge(3)?;
+                let len = (opcode - 0x5f) as usize;
+                let mut word = [0; 32];
+                copy_padded(&mut word[32 - len..], &self.code, self.pc);
+                self.pc += len;
+                self.push(U256::from_big_endian(&word))?;
+            }
+            // DUP1 - DUP16
+            0x80..=0x8f => {
+                self.gas.charge(3)?;
+                let index = (opcode - 0x80) as usize;
+                let value = *self
+                    .stack
+                    .iter()
+                    .rev()
+                    .nth(index)
+                    .ok_or(Halt::StackUnderflow)?;
+                self.push(value)?;
+            }
+            // SWAP1 - SWAP16
+            0x90..=0x9f => {
+                self.gas.charge(3)?;
+                let index = (opcode - 0x8f) as usize;
+                let len = self.stack.len();
+                if index >= len {
+                    return Err(Halt::StackUnderflow);
+                }
+                self.stack.swap(len - 1, len - 1 - index);
+            }
+            // LOG0 - LOG4
+            0xa0..=0xa4 => {
+                self.check_static()?;
+                let topics = (opcode - 0xa0) as usize;
+                self.gas.charge(375 + 375 * topics as u64)?;
+                let (offset, len) = self.memory_range()?;
+                self.gas.charge(8 * len as u64)?;
+                let topics = (0..topics)
+                    .map(|_| {
+                        let mut topic = H256::zero();
+                        self.pop()?.to_big_endian(topic.as_bytes_mut());
+                        Ok(topic)
+                    })
+                    .collect::<Result<_, Halt>>()?;
+                let data = self.memory_slice(offset, len);
+                evm.state.log(Log {
+                    address: self.call.address,
+                    topics,
+                    data,
+                });
+            }
+            // CREATE, CREATE2
+            0xf0 | 0xf5 => {
+                self.check_static()?;
+                self.gas.charge(32_000)?;
+                let value = self.pop()?;
+                let (offset, len) = self.memory_range()?;
+                let salt = if opcode == 0xf5 {
+                    let mut salt = H256::zero();
+                    self.pop()?.to_big_endian(salt.as_bytes_mut());
+                    self.gas.charge(6 * words(len))?;
+                    Some(salt)
+                } else {
+                    None
+                };
+                if len > super::MAX_INIT_CODE_SIZE {
+                    return Err(Halt::OutOfGas);
+                }
+                self.gas.charge(2 * words(len))?;
+                let init_code = self.memory_slice(offset, len);
+
+                let gas = self.gas.remaining - self.gas.remaining / 64;
+                self.gas.charge(gas)?;
+                let (result, address) = evm.create(
+                    self.call.address,
+                    value,
+                    init_code.into(),
+                    salt,
+                    gas,
+                    self.depth + 1,
+                );
+                self.gas.remaining += result.gas_left;
+                self.return_data = match result.outcome {
+                    Outcome::Revert(data) => data,
+                    _ => Vec::new(),
+                };
+                if address.is_some() {
+                    self.gas.refund += result.refund;
+                }
+                self.push(address.map_or_else(U256::zero, address_to_word))?;
+            }
+            // CALL, CALLCODE, DELEGATECALL, STATICCALL
+            0xf1 | 0xf2 | 0xf4 | 0xfa => self.call(evm, opcode)?,
+            // RETURN, REVERT
+            0xf3 | 0xfd => {
+                let (offset, len) = self.memory_range()?;
+                let data = self.memory_slice(offset, len);
+                return Ok(Some(if opcode == 0xf3 {
+                    Outcome::Success(data)
+                } else {
+                    Outcome::Revert(data)
+                }));
+            }
+            // SELFDESTRUCT
+            0xff => {
+                self.check_static()?;
+                let beneficiary = self.pop_address()?;
+                let cold = evm.state.warm_account(beneficiary);
+                let balance = evm.state.account(self.call.address).balance;
+                let mut cost = 5000 + if cold { 2600 } else { 0 };
+                if !balance.is_zero() && evm.state.account(beneficiary).is_empty() {
+                    cost += 25_000;
+                }
+                self.gas.charge(cost)?;
+
+                // NOTE: Since EIP-6780, accounts are only deleted when they
+                //   self destruct in the transaction that created them.
+                let created = evm.state.is_created(self.call.address);
+                if beneficiary != self.call.address {
+                    evm.state.transfer(self.call.address, beneficiary, balance);
+                }
+                if created {
+                    evm.state.set_balance(self.call.address, U256::zero());
+                    evm.state.mark_destroyed(self.call.address);
+                }
+                return Ok(Some(Outcome::Success(Vec::new())));
+            }
+            _ => return Err(Halt::InvalidOpcode(opcode)),
+        }
+        Ok(None)
+    }
+
+    fn jump(&mut self, dest: U256) -> Result<(), Halt> {
+        let dest = saturating_usize(dest);
+        if !self.jumpdests.get(dest).copied().unwrap_or(false) {
+            return Err(Halt::InvalidJump);
+        }
+        self.pc = dest;
+        Ok(())
+    }
+
+    /// Implements `SSTORE` with the gas costs and refunds of EIP-2200,
+    /// EIP-2929 and EIP-3529.
+    fn sstore<D: Database>(
+        &mut self,
+        evm: &mut Evm<D>,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), Halt> {
+        let address = self.call.address;
+        let cold = evm.state.warm_slot(address, slot);
+        let current = evm.state.slot(address, slot);
+        let (original, current) = (current.original, current.current);
+
+        let mut cost = if cold { 2100 } else { 0 };
+        cost += if current == value || original != current {
+            100
+        } else if original.is_zero() {
+            20_000
+        } else {
+            2900
+        };
+        self.gas.charge(cost)?;
+
+        if current != value {
+            if original == current {
+                if !original.is_zero() && value.is_zero() {
+                    self.gas.refund += 4800;
+                }
+            } else {
+                if !original.is_zero() {
+                    if current.is_zero() {
+                        self.gas.refund -= 4800;
+                    } else if value.is_zero() {
+                        self.gas.refund += 4800;
+                    }
+                }
+                if original == value {
+                    self.gas.refund += if original.is_zero() {
+                        20_000 - 100
+                    } else {
+                        2900 - 100
+                    };
+                }
+            }
+        }
+        evm.state.sstore(address, slot, value);
+        Ok(())
+    }
+
+    /// Implements the `CALL` family of instructions.
+    fn call<D: Database>(&mut self, evm: &mut Evm<D>, opcode: u8) -> Result<(), Halt> {
+        let gas = self.pop()?;
+        let target = self.pop_address()?;
+        let value = if opcode == 0xf1 || opcode == 0xf2 {
+            self.pop()?
+        } else {
+            U256::zero()
+        };
+        let input_offset = self.pop()?;
+        let input_len = self.pop()?;
+        let output_offset = self.pop()?;
+        let output_len = self.pop()?;
+        if opcode == 0xf1 && !value.is_zero() {
+            self.check_static()?;
+        }
+
+        let input_offset = self.expand(input_offset, input_len)?;
+        let output = self.expand(output_offset, output_len)?;
+        let cold = evm.state.warm_account(target);
+        let mut cost = access_cost(cold, 2600);
+        if !value.is_zero() {
+            cost += 9000;
+            if opcode == 0xf1 && evm.state.account(target).is_empty() {
+                cost += 25_000;
+            }
+        }
+        self.gas.charge(cost)?;
+
+        let available = self.gas.remaining - self.gas.remaining / 64;
+        let gas = saturating_u64(gas).min(available);
+        self.gas.charge(gas)?;
+        let stipend = if value.is_zero() { 0 } else { 2300 };
+
+        let input = self.memory_slice(input_offset, input_len.low_u64() as usize);
+        let call = match opcode {
+            0xf1 => Call {
+                caller: self.call.address,
+                address: target,
+                code_address: target,
+                value,
+                transfer: true,
+                input,
+                gas: gas + stipend,
+                is_static: self.call.is_static,
+            },
+            0xf2 => Call {
+                caller: self.call.address,
+                address: self.call.address,
+                code_address: target,
+                value,
+                transfer: true,
+                input,
+                gas: gas + stipend,
+                is_static: self.call.is_static,
+            },
+            0xf4 => Call {
+                caller: self.call.caller,
+                address: self.call.address,
+                code_address: target,
+                value: self.call.value,
+                transfer: false,
+                input,
+                gas,
+                is_static: self.call.is_static,
+            },
+            _ => Call {
+                caller: self.call.address,
+                address: target,
+                code_address: target,
+                value: U256::zero(),
+                transfer: true,
+                input,
+                gas,
+                is_static: true,
+            },
+        };
+        let result = evm.call(call, self.depth + 1);
+
+        self.gas.remaining += result.gas_left;
+        let success = result.outcome.is_success();
+        if success {
+            self.gas.refund += result.refund;
+        }
+        self.return_data = match result.outcome {
+            Outcome::Success(data) | Outcome::Revert(data) => data,
+            Outcome::Halt(_) => Vec::new(),
+        };
+        let len = (output_len.low_u64() as usize).min(self.return_data.len());
+        if len > 0 {
+            self.memory[output..output + len].copy_from_slice(&self.return_data[..len]);
+        }
+        self.push(bool_to_word(success))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_arithmetic() {
+        let minus = |value: u64| I256::from(-(value as i64)).into_raw();
+        assert_eq!(sar(4.into(), minus(32)), minus(2));
+        assert_eq!(sar(300.into(), minus(1)), U256::MAX);
+        assert_eq!(sar(1.into(), 6.into()), 3.into());
+        assert_eq!(signextend(0.into(), 0xff.into()), U256::MAX);
+        assert_eq!(signextend(0.into(), 0x17f.into()), 0x7f.into());
+        assert_eq!(signextend(40.into(), 0xff.into()), 0xff.into());
+    }
+
+    #[test]
+    fn jump_destinations_skip_push_data() {
+        // PUSH1 0x5b JUMPDEST PUSH2 0x5b5b
+        assert_eq!(
+            analyze(&[0x60, 0x5b, 0x5b, 0x61, 0x5b, 0x5b]),
+            [false, false, true, false, false, false]
+        );
+    }
+}
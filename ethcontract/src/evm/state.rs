@@ -0,0 +1,331 @@
+//! Journaled account state used during execution, so that the changes of
+//! reverted calls can be undone.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use web3::signing::keccak256;
+use web3::types::{Address, H256, U256};
+
+/// The state of an account outside of its storage.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct AccountInfo {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Arc<[u8]>,
+}
+
+impl AccountInfo {
+    /// Returns whether the account is empty as defined by EIP-161.
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_zero() && self.nonce == 0 && self.code.is_empty()
+    }
+}
+
+/// The state that transactions are executed against.
+pub(crate) trait Database {
+    /// Returns the account at an address, or an empty account if it does not
+    /// exist.
+    fn account(&mut self, address: Address) -> AccountInfo;
+
+    /// Returns the value of a storage slot of an account.
+    fn storage(&mut self, address: Address, slot: U256) -> U256;
+
+    /// Returns the hash of a block, or zero if it is not known.
+    fn block_hash(&mut self, number: u64) -> H256;
+}
+
+/// A log emitted during execution.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+/// The state of an account after a transaction, along with the storage slots
+/// the transaction wrote.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct AccountChange {
+    pub info: AccountInfo,
+    pub storage: Vec<(U256, U256)>,
+    pub created: bool,
+    pub destroyed: bool,
+}
+
+/// A storage slot, with its value at the start of the transaction for
+/// computing SSTORE gas costs and refunds.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Slot {
+    pub original: U256,
+    pub current: U256,
+}
+
+#[derive(Clone, Debug)]
+struct Account {
+    info: AccountInfo,
+    storage: HashMap<U256, Slot>,
+    created: bool,
+    destroyed: bool,
+}
+
+/// A change that can be undone when reverting to a checkpoint.
+#[derive(Debug)]
+enum Entry {
+    Balance(Address, U256),
+    Nonce(Address, u64),
+    Code(Address, Arc<[u8]>),
+    Storage(Address, U256, U256),
+    Transient(Address, U256, U256),
+    Created(Address),
+    Destroyed(Address),
+    WarmAccount(Address),
+    WarmSlot(Address, U256),
+}
+
+/// A point in the journal that changes can be reverted to.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Checkpoint {
+    entries: usize,
+    logs: usize,
+}
+
+/// The state of the accounts touched by a transaction on top of a database.
+pub(crate) struct Journal<'a, D> {
+    db: &'a mut D,
+    accounts: HashMap<Address, Account>,
+    transient: HashMap<(Address, U256), U256>,
+    warm_accounts: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    entries: Vec<Entry>,
+    logs: Vec<Log>,
+}
+
+impl<'a, D: Database> Journal<'a, D> {
+    pub fn new(db: &'a mut D) -> Self {
+        Journal {
+            db,
+            accounts: HashMap::new(),
+            transient: HashMap::new(),
+            warm_accounts: HashSet::new(),
+            warm_slots: HashSet::new(),
+            entries: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    fn load(&mut self, address: Address) -> &mut Account {
+        let db = &mut self.db;
+        self.accounts.entry(address).or_insert_with(|| Account {
+            info: db.account(address),
+            storage: HashMap::new(),
+            created: false,
+            destroyed: false,
+        })
+    }
+
+    pub fn account(&mut self, address: Address) -> &AccountInfo {
+        &self.load(address).info
+    }
+
+    pub fn block_hash(&mut self, number: u64) -> H256 {
+        self.db.block_hash(number)
+    }
+
+    /// Returns the hash of the code of an account, or zero for empty
+    /// accounts.
+    pub fn code_hash(&mut self, address: Address) -> H256 {
+        let info = self.account(address);
+        if info.is_empty() {
+            H256::zero()
+        } else {
+            H256(keccak256(&info.code))
+        }
+    }
+
+    /// Marks an account as accessed, returning whether it was cold.
+    pub fn warm_account(&mut self, address: Address) -> bool {
+        let cold = self.warm_accounts.insert(address);
+        if cold {
+            self.entries.push(Entry::WarmAccount(address));
+        }
+        cold
+    }
+
+    /// Marks a storage slot as accessed, returning whether it was cold.
+    pub fn warm_slot(&mut self, address: Address, slot: U256) -> bool {
+        let cold = self.warm_slots.insert((address, slot));
+        if cold {
+            self.entries.push(Entry::WarmSlot(address, slot));
+        }
+        cold
+    }
+
+    pub fn slot(&mut self, address: Address, slot: U256) -> Slot {
+        if let Some(value) = self.load(address).storage.get(&slot) {
+            return *value;
+        }
+        let value = if self.accounts[&address].created {
+            U256::zero()
+        } else {
+            self.db.storage(address, slot)
+        };
+        let value = Slot {
+            original: value,
+            current: value,
+        };
+        self.load(address).storage.insert(slot, value);
+        value
+    }
+
+    pub fn sstore(&mut self, address: Address, slot: U256, value: U256) {
+        let old = self.slot(address, slot).current;
+        self.entries.push(Entry::Storage(address, slot, old));
+        if let Some(entry) = self.load(address).storage.get_mut(&slot) {
+            entry.current = value;
+        }
+    }
+
+    pub fn tload(&self, address: Address, slot: U256) -> U256 {
+        self.transient
+            .get(&(address, slot))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn tstore(&mut self, address: Address, slot: U256, value: U256) {
+        let old = self
+            .transient
+            .insert((address, slot), value)
+            .unwrap_or_default();
+        self.entries.push(Entry::Transient(address, slot, old));
+    }
+
+    pub fn set_balance(&mut self, address: Address, balance: U256) {
+        let account = self.load(address);
+        let old = std::mem::replace(&mut account.info.balance, balance);
+        self.entries.push(Entry::Balance(address, old));
+    }
+
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) {
+        let account = self.load(address);
+        let old = std::mem::replace(&mut account.info.nonce, nonce);
+        self.entries.push(Entry::Nonce(address, old));
+    }
+
+    pub fn set_code(&mut self, address: Address, code: Arc<[u8]>) {
+        let account = self.load(address);
+        let old = std::mem::replace(&mut account.info.code, code);
+        self.entries.push(Entry::Code(address, old));
+    }
+
+    /// Moves value between accounts, returning `false` without changing any
+    /// balances if the sender's balance is insufficient.
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) -> bool {
+        let balance = self.account(from).balance;
+        if balance < value {
+            return false;
+        }
+        if value.is_zero() || from == to {
+            self.load(to);
+            return true;
+        }
+        self.set_balance(from, balance - value);
+        let balance = self.account(to).balance;
+        self.set_balance(to, balance.saturating_add(value));
+        true
+    }
+
+    /// Marks an account as created by the current transaction, so that its
+    /// storage starts out empty.
+    pub fn mark_created(&mut self, address: Address) {
+        let account = self.load(address);
+        if !account.created {
+            account.created = true;
+            account.storage.clear();
+            self.entries.push(Entry::Created(address));
+        }
+    }
+
+    pub fn is_created(&mut self, address: Address) -> bool {
+        self.load(address).created
+    }
+
+    pub fn mark_destroyed(&mut self, address: Address) {
+        let account = self.load(address);
+        if !account.destroyed {
+            account.destroyed = true;
+            self.entries.push(Entry::Destroyed(address));
+        }
+    }
+
+    pub fn log(&mut self, log: Log) {
+        self.logs.push(log);
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            entries: self.entries.len(),
+            logs: self.logs.len(),
+        }
+    }
+
+    /// Undoes all changes made since a checkpoint.
+    pub fn revert(&mut self, checkpoint: Checkpoint) {
+        self.logs.truncate(checkpoint.logs);
+        while self.entries.len() > checkpoint.entries {
+            let entry = match self.entries.pop() {
+                Some(entry) => entry,
+                None => break,
+            };
+            match entry {
+                Entry::Balance(address, old) => self.load(address).info.balance = old,
+                Entry::Nonce(address, old) => self.load(address).info.nonce = old,
+                Entry::Code(address, old) => self.load(address).info.code = old,
+                Entry::Storage(address, slot, old) => {
+                    if let Some(entry) = self.load(address).storage.get_mut(&slot) {
+                        entry.current = old;
+                    }
+                }
+                Entry::Transient(address, slot, old) => {
+                    self.transient.insert((address, slot), old);
+                }
+                Entry::Created(address) => self.load(address).created = false,
+                Entry::Destroyed(address) => self.load(address).destroyed = false,
+                Entry::WarmAccount(address) => {
+                    self.warm_accounts.remove(&address);
+                }
+                Entry::WarmSlot(address, slot) => {
+                    self.warm_slots.remove(&(address, slot));
+                }
+            }
+        }
+    }
+
+    /// Finishes the transaction, returning its logs and the final state of
+    /// the accounts it touched.
+    pub fn finish(self) -> (Vec<Log>, Vec<(Address, AccountChange)>) {
+        let mut changes = self
+            .accounts
+            .into_iter()
+            .map(|(address, account)| {
+                let created = account.created;
+                let mut storage = account
+                    .storage
+                    .into_iter()
+                    .filter(|(_, slot)| created || slot.current != slot.original)
+                    .map(|(slot, value)| (slot, value.current))
+                    .collect::<Vec<_>>();
+                storage.sort();
+                let change = AccountChange {
+                    info: account.info,
+                    storage,
+                    created: account.created,
+                    destroyed: account.destroyed,
+                };
+                (address, change)
+            })
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|(address, _)| *address);
+        (self.logs, changes)
+    }
+}
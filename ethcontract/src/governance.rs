@@ -0,0 +1,260 @@
+//! Encoding of OpenZeppelin `Governor` proposals and `TimelockController`
+//! batch operations from contract method calls.
+//!
+//! A [`Proposal`] bundles the calls built with generated contract bindings
+//! into the `targets`, `values` and `calldatas` arrays expected by the
+//! `propose`, `queue` and `execute` functions of a governor, and by the
+//! `scheduleBatch` and `executeBatch` functions of a timelock:
+//!
+//! ```ignore
+//! let proposal = Proposal::new("Increase the fee to 1%")
+//!     .call(token.set_fee(100.into()))
+//!     .call(treasury.transfer(recipient, amount));
+//! let (targets, values, calldatas, description) = proposal.propose_args();
+//! governor.propose(targets, values, calldatas, description).send().await?;
+//! ```
+
+use crate::contract::MethodBuilder;
+use crate::tokens::{Bytes as TokenBytes, Tokenize};
+use ethcontract_common::abi::{self, Token};
+use ethcontract_common::hash::keccak256;
+use web3::types::{Address, Bytes, H256, U256};
+use web3::Transport;
+
+/// A single call executed by a proposal.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProposalAction {
+    /// The contract that is called.
+    pub target: Address,
+    /// The amount of ETH sent with the call.
+    pub value: U256,
+    /// The calldata of the call.
+    pub calldata: Bytes,
+}
+
+/// A governance proposal made of a list of calls and a description.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Proposal {
+    /// The calls executed by the proposal, in order.
+    pub actions: Vec<ProposalAction>,
+    /// The description of the proposal.
+    pub description: String,
+}
+
+impl Proposal {
+    /// Creates a new empty proposal with the specified description.
+    pub fn new(description: impl Into<String>) -> Self {
+        Proposal {
+            actions: Vec::new(),
+            description: description.into(),
+        }
+    }
+
+    /// Adds a contract method call to the proposal. The target, value and
+    /// calldata of the call are taken from the method builder, so any other
+    /// transaction parameters such as the sender or gas are ignored.
+    pub fn call<T: Transport, R: Tokenize, P>(self, method: MethodBuilder<T, R, P>) -> Self {
+        let tx = method.tx;
+        self.action(
+            tx.to.expect("method builders always have a target"),
+            tx.value.unwrap_or_default(),
+            tx.data.unwrap_or_default(),
+        )
+    }
+
+    /// Adds a raw call to the proposal.
+    pub fn action(mut self, target: Address, value: U256, calldata: Bytes) -> Self {
+        self.actions.push(ProposalAction {
+            target,
+            value,
+            calldata,
+        });
+        self
+    }
+
+    /// Returns the called contracts of the proposal.
+    pub fn targets(&self) -> Vec<Address> {
+        self.actions.iter().map(|action| action.target).collect()
+    }
+
+    /// Returns the amounts of ETH sent with each call of the proposal.
+    pub fn values(&self) -> Vec<U256> {
+        self.actions.iter().map(|action| action.value).collect()
+    }
+
+    /// Returns the calldatas of the proposal.
+    pub fn calldatas(&self) -> Vec<TokenBytes<Vec<u8>>> {
+        self.actions
+            .iter()
+            .map(|action| TokenBytes(action.calldata.0.clone()))
+            .collect()
+    }
+
+    /// Returns the hash of the description, which identifies the proposal
+    /// together with its calls when queuing and executing it.
+    pub fn description_hash(&self) -> H256 {
+        H256(keccak256(self.description.as_bytes()))
+    }
+
+    /// Computes the proposal ID assigned by a `Governor` contract, which is
+    /// `keccak256(abi.encode(targets, values, calldatas, descriptionHash))`.
+    pub fn id(&self) -> U256 {
+        let encoded = abi::encode(&[
+            self.targets().into_token(),
+            self.values().into_token(),
+            self.calldatas().into_token(),
+            Token::FixedBytes(self.description_hash().as_bytes().to_vec()),
+        ]);
+        U256::from_big_endian(&keccak256(encoded))
+    }
+
+    /// Computes the ID of the batch operation scheduled on a
+    /// `TimelockController` for the proposal calls, which is
+    /// `keccak256(abi.encode(targets, values, payloads, predecessor, salt))`.
+    pub fn timelock_operation_id(&self, predecessor: H256, salt: H256) -> H256 {
+        let encoded = abi::encode(&[
+            self.targets().into_token(),
+            self.values().into_token(),
+            self.calldatas().into_token(),
+            Token::FixedBytes(predecessor.as_bytes().to_vec()),
+            Token::FixedBytes(salt.as_bytes().to_vec()),
+        ]);
+        H256(keccak256(encoded))
+    }
+
+    /// Returns the arguments of the `Governor` `propose` function.
+    #[allow(clippy::type_complexity)]
+    pub fn propose_args(&self) -> (Vec<Address>, Vec<U256>, Vec<TokenBytes<Vec<u8>>>, String) {
+        (
+            self.targets(),
+            self.values(),
+            self.calldatas(),
+            self.description.clone(),
+        )
+    }
+
+    /// Returns the arguments of the `Governor` `queue` and `execute`
+    /// functions.
+    #[allow(clippy::type_complexity)]
+    pub fn execute_args(
+        &self,
+    ) -> (
+        Vec<Address>,
+        Vec<U256>,
+        Vec<TokenBytes<Vec<u8>>>,
+        TokenBytes<[u8; 32]>,
+    ) {
+        (
+            self.targets(),
+            self.values(),
+            self.calldatas(),
+            TokenBytes(self.description_hash().0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::Instance;
+    use crate::test::prelude::*;
+    use ethcontract_common::hash::function_selector;
+    use ethcontract_common::Abi;
+    use web3::api::Web3;
+
+    fn proposal() -> Proposal {
+        Proposal::new("Proposal #1").action(
+            Address::repeat_byte(0x42),
+            1.into(),
+            Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+        )
+    }
+
+    /// ABI encodes the arrays of the test proposal followed by the static
+    /// `words`.
+    fn encoded(words: &[H256]) -> Vec<u8> {
+        let offset = (3 + words.len()) * 32;
+        let words = words.iter().map(hex::encode).collect::<Vec<_>>();
+        let encoded = [
+            // offsets of the targets, values and calldatas arrays
+            format!("{:064x}", offset),
+            format!("{:064x}", offset + 0x40),
+            format!("{:064x}", offset + 0x80),
+            words.concat(),
+            // targets
+            format!("{:064x}", 1),
+            hex::encode(H256::from(Address::repeat_byte(0x42))),
+            // values
+            format!("{:064x}", 1),
+            format!("{:064x}", 1),
+            // calldatas
+            format!("{:064x}", 1),
+            format!("{:064x}", 0x20),
+            format!("{:064x}", 4),
+            format!("deadbeef{}", "0".repeat(56)),
+        ]
+        .concat();
+        hex::decode(encoded).unwrap()
+    }
+
+    #[test]
+    fn proposal_id() {
+        let proposal = proposal();
+        let description_hash = keccak256("Proposal #1");
+        assert_eq!(proposal.description_hash(), H256(description_hash));
+
+        let encoded = encoded(&[H256(description_hash)]);
+        assert_eq!(proposal.id(), U256::from_big_endian(&keccak256(encoded)));
+    }
+
+    #[test]
+    fn timelock_operation_id() {
+        let proposal = proposal();
+        let (predecessor, salt) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+        let encoded = encoded(&[predecessor, salt]);
+        assert_eq!(
+            proposal.timelock_operation_id(predecessor, salt),
+            H256(keccak256(encoded)),
+        );
+    }
+
+    #[test]
+    fn proposal_from_method_calls() {
+        let transport = TestTransport::new();
+        let abi = Abi::load(
+            r#"[{
+                "type": "function",
+                "name": "setFee",
+                "inputs": [{ "name": "fee", "type": "uint256" }],
+                "outputs": [],
+                "stateMutability": "payable"
+            }]"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let instance = Instance::at(Web3::new(transport), abi, Address::repeat_byte(0x42));
+
+        let proposal = Proposal::new("Set fee").call(
+            instance
+                .method::<_, ()>(function_selector("setFee(uint256)"), (U256::from(100),))
+                .unwrap()
+                .value(1.into()),
+        );
+
+        assert_eq!(
+            proposal.actions,
+            vec![ProposalAction {
+                target: Address::repeat_byte(0x42),
+                value: 1.into(),
+                calldata: Bytes(
+                    [
+                        &function_selector("setFee(uint256)")[..],
+                        H256::from_low_u64_be(100).as_bytes(),
+                    ]
+                    .concat()
+                ),
+            }],
+        );
+    }
+}
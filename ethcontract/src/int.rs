@@ -8,7 +8,6 @@ use std::fmt;
 use std::iter;
 use std::ops;
 use std::str;
-use std::{i128, i64, u64};
 use web3::types::U256;
 
 /// Compute the two's complement of a U256.
@@ -262,7 +261,7 @@ impl I256 {
 
     /// Convert from a decimal string.
     pub fn from_dec_str(value: &str) -> Result<Self, ParseI256Error> {
-        let (sign, value) = match value.as_bytes().get(0) {
+        let (sign, value) = match value.as_bytes().first() {
             Some(b'+') => (Sign::Positive, &value[1..]),
             Some(b'-') => (Sign::Negative, &value[1..]),
             _ => (Sign::Positive, value),
@@ -277,7 +276,7 @@ impl I256 {
 
     /// Convert from a hexadecimal string.
     pub fn from_hex_str(value: &str) -> Result<Self, ParseI256Error> {
-        let (sign, value) = match value.as_bytes().get(0) {
+        let (sign, value) = match value.as_bytes().first() {
             Some(b'+') => (Sign::Positive, &value[1..]),
             Some(b'-') => (Sign::Negative, &value[1..]),
             _ => (Sign::Positive, value),
@@ -910,8 +909,8 @@ macro_rules! impl_from {
                 type Error = TryFromBigIntError;
 
                 fn try_from(value: I256) -> Result<Self, Self::Error> {
-                    if value < I256::from(Self::min_value()) ||
-                        value > I256::from(Self::max_value()) {
+                    if value < I256::from(Self::MIN) ||
+                        value > I256::from(Self::MAX) {
                         return Err(TryFromBigIntError);
                     }
 
@@ -1239,7 +1238,7 @@ mod tests {
     }
 
     #[test]
-    #[allow(clippy::cognitive_complexity)]
+    #[allow(clippy::cognitive_complexity, clippy::redundant_pattern_matching)]
     fn std_num_conversion() {
         let small_positive = I256::from(42);
         let small_negative = I256::from(-42);
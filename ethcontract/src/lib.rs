@@ -93,22 +93,44 @@
 #[path = "test/macros.rs"]
 mod test_macros;
 
+pub mod abi;
+#[cfg(feature = "test-helpers")]
+pub mod arbitrary;
 pub mod batch;
+pub mod block;
+pub mod bridge;
+pub mod bytecode;
+pub mod ccip;
+pub mod chains;
 pub mod contract;
+pub mod dev;
 pub mod errors;
+mod evm;
+pub mod governance;
 mod int;
 pub mod log;
+pub mod multisig;
+pub mod nft;
+pub mod permit2;
+pub mod pipeline;
+pub mod rpc;
 pub mod secret;
+pub mod serialization;
+pub mod session;
+#[cfg(feature = "sinks")]
+pub mod sinks;
+pub mod storage;
 pub mod tokens;
 pub mod transaction;
 pub mod transport;
+pub mod units;
 
 pub use crate::contract::Instance;
 pub use crate::prelude::*;
 pub use ethcontract_common as common;
 pub use ethcontract_common::contract::Contract;
 #[cfg(feature = "derive")]
-pub use ethcontract_derive::contract;
+pub use ethcontract_derive::{address, contract, event_topic, selector};
 pub use futures;
 pub use jsonrpc_core as jsonrpc;
 pub use serde_json as json;
@@ -117,17 +139,33 @@ pub use web3;
 pub mod prelude {
     //! A prelude module for importing commonly used types when interacting with
     //! generated contracts.
-
-    pub use crate::contract::{Event, EventMetadata, EventStatus, RawLog, StreamEvent, Topic};
+    //!
+    //! All types in the prelude are also re-exported from the crate root. The
+    //! generated contract bindings refer to these re-exports and to the
+    //! modules of this crate, never to the underlying `web3` and `ethabi`
+    //! crates directly, so using them in application code ensures that the
+    //! versions of the `web3` and `ethabi` types always match the generated
+    //! code.
+
+    pub use crate::block::{BlockId, BlockTag};
+    pub use crate::contract::{
+        Event, EventId, EventMetadata, EventStatus, RawLog, StreamEvent, Topic,
+    };
     pub use crate::int::I256;
     pub use crate::secret::{Password, PrivateKey};
     pub use crate::tokens::Bytes;
     pub use crate::transaction::{Account, GasPrice};
+    pub use crate::transport::DynTransport;
     pub use ethcontract_common::TransactionHash;
     pub use web3::api::Web3;
+    pub use web3::error::Error as Web3Error;
     #[cfg(feature = "http")]
     pub use web3::transports::Http;
-    pub use web3::types::{Address, BlockId, BlockNumber, TransactionCondition, H160, H256, U256};
+    pub use web3::types::{
+        Address, BlockNumber, Log, TransactionCondition, TransactionReceipt, H160, H256, U128,
+        U256, U64,
+    };
+    pub use web3::{BatchTransport, DuplexTransport, Transport};
 }
 
 pub mod dyns {
@@ -136,7 +174,8 @@ pub mod dyns {
     //! generated code.
 
     use crate::contract::{
-        AllEventsBuilder, DeployBuilder, EventBuilder, Instance, MethodBuilder, ViewMethodBuilder,
+        AllEventsBuilder, DeployBuilder, EventBuilder, Instance, MethodBuilder, Payable,
+        ViewMethodBuilder,
     };
     pub use crate::transport::DynTransport;
     use web3::api::Web3;
@@ -151,7 +190,7 @@ pub mod dyns {
     pub type DynDeployBuilder<D> = DeployBuilder<DynTransport, D>;
 
     /// Type alias for a `MethodBuilder` with an underlying `DynTransport`.
-    pub type DynMethodBuilder<R> = MethodBuilder<DynTransport, R>;
+    pub type DynMethodBuilder<R, P = Payable> = MethodBuilder<DynTransport, R, P>;
 
     /// Type alias for a `ViewMethodBuilder` with an underlying `DynTransport`.
     pub type DynViewMethodBuilder<R> = ViewMethodBuilder<DynTransport, R>;
@@ -170,11 +209,13 @@ pub mod private {
     //! for these definitions.
 
     pub use lazy_static::lazy_static;
+    pub use serde;
 }
 
 #[cfg(test)]
 #[allow(missing_docs)]
 mod test {
+    pub mod contracts;
     pub mod prelude;
     pub mod transport;
 }
@@ -1,19 +1,34 @@
 //! This module implements event builders and streams for retrieving events
 //! emitted by a contract.
 
+mod bloom;
+mod filter;
+mod multiplex;
+mod range;
+mod source;
+
+pub use self::bloom::Bloom;
+use self::bloom::BloomLogStream;
+use self::filter::FilterLogStream;
+pub use self::multiplex::{LogMultiplexer, MultiplexedLogStream};
+pub use self::range::AdaptiveBlockRange;
+pub use self::source::{LogSource, DEFAULT_MAX_SUBSCRIPTION_FAILURES};
+use crate::block::{BlockId, BlockTag};
 use crate::errors::ExecutionError;
 use ethcontract_common::abi::{Topic, TopicFilter};
-use futures::future::{self, TryFutureExt};
-use futures::stream::{self, Stream, TryStreamExt};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use std::num::NonZeroU64;
 use std::time::Duration;
 use web3::api::Web3;
-use web3::types::{Address, BlockNumber, Filter, FilterBuilder, Log, H256};
-use web3::Transport;
+use web3::types::{Address, BlockNumber, Filter, FilterBuilder, Log, H256, U256};
+use web3::{DuplexTransport, Transport};
 
 /// The default poll interval to use for polling logs from the block chain.
 #[cfg(not(test))]
 pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The poll interval used in tests, so that polling streams resolve
+/// immediately.
 #[cfg(test)]
 pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(0);
 
@@ -29,12 +44,15 @@ pub struct LogFilterBuilder<T: Transport> {
     web3: Web3<T>,
     /// The block to start streaming logs from.
     ///
-    /// See [`web3::types::BlockNumber`] for more details on possible values.
-    pub from_block: Option<BlockNumber>,
+    /// See [`BlockId`] for more details on possible values.
+    pub from_block: Option<BlockId>,
     /// The block to stop streaming logs from.
     ///
-    /// See [`web3::types::BlockNumber`] for more details on possible values.
-    pub to_block: Option<BlockNumber>,
+    /// See [`BlockId`] for more details on possible values.
+    pub to_block: Option<BlockId>,
+    /// The hash of a single block to retrieve logs for, in which case the
+    /// from and to blocks are ignored.
+    pub block_hash: Option<H256>,
     /// The contract addresses to filter logs for.
     pub address: Vec<Address>,
     /// Topic filters used for filtering logs based on indexed topics.
@@ -48,8 +66,17 @@ pub struct LogFilterBuilder<T: Transport> {
     /// logs. This provides no guarantee in how many logs will be returned per
     /// page, but used to limit the block range for the query.
     pub block_page_size: Option<NonZeroU64>,
+    /// The adaptive block page size to use when doing a paginated query on
+    /// past logs, in which case the block page size is ignored.
+    pub block_range: Option<AdaptiveBlockRange>,
     /// The polling interval for querying the node for more logs.
     pub poll_interval: Option<Duration>,
+    /// The expected block time of the chain, used as the polling interval
+    /// if none is specified.
+    pub block_time: Option<Duration>,
+    /// Stream logs by polling blocks and only querying the logs of blocks
+    /// whose logs bloom possibly matches the filter.
+    pub bloom_filter: bool,
 }
 
 impl<T: Transport> LogFilterBuilder<T> {
@@ -59,11 +86,15 @@ impl<T: Transport> LogFilterBuilder<T> {
             web3,
             from_block: None,
             to_block: None,
+            block_hash: None,
             address: Vec::new(),
             topics: TopicFilter::default(),
             limit: None,
             block_page_size: None,
+            block_range: None,
             poll_interval: None,
+            block_time: None,
+            bloom_filter: false,
         }
     }
 
@@ -71,8 +102,8 @@ impl<T: Transport> LogFilterBuilder<T> {
     ///
     /// If left unset defaults to the latest block.
     #[allow(clippy::wrong_self_convention)]
-    pub fn from_block(mut self, block: BlockNumber) -> Self {
-        self.from_block = Some(block);
+    pub fn from_block(mut self, block: impl Into<BlockId>) -> Self {
+        self.from_block = Some(block.into());
         self
     }
 
@@ -80,8 +111,17 @@ impl<T: Transport> LogFilterBuilder<T> {
     ///
     /// If left unset defaults to the streaming until the end of days.
     #[allow(clippy::wrong_self_convention)]
-    pub fn to_block(mut self, block: BlockNumber) -> Self {
-        self.to_block = Some(block);
+    pub fn to_block(mut self, block: impl Into<BlockId>) -> Self {
+        self.to_block = Some(block.into());
+        self
+    }
+
+    /// Restricts the logs to the block with the specified hash, as specified
+    /// in EIP-234. This takes precedence over the from and to blocks.
+    ///
+    /// Note that this option cannot be used for log streams.
+    pub fn block_hash(mut self, hash: H256) -> Self {
+        self.block_hash = Some(hash);
         self
     }
 
@@ -138,6 +178,14 @@ impl<T: Transport> LogFilterBuilder<T> {
         self
     }
 
+    /// Uses an adaptive block page size when doing a paginated query on past
+    /// logs, that shrinks when the provider rejects a query because its block
+    /// range is too large. See [`AdaptiveBlockRange`] for more details.
+    pub fn adaptive_block_range(mut self, value: AdaptiveBlockRange) -> Self {
+        self.block_range = Some(value);
+        self
+    }
+
     /// The polling interval. This is used as the interval between consecutive
     /// `eth_getLogs` calls to get log updates.
     pub fn poll_interval(mut self, value: Duration) -> Self {
@@ -145,14 +193,71 @@ impl<T: Transport> LogFilterBuilder<T> {
         self
     }
 
+    /// The expected block time of the chain. If no polling interval is
+    /// specified, log streams poll the node once per block instead of using
+    /// the default polling interval.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.block_time = Some(value);
+        self
+    }
+
+    /// Returns the polling interval of log streams.
+    fn resolved_poll_interval(&self) -> Duration {
+        self.poll_interval
+            .or(self.block_time)
+            .unwrap_or(DEFAULT_POLL_INTERVAL)
+    }
+
+    /// Specifies whether log streams poll new blocks and check their logs
+    /// bloom before querying their logs, instead of using a node side log
+    /// filter.
+    ///
+    /// This skips the `eth_getLogs` query for blocks that provably contain no
+    /// matching logs, which cuts RPC usage for sparse log streams on
+    /// backends that do not support log filters or that charge per request.
+    /// Note that this requires one `eth_getBlockByNumber` request per block.
+    /// Logs of blocks removed by re-orgs of up to 64 blocks are emitted as
+    /// removed logs.
+    pub fn bloom_filter(mut self, enabled: bool) -> Self {
+        self.bloom_filter = enabled;
+        self
+    }
+
+    /// Resolves the from and to blocks that are specified by hash or by the
+    /// `safe` and `finalized` tags to their block numbers, since they can not
+    /// be expressed in web3 filters. This is done when logs are queried or
+    /// streamed, and is only needed for using [`LogFilterBuilder::into_filter`]
+    /// directly.
+    pub async fn resolve_blocks(mut self) -> Result<Self, ExecutionError> {
+        if self.block_hash.is_some() {
+            return Ok(self);
+        }
+        for block in &mut [&mut self.from_block, &mut self.to_block] {
+            if let Some(id) = block {
+                if id.block_number().is_none() {
+                    **block = Some(id.resolve_block_number(&self.web3).await?.into());
+                }
+            }
+        }
+        Ok(self)
+    }
+
     /// Returns a web3 filter builder needed for querying and streaming logs.
+    ///
+    /// Note that from and to blocks that are specified by hash or by the
+    /// `safe` and `finalized` tags are ignored unless they were resolved with
+    /// [`LogFilterBuilder::resolve_blocks`].
     pub fn into_filter(self) -> FilterBuilder {
         let mut filter = FilterBuilder::default();
-        if let Some(from_block) = self.from_block {
-            filter = filter.from_block(from_block);
-        }
-        if let Some(to_block) = self.to_block {
-            filter = filter.to_block(to_block);
+        if let Some(block_hash) = self.block_hash {
+            filter = filter.block_hash(block_hash);
+        } else {
+            if let Some(from_block) = self.from_block.and_then(BlockId::block_number) {
+                filter = filter.from_block(from_block);
+            }
+            if let Some(to_block) = self.to_block.and_then(BlockId::block_number) {
+                filter = filter.to_block(to_block);
+            }
         }
         if !self.address.is_empty() {
             filter = filter.address(self.address);
@@ -177,7 +282,7 @@ impl<T: Transport> LogFilterBuilder<T> {
     /// use the `past_logs_pages` method instead.
     pub async fn past_logs(self) -> Result<Vec<Log>, ExecutionError> {
         let web3 = self.web3.clone();
-        let filter = self.into_filter();
+        let filter = self.resolve_blocks().await?.into_filter();
         let logs = web3.eth().logs(filter.build()).await?;
 
         Ok(logs)
@@ -195,24 +300,115 @@ impl<T: Transport> LogFilterBuilder<T> {
     }
 
     /// Creates a filter-based log stream that emits logs for each filter change.
+    ///
+    /// The stream polls a node side filter created with `eth_newFilter`. If
+    /// the node removes the filter, for example because it was not polled
+    /// within the node's filter timeout, then the filter is re-created
+    /// starting at the block of the last emitted log and the logs that were
+    /// missed in the meantime are emitted with `eth_getFilterLogs`. If no log
+    /// was emitted yet, the filter is re-created with its original block
+    /// range, except that filters starting at the latest block start after
+    /// the block that was the latest when the filter was first created, which
+    /// costs an additional `eth_blockNumber` request.
+    ///
+    /// If the bloom filter option is enabled, the stream instead polls blocks
+    /// starting at the from block, or the next block if not specified, and
+    /// ends after the to block if it is a block number.
     pub fn stream(self) -> impl Stream<Item = Result<Log, ExecutionError>> {
-        let web3 = self.web3.clone();
-        let poll_interval = self.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
-        let filter = self.into_filter();
+        if self.bloom_filter && self.block_hash.is_none() {
+            return self.bloom_stream().left_stream();
+        }
 
-        async move {
-            let eth_filter = web3
-                .eth_filter()
-                .create_logs_filter(filter.build())
-                .await
-                .map_err(ExecutionError::from)?;
-            let stream = eth_filter
-                .stream(poll_interval)
-                .map_err(ExecutionError::from);
+        self.filter_stream(None).right_stream()
+    }
+
+    /// Creates a log stream polling a node side filter. If the position of a
+    /// previously emitted log is specified, then the logs following it that
+    /// are already part of the chain are emitted first.
+    pub(crate) fn filter_stream(
+        self,
+        resume_after: Option<(u64, U256)>,
+    ) -> impl Stream<Item = Result<Log, ExecutionError>> {
+        stream::once(self.resolve_blocks())
+            .map_ok(move |builder| {
+                let web3 = builder.web3.clone();
+                let poll_interval = builder.resolved_poll_interval();
+                let block_hash = builder.block_hash.is_some();
+                let starts_at_latest = match builder.from_block.and_then(BlockId::block_number) {
+                    None | Some(BlockNumber::Latest) | Some(BlockNumber::Pending) => !block_hash,
+                    _ => false,
+                };
+                let filter = builder.into_filter();
+                let mut state = FilterLogStream::new(web3, filter, block_hash, poll_interval);
+                if starts_at_latest {
+                    state = state.starts_at_latest();
+                }
+                if let Some(last_log) = resume_after {
+                    state = state.resume_after(last_log);
+                }
+
+                stream::try_unfold(state, FilterLogStream::next)
+            })
+            .try_flatten()
+    }
 
-            Ok(stream)
+    /// Creates a log stream that shares an `eth_subscribe` subscription with
+    /// other streams of the specified multiplexer. See [`LogMultiplexer`] for
+    /// more details.
+    ///
+    /// Only the address and topic filters are used, the stream emits logs of
+    /// new blocks as they get pushed by the node.
+    pub fn multiplexed_stream<M: DuplexTransport>(
+        self,
+        multiplexer: &LogMultiplexer<M>,
+    ) -> MultiplexedLogStream<M> {
+        multiplexer.stream(self.address, self.topics)
+    }
+
+    /// Returns a copy of the topic filters, since `TopicFilter` does not
+    /// implement `Clone`.
+    pub(crate) fn copy_topics(&self) -> TopicFilter {
+        TopicFilter {
+            topic0: copy_topic(&self.topics.topic0),
+            topic1: copy_topic(&self.topics.topic1),
+            topic2: copy_topic(&self.topics.topic2),
+            topic3: copy_topic(&self.topics.topic3),
         }
-        .try_flatten_stream()
+    }
+
+    /// Creates a log stream polling blocks and checking their logs bloom.
+    fn bloom_stream(self) -> impl Stream<Item = Result<Log, ExecutionError>> {
+        stream::once(self.resolve_blocks())
+            .map_ok(|builder| {
+                let block_number = |block| match block {
+                    Some(BlockId::Number(number)) => Some(number.as_u64()),
+                    Some(BlockId::Tag(BlockTag::Earliest)) => Some(0),
+                    _ => None,
+                };
+                let next_block = block_number(builder.from_block);
+                let end_block = block_number(builder.to_block);
+
+                let state = BloomLogStream {
+                    web3: builder.web3.clone(),
+                    addresses: builder.address.clone(),
+                    topics: builder.copy_topics(),
+                    poll_interval: builder.resolved_poll_interval(),
+                    next_block,
+                    end_block,
+                    latest_block: 0,
+                    recent: Default::default(),
+                    pending: Default::default(),
+                    filter: LogFilterBuilder {
+                        from_block: None,
+                        to_block: None,
+                        limit: None,
+                        ..builder
+                    }
+                    .into_filter(),
+                };
+                stream::try_unfold(state, BloomLogStream::next)
+            })
+            .try_flatten()
     }
 }
 
@@ -225,6 +421,15 @@ fn topic_to_option(topic: Topic<H256>) -> Option<Vec<H256>> {
     }
 }
 
+/// Copies a `Topic`, since it does not implement `Clone`.
+fn copy_topic(topic: &Topic<H256>) -> Topic<H256> {
+    match topic {
+        Topic::Any => Topic::Any,
+        Topic::OneOf(v) => Topic::OneOf(v.clone()),
+        Topic::This(t) => Topic::This(*t),
+    }
+}
+
 /// Internal unfold context for creating a `past_logs` `Stream`.
 enum PastLogsStream<T: Transport> {
     Init(LogFilterBuilder<T>),
@@ -259,14 +464,29 @@ impl<T: Transport> PastLogsStream<T> {
     }
 
     async fn init(builder: LogFilterBuilder<T>) -> Result<Self, ExecutionError> {
-        let from_block = builder.from_block.unwrap_or(BlockNumber::Latest);
-        let to_block = builder.to_block.unwrap_or(BlockNumber::Latest);
+        if builder.block_hash.is_some() {
+            let web3 = builder.web3.clone();
+            return Ok(PastLogsStream::Querying(
+                web3,
+                builder.into_filter().build(),
+            ));
+        }
+
+        let builder = builder.resolve_blocks().await?;
+        let block_number = |block: Option<BlockId>| {
+            block
+                .and_then(BlockId::block_number)
+                .unwrap_or(BlockNumber::Latest)
+        };
+        let from_block = block_number(builder.from_block);
+        let to_block = block_number(builder.to_block);
 
         let web3 = builder.web3.clone();
         let block_page_size = builder
             .block_page_size
             .map(|size| size.get())
             .unwrap_or(DEFAULT_BLOCK_PAGE_SIZE);
+        let block_range = builder.block_range.clone();
         let filter = builder.into_filter();
 
         let start_block = match from_block {
@@ -288,6 +508,7 @@ impl<T: Transport> PastLogsStream<T> {
                 web3,
                 to_block,
                 block_page_size,
+                block_range,
                 filter,
                 page_block,
                 end_block,
@@ -307,6 +528,9 @@ struct PastLogsPager<T: Transport> {
     to_block: BlockNumber,
     /// The block page size being used for queries.
     block_page_size: u64,
+    /// The adaptive block page size being used for queries instead of the
+    /// block page size, if specified.
+    block_range: Option<AdaptiveBlockRange>,
     /// The web3 filter used for retrieving the logs.
     filter: FilterBuilder,
 
@@ -326,8 +550,13 @@ impl<T: Transport> PastLogsPager<T> {
         );
 
         while self.page_block <= self.end_block {
+            let page_size = match &self.block_range {
+                Some(range) => range.get(),
+                None => self.block_page_size,
+            };
             // NOTE: Log block ranges are inclusive.
-            let page_end = self.page_block + self.block_page_size - 1;
+            let page_end = self.page_block + page_size - 1;
+            let page_range = page_end.min(self.end_block) - self.page_block + 1;
             let page_to_block = if page_end < self.end_block {
                 BlockNumber::Number(page_end.into())
             } else {
@@ -340,7 +569,7 @@ impl<T: Transport> PastLogsPager<T> {
                 self.to_block
             };
 
-            let page = self
+            let result = self
                 .web3
                 .eth()
                 .logs(
@@ -350,7 +579,21 @@ impl<T: Transport> PastLogsPager<T> {
                         .to_block(page_to_block)
                         .build(),
                 )
-                .await?;
+                .await
+                .map_err(ExecutionError::from);
+            let page = match (result, &self.block_range) {
+                (Ok(page), Some(range)) => {
+                    range.succeed(page_range);
+                    page
+                }
+                (Ok(page), None) => page,
+                (Err(err), Some(range))
+                    if range::is_range_error(&err) && range.shrink(page_range) =>
+                {
+                    continue
+                }
+                (Err(err), _) => return Err(err),
+            };
 
             self.page_block = page_end + 1;
             if page.is_empty() {
@@ -367,6 +610,7 @@ impl<T: Transport> PastLogsPager<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::errors::RpcError;
     use crate::test::prelude::*;
     use futures::stream::StreamExt;
     use serde_json::Value;
@@ -388,6 +632,20 @@ mod tests {
         })
     }
 
+    #[test]
+    fn poll_interval_from_block_time() {
+        let web3 = Web3::new(TestTransport::new());
+        let block_time = Duration::from_millis(250);
+        let poll_interval = Duration::from_secs(1);
+
+        let builder = LogFilterBuilder::new(web3);
+        assert_eq!(builder.resolved_poll_interval(), DEFAULT_POLL_INTERVAL);
+        let builder = builder.block_time(block_time);
+        assert_eq!(builder.resolved_poll_interval(), block_time);
+        let builder = builder.poll_interval(poll_interval);
+        assert_eq!(builder.resolved_poll_interval(), poll_interval);
+    }
+
     #[test]
     fn past_logs_options() {
         let mut transport = TestTransport::new();
@@ -400,7 +658,7 @@ mod tests {
         transport.add_response(json!([generate_log("awesome")]));
 
         let logs = LogFilterBuilder::new(web3)
-            .from_block(66.into())
+            .from_block(66)
             .to_block(BlockNumber::Pending)
             .address(vec![address])
             .topic0(Topic::This(topics[0]))
@@ -433,6 +691,43 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn past_logs_resolve_block_hashes_and_tags() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let hash = H256::repeat_byte(0x42);
+
+        let block = |number: u64| {
+            json!(web3::types::Block::<H256> {
+                hash: Some(H256::repeat_byte(number as _)),
+                number: Some(number.into()),
+                ..Default::default()
+            })
+        };
+        transport.add_response(block(10));
+        transport.add_response(block(20));
+        transport.add_response(json!([generate_log("awesome")]));
+
+        let logs = LogFilterBuilder::new(web3)
+            .from_block(hash)
+            .to_block(BlockTag::Finalized)
+            .past_logs()
+            .immediate()
+            .expect("failed to get past logs");
+
+        assert_eq!(logs[0].log_type.as_deref(), Some("awesome"));
+        transport.assert_request("eth_getBlockByHash", &[json!(hash), json!(false)]);
+        transport.assert_request("eth_getBlockByNumber", &[json!("finalized"), json!(false)]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "fromBlock": U64::from(10),
+                "toBlock": U64::from(20),
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn past_log_stream_logs() {
         let mut transport = TestTransport::new();
@@ -450,7 +745,7 @@ mod tests {
         transport.add_response(json!([log, log]));
 
         let mut raw_events = LogFilterBuilder::new(web3)
-            .from_block(10.into())
+            .from_block(10)
             .to_block(BlockNumber::Pending)
             .address(vec![address])
             .topic0(Topic::This(topic))
@@ -511,11 +806,50 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn past_log_stream_adaptive_block_range() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let range = AdaptiveBlockRange::new(10);
+
+        transport.add_error(web3::Error::Rpc(jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::ServerError(-32005),
+            message: "query returned more than 10000 results".to_owned(),
+            data: None,
+        }));
+        for _ in 0..4 {
+            transport.add_response(json!([generate_log("awesome")]));
+        }
+
+        let pages = LogFilterBuilder::new(web3)
+            .from_block(0)
+            .to_block(19)
+            .adaptive_block_range(range.clone())
+            .past_logs_pages()
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(pages.len(), 4);
+        assert_eq!(range.get(), 5);
+        for (from_block, to_block) in &[(0, 9), (0, 4), (5, 9), (10, 14), (15, 19)] {
+            transport.assert_request(
+                "eth_getLogs",
+                &[json!({
+                    "fromBlock": U64::from(*from_block),
+                    "toBlock": U64::from(*to_block),
+                })],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn log_stream_next_log() {
         let mut transport = TestTransport::new();
         let web3 = Web3::new(transport.clone());
 
+        // latest block
+        transport.add_response(json!("0x1"));
         // filter created
         transport.add_response(json!("0xf0"));
         // get logs filter
@@ -530,8 +864,264 @@ mod tests {
             .expect("failed to get log from log stream");
 
         assert_eq!(log.log_type.as_deref(), Some("awesome"));
+        transport.assert_request("eth_blockNumber", &[]);
         transport.assert_request("eth_newFilter", &[json!({})]);
         transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
         transport.assert_no_more_requests();
     }
+
+    fn generate_positioned_log(block: u64, index: u64) -> Value {
+        let mut log = generate_log("positioned");
+        log["blockNumber"] = json!(U64::from(block));
+        log["logIndex"] = json!(U256::from(index));
+        log
+    }
+
+    fn filter_not_found() -> web3::Error {
+        web3::Error::Rpc(jsonrpc_core::Error {
+            code: (-32000).into(),
+            message: "filter not found".to_owned(),
+            data: None,
+        })
+    }
+
+    #[test]
+    fn log_stream_recreates_missing_filters() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // latest block
+        transport.add_response(json!("0x1"));
+        // filter created
+        transport.add_response(json!("0xf0"));
+        // get filter changes
+        transport.add_response(json!([generate_positioned_log(5, 0)]));
+        // filter expired
+        transport.add_error(filter_not_found());
+        // filter re-created
+        transport.add_response(json!("0xf1"));
+        // get filter logs, including the already emitted log
+        transport.add_response(json!([
+            generate_positioned_log(5, 0),
+            generate_positioned_log(5, 1)
+        ]));
+
+        let logs = LogFilterBuilder::new(web3)
+            .stream()
+            .take(2)
+            .collect::<Vec<_>>()
+            .wait()
+            .into_iter()
+            .map(|log| {
+                let log = log.expect("failed to get log from log stream");
+                (log.block_number.unwrap().as_u64(), log.log_index.unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(logs, vec![(5, 0.into()), (5, 1.into())]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_newFilter", &[json!({})]);
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_request("eth_newFilter", &[json!({ "fromBlock": "0x5" })]);
+        transport.assert_request("eth_getFilterLogs", &[json!("0xf1")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn log_stream_recreates_filters_from_start_block() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // latest block
+        transport.add_response(json!("0x4"));
+        // filter created
+        transport.add_response(json!("0xf0"));
+        // filter expired before any log was emitted
+        transport.add_error(filter_not_found());
+        // filter re-created
+        transport.add_response(json!("0xf1"));
+        // get filter logs of the blocks since the filter was first created
+        transport.add_response(json!([generate_positioned_log(5, 0)]));
+
+        let log = LogFilterBuilder::new(web3)
+            .stream()
+            .boxed()
+            .next()
+            .wait()
+            .expect("log stream did not produce any logs")
+            .expect("failed to get log from log stream");
+
+        assert_eq!(log.block_number, Some(5.into()));
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_newFilter", &[json!({})]);
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_request("eth_newFilter", &[json!({ "fromBlock": "0x5" })]);
+        transport.assert_request("eth_getFilterLogs", &[json!("0xf1")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn log_stream_fails_when_filters_keep_missing() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!("0xf0"));
+        for _ in 0..=3 {
+            transport.add_error(filter_not_found());
+            transport.add_response(json!("0xf0"));
+            transport.add_response(json!([]));
+        }
+
+        let err = LogFilterBuilder::new(web3)
+            .stream()
+            .boxed()
+            .next()
+            .wait()
+            .expect("log stream ended")
+            .unwrap_err();
+
+        assert_eq!(err.rpc_error(), Some(RpcError::FilterNotFound));
+    }
+
+    fn generate_block(number: u64, hash: H256, parent_hash: H256, bloom: Option<Bloom>) -> Value {
+        json!({
+            "hash": hash,
+            "parentHash": parent_hash,
+            "sha3Uncles": H256::zero(),
+            "miner": Address::zero(),
+            "stateRoot": H256::zero(),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": U64::from(number),
+            "gasUsed": "0x0",
+            "gasLimit": "0x0",
+            "extraData": "0x",
+            "logsBloom": bloom.map(|bloom| bloom.0),
+            "timestamp": "0x0",
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "sealFields": [],
+            "uncles": [],
+            "transactions": [],
+            "size": "0x0",
+            "mixHash": H256::zero(),
+            "nonce": "0x0000000000000000",
+        })
+    }
+
+    #[test]
+    fn bloom_stream_skips_non_matching_blocks() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = Address::repeat_byte(0x42);
+        let topic = H256::repeat_byte(42);
+        let mut bloom = Bloom::default();
+        bloom.accrue(address.as_bytes());
+        bloom.accrue(topic.as_bytes());
+
+        // get latest block
+        transport.add_response(json!(U64::from(11)));
+        // block 10 without matching logs
+        transport.add_response(generate_block(
+            10,
+            H256::repeat_byte(10),
+            H256::repeat_byte(9),
+            Some(Bloom::default()),
+        ));
+        // block 11 with possibly matching logs
+        transport.add_response(generate_block(
+            11,
+            H256::repeat_byte(11),
+            H256::repeat_byte(10),
+            Some(bloom),
+        ));
+        transport.add_response(json!([generate_log("awesome")]));
+
+        let logs = LogFilterBuilder::new(web3)
+            .from_block(10)
+            .to_block(11)
+            .address(vec![address])
+            .topic0(Topic::This(topic))
+            .bloom_filter(true)
+            .stream()
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(
+            logs[0].as_ref().unwrap().log_type.as_deref(),
+            Some("awesome")
+        );
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request(
+            "eth_getBlockByNumber",
+            &[json!(U64::from(10)), json!(false)],
+        );
+        transport.assert_request(
+            "eth_getBlockByNumber",
+            &[json!(U64::from(11)), json!(false)],
+        );
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "blockHash": H256::repeat_byte(11),
+                "topics": [topic],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn bloom_stream_removes_reorged_logs() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // get latest block
+        transport.add_response(json!(U64::from(11)));
+        // block 10 which later gets re-orged
+        transport.add_response(generate_block(10, H256::repeat_byte(1), H256::zero(), None));
+        transport.add_response(json!([generate_log("reorged")]));
+        // block 11 on top of a different block 10
+        transport.add_response(generate_block(
+            11,
+            H256::repeat_byte(3),
+            H256::repeat_byte(2),
+            None,
+        ));
+        // new block 10 and block 11
+        transport.add_response(generate_block(10, H256::repeat_byte(2), H256::zero(), None));
+        transport.add_response(json!([]));
+        transport.add_response(generate_block(
+            11,
+            H256::repeat_byte(3),
+            H256::repeat_byte(2),
+            None,
+        ));
+        transport.add_response(json!([generate_log("new")]));
+
+        let logs = LogFilterBuilder::new(web3)
+            .from_block(10)
+            .to_block(11)
+            .bloom_filter(true)
+            .stream()
+            .map(|log| {
+                let log = log.unwrap();
+                (log.log_type.unwrap(), log.removed)
+            })
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(
+            logs,
+            vec![
+                ("reorged".to_owned(), Some(false)),
+                ("reorged".to_owned(), Some(true)),
+                ("new".to_owned(), Some(false)),
+            ]
+        );
+    }
 }
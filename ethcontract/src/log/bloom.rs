@@ -0,0 +1,249 @@
+//! Implementation of block header logs bloom checking, used for streaming
+//! logs by polling blocks and skipping `eth_getLogs` queries for blocks that
+//! provably contain no matching logs.
+
+use crate::errors::ExecutionError;
+use ethcontract_common::abi::{Topic, TopicFilter};
+use ethcontract_common::hash;
+use futures_timer::Delay;
+use std::collections::VecDeque;
+use std::time::Duration;
+use web3::api::Web3;
+use web3::types::{Address, BlockId, FilterBuilder, Log, H2048, H256};
+use web3::Transport;
+
+/// The number of most recent blocks whose logs are remembered in order to
+/// emit removed logs on re-orgs.
+const MAX_REORG_DEPTH: usize = 64;
+
+/// A 2048-bit logs bloom filter, as included in block headers.
+///
+/// A bloom filter can have false positives but no false negatives, so a block
+/// whose bloom does not contain an address or topic provably contains no logs
+/// emitted by that address or with that topic.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Bloom(pub H2048);
+
+impl Bloom {
+    /// Adds an input, such as an address or a topic, to the bloom filter.
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (index, mask) in bloom_bits(input) {
+            self.0 .0[index] |= mask;
+        }
+    }
+
+    /// Returns `true` if the bloom filter possibly contains an input.
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        bloom_bits(input)
+            .iter()
+            .all(|&(index, mask)| self.0 .0[index] & mask == mask)
+    }
+
+    /// Returns `true` if the block this bloom filter belongs to possibly
+    /// contains a log emitted by the specified address with all of the
+    /// specified topics.
+    pub fn possibly_contains(&self, address: Address, topics: &[H256]) -> bool {
+        self.contains_input(address.as_bytes())
+            && topics
+                .iter()
+                .all(|topic| self.contains_input(topic.as_bytes()))
+    }
+
+    /// Returns `true` if the block this bloom filter belongs to possibly
+    /// contains a log matching a log filter with the specified addresses and
+    /// topics. An empty address list matches logs from any address.
+    pub fn possibly_matches(&self, addresses: &[Address], topics: &TopicFilter) -> bool {
+        let topic_matches = |topic: &Topic<H256>| match topic {
+            Topic::Any => true,
+            Topic::OneOf(topics) => topics
+                .iter()
+                .any(|topic| self.contains_input(topic.as_bytes())),
+            Topic::This(topic) => self.contains_input(topic.as_bytes()),
+        };
+
+        (addresses.is_empty()
+            || addresses
+                .iter()
+                .any(|address| self.contains_input(address.as_bytes())))
+            && topic_matches(&topics.topic0)
+            && topic_matches(&topics.topic1)
+            && topic_matches(&topics.topic2)
+            && topic_matches(&topics.topic3)
+    }
+}
+
+impl From<H2048> for Bloom {
+    fn from(bloom: H2048) -> Self {
+        Bloom(bloom)
+    }
+}
+
+/// Computes the byte indices and bit masks of the three bits an input sets in
+/// a bloom filter, as specified in the Ethereum yellow paper.
+fn bloom_bits(input: &[u8]) -> [(usize, u8); 3] {
+    let hash = hash::keccak256(input);
+    let mut bits = [(0, 0); 3];
+    for (i, bit) in bits.iter_mut().enumerate() {
+        let position = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) % 2048;
+        *bit = (255 - position / 8, 1 << (position % 8));
+    }
+    bits
+}
+
+/// Internal state of a log stream that polls blocks and only queries the logs
+/// of blocks whose logs bloom possibly matches the filter.
+pub(crate) struct BloomLogStream<T: Transport> {
+    pub(crate) web3: Web3<T>,
+    /// The web3 filter without a block range, used for querying the logs of
+    /// single blocks.
+    pub(crate) filter: FilterBuilder,
+    pub(crate) addresses: Vec<Address>,
+    pub(crate) topics: TopicFilter,
+    pub(crate) poll_interval: Duration,
+    /// The next block to process, or `None` to start after the latest block.
+    pub(crate) next_block: Option<u64>,
+    /// The last block to process, or `None` to stream indefinitely.
+    pub(crate) end_block: Option<u64>,
+    /// The latest block number reported by the node.
+    pub(crate) latest_block: u64,
+    /// The number, hash and logs of the most recently processed blocks.
+    pub(crate) recent: VecDeque<(u64, H256, Vec<Log>)>,
+    /// The logs that are ready to be emitted.
+    pub(crate) pending: VecDeque<Log>,
+}
+
+impl<T: Transport> BloomLogStream<T> {
+    pub(crate) async fn next(mut self) -> Result<Option<(Log, Self)>, ExecutionError> {
+        loop {
+            if let Some(log) = self.pending.pop_front() {
+                return Ok(Some((log, self)));
+            }
+            match (self.next_block, self.end_block) {
+                (Some(next_block), Some(end_block)) if next_block > end_block => return Ok(None),
+                _ => self.poll().await?,
+            }
+        }
+    }
+
+    /// Processes the next block, or waits for the poll interval if there is
+    /// no new block.
+    async fn poll(&mut self) -> Result<(), ExecutionError> {
+        if self
+            .next_block
+            .map_or(true, |next_block| next_block > self.latest_block)
+        {
+            self.latest_block = self.web3.eth().block_number().await?.as_u64();
+        }
+        let next_block = *self.next_block.get_or_insert(self.latest_block + 1);
+        if next_block > self.latest_block {
+            Delay::new(self.poll_interval).await;
+            return Ok(());
+        }
+
+        let block = self
+            .web3
+            .eth()
+            .block(BlockId::Number(next_block.into()))
+            .await?
+            .ok_or(ExecutionError::MissingBlock)?;
+        let hash = block.hash.ok_or(ExecutionError::MissingBlock)?;
+
+        // NOTE: If the block does not extend the last processed block, then a
+        //   re-org happened. Remove the logs of the last processed block and
+        //   process the new block at its height instead.
+        if let Some((number, hash, _)) = self.recent.back() {
+            if *number + 1 == next_block && *hash != block.parent_hash {
+                let (number, _, logs) = self.recent.pop_back().expect("recent block");
+                self.pending.extend(logs.into_iter().rev().map(|log| Log {
+                    removed: Some(true),
+                    ..log
+                }));
+                self.next_block = Some(number);
+                return Ok(());
+            }
+        }
+
+        let possibly_matches = block.logs_bloom.map_or(true, |bloom| {
+            Bloom(bloom).possibly_matches(&self.addresses, &self.topics)
+        });
+        let logs = if possibly_matches {
+            self.web3
+                .eth()
+                .logs(self.filter.clone().block_hash(hash).build())
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        self.pending.extend(logs.iter().cloned());
+        self.recent.push_back((next_block, hash, logs));
+        if self.recent.len() > MAX_REORG_DEPTH {
+            self.recent.pop_front();
+        }
+        self.next_block = Some(next_block + 1);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bloom_of(address: Address, topics: &[H256]) -> Bloom {
+        let mut bloom = Bloom::default();
+        bloom.accrue(address.as_bytes());
+        for topic in topics {
+            bloom.accrue(topic.as_bytes());
+        }
+        bloom
+    }
+
+    #[test]
+    fn bloom_bits_match_yellow_paper() {
+        // Bloom of the empty input, the keccak256 hash of which starts with
+        // 0xc5d2_4601_86f7; the bits are 0x5d2, 0x601 and 0x6f7.
+        let mut bloom = Bloom::default();
+        bloom.accrue(&[]);
+
+        let mut expected = H2048::zero();
+        for position in &[0x5d2usize, 0x601, 0x6f7] {
+            expected.0[255 - position / 8] |= 1 << (position % 8);
+        }
+        assert_eq!(bloom.0, expected);
+    }
+
+    #[test]
+    fn bloom_possibly_contains() {
+        let address = Address::repeat_byte(0x22);
+        let topic = H256::repeat_byte(0x11);
+        let bloom = bloom_of(address, &[topic]);
+
+        assert!(bloom.possibly_contains(address, &[topic]));
+        assert!(bloom.possibly_contains(address, &[]));
+        assert!(!bloom.possibly_contains(Address::repeat_byte(0x33), &[topic]));
+        assert!(!bloom.possibly_contains(address, &[H256::repeat_byte(0x44)]));
+        assert!(!Bloom::default().possibly_contains(address, &[]));
+    }
+
+    #[test]
+    fn bloom_possibly_matches_filter() {
+        let address = Address::repeat_byte(0x22);
+        let topic = H256::repeat_byte(0x11);
+        let other = H256::repeat_byte(0x44);
+        let bloom = bloom_of(address, &[topic]);
+
+        let filter = |topic0| TopicFilter {
+            topic0,
+            ..Default::default()
+        };
+        assert!(bloom.possibly_matches(&[], &filter(Topic::Any)));
+        assert!(bloom.possibly_matches(
+            &[Address::repeat_byte(0x33), address],
+            &filter(Topic::This(topic))
+        ));
+        assert!(bloom.possibly_matches(&[], &filter(Topic::OneOf(vec![other, topic]))));
+        assert!(!bloom.possibly_matches(&[], &filter(Topic::This(other))));
+        assert!(!bloom.possibly_matches(&[Address::repeat_byte(0x33)], &filter(Topic::Any)));
+    }
+}
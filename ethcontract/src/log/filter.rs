@@ -0,0 +1,210 @@
+//! Implementation of log streams over node side log filters created with
+//! `eth_newFilter`, that transparently re-create filters that were removed by
+//! the node, for example because they were not polled for a while or because
+//! the request was routed to a different backend node.
+
+use crate::errors::{ExecutionError, RpcError};
+use futures_timer::Delay;
+use std::collections::VecDeque;
+use std::time::Duration;
+use web3::api::Web3;
+use web3::types::{BlockNumber, FilterBuilder, Log, U256};
+use web3::{helpers, Transport};
+
+/// The number of consecutive filter re-creations after which a log stream
+/// fails, since the node keeps losing its filters.
+const MAX_FILTER_RECREATIONS: usize = 3;
+
+/// Internal state of a log stream that polls a node side log filter.
+pub(crate) struct FilterLogStream<T: Transport> {
+    web3: Web3<T>,
+    /// The web3 filter used for creating the node side filter.
+    filter: FilterBuilder,
+    /// Whether the filter is for a single block hash, in which case its block
+    /// range is never changed when re-creating it.
+    block_hash: bool,
+    poll_interval: Duration,
+    /// The ID of the node side filter, or `None` if it was not created yet.
+    id: Option<U256>,
+    /// Whether the filter starts at the latest block, in which case the block
+    /// it was first created at is recorded.
+    starts_at_latest: bool,
+    /// The block following the latest block when the filter was first
+    /// created, from which it is re-created if no log was emitted yet.
+    start_block: Option<u64>,
+    /// The block number and log index of the last emitted log.
+    last_log: Option<(u64, U256)>,
+    /// The position of the last log emitted before the filter was re-created.
+    /// Logs up to this position are skipped when recovering missed logs.
+    recovered_until: Option<(u64, U256)>,
+    /// The number of consecutive filter re-creations.
+    recreations: usize,
+    /// Whether the logs following the last emitted log need to be queried
+    /// after creating the filter.
+    catch_up: bool,
+    /// The logs that are ready to be emitted.
+    pending: VecDeque<Log>,
+}
+
+impl<T: Transport> FilterLogStream<T> {
+    pub(crate) fn new(
+        web3: Web3<T>,
+        filter: FilterBuilder,
+        block_hash: bool,
+        poll_interval: Duration,
+    ) -> Self {
+        FilterLogStream {
+            web3,
+            filter,
+            block_hash,
+            poll_interval,
+            id: None,
+            starts_at_latest: false,
+            start_block: None,
+            last_log: None,
+            recovered_until: None,
+            recreations: 0,
+            catch_up: false,
+            pending: Default::default(),
+        }
+    }
+
+    /// Specifies that the filter starts at the latest block, so that filters
+    /// that are re-created before any log was emitted start after the block
+    /// that was the latest when the filter was first created, instead of
+    /// missing the logs of the blocks in between.
+    pub(crate) fn starts_at_latest(mut self) -> Self {
+        self.starts_at_latest = true;
+        self
+    }
+
+    /// Resumes a log stream after the specified previously emitted log, by
+    /// querying the logs that follow it once the filter was created.
+    pub(crate) fn resume_after(mut self, last_log: (u64, U256)) -> Self {
+        self.last_log = Some(last_log);
+        self.catch_up = true;
+        self
+    }
+
+    pub(crate) async fn next(mut self) -> Result<Option<(Log, Self)>, ExecutionError> {
+        loop {
+            if let Some(log) = self.pending.pop_front() {
+                if let (Some(block), Some(index), false) =
+                    (log.block_number, log.log_index, log.removed == Some(true))
+                {
+                    self.last_log = Some((block.as_u64(), index));
+                }
+                return Ok(Some((log, self)));
+            }
+            self.poll().await?;
+        }
+    }
+
+    /// Polls the filter for changes, waiting for the poll interval if there
+    /// are none, and re-creates the filter if the node no longer knows it.
+    async fn poll(&mut self) -> Result<(), ExecutionError> {
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                self.create().await?;
+                if self.catch_up {
+                    self.catch_up().await?;
+                }
+                return Ok(());
+            }
+        };
+
+        let changes = self
+            .web3
+            .transport()
+            .execute("eth_getFilterChanges", vec![helpers::serialize(&id)])
+            .await
+            .map_err(ExecutionError::from)
+            .and_then(|changes| Ok(helpers::decode::<Vec<Log>>(changes)?));
+        match changes {
+            Ok(logs) => {
+                self.recreations = 0;
+                if logs.is_empty() {
+                    Delay::new(self.poll_interval).await;
+                }
+                self.push(logs);
+                self.recovered_until = None;
+                Ok(())
+            }
+            Err(err)
+                if err.rpc_error() == Some(RpcError::FilterNotFound)
+                    && self.recreations < MAX_FILTER_RECREATIONS =>
+            {
+                self.recreations += 1;
+                self.recreate().await
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates the node side filter, starting at the block of the last emitted
+    /// log when resuming a log stream, or at the recorded start block when
+    /// re-creating a filter before any log was emitted.
+    async fn create(&mut self) -> Result<(), ExecutionError> {
+        if !self.block_hash {
+            match (self.last_log, self.start_block) {
+                (Some((block, _)), _) | (None, Some(block)) => {
+                    self.filter = self
+                        .filter
+                        .clone()
+                        .from_block(BlockNumber::Number(block.into()));
+                }
+                (None, None) if self.starts_at_latest => {
+                    let latest = self.web3.eth().block_number().await?;
+                    self.start_block = Some(latest.as_u64() + 1);
+                }
+                (None, None) => {}
+            }
+        }
+        let id = self
+            .web3
+            .transport()
+            .execute(
+                "eth_newFilter",
+                vec![helpers::serialize(&self.filter.clone().build())],
+            )
+            .await?;
+        self.id = Some(helpers::decode(id)?);
+        Ok(())
+    }
+
+    /// Re-creates the node side filter starting at the block of the last
+    /// emitted log, and queues the logs that were missed while the filter did
+    /// not exist.
+    async fn recreate(&mut self) -> Result<(), ExecutionError> {
+        self.create().await?;
+        self.catch_up().await
+    }
+
+    /// Queues the logs of the filter that follow the last emitted log.
+    async fn catch_up(&mut self) -> Result<(), ExecutionError> {
+        let id = self.id.expect("filter was created");
+        let logs = self
+            .web3
+            .transport()
+            .execute("eth_getFilterLogs", vec![helpers::serialize(&id)])
+            .await?;
+        self.recovered_until = self.last_log;
+        self.push(helpers::decode(logs)?);
+        Ok(())
+    }
+
+    /// Queues logs to be emitted, skipping logs that were already emitted
+    /// before the filter was re-created.
+    fn push(&mut self, logs: Vec<Log>) {
+        let recovered_until = self.recovered_until;
+        self.pending.extend(logs.into_iter().filter(|log| {
+            match (recovered_until, log.block_number, log.log_index) {
+                (Some(last), Some(block), Some(index)) if log.removed != Some(true) => {
+                    (block.as_u64(), index) > last
+                }
+                _ => true,
+            }
+        }));
+    }
+}
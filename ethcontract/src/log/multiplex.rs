@@ -0,0 +1,625 @@
+//! Implementation of log stream multiplexing, used for sharing a minimal set
+//! of `eth_subscribe` log subscriptions between many log streams targeting the
+//! same provider.
+
+use super::copy_topic;
+use crate::errors::ExecutionError;
+use ethcontract_common::abi::{Topic, TopicFilter};
+use futures::stream::Stream;
+use futures::task::{self, ArcWake, Context, Poll, Waker};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use web3::api::SubscriptionId;
+use web3::types::{Address, FilterBuilder, Log, H256};
+use web3::{helpers, DuplexTransport};
+
+/// A multiplexer for log streams over shared `eth_subscribe` subscriptions.
+///
+/// Providers usually limit the number of subscriptions per connection, so
+/// opening one subscription per event stream does not scale to many contract
+/// instances. Instead, log streams created with a multiplexer share a single
+/// subscription whose filter contains the merged addresses and topics of all
+/// its streams, and logs are demultiplexed to the streams whose filters they
+/// match on the client side.
+///
+/// The subscription is opened when one of its streams is first polled. Streams
+/// that are created afterwards and whose filters are not covered by the open
+/// subscription's filter get a new subscription, so that the logs of existing
+/// streams are never interrupted. Subscriptions are closed once all of their
+/// streams are dropped.
+///
+/// Note that merging filters widens them, so the provider may send logs that
+/// do not match any of the streams. These logs are discarded.
+pub struct LogMultiplexer<T: DuplexTransport> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+impl<T: DuplexTransport> LogMultiplexer<T> {
+    /// Creates a new log multiplexer using the specified duplex transport, for
+    /// example a WebSocket transport.
+    pub fn new(transport: T) -> Self {
+        LogMultiplexer {
+            inner: Arc::new(Mutex::new(Inner {
+                transport,
+                subscriptions: Vec::new(),
+                streams: Vec::new(),
+            })),
+        }
+    }
+
+    /// Creates a new stream of logs matching the specified addresses and
+    /// topics. An empty address list matches logs from any address.
+    pub fn stream(&self, addresses: Vec<Address>, topics: TopicFilter) -> MultiplexedLogStream<T> {
+        let filter = LogFilter { addresses, topics };
+        let mut inner = self.inner.lock().expect("log multiplexer poisoned");
+
+        let index = inner.subscriptions.iter().position(|subscription| {
+            subscription.streams == 0
+                || match subscription.state {
+                    SubscriptionState::Idle => true,
+                    SubscriptionState::Closed => false,
+                    _ => subscription.filter.covers(&filter),
+                }
+        });
+        let subscription = match index {
+            Some(index) => index,
+            None => {
+                inner.subscriptions.push(Subscription::default());
+                inner.subscriptions.len() - 1
+            }
+        };
+        let shared = &mut inner.subscriptions[subscription];
+        shared.filter = if shared.streams == 0 {
+            filter.clone()
+        } else {
+            shared.filter.merge(&filter)
+        };
+
+        let id = inner.streams.len();
+        inner.streams.push(Some(StreamState {
+            subscription,
+            filter,
+            logs: VecDeque::new(),
+        }));
+        inner.subscriptions[subscription].streams += 1;
+
+        MultiplexedLogStream {
+            inner: self.inner.clone(),
+            id,
+        }
+    }
+
+    /// Returns the number of open or pending subscriptions.
+    pub fn subscription_count(&self) -> usize {
+        let inner = self.inner.lock().expect("log multiplexer poisoned");
+        inner
+            .subscriptions
+            .iter()
+            .filter(|subscription| subscription.streams > 0)
+            .count()
+    }
+}
+
+impl<T: DuplexTransport> Clone for LogMultiplexer<T> {
+    fn clone(&self) -> Self {
+        LogMultiplexer {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: DuplexTransport> Debug for LogMultiplexer<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("LogMultiplexer")
+            .field("subscriptions", &self.subscription_count())
+            .finish()
+    }
+}
+
+/// A stream of logs sharing a subscription with other streams of a
+/// [`LogMultiplexer`].
+pub struct MultiplexedLogStream<T: DuplexTransport> {
+    inner: Arc<Mutex<Inner<T>>>,
+    id: usize,
+}
+
+impl<T: DuplexTransport> Stream for MultiplexedLogStream<T> {
+    type Item = Result<Log, ExecutionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.lock().expect("log multiplexer poisoned");
+        inner.poll_stream(self.id, cx)
+    }
+}
+
+impl<T: DuplexTransport> Drop for MultiplexedLogStream<T> {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.remove_stream(self.id);
+        }
+    }
+}
+
+impl<T: DuplexTransport> Debug for MultiplexedLogStream<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("MultiplexedLogStream")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// The shared state of a log multiplexer.
+struct Inner<T: DuplexTransport> {
+    transport: T,
+    subscriptions: Vec<Subscription<T>>,
+    /// The state of every created stream, or `None` for dropped streams.
+    streams: Vec<Option<StreamState>>,
+}
+
+impl<T: DuplexTransport> Inner<T> {
+    fn poll_stream(
+        &mut self,
+        id: usize,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Log, ExecutionError>>> {
+        let index = self.streams[id]
+            .as_ref()
+            .expect("polled dropped stream")
+            .subscription;
+        self.subscriptions[index].wakers.register(id, cx.waker());
+
+        loop {
+            if let Some(log) = self.stream_mut(id).logs.pop_front() {
+                return Poll::Ready(Some(Ok(log)));
+            }
+
+            let transport = &self.transport;
+            let subscription = &mut self.subscriptions[index];
+            let waker = task::waker(subscription.wakers.clone());
+            let mut cx = Context::from_waker(&waker);
+
+            let notification = match &mut subscription.state {
+                SubscriptionState::Idle => {
+                    let request = transport.execute(
+                        "eth_subscribe",
+                        vec![
+                            helpers::serialize(&"logs"),
+                            helpers::serialize(&subscription.filter.to_web3().build()),
+                        ],
+                    );
+                    subscription.state = SubscriptionState::Subscribing(Box::pin(request));
+                    continue;
+                }
+                SubscriptionState::Subscribing(request) => {
+                    let result = match request.as_mut().poll(&mut cx) {
+                        Poll::Ready(result) => result,
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    let subscribed = result.and_then(helpers::decode::<String>).and_then(|id| {
+                        let notifications = transport.subscribe(id.clone().into())?;
+                        Ok(SubscriptionState::Active(id, Box::pin(notifications)))
+                    });
+                    match subscribed {
+                        Ok(state) => subscription.state = state,
+                        Err(err) => {
+                            subscription.state = SubscriptionState::Idle;
+                            return Poll::Ready(Some(Err(err.into())));
+                        }
+                    }
+                    continue;
+                }
+                SubscriptionState::Active(_, notifications) => {
+                    match notifications.as_mut().poll_next(&mut cx) {
+                        Poll::Ready(Some(notification)) => notification,
+                        Poll::Ready(None) => {
+                            subscription.state = SubscriptionState::Closed;
+                            continue;
+                        }
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                SubscriptionState::Closed => return Poll::Ready(None),
+            };
+
+            let log = match helpers::decode::<Log>(notification) {
+                Ok(log) => log,
+                Err(err) => return Poll::Ready(Some(Err(err.into()))),
+            };
+            for stream in self.streams.iter_mut().flatten() {
+                if stream.subscription == index && stream.filter.matches(&log) {
+                    stream.logs.push_back(log.clone());
+                }
+            }
+            self.subscriptions[index].wakers.wake_others(id);
+        }
+    }
+
+    fn stream_mut(&mut self, id: usize) -> &mut StreamState {
+        self.streams[id].as_mut().expect("polled dropped stream")
+    }
+
+    fn remove_stream(&mut self, id: usize) {
+        let stream = match self.streams[id].take() {
+            Some(stream) => stream,
+            None => return,
+        };
+        let subscription = &mut self.subscriptions[stream.subscription];
+        subscription.wakers.remove(id);
+        subscription.streams -= 1;
+        if subscription.streams == 0 {
+            if let SubscriptionState::Active(id, _) = &subscription.state {
+                // NOTE: The unsubscribe request is sent without waiting for
+                //   its response, as we can't block when dropping a stream.
+                let _ = self.transport.unsubscribe(SubscriptionId::from(id.clone()));
+                drop(
+                    self.transport
+                        .execute("eth_unsubscribe", vec![helpers::serialize(id)]),
+                );
+            }
+            *subscription = Subscription::default();
+        }
+    }
+}
+
+/// The state of a single stream of a multiplexer.
+struct StreamState {
+    /// The index of the subscription the stream receives logs from.
+    subscription: usize,
+    filter: LogFilter,
+    /// The received logs that were not yet emitted by the stream.
+    logs: VecDeque<Log>,
+}
+
+/// A subscription shared by multiple streams.
+struct Subscription<T: DuplexTransport> {
+    /// The merged filter of the subscription's streams.
+    filter: LogFilter,
+    state: SubscriptionState<T>,
+    /// The number of streams receiving logs from the subscription.
+    streams: usize,
+    wakers: Arc<Wakers>,
+}
+
+impl<T: DuplexTransport> Default for Subscription<T> {
+    fn default() -> Self {
+        Subscription {
+            filter: LogFilter::default(),
+            state: SubscriptionState::Idle,
+            streams: 0,
+            wakers: Default::default(),
+        }
+    }
+}
+
+enum SubscriptionState<T: DuplexTransport> {
+    /// The subscription has not yet been requested.
+    Idle,
+    /// The subscription was requested.
+    Subscribing(Pin<Box<T::Out>>),
+    /// The subscription is open.
+    Active(String, Pin<Box<T::NotificationStream>>),
+    /// The subscription was closed by the transport.
+    Closed,
+}
+
+/// The wakers of all streams sharing a subscription. Since only the stream
+/// that is polled registers its waker with the subscription, all waiting
+/// streams are woken when the subscription makes progress.
+#[derive(Default)]
+struct Wakers(Mutex<Vec<(usize, Waker)>>);
+
+impl Wakers {
+    fn register(&self, id: usize, waker: &Waker) {
+        let mut wakers = self.0.lock().expect("wakers poisoned");
+        match wakers.iter_mut().find(|(stream, _)| *stream == id) {
+            Some((_, registered)) => {
+                if !registered.will_wake(waker) {
+                    *registered = waker.clone();
+                }
+            }
+            None => wakers.push((id, waker.clone())),
+        }
+    }
+
+    fn remove(&self, id: usize) {
+        let mut wakers = self.0.lock().expect("wakers poisoned");
+        wakers.retain(|(stream, _)| *stream != id);
+    }
+
+    fn wake_others(&self, id: usize) {
+        let wakers = self.0.lock().expect("wakers poisoned");
+        for (_, waker) in wakers.iter().filter(|(stream, _)| *stream != id) {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+impl ArcWake for Wakers {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let wakers = arc_self.0.lock().expect("wakers poisoned");
+        for (_, waker) in wakers.iter() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// A log filter on addresses and topics.
+#[derive(Debug, Default, PartialEq)]
+struct LogFilter {
+    /// The addresses to match, or empty to match logs from any address.
+    addresses: Vec<Address>,
+    topics: TopicFilter,
+}
+
+impl LogFilter {
+    /// Returns `true` if the log matches the filter.
+    fn matches(&self, log: &Log) -> bool {
+        let topic_matches = |topic: &Topic<H256>, index: usize| match topic {
+            Topic::Any => true,
+            Topic::OneOf(topics) => log
+                .topics
+                .get(index)
+                .map_or(false, |topic| topics.contains(topic)),
+            Topic::This(topic) => log.topics.get(index) == Some(topic),
+        };
+
+        (self.addresses.is_empty() || self.addresses.contains(&log.address))
+            && topic_matches(&self.topics.topic0, 0)
+            && topic_matches(&self.topics.topic1, 1)
+            && topic_matches(&self.topics.topic2, 2)
+            && topic_matches(&self.topics.topic3, 3)
+    }
+
+    /// Returns `true` if every log matching the other filter also matches this
+    /// filter.
+    fn covers(&self, other: &LogFilter) -> bool {
+        let topic_covers = |topic: &Topic<H256>, other: &Topic<H256>| match topic {
+            Topic::Any => true,
+            _ => topic_values(other).map_or(false, |values| {
+                values.iter().all(|value| topic_contains(topic, value))
+            }),
+        };
+
+        (self.addresses.is_empty()
+            || (!other.addresses.is_empty()
+                && other
+                    .addresses
+                    .iter()
+                    .all(|address| self.addresses.contains(address))))
+            && topic_covers(&self.topics.topic0, &other.topics.topic0)
+            && topic_covers(&self.topics.topic1, &other.topics.topic1)
+            && topic_covers(&self.topics.topic2, &other.topics.topic2)
+            && topic_covers(&self.topics.topic3, &other.topics.topic3)
+    }
+
+    /// Merges two filters into a filter that matches all logs matching either
+    /// of them.
+    fn merge(&self, other: &LogFilter) -> LogFilter {
+        let addresses = if self.addresses.is_empty() || other.addresses.is_empty() {
+            Vec::new()
+        } else {
+            let mut addresses = self.addresses.clone();
+            for address in &other.addresses {
+                if !addresses.contains(address) {
+                    addresses.push(*address);
+                }
+            }
+            addresses
+        };
+        let merge_topic = |topic: &Topic<H256>, other: &Topic<H256>| match (
+            topic_values(topic),
+            topic_values(other),
+        ) {
+            (Some(mut values), Some(others)) => {
+                for value in others {
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                Topic::OneOf(values)
+            }
+            _ => Topic::Any,
+        };
+
+        LogFilter {
+            addresses,
+            topics: TopicFilter {
+                topic0: merge_topic(&self.topics.topic0, &other.topics.topic0),
+                topic1: merge_topic(&self.topics.topic1, &other.topics.topic1),
+                topic2: merge_topic(&self.topics.topic2, &other.topics.topic2),
+                topic3: merge_topic(&self.topics.topic3, &other.topics.topic3),
+            },
+        }
+    }
+
+    /// Returns the web3 filter for subscribing to the logs matching the
+    /// filter.
+    fn to_web3(&self) -> FilterBuilder {
+        let mut filter = FilterBuilder::default();
+        if !self.addresses.is_empty() {
+            filter = filter.address(self.addresses.clone());
+        }
+        if self.topics != TopicFilter::default() {
+            filter = filter.topics(
+                topic_values(&self.topics.topic0),
+                topic_values(&self.topics.topic1),
+                topic_values(&self.topics.topic2),
+                topic_values(&self.topics.topic3),
+            );
+        }
+        filter
+    }
+}
+
+impl Clone for LogFilter {
+    fn clone(&self) -> Self {
+        LogFilter {
+            addresses: self.addresses.clone(),
+            topics: TopicFilter {
+                topic0: copy_topic(&self.topics.topic0),
+                topic1: copy_topic(&self.topics.topic1),
+                topic2: copy_topic(&self.topics.topic2),
+                topic3: copy_topic(&self.topics.topic3),
+            },
+        }
+    }
+}
+
+/// Returns the values a topic matches, or `None` if it matches any value.
+fn topic_values(topic: &Topic<H256>) -> Option<Vec<H256>> {
+    match topic {
+        Topic::Any => None,
+        Topic::OneOf(values) => Some(values.clone()),
+        Topic::This(value) => Some(vec![*value]),
+    }
+}
+
+fn topic_contains(topic: &Topic<H256>, value: &H256) -> bool {
+    match topic {
+        Topic::Any => true,
+        Topic::OneOf(values) => values.contains(value),
+        Topic::This(topic) => topic == value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use futures::{FutureExt, StreamExt};
+    use serde_json::Value;
+
+    fn generate_log(address: Address, topic: H256) -> Value {
+        json!({
+            "address": address,
+            "topics": [topic],
+            "data": "0x",
+            "blockHash": H256::zero(),
+            "blockNumber": "0x0",
+            "transactionHash": H256::zero(),
+            "transactionIndex": "0x0",
+            "logIndex": "0x0",
+            "transactionLogIndex": "0x0",
+            "logType": null,
+            "removed": false,
+        })
+    }
+
+    fn this(topic: H256) -> TopicFilter {
+        TopicFilter {
+            topic0: Topic::This(topic),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn multiplexed_streams_share_subscription() {
+        let mut transport = TestTransport::new();
+        let multiplexer = LogMultiplexer::new(transport.clone());
+
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let (ta, tb) = (H256::repeat_byte(0xa), H256::repeat_byte(0xb));
+        let mut stream_a = multiplexer.stream(vec![a], this(ta));
+        let mut stream_b = multiplexer.stream(vec![b], this(tb));
+        assert_eq!(multiplexer.subscription_count(), 1);
+
+        transport.add_response(json!("0x1"));
+        assert!(stream_a.next().now_or_never().is_none());
+        transport.assert_request(
+            "eth_subscribe",
+            &[
+                json!("logs"),
+                json!({
+                    "address": [a, b],
+                    "topics": [[ta, tb]],
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        // NOTE: The last log matches the merged filter but neither stream.
+        transport.notify("0x1", generate_log(b, tb));
+        transport.notify("0x1", generate_log(a, ta));
+        transport.notify("0x1", generate_log(a, tb));
+
+        let log = stream_a.next().immediate().unwrap().unwrap();
+        assert_eq!((log.address, log.topics), (a, vec![ta]));
+        let log = stream_b.next().immediate().unwrap().unwrap();
+        assert_eq!((log.address, log.topics), (b, vec![tb]));
+        assert!(stream_a.next().now_or_never().is_none());
+        assert!(stream_b.next().now_or_never().is_none());
+
+        drop(stream_a);
+        assert!(transport.is_subscribed("0x1"));
+        transport.add_response(json!(true));
+        drop(stream_b);
+        assert!(!transport.is_subscribed("0x1"));
+        assert_eq!(multiplexer.subscription_count(), 0);
+        transport.assert_request("eth_unsubscribe", &[json!("0x1")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn multiplexed_streams_not_covered_by_subscription() {
+        let mut transport = TestTransport::new();
+        let multiplexer = LogMultiplexer::new(transport.clone());
+
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let topic = H256::repeat_byte(0xa);
+        let mut stream = multiplexer.stream(vec![a, b], this(topic));
+        transport.add_response(json!("0x1"));
+        assert!(stream.next().now_or_never().is_none());
+
+        let _covered = multiplexer.stream(vec![b], this(topic));
+        assert_eq!(multiplexer.subscription_count(), 1);
+        let mut any_topic = multiplexer.stream(vec![a], TopicFilter::default());
+        assert_eq!(multiplexer.subscription_count(), 2);
+
+        transport.add_response(json!("0x2"));
+        assert!(any_topic.next().now_or_never().is_none());
+        transport.assert_request(
+            "eth_subscribe",
+            &[
+                json!("logs"),
+                json!({ "address": [a, b], "topics": [topic] }),
+            ],
+        );
+        transport.assert_request("eth_subscribe", &[json!("logs"), json!({ "address": a })]);
+
+        transport.notify("0x1", generate_log(a, topic));
+        transport.notify("0x2", generate_log(a, topic));
+        assert!(stream.next().immediate().is_some());
+        assert!(any_topic.next().immediate().is_some());
+        assert!(stream.next().now_or_never().is_none());
+        assert!(any_topic.next().now_or_never().is_none());
+    }
+
+    #[test]
+    fn log_filter_merge_and_cover() {
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let (ta, tb) = (H256::repeat_byte(0xa), H256::repeat_byte(0xb));
+        let filter = |addresses, topics| LogFilter { addresses, topics };
+
+        let merged = filter(vec![a], this(ta)).merge(&filter(vec![b, a], this(tb)));
+        assert_eq!(
+            merged,
+            filter(
+                vec![a, b],
+                TopicFilter {
+                    topic0: Topic::OneOf(vec![ta, tb]),
+                    ..Default::default()
+                }
+            ),
+        );
+        assert!(merged.covers(&filter(vec![b], this(ta))));
+        assert!(!merged.covers(&filter(vec![], this(ta))));
+        assert!(!merged.covers(&filter(vec![a], TopicFilter::default())));
+
+        let any = filter(vec![a], this(ta)).merge(&filter(vec![], TopicFilter::default()));
+        assert_eq!(any, LogFilter::default());
+        assert!(any.covers(&merged));
+    }
+}
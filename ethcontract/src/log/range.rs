@@ -0,0 +1,206 @@
+//! Implementation of adaptive block ranges for `eth_getLogs` queries, that
+//! learn the maximum block range supported by a provider.
+
+use crate::errors::ExecutionError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use web3::error::Error as Web3Error;
+
+/// The number of consecutive successful queries with the current block range
+/// after which the block range grows again.
+const GROW_AFTER: u64 = 8;
+
+/// The JSON RPC error code used by some providers, notably Infura, when a log
+/// query returns too many results.
+const LIMIT_EXCEEDED: i64 = -32005;
+
+/// Substrings of the JSON RPC error messages providers return when the block
+/// range or result size of a log query is too large.
+const RANGE_ERRORS: &[&str] = &[
+    "block range",
+    "more than",
+    "too many results",
+    "too many logs",
+    "too many blocks",
+    "response too large",
+    "result too large",
+    "response size",
+    "limit exceeded",
+];
+
+/// A block page size for paginated log queries that adapts to the provider.
+///
+/// When a log query fails because its block range or result set is too large,
+/// the block range is halved and the query is retried. After a number of
+/// consecutive successful queries the range grows back gradually, up to the
+/// maximum block range. Adaptive block ranges are cheap to clone and share the
+/// learned block range, so that it persists across queries. Every
+/// [`DynTransport`](crate::transport::DynTransport) has one, see
+/// [`DynTransport::block_range`](crate::transport::DynTransport::block_range).
+#[derive(Clone, Debug)]
+pub struct AdaptiveBlockRange {
+    inner: Arc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    max: u64,
+    current: AtomicU64,
+    successes: AtomicU64,
+}
+
+impl AdaptiveBlockRange {
+    /// Creates a new adaptive block range that starts at, and never grows
+    /// beyond, the specified maximum block range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum block range is 0.
+    pub fn new(max: u64) -> Self {
+        assert!(max > 0, "block range cannot be 0");
+        AdaptiveBlockRange {
+            inner: Arc::new(State {
+                max,
+                current: AtomicU64::new(max),
+                successes: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns the currently learned block range.
+    pub fn get(&self) -> u64 {
+        self.inner.current.load(Ordering::Relaxed)
+    }
+
+    /// Returns the maximum block range.
+    pub fn max(&self) -> u64 {
+        self.inner.max
+    }
+
+    /// Shrinks the block range after a query with the specified range failed
+    /// because it was too large. Returns `false` if the range cannot shrink any
+    /// further.
+    pub(crate) fn shrink(&self, failed: u64) -> bool {
+        if failed <= 1 {
+            return false;
+        }
+        let shrunk = failed / 2;
+        self.inner.current.fetch_min(shrunk, Ordering::Relaxed);
+        self.inner.successes.store(0, Ordering::Relaxed);
+        true
+    }
+
+    /// Records a successful query with the specified range, growing the block
+    /// range after enough consecutive successes.
+    pub(crate) fn succeed(&self, range: u64) {
+        let current = self.get();
+        if range < current || current >= self.inner.max {
+            return;
+        }
+        let successes = self.inner.successes.fetch_add(1, Ordering::Relaxed) + 1;
+        if successes >= GROW_AFTER {
+            let grown = (current + (current / 4).max(1)).min(self.inner.max);
+            // NOTE: Only grow if no other query changed the range meanwhile.
+            if self
+                .inner
+                .current
+                .compare_exchange(current, grown, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.inner.successes.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Returns `true` if a log query failed because its block range or result set
+/// was too large.
+pub(crate) fn is_range_error(err: &ExecutionError) -> bool {
+    match err {
+        ExecutionError::Web3(Web3Error::Rpc(err)) => {
+            let message = err.message.to_lowercase();
+            err.code.code() == LIMIT_EXCEEDED
+                || RANGE_ERRORS.iter().any(|error| message.contains(error))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
+
+    #[test]
+    fn adaptive_block_range_shrinks_and_grows() {
+        let range = AdaptiveBlockRange::new(1000);
+        assert_eq!(range.get(), 1000);
+
+        assert!(range.shrink(1000));
+        assert!(range.shrink(500));
+        assert_eq!(range.get(), 250);
+
+        // NOTE: Failures of queries with stale ranges only shrink to half of
+        //   their range.
+        assert!(range.shrink(1000));
+        assert_eq!(range.get(), 250);
+
+        for _ in 0..GROW_AFTER - 1 {
+            range.succeed(250);
+        }
+        range.succeed(100);
+        assert_eq!(range.get(), 250);
+        range.succeed(250);
+        assert_eq!(range.get(), 312);
+
+        for _ in 0..GROW_AFTER * 10 {
+            range.succeed(range.get());
+        }
+        assert_eq!(range.get(), 1000);
+
+        let range = AdaptiveBlockRange::new(2);
+        assert!(range.shrink(2));
+        assert!(!range.shrink(1));
+        assert_eq!(range.get(), 1);
+    }
+
+    #[test]
+    fn range_errors() {
+        let rpc_error = |code: i64, message: &str| {
+            ExecutionError::Web3(Web3Error::Rpc(JsonrpcError {
+                code: ErrorCode::ServerError(code),
+                message: message.to_owned(),
+                data: None,
+            }))
+        };
+
+        assert!(is_range_error(&rpc_error(
+            -32005,
+            "query returned more than 10000 results"
+        )));
+        assert!(is_range_error(&rpc_error(
+            -32602,
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"
+        )));
+        assert!(is_range_error(&rpc_error(
+            -32000,
+            "exceed maximum block range: 5000"
+        )));
+        assert!(is_range_error(&rpc_error(
+            -32000,
+            "query returned too many logs"
+        )));
+        assert!(!is_range_error(&rpc_error(-32000, "header not found")));
+        assert!(!is_range_error(&rpc_error(-32000, "too many requests")));
+        assert!(!is_range_error(&rpc_error(
+            -32000,
+            "request body too large"
+        )));
+        assert!(!is_range_error(&rpc_error(-32000, "index out of range")));
+        assert!(!is_range_error(&rpc_error(
+            -32602,
+            "value out of range for uint64"
+        )));
+        assert!(!is_range_error(&ExecutionError::MissingBlock));
+    }
+}
@@ -0,0 +1,403 @@
+//! Implementation of a transport agnostic log source, that streams logs over
+//! `eth_subscribe` subscriptions when possible and falls back to polling with
+//! `eth_getLogs` or node side filters otherwise.
+
+use super::{LogFilterBuilder, LogMultiplexer};
+use crate::errors::ExecutionError;
+use crate::transport::DynTransport;
+use ethcontract_common::abi::TopicFilter;
+use futures::future::{self, FutureExt};
+use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use web3::types::{Address, Log, U256};
+use web3::{DuplexTransport, Transport};
+
+/// The default number of consecutive subscription failures after which log
+/// streams fall back to polling.
+pub const DEFAULT_MAX_SUBSCRIPTION_FAILURES: usize = 3;
+
+/// A function for subscribing to the logs matching a filter.
+type Subscriber = dyn Fn(Vec<Address>, TopicFilter) -> BoxStream<'static, Result<Log, ExecutionError>>
+    + Send
+    + Sync;
+
+/// A source of logs for log streams, allowing event consuming code to be
+/// agnostic of the transport used for communicating with the node.
+///
+/// Log sources with a duplex transport stream logs over `eth_subscribe`
+/// subscriptions shared between all streams using the source, see
+/// [`LogMultiplexer`]. If a subscription fails repeatedly or gets closed, for
+/// example because the WebSocket connection dropped, then the stream
+/// transparently falls back to polling with `eth_getLogs`, starting at the
+/// block of the last emitted log. Log sources without a duplex transport
+/// always poll, checking the logs bloom of every block before querying its
+/// logs, or polling node side filters created with `eth_newFilter` for
+/// providers that prefer them.
+#[derive(Clone)]
+pub struct LogSource {
+    subscriber: Option<Arc<Subscriber>>,
+    max_subscription_failures: usize,
+    server_filters: bool,
+}
+
+impl LogSource {
+    /// Creates a log source that always polls for new logs.
+    pub fn polling() -> Self {
+        LogSource {
+            subscriber: None,
+            max_subscription_failures: DEFAULT_MAX_SUBSCRIPTION_FAILURES,
+            server_filters: false,
+        }
+    }
+
+    /// Creates a log source that always polls node side log filters created
+    /// with `eth_newFilter` for new logs.
+    pub fn server_filter() -> Self {
+        LogSource::polling().server_filters(true)
+    }
+
+    /// Creates a log source that prefers subscriptions over the specified
+    /// duplex transport, falling back to polling over the transport of the
+    /// streamed log filters.
+    pub fn subscription<D>(transport: D) -> Self
+    where
+        D: DuplexTransport + Send + 'static,
+        D::Out: Send,
+        D::NotificationStream: Send,
+    {
+        let multiplexer = LogMultiplexer::new(transport);
+        LogSource {
+            subscriber: Some(Arc::new(move |addresses, topics| {
+                multiplexer.stream(addresses, topics).boxed()
+            })),
+            ..LogSource::polling()
+        }
+    }
+
+    /// Creates a log source for a dynamic transport, preferring subscriptions
+    /// if the underlying transport is a WebSocket or IPC transport.
+    #[allow(unused_variables)]
+    pub fn detect(transport: &DynTransport) -> Self {
+        #[cfg(any(
+            feature = "ws-tokio",
+            feature = "ws-async-std",
+            feature = "ws-tls-tokio",
+            feature = "ws-tls-async-std",
+        ))]
+        if let Some(ws) = transport.downcast::<web3::transports::WebSocket>() {
+            return LogSource::subscription(ws.clone());
+        }
+        #[cfg(feature = "ipc-tokio")]
+        if let Some(ipc) = transport.downcast::<web3::transports::Ipc>() {
+            return LogSource::subscription(ipc.clone());
+        }
+        LogSource::polling()
+    }
+
+    /// Sets the number of consecutive subscription failures after which log
+    /// streams fall back to polling.
+    pub fn max_subscription_failures(mut self, value: usize) -> Self {
+        self.max_subscription_failures = value;
+        self
+    }
+
+    /// Sets whether streams poll node side log filters with
+    /// `eth_getFilterChanges` instead of polling blocks and their logs, both
+    /// for polling log sources and when falling back from subscriptions.
+    /// Filters that were removed by the node are re-created transparently,
+    /// see [`LogFilterBuilder::stream`].
+    pub fn server_filters(mut self, enabled: bool) -> Self {
+        self.server_filters = enabled;
+        self
+    }
+
+    /// Returns `true` if the log source prefers subscriptions.
+    pub fn is_subscription(&self) -> bool {
+        self.subscriber.is_some()
+    }
+
+    /// Creates a stream of the new logs matching the specified log filter.
+    ///
+    /// Log filters with a block range or a block hash are always polled, since
+    /// subscriptions only emit logs of new blocks.
+    pub fn stream<T: Transport>(
+        &self,
+        filter: LogFilterBuilder<T>,
+    ) -> impl Stream<Item = Result<Log, ExecutionError>> {
+        let subscribe = match &self.subscriber {
+            Some(subscriber)
+                if filter.from_block.is_none()
+                    && filter.to_block.is_none()
+                    && filter.block_hash.is_none() =>
+            {
+                Some(subscriber(filter.address.clone(), filter.copy_topics()))
+            }
+            _ => None,
+        };
+
+        let last_log = Arc::new(Mutex::new(None));
+        let subscription = match subscribe {
+            Some(subscription) => subscription_stream(
+                subscription,
+                self.max_subscription_failures,
+                last_log.clone(),
+            )
+            .left_stream(),
+            None => stream::empty().right_stream(),
+        };
+
+        let server_filters = self.server_filters;
+        let fallback = future::lazy(move |_| {
+            let last_log = *last_log.lock().expect("last log poisoned");
+            let logs = if server_filters {
+                filter.filter_stream(last_log).left_stream()
+            } else {
+                let filter = match last_log {
+                    Some((block, _)) => filter.from_block(block),
+                    None => filter,
+                };
+                filter.bloom_filter(true).stream().right_stream()
+            };
+            logs.try_filter(move |log| {
+                let emitted = match (last_log, log.block_number, log.log_index) {
+                    (Some((block, index)), Some(number), Some(log_index)) => {
+                        number.as_u64() == block && log_index <= index
+                    }
+                    _ => false,
+                };
+                future::ready(!emitted)
+            })
+        })
+        .flatten_stream();
+
+        subscription.map(Ok).chain(fallback)
+    }
+}
+
+impl Debug for LogSource {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("LogSource")
+            .field("subscription", &self.is_subscription())
+            .field("max_subscription_failures", &self.max_subscription_failures)
+            .field("server_filters", &self.server_filters)
+            .finish()
+    }
+}
+
+/// Creates a stream of the logs of a subscription that ends after the
+/// specified number of consecutive failures or when the subscription gets
+/// closed. The block number and log index of the last emitted log are
+/// recorded so that polling can resume from there.
+fn subscription_stream(
+    subscription: BoxStream<'static, Result<Log, ExecutionError>>,
+    max_failures: usize,
+    last_log: Arc<Mutex<Option<(u64, U256)>>>,
+) -> impl Stream<Item = Log> {
+    stream::unfold(subscription, move |mut subscription| {
+        let last_log = last_log.clone();
+        async move {
+            let mut failures = 0;
+            loop {
+                match subscription.next().await? {
+                    Ok(log) => {
+                        if let (Some(block), Some(index)) = (log.block_number, log.log_index) {
+                            if log.removed != Some(true) {
+                                *last_log.lock().expect("last log poisoned") =
+                                    Some((block.as_u64(), index));
+                            }
+                        }
+                        return Some((log, subscription));
+                    }
+                    Err(_) => {
+                        failures += 1;
+                        if failures >= max_failures {
+                            return None;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use serde_json::Value;
+    use web3::api::{SubscriptionId, Web3};
+    use web3::types::{H256, U64};
+
+    fn generate_log(block: u64, index: u64) -> Value {
+        json!({
+            "address": Address::zero(),
+            "topics": [],
+            "data": "0x",
+            "blockHash": H256::repeat_byte(block as _),
+            "blockNumber": U64::from(block),
+            "transactionHash": H256::zero(),
+            "transactionIndex": "0x0",
+            "logIndex": U256::from(index),
+            "transactionLogIndex": "0x0",
+            "logType": null,
+            "removed": false,
+        })
+    }
+
+    fn generate_block(number: u64) -> Value {
+        json!({
+            "hash": H256::repeat_byte(number as _),
+            "parentHash": H256::repeat_byte(number as u8 - 1),
+            "sha3Uncles": H256::zero(),
+            "miner": Address::zero(),
+            "stateRoot": H256::zero(),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": U64::from(number),
+            "gasUsed": "0x0",
+            "gasLimit": "0x0",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x0",
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "sealFields": [],
+            "uncles": [],
+            "transactions": [],
+            "size": "0x0",
+            "mixHash": H256::zero(),
+            "nonce": "0x0000000000000000",
+        })
+    }
+
+    fn log_position(log: Option<Result<Log, ExecutionError>>) -> (u64, u64) {
+        let log = log.expect("log stream ended").expect("log stream failed");
+        (
+            log.block_number.unwrap().as_u64(),
+            log.log_index.unwrap().as_u64(),
+        )
+    }
+
+    #[test]
+    fn polling_source_queries_logs() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let source = LogSource::polling();
+        assert!(!source.is_subscription());
+
+        transport.add_response(json!(U64::from(10)));
+        transport.add_response(generate_block(10));
+        transport.add_response(json!([generate_log(10, 0)]));
+
+        let mut logs = source
+            .stream(LogFilterBuilder::new(web3).from_block(10))
+            .boxed();
+        assert_eq!(log_position(logs.next().immediate()), (10, 0));
+
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request(
+            "eth_getBlockByNumber",
+            &[json!(U64::from(10)), json!(false)],
+        );
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "blockHash": H256::repeat_byte(10) })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn subscription_source_falls_back_to_polling() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let source = LogSource::subscription(transport.clone());
+        assert!(source.is_subscription());
+
+        transport.add_response(json!("0x1"));
+        let mut logs = source.stream(LogFilterBuilder::new(web3)).boxed();
+        assert!(logs.next().now_or_never().is_none());
+        transport.assert_request("eth_subscribe", &[json!("logs"), json!({})]);
+
+        transport.notify("0x1", generate_log(5, 1));
+        assert_eq!(log_position(logs.next().immediate()), (5, 1));
+
+        // NOTE: Close the subscription, for example because the WebSocket
+        //   connection dropped. Polling resumes at the block of the last log
+        //   and only emits the logs that were not yet emitted.
+        transport
+            .unsubscribe(SubscriptionId::from("0x1".to_owned()))
+            .unwrap();
+        transport.add_response(json!(U64::from(5)));
+        transport.add_response(generate_block(5));
+        transport.add_response(json!([
+            generate_log(5, 0),
+            generate_log(5, 1),
+            generate_log(5, 2)
+        ]));
+        assert_eq!(log_position(logs.next().immediate()), (5, 2));
+
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getBlockByNumber", &[json!(U64::from(5)), json!(false)]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "blockHash": H256::repeat_byte(5) })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn subscription_source_falls_back_to_server_filters() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let source = LogSource::subscription(transport.clone()).server_filters(true);
+
+        transport.add_response(json!("0x1"));
+        let mut logs = source.stream(LogFilterBuilder::new(web3)).boxed();
+        assert!(logs.next().now_or_never().is_none());
+        transport.notify("0x1", generate_log(5, 1));
+        assert_eq!(log_position(logs.next().immediate()), (5, 1));
+
+        transport
+            .unsubscribe(SubscriptionId::from("0x1".to_owned()))
+            .unwrap();
+        transport.add_response(json!("0xf0"));
+        transport.add_response(json!([generate_log(5, 1), generate_log(5, 2)]));
+        assert_eq!(log_position(logs.next().immediate()), (5, 2));
+
+        transport.assert_request("eth_subscribe", &[json!("logs"), json!({})]);
+        transport.assert_request("eth_newFilter", &[json!({ "fromBlock": "0x5" })]);
+        transport.assert_request("eth_getFilterLogs", &[json!("0xf0")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn subscription_source_falls_back_after_failures() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let source = LogSource::subscription(transport.clone()).max_subscription_failures(2);
+
+        // invalid subscription IDs
+        transport.add_response(json!(1));
+        transport.add_response(json!(2));
+        // polling starts after the latest block
+        transport.add_response(json!(U64::from(6)));
+        transport.add_response(json!(U64::from(7)));
+        transport.add_response(generate_block(7));
+        transport.add_response(json!([generate_log(7, 0)]));
+
+        let mut logs = source.stream(LogFilterBuilder::new(web3)).boxed();
+        assert_eq!(log_position(logs.next().wait()), (7, 0));
+
+        transport.assert_request("eth_subscribe", &[json!("logs"), json!({})]);
+        transport.assert_request("eth_subscribe", &[json!("logs"), json!({})]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getBlockByNumber", &[json!(U64::from(7)), json!(false)]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "blockHash": H256::repeat_byte(7) })],
+        );
+        transport.assert_no_more_requests();
+    }
+}
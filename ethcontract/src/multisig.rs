@@ -0,0 +1,296 @@
+//! Collection and packing of multiple EIP-712 signatures over the same
+//! message, for multi-signature wallets and off-chain order books.
+//!
+//! A [`SignatureSet`] collects the signatures of the EIP-712 hash of a message,
+//! recovers and deduplicates their signers, and packs them in the formats
+//! expected by contracts that verify multiple signatures. Signatures are
+//! always ordered by ascending signer address, as required by the Safe
+//! `checkSignatures` function and most other multi-signature verifiers:
+//!
+//! ```ignore
+//! let mut signatures = SignatureSet::new(safe_tx_hash, threshold);
+//! signatures.sign(&web3, &owner, typed_data).await?;
+//! signatures.insert(signature_from_other_owner)?;
+//! safe.exec_transaction(/* ... */, signatures.packed()?).send().await?;
+//! ```
+
+use crate::errors::{ExecutionError, MultisigError};
+use crate::tokens::Bytes as TokenBytes;
+use crate::transaction::Account;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use web3::api::Web3;
+use web3::signing;
+use web3::types::{Address, H256};
+use web3::Transport;
+
+/// The length of an `r || s || v` signature.
+const SIGNATURE_LEN: usize = 65;
+
+/// A set of signatures over the same EIP-712 hash, collected towards a
+/// signature threshold.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignatureSet {
+    hash: H256,
+    threshold: usize,
+    signatures: BTreeMap<Address, [u8; SIGNATURE_LEN]>,
+}
+
+impl SignatureSet {
+    /// Creates an empty set of signatures over the specified hash that
+    /// requires `threshold` signatures to be complete.
+    pub fn new(hash: H256, threshold: usize) -> Self {
+        SignatureSet {
+            hash,
+            threshold,
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the signed hash.
+    pub fn hash(&self) -> H256 {
+        self.hash
+    }
+
+    /// Returns the number of required signatures.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns the number of collected signatures.
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Returns true if no signatures were collected.
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Returns true if enough signatures were collected to meet the threshold.
+    pub fn is_complete(&self) -> bool {
+        self.signatures.len() >= self.threshold
+    }
+
+    /// Returns the signers of the collected signatures in ascending order.
+    pub fn signers(&self) -> Vec<Address> {
+        self.signatures.keys().copied().collect()
+    }
+
+    /// Adds a 65-byte signature and returns its recovered signer. Signatures
+    /// with a `v` value of 0 or 1 are normalized to 27 or 28.
+    pub fn insert(&mut self, signature: impl AsRef<[u8]>) -> Result<Address, MultisigError> {
+        let (signer, signature) = recover(self.hash, signature.as_ref())?;
+        self.add(signer, signature)
+    }
+
+    /// Adds a 65-byte signature that is expected to be from the specified
+    /// signer, returning an error if it was signed by a different account.
+    pub fn insert_from(
+        &mut self,
+        signer: Address,
+        signature: impl AsRef<[u8]>,
+    ) -> Result<(), MultisigError> {
+        let (recovered, signature) = recover(self.hash, signature.as_ref())?;
+        if recovered != signer {
+            return Err(MultisigError::UnexpectedSigner {
+                expected: signer,
+                recovered,
+            });
+        }
+        self.add(signer, signature)?;
+        Ok(())
+    }
+
+    /// Signs the message with an account and adds the signature.
+    ///
+    /// Accounts with a private key or an external signer sign the hash of the
+    /// set directly, while the node or the remote signer are asked to sign the
+    /// JSON `typed_data` with `eth_signTypedData_v4`. The typed data must
+    /// hash to the hash of the set, otherwise the signature is rejected as
+    /// being from an unexpected signer.
+    pub async fn sign<T: Transport>(
+        &mut self,
+        web3: &Web3<T>,
+        account: &Account,
+        typed_data: Value,
+    ) -> Result<(), ExecutionError> {
+        let signature = account.sign_typed_data(web3, typed_data, self.hash).await?;
+        self.insert_from(account.address(), signature.0)?;
+        Ok(())
+    }
+
+    /// Adds all signatures of another set over the same hash.
+    pub fn merge(&mut self, other: SignatureSet) -> Result<(), MultisigError> {
+        if self.hash != other.hash {
+            return Err(MultisigError::HashMismatch);
+        }
+        for (signer, signature) in other.signatures {
+            self.add(signer, signature)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the signatures concatenated in ascending signer order, as
+    /// expected by the Safe `execTransaction` function. Returns an error if
+    /// the threshold is not met.
+    pub fn packed(&self) -> Result<TokenBytes<Vec<u8>>, MultisigError> {
+        self.check_threshold()?;
+        Ok(TokenBytes(
+            self.signatures.values().flatten().copied().collect(),
+        ))
+    }
+
+    /// Returns the signatures as separate byte arrays in ascending signer
+    /// order, for contracts that take a `bytes[]` parameter. Returns an error
+    /// if the threshold is not met.
+    pub fn signatures(&self) -> Result<Vec<TokenBytes<Vec<u8>>>, MultisigError> {
+        self.check_threshold()?;
+        Ok(self
+            .signatures
+            .values()
+            .map(|signature| TokenBytes(signature.to_vec()))
+            .collect())
+    }
+
+    fn add(
+        &mut self,
+        signer: Address,
+        signature: [u8; SIGNATURE_LEN],
+    ) -> Result<Address, MultisigError> {
+        if self.signatures.contains_key(&signer) {
+            return Err(MultisigError::DuplicateSigner(signer));
+        }
+        self.signatures.insert(signer, signature);
+        Ok(signer)
+    }
+
+    fn check_threshold(&self) -> Result<(), MultisigError> {
+        if self.is_complete() {
+            Ok(())
+        } else {
+            Err(MultisigError::BelowThreshold {
+                signatures: self.signatures.len(),
+                threshold: self.threshold,
+            })
+        }
+    }
+}
+
+/// Recovers the signer of a signature and normalizes its `v` value.
+fn recover(hash: H256, signature: &[u8]) -> Result<(Address, [u8; SIGNATURE_LEN]), MultisigError> {
+    if signature.len() != SIGNATURE_LEN {
+        return Err(MultisigError::InvalidLength(signature.len()));
+    }
+    let recovery_id = match signature[64] {
+        v @ 0..=1 => v,
+        v @ 27..=28 => v - 27,
+        _ => return Err(MultisigError::InvalidSignature),
+    };
+    let signer = signing::recover(hash.as_bytes(), &signature[..64], recovery_id as _)
+        .map_err(|_| MultisigError::InvalidSignature)?;
+
+    let mut normalized = [0; SIGNATURE_LEN];
+    normalized.copy_from_slice(signature);
+    normalized[64] = 27 + recovery_id;
+    Ok((signer, normalized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use ethcontract_common::hash::keccak256;
+
+    fn keys() -> Vec<PrivateKey> {
+        vec![
+            key!("0x0101010101010101010101010101010101010101010101010101010101010101"),
+            key!("0x0202020202020202020202020202020202020202020202020202020202020202"),
+            key!("0x0303030303030303030303030303030303030303030303030303030303030303"),
+        ]
+    }
+
+    #[test]
+    fn collects_sorted_signatures() {
+        let web3 = Web3::new(TestTransport::new());
+        let hash = H256(keccak256("message"));
+        let keys = keys();
+
+        let mut set = SignatureSet::new(hash, 2);
+        for key in &keys[..2] {
+            set.sign(&web3, &Account::Offline(key.clone(), None), Value::Null)
+                .immediate()
+                .expect("failed to sign");
+        }
+        assert!(set.is_complete());
+
+        let mut signers = keys[..2]
+            .iter()
+            .map(PrivateKey::public_address)
+            .collect::<Vec<_>>();
+        signers.sort();
+        assert_eq!(set.signers(), signers);
+
+        let packed = set.packed().unwrap().0;
+        assert_eq!(packed.len(), 130);
+        for (signer, signature) in signers.iter().zip(packed.chunks(65)) {
+            assert!(matches!(signature[64], 27 | 28));
+            let mut other = SignatureSet::new(hash, 1);
+            assert_eq!(other.insert(signature).unwrap(), *signer);
+        }
+        assert_eq!(
+            set.signatures().unwrap(),
+            packed
+                .chunks(65)
+                .map(|signature| TokenBytes(signature.to_vec()))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn validates_signatures() {
+        let web3 = Web3::new(TestTransport::new());
+        let hash = H256(keccak256("message"));
+        let keys = keys();
+
+        let mut first = SignatureSet::new(hash, 3);
+        first
+            .sign(&web3, &Account::Offline(keys[0].clone(), None), Value::Null)
+            .immediate()
+            .unwrap();
+        assert!(matches!(
+            first.packed(),
+            Err(MultisigError::BelowThreshold {
+                signatures: 1,
+                threshold: 3,
+            })
+        ));
+
+        let signature = first.signatures.values().next().copied().unwrap();
+        assert!(matches!(
+            first.insert(signature),
+            Err(MultisigError::DuplicateSigner(_))
+        ));
+        assert!(matches!(
+            first.insert(&signature[..64]),
+            Err(MultisigError::InvalidLength(64))
+        ));
+        assert!(matches!(
+            first.insert_from(keys[1].public_address(), signature),
+            Err(MultisigError::UnexpectedSigner { .. })
+        ));
+
+        let mut second = SignatureSet::new(hash, 3);
+        second
+            .sign(&web3, &Account::Offline(keys[1].clone(), None), Value::Null)
+            .immediate()
+            .unwrap();
+        first.merge(second).unwrap();
+        assert_eq!(first.len(), 2);
+        assert!(matches!(
+            first.merge(SignatureSet::new(H256::zero(), 3)),
+            Err(MultisigError::HashMismatch)
+        ));
+    }
+}
@@ -0,0 +1,431 @@
+//! Helpers for retrieving the metadata of ERC721 and ERC1155 tokens.
+//!
+//! Token metadata URIs returned by `tokenURI` or `uri` are resolved, with
+//! inline `data:` URIs being decoded directly and all other URIs (such as
+//! `https://` and `ipfs://` URIs) being retrieved with a `ContentFetcher`.
+
+use crate::contract::Instance;
+use crate::errors::{MetadataError, MethodError};
+use ethcontract_common::hash::function_selector;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt::Debug;
+use web3::types::U256;
+use web3::Transport;
+
+/// The signature of the ERC721 metadata `tokenURI` function.
+const TOKEN_URI: &str = "tokenURI(uint256)";
+
+/// The signature of the ERC1155 metadata `uri` function.
+const URI: &str = "uri(uint256)";
+
+/// The metadata of an ERC721 or ERC1155 token.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    /// The name of the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The description of the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The URI of the token image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// An external URL for viewing the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    /// The URI of a multimedia attachment for the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    /// The attributes of the token.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<TokenAttribute>,
+    /// Any additional non-standard metadata fields.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// An attribute of an ERC721 or ERC1155 token.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenAttribute {
+    /// The name of the trait.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trait_type: Option<String>,
+    /// The value of the trait.
+    #[serde(default)]
+    pub value: Value,
+    /// How the trait should be displayed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_type: Option<String>,
+}
+
+/// A fetcher for retrieving content by URI.
+pub trait ContentFetcher: Debug + Send + Sync {
+    /// Retrieves the content at the specified URI. Note that `data:` URIs are
+    /// decoded directly and are never passed to content fetchers.
+    fn fetch<'a>(&'a self, uri: &'a str) -> BoxFuture<'a, Result<Vec<u8>, MetadataError>>;
+}
+
+impl<T: Transport> Instance<T> {
+    /// Retrieves the metadata of a token for an ERC721 contract implementing
+    /// `tokenURI` or an ERC1155 contract implementing `uri`.
+    pub async fn token_metadata(
+        &self,
+        token_id: U256,
+        fetcher: &dyn ContentFetcher,
+    ) -> Result<TokenMetadata, MetadataError> {
+        let signature = [TOKEN_URI, URI]
+            .iter()
+            .copied()
+            .find(|signature| {
+                self.abi()
+                    .functions()
+                    .any(|function| function.signature().starts_with(signature))
+            })
+            .ok_or(MetadataError::Unsupported)?;
+
+        let uri = self
+            .view_method::<_, String>(function_selector(signature), (token_id,))
+            .map_err(|err| MethodError::from_parts(signature.to_owned(), err.into()))?
+            .call()
+            .await?;
+
+        fetch_metadata(&token_uri(&uri, token_id), fetcher).await
+    }
+}
+
+/// Substitutes the `{id}` placeholder of an ERC1155 metadata URI with the
+/// hex encoded token ID.
+pub fn token_uri(uri: &str, token_id: U256) -> String {
+    uri.replace("{id}", &format!("{:064x}", token_id))
+}
+
+/// Retrieves and deserializes token metadata from its URI.
+pub async fn fetch_metadata(
+    uri: &str,
+    fetcher: &dyn ContentFetcher,
+) -> Result<TokenMetadata, MetadataError> {
+    let content = match decode_data_uri(uri)? {
+        Some(content) => content,
+        None => fetcher.fetch(uri).await?,
+    };
+    Ok(serde_json::from_slice(&content)?)
+}
+
+/// Decodes the content of a `data:` URI. Returns `None` for other URIs.
+pub fn decode_data_uri(uri: &str) -> Result<Option<Vec<u8>>, MetadataError> {
+    let data = match uri.strip_prefix("data:") {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let invalid = || MetadataError::InvalidUri(uri.to_owned());
+
+    let (media_type, data) = data.split_at(data.find(',').ok_or_else(invalid)?);
+    let data = percent_decode(&data[1..]).ok_or_else(invalid)?;
+    if media_type.ends_with(";base64") {
+        base64::decode(&data).map(Some).map_err(|_| invalid())
+    } else {
+        Ok(Some(data))
+    }
+}
+
+/// Decodes percent encoded characters in a URI.
+fn percent_decode(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = data.bytes();
+    let mut decoded = Vec::with_capacity(data.len());
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(feature = "nft-metadata")]
+pub use self::http::HttpFetcher;
+
+#[cfg(feature = "nft-metadata")]
+mod http {
+    use super::ContentFetcher;
+    use crate::errors::{IpfsError, MetadataError};
+    use ethcontract_common::ipfs::{IpfsGateways, IpfsPath};
+    use futures::future::{BoxFuture, FutureExt};
+    use std::future::Future;
+
+    /// A content fetcher that retrieves content over HTTP, with `ipfs://`
+    /// URIs being retrieved through IPFS gateways.
+    ///
+    /// IPFS gateways are tried in order until one of them returns content that
+    /// matches its content identifier, so that a misbehaving gateway cannot
+    /// serve tampered metadata for content that can be verified.
+    #[derive(Clone, Debug)]
+    pub struct HttpFetcher {
+        client: reqwest::Client,
+        ipfs: IpfsGateways,
+    }
+
+    impl HttpFetcher {
+        /// Creates a new HTTP content fetcher using the default public IPFS
+        /// gateways for retrieving `ipfs://` URIs.
+        pub fn new() -> Self {
+            HttpFetcher {
+                client: reqwest::Client::new(),
+                ipfs: IpfsGateways::default(),
+            }
+        }
+
+        /// Sets the IPFS gateways used for retrieving `ipfs://` URIs.
+        pub fn with_ipfs_gateways(mut self, gateways: IpfsGateways) -> Self {
+            self.ipfs = gateways;
+            self
+        }
+
+        /// Sets the HTTP client used for making requests.
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.client = client;
+            self
+        }
+
+        async fn get(&self, url: String) -> Result<Vec<u8>, MetadataError> {
+            let content = self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            Ok(content.to_vec())
+        }
+    }
+
+    impl Default for HttpFetcher {
+        fn default() -> Self {
+            HttpFetcher::new()
+        }
+    }
+
+    impl ContentFetcher for HttpFetcher {
+        fn fetch<'a>(&'a self, uri: &'a str) -> BoxFuture<'a, Result<Vec<u8>, MetadataError>> {
+            async move {
+                if uri.starts_with("ipfs://") {
+                    let path = uri.parse()?;
+                    retrieve(&self.ipfs, &path, |url| self.get(url)).await
+                } else {
+                    self.get(uri.to_owned()).await
+                }
+            }
+            .boxed()
+        }
+    }
+
+    /// Retrieves IPFS content from the first gateway that returns valid
+    /// content, returning the last error if all gateways fail.
+    async fn retrieve<F, Fut>(
+        gateways: &IpfsGateways,
+        path: &IpfsPath,
+        get: F,
+    ) -> Result<Vec<u8>, MetadataError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, MetadataError>>,
+    {
+        let mut result = Err(IpfsError::NoGateways.into());
+        for url in gateways.urls(path) {
+            result = match get(url).await {
+                Ok(content) => gateways
+                    .verify(path, &content)
+                    .map(|_| content)
+                    .map_err(From::from),
+                Err(err) => Err(err),
+            };
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test::prelude::*;
+        use futures::future;
+
+        const HELLO_WORLD: &str = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+
+        #[test]
+        fn retrieve_from_first_valid_gateway() {
+            let gateways = IpfsGateways::new(vec![
+                "https://down.test",
+                "https://evil.test",
+                "https://good.test",
+            ]);
+            let path = format!("ipfs://{}", HELLO_WORLD).parse().unwrap();
+
+            let content = retrieve(&gateways, &path, |url| {
+                future::ready(match url.as_str() {
+                    url if url.starts_with("https://down.test") => {
+                        Err(MetadataError::Fetch("offline".into()))
+                    }
+                    url if url.starts_with("https://evil.test") => Ok(b"evil".to_vec()),
+                    url => {
+                        assert_eq!(url, format!("https://good.test/{}", HELLO_WORLD));
+                        Ok(b"hello world\n".to_vec())
+                    }
+                })
+            })
+            .immediate()
+            .unwrap();
+
+            assert_eq!(content, b"hello world\n");
+        }
+
+        #[test]
+        fn retrieve_returns_last_error() {
+            let gateways = IpfsGateways::new(vec!["https://evil.test"]);
+            let path = format!("ipfs://{}", HELLO_WORLD).parse().unwrap();
+
+            let result =
+                retrieve(&gateways, &path, |_| future::ready(Ok(b"evil".to_vec()))).immediate();
+            assert!(matches!(
+                result,
+                Err(MetadataError::Ipfs(IpfsError::HashMismatch(_)))
+            ));
+
+            let result = retrieve(&IpfsGateways::new(Vec::<String>::new()), &path, |_| {
+                future::ready(Ok(Vec::new()))
+            })
+            .immediate();
+            assert!(matches!(
+                result,
+                Err(MetadataError::Ipfs(IpfsError::NoGateways))
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use ethcontract_common::Abi;
+    use futures::future::{self, FutureExt};
+    use web3::api::Web3;
+    use web3::types::Address;
+
+    /// A content fetcher that returns a fixed response.
+    #[derive(Debug)]
+    struct StaticFetcher(&'static str, &'static str);
+
+    impl ContentFetcher for StaticFetcher {
+        fn fetch<'a>(&'a self, uri: &'a str) -> BoxFuture<'a, Result<Vec<u8>, MetadataError>> {
+            assert_eq!(uri, self.0);
+            future::ready(Ok(self.1.as_bytes().to_vec())).boxed()
+        }
+    }
+
+    #[test]
+    fn decode_data_uris() {
+        assert_eq!(
+            decode_data_uri("data:application/json;base64,eyJuYW1lIjoiQSJ9").unwrap(),
+            Some(br#"{"name":"A"}"#.to_vec()),
+        );
+        assert_eq!(
+            decode_data_uri("data:application/json,%7B%22name%22:%22A%22%7D").unwrap(),
+            Some(br#"{"name":"A"}"#.to_vec()),
+        );
+        assert_eq!(decode_data_uri("ipfs://QmHash").unwrap(), None);
+        assert!(decode_data_uri("data:application/json").is_err());
+        assert!(decode_data_uri("data:application/json,%7").is_err());
+        assert!(decode_data_uri("data:application/json;base64,!").is_err());
+    }
+
+    #[test]
+    fn substitute_token_id() {
+        assert_eq!(
+            token_uri("https://token.test/{id}.json", 0x2a.into()),
+            "https://token.test/000000000000000000000000000000000000000000000000000000000000002a.json",
+        );
+        assert_eq!(token_uri("ipfs://QmHash/42", 42.into()), "ipfs://QmHash/42");
+    }
+
+    #[test]
+    fn erc721_token_metadata() {
+        let mut transport = TestTransport::new();
+        let abi = r#"[{
+            "type": "function",
+            "name": "tokenURI",
+            "inputs": [{ "name": "tokenId", "type": "uint256" }],
+            "outputs": [{ "name": "", "type": "string" }],
+            "stateMutability": "view"
+        }]"#;
+        let instance = Instance::at(
+            Web3::new(transport.clone()),
+            Abi::load(abi.as_bytes()).unwrap(),
+            Address::repeat_byte(0x42),
+        );
+        let fetcher = StaticFetcher(
+            "ipfs://QmHash/1",
+            r#"{
+                "name": "Token #1",
+                "image": "ipfs://QmImage",
+                "attributes": [{ "trait_type": "Color", "value": "red" }],
+                "background_color": "ffffff"
+            }"#,
+        );
+
+        transport.add_response(json!(
+            "0x\
+             0000000000000000000000000000000000000000000000000000000000000020\
+             000000000000000000000000000000000000000000000000000000000000000f\
+             697066733a2f2f516d486173682f310000000000000000000000000000000000"
+        ));
+        let metadata = instance
+            .token_metadata(1.into(), &fetcher)
+            .immediate()
+            .expect("token metadata");
+
+        assert_eq!(metadata.name.as_deref(), Some("Token #1"));
+        assert_eq!(metadata.image.as_deref(), Some("ipfs://QmImage"));
+        assert_eq!(
+            metadata.attributes,
+            vec![TokenAttribute {
+                trait_type: Some("Color".to_owned()),
+                value: json!("red"),
+                display_type: None,
+            }],
+        );
+        assert_eq!(metadata.extra["background_color"], json!("ffffff"));
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": Address::repeat_byte(0x42),
+                    "data": "0xc87b56dd0000000000000000000000000000000000000000000000000000000000000001",
+                }),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn unsupported_contract() {
+        let instance = Instance::at(
+            Web3::new(TestTransport::new()),
+            Abi::load(b"[]" as &[u8]).unwrap(),
+            Address::zero(),
+        );
+        let fetcher = StaticFetcher("", "");
+
+        assert!(matches!(
+            instance.token_metadata(1.into(), &fetcher).immediate(),
+            Err(MetadataError::Unsupported)
+        ));
+    }
+}
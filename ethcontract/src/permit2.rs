@@ -0,0 +1,716 @@
+//! Construction and signing of Uniswap Permit2 EIP-712 permits.
+//!
+//! Permit2 allows token approvals and transfers to be authorized by a signed
+//! message instead of an `approve` transaction. This module implements the
+//! `PermitSingle` and `PermitBatch` messages of the `AllowanceTransfer`
+//! interface and the `PermitTransferFrom` message of the `SignatureTransfer`
+//! interface. A permit is signed with [`sign`] by any runtime [`Account`],
+//! and the resulting [`SignedPermit`] provides the arguments for the Permit2
+//! functions or for downstream contracts that forward permits:
+//!
+//! ```ignore
+//! let permit = PermitSingle {
+//!     details: PermitDetails {
+//!         token: weth.address(),
+//!         amount: amount,
+//!         expiration: expiration,
+//!         nonce: 0,
+//!     },
+//!     spender: router.address(),
+//!     sig_deadline: deadline,
+//! };
+//! let domain = Permit2Domain::new(chain_id);
+//! let signed = permit2::sign(&web3, &account, &domain, permit).await?;
+//! let (owner, permit, signature) = signed.permit_args();
+//! permit2_contract.permit(owner, permit, signature).send().await?;
+//! ```
+
+use crate::errors::ExecutionError;
+use crate::tokens::Bytes as TokenBytes;
+use crate::transaction::Account;
+use ethcontract_common::hash::keccak256;
+use serde_json::{json, Value};
+use web3::api::Web3;
+use web3::types::{Address, Bytes, H160, H256, U256};
+use web3::Transport;
+
+/// The address of the canonical Permit2 contract, which is deployed at the same
+/// address on all chains.
+pub const PERMIT2: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x22, 0xd4, 0x73, 0x03, 0x0f, 0x11, 0x6d, 0xde, 0xe9, 0xf6, 0xb4,
+    0x3a, 0xc7, 0x8b, 0xa3,
+]);
+
+/// The `EIP712Domain` type of the Permit2 domain.
+const DOMAIN_TYPE: &[(&str, &str)] = &[
+    ("name", "string"),
+    ("chainId", "uint256"),
+    ("verifyingContract", "address"),
+];
+
+/// The `PermitDetails` type of the `AllowanceTransfer` interface.
+const PERMIT_DETAILS_TYPE: (&str, &[(&str, &str)]) = (
+    "PermitDetails",
+    &[
+        ("token", "address"),
+        ("amount", "uint160"),
+        ("expiration", "uint48"),
+        ("nonce", "uint48"),
+    ],
+);
+
+/// The `PermitSingle` type of the `AllowanceTransfer` interface.
+const PERMIT_SINGLE_TYPE: (&str, &[(&str, &str)]) = (
+    "PermitSingle",
+    &[
+        ("details", "PermitDetails"),
+        ("spender", "address"),
+        ("sigDeadline", "uint256"),
+    ],
+);
+
+/// The `PermitBatch` type of the `AllowanceTransfer` interface.
+const PERMIT_BATCH_TYPE: (&str, &[(&str, &str)]) = (
+    "PermitBatch",
+    &[
+        ("details", "PermitDetails[]"),
+        ("spender", "address"),
+        ("sigDeadline", "uint256"),
+    ],
+);
+
+/// The `PermitTransferFrom` type of the `SignatureTransfer` interface.
+const PERMIT_TRANSFER_FROM_TYPE: (&str, &[(&str, &str)]) = (
+    "PermitTransferFrom",
+    &[
+        ("permitted", "TokenPermissions"),
+        ("spender", "address"),
+        ("nonce", "uint256"),
+        ("deadline", "uint256"),
+    ],
+);
+
+/// The `TokenPermissions` type of the `SignatureTransfer` interface.
+const TOKEN_PERMISSIONS_TYPE: (&str, &[(&str, &str)]) = (
+    "TokenPermissions",
+    &[("token", "address"), ("amount", "uint256")],
+);
+
+/// The EIP-712 domain of a Permit2 contract.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Permit2Domain {
+    /// The chain ID of the network.
+    pub chain_id: u64,
+    /// The address of the Permit2 contract.
+    pub verifying_contract: Address,
+}
+
+impl Permit2Domain {
+    /// Creates the domain of the canonical Permit2 contract on the specified
+    /// chain.
+    pub fn new(chain_id: u64) -> Self {
+        Permit2Domain {
+            chain_id,
+            verifying_contract: PERMIT2,
+        }
+    }
+
+    /// Specify the address of the Permit2 contract, for chains where it is
+    /// not deployed at the canonical address.
+    pub fn verifying_contract(mut self, address: Address) -> Self {
+        self.verifying_contract = address;
+        self
+    }
+
+    /// Returns the EIP-712 domain separator.
+    pub fn separator(&self) -> H256 {
+        struct_hash(
+            ("EIP712Domain", DOMAIN_TYPE),
+            &[],
+            &[
+                H256(keccak256("Permit2")),
+                uint(self.chain_id.into()),
+                address(self.verifying_contract),
+            ],
+        )
+    }
+
+    /// Returns the JSON representation of the domain.
+    fn to_json(self) -> Value {
+        json!({
+            "name": "Permit2",
+            "chainId": self.chain_id,
+            "verifyingContract": self.verifying_contract,
+        })
+    }
+}
+
+/// A Permit2 message that can be signed.
+pub trait Permit2Message {
+    /// Returns the EIP-712 struct hash of the message.
+    fn struct_hash(&self) -> H256;
+
+    /// Returns the JSON representation of the message together with its
+    /// primary type and the definitions of the types it uses, as expected by
+    /// `eth_signTypedData_v4`.
+    fn typed_data(&self) -> (&'static str, Value, Value);
+
+    /// Returns the EIP-712 hash of the message in the specified domain, which
+    /// is the digest that is signed.
+    fn signing_hash(&self, domain: &Permit2Domain) -> H256 {
+        let mut message = [0; 66];
+        message[..2].copy_from_slice(&[0x19, 0x01]);
+        message[2..34].copy_from_slice(domain.separator().as_bytes());
+        message[34..].copy_from_slice(self.struct_hash().as_bytes());
+        H256(keccak256(message))
+    }
+}
+
+/// The allowance details of a token in an `AllowanceTransfer` permit.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PermitDetails {
+    /// The token to approve.
+    pub token: Address,
+    /// The approved amount, which must fit in a `uint160`.
+    pub amount: U256,
+    /// The timestamp at which the allowance expires, which must fit in a
+    /// `uint48`.
+    pub expiration: u64,
+    /// The allowance nonce of the owner for the token and spender, which must
+    /// fit in a `uint48`.
+    pub nonce: u64,
+}
+
+impl PermitDetails {
+    /// Returns the details as a tuple, which is how they are represented in
+    /// generated contract bindings.
+    pub fn into_tuple(self) -> (Address, U256, u64, u64) {
+        (self.token, self.amount, self.expiration, self.nonce)
+    }
+
+    fn struct_hash(&self) -> H256 {
+        struct_hash(
+            PERMIT_DETAILS_TYPE,
+            &[],
+            &[
+                address(self.token),
+                uint(self.amount),
+                uint(self.expiration.into()),
+                uint(self.nonce.into()),
+            ],
+        )
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "token": self.token,
+            "amount": self.amount.to_string(),
+            "expiration": self.expiration.to_string(),
+            "nonce": self.nonce.to_string(),
+        })
+    }
+}
+
+/// An `AllowanceTransfer` permit approving a spender for a single token.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PermitSingle {
+    /// The allowance details of the token.
+    pub details: PermitDetails,
+    /// The approved spender.
+    pub spender: Address,
+    /// The timestamp after which the signature is no longer valid.
+    pub sig_deadline: U256,
+}
+
+impl PermitSingle {
+    /// Returns the permit as a tuple, which is how it is represented in
+    /// generated contract bindings.
+    pub fn into_tuple(self) -> ((Address, U256, u64, u64), Address, U256) {
+        (self.details.into_tuple(), self.spender, self.sig_deadline)
+    }
+}
+
+impl Permit2Message for PermitSingle {
+    fn struct_hash(&self) -> H256 {
+        struct_hash(
+            PERMIT_SINGLE_TYPE,
+            &[PERMIT_DETAILS_TYPE],
+            &[
+                self.details.struct_hash(),
+                address(self.spender),
+                uint(self.sig_deadline),
+            ],
+        )
+    }
+
+    fn typed_data(&self) -> (&'static str, Value, Value) {
+        (
+            "PermitSingle",
+            json!({
+                "PermitSingle": json_type(PERMIT_SINGLE_TYPE.1),
+                "PermitDetails": json_type(PERMIT_DETAILS_TYPE.1),
+            }),
+            json!({
+                "details": self.details.to_json(),
+                "spender": self.spender,
+                "sigDeadline": self.sig_deadline.to_string(),
+            }),
+        )
+    }
+}
+
+/// An `AllowanceTransfer` permit approving a spender for multiple tokens.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PermitBatch {
+    /// The allowance details of the tokens.
+    pub details: Vec<PermitDetails>,
+    /// The approved spender.
+    pub spender: Address,
+    /// The timestamp after which the signature is no longer valid.
+    pub sig_deadline: U256,
+}
+
+impl PermitBatch {
+    /// Returns the permit as a tuple, which is how it is represented in
+    /// generated contract bindings.
+    #[allow(clippy::type_complexity)]
+    pub fn into_tuple(self) -> (Vec<(Address, U256, u64, u64)>, Address, U256) {
+        (
+            self.details
+                .into_iter()
+                .map(PermitDetails::into_tuple)
+                .collect(),
+            self.spender,
+            self.sig_deadline,
+        )
+    }
+}
+
+impl Permit2Message for PermitBatch {
+    fn struct_hash(&self) -> H256 {
+        let details = self
+            .details
+            .iter()
+            .map(|details| details.struct_hash().0)
+            .collect::<Vec<_>>()
+            .concat();
+        struct_hash(
+            PERMIT_BATCH_TYPE,
+            &[PERMIT_DETAILS_TYPE],
+            &[
+                H256(keccak256(details)),
+                address(self.spender),
+                uint(self.sig_deadline),
+            ],
+        )
+    }
+
+    fn typed_data(&self) -> (&'static str, Value, Value) {
+        (
+            "PermitBatch",
+            json!({
+                "PermitBatch": json_type(PERMIT_BATCH_TYPE.1),
+                "PermitDetails": json_type(PERMIT_DETAILS_TYPE.1),
+            }),
+            json!({
+                "details": self
+                    .details
+                    .iter()
+                    .map(|details| details.to_json())
+                    .collect::<Vec<_>>(),
+                "spender": self.spender,
+                "sigDeadline": self.sig_deadline.to_string(),
+            }),
+        )
+    }
+}
+
+/// A `SignatureTransfer` permit allowing a spender to transfer a token once.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PermitTransferFrom {
+    /// The token that can be transferred.
+    pub token: Address,
+    /// The maximum amount that can be transferred.
+    pub amount: U256,
+    /// The spender that is allowed to transfer the tokens. The spender is not
+    /// part of the permit that is passed to the contract, since it is the
+    /// caller of `permitTransferFrom`.
+    pub spender: Address,
+    /// An unordered nonce of the owner, which can only be used once.
+    pub nonce: U256,
+    /// The timestamp after which the signature is no longer valid.
+    pub deadline: U256,
+}
+
+impl PermitTransferFrom {
+    /// Returns the permit as a tuple, which is how it is represented in
+    /// generated contract bindings.
+    pub fn into_tuple(self) -> ((Address, U256), U256, U256) {
+        ((self.token, self.amount), self.nonce, self.deadline)
+    }
+}
+
+impl Permit2Message for PermitTransferFrom {
+    fn struct_hash(&self) -> H256 {
+        struct_hash(
+            PERMIT_TRANSFER_FROM_TYPE,
+            &[TOKEN_PERMISSIONS_TYPE],
+            &[
+                struct_hash(
+                    TOKEN_PERMISSIONS_TYPE,
+                    &[],
+                    &[address(self.token), uint(self.amount)],
+                ),
+                address(self.spender),
+                uint(self.nonce),
+                uint(self.deadline),
+            ],
+        )
+    }
+
+    fn typed_data(&self) -> (&'static str, Value, Value) {
+        (
+            "PermitTransferFrom",
+            json!({
+                "PermitTransferFrom": json_type(PERMIT_TRANSFER_FROM_TYPE.1),
+                "TokenPermissions": json_type(TOKEN_PERMISSIONS_TYPE.1),
+            }),
+            json!({
+                "permitted": {
+                    "token": self.token,
+                    "amount": self.amount.to_string(),
+                },
+                "spender": self.spender,
+                "nonce": self.nonce.to_string(),
+                "deadline": self.deadline.to_string(),
+            }),
+        )
+    }
+}
+
+/// A permit signed by its owner.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignedPermit<P> {
+    /// The owner of the tokens that signed the permit.
+    pub owner: Address,
+    /// The permit.
+    pub permit: P,
+    /// The 65-byte signature of the permit.
+    pub signature: Bytes,
+}
+
+impl<P> SignedPermit<P> {
+    /// Returns the signature in the representation used by generated
+    /// contract bindings, for passing it to contracts that forward permits
+    /// to Permit2.
+    pub fn signature(&self) -> TokenBytes<Vec<u8>> {
+        TokenBytes(self.signature.0.clone())
+    }
+}
+
+impl SignedPermit<PermitSingle> {
+    /// Returns the arguments of the Permit2
+    /// `permit(address,PermitSingle,bytes)` function.
+    #[allow(clippy::type_complexity)]
+    pub fn permit_args(
+        &self,
+    ) -> (
+        Address,
+        ((Address, U256, u64, u64), Address, U256),
+        TokenBytes<Vec<u8>>,
+    ) {
+        (self.owner, self.permit.into_tuple(), self.signature())
+    }
+}
+
+impl SignedPermit<PermitBatch> {
+    /// Returns the arguments of the Permit2
+    /// `permit(address,PermitBatch,bytes)` function.
+    #[allow(clippy::type_complexity)]
+    pub fn permit_args(
+        &self,
+    ) -> (
+        Address,
+        (Vec<(Address, U256, u64, u64)>, Address, U256),
+        TokenBytes<Vec<u8>>,
+    ) {
+        (
+            self.owner,
+            self.permit.clone().into_tuple(),
+            self.signature(),
+        )
+    }
+}
+
+impl SignedPermit<PermitTransferFrom> {
+    /// Returns the arguments of the Permit2
+    /// `permitTransferFrom(PermitTransferFrom,SignatureTransferDetails,address,bytes)`
+    /// function for transferring `amount` tokens to `to`.
+    #[allow(clippy::type_complexity)]
+    pub fn transfer_args(
+        &self,
+        to: Address,
+        amount: U256,
+    ) -> (
+        ((Address, U256), U256, U256),
+        (Address, U256),
+        Address,
+        TokenBytes<Vec<u8>>,
+    ) {
+        (
+            self.permit.into_tuple(),
+            (to, amount),
+            self.owner,
+            self.signature(),
+        )
+    }
+}
+
+/// Signs a Permit2 message with an account.
+///
+/// Accounts with a private key or an external signer sign the EIP-712 hash of
+/// the message directly, while the node or the remote signer are asked to sign
+/// the typed data with `eth_signTypedData_v4`, so that wallets can display the
+/// permit to the user. Locked accounts are not supported.
+pub async fn sign<T: Transport, P: Permit2Message>(
+    web3: &Web3<T>,
+    account: &Account,
+    domain: &Permit2Domain,
+    permit: P,
+) -> Result<SignedPermit<P>, ExecutionError> {
+    let (primary_type, mut types, message) = permit.typed_data();
+    types["EIP712Domain"] = json_type(DOMAIN_TYPE);
+    let typed_data = json!({
+        "types": types,
+        "primaryType": primary_type,
+        "domain": domain.to_json(),
+        "message": message,
+    });
+    let signature = account
+        .sign_typed_data(web3, typed_data, permit.signing_hash(domain))
+        .await?;
+
+    Ok(SignedPermit {
+        owner: account.address(),
+        permit,
+        signature,
+    })
+}
+
+/// Computes the EIP-712 hash of a struct from its type, the types it
+/// references and its encoded fields.
+fn struct_hash(
+    (name, fields): (&str, &[(&str, &str)]),
+    referenced: &[(&str, &[(&str, &str)])],
+    values: &[H256],
+) -> H256 {
+    debug_assert_eq!(fields.len(), values.len());
+
+    let mut encoded_type = encode_type(name, fields);
+    for (name, fields) in referenced {
+        encoded_type.push_str(&encode_type(name, fields));
+    }
+
+    let mut encoded = keccak256(encoded_type).to_vec();
+    for value in values {
+        encoded.extend_from_slice(value.as_bytes());
+    }
+    H256(keccak256(encoded))
+}
+
+/// Encodes a struct type, for example `Mail(address from,address to)`.
+fn encode_type(name: &str, fields: &[(&str, &str)]) -> String {
+    let fields = fields
+        .iter()
+        .map(|(name, kind)| format!("{} {}", kind, name))
+        .collect::<Vec<_>>();
+    format!("{}({})", name, fields.join(","))
+}
+
+/// Returns the JSON definition of a struct type.
+fn json_type(fields: &[(&str, &str)]) -> Value {
+    fields
+        .iter()
+        .map(|(name, kind)| json!({ "name": name, "type": kind }))
+        .collect()
+}
+
+fn address(address: Address) -> H256 {
+    address.into()
+}
+
+fn uint(value: U256) -> H256 {
+    let mut word = H256::zero();
+    value.to_big_endian(word.as_bytes_mut());
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use web3::signing;
+
+    fn type_hash(
+        (name, fields): (&str, &[(&str, &str)]),
+        referenced: &[(&str, &[(&str, &str)])],
+    ) -> H256 {
+        let mut encoded_type = encode_type(name, fields);
+        for (name, fields) in referenced {
+            encoded_type.push_str(&encode_type(name, fields));
+        }
+        H256(keccak256(encoded_type))
+    }
+
+    fn permit() -> PermitSingle {
+        PermitSingle {
+            details: PermitDetails {
+                token: Address::repeat_byte(0x42),
+                amount: 1_000_000.into(),
+                expiration: 1_700_000_000,
+                nonce: 1,
+            },
+            spender: Address::repeat_byte(0x01),
+            sig_deadline: 1_700_000_000.into(),
+        }
+    }
+
+    #[test]
+    fn type_hashes() {
+        // Type hashes from the Permit2 `PermitHash` library.
+        assert_eq!(
+            type_hash(PERMIT_DETAILS_TYPE, &[]),
+            "65626cad6cb96493bf6f5ebea28756c966f023ab9e8a83a7101849d5573b3678"
+                .parse::<H256>()
+                .unwrap(),
+        );
+        assert_eq!(
+            type_hash(PERMIT_SINGLE_TYPE, &[PERMIT_DETAILS_TYPE]),
+            "f3841cd1ff0085026a6327b620b67997ce40f282c88a8e905a7a5626e310f3d0"
+                .parse::<H256>()
+                .unwrap(),
+        );
+        assert_eq!(
+            type_hash(PERMIT_BATCH_TYPE, &[PERMIT_DETAILS_TYPE]),
+            "af1b0d30d2cab0380e68f0689007e3254993c596f2fdd0aaa7f4d04f79440863"
+                .parse::<H256>()
+                .unwrap(),
+        );
+        assert_eq!(
+            type_hash(TOKEN_PERMISSIONS_TYPE, &[]),
+            "618358ac3db8dc274f0cd8829da7e234bd48cd73c4a740aede1adec9846d06a1"
+                .parse::<H256>()
+                .unwrap(),
+        );
+        assert_eq!(
+            type_hash(PERMIT_TRANSFER_FROM_TYPE, &[TOKEN_PERMISSIONS_TYPE]),
+            "939c21a48a8dbe3a9a2404a1d46691e4d39f6583d6ec6b35714604c986d80106"
+                .parse::<H256>()
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn sign_offline() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let key = PrivateKey::from_raw([0x11; 32]).unwrap();
+        let domain = Permit2Domain::new(1);
+
+        let signed = sign(
+            &web3,
+            &Account::Offline(key.clone(), None),
+            &domain,
+            permit(),
+        )
+        .immediate()
+        .unwrap();
+
+        assert_eq!(signed.owner, key.public_address());
+        assert_eq!(signed.signature.0.len(), 65);
+        let hash = permit().signing_hash(&domain);
+        let recovery_id = signed.signature.0[64] - 27;
+        assert_eq!(
+            signing::recover(hash.as_bytes(), &signed.signature.0[..64], recovery_id as _),
+            Ok(key.public_address()),
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn sign_with_node() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let owner = Address::repeat_byte(0x99);
+        let domain = Permit2Domain::new(1);
+        let signature = Bytes(vec![0x1b; 65]);
+
+        transport.add_response(json!(signature));
+        let signed = sign(&web3, &Account::Local(owner, None), &domain, permit())
+            .immediate()
+            .unwrap();
+
+        assert_eq!(signed.signature, signature);
+        transport.assert_request(
+            "eth_signTypedData_v4",
+            &[
+                json!(owner),
+                json!({
+                    "types": {
+                        "EIP712Domain": [
+                            { "name": "name", "type": "string" },
+                            { "name": "chainId", "type": "uint256" },
+                            { "name": "verifyingContract", "type": "address" },
+                        ],
+                        "PermitSingle": [
+                            { "name": "details", "type": "PermitDetails" },
+                            { "name": "spender", "type": "address" },
+                            { "name": "sigDeadline", "type": "uint256" },
+                        ],
+                        "PermitDetails": [
+                            { "name": "token", "type": "address" },
+                            { "name": "amount", "type": "uint160" },
+                            { "name": "expiration", "type": "uint48" },
+                            { "name": "nonce", "type": "uint48" },
+                        ],
+                    },
+                    "primaryType": "PermitSingle",
+                    "domain": {
+                        "name": "Permit2",
+                        "chainId": 1,
+                        "verifyingContract": PERMIT2,
+                    },
+                    "message": {
+                        "details": {
+                            "token": Address::repeat_byte(0x42),
+                            "amount": "1000000",
+                            "expiration": "1700000000",
+                            "nonce": "1",
+                        },
+                        "spender": Address::repeat_byte(0x01),
+                        "sigDeadline": "1700000000",
+                    },
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        let (permit_owner, permit, signature) = signed.permit_args();
+        assert_eq!(permit_owner, owner);
+        assert_eq!(
+            permit,
+            (
+                (
+                    Address::repeat_byte(0x42),
+                    1_000_000.into(),
+                    1_700_000_000,
+                    1
+                ),
+                Address::repeat_byte(0x01),
+                1_700_000_000.into(),
+            ),
+        );
+        assert_eq!(signature.0, vec![0x1b; 65]);
+    }
+}
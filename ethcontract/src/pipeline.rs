@@ -0,0 +1,322 @@
+//! Module for composing contract calls that depend on the results of other
+//! calls into pipelines that are executed with as few roundtrips as possible.
+//!
+//! A [`Pipeline`] describes a read flow as a graph of view calls, where the
+//! outputs of calls can be used to build the next calls. Independent calls are
+//! sent together in one batch, so a pipeline needs one roundtrip per level of
+//! dependent calls:
+//!
+//! ```ignore
+//! let quote = Pipeline::read(pool.slot_0())
+//!     .then(move |(_, tick, ..)| quoter.quote(tick))
+//!     .join(Pipeline::read(token.decimals()))
+//!     .execute(&web3, 100)
+//!     .await?;
+//! ```
+
+use crate::batch::CallBatch;
+use crate::contract::ViewMethodBuilder;
+use crate::errors::MethodError;
+use crate::tokens::Tokenize;
+use futures::future::{self, BoxFuture, FutureExt};
+use web3::api::Web3;
+use web3::{BatchTransport, Transport};
+
+/// A function adding the calls of the next step of a pipeline to a batch. The
+/// returned future resolves to the remaining pipeline once the batch executed.
+type Push<B, O> = Box<
+    dyn FnOnce(&mut CallBatch<B>) -> BoxFuture<'static, Result<Pipeline<B, O>, MethodError>> + Send,
+>;
+
+/// A composition of contract view calls, where the outputs of calls can feed
+/// the inputs of the next calls, with an output of type `O`.
+///
+/// Pipelines are lazy and do not send any requests until they are executed.
+/// Each step of the execution batches all calls whose inputs are known into a
+/// single roundtrip to the node over the transport `B`.
+#[must_use = "pipelines do nothing unless executed"]
+pub struct Pipeline<B: BatchTransport, O> {
+    step: Step<B, O>,
+}
+
+enum Step<B: BatchTransport, O> {
+    Done(O),
+    Pending(Push<B, O>),
+}
+
+impl<B, O> Pipeline<B, O>
+where
+    B: BatchTransport + Send + 'static,
+    O: Send + 'static,
+{
+    /// Creates a pipeline that outputs a known value without making any calls.
+    pub fn value(output: O) -> Self {
+        Pipeline {
+            step: Step::Done(output),
+        }
+    }
+
+    /// Creates a pipeline that outputs the result of a view call.
+    pub fn read<T>(call: ViewMethodBuilder<T, O>) -> Self
+    where
+        T: Transport + Send + 'static,
+        O: Tokenize,
+    {
+        Pipeline::pending(move |batch| {
+            let call = call.batch_call(batch);
+            async move { Ok(Pipeline::value(call.await?)) }.boxed()
+        })
+    }
+
+    /// Creates a pipeline that outputs the results of all specified
+    /// pipelines. The pipelines are executed side by side, so their
+    /// independent calls share roundtrips.
+    pub fn join_all(pipelines: impl IntoIterator<Item = Pipeline<B, O>>) -> Pipeline<B, Vec<O>> {
+        let pipelines = pipelines.into_iter().collect::<Vec<_>>();
+        if pipelines
+            .iter()
+            .all(|pipeline| matches!(pipeline.step, Step::Done(_)))
+        {
+            return Pipeline::value(
+                pipelines
+                    .into_iter()
+                    .filter_map(|pipeline| match pipeline.step {
+                        Step::Done(output) => Some(output),
+                        Step::Pending(_) => None,
+                    })
+                    .collect(),
+            );
+        }
+
+        Pipeline::pending(move |batch| {
+            let pending = pipelines
+                .into_iter()
+                .map(|pipeline| pipeline.push(batch))
+                .collect::<Vec<_>>();
+            async move { Ok(Pipeline::join_all(future::try_join_all(pending).await?)) }.boxed()
+        })
+    }
+
+    /// Continues the pipeline with a view call built from its output.
+    pub fn then<T, U, F>(self, f: F) -> Pipeline<B, U>
+    where
+        T: Transport + Send + 'static,
+        U: Tokenize + Send + 'static,
+        F: FnOnce(O) -> ViewMethodBuilder<T, U> + Send + 'static,
+    {
+        self.and_then(move |output| Pipeline::read(f(output)))
+    }
+
+    /// Continues the pipeline with another pipeline built from its output,
+    /// for example for making multiple calls that depend on the output.
+    pub fn and_then<U, F>(self, f: F) -> Pipeline<B, U>
+    where
+        U: Send + 'static,
+        F: FnOnce(O) -> Pipeline<B, U> + Send + 'static,
+    {
+        match self.step {
+            Step::Done(output) => f(output),
+            Step::Pending(push) => Pipeline::pending(move |batch| {
+                let next = push(batch);
+                async move { Ok(next.await?.and_then(f)) }.boxed()
+            }),
+        }
+    }
+
+    /// Transforms the output of the pipeline without making any calls.
+    pub fn map<U, F>(self, f: F) -> Pipeline<B, U>
+    where
+        U: Send + 'static,
+        F: FnOnce(O) -> U + Send + 'static,
+    {
+        self.and_then(move |output| Pipeline::value(f(output)))
+    }
+
+    /// Combines the pipeline with another independent pipeline, outputting
+    /// both of their outputs. The calls of both pipelines share roundtrips.
+    pub fn join<U>(self, other: Pipeline<B, U>) -> Pipeline<B, (O, U)>
+    where
+        U: Send + 'static,
+    {
+        match (self.step, other.step) {
+            (Step::Done(left), Step::Done(right)) => Pipeline::value((left, right)),
+            (left, right) => {
+                let (left, right) = (Pipeline { step: left }, Pipeline { step: right });
+                Pipeline::pending(move |batch| {
+                    let left = left.push(batch);
+                    let right = right.push(batch);
+                    async move {
+                        let (left, right) = future::try_join(left, right).await?;
+                        Ok(left.join(right))
+                    }
+                    .boxed()
+                })
+            }
+        }
+    }
+
+    /// Executes the pipeline, sending the calls of each step in batches of
+    /// `batch_size` requests, each in a single roundtrip, and returns its
+    /// output. Execution stops at the first call that fails.
+    ///
+    /// Note that the timeouts of the calls do not apply to batched calls.
+    pub async fn execute(self, web3: &Web3<B>, batch_size: usize) -> Result<O, MethodError> {
+        let mut pipeline = self;
+        loop {
+            match pipeline.step {
+                Step::Done(output) => return Ok(output),
+                Step::Pending(push) => {
+                    let mut batch = CallBatch::new(web3.transport().clone());
+                    let next = push(&mut batch);
+                    batch.execute_all(batch_size.max(1)).await;
+                    pipeline = next.await?;
+                }
+            }
+        }
+    }
+
+    fn pending<F>(push: F) -> Self
+    where
+        F: FnOnce(&mut CallBatch<B>) -> BoxFuture<'static, Result<Self, MethodError>>
+            + Send
+            + 'static,
+    {
+        Pipeline {
+            step: Step::Pending(Box::new(push)),
+        }
+    }
+
+    /// Adds the calls of the next step to a batch, or returns the remaining
+    /// pipeline right away if it has no more calls.
+    fn push(self, batch: &mut CallBatch<B>) -> BoxFuture<'static, Result<Self, MethodError>> {
+        match self.step {
+            Step::Done(output) => future::ok(Pipeline::value(output)).boxed(),
+            Step::Pending(push) => push(batch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::MethodBuilder;
+    use crate::test::prelude::*;
+    use ethcontract_common::abi::{Function, Param, ParamType, Token};
+    use web3::types::{Address, Bytes, U256};
+
+    fn word(value: u64) -> String {
+        format!("0x{:064x}", value)
+    }
+
+    fn function(name: &str, inputs: usize) -> Function {
+        let param = |name: &str| Param {
+            name: name.to_owned(),
+            kind: ParamType::Uint(256),
+        };
+        #[allow(deprecated)]
+        Function {
+            name: name.to_owned(),
+            inputs: (0..inputs).map(|_| param("value")).collect(),
+            outputs: vec![param("")],
+            constant: false,
+            state_mutability: Default::default(),
+        }
+    }
+
+    fn call(
+        web3: &Web3<TestTransport>,
+        address: Address,
+        function: Function,
+        inputs: &[U256],
+    ) -> (ViewMethodBuilder<TestTransport, U256>, Bytes) {
+        let tokens = inputs.iter().copied().map(Token::Uint).collect::<Vec<_>>();
+        let data = Bytes(function.encode_input(&tokens).unwrap());
+        let call = MethodBuilder::new(web3.clone(), function, address, data.clone()).view();
+        (call, data)
+    }
+
+    #[test]
+    fn batches_independent_calls() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let pool = addr!("0x0123456789012345678901234567890123456789");
+        let quoter = addr!("0x9876543210987654321098765432109876543210");
+
+        let (price, price_data) = call(&web3, pool, function("price", 0), &[]);
+        let (liquidity, liquidity_data) = call(&web3, pool, function("liquidity", 0), &[]);
+        let (_, quote_data) = call(&web3, quoter, function("quote", 2), &[2.into(), 3.into()]);
+        let quote = {
+            let web3 = web3.clone();
+            move |(price, liquidity): (U256, U256)| {
+                call(&web3, quoter, function("quote", 2), &[price, liquidity]).0
+            }
+        };
+
+        transport.add_response(json!([word(2), word(3)]));
+        transport.add_response(json!([word(42)]));
+        let (quote, doubled) = Pipeline::read(price)
+            .join(Pipeline::read(liquidity))
+            .then(quote)
+            .join(Pipeline::value(21).map(|value: u64| value * 2))
+            .execute(&web3, 10)
+            .immediate()
+            .expect("pipeline error");
+
+        assert_eq!(quote, U256::from(42));
+        assert_eq!(doubled, 42);
+        for (address, data) in &[
+            (pool, price_data),
+            (pool, liquidity_data),
+            (quoter, quote_data),
+        ] {
+            transport.assert_request(
+                "eth_call",
+                &[json!({ "to": address, "data": data }), json!("latest")],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn fans_out_dependent_calls() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let registry = addr!("0x0123456789012345678901234567890123456789");
+
+        let (count, count_data) = call(&web3, registry, function("count", 0), &[]);
+        let pipeline = {
+            let web3 = web3.clone();
+            Pipeline::read(count).and_then(move |count| {
+                Pipeline::join_all((0..count.as_u64()).map(|index| {
+                    let (item, _) = call(&web3, registry, function("item", 1), &[index.into()]);
+                    Pipeline::read(item)
+                }))
+            })
+        };
+
+        transport.add_response(json!([word(3)]));
+        transport.add_response(json!([word(7), word(8), word(9)]));
+        let items = pipeline
+            .map(|items| items.into_iter().fold(U256::zero(), |sum, item| sum + item))
+            .execute(&web3, 10)
+            .immediate()
+            .expect("pipeline error");
+
+        assert_eq!(items, U256::from(24));
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({ "to": registry, "data": count_data }),
+                json!("latest"),
+            ],
+        );
+        for index in 0..3 {
+            let (_, data) = call(&web3, registry, function("item", 1), &[index.into()]);
+            transport.assert_request(
+                "eth_call",
+                &[json!({ "to": registry, "data": data }), json!("latest")],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+}
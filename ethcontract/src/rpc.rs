@@ -0,0 +1,129 @@
+//! Low-level access to JSON-RPC methods that are not covered by the contract
+//! bindings, such as the non-standard `debug_*`, `trace_*` and `txpool_*`
+//! namespaces, the EIP-1559 `eth_feeHistory` method, or the
+//! `eth_getBlockReceipts` method for fetching many receipts at once. Blocks
+//! and logs can also be fetched in bulk over the EIP-1767 GraphQL API of a
+//! node with a [`GraphQlFetcher`], which falls back to JSON-RPC for nodes
+//! that do not expose it.
+//!
+//! This allows calling arbitrary node methods over the same transport that is
+//! used for the contract bindings, without having to maintain a second
+//! client. Typed wrappers are provided for commonly used methods, as well as
+//! an [`InternalCallTracer`] for retrieving internal transactions over either
+//! tracing namespace.
+
+mod debug;
+mod decode;
+mod fee;
+mod graphql;
+mod internal;
+mod receipts;
+mod trace;
+mod txpool;
+
+pub use self::debug::{
+    debug_trace_block_by_number, debug_trace_call, debug_trace_transaction, CallFrame, StructLog,
+    StructLogTrace, TraceOptions, TransactionTrace,
+};
+pub(crate) use self::decode::DisplayToken;
+pub use self::decode::{CallTraceDecoder, DecodedCallFrame, DecodedMethod};
+pub use self::fee::{fee_history, FeeHistory};
+#[cfg(feature = "http")]
+pub use self::graphql::HttpGraphQlClient;
+pub use self::graphql::{GraphQlClient, GraphQlFetcher};
+pub use self::internal::{InternalCall, InternalCallTracer, TraceBackend};
+pub use self::receipts::{block_receipts, ReceiptFetcher};
+pub use self::trace::{
+    trace_block, trace_call, trace_filter, trace_replay_transaction, trace_transaction,
+};
+pub use self::txpool::{
+    pool_transaction, txpool_content, txpool_status, PoolState, PooledTransaction, TxpoolContent,
+    TxpoolTransaction,
+};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::{helpers, Transport};
+
+/// Calls a JSON-RPC method with the specified parameters and deserializes its
+/// result.
+///
+/// Parameters are serialized to JSON, with tuples and sequences being used as
+/// positional parameters, `()` for no parameters, and any other value as a
+/// single parameter.
+///
+/// ```no_run
+/// # async fn run(web3: ethcontract::Web3<ethcontract::Http>) -> Result<(), ethcontract::web3::Error> {
+/// # let hash = ethcontract::H256::zero();
+/// use ethcontract::json::{json, Value};
+/// use ethcontract::rpc;
+///
+/// let trace: Value = rpc::call(
+///     &web3,
+///     "debug_traceTransaction",
+///     (hash, json!({ "tracer": "callTracer" })),
+/// )
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn call<T, P, R>(web3: &Web3<T>, method: &str, params: P) -> Result<R, Web3Error>
+where
+    T: Transport,
+    P: Serialize,
+    R: DeserializeOwned,
+{
+    let params = match helpers::serialize(&params) {
+        Value::Array(params) => params,
+        Value::Null => Vec::new(),
+        param => vec![param],
+    };
+    let result = web3.transport().execute(method, params).await?;
+    helpers::decode(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use web3::types::{H256, U64};
+
+    #[test]
+    fn call_with_params() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!("0x2a"));
+        let result: U64 = call(&web3, "eth_blockNumber", ()).immediate().unwrap();
+        assert_eq!(result, U64::from(42));
+        transport.assert_request("eth_blockNumber", &[]);
+
+        transport.add_response(json!(null));
+        let result: Option<Value> = call(&web3, "eth_getTransactionByHash", H256::zero())
+            .immediate()
+            .unwrap();
+        assert_eq!(result, None);
+        transport.assert_request("eth_getTransactionByHash", &[json!(H256::zero())]);
+
+        transport.add_response(json!(true));
+        let result: bool = call(&web3, "custom_method", (1, "two", [3]))
+            .immediate()
+            .unwrap();
+        assert!(result);
+        transport.assert_request("custom_method", &[json!(1), json!("two"), json!([3])]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn call_with_invalid_result() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!("not a number"));
+        let result = call::<_, _, U64>(&web3, "eth_blockNumber", ()).immediate();
+        assert!(matches!(result, Err(Web3Error::Decoder(_))));
+    }
+}
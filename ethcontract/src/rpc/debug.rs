@@ -0,0 +1,307 @@
+//! Typed wrappers for the `debug_*` tracing namespace implemented by Geth and
+//! compatible nodes.
+
+use crate::rpc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{Address, BlockNumber, Bytes, CallRequest, H256, U256};
+use web3::Transport;
+
+/// Options for tracing transactions with `debug_traceTransaction` and
+/// `debug_traceCall`. Defaults to the struct logger.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    /// The tracer to use, for example `callTracer` or `prestateTracer`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracer: Option<String>,
+    /// The configuration of the tracer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracer_config: Option<Value>,
+    /// The timeout for tracing, for example `10s`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Disable storage capture for the struct logger.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_storage: Option<bool>,
+    /// Disable stack capture for the struct logger.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_stack: Option<bool>,
+    /// Enable memory capture for the struct logger.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_memory: Option<bool>,
+}
+
+impl TraceOptions {
+    /// Creates trace options for the built-in `callTracer`, which returns a
+    /// `CallFrame`.
+    pub fn call_tracer() -> Self {
+        TraceOptions {
+            tracer: Some("callTracer".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Creates trace options for the built-in `prestateTracer`, which returns
+    /// the state of the accounts touched by a transaction before it executed.
+    pub fn prestate_tracer() -> Self {
+        TraceOptions {
+            tracer: Some("prestateTracer".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Specify the tracer configuration.
+    pub fn tracer_config(mut self, value: Value) -> Self {
+        self.tracer_config = Some(value);
+        self
+    }
+
+    /// Specify the timeout for tracing.
+    pub fn timeout(mut self, value: impl Into<String>) -> Self {
+        self.timeout = Some(value.into());
+        self
+    }
+}
+
+/// A call frame returned by the `callTracer`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// The type of call, for example `CALL`, `DELEGATECALL` or `CREATE`.
+    #[serde(rename = "type")]
+    pub call_type: String,
+    /// The caller.
+    pub from: Address,
+    /// The callee, or the created contract address.
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// The ETH value transferred with the call.
+    #[serde(default)]
+    pub value: Option<U256>,
+    /// The gas provided for the call.
+    pub gas: U256,
+    /// The gas used by the call.
+    pub gas_used: U256,
+    /// The call data.
+    pub input: Bytes,
+    /// The return data of the call.
+    #[serde(default)]
+    pub output: Option<Bytes>,
+    /// The error if the call failed.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The decoded revert reason if the call reverted with a reason.
+    #[serde(default)]
+    pub revert_reason: Option<String>,
+    /// The nested calls made during the call.
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+}
+
+/// The trace returned by the default struct logger.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    /// The gas used by the transaction.
+    pub gas: u64,
+    /// Whether or not the transaction failed.
+    pub failed: bool,
+    /// The hex encoded return data of the transaction.
+    pub return_value: String,
+    /// The executed opcodes.
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// An executed opcode in a struct logger trace.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    /// The program counter.
+    pub pc: u64,
+    /// The opcode name.
+    pub op: String,
+    /// The remaining gas.
+    pub gas: u64,
+    /// The gas cost of the opcode.
+    pub gas_cost: u64,
+    /// The call depth.
+    pub depth: u64,
+    /// The error of the opcode, if any.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The stack, unless stack capture was disabled.
+    #[serde(default)]
+    pub stack: Option<Vec<String>>,
+    /// The memory, if memory capture was enabled.
+    #[serde(default)]
+    pub memory: Option<Vec<String>>,
+    /// The storage, unless storage capture was disabled.
+    #[serde(default)]
+    pub storage: Option<BTreeMap<String, String>>,
+}
+
+/// Traces a transaction with `debug_traceTransaction`. The result type
+/// depends on the tracer, `StructLogTrace` for the default struct logger and
+/// `CallFrame` for the `callTracer`.
+pub async fn debug_trace_transaction<T, R>(
+    web3: &Web3<T>,
+    hash: H256,
+    options: &TraceOptions,
+) -> Result<R, Web3Error>
+where
+    T: Transport,
+    R: DeserializeOwned,
+{
+    rpc::call(web3, "debug_traceTransaction", (hash, options)).await
+}
+
+/// The trace of a transaction in a block traced with
+/// `debug_traceBlockByNumber`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionTrace<R> {
+    /// The hash of the traced transaction. Older nodes do not include it.
+    #[serde(default)]
+    pub tx_hash: Option<H256>,
+    /// The trace of the transaction, unless tracing it failed.
+    #[serde(default = "Option::default")]
+    pub result: Option<R>,
+    /// The error if tracing the transaction failed.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Traces all transactions in a block with `debug_traceBlockByNumber`. The
+/// result type depends on the tracer, see [`debug_trace_transaction`].
+pub async fn debug_trace_block_by_number<T, R>(
+    web3: &Web3<T>,
+    block: BlockNumber,
+    options: &TraceOptions,
+) -> Result<Vec<TransactionTrace<R>>, Web3Error>
+where
+    T: Transport,
+    R: DeserializeOwned,
+{
+    rpc::call(web3, "debug_traceBlockByNumber", (block, options)).await
+}
+
+/// Traces a call on top of the state of a block with `debug_traceCall`. The
+/// result type depends on the tracer, see [`debug_trace_transaction`].
+pub async fn debug_trace_call<T, R>(
+    web3: &Web3<T>,
+    request: CallRequest,
+    block: BlockNumber,
+    options: &TraceOptions,
+) -> Result<R, Web3Error>
+where
+    T: Transport,
+    R: DeserializeOwned,
+{
+    rpc::call(web3, "debug_traceCall", (request, block, options)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    #[test]
+    fn trace_transaction_with_call_tracer() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let hash = H256::repeat_byte(1);
+
+        transport.add_response(json!({
+            "type": "CALL",
+            "from": Address::repeat_byte(2),
+            "to": Address::repeat_byte(3),
+            "value": "0x0",
+            "gas": "0x1337",
+            "gasUsed": "0x42",
+            "input": "0x01020304",
+            "output": "0x",
+            "calls": [{
+                "type": "STATICCALL",
+                "from": Address::repeat_byte(3),
+                "to": Address::repeat_byte(4),
+                "gas": "0x100",
+                "gasUsed": "0x10",
+                "input": "0x",
+                "error": "execution reverted",
+            }],
+        }));
+        let frame: CallFrame = debug_trace_transaction(
+            &web3,
+            hash,
+            &TraceOptions::call_tracer().tracer_config(json!({ "onlyTopCall": false })),
+        )
+        .immediate()
+        .unwrap();
+
+        assert_eq!(frame.call_type, "CALL");
+        assert_eq!(frame.gas_used, 0x42.into());
+        assert_eq!(frame.calls.len(), 1);
+        assert_eq!(frame.calls[0].to, Some(Address::repeat_byte(4)));
+        assert_eq!(frame.calls[0].error.as_deref(), Some("execution reverted"));
+        transport.assert_request(
+            "debug_traceTransaction",
+            &[
+                json!(hash),
+                json!({
+                    "tracer": "callTracer",
+                    "tracerConfig": { "onlyTopCall": false },
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn trace_call_with_struct_logger() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!({
+            "gas": 21000,
+            "failed": false,
+            "returnValue": "",
+            "structLogs": [{
+                "pc": 0,
+                "op": "STOP",
+                "gas": 0,
+                "gasCost": 0,
+                "depth": 1,
+                "stack": [],
+            }],
+        }));
+        let trace: StructLogTrace = debug_trace_call(
+            &web3,
+            CallRequest {
+                to: Some(Address::repeat_byte(1)),
+                ..Default::default()
+            },
+            BlockNumber::Latest,
+            &TraceOptions::default(),
+        )
+        .immediate()
+        .unwrap();
+
+        assert_eq!(trace.gas, 21000);
+        assert_eq!(trace.struct_logs[0].op, "STOP");
+        assert_eq!(trace.struct_logs[0].storage, None);
+        transport.assert_request(
+            "debug_traceCall",
+            &[
+                json!({ "to": Address::repeat_byte(1) }),
+                json!("latest"),
+                json!({}),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+}
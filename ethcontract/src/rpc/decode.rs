@@ -0,0 +1,378 @@
+//! Decoding of `callTracer` call frames with contract ABIs into annotated
+//! call trees, for example for post-mortems of failed transactions.
+
+use crate::abi;
+use crate::contract::Instance;
+use crate::errors::revert;
+use crate::int::I256;
+use crate::rpc::CallFrame;
+use ethcontract_common::abi::{Param, Token};
+use ethcontract_common::abiext::FunctionExt;
+use ethcontract_common::Abi;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use web3::types::{Address, Bytes, U256};
+use web3::Transport;
+
+/// A decoder for `callTracer` call frames that annotates each frame with the
+/// decoded contract method call.
+///
+/// Contract ABIs are registered either for a specific address, or for any
+/// address in which case methods are matched by their selector.
+#[derive(Clone, Debug, Default)]
+pub struct CallTraceDecoder {
+    contracts: HashMap<Address, (String, Abi)>,
+    abis: Vec<(String, Abi)>,
+}
+
+impl CallTraceDecoder {
+    /// Creates a new decoder without any contract ABIs.
+    pub fn new() -> Self {
+        CallTraceDecoder::default()
+    }
+
+    /// Registers the ABI of a named contract at the specified address.
+    pub fn contract(mut self, name: impl Into<String>, address: Address, abi: Abi) -> Self {
+        self.contracts.insert(address, (name.into(), abi));
+        self
+    }
+
+    /// Registers the ABI and address of a contract instance, for example the
+    /// `raw_instance()` of generated contract bindings.
+    pub fn instance<T: Transport>(self, name: impl Into<String>, instance: &Instance<T>) -> Self {
+        self.contract(name, instance.address(), instance.abi().clone())
+    }
+
+    /// Registers the ABI of a named contract that is used for decoding calls
+    /// to any address that is not registered with `contract`, for example
+    /// for contracts that are deployed by the traced transaction.
+    pub fn abi(mut self, name: impl Into<String>, abi: Abi) -> Self {
+        self.abis.push((name.into(), abi));
+        self
+    }
+
+    /// Decodes a call frame and all of its nested calls.
+    pub fn decode(&self, frame: &CallFrame) -> DecodedCallFrame {
+        let method = frame
+            .to
+            .filter(|_| !frame.call_type.starts_with("CREATE"))
+            .and_then(|to| {
+                self.decode_method(
+                    to,
+                    &frame.input.0,
+                    frame.output.as_ref().map(|output| &output.0[..]),
+                    frame.error.is_some(),
+                )
+            });
+
+        DecodedCallFrame {
+            call_type: frame.call_type.clone(),
+            from: frame.from,
+            to: frame.to,
+            value: frame.value,
+            gas_used: frame.gas_used,
+            input: frame.input.clone(),
+            output: frame.output.clone(),
+            method,
+            error: frame.error.clone(),
+            revert_reason: frame.revert_reason.clone().or_else(|| {
+                frame
+                    .error
+                    .as_ref()
+                    .and(frame.output.as_ref())
+                    .and_then(|output| revert::decode_reason(&output.0))
+            }),
+            calls: frame.calls.iter().map(|call| self.decode(call)).collect(),
+        }
+    }
+
+    /// Decodes a method call to the specified address with the ABI registered
+    /// for it. Return data is only decoded for successful calls.
+    pub(crate) fn decode_method(
+        &self,
+        to: Address,
+        input: &[u8],
+        output: Option<&[u8]>,
+        failed: bool,
+    ) -> Option<DecodedMethod> {
+        if input.len() < 4 {
+            return None;
+        }
+
+        let candidates = match self.contracts.get(&to) {
+            Some(contract) => std::slice::from_ref(contract),
+            None => &self.abis[..],
+        };
+        candidates.iter().find_map(|(name, abi)| {
+            let function = abi
+                .functions()
+                .find(|function| function.selector() == input[..4])?;
+            let inputs = abi::decode_inputs(function, &input[4..]).ok()?;
+            let outputs = output
+                .filter(|_| !failed)
+                .and_then(|output| abi::decode_outputs(function, output).ok());
+
+            Some(DecodedMethod {
+                contract: name.clone(),
+                name: function.name.clone(),
+                signature: function.abi_signature(),
+                inputs: named(&function.inputs, inputs),
+                outputs: outputs.map(|outputs| named(&function.outputs, outputs)),
+            })
+        })
+    }
+}
+
+fn named(params: &[Param], tokens: Vec<Token>) -> Vec<(String, Token)> {
+    params
+        .iter()
+        .map(|param| param.name.clone())
+        .zip(tokens)
+        .collect()
+}
+
+/// A call frame annotated with its decoded contract method call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedCallFrame {
+    /// The type of call, for example `CALL`, `DELEGATECALL` or `CREATE`.
+    pub call_type: String,
+    /// The caller.
+    pub from: Address,
+    /// The callee, or the created contract address.
+    pub to: Option<Address>,
+    /// The ETH value transferred with the call.
+    pub value: Option<U256>,
+    /// The gas used by the call.
+    pub gas_used: U256,
+    /// The call data.
+    pub input: Bytes,
+    /// The return data of the call.
+    pub output: Option<Bytes>,
+    /// The decoded method call, if the callee's ABI is known.
+    pub method: Option<DecodedMethod>,
+    /// The error if the call failed.
+    pub error: Option<String>,
+    /// The decoded revert reason if the call reverted with a reason.
+    pub revert_reason: Option<String>,
+    /// The decoded nested calls made during the call.
+    pub calls: Vec<DecodedCallFrame>,
+}
+
+/// A decoded contract method call in a call frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedMethod {
+    /// The name of the called contract.
+    pub contract: String,
+    /// The name of the method.
+    pub name: String,
+    /// The signature of the method, for example `transfer(address,uint256)`.
+    pub signature: String,
+    /// The names and values of the method parameters.
+    pub inputs: Vec<(String, Token)>,
+    /// The names and values of the method return values. This is `None` if
+    /// the call failed or its return data could not be decoded.
+    pub outputs: Option<Vec<(String, Token)>>,
+}
+
+impl DecodedCallFrame {
+    fn fmt_indented(&self, f: &mut Formatter, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}[{}] ", "", self.call_type, indent = depth * 2)?;
+        match (&self.method, self.to) {
+            (Some(method), _) => {
+                write!(f, "{}.{}(", method.contract, method.name)?;
+                fmt_params(f, &method.inputs)?;
+                write!(f, ")")?;
+                if let Some(outputs) = &method.outputs {
+                    write!(f, " -> (")?;
+                    fmt_params(f, outputs)?;
+                    write!(f, ")")?;
+                }
+            }
+            (None, Some(to)) => write!(f, "{:?}", to)?,
+            (None, None) => write!(f, "<unknown>")?,
+        }
+        if let Some(value) = self.value.filter(|value| !value.is_zero()) {
+            write!(f, " value: {}", value)?;
+        }
+        write!(f, " gas: {}", self.gas_used)?;
+        match (&self.revert_reason, &self.error) {
+            (Some(reason), _) => write!(f, " reverted: {:?}", reason)?,
+            (None, Some(error)) => write!(f, " error: {}", error)?,
+            (None, None) => {}
+        }
+        writeln!(f)?;
+
+        for call in &self.calls {
+            call.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_params(f: &mut Formatter, params: &[(String, Token)]) -> fmt::Result {
+    for (i, (name, value)) in params.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        if !name.is_empty() {
+            write!(f, "{}: ", name)?;
+        }
+        fmt_token(f, value)?;
+    }
+    Ok(())
+}
+
+fn fmt_tokens(f: &mut Formatter, tokens: &[Token]) -> fmt::Result {
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        fmt_token(f, token)?;
+    }
+    Ok(())
+}
+
+fn fmt_token(f: &mut Formatter, token: &Token) -> fmt::Result {
+    match token {
+        Token::Address(address) => write!(f, "{:?}", address),
+        Token::Uint(value) => write!(f, "{}", value),
+        Token::Int(value) => write!(f, "{}", I256::from_raw(*value)),
+        Token::Bool(value) => write!(f, "{}", value),
+        Token::String(value) => write!(f, "{:?}", value),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => write!(f, "0x{}", hex::encode(bytes)),
+        Token::Array(tokens) | Token::FixedArray(tokens) => {
+            write!(f, "[")?;
+            fmt_tokens(f, tokens)?;
+            write!(f, "]")
+        }
+        Token::Tuple(tokens) => {
+            write!(f, "(")?;
+            fmt_tokens(f, tokens)?;
+            write!(f, ")")
+        }
+    }
+}
+
+/// A wrapper for displaying a token in a human readable format, with decimal
+/// integers and hex encoded addresses and bytes.
+pub(crate) struct DisplayToken<'a>(pub &'a Token);
+
+impl Display for DisplayToken<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_token(f, self.0)
+    }
+}
+
+/// Formats the call tree with one indented line per call frame.
+impl Display for DecodedCallFrame {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract_common::abi::encode;
+
+    const TOKEN_ABI: &str = r#"[{
+        "type": "function",
+        "name": "transfer",
+        "inputs": [
+            { "name": "to", "type": "address" },
+            { "name": "amount", "type": "uint256" }
+        ],
+        "outputs": [{ "name": "", "type": "bool" }],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    fn frame(
+        to: Address,
+        input: Vec<u8>,
+        output: Option<Vec<u8>>,
+        error: Option<&str>,
+    ) -> CallFrame {
+        CallFrame {
+            call_type: "CALL".to_owned(),
+            from: Address::repeat_byte(1),
+            to: Some(to),
+            value: None,
+            gas: 0x1000.into(),
+            gas_used: 0x100.into(),
+            input: Bytes(input),
+            output: output.map(Bytes),
+            error: error.map(str::to_owned),
+            revert_reason: None,
+            calls: Vec::new(),
+        }
+    }
+
+    fn transfer_call(to: Address, amount: u64) -> Vec<u8> {
+        let mut input = hex::decode("a9059cbb").unwrap();
+        input.extend(encode(&[Token::Address(to), Token::Uint(amount.into())]));
+        input
+    }
+
+    #[test]
+    fn decode_call_tree() {
+        let abi = Abi::load(TOKEN_ABI.as_bytes()).unwrap();
+        let (token, proxy, recipient) = (
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+            Address::repeat_byte(4),
+        );
+
+        let mut root = frame(
+            token,
+            transfer_call(recipient, 100),
+            Some(encode(&[Token::Bool(true)])),
+            None,
+        );
+        root.calls.push(frame(
+            proxy,
+            transfer_call(recipient, 1),
+            Some(revert::encode_reason("no balance")),
+            Some("execution reverted"),
+        ));
+        root.calls
+            .push(frame(Address::repeat_byte(5), vec![1, 2], None, None));
+
+        let decoded = CallTraceDecoder::new()
+            .contract("Token", token, abi.clone())
+            .abi("Proxy", abi)
+            .decode(&root);
+
+        let method = decoded.method.as_ref().unwrap();
+        assert_eq!(method.signature, "transfer(address,uint256)");
+        assert_eq!(
+            method.inputs,
+            vec![
+                ("to".to_owned(), Token::Address(recipient)),
+                ("amount".to_owned(), Token::Uint(100.into())),
+            ],
+        );
+        assert_eq!(
+            method.outputs,
+            Some(vec![(String::new(), Token::Bool(true))])
+        );
+        assert_eq!(decoded.calls[0].method.as_ref().unwrap().contract, "Proxy");
+        assert_eq!(decoded.calls[0].method.as_ref().unwrap().outputs, None);
+        assert_eq!(
+            decoded.calls[0].revert_reason.as_deref(),
+            Some("no balance")
+        );
+        assert_eq!(decoded.calls[1].method, None);
+
+        assert_eq!(
+            decoded.to_string(),
+            format!(
+                "[CALL] Token.transfer(to: {:?}, amount: 100) -> (true) gas: 256\n  \
+                 [CALL] Proxy.transfer(to: {:?}, amount: 1) gas: 256 reverted: \"no balance\"\n  \
+                 [CALL] {:?} gas: 256\n",
+                recipient,
+                recipient,
+                Address::repeat_byte(5),
+            ),
+        );
+    }
+}
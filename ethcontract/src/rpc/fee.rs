@@ -0,0 +1,104 @@
+//! Typed wrapper for the EIP-1559 `eth_feeHistory` method.
+
+use crate::rpc;
+use serde::Deserialize;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{BlockNumber, U256, U64};
+use web3::Transport;
+
+/// The fee history of a range of blocks returned by `eth_feeHistory`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// The number of the oldest block in the range.
+    pub oldest_block: U64,
+    /// The base fees per gas of the blocks in the range, followed by the base
+    /// fee per gas of the block after the newest block in the range.
+    #[serde(default)]
+    pub base_fee_per_gas: Vec<U256>,
+    /// The ratios of gas used to the gas limit of the blocks in the range.
+    #[serde(default)]
+    pub gas_used_ratio: Vec<f64>,
+    /// The priority fees per gas at the requested percentiles of the
+    /// transactions in each block in the range, weighted by gas used.
+    #[serde(default)]
+    pub reward: Vec<Vec<U256>>,
+}
+
+impl FeeHistory {
+    /// Returns the base fee per gas of the block after the newest block in the
+    /// range, which is known ahead of time from the gas used by the newest
+    /// block.
+    pub fn next_base_fee_per_gas(&self) -> Option<U256> {
+        self.base_fee_per_gas.last().copied()
+    }
+
+    /// Returns the median over the blocks in the range of the priority fees
+    /// per gas at the requested percentile with the specified index.
+    pub fn median_reward(&self, percentile_index: usize) -> Option<U256> {
+        let mut rewards = self
+            .reward
+            .iter()
+            .filter_map(|rewards| rewards.get(percentile_index).copied())
+            .collect::<Vec<_>>();
+        if rewards.is_empty() {
+            return None;
+        }
+
+        rewards.sort_unstable();
+        Some(rewards[rewards.len() / 2])
+    }
+}
+
+/// Returns the fee history of the specified number of blocks up to the latest
+/// block with `eth_feeHistory`, including the priority fees per gas paid at
+/// the specified percentiles of each block.
+pub async fn fee_history<T: Transport>(
+    web3: &Web3<T>,
+    block_count: u64,
+    reward_percentiles: &[f64],
+) -> Result<FeeHistory, Web3Error> {
+    rpc::call(
+        web3,
+        "eth_feeHistory",
+        (
+            U256::from(block_count),
+            BlockNumber::Latest,
+            reward_percentiles,
+        ),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    #[test]
+    fn retrieve_fee_history() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!({
+            "oldestBlock": "0x10",
+            "baseFeePerGas": ["0x64", "0x6e", "0x78", "0x82"],
+            "gasUsedRatio": [0.5, 0.9, 0.7],
+            "reward": [["0x1", "0x5"], ["0x3", "0x9"], ["0x2", "0x7"]],
+        }));
+        let history = fee_history(&web3, 3, &[10.0, 90.0]).immediate().unwrap();
+
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0x3"), json!("latest"), json!([10.0, 90.0])],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(history.oldest_block, 0x10.into());
+        assert_eq!(history.next_base_fee_per_gas(), Some(0x82.into()));
+        assert_eq!(history.median_reward(0), Some(2.into()));
+        assert_eq!(history.median_reward(1), Some(7.into()));
+        assert_eq!(history.median_reward(2), None);
+    }
+}
@@ -0,0 +1,682 @@
+//! Block and log retrieval over the standardized EIP-1767 GraphQL API,
+//! falling back to JSON-RPC for nodes that do not expose it.
+
+use crate::errors::{ExecutionError, GraphQlError};
+use futures::future::{self, BoxFuture};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use web3::api::Web3;
+use web3::types::{Block, BlockId, BlockNumber, Filter, Log, H256};
+use web3::Transport;
+
+/// The fields of a GraphQL block that are needed for a JSON-RPC block.
+const BLOCK_FIELDS: &str = "\
+    number hash parent { hash } nonce transactionsRoot stateRoot receiptsRoot \
+    miner { address } extraData gasLimit gasUsed baseFeePerGas timestamp \
+    logsBloom mixHash difficulty totalDifficulty ommerHash ommers { hash } \
+    transactions { hash }";
+
+/// The query for the logs matching a filter.
+const LOGS_QUERY: &str = "\
+    query($filter: FilterCriteria!) { logs(filter: $filter) { \
+        index account { address } topics data \
+        transaction { hash index block { number hash } } \
+    } }";
+
+/// A client for executing queries against the GraphQL API of a node.
+pub trait GraphQlClient: Debug + Send + Sync {
+    /// Executes a query with the specified variables and returns the `data`
+    /// of the response. Clients should return [`GraphQlError::Unsupported`]
+    /// if the node does not expose a GraphQL API.
+    fn query<'a>(
+        &'a self,
+        query: &'a str,
+        variables: Value,
+    ) -> BoxFuture<'a, Result<Value, GraphQlError>>;
+}
+
+/// A fetcher for blocks and logs that uses the GraphQL API of a node when
+/// available, which retrieves ranges of blocks and logs together with their
+/// transaction and block data with a single query.
+///
+/// Queries are made over JSON-RPC if no GraphQL client is configured, if the
+/// GraphQL query fails or if the query can't be expressed with GraphQL, such
+/// as for pending blocks. Nodes that do not support GraphQL are detected on
+/// the first query, after which only JSON-RPC is used. Fetchers are cheap to
+/// clone and clones share the detected support.
+#[derive(Clone, Debug)]
+pub struct GraphQlFetcher<T: Transport> {
+    web3: Web3<T>,
+    client: Option<Arc<dyn GraphQlClient>>,
+    graphql: Arc<AtomicBool>,
+}
+
+impl<T: Transport> GraphQlFetcher<T> {
+    /// Creates a new fetcher for the specified web3 provider, which only uses
+    /// JSON-RPC until a GraphQL client is set.
+    pub fn new(web3: Web3<T>) -> Self {
+        GraphQlFetcher {
+            web3,
+            client: None,
+            graphql: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Sets the client for the GraphQL API of the node.
+    pub fn client(mut self, value: Arc<dyn GraphQlClient>) -> Self {
+        self.client = Some(value);
+        self
+    }
+
+    /// Returns `true` if a GraphQL client is set and the node was not
+    /// detected not to support GraphQL.
+    pub fn supports_graphql(&self) -> bool {
+        self.client.is_some() && self.graphql.load(Ordering::Relaxed)
+    }
+
+    /// Returns a block with the hashes of its transactions, or `None` if the
+    /// block does not exist.
+    pub async fn block(&self, block: BlockId) -> Result<Option<Block<H256>>, ExecutionError> {
+        let variables = match block {
+            BlockId::Hash(hash) => Some(json!({ "hash": hash })),
+            BlockId::Number(BlockNumber::Number(number)) => {
+                Some(json!({ "number": number.as_u64() }))
+            }
+            BlockId::Number(BlockNumber::Earliest) => Some(json!({ "number": 0 })),
+            BlockId::Number(BlockNumber::Latest) => Some(json!({})),
+            BlockId::Number(BlockNumber::Pending) => None,
+        };
+        if let Some(variables) = variables {
+            let query = format!(
+                "query($number: Long, $hash: Bytes32) {{ \
+                    block(number: $number, hash: $hash) {{ {} }} \
+                }}",
+                BLOCK_FIELDS,
+            );
+            if let Some(data) = self.query(&query, variables).await {
+                match &data["block"] {
+                    Value::Null => return Ok(None),
+                    block => {
+                        if let Ok(block) = serde_json::from_value(rpc_block(block)) {
+                            return Ok(Some(block));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(self.web3.eth().block(block).await?)
+    }
+
+    /// Returns the blocks in the specified range with the hashes of their
+    /// transactions. The blocks end at the latest block if the range extends
+    /// past it.
+    pub async fn blocks(
+        &self,
+        range: RangeInclusive<u64>,
+    ) -> Result<Vec<Block<H256>>, ExecutionError> {
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            "query($from: Long!, $to: Long) {{ blocks(from: $from, to: $to) {{ {} }} }}",
+            BLOCK_FIELDS,
+        );
+        let variables = json!({ "from": range.start(), "to": range.end() });
+        if let Some(data) = self.query(&query, variables).await {
+            if let Some(blocks) = convert_list(&data["blocks"], rpc_block) {
+                return Ok(blocks);
+            }
+        }
+
+        let blocks = future::try_join_all(
+            range.map(|number| self.web3.eth().block(BlockId::Number(number.into()))),
+        )
+        .await?;
+        Ok(blocks
+            .into_iter()
+            .take_while(Option::is_some)
+            .flatten()
+            .collect())
+    }
+
+    /// Returns the logs matching a filter. Filters for the logs of a single
+    /// block hash or with a limit are always queried over JSON-RPC.
+    pub async fn logs(&self, filter: Filter) -> Result<Vec<Log>, ExecutionError> {
+        if let Some(criteria) = filter_criteria(&filter) {
+            if let Some(data) = self.query(LOGS_QUERY, json!({ "filter": criteria })).await {
+                if let Some(logs) = convert_list(&data["logs"], rpc_log) {
+                    return Ok(logs);
+                }
+            }
+        }
+
+        Ok(self.web3.eth().logs(filter).await?)
+    }
+
+    /// Executes a GraphQL query, returning `None` if the query should be made
+    /// over JSON-RPC instead.
+    async fn query(&self, query: &str, variables: Value) -> Option<Value> {
+        if !self.supports_graphql() {
+            return None;
+        }
+        match self.client.as_ref()?.query(query, variables).await {
+            Ok(data) => Some(data),
+            Err(GraphQlError::Unsupported) => {
+                self.graphql.store(false, Ordering::Relaxed);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Converts a list of GraphQL objects into JSON-RPC objects and deserializes
+/// them, returning `None` if any of them is invalid.
+fn convert_list<T: DeserializeOwned>(list: &Value, convert: fn(&Value) -> Value) -> Option<Vec<T>> {
+    list.as_array()?
+        .iter()
+        .map(|item| serde_json::from_value(convert(item)).ok())
+        .collect()
+}
+
+/// Converts a GraphQL `Long` or `Int`, which is either a number or a hex
+/// string depending on the node, into a JSON-RPC quantity.
+fn quantity(value: &Value) -> Value {
+    match value.as_u64() {
+        Some(value) => json!(format!("{:#x}", value)),
+        None => value.clone(),
+    }
+}
+
+/// Converts a GraphQL block into a JSON-RPC block.
+fn rpc_block(block: &Value) -> Value {
+    let hashes = |field: &str| match block[field].as_array() {
+        Some(items) => items.iter().map(|item| item["hash"].clone()).collect(),
+        None => Vec::new(),
+    };
+    let parent_hash = match &block["parent"]["hash"] {
+        Value::Null => json!(H256::zero()),
+        hash => hash.clone(),
+    };
+
+    json!({
+        "hash": block["hash"],
+        "parentHash": parent_hash,
+        "sha3Uncles": block["ommerHash"],
+        "miner": block["miner"]["address"],
+        "stateRoot": block["stateRoot"],
+        "transactionsRoot": block["transactionsRoot"],
+        "receiptsRoot": block["receiptsRoot"],
+        "number": quantity(&block["number"]),
+        "gasUsed": quantity(&block["gasUsed"]),
+        "gasLimit": quantity(&block["gasLimit"]),
+        "baseFeePerGas": quantity(&block["baseFeePerGas"]),
+        "extraData": block["extraData"],
+        "logsBloom": block["logsBloom"],
+        "timestamp": quantity(&block["timestamp"]),
+        "difficulty": quantity(&block["difficulty"]),
+        "totalDifficulty": quantity(&block["totalDifficulty"]),
+        "uncles": hashes("ommers"),
+        "transactions": hashes("transactions"),
+        "mixHash": block["mixHash"],
+        "nonce": block["nonce"],
+    })
+}
+
+/// Converts a GraphQL log into a JSON-RPC log.
+fn rpc_log(log: &Value) -> Value {
+    let transaction = &log["transaction"];
+    json!({
+        "address": log["account"]["address"],
+        "topics": log["topics"],
+        "data": log["data"],
+        "blockHash": transaction["block"]["hash"],
+        "blockNumber": quantity(&transaction["block"]["number"]),
+        "transactionHash": transaction["hash"],
+        "transactionIndex": quantity(&transaction["index"]),
+        "logIndex": quantity(&log["index"]),
+        "removed": false,
+    })
+}
+
+/// Converts a JSON-RPC log filter into GraphQL filter criteria, or returns
+/// `None` if the filter can't be expressed with GraphQL.
+fn filter_criteria(filter: &Filter) -> Option<Value> {
+    let filter = serde_json::to_value(filter).ok()?;
+    if !filter["blockHash"].is_null() || !filter["limit"].is_null() {
+        return None;
+    }
+
+    // Single values and lists are serialized the same way for addresses and
+    // topics, but GraphQL always expects lists.
+    let list = |value: &Value| match value {
+        Value::Array(values) => values.clone(),
+        Value::Null => Vec::new(),
+        value => vec![value.clone()],
+    };
+
+    let mut criteria = Map::new();
+    for &key in &["fromBlock", "toBlock"] {
+        match filter[key].as_str() {
+            // GraphQL filters default to the latest block.
+            None | Some("latest") => {}
+            Some("earliest") => {
+                criteria.insert(key.to_owned(), json!(0));
+            }
+            Some(number) => {
+                let number = u64::from_str_radix(number.strip_prefix("0x")?, 16).ok()?;
+                criteria.insert(key.to_owned(), json!(number));
+            }
+        }
+    }
+    if !filter["address"].is_null() {
+        criteria.insert("addresses".to_owned(), list(&filter["address"]).into());
+    }
+    if let Some(topics) = filter["topics"].as_array() {
+        let topics = topics.iter().map(|topic| Value::from(list(topic)));
+        criteria.insert("topics".to_owned(), topics.collect());
+    }
+
+    Some(criteria.into())
+}
+
+#[cfg(feature = "http")]
+pub use self::http::HttpGraphQlClient;
+
+#[cfg(feature = "http")]
+mod http {
+    use super::GraphQlClient;
+    use crate::errors::GraphQlError;
+    use futures::future::{BoxFuture, FutureExt};
+    use reqwest::header::CONTENT_TYPE;
+    use reqwest::StatusCode;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    /// A GraphQL client that queries a node over HTTP. Geth and Besu nodes
+    /// serve GraphQL on the `/graphql` path of their HTTP JSON-RPC endpoint
+    /// when enabled.
+    #[derive(Clone, Debug)]
+    pub struct HttpGraphQlClient {
+        client: reqwest::Client,
+        url: String,
+    }
+
+    impl HttpGraphQlClient {
+        /// Creates a client for the GraphQL endpoint at the specified URL.
+        pub fn new(url: impl Into<String>) -> Self {
+            HttpGraphQlClient {
+                client: reqwest::Client::new(),
+                url: url.into(),
+            }
+        }
+
+        /// Sets the HTTP client used for making requests.
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.client = client;
+            self
+        }
+
+        async fn execute(&self, query: &str, variables: Value) -> Result<Value, GraphQlError> {
+            let body = json!({ "query": query, "variables": variables });
+            let response = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await?;
+
+            // NOTE: Nodes with GraphQL disabled respond with one of these
+            //   statuses for the GraphQL path, or serve JSON-RPC on it.
+            let unsupported = [
+                StatusCode::NOT_FOUND,
+                StatusCode::METHOD_NOT_ALLOWED,
+                StatusCode::NOT_IMPLEMENTED,
+            ];
+            if unsupported.contains(&response.status()) {
+                return Err(GraphQlError::Unsupported);
+            }
+
+            let body = response.error_for_status()?.bytes().await?;
+            response_data(&body)
+        }
+    }
+
+    impl GraphQlClient for HttpGraphQlClient {
+        fn query<'a>(
+            &'a self,
+            query: &'a str,
+            variables: Value,
+        ) -> BoxFuture<'a, Result<Value, GraphQlError>> {
+            self.execute(query, variables).boxed()
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(default)]
+        data: Value,
+        #[serde(default)]
+        errors: Vec<ResponseError>,
+        // JSON-RPC responses, which are returned when the node serves
+        // JSON-RPC on the GraphQL path.
+        #[serde(default)]
+        jsonrpc: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct ResponseError {
+        message: String,
+    }
+
+    /// Returns the data of a GraphQL response.
+    pub(super) fn response_data(body: &[u8]) -> Result<Value, GraphQlError> {
+        let response = serde_json::from_slice::<Response>(body)
+            .map_err(|err| GraphQlError::InvalidResponse(err.to_string()))?;
+        if response.jsonrpc.is_some() {
+            return Err(GraphQlError::Unsupported);
+        }
+        if !response.errors.is_empty() {
+            let messages = response
+                .errors
+                .into_iter()
+                .map(|error| error.message)
+                .collect::<Vec<_>>();
+            return Err(GraphQlError::Query(messages.join("; ")));
+        }
+        Ok(response.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use web3::types::{Address, FilterBuilder, H2048, U256, U64};
+
+    /// A GraphQL client returning queued responses, with `None` responses
+    /// indicating that GraphQL is unsupported.
+    #[derive(Debug, Default)]
+    struct TestClient {
+        requests: Mutex<Vec<(String, Value)>>,
+        responses: Mutex<VecDeque<Option<Value>>>,
+    }
+
+    impl TestClient {
+        fn new(responses: Vec<Option<Value>>) -> Arc<Self> {
+            Arc::new(TestClient {
+                requests: Default::default(),
+                responses: Mutex::new(responses.into()),
+            })
+        }
+
+        fn variables(&self) -> Vec<Value> {
+            let requests = self.requests.lock().unwrap();
+            requests
+                .iter()
+                .map(|(_, variables)| variables.clone())
+                .collect()
+        }
+    }
+
+    impl GraphQlClient for TestClient {
+        fn query<'a>(
+            &'a self,
+            query: &'a str,
+            variables: Value,
+        ) -> BoxFuture<'a, Result<Value, GraphQlError>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((query.to_owned(), variables));
+            let response = self.responses.lock().unwrap().pop_front().unwrap();
+            Box::pin(async { response.ok_or(GraphQlError::Unsupported) })
+        }
+    }
+
+    fn graphql_block(number: u8) -> Value {
+        json!({
+            "number": number,
+            "hash": H256::repeat_byte(number),
+            "parent": { "hash": H256::repeat_byte(number - 1) },
+            "nonce": "0x0000000000000000",
+            "transactionsRoot": H256::zero(),
+            "stateRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "miner": { "address": Address::repeat_byte(1) },
+            "extraData": "0x",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": 21000,
+            "baseFeePerGas": "0x7",
+            "timestamp": "0x5f5e100",
+            "logsBloom": H2048::zero(),
+            "mixHash": H256::zero(),
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "ommerHash": H256::zero(),
+            "ommers": [],
+            "transactions": [{ "hash": H256::repeat_byte(0x11) }],
+        })
+    }
+
+    fn json_rpc_block(number: u8) -> Value {
+        json!({
+            "hash": H256::repeat_byte(number),
+            "parentHash": H256::zero(),
+            "sha3Uncles": H256::zero(),
+            "miner": Address::zero(),
+            "stateRoot": H256::zero(),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": U64::from(number),
+            "gasUsed": "0x0",
+            "gasLimit": "0x0",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x0",
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "sealFields": [],
+            "uncles": [],
+            "transactions": [],
+            "size": "0x0",
+            "mixHash": H256::zero(),
+            "nonce": "0x0000000000000000",
+        })
+    }
+
+    #[test]
+    fn fetches_blocks_with_graphql() {
+        let transport = TestTransport::new();
+        let client = TestClient::new(vec![
+            Some(json!({ "block": graphql_block(1) })),
+            Some(json!({ "blocks": [graphql_block(1), graphql_block(2)] })),
+            Some(json!({ "block": null })),
+        ]);
+        let fetcher = GraphQlFetcher::new(Web3::new(transport.clone())).client(client.clone());
+
+        let block = fetcher
+            .block(BlockId::Number(1.into()))
+            .immediate()
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.number, Some(1.into()));
+        assert_eq!(block.hash, Some(H256::repeat_byte(1)));
+        assert_eq!(block.parent_hash, H256::repeat_byte(0));
+        assert_eq!(block.author, Address::repeat_byte(1));
+        assert_eq!(block.gas_limit, U256::from(30_000_000));
+        assert_eq!(block.gas_used, U256::from(21000));
+        assert_eq!(block.base_fee_per_gas, Some(7.into()));
+        assert_eq!(block.transactions, vec![H256::repeat_byte(0x11)]);
+
+        let blocks = fetcher.blocks(1..=2).immediate().unwrap();
+        assert_eq!(
+            blocks.iter().map(|block| block.number).collect::<Vec<_>>(),
+            vec![Some(1.into()), Some(2.into())],
+        );
+
+        let block = fetcher
+            .block(BlockId::Hash(H256::repeat_byte(3)))
+            .immediate()
+            .unwrap();
+        assert_eq!(block, None);
+
+        assert_eq!(
+            client.variables(),
+            vec![
+                json!({ "number": 1 }),
+                json!({ "from": 1, "to": 2 }),
+                json!({ "hash": H256::repeat_byte(3) }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn fetches_logs_with_graphql() {
+        let transport = TestTransport::new();
+        let (address, topic) = (Address::repeat_byte(0x42), H256::repeat_byte(0x43));
+        let client = TestClient::new(vec![Some(json!({
+            "logs": [{
+                "index": 3,
+                "account": { "address": address },
+                "topics": [topic],
+                "data": "0x01",
+                "transaction": {
+                    "hash": H256::repeat_byte(0x11),
+                    "index": 2,
+                    "block": { "number": "0x5", "hash": H256::repeat_byte(5) },
+                },
+            }],
+        }))]);
+        let fetcher = GraphQlFetcher::new(Web3::new(transport.clone())).client(client.clone());
+
+        let filter = FilterBuilder::default()
+            .from_block(1.into())
+            .to_block(BlockNumber::Latest)
+            .address(vec![address])
+            .topics(None, Some(vec![topic]), None, None)
+            .build();
+        let logs = fetcher.logs(filter).immediate().unwrap();
+
+        assert_eq!(
+            client.variables(),
+            vec![json!({
+                "filter": {
+                    "fromBlock": 1,
+                    "addresses": [address],
+                    "topics": [[], [topic]],
+                },
+            })],
+        );
+        assert_eq!(logs.len(), 1);
+        let log = &logs[0];
+        assert_eq!(log.address, address);
+        assert_eq!(log.topics, vec![topic]);
+        assert_eq!(log.data.0, vec![1]);
+        assert_eq!(log.block_hash, Some(H256::repeat_byte(5)));
+        assert_eq!(log.block_number, Some(5.into()));
+        assert_eq!(log.transaction_hash, Some(H256::repeat_byte(0x11)));
+        assert_eq!(log.transaction_index, Some(2.into()));
+        assert_eq!(log.log_index, Some(3.into()));
+        assert_eq!(log.removed, Some(false));
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn falls_back_to_json_rpc() {
+        let mut transport = TestTransport::new();
+        let client = TestClient::new(vec![None]);
+        let fetcher = GraphQlFetcher::new(Web3::new(transport.clone())).client(client.clone());
+        assert!(fetcher.supports_graphql());
+
+        transport.add_response(json_rpc_block(1));
+        let block = fetcher
+            .block(BlockId::Number(1.into()))
+            .immediate()
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.number, Some(1.into()));
+        assert!(!fetcher.supports_graphql());
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x1"), json!(false)]);
+
+        // Once unsupported, queries are only made over JSON-RPC.
+        transport.add_response(json!([]));
+        let logs = fetcher
+            .logs(FilterBuilder::default().from_block(1.into()).build())
+            .immediate()
+            .unwrap();
+        assert!(logs.is_empty());
+        transport.assert_request("eth_getLogs", &[json!({ "fromBlock": "0x1" })]);
+        transport.assert_no_more_requests();
+        assert_eq!(client.variables().len(), 1);
+    }
+
+    #[test]
+    fn unsupported_queries_use_json_rpc() {
+        let mut transport = TestTransport::new();
+        let client = TestClient::new(vec![]);
+        let fetcher = GraphQlFetcher::new(Web3::new(transport.clone())).client(client.clone());
+
+        transport.add_response(json_rpc_block(1));
+        fetcher
+            .block(BlockId::Number(BlockNumber::Pending))
+            .immediate()
+            .unwrap();
+        transport.assert_request("eth_getBlockByNumber", &[json!("pending"), json!(false)]);
+
+        let filter = FilterBuilder::default()
+            .block_hash(H256::repeat_byte(1))
+            .build();
+        transport.add_response(json!([]));
+        fetcher.logs(filter).immediate().unwrap();
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "blockHash": H256::repeat_byte(1) })],
+        );
+
+        // Ranges fetched over JSON-RPC end at the latest block.
+        let fetcher = GraphQlFetcher::new(Web3::new(transport.clone()));
+        transport.add_response(json_rpc_block(1));
+        transport.add_response(Value::Null);
+        let blocks = fetcher.blocks(1..=2).immediate().unwrap();
+        assert_eq!(blocks.len(), 1);
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x1"), json!(false)]);
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x2"), json!(false)]);
+        transport.assert_no_more_requests();
+
+        assert!(client.variables().is_empty());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn graphql_responses() {
+        use super::http::response_data;
+
+        assert_eq!(
+            response_data(br#"{ "data": { "block": null } }"#).unwrap(),
+            json!({ "block": null }),
+        );
+        assert!(matches!(
+            response_data(br#"{ "errors": [{ "message": "a" }, { "message": "b" }] }"#),
+            Err(GraphQlError::Query(message)) if message == "a; b"
+        ));
+        assert!(matches!(
+            response_data(br#"{ "jsonrpc": "2.0", "id": 1, "error": {} }"#),
+            Err(GraphQlError::Unsupported)
+        ));
+        assert!(matches!(
+            response_data(b"not json"),
+            Err(GraphQlError::InvalidResponse(_))
+        ));
+    }
+}
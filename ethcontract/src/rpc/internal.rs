@@ -0,0 +1,592 @@
+//! Tracing of internal transactions, that is the ETH transfers and contract
+//! calls made by contracts during the execution of a transaction, over both
+//! the OpenEthereum style `trace_*` and the Geth style `debug_*` namespaces.
+
+use crate::rpc::{self, CallFrame, CallTraceDecoder, DecodedMethod, TraceOptions};
+use std::ops::RangeInclusive;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{
+    Action, Address, BlockNumber, Bytes, CallType, Res, Trace, TraceFilterBuilder, H256, U256,
+};
+use web3::Transport;
+
+/// The tracing namespace used for retrieving internal transactions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceBackend {
+    /// The `trace_block`, `trace_transaction` and `trace_filter` methods
+    /// implemented by OpenEthereum, Nethermind and Erigon.
+    Parity,
+    /// The `debug_traceBlockByNumber` and `debug_traceTransaction` methods
+    /// with the `callTracer`, implemented by Geth and compatible nodes.
+    Geth,
+}
+
+/// An internal transaction, that is a call, contract creation or self
+/// destruct made by a contract during the execution of a transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InternalCall {
+    /// The number of the block containing the transaction.
+    pub block_number: u64,
+    /// The hash of the transaction, if known.
+    pub transaction_hash: Option<H256>,
+    /// The index of the transaction in its block.
+    pub transaction_position: Option<usize>,
+    /// The position of the call in the call tree of the transaction, as the
+    /// indices of the nested calls leading to it. This is never empty, since
+    /// the top-level call is the transaction itself.
+    pub trace_address: Vec<usize>,
+    /// The type of call, for example `CALL`, `DELEGATECALL`, `CREATE` or
+    /// `SELFDESTRUCT`.
+    pub call_type: String,
+    /// The caller, or the destructed contract for self destructs.
+    pub from: Address,
+    /// The callee, the created contract address or the beneficiary of a self
+    /// destruct. This is `None` for contract creations that failed.
+    pub to: Option<Address>,
+    /// The ETH value transferred with the call.
+    pub value: U256,
+    /// The call data, or the init code for contract creations.
+    pub input: Bytes,
+    /// The return data of the call.
+    pub output: Option<Bytes>,
+    /// The error if the call failed.
+    pub error: Option<String>,
+    /// The decoded method call, if the callee's ABI is registered with the
+    /// decoder of the tracer.
+    pub method: Option<DecodedMethod>,
+}
+
+impl InternalCall {
+    /// Returns `true` if the call succeeded and transferred ETH between two
+    /// accounts. Delegate calls and call codes never transfer ETH, since
+    /// they execute in the context of the caller.
+    pub fn is_transfer(&self) -> bool {
+        !self.value.is_zero()
+            && self.error.is_none()
+            && self.call_type != "DELEGATECALL"
+            && self.call_type != "CALLCODE"
+    }
+
+    /// Returns `true` if the call is a contract call with call data, as
+    /// opposed to a plain ETH transfer, contract creation or self destruct.
+    pub fn is_contract_call(&self) -> bool {
+        self.input.0.len() >= 4
+            && !self.call_type.starts_with("CREATE")
+            && self.call_type != "SELFDESTRUCT"
+    }
+}
+
+/// A tracer for the internal transactions of blocks and transactions, that
+/// decodes the internal contract calls with the ABIs registered with its
+/// decoder.
+#[derive(Clone, Debug)]
+pub struct InternalCallTracer<T: Transport> {
+    web3: Web3<T>,
+    backend: TraceBackend,
+    decoder: CallTraceDecoder,
+}
+
+impl<T: Transport> InternalCallTracer<T> {
+    /// Creates a new tracer using the specified tracing namespace.
+    pub fn new(web3: Web3<T>, backend: TraceBackend) -> Self {
+        InternalCallTracer {
+            web3,
+            backend,
+            decoder: CallTraceDecoder::new(),
+        }
+    }
+
+    /// Sets the decoder used for decoding internal contract calls.
+    pub fn decoder(mut self, decoder: CallTraceDecoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Returns the internal transactions of all transactions in a block, in
+    /// execution order.
+    pub async fn block(&self, block: u64) -> Result<Vec<InternalCall>, Web3Error> {
+        match self.backend {
+            TraceBackend::Parity => {
+                let traces = rpc::trace_block(&self.web3, block.into()).await?;
+                Ok(self.convert_traces(traces))
+            }
+            TraceBackend::Geth => {
+                let traces = rpc::debug_trace_block_by_number::<_, CallFrame>(
+                    &self.web3,
+                    block.into(),
+                    &TraceOptions::call_tracer(),
+                )
+                .await?;
+
+                let mut calls = Vec::new();
+                for (position, trace) in traces.into_iter().enumerate() {
+                    if let Some(frame) = trace.result {
+                        self.flatten_frame(
+                            &frame,
+                            block,
+                            trace.tx_hash,
+                            Some(position),
+                            &mut Vec::new(),
+                            &mut calls,
+                        );
+                    }
+                }
+                Ok(calls)
+            }
+        }
+    }
+
+    /// Returns the internal transactions of a transaction, in execution
+    /// order.
+    pub async fn transaction(&self, hash: H256) -> Result<Vec<InternalCall>, Web3Error> {
+        match self.backend {
+            TraceBackend::Parity => {
+                let traces = rpc::trace_transaction(&self.web3, hash).await?;
+                Ok(self.convert_traces(traces))
+            }
+            TraceBackend::Geth => {
+                let receipt = self.web3.eth().transaction_receipt(hash).await?;
+                let (block, position) = match receipt {
+                    Some(receipt) => (
+                        receipt.block_number.map_or(0, |number| number.as_u64()),
+                        Some(receipt.transaction_index.as_usize()),
+                    ),
+                    None => return Ok(Vec::new()),
+                };
+                let frame: CallFrame =
+                    rpc::debug_trace_transaction(&self.web3, hash, &TraceOptions::call_tracer())
+                        .await?;
+
+                let mut calls = Vec::new();
+                self.flatten_frame(
+                    &frame,
+                    block,
+                    Some(hash),
+                    position,
+                    &mut Vec::new(),
+                    &mut calls,
+                );
+                Ok(calls)
+            }
+        }
+    }
+
+    /// Returns the internal transactions to any of the specified addresses
+    /// in a range of blocks, or all internal transactions in the range if no
+    /// addresses are specified.
+    ///
+    /// This uses a single `trace_filter` query with the `Parity` backend, and
+    /// traces every block in the range with the `Geth` backend.
+    pub async fn filter(
+        &self,
+        blocks: RangeInclusive<u64>,
+        addresses: &[Address],
+    ) -> Result<Vec<InternalCall>, Web3Error> {
+        let is_match = |call: &InternalCall| {
+            addresses.is_empty() || call.to.map_or(false, |to| addresses.contains(&to))
+        };
+
+        match self.backend {
+            TraceBackend::Parity => {
+                let mut filter = TraceFilterBuilder::default()
+                    .from_block(BlockNumber::Number((*blocks.start()).into()))
+                    .to_block(BlockNumber::Number((*blocks.end()).into()));
+                if !addresses.is_empty() {
+                    filter = filter.to_address(addresses.to_vec());
+                }
+                let traces = rpc::trace_filter(&self.web3, filter.build()).await?;
+                Ok(self
+                    .convert_traces(traces)
+                    .into_iter()
+                    .filter(is_match)
+                    .collect())
+            }
+            TraceBackend::Geth => {
+                let mut calls = Vec::new();
+                for block in blocks {
+                    calls.extend(self.block(block).await?.into_iter().filter(is_match));
+                }
+                Ok(calls)
+            }
+        }
+    }
+
+    /// Converts `trace_*` traces into internal transactions, skipping the
+    /// top-level calls and block rewards.
+    fn convert_traces(&self, traces: Vec<Trace>) -> Vec<InternalCall> {
+        traces
+            .into_iter()
+            .filter(|trace| !trace.trace_address.is_empty())
+            .filter_map(|trace| {
+                let (call_type, from, to, value, input, output) = match (trace.action, trace.result)
+                {
+                    (Action::Call(call), result) => {
+                        let output = match result {
+                            Some(Res::Call(result)) => Some(result.output),
+                            _ => None,
+                        };
+                        (
+                            call_type_name(&call.call_type),
+                            call.from,
+                            Some(call.to),
+                            call.value,
+                            call.input,
+                            output,
+                        )
+                    }
+                    (Action::Create(create), result) => {
+                        let (to, output) = match result {
+                            Some(Res::Create(result)) => (Some(result.address), Some(result.code)),
+                            _ => (None, None),
+                        };
+                        ("CREATE", create.from, to, create.value, create.init, output)
+                    }
+                    (Action::Suicide(suicide), _) => (
+                        "SELFDESTRUCT",
+                        suicide.address,
+                        Some(suicide.refund_address),
+                        suicide.balance,
+                        Bytes::default(),
+                        None,
+                    ),
+                    (Action::Reward(_), _) => return None,
+                };
+
+                Some(self.decoded(InternalCall {
+                    block_number: trace.block_number,
+                    transaction_hash: trace.transaction_hash,
+                    transaction_position: trace.transaction_position,
+                    trace_address: trace.trace_address,
+                    call_type: call_type.to_owned(),
+                    from,
+                    to,
+                    value,
+                    input,
+                    output,
+                    error: trace.error,
+                    method: None,
+                }))
+            })
+            .collect()
+    }
+
+    /// Flattens the nested calls of a `callTracer` call frame into internal
+    /// transactions, in execution order.
+    fn flatten_frame(
+        &self,
+        frame: &CallFrame,
+        block: u64,
+        transaction_hash: Option<H256>,
+        transaction_position: Option<usize>,
+        trace_address: &mut Vec<usize>,
+        calls: &mut Vec<InternalCall>,
+    ) {
+        for (index, call) in frame.calls.iter().enumerate() {
+            trace_address.push(index);
+            calls.push(self.decoded(InternalCall {
+                block_number: block,
+                transaction_hash,
+                transaction_position,
+                trace_address: trace_address.clone(),
+                call_type: call.call_type.clone(),
+                from: call.from,
+                to: call.to,
+                value: call.value.unwrap_or_default(),
+                input: call.input.clone(),
+                output: call.output.clone(),
+                error: call.error.clone(),
+                method: None,
+            }));
+            self.flatten_frame(
+                call,
+                block,
+                transaction_hash,
+                transaction_position,
+                trace_address,
+                calls,
+            );
+            trace_address.pop();
+        }
+    }
+
+    fn decoded(&self, mut call: InternalCall) -> InternalCall {
+        if call.is_contract_call() {
+            call.method = call.to.and_then(|to| {
+                self.decoder.decode_method(
+                    to,
+                    &call.input.0,
+                    call.output.as_ref().map(|output| &output.0[..]),
+                    call.error.is_some(),
+                )
+            });
+        }
+        call
+    }
+}
+
+/// Returns the `callTracer` name of a `trace_*` call type.
+fn call_type_name(call_type: &CallType) -> &'static str {
+    match call_type {
+        CallType::None | CallType::Call => "CALL",
+        CallType::CallCode => "CALLCODE",
+        CallType::DelegateCall => "DELEGATECALL",
+        CallType::StaticCall => "STATICCALL",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use ethcontract_common::abi::{encode, Token};
+    use ethcontract_common::Abi;
+    use serde_json::Value;
+
+    const TOKEN_ABI: &str = r#"[{
+        "type": "function",
+        "name": "transfer",
+        "inputs": [
+            { "name": "to", "type": "address" },
+            { "name": "amount", "type": "uint256" }
+        ],
+        "outputs": [{ "name": "", "type": "bool" }],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    fn transfer_call(to: Address, amount: u64) -> Bytes {
+        let mut input = hex::decode("a9059cbb").unwrap();
+        input.extend(encode(&[Token::Address(to), Token::Uint(amount.into())]));
+        Bytes(input)
+    }
+
+    fn parity_trace(trace_address: Vec<usize>, action: Value, result: Value) -> Value {
+        json!({
+            "action": action,
+            "result": result,
+            "traceAddress": trace_address,
+            "subtraces": 0,
+            "transactionPosition": 0,
+            "transactionHash": H256::repeat_byte(9),
+            "blockNumber": 42,
+            "blockHash": H256::repeat_byte(42),
+            "type": if action.get("init").is_some() { "create" } else { "call" },
+            "error": null,
+        })
+    }
+
+    fn decoder() -> CallTraceDecoder {
+        let abi = Abi::load(TOKEN_ABI.as_bytes()).unwrap();
+        CallTraceDecoder::new().contract("Token", Address::repeat_byte(3), abi)
+    }
+
+    #[test]
+    fn parity_block_internal_calls() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let tracer = InternalCallTracer::new(web3, TraceBackend::Parity).decoder(decoder());
+
+        let call = |to: Address, value: u64, input: &Bytes| {
+            json!({
+                "from": Address::repeat_byte(1),
+                "to": to,
+                "value": U256::from(value),
+                "gas": "0x1000",
+                "input": input,
+                "callType": "call",
+            })
+        };
+        let transfer = transfer_call(Address::repeat_byte(4), 100);
+        transport.add_response(json!([
+            parity_trace(
+                vec![],
+                call(Address::repeat_byte(2), 0, &Bytes(vec![1, 2, 3, 4])),
+                json!({ "gasUsed": "0x100", "output": "0x" }),
+            ),
+            parity_trace(
+                vec![0],
+                call(Address::repeat_byte(3), 0, &transfer),
+                json!({ "gasUsed": "0x10", "output": Bytes(encode(&[Token::Bool(true)])) }),
+            ),
+            parity_trace(
+                vec![1],
+                call(Address::repeat_byte(5), 7, &Bytes::default()),
+                json!({ "gasUsed": "0x0", "output": "0x" }),
+            ),
+            parity_trace(
+                vec![2],
+                json!({
+                    "from": Address::repeat_byte(2),
+                    "value": "0x0",
+                    "gas": "0x1000",
+                    "init": "0x00",
+                }),
+                json!({
+                    "gasUsed": "0x10",
+                    "code": "0x00",
+                    "address": Address::repeat_byte(6),
+                }),
+            ),
+        ]));
+
+        let calls = tracer.block(42).immediate().unwrap();
+        transport.assert_request("trace_block", &[json!("0x2a")]);
+        transport.assert_no_more_requests();
+
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].trace_address, vec![0]);
+        assert_eq!(calls[0].transaction_hash, Some(H256::repeat_byte(9)));
+        assert!(calls[0].is_contract_call());
+        assert!(!calls[0].is_transfer());
+        let method = calls[0].method.as_ref().unwrap();
+        assert_eq!(method.contract, "Token");
+        assert_eq!(method.name, "transfer");
+        assert_eq!(
+            method.outputs,
+            Some(vec![(String::new(), Token::Bool(true))])
+        );
+
+        assert_eq!(calls[1].to, Some(Address::repeat_byte(5)));
+        assert_eq!(calls[1].value, 7.into());
+        assert!(calls[1].is_transfer());
+        assert!(!calls[1].is_contract_call());
+
+        assert_eq!(calls[2].call_type, "CREATE");
+        assert_eq!(calls[2].to, Some(Address::repeat_byte(6)));
+        assert_eq!(calls[2].method, None);
+    }
+
+    #[test]
+    fn geth_block_internal_calls() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let tracer = InternalCallTracer::new(web3, TraceBackend::Geth).decoder(decoder());
+
+        let transfer = transfer_call(Address::repeat_byte(4), 100);
+        transport.add_response(json!([
+            {
+                "txHash": H256::repeat_byte(8),
+                "result": {
+                    "type": "CALL",
+                    "from": Address::repeat_byte(1),
+                    "to": Address::repeat_byte(2),
+                    "value": "0x0",
+                    "gas": "0x1000",
+                    "gasUsed": "0x100",
+                    "input": "0x",
+                },
+            },
+            {
+                "txHash": H256::repeat_byte(9),
+                "result": {
+                    "type": "CALL",
+                    "from": Address::repeat_byte(1),
+                    "to": Address::repeat_byte(2),
+                    "value": "0x0",
+                    "gas": "0x1000",
+                    "gasUsed": "0x100",
+                    "input": "0x01020304",
+                    "calls": [{
+                        "type": "DELEGATECALL",
+                        "from": Address::repeat_byte(2),
+                        "to": Address::repeat_byte(7),
+                        "value": "0x5",
+                        "gas": "0x800",
+                        "gasUsed": "0x80",
+                        "input": "0x01020304",
+                        "calls": [{
+                            "type": "CALL",
+                            "from": Address::repeat_byte(2),
+                            "to": Address::repeat_byte(3),
+                            "gas": "0x400",
+                            "gasUsed": "0x40",
+                            "input": transfer,
+                            "error": "execution reverted",
+                        }],
+                    }, {
+                        "type": "CALL",
+                        "from": Address::repeat_byte(2),
+                        "to": Address::repeat_byte(5),
+                        "value": "0x5",
+                        "gas": "0x800",
+                        "gasUsed": "0x0",
+                        "input": "0x",
+                    }],
+                },
+            },
+        ]));
+
+        let calls = tracer.block(42).immediate().unwrap();
+        transport.assert_request(
+            "debug_traceBlockByNumber",
+            &[json!("0x2a"), json!({ "tracer": "callTracer" })],
+        );
+        transport.assert_no_more_requests();
+
+        let addresses = calls
+            .iter()
+            .map(|call| call.trace_address.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![vec![0], vec![0, 0], vec![1]]);
+        assert!(calls
+            .iter()
+            .all(|call| call.transaction_hash == Some(H256::repeat_byte(9))
+                && call.transaction_position == Some(1)
+                && call.block_number == 42));
+
+        assert!(!calls[0].is_transfer());
+        let method = calls[1].method.as_ref().unwrap();
+        assert_eq!(method.name, "transfer");
+        assert_eq!(method.outputs, None);
+        assert!(calls[2].is_transfer());
+    }
+
+    #[test]
+    fn geth_filter_traces_every_block() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let tracer = InternalCallTracer::new(web3, TraceBackend::Geth);
+
+        let trace = |to: Address| {
+            json!([{
+                "result": {
+                    "type": "CALL",
+                    "from": Address::repeat_byte(1),
+                    "to": Address::repeat_byte(2),
+                    "gas": "0x1000",
+                    "gasUsed": "0x100",
+                    "input": "0x",
+                    "calls": [{
+                        "type": "CALL",
+                        "from": Address::repeat_byte(2),
+                        "to": to,
+                        "value": "0x1",
+                        "gas": "0x800",
+                        "gasUsed": "0x0",
+                        "input": "0x",
+                    }],
+                },
+            }])
+        };
+        transport.add_response(trace(Address::repeat_byte(3)));
+        transport.add_response(trace(Address::repeat_byte(4)));
+
+        let calls = tracer
+            .filter(1..=2, &[Address::repeat_byte(4)])
+            .immediate()
+            .unwrap();
+        transport.assert_request(
+            "debug_traceBlockByNumber",
+            &[json!("0x1"), json!({ "tracer": "callTracer" })],
+        );
+        transport.assert_request(
+            "debug_traceBlockByNumber",
+            &[json!("0x2"), json!({ "tracer": "callTracer" })],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].block_number, 2);
+        assert_eq!(calls[0].transaction_hash, None);
+    }
+}
@@ -0,0 +1,354 @@
+//! Batched retrieval of transaction receipts with `eth_getBlockReceipts`,
+//! falling back to per transaction queries for nodes that do not support it.
+
+use crate::errors::{ExecutionError, RpcError};
+use crate::rpc;
+use futures::future;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{BlockId, BlockNumber, Log, TransactionReceipt, H256};
+use web3::Transport;
+
+/// Returns the receipts of all transactions in a block with
+/// `eth_getBlockReceipts`, or `None` if the block does not exist.
+pub async fn block_receipts<T: Transport>(
+    web3: &Web3<T>,
+    block: BlockId,
+) -> Result<Option<Vec<TransactionReceipt>>, Web3Error> {
+    match block {
+        // NOTE: Block hashes are passed as plain strings instead of EIP-1898
+        //   objects, since not all nodes support the latter here.
+        BlockId::Hash(hash) => rpc::call(web3, "eth_getBlockReceipts", (hash,)).await,
+        BlockId::Number(number) => rpc::call(web3, "eth_getBlockReceipts", (number,)).await,
+    }
+}
+
+/// A fetcher for transaction receipts that retrieves the receipts of all
+/// transactions in a block with a single `eth_getBlockReceipts` call.
+///
+/// This significantly reduces the number of requests needed for confirming
+/// many transactions or for enriching many logs from the same blocks. Nodes
+/// that do not support `eth_getBlockReceipts` are detected on the first call,
+/// after which receipts are fetched per transaction. Fetchers are cheap to
+/// clone and clones share the detected support.
+#[derive(Clone, Debug)]
+pub struct ReceiptFetcher<T: Transport> {
+    web3: Web3<T>,
+    block_receipts: Arc<AtomicBool>,
+}
+
+impl<T: Transport> ReceiptFetcher<T> {
+    /// Creates a new receipt fetcher for the specified web3 provider.
+    pub fn new(web3: Web3<T>) -> Self {
+        ReceiptFetcher {
+            web3,
+            block_receipts: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Returns `true` unless the node was detected not to support
+    /// `eth_getBlockReceipts`.
+    pub fn supports_block_receipts(&self) -> bool {
+        self.block_receipts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the receipts of all transactions in a block, ordered by their
+    /// index in the block.
+    pub async fn block(&self, block: BlockId) -> Result<Vec<TransactionReceipt>, ExecutionError> {
+        if self.supports_block_receipts() {
+            match block_receipts(&self.web3, block).await {
+                Ok(Some(receipts)) => return Ok(receipts),
+                Ok(None) => return Err(ExecutionError::MissingBlock),
+                Err(err) if is_unsupported(&err) => {
+                    self.block_receipts.store(false, Ordering::Relaxed)
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let transactions = self
+            .web3
+            .eth()
+            .block(block)
+            .await?
+            .ok_or(ExecutionError::MissingBlock)?
+            .transactions;
+        self.transactions(&transactions).await
+    }
+
+    /// Returns the receipts of the transactions that emitted the specified
+    /// logs, keyed by transaction hash.
+    ///
+    /// The receipts of blocks with logs from more than one transaction are
+    /// fetched with a single call per block.
+    pub async fn logs(
+        &self,
+        logs: &[Log],
+    ) -> Result<HashMap<H256, TransactionReceipt>, ExecutionError> {
+        let mut blocks = HashMap::<H256, HashSet<H256>>::new();
+        for log in logs {
+            if let (Some(block), Some(transaction)) = (log.block_hash, log.transaction_hash) {
+                blocks.entry(block).or_default().insert(transaction);
+            }
+        }
+
+        let mut single_transactions = Vec::new();
+        let mut block_queries = Vec::new();
+        for (block, transactions) in blocks {
+            if transactions.len() == 1 {
+                single_transactions.extend(transactions);
+            } else {
+                block_queries.push(async move {
+                    let receipts = self.block(BlockId::Hash(block)).await?;
+                    Ok::<_, ExecutionError>(
+                        receipts
+                            .into_iter()
+                            .filter(|receipt| transactions.contains(&receipt.transaction_hash))
+                            .collect::<Vec<_>>(),
+                    )
+                });
+            }
+        }
+
+        let (single_receipts, block_receipts) = future::try_join(
+            self.transactions(&single_transactions),
+            future::try_join_all(block_queries),
+        )
+        .await?;
+        Ok(single_receipts
+            .into_iter()
+            .chain(block_receipts.into_iter().flatten())
+            .map(|receipt| (receipt.transaction_hash, receipt))
+            .collect())
+    }
+
+    /// Searches the specified range of blocks for the receipts of the
+    /// specified transactions, keyed by transaction hash, stopping as soon as
+    /// all of them are found. This is useful for confirming many transactions
+    /// sent since a known block.
+    pub async fn find(
+        &self,
+        transactions: &[H256],
+        blocks: RangeInclusive<u64>,
+    ) -> Result<HashMap<H256, TransactionReceipt>, ExecutionError> {
+        let mut pending = transactions.iter().copied().collect::<HashSet<_>>();
+        let mut found = HashMap::new();
+        for block in blocks {
+            if pending.is_empty() {
+                break;
+            }
+            let receipts = self
+                .block(BlockId::Number(BlockNumber::Number(block.into())))
+                .await?;
+            for receipt in receipts {
+                if pending.remove(&receipt.transaction_hash) {
+                    found.insert(receipt.transaction_hash, receipt);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Fetches the receipts of the specified mined transactions one by one.
+    async fn transactions(
+        &self,
+        transactions: &[H256],
+    ) -> Result<Vec<TransactionReceipt>, ExecutionError> {
+        future::try_join_all(transactions.iter().map(|&hash| async move {
+            self.web3
+                .eth()
+                .transaction_receipt(hash)
+                .await?
+                .ok_or(ExecutionError::MissingTransaction(hash))
+        }))
+        .await
+    }
+}
+
+/// Returns `true` if a call failed because the node does not support the
+/// method.
+fn is_unsupported(err: &Web3Error) -> bool {
+    matches!(
+        err,
+        Web3Error::Rpc(err) if RpcError::from_jsonrpc(err) == Some(RpcError::MethodNotFound)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
+    use serde_json::Value;
+    use web3::types::{Address, H2048, U64};
+
+    fn receipt(tx_hash: H256, block: u8) -> Value {
+        json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockNumber": U64::from(block),
+            "blockHash": H256::repeat_byte(block),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x100",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x1",
+        })
+    }
+
+    fn block(number: u8, transactions: &[H256]) -> Value {
+        json!({
+            "hash": H256::repeat_byte(number),
+            "parentHash": H256::zero(),
+            "sha3Uncles": H256::zero(),
+            "miner": Address::zero(),
+            "stateRoot": H256::zero(),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": U64::from(number),
+            "gasUsed": "0x0",
+            "gasLimit": "0x0",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x0",
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "sealFields": [],
+            "uncles": [],
+            "transactions": transactions,
+            "size": "0x0",
+            "mixHash": H256::zero(),
+            "nonce": "0x0000000000000000",
+        })
+    }
+
+    fn log(tx_hash: H256, block: u8) -> Log {
+        Log {
+            block_hash: Some(H256::repeat_byte(block)),
+            transaction_hash: Some(tx_hash),
+            ..serde_json::from_value(json!({
+                "address": Address::zero(),
+                "topics": [],
+                "data": "0x",
+            }))
+            .unwrap()
+        }
+    }
+
+    #[test]
+    fn fetches_block_receipts() {
+        let mut transport = TestTransport::new();
+        let fetcher = ReceiptFetcher::new(Web3::new(transport.clone()));
+
+        let (tx1, tx2) = (H256::repeat_byte(0x11), H256::repeat_byte(0x12));
+        transport.add_response(json!([receipt(tx1, 1), receipt(tx2, 1)]));
+        let receipts = fetcher
+            .block(BlockId::Number(1.into()))
+            .immediate()
+            .unwrap();
+        assert_eq!(
+            receipts
+                .iter()
+                .map(|receipt| receipt.transaction_hash)
+                .collect::<Vec<_>>(),
+            vec![tx1, tx2],
+        );
+
+        transport.add_response(json!(null));
+        assert!(matches!(
+            fetcher.block(BlockId::Number(2.into())).immediate(),
+            Err(ExecutionError::MissingBlock)
+        ));
+
+        transport.assert_request("eth_getBlockReceipts", &[json!("0x1")]);
+        transport.assert_request("eth_getBlockReceipts", &[json!("0x2")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn falls_back_to_transaction_receipts() {
+        let mut transport = TestTransport::new();
+        let fetcher = ReceiptFetcher::new(Web3::new(transport.clone()));
+        let tx = H256::repeat_byte(0x11);
+
+        transport.add_error(Web3Error::Rpc(JsonrpcError {
+            code: ErrorCode::MethodNotFound,
+            message: "the method eth_getBlockReceipts does not exist/is not available".to_owned(),
+            data: None,
+        }));
+        transport.add_response(block(1, &[tx]));
+        transport.add_response(receipt(tx, 1));
+        let receipts = fetcher
+            .block(BlockId::Number(1.into()))
+            .immediate()
+            .unwrap();
+        assert_eq!(receipts[0].transaction_hash, tx);
+        assert!(!fetcher.clone().supports_block_receipts());
+
+        transport.add_response(block(2, &[]));
+        let receipts = fetcher
+            .block(BlockId::Number(2.into()))
+            .immediate()
+            .unwrap();
+        assert!(receipts.is_empty());
+
+        transport.assert_request("eth_getBlockReceipts", &[json!("0x1")]);
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x1"), json!(false)]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(tx)]);
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x2"), json!(false)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn fetches_log_receipts_per_block() {
+        let mut transport = TestTransport::new();
+        let fetcher = ReceiptFetcher::new(Web3::new(transport.clone()));
+
+        let (tx1, tx2, tx3) = (
+            H256::repeat_byte(0x11),
+            H256::repeat_byte(0x12),
+            H256::repeat_byte(0x21),
+        );
+        let other = H256::repeat_byte(0x13);
+        let logs = [log(tx1, 1), log(tx2, 1), log(tx1, 1), log(tx3, 2)];
+
+        // NOTE: Single transaction receipts are requested first.
+        transport.add_response(receipt(tx3, 2));
+        transport.add_response(json!(
+            [receipt(tx1, 1), receipt(other, 1), receipt(tx2, 1),]
+        ));
+
+        let receipts = fetcher.logs(&logs).immediate().unwrap();
+        let mut hashes = receipts.keys().copied().collect::<Vec<_>>();
+        hashes.sort();
+        assert_eq!(hashes, vec![tx1, tx2, tx3]);
+
+        transport.assert_request("eth_getTransactionReceipt", &[json!(tx3)]);
+        transport.assert_request("eth_getBlockReceipts", &[json!(H256::repeat_byte(1))]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn finds_receipts_in_blocks() {
+        let mut transport = TestTransport::new();
+        let fetcher = ReceiptFetcher::new(Web3::new(transport.clone()));
+        let (tx1, tx2) = (H256::repeat_byte(0x21), H256::repeat_byte(0x31));
+
+        transport.add_response(json!([]));
+        transport.add_response(json!([receipt(tx1, 2)]));
+        transport.add_response(json!([receipt(tx2, 3)]));
+
+        let receipts = fetcher.find(&[tx1, tx2], 1..=10).immediate().unwrap();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[&tx2].block_number, Some(3.into()));
+
+        for block in 1..=3 {
+            transport.assert_request("eth_getBlockReceipts", &[json!(U64::from(block))]);
+        }
+        transport.assert_no_more_requests();
+    }
+}
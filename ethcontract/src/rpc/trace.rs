@@ -0,0 +1,51 @@
+//! Typed wrappers for the `trace_*` namespace implemented by OpenEthereum,
+//! Nethermind and Erigon.
+
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{BlockNumber, BlockTrace, CallRequest, Trace, TraceFilter, TraceType, H256};
+use web3::Transport;
+
+/// Returns the traces of a transaction with `trace_transaction`.
+pub async fn trace_transaction<T: Transport>(
+    web3: &Web3<T>,
+    hash: H256,
+) -> Result<Vec<Trace>, Web3Error> {
+    web3.trace().transaction(hash).await
+}
+
+/// Returns the traces of all transactions in a block with `trace_block`.
+pub async fn trace_block<T: Transport>(
+    web3: &Web3<T>,
+    block: BlockNumber,
+) -> Result<Vec<Trace>, Web3Error> {
+    web3.trace().block(block).await
+}
+
+/// Returns the traces matching a filter with `trace_filter`.
+pub async fn trace_filter<T: Transport>(
+    web3: &Web3<T>,
+    filter: TraceFilter,
+) -> Result<Vec<Trace>, Web3Error> {
+    web3.trace().filter(filter).await
+}
+
+/// Executes a call and returns the requested traces with `trace_call`.
+pub async fn trace_call<T: Transport>(
+    web3: &Web3<T>,
+    request: CallRequest,
+    trace_types: Vec<TraceType>,
+    block: Option<BlockNumber>,
+) -> Result<BlockTrace, Web3Error> {
+    web3.trace().call(request, trace_types, block).await
+}
+
+/// Replays a transaction and returns the requested traces with
+/// `trace_replayTransaction`.
+pub async fn trace_replay_transaction<T: Transport>(
+    web3: &Web3<T>,
+    hash: H256,
+    trace_types: Vec<TraceType>,
+) -> Result<BlockTrace, Web3Error> {
+    web3.trace().replay_transaction(hash, trace_types).await
+}
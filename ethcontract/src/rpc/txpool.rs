@@ -0,0 +1,241 @@
+//! Typed wrappers for the `txpool_*` namespace implemented by Geth and
+//! compatible nodes, for inspecting transactions that are waiting to be
+//! mined.
+
+use crate::rpc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{Address, Bytes, TxpoolStatus, H256, U256, U64};
+use web3::Transport;
+
+/// The content of the transaction pool, grouped by sender and nonce.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TxpoolContent {
+    /// The transactions that are ready to be included in the next blocks.
+    pub pending: BTreeMap<Address, BTreeMap<String, TxpoolTransaction>>,
+    /// The transactions that are scheduled for future execution, for example
+    /// because of a nonce gap.
+    pub queued: BTreeMap<Address, BTreeMap<String, TxpoolTransaction>>,
+}
+
+/// A transaction in the transaction pool.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxpoolTransaction {
+    /// The transaction hash.
+    pub hash: H256,
+    /// The transaction nonce.
+    pub nonce: U256,
+    /// The sender of the transaction.
+    pub from: Address,
+    /// The receiver of the transaction, `None` for contract deployments.
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// The ETH value transferred with the transaction.
+    pub value: U256,
+    /// The gas limit of the transaction.
+    pub gas: U256,
+    /// The gas price of legacy transactions. Some nodes also report the
+    /// maximum fee per gas of EIP-1559 transactions as their gas price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<U256>,
+    /// The maximum fee per gas of EIP-1559 transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    /// The maximum priority fee per gas of EIP-1559 transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The transaction data.
+    pub input: Bytes,
+    /// The transaction type, `None` for legacy transactions.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<U64>,
+}
+
+impl TxpoolTransaction {
+    /// Returns the maximum fee per gas that the transaction pays, which is the
+    /// maximum fee per gas of EIP-1559 transactions or the gas price of legacy
+    /// transactions.
+    pub fn max_fee(&self) -> Option<U256> {
+        self.max_fee_per_gas.or(self.gas_price)
+    }
+}
+
+/// The state of a transaction in the transaction pool.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PoolState {
+    /// The transaction is ready to be included in the next blocks.
+    Pending,
+    /// The transaction is scheduled for future execution.
+    Queued,
+}
+
+/// A transaction of an account in the transaction pool, along with its pool
+/// state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PooledTransaction {
+    /// The state of the transaction in the pool.
+    pub state: PoolState,
+    /// The transaction.
+    pub transaction: TxpoolTransaction,
+}
+
+impl TxpoolContent {
+    /// Returns the transactions of an account in the pool, ordered by nonce.
+    pub fn account_transactions(&self, account: Address) -> Vec<PooledTransaction> {
+        let mut transactions = [
+            (PoolState::Pending, &self.pending),
+            (PoolState::Queued, &self.queued),
+        ]
+        .iter()
+        .filter_map(|(state, transactions)| Some((*state, transactions.get(&account)?)))
+        .flat_map(|(state, transactions)| {
+            transactions
+                .values()
+                .map(move |transaction| PooledTransaction {
+                    state,
+                    transaction: transaction.clone(),
+                })
+        })
+        .collect::<Vec<_>>();
+        transactions.sort_by_key(|pooled| pooled.transaction.nonce);
+        transactions
+    }
+}
+
+/// Returns the content of the transaction pool with `txpool_content`.
+pub async fn txpool_content<T: Transport>(web3: &Web3<T>) -> Result<TxpoolContent, Web3Error> {
+    rpc::call(web3, "txpool_content", ()).await
+}
+
+/// Returns the number of pending and queued transactions in the transaction
+/// pool with `txpool_status`.
+pub async fn txpool_status<T: Transport>(web3: &Web3<T>) -> Result<TxpoolStatus, Web3Error> {
+    web3.txpool().status().await
+}
+
+/// Returns the transaction of an account with the specified nonce if it is
+/// in the transaction pool, along with whether it is pending or queued.
+///
+/// This can be used for detecting stuck transactions, for example when a
+/// transaction remains in the pool with a fee below the current gas price,
+/// or is queued behind a missing nonce.
+///
+/// Note that this retrieves the entire content of the transaction pool, which
+/// can be large for nodes with a busy transaction pool.
+pub async fn pool_transaction<T: Transport>(
+    web3: &Web3<T>,
+    account: Address,
+    nonce: U256,
+) -> Result<Option<PooledTransaction>, Web3Error> {
+    let content = txpool_content(web3).await?;
+    Ok(content
+        .account_transactions(account)
+        .into_iter()
+        .find(|pooled| pooled.transaction.nonce == nonce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use serde_json::Value;
+
+    fn transaction(from: Address, nonce: u64) -> Value {
+        json!({
+            "hash": H256::from_low_u64_be(nonce),
+            "nonce": U256::from(nonce),
+            "blockHash": null,
+            "blockNumber": null,
+            "transactionIndex": null,
+            "from": from,
+            "to": Address::repeat_byte(0xff),
+            "value": "0x0",
+            "gas": "0x5208",
+            "maxFeePerGas": "0x2540be400",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "gasPrice": "0x2540be400",
+            "input": "0x",
+            "type": "0x2",
+        })
+    }
+
+    #[test]
+    fn account_pool_transactions() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (account, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        let content = json!({
+            "pending": {
+                "0x0101010101010101010101010101010101010101": {
+                    "5": transaction(account, 5),
+                    "4": transaction(account, 4),
+                },
+                "0x0202020202020202020202020202020202020202": {
+                    "7": transaction(other, 7),
+                },
+            },
+            "queued": {
+                "0x0101010101010101010101010101010101010101": {
+                    "7": transaction(account, 7),
+                },
+            },
+        });
+
+        transport.add_response(content.clone());
+        let content = txpool_content(&web3).immediate().unwrap();
+        assert_eq!(
+            content
+                .account_transactions(account)
+                .iter()
+                .map(|pooled| (pooled.state, pooled.transaction.nonce.as_u64()))
+                .collect::<Vec<_>>(),
+            vec![
+                (PoolState::Pending, 4),
+                (PoolState::Pending, 5),
+                (PoolState::Queued, 7),
+            ],
+        );
+        transport.assert_request("txpool_content", &[]);
+
+        transport.add_response(json!({ "pending": {}, "queued": {} }));
+        assert_eq!(
+            pool_transaction(&web3, account, 7.into())
+                .immediate()
+                .unwrap(),
+            None,
+        );
+        transport.assert_request("txpool_content", &[]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn queued_transaction_fee() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let account = Address::repeat_byte(1);
+
+        transport.add_response(json!({
+            "pending": {},
+            "queued": {
+                "0x0101010101010101010101010101010101010101": {
+                    "7": transaction(account, 7),
+                },
+            },
+        }));
+        let pooled = pool_transaction(&web3, account, 7.into())
+            .immediate()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(pooled.state, PoolState::Queued);
+        assert_eq!(pooled.transaction.max_fee(), Some(10_000_000_000u64.into()));
+        assert_eq!(
+            pooled.transaction.max_priority_fee_per_gas,
+            Some(1_000_000_000.into())
+        );
+    }
+}
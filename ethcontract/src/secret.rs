@@ -22,7 +22,7 @@ pub struct PrivateKey(Zeroizing<ZeroizeableSecretKey>);
 impl PrivateKey {
     /// Creates a new private key from raw bytes.
     pub fn from_raw(raw: [u8; 32]) -> Result<Self, InvalidPrivateKey> {
-        PrivateKey::from_slice(&raw)
+        PrivateKey::from_slice(raw)
     }
 
     /// Creates a new private key from a slice of bytes.
@@ -43,7 +43,7 @@ impl PrivateKey {
     /// Gets the public address for a given private key.
     pub fn public_address(&self) -> Address {
         let secp = Secp256k1::signing_only();
-        let public_key = PublicKey::from_secret_key(&secp, &*self).serialize_uncompressed();
+        let public_key = PublicKey::from_secret_key(&secp, self).serialize_uncompressed();
 
         // NOTE: An ethereum address is the last 20 bytes of the keccak hash of
         //   the public key. Note that `libsecp256k1` public key is serialized
@@ -0,0 +1,150 @@
+//! Alternative `serde` representations of Ethereum types, for use with the
+//! `#[serde(with = "...")]` attribute.
+//!
+//! By default, `U256` and `I256` values are serialized as hexadecimal strings
+//! and addresses as lowercase hexadecimal strings. The generated event data
+//! types use these modules for the representations chosen with the serde
+//! options of the generator, and they can also be used for application types
+//! that need to match existing JSON APIs:
+//!
+//! ```
+//! # use ethcontract::{Address, U256};
+//! # use serde::Serialize;
+//! #[derive(Serialize)]
+//! struct Transfer {
+//!     #[serde(with = "ethcontract::serialization::address_checksummed")]
+//!     to: Address,
+//!     #[serde(with = "ethcontract::serialization::u256_decimal")]
+//!     value: U256,
+//! }
+//! ```
+
+/// Serializes `U256` values as decimal strings. Deserialization accepts
+/// decimal strings and JSON numbers.
+pub mod u256_decimal {
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::Serializer;
+    use serde_json::Value;
+    use web3::types::U256;
+
+    /// Serializes a value as a decimal string.
+    pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    /// Deserializes a value from a decimal string or number.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(value) => U256::from_dec_str(&value).map_err(de::Error::custom),
+            Value::Number(value) => value
+                .as_u64()
+                .map(U256::from)
+                .ok_or_else(|| de::Error::custom("invalid decimal number")),
+            _ => Err(de::Error::custom("expected a decimal string")),
+        }
+    }
+}
+
+/// Serializes `I256` values as decimal strings. Deserialization accepts
+/// decimal strings and JSON numbers.
+pub mod i256_decimal {
+    use crate::int::I256;
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::Serializer;
+    use serde_json::Value;
+
+    /// Serializes a value as a decimal string.
+    pub fn serialize<S: Serializer>(value: &I256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    /// Deserializes a value from a decimal string or number.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<I256, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(value) => I256::from_dec_str(&value).map_err(de::Error::custom),
+            Value::Number(value) => value
+                .as_i64()
+                .map(I256::from)
+                .ok_or_else(|| de::Error::custom("invalid decimal number")),
+            _ => Err(de::Error::custom("expected a decimal string")),
+        }
+    }
+}
+
+/// Serializes addresses as EIP-55 checksummed strings. Deserialization
+/// accepts lowercase and uppercase addresses, and verifies the checksum of
+/// mixed-case addresses.
+pub mod address_checksummed {
+    use ethcontract_common::address;
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::Serializer;
+    use web3::types::Address;
+
+    /// Serializes an address as a checksummed string.
+    pub fn serialize<S: Serializer>(value: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&address::to_checksum(value))
+    }
+
+    /// Deserializes an address from a hexadecimal string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Address, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        address::parse_checksummed(&value).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::int::I256;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use web3::types::{Address, U256};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Values {
+        #[serde(with = "super::u256_decimal")]
+        uint: U256,
+        #[serde(with = "super::i256_decimal")]
+        int: I256,
+        #[serde(with = "super::address_checksummed")]
+        address: Address,
+    }
+
+    #[test]
+    fn alternative_representations() {
+        let values = Values {
+            uint: U256::exp10(20),
+            int: I256::from(-42),
+            address: "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+                .parse()
+                .unwrap(),
+        };
+        let value = json!({
+            "uint": "100000000000000000000",
+            "int": "-42",
+            "address": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        });
+        assert_eq!(serde_json::to_value(&values).unwrap(), value);
+        assert_eq!(serde_json::from_value::<Values>(value).unwrap(), values);
+
+        let numbers = json!({
+            "uint": 1337,
+            "int": -1,
+            "address": "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+        });
+        assert_eq!(
+            serde_json::from_value::<Values>(numbers).unwrap(),
+            Values {
+                uint: 1337.into(),
+                int: I256::minus_one(),
+                ..values
+            },
+        );
+
+        let bad_checksum = json!({
+            "uint": "0",
+            "int": "0",
+            "address": "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        });
+        assert!(serde_json::from_value::<Values>(bad_checksum).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Implementation of read sessions that pin contract calls and log queries to
+//! a single block, providing an internally consistent view of the chain state
+//! even if new blocks are mined while reading.
+
+use crate::block::{self, BlockTag};
+use crate::contract::{Event, EventBuilder, ViewMethodBuilder};
+use crate::errors::{EventError, ExecutionError, MethodError};
+use crate::log::LogFilterBuilder;
+use crate::tokens::Tokenize;
+use web3::api::Web3;
+use web3::types::{Address, BlockId, BlockNumber, Bytes, Log, H256, U256, U64};
+use web3::Transport;
+
+/// A read session pinned to a single block.
+///
+/// The block is resolved once when the session is created, and all calls and
+/// log queries made through the session are made against that block instead
+/// of the latest block at the time of the request.
+///
+/// Calls are pinned to the block by hash as specified in EIP-1898, so they
+/// fail instead of silently reading from a different block if the pinned
+/// block gets re-orged out of the chain. Log queries for block ranges use
+/// block numbers and are capped to the pinned block.
+#[derive(Debug, Clone)]
+pub struct ReadSession<T: Transport> {
+    web3: Web3<T>,
+    block_hash: H256,
+    block_number: U64,
+}
+
+impl<T: Transport> ReadSession<T> {
+    /// Creates a new read session pinned to the latest block.
+    pub async fn new(web3: Web3<T>) -> Result<Self, ExecutionError> {
+        ReadSession::at_tag(web3, BlockTag::Latest).await
+    }
+
+    /// Creates a new read session pinned to the block that currently has the
+    /// specified tag, for example to only read finalized state.
+    pub async fn at_tag(web3: Web3<T>, tag: BlockTag) -> Result<Self, ExecutionError> {
+        let (block_number, block_hash) = tag.resolve(&web3).await?;
+        Ok(ReadSession::at(web3, block_hash, block_number))
+    }
+
+    /// Creates a new read session pinned to an already resolved block.
+    pub fn at(web3: Web3<T>, block_hash: H256, block_number: U64) -> Self {
+        ReadSession {
+            web3,
+            block_hash,
+            block_number,
+        }
+    }
+
+    /// Returns a reference to the underlying `web3` provider.
+    pub fn web3(&self) -> &Web3<T> {
+        &self.web3
+    }
+
+    /// Returns the hash of the pinned block.
+    pub fn block_hash(&self) -> H256 {
+        self.block_hash
+    }
+
+    /// Returns the number of the pinned block.
+    pub fn block_number(&self) -> U64 {
+        self.block_number
+    }
+
+    /// Returns the block identifier used for pinning calls.
+    pub fn block(&self) -> BlockId {
+        BlockId::Hash(self.block_hash)
+    }
+
+    /// Pins a view method call to the session block.
+    pub fn view<R: Tokenize>(&self, method: ViewMethodBuilder<T, R>) -> ViewMethodBuilder<T, R> {
+        method.block(self.block())
+    }
+
+    /// Calls a contract method at the session block.
+    pub async fn call<R: Tokenize>(
+        &self,
+        method: ViewMethodBuilder<T, R>,
+    ) -> Result<R, MethodError> {
+        self.view(method).call().await
+    }
+
+    /// Pins a log filter to the session block.
+    ///
+    /// Block ranges ending at the latest or pending block, or after the session
+    /// block, end at the session block instead, and ranges starting at the
+    /// latest or pending block start at the session block. Filters for only
+    /// the session block are converted to filter by its block hash.
+    pub fn logs(&self, mut filter: LogFilterBuilder<T>) -> LogFilterBuilder<T> {
+        if filter.block_hash.is_some() {
+            return filter;
+        }
+
+        let from_block = self.pin_block(filter.from_block);
+        let to_block = self.pin_block(filter.to_block);
+        let block = block::BlockId::Number(self.block_number);
+        if from_block == block && to_block == block {
+            filter.from_block = None;
+            filter.to_block = None;
+            filter.block_hash(self.block_hash)
+        } else {
+            filter.from_block(from_block).to_block(to_block)
+        }
+    }
+
+    /// Queries past logs matching the filter up to the session block. See
+    /// [`ReadSession::logs`] for more details.
+    pub async fn past_logs(&self, filter: LogFilterBuilder<T>) -> Result<Vec<Log>, ExecutionError> {
+        self.logs(filter).past_logs().await
+    }
+
+    /// Queries past contract events matching the event builder up to the
+    /// session block. See [`ReadSession::logs`] for more details.
+    pub async fn events<E: Tokenize>(
+        &self,
+        mut builder: EventBuilder<T, E>,
+    ) -> Result<Vec<Event<E>>, EventError> {
+        builder.filter = self.logs(builder.filter);
+        builder.query().await
+    }
+
+    /// Retrieves the ETH balance of an account at the session block.
+    pub async fn balance(&self, address: Address) -> Result<U256, ExecutionError> {
+        let balance = self
+            .web3
+            .eth()
+            .balance(address, Some(BlockNumber::Number(self.block_number)))
+            .await?;
+        Ok(balance)
+    }
+
+    /// Retrieves the code of an account at the session block.
+    pub async fn code(&self, address: Address) -> Result<Bytes, ExecutionError> {
+        let code = self
+            .web3
+            .eth()
+            .code(address, Some(BlockNumber::Number(self.block_number)))
+            .await?;
+        Ok(code)
+    }
+
+    fn pin_block(&self, block: Option<block::BlockId>) -> block::BlockId {
+        match block {
+            Some(block::BlockId::Tag(BlockTag::Earliest)) => U64::zero().into(),
+            Some(block::BlockId::Number(number)) => number.min(self.block_number).into(),
+            Some(block::BlockId::Tag(BlockTag::Latest))
+            | Some(block::BlockId::Tag(BlockTag::Pending))
+            | None => self.block_number.into(),
+            // NOTE: Blocks by hash and the safe and finalized tags are only
+            //   resolved to their numbers when the logs are queried.
+            Some(block) => block,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::MethodBuilder;
+    use crate::test::prelude::*;
+    use ethcontract_common::abi::{Function, Param, ParamType};
+    use web3::types::Block;
+
+    fn session(transport: &mut TestTransport) -> ReadSession<TestTransport> {
+        let web3 = Web3::new(transport.clone());
+        transport.add_response(json!(Block::<H256> {
+            hash: Some(H256::repeat_byte(0x42)),
+            number: Some(100.into()),
+            ..Default::default()
+        }));
+        let session = ReadSession::new(web3).immediate().expect("session failed");
+        transport.assert_request("eth_getBlockByNumber", &[json!("latest"), json!(false)]);
+        session
+    }
+
+    #[test]
+    fn session_pins_calls_to_block_hash() {
+        let mut transport = TestTransport::new();
+        let session = session(&mut transport);
+        assert_eq!(session.block_number(), 100.into());
+
+        #[allow(deprecated)]
+        let function = Function {
+            name: "test".to_owned(),
+            inputs: Vec::new(),
+            outputs: vec![Param {
+                name: "".to_owned(),
+                kind: ParamType::Uint(256),
+            }],
+            constant: false,
+            state_mutability: Default::default(),
+        };
+        let address = Address::repeat_byte(1);
+        let data = Bytes(function.encode_input(&[]).unwrap());
+        let method =
+            MethodBuilder::<_, U256>::new(session.web3().clone(), function, address, data.clone())
+                .view();
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        ));
+        let result = session.call(method).immediate().expect("call failed");
+
+        assert_eq!(result, 42.into());
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({ "to": address, "data": data }),
+                json!({ "blockHash": H256::repeat_byte(0x42) }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn session_pins_log_queries() {
+        let mut transport = TestTransport::new();
+        let session = session(&mut transport);
+        let web3 = session.web3().clone();
+
+        let filter = session.logs(LogFilterBuilder::new(web3.clone()).from_block(10));
+        assert_eq!(filter.from_block, Some(10.into()));
+        assert_eq!(filter.to_block, Some(100.into()));
+        assert_eq!(filter.block_hash, None);
+
+        let filter = session.logs(
+            LogFilterBuilder::new(web3.clone())
+                .from_block(BlockNumber::Earliest)
+                .to_block(1000),
+        );
+        assert_eq!(filter.from_block, Some(0.into()));
+        assert_eq!(filter.to_block, Some(100.into()));
+
+        transport.add_response(json!([]));
+        let logs = session
+            .past_logs(LogFilterBuilder::new(web3))
+            .immediate()
+            .expect("logs failed");
+
+        assert!(logs.is_empty());
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "blockHash": H256::repeat_byte(0x42) })],
+        );
+        transport.assert_no_more_requests();
+    }
+}
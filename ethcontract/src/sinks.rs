@@ -0,0 +1,522 @@
+//! Adapters for writing decoded contract events into databases and other
+//! sinks, so that event indexers don't need to rewrite the glue between event
+//! streams and their storage.
+//!
+//! Events are mapped to [`EventRow`]s, whose columns are the fields of the
+//! event data serialized with `serde`, and written in batches to a [`Sink`]
+//! with [`write_events`]. Each batch is written before more events are pulled
+//! from the stream, so that a slow sink applies backpressure to the stream
+//! instead of buffering events in memory:
+//!
+//! ```ignore
+//! let sink = SqlSink::new(pool, "transfers", &["from", "to", "value"]);
+//! let events = token.events().transfer().stream();
+//! sinks::write_events(events, &sink, SinkOptions::default()).await?;
+//! ```
+//!
+//! The [`SqlSink`] writes rows to a SQL table with any database library
+//! implementing [`SqlExecutor`], for example an `sqlx` connection pool.
+//!
+//! This module is only available with the `sinks` feature.
+
+use crate::contract::{Event, EventId, EventStatus, StreamEvent};
+use crate::errors::{EventError, SinkError};
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::{Stream, StreamExt as _};
+use futures_timer::Delay;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::time::Duration;
+use web3::types::H256;
+
+/// A decoded event mapped to a row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventRow {
+    /// The identifier of the event.
+    pub id: EventId,
+    /// The hash of the transaction that emitted the event.
+    pub transaction_hash: H256,
+    /// Whether the event was removed by a re-org, in which case the row
+    /// should be deleted from the sink.
+    pub removed: bool,
+    /// The columns of the event data. Event data that serializes to an
+    /// object, such as event structs, has one column per field, and any other
+    /// event data has a single `value` column.
+    pub columns: Map<String, Value>,
+}
+
+impl EventRow {
+    /// Maps an event to a row. Returns an error for pending events, which
+    /// have no identifier.
+    pub fn from_event<T: Serialize>(event: &Event<T>) -> Result<Self, SinkError> {
+        EventRow::new(event, &event.data, false)
+    }
+
+    /// Maps an event from an event stream to a row, which is marked as
+    /// removed for events that were removed by a re-org.
+    pub fn from_stream_event<T: Serialize>(event: &StreamEvent<T>) -> Result<Self, SinkError> {
+        EventRow::new(event, event.inner_data(), event.is_removed())
+    }
+
+    fn new<T, D: Serialize>(event: &Event<T>, data: &D, removed: bool) -> Result<Self, SinkError> {
+        let meta = event.meta.as_ref().ok_or(SinkError::Pending)?;
+        let columns = match serde_json::to_value(data)? {
+            Value::Object(columns) => columns,
+            value => Some(("value".to_owned(), value)).into_iter().collect(),
+        };
+        Ok(EventRow {
+            id: meta.id(),
+            transaction_hash: meta.transaction_hash,
+            removed,
+            columns,
+        })
+    }
+}
+
+/// A destination for event rows, such as a database table.
+pub trait Sink: Send + Sync {
+    /// Writes a batch of rows in order. Rows that are marked as removed
+    /// should be deleted.
+    fn write<'a>(&'a self, rows: &'a [EventRow]) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+/// Options for writing events to a sink.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SinkOptions {
+    /// The maximum number of rows written in a single batch.
+    pub batch_size: usize,
+    /// The maximum amount of time that rows are buffered before they are
+    /// written, so that rows are written in time when events are infrequent.
+    /// If `None`, rows are only written once a batch is full or the stream
+    /// ends.
+    pub flush_interval: Option<Duration>,
+}
+
+impl Default for SinkOptions {
+    fn default() -> Self {
+        SinkOptions {
+            batch_size: 100,
+            flush_interval: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// Writes the events of an event stream to a sink in batches until the stream
+/// ends, returning the number of written rows. Events removed by re-orgs are
+/// written as removed rows.
+pub async fn write_events<S, T>(
+    events: S,
+    sink: &dyn Sink,
+    options: SinkOptions,
+) -> Result<usize, SinkError>
+where
+    S: Stream<Item = Result<StreamEvent<T>, EventError>>,
+    T: Serialize,
+{
+    let rows = events.map(|event| EventRow::from_stream_event(&event?));
+    write_rows(rows, sink, options).await
+}
+
+/// Writes a stream of rows to a sink in batches until the stream ends,
+/// returning the number of written rows.
+pub async fn write_rows<S>(
+    rows: S,
+    sink: &dyn Sink,
+    options: SinkOptions,
+) -> Result<usize, SinkError>
+where
+    S: Stream<Item = Result<EventRow, SinkError>>,
+{
+    futures::pin_mut!(rows);
+    let batch_size = options.batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut deadline = None;
+    let mut written = 0;
+
+    loop {
+        let next = match &mut deadline {
+            Some(deadline) => match future::select(rows.next(), deadline).await {
+                Either::Left((next, _)) => Some(next),
+                Either::Right(_) => None,
+            },
+            None => Some(rows.next().await),
+        };
+
+        let ended = matches!(next, Some(None));
+        let flush = match next {
+            Some(Some(row)) => {
+                batch.push(row?);
+                if batch.len() == 1 {
+                    deadline = options.flush_interval.map(Delay::new);
+                }
+                batch.len() >= batch_size
+            }
+            // The stream ended or the flush interval elapsed.
+            Some(None) | None => true,
+        };
+        if flush && !batch.is_empty() {
+            sink.write(&batch).await?;
+            written += batch.len();
+            batch.clear();
+            deadline = None;
+        }
+        if ended {
+            return Ok(written);
+        }
+    }
+}
+
+/// An executor of SQL statements with positional parameters, such as a
+/// connection or connection pool of an `sqlx` style database library. The
+/// parameters are JSON values that the executor binds to the statement.
+pub trait SqlExecutor: Send + Sync {
+    /// Executes a statement with the specified parameters.
+    fn execute<'a>(
+        &'a self,
+        statement: &'a str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+/// The syntax of positional parameters in SQL statements.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Placeholders {
+    /// Numbered parameters such as `$1`, used by PostgreSQL.
+    Numbered,
+    /// Question mark parameters, used by MySQL and SQLite.
+    QuestionMark,
+}
+
+/// The columns of the event metadata, which precede the event columns.
+const METADATA_COLUMNS: &[&str] = &[
+    "block_number",
+    "block_hash",
+    "transaction_index",
+    "log_index",
+    "transaction_hash",
+];
+
+/// A sink that writes rows to a SQL table, inserting the rows of a batch with
+/// a single `INSERT` statement and deleting removed rows by their block hash
+/// and log index.
+///
+/// The table must have the `block_number`, `block_hash`, `transaction_index`,
+/// `log_index` and `transaction_hash` metadata columns followed by the event
+/// columns. Event columns that are missing from a row are inserted as `NULL`.
+/// Note that the table and column names are used in statements as is.
+#[derive(Clone, Debug)]
+pub struct SqlSink<E> {
+    executor: E,
+    table: String,
+    columns: Vec<String>,
+    placeholders: Placeholders,
+}
+
+impl<E: SqlExecutor> SqlSink<E> {
+    /// Creates a new sink that writes the specified event columns to a table.
+    /// Parameters use the numbered syntax by default.
+    pub fn new<I, S>(executor: E, table: impl Into<String>, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        SqlSink {
+            executor,
+            table: table.into(),
+            columns: columns
+                .into_iter()
+                .map(|column| column.as_ref().to_owned())
+                .collect(),
+            placeholders: Placeholders::Numbered,
+        }
+    }
+
+    /// Sets the syntax of statement parameters.
+    pub fn placeholders(mut self, value: Placeholders) -> Self {
+        self.placeholders = value;
+        self
+    }
+
+    /// Returns the statement for inserting the specified number of rows.
+    pub fn insert_statement(&self, rows: usize) -> String {
+        let columns = METADATA_COLUMNS
+            .iter()
+            .copied()
+            .chain(self.columns.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        let values = (0..rows)
+            .map(|row| {
+                let params = (0..columns.len())
+                    .map(|column| self.placeholder(row * columns.len() + column))
+                    .collect::<Vec<_>>();
+                format!("({})", params.join(", "))
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            self.table,
+            columns.join(", "),
+            values.join(", "),
+        )
+    }
+
+    /// Returns the statement for deleting a removed row.
+    pub fn delete_statement(&self) -> String {
+        format!(
+            "DELETE FROM {} WHERE block_hash = {} AND log_index = {}",
+            self.table,
+            self.placeholder(0),
+            self.placeholder(1),
+        )
+    }
+
+    fn placeholder(&self, index: usize) -> String {
+        match self.placeholders {
+            Placeholders::Numbered => format!("${}", index + 1),
+            Placeholders::QuestionMark => "?".to_owned(),
+        }
+    }
+
+    async fn insert(&self, rows: &[EventRow]) -> Result<(), SinkError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let params = rows
+            .iter()
+            .flat_map(|row| {
+                let metadata = vec![
+                    Value::from(row.id.block_number),
+                    Value::from(format!("{:?}", row.id.block_hash)),
+                    Value::from(row.id.transaction_index),
+                    Value::from(row.id.log_index),
+                    Value::from(format!("{:?}", row.transaction_hash)),
+                ];
+                let columns = self
+                    .columns
+                    .iter()
+                    .map(move |column| row.columns.get(column).cloned().unwrap_or(Value::Null));
+                metadata.into_iter().chain(columns)
+            })
+            .collect();
+        self.executor
+            .execute(&self.insert_statement(rows.len()), params)
+            .await
+    }
+
+    async fn write_rows(&self, rows: &[EventRow]) -> Result<(), SinkError> {
+        // Consecutive added rows are inserted together, while removed rows
+        // are deleted in order so that rows re-added after a re-org are kept.
+        let mut added = 0;
+        for (i, row) in rows.iter().enumerate() {
+            if row.removed {
+                self.insert(&rows[added..i]).await?;
+                added = i + 1;
+                let params = vec![
+                    Value::from(format!("{:?}", row.id.block_hash)),
+                    Value::from(row.id.log_index),
+                ];
+                self.executor
+                    .execute(&self.delete_statement(), params)
+                    .await?;
+            }
+        }
+        self.insert(&rows[added..]).await
+    }
+}
+
+impl<E: SqlExecutor> Sink for SqlSink<E> {
+    fn write<'a>(&'a self, rows: &'a [EventRow]) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(self.write_rows(rows))
+    }
+}
+
+impl<T: Serialize> Event<EventStatus<T>> {
+    /// Maps the event to a row for writing it to a sink.
+    pub fn to_row(&self) -> Result<EventRow, SinkError> {
+        EventRow::from_stream_event(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::EventMetadata;
+    use futures::executor::block_on;
+    use futures::stream;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Serialize)]
+    struct Transfer {
+        from: u64,
+        value: u64,
+    }
+
+    fn event(log_index: usize, removed: bool) -> StreamEvent<Transfer> {
+        let data = Transfer {
+            from: 1,
+            value: log_index as _,
+        };
+        Event {
+            data: if removed {
+                EventStatus::Removed(data)
+            } else {
+                EventStatus::Added(data)
+            },
+            meta: Some(EventMetadata {
+                block_hash: H256::repeat_byte(1),
+                block_number: 42,
+                transaction_hash: H256::repeat_byte(2),
+                transaction_index: 0,
+                log_index,
+                transaction_log_index: None,
+                log_type: None,
+            }),
+        }
+    }
+
+    #[derive(Default)]
+    struct TestSink(Mutex<Vec<Vec<EventRow>>>);
+
+    impl Sink for TestSink {
+        fn write<'a>(&'a self, rows: &'a [EventRow]) -> BoxFuture<'a, Result<(), SinkError>> {
+            self.0.lock().unwrap().push(rows.to_vec());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[derive(Default)]
+    struct TestExecutor(Mutex<Vec<(String, Vec<Value>)>>);
+
+    impl SqlExecutor for TestExecutor {
+        fn execute<'a>(
+            &'a self,
+            statement: &'a str,
+            params: Vec<Value>,
+        ) -> BoxFuture<'a, Result<(), SinkError>> {
+            self.0.lock().unwrap().push((statement.to_owned(), params));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn event_rows() {
+        let row = event(3, true).to_row().unwrap();
+        assert_eq!(row.id.log_index, 3);
+        assert!(row.removed);
+        assert_eq!(json!(row.columns), json!({ "from": 1, "value": 3 }));
+
+        let row = EventRow::from_event(&Event {
+            data: 7u64,
+            meta: event(0, false).meta,
+        })
+        .unwrap();
+        assert_eq!(json!(row.columns), json!({ "value": 7 }));
+
+        assert!(matches!(
+            EventRow::from_event(&Event {
+                data: (),
+                meta: None
+            }),
+            Err(SinkError::Pending)
+        ));
+    }
+
+    #[test]
+    fn writes_events_in_batches() {
+        let sink = TestSink::default();
+        let events = stream::iter((0..5).map(|i| Ok(event(i, false))));
+        let options = SinkOptions {
+            batch_size: 2,
+            flush_interval: None,
+        };
+
+        let written = block_on(write_events(events, &sink, options)).unwrap();
+        assert_eq!(written, 5);
+
+        let batches = sink.0.into_inner().unwrap();
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+    }
+
+    #[test]
+    fn flushes_rows_after_interval() {
+        let sink = TestSink::default();
+        let events = stream::iter(vec![Ok(event(0, false))]).chain(stream::pending());
+        let options = SinkOptions {
+            batch_size: 100,
+            flush_interval: Some(Duration::from_millis(10)),
+        };
+
+        block_on(future::select(
+            Box::pin(write_events(events, &sink, options)),
+            Delay::new(Duration::from_millis(500)),
+        ));
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sql_sink_statements() {
+        let sink = SqlSink::new(TestExecutor::default(), "transfers", &["from", "value"]);
+        assert_eq!(
+            sink.insert_statement(2),
+            "INSERT INTO transfers \
+             (block_number, block_hash, transaction_index, log_index, transaction_hash, from, value) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7), ($8, $9, $10, $11, $12, $13, $14)",
+        );
+        let sink = sink.placeholders(Placeholders::QuestionMark);
+        assert_eq!(
+            sink.delete_statement(),
+            "DELETE FROM transfers WHERE block_hash = ? AND log_index = ?",
+        );
+    }
+
+    #[test]
+    fn sql_sink_inserts_and_deletes_rows() {
+        let sink = SqlSink::new(TestExecutor::default(), "transfers", &["value", "to"]);
+        let rows = [
+            event(0, false),
+            event(1, false),
+            event(1, true),
+            event(2, false),
+        ]
+        .iter()
+        .map(|event| event.to_row().unwrap())
+        .collect::<Vec<_>>();
+        block_on(sink.write(&rows)).unwrap();
+
+        let block_hash = json!(format!("{:?}", H256::repeat_byte(1)));
+        let transaction_hash = json!(format!("{:?}", H256::repeat_byte(2)));
+        let metadata = |log_index: usize| {
+            vec![
+                json!(42),
+                block_hash.clone(),
+                json!(0),
+                json!(log_index),
+                transaction_hash.clone(),
+            ]
+        };
+        let statements = sink.executor.0.lock().unwrap().clone();
+        assert_eq!(
+            statements,
+            vec![
+                (
+                    sink.insert_statement(2),
+                    [metadata(0), vec![json!(0), Value::Null]]
+                        .iter()
+                        .chain(&[metadata(1), vec![json!(1), Value::Null]])
+                        .flatten()
+                        .cloned()
+                        .collect(),
+                ),
+                (sink.delete_statement(), vec![block_hash.clone(), json!(1)]),
+                (
+                    sink.insert_statement(1),
+                    [metadata(2), vec![json!(2), Value::Null]].concat(),
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Typed access to the storage slots of contract state variables.
+//!
+//! Contract bindings generated from artifacts containing a storage layout
+//! have a `storage` module with a function per state variable, which returns
+//! the [`StorageSlot`] of the variable, or of the element of a mapping or an
+//! array for the specified keys and indices. Storage slots allow reading
+//! state variables that have no getter, and constructing state overrides for
+//! simulating transactions:
+//!
+//! ```ignore
+//! let balance = storage::balances(owner).read(&web3, token, None).await?;
+//! let overrides = OverrideBuilder::new(token)
+//!     .set(storage::balances(owner), balance * 2)
+//!     .build()?;
+//! ```
+
+use crate::errors::ExecutionError;
+use crate::tokens::{Error as TokenError, Tokenize};
+use ethcontract_common::abi::Token;
+use ethcontract_common::storage;
+pub use ethcontract_common::storage::{array_slot, element_slot};
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use web3::api::Web3;
+use web3::types::{Address, BlockNumber, H256, U256};
+use web3::Transport;
+
+/// The storage slot of a state variable of value type `T`. Value types of
+/// less than 32 bytes may be packed with other variables into the same slot,
+/// in which case the variable is stored at a byte offset within the slot.
+pub struct StorageSlot<T> {
+    slot: U256,
+    offset: usize,
+    size: usize,
+    label: &'static str,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: Tokenize> StorageSlot<T> {
+    /// Creates a storage slot for a variable of `size` bytes stored at a byte
+    /// `offset` from the lower end of `slot`. The `label` is the canonical
+    /// name of the Solidity type of the variable, for example `uint128`.
+    pub fn new(slot: U256, offset: usize, size: usize, label: &'static str) -> Self {
+        StorageSlot {
+            slot,
+            offset,
+            size,
+            label,
+            value: PhantomData,
+        }
+    }
+
+    /// Returns the storage slot.
+    pub fn slot(&self) -> H256 {
+        let mut slot = H256::zero();
+        self.slot.to_big_endian(slot.as_bytes_mut());
+        slot
+    }
+
+    /// Returns the byte offset of the variable within the slot.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of bytes used by the variable.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Decodes the variable from the value of its storage slot.
+    pub fn decode(&self, word: H256) -> Result<T, TokenError> {
+        let (start, end) = self.range();
+        let token = storage::decode_value(self.label, &word.as_bytes()[start..end])
+            .ok_or(TokenError::TypeMismatch)?;
+        T::from_token(token)
+    }
+
+    /// Encodes the variable into the value of its storage slot, keeping the
+    /// other variables packed into the slot.
+    pub fn encode(&self, word: H256, value: T) -> Result<H256, TokenError> {
+        let token = value.into_token();
+        let bytes = storage::encode_value(&token, self.size).ok_or(match token {
+            Token::Uint(_) | Token::Int(_) => TokenError::IntegerMismatch,
+            Token::FixedBytes(_) => TokenError::FixedBytesLengthsMismatch,
+            _ => TokenError::TypeMismatch,
+        })?;
+        let (start, end) = self.range();
+        let mut word = word;
+        word.as_bytes_mut()[start..end].copy_from_slice(&bytes);
+        Ok(word)
+    }
+
+    /// Reads the variable from the storage of the contract at `address` in
+    /// the specified block, or in the latest block if `None`.
+    pub async fn read<Tr: Transport>(
+        &self,
+        web3: &Web3<Tr>,
+        address: Address,
+        block: Option<BlockNumber>,
+    ) -> Result<T, ExecutionError> {
+        let word = web3.eth().storage(address, self.slot, block).await?;
+        Ok(self.decode(word)?)
+    }
+
+    fn range(&self) -> (usize, usize) {
+        let end = 32 - self.offset;
+        (end - self.size, end)
+    }
+}
+
+impl<T> Clone for StorageSlot<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for StorageSlot<T> {}
+
+impl<T> Debug for StorageSlot<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("StorageSlot")
+            .field("slot", &self.slot)
+            .field("offset", &self.offset)
+            .field("size", &self.size)
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for StorageSlot<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.slot == other.slot
+            && self.offset == other.offset
+            && self.size == other.size
+            && self.label == other.label
+    }
+}
+
+impl<T> Eq for StorageSlot<T> {}
+
+/// Computes the storage slot of the value of a mapping stored at `slot` for
+/// the specified key.
+pub fn mapping_slot(slot: U256, key: impl Tokenize) -> U256 {
+    storage::mapping_slot(slot, &key.into_token())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    #[test]
+    fn packed_values() {
+        let paused = StorageSlot::<bool>::new(0.into(), 20, 1, "bool");
+        let owner = StorageSlot::<Address>::new(0.into(), 0, 20, "address");
+        let delta = StorageSlot::<i64>::new(0.into(), 21, 8, "int64");
+
+        let word = owner.encode(H256::zero(), Address::repeat_byte(1)).unwrap();
+        let word = paused.encode(word, true).unwrap();
+        let word = delta.encode(word, -2).unwrap();
+
+        let mut expected = [0; 32];
+        expected[3..11].copy_from_slice(&(-2i64).to_be_bytes());
+        expected[11] = 1;
+        expected[12..].copy_from_slice(&[1; 20]);
+        assert_eq!(word, H256(expected));
+
+        assert_eq!(owner.decode(word).unwrap(), Address::repeat_byte(1));
+        assert!(paused.decode(word).unwrap());
+        assert_eq!(delta.decode(word).unwrap(), -2);
+
+        let small = StorageSlot::<u32>::new(1.into(), 0, 3, "uint24");
+        assert!(matches!(
+            small.encode(H256::zero(), 1 << 24),
+            Err(TokenError::IntegerMismatch)
+        ));
+    }
+
+    #[test]
+    fn read_mapping_value() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let token = addr!("0x0102030405060708091011121314151617181920");
+        let owner = Address::repeat_byte(0x42);
+
+        let slot = StorageSlot::<U256>::new(mapping_slot(2.into(), owner), 0, 32, "uint256");
+        transport.add_response(json!(H256::from_low_u64_be(1337)));
+        let balance = slot
+            .read(&web3, token, Some(BlockNumber::Number(7.into())))
+            .immediate()
+            .unwrap();
+
+        transport.assert_request(
+            "eth_getStorageAt",
+            &[json!(token), json!(slot.slot), json!("0x7")],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(balance, U256::from(1337));
+    }
+}
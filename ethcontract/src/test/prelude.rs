@@ -4,7 +4,6 @@ pub use crate::test::transport::TestTransport;
 use futures::future::FutureExt;
 pub use serde_json::json;
 use std::future::Future;
-pub use web3::api::Web3;
 
 /// An extension future to wait for a future.
 pub trait FutureTestExt: Future {
@@ -1,12 +1,14 @@
 //! Implementation of a transport for testing purposes. This is largely based on
 //! the `rust-web3` `TestTransport` type with some modifications.
 
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use jsonrpc_core::{Call, Value};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::{Arc, Mutex};
+use web3::api::SubscriptionId;
 use web3::futures::future::{self, Ready};
 use web3::helpers;
-use web3::{error::Error, BatchTransport};
+use web3::{error::Error, BatchTransport, DuplexTransport};
 use web3::{RequestId, Transport};
 
 /// Type alias for request method and value pairs
@@ -16,7 +18,8 @@ type Requests = Vec<(String, Vec<Value>)>;
 struct Inner {
     asserted: usize,
     requests: Requests,
-    responses: VecDeque<Value>,
+    responses: VecDeque<Result<Value, Error>>,
+    subscriptions: BTreeMap<SubscriptionId, UnboundedSender<Value>>,
 }
 
 /// Test transport
@@ -38,7 +41,7 @@ impl Transport for TestTransport {
     fn send(&self, id: RequestId, request: Call) -> Self::Out {
         let mut inner = self.inner.lock().unwrap();
         match inner.responses.pop_front() {
-            Some(response) => future::ok(response),
+            Some(response) => future::ready(response),
             None => {
                 println!("Unexpected request (id: {:?}): {:?}", id, request);
                 future::err(Error::Unreachable)
@@ -78,6 +81,23 @@ impl BatchTransport for TestTransport {
     }
 }
 
+impl DuplexTransport for TestTransport {
+    type NotificationStream = UnboundedReceiver<Value>;
+
+    fn subscribe(&self, id: SubscriptionId) -> Result<Self::NotificationStream, Error> {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscriptions.insert(id, sender);
+        Ok(receiver)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscriptions.remove(&id);
+        Ok(())
+    }
+}
+
 impl TestTransport {
     /// Create a new test transport instance.
     pub fn new() -> Self {
@@ -87,7 +107,32 @@ impl TestTransport {
     /// Add a response to an eventual request.
     pub fn add_response(&mut self, value: Value) {
         let mut inner = self.inner.lock().unwrap();
-        inner.responses.push_back(value);
+        inner.responses.push_back(Ok(value));
+    }
+
+    /// Add an error response to an eventual request.
+    pub fn add_error(&mut self, error: Error) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.responses.push_back(Err(error));
+    }
+
+    /// Send a notification to a subscription.
+    pub fn notify(&self, id: &str, value: Value) {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .subscriptions
+            .get(&SubscriptionId::from(id.to_owned()))
+            .expect("Expected subscription.")
+            .unbounded_send(value)
+            .expect("Subscription closed.");
+    }
+
+    /// Returns whether a subscription is open.
+    pub fn is_subscribed(&self, id: &str) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .subscriptions
+            .contains_key(&SubscriptionId::from(id.to_owned()))
     }
 
     /// Assert that a request was made.
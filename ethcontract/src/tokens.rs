@@ -14,10 +14,13 @@
 
 use crate::I256;
 use arrayvec::ArrayVec;
-use ethcontract_common::{abi::Token, TransactionHash};
-use serde::{Deserialize, Serialize};
+use ethcontract_common::abi::{self, Token};
+use ethcontract_common::{hash, TransactionHash};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::convert::TryInto;
-use web3::types::{Address, U256};
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use web3::types::{Address, H256, U256};
 
 /// A tokenization related error.
 #[derive(Debug, thiserror::Error)]
@@ -50,6 +53,35 @@ pub trait Tokenize {
     fn into_token(self) -> Token;
 }
 
+/// A value decoded in lenient mode, along with the data following its ABI
+/// encoding that could not be decoded. This happens for example when decoding
+/// the return data of a method or the data of an event from a newer version of
+/// a contract that added trailing outputs or event fields.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Lenient<T> {
+    /// The decoded value.
+    pub value: T,
+    /// The remaining data following the standard ABI encoding of the value.
+    pub remainder: Vec<u8>,
+}
+
+impl<T> Lenient<T> {
+    /// Returns `true` if all of the data was decoded.
+    pub fn is_exact(&self) -> bool {
+        self.remainder.is_empty()
+    }
+
+    /// Creates a lenient value from the tokens decoded from the specified
+    /// data, assuming that the data uses the standard ABI encoding.
+    pub(crate) fn from_decoded(value: T, tokens: &[Token], data: &[u8]) -> Self {
+        let len = abi::encode(tokens).len();
+        Lenient {
+            value,
+            remainder: data.get(len..).unwrap_or_default().to_vec(),
+        }
+    }
+}
+
 /// Wrapper around Vec<u8> and [u8; N] representing Token::{Bytes, FixedBytes}. Distinguishes a list
 /// of u8 from bytes.
 #[derive(
@@ -57,6 +89,160 @@ pub trait Tokenize {
 )]
 pub struct Bytes<T>(pub T);
 
+/// The Keccak-256 hash of the value of an indexed event parameter with a
+/// dynamic type such as `string`, `bytes`, an array or a tuple. Logs only
+/// contain the hash of these values in their topics, so the original value of
+/// type `T` cannot be recovered, but the hash can be compared against
+/// candidate values.
+///
+/// ```
+/// # use ethcontract::tokens::Hash;
+/// let hash = Hash::of("ethcontract".to_owned());
+/// assert!(hash == "ethcontract".to_owned());
+/// assert!(hash != "web3".to_owned());
+/// ```
+pub struct Hash<T> {
+    hash: H256,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> Hash<T> {
+    /// Creates a value hash from its topic.
+    pub fn from_hash(hash: H256) -> Self {
+        Hash {
+            hash,
+            value: PhantomData,
+        }
+    }
+
+    /// Returns the hash as it appears in log topics.
+    pub fn hash(&self) -> H256 {
+        self.hash
+    }
+
+    /// Computes the hash of a value, which is the Keccak-256 hash of its
+    /// in-place ABI encoding as specified for indexed event parameters.
+    pub fn of(value: T) -> Self
+    where
+        T: Tokenize,
+    {
+        Self::from_hash(topic_hash(&value.into_token()))
+    }
+
+    /// Returns `true` if this is the hash of the specified value.
+    pub fn matches(&self, value: T) -> bool
+    where
+        T: Tokenize,
+    {
+        *self == Self::of(value)
+    }
+}
+
+/// Computes the topic of an indexed event parameter value with a dynamic
+/// type. Strings and bytes are hashed as is, while arrays and tuples are
+/// hashed with their elements padded to 32 bytes and without length prefixes.
+fn topic_hash(token: &Token) -> H256 {
+    fn encode(token: &Token, out: &mut Vec<u8>) {
+        match token {
+            Token::String(value) => pad(value.as_bytes(), out),
+            Token::Bytes(value) => pad(value, out),
+            Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+                for token in tokens {
+                    encode(token, out);
+                }
+            }
+            token => out.extend(abi::encode(std::slice::from_ref(token))),
+        }
+    }
+
+    fn pad(bytes: &[u8], out: &mut Vec<u8>) {
+        out.extend_from_slice(bytes);
+        out.resize(out.len() + (32 - bytes.len() % 32) % 32, 0);
+    }
+
+    let hash = match token {
+        Token::String(value) => hash::keccak256(value),
+        Token::Bytes(value) => hash::keccak256(value),
+        token => {
+            let mut encoded = Vec::new();
+            encode(token, &mut encoded);
+            hash::keccak256(encoded)
+        }
+    };
+    H256(hash)
+}
+
+impl<T> Clone for Hash<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Hash<T> {}
+
+impl<T> Debug for Hash<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("Hash").field(&self.hash).finish()
+    }
+}
+
+impl<T> Default for Hash<T> {
+    fn default() -> Self {
+        Self::from_hash(H256::zero())
+    }
+}
+
+impl<T> PartialEq for Hash<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl<T> Eq for Hash<T> {}
+
+impl<T> PartialEq<T> for Hash<T>
+where
+    T: Tokenize + Clone,
+{
+    fn eq(&self, other: &T) -> bool {
+        self.matches(other.clone())
+    }
+}
+
+impl<T> std::hash::Hash for Hash<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&self.hash, state);
+    }
+}
+
+impl<T> From<H256> for Hash<T> {
+    fn from(hash: H256) -> Self {
+        Self::from_hash(hash)
+    }
+}
+
+impl<T> Serialize for Hash<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.hash.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Hash<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        H256::deserialize(deserializer).map(Self::from_hash)
+    }
+}
+
+impl<T> Tokenize for Hash<T> {
+    fn from_token(token: Token) -> Result<Self, Error> {
+        H256::from_token(token).map(Self::from_hash)
+    }
+
+    fn into_token(self) -> Token {
+        self.hash.into_token()
+    }
+}
+
 impl Tokenize for Token {
     fn from_token(token: Token) -> Result<Self, Error>
     where
@@ -333,6 +519,48 @@ mod tests {
         assert_eq!(value, T::from_token(value.clone().into_token()).unwrap());
     }
 
+    #[test]
+    fn lenient_remainder() {
+        let tokens = [Token::Uint(1.into()), Token::String("a".to_owned())];
+        let mut data = abi::encode(&tokens);
+        let exact = Lenient::from_decoded((), &tokens, &data);
+        assert!(exact.is_exact());
+
+        data.extend_from_slice(&[0xff; 32]);
+        let lenient = Lenient::from_decoded((), &tokens, &data);
+        assert_eq!(lenient.remainder, vec![0xff; 32]);
+    }
+
+    #[test]
+    fn indexed_value_hashes() {
+        let hash = Hash::of("hello".to_owned());
+        assert_eq!(
+            hash.hash(),
+            "1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8"
+                .parse()
+                .unwrap(),
+        );
+        assert!(hash == "hello".to_owned());
+        assert!(!hash.matches("world".to_owned()));
+        assert_eq!(Hash::<String>::from_token(hash.into_token()).unwrap(), hash);
+
+        assert_eq!(
+            Hash::of(vec![U256::from(1), U256::from(2)]).hash(),
+            H256(hash::keccak256(abi::encode(&[
+                Token::Uint(1.into()),
+                Token::Uint(2.into()),
+            ]))),
+        );
+
+        let mut padded = [0; 64];
+        padded[0] = b'a';
+        padded[32..35].copy_from_slice(b"bcd");
+        assert_eq!(
+            Hash::of(("a".to_owned(), Bytes(b"bcd".to_vec()))).hash(),
+            H256(hash::keccak256(padded)),
+        );
+    }
+
     #[test]
     fn single_tokenize_roundtrip() {
         assert_single_tokenize_roundtrip(u8::MIN);
@@ -1,20 +1,71 @@
 //! Implementation for setting up, signing, estimating gas and sending
 //! transactions on the Ethereum network.
 
+mod authorization;
+pub mod blob;
 mod build;
 pub mod confirm;
+mod extension;
 pub mod gas_price;
+mod guard;
+mod hook;
+pub mod journal;
+mod kms;
+mod local;
+mod nonce;
+mod overrides;
+mod pending;
+mod preflight;
+mod preview;
+mod receipt;
+mod remote;
+mod rlp;
 mod send;
-
+mod signer;
+mod simulate;
+mod summary;
+
+pub use self::authorization::{
+    Authorization, AuthorizationList, SetCodeTransaction, SignedAuthorization, SET_CODE_TX_TYPE,
+};
+pub use self::blob::BlobTransactionBuilder;
 pub use self::build::Transaction;
 use self::confirm::ConfirmParams;
-pub use self::gas_price::GasPrice;
+pub use self::extension::{ExtraFields, FeeCurrency, TransactionExtension};
+pub use self::gas_price::{GasPrice, PriorityFee, PriorityFeeSource};
+pub use self::guard::{GuardDecision, TransactionGuard};
+pub use self::hook::{MethodCall, TransactionHook, TransactionInfo};
+pub use self::journal::TxJournal;
+pub use self::kms::{KmsClient, KmsSigner};
+pub use self::local::{LocalSimulation, SimulationStatus};
+pub use self::nonce::NonceGaps;
+pub use self::overrides::{AccountOverride, OverrideBuilder, StateOverrides};
+pub use self::pending::PendingTransaction;
+pub use self::preview::TransactionPreview;
+pub use self::receipt::ReceiptDetails;
+pub use self::remote::RemoteSigner;
 pub use self::send::TransactionResult;
+pub use self::signer::{EcdsaSignature, Signer};
+pub use self::simulate::{
+    AccountChanges, StateChanges, StorageLayout, StorageType, StorageVariable,
+    StorageVariableChange, ValueChange,
+};
+pub use self::summary::TransactionSummary;
+use crate::block::{self, BlockTag};
 use crate::errors::ExecutionError;
+use crate::rpc::TraceBackend;
 use crate::secret::{Password, PrivateKey};
+use ethcontract_common::abi::Function;
+use futures::future::{self, Either};
+use futures_timer::Delay;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 use web3::api::Web3;
-use web3::types::{Address, Bytes, CallRequest, TransactionCondition, U256};
-use web3::Transport;
+use web3::types::{
+    Address, BlockId, BlockNumber, Bytes, CallRequest, TransactionCondition, H256, U256,
+};
+use web3::{helpers, Transport};
 
 /// The account type used for signing the transaction.
 #[derive(Clone, Debug)]
@@ -26,6 +77,13 @@ pub enum Account {
     /// Do offline signing with private key and optionally specify chain ID. If
     /// no chain ID is specified, then it will default to the network ID.
     Offline(PrivateKey, Option<u64>),
+    /// Delegate signing to an external signing service. The nonce and gas
+    /// price are resolved before signing.
+    Remote(RemoteSigner),
+    /// Sign the transaction hash with an external signer, such as a
+    /// `KmsSigner`, and optionally specify chain ID. If no chain ID is
+    /// specified, then it will default to the chain ID of the node.
+    Signer(Arc<dyn Signer>, Option<u64>),
 }
 
 impl Account {
@@ -35,8 +93,77 @@ impl Account {
             Account::Local(address, _) => *address,
             Account::Locked(address, _, _) => *address,
             Account::Offline(key, _) => key.public_address(),
+            Account::Remote(signer) => signer.address(),
+            Account::Signer(signer, _) => signer.address(),
         }
     }
+
+    /// Retrieves the ETH balance of the account in the latest block.
+    pub async fn balance<T: Transport>(&self, web3: &Web3<T>) -> Result<U256, ExecutionError> {
+        self.balance_at(web3, BlockTag::Latest).await
+    }
+
+    /// Retrieves the ETH balance of the account in the specified block.
+    pub async fn balance_at<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        block: impl Into<block::BlockId>,
+    ) -> Result<U256, ExecutionError> {
+        let block = match block.into() {
+            block::BlockId::Hash(hash) => web3
+                .eth()
+                .block(BlockId::Hash(hash))
+                .await?
+                .and_then(|block| block.number)
+                .ok_or(ExecutionError::MissingBlock)?
+                .into(),
+            block => block,
+        };
+        let balance = web3
+            .transport()
+            .execute(
+                "eth_getBalance",
+                vec![
+                    helpers::serialize(&self.address()),
+                    helpers::serialize(&block),
+                ],
+            )
+            .await?;
+        Ok(helpers::decode(balance)?)
+    }
+
+    /// Signs EIP-712 typed data with the account, returning the 65-byte
+    /// signature. Accounts with a private key or an external signer sign the
+    /// `hash` of the typed data directly, while the node or the remote signer
+    /// sign the JSON `typed_data` with `eth_signTypedData_v4`.
+    pub(crate) async fn sign_typed_data<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        typed_data: serde_json::Value,
+        hash: H256,
+    ) -> Result<Bytes, ExecutionError> {
+        let signature = match self {
+            Account::Local(address, _) => {
+                let signature = web3
+                    .transport()
+                    .execute(
+                        "eth_signTypedData_v4",
+                        vec![helpers::serialize(address), typed_data],
+                    )
+                    .await?;
+                helpers::decode(signature)?
+            }
+            Account::Locked(address, _, _) => {
+                return Err(ExecutionError::TypedDataUnsupported(*address))
+            }
+            Account::Offline(key, _) => Bytes(signer::sign_hash_recoverable(key, hash).await?),
+            Account::Remote(signer) => signer.sign_typed_data(typed_data).await?,
+            Account::Signer(signer, _) => {
+                Bytes(signer::sign_hash_recoverable(signer.as_ref(), hash).await?)
+            }
+        };
+        Ok(signature)
+    }
 }
 
 /// The condition on which a transaction's `SendFuture` gets resolved.
@@ -86,9 +213,42 @@ pub struct TransactionBuilder<T: Transport> {
     /// Optional nonce to use. Defaults to the signing account's current
     /// transaction count.
     pub nonce: Option<U256>,
+    /// Optional block tag for looking up the transaction count of the sender
+    /// when no nonce is specified. Defaults to the block used by the signing
+    /// strategy.
+    pub nonce_tag: Option<BlockTag>,
     /// Optional resolve conditions. Defaults to waiting the transaction to be
     /// mined without any extra confirmation blocks.
     pub resolve: Option<ResolveCondition>,
+    /// Optional timeout for sending the transaction, including waiting for
+    /// its confirmation. Defaults to no timeout.
+    pub timeout: Option<Duration>,
+    /// Optional expected block time of the chain, from which the default
+    /// poll intervals for confirming the transaction are derived.
+    pub block_time: Option<Duration>,
+    /// Optional hook that is notified before the transaction is signed and
+    /// after it is sent.
+    pub hook: Option<Arc<dyn TransactionHook>>,
+    /// Optional guard that is consulted before the transaction is signed.
+    pub guard: Option<Arc<dyn TransactionGuard>>,
+    /// Optional journal that records the transaction before it is broadcast
+    /// and tracks its lifecycle.
+    pub journal: Option<TxJournal>,
+    /// Whether to check the transaction against the latest block before it
+    /// is signed. Defaults to no preflight checks.
+    pub preflight: bool,
+    /// Whether to check that the sender can pay for the transaction before it
+    /// is signed. Defaults to no balance check.
+    pub check_balance: bool,
+    /// Extensions with additional transaction fields for chains with
+    /// nonstandard transaction envelopes.
+    pub extensions: Vec<Arc<dyn TransactionExtension>>,
+    /// Signed EIP-7702 authorizations that make the transaction a set code
+    /// transaction. Defaults to no authorizations.
+    pub authorization_list: Vec<SignedAuthorization>,
+    /// The contract function that is being called by the transaction, used
+    /// for decoding the method call for transaction hooks.
+    pub(crate) function: Option<Function>,
 }
 
 impl<T: Transport> TransactionBuilder<T> {
@@ -103,7 +263,18 @@ impl<T: Transport> TransactionBuilder<T> {
             value: None,
             data: None,
             nonce: None,
+            nonce_tag: None,
             resolve: None,
+            timeout: None,
+            block_time: None,
+            hook: None,
+            guard: None,
+            journal: None,
+            preflight: false,
+            check_balance: false,
+            extensions: Vec::new(),
+            authorization_list: Vec::new(),
+            function: None,
         }
     }
 
@@ -156,6 +327,14 @@ impl<T: Transport> TransactionBuilder<T> {
         self
     }
 
+    /// Specify the block tag for looking up the nonce of the sender, if no
+    /// nonce is specified. The nonce is only looked up for transactions with
+    /// a sender, otherwise the node chooses the nonce.
+    pub fn nonce_tag(mut self, value: BlockTag) -> Self {
+        self.nonce_tag = Some(value);
+        self
+    }
+
     /// Specify the resolve condition, if not specified will default to waiting
     /// for the transaction to be mined (but not confirmed by any extra blocks).
     pub fn resolve(mut self, value: ResolveCondition) -> Self {
@@ -180,28 +359,259 @@ impl<T: Transport> TransactionBuilder<T> {
         self
     }
 
+    /// Specify a timeout for sending the transaction. The timeout includes
+    /// building and signing the transaction as well as waiting for its
+    /// confirmation.
+    ///
+    /// If the timeout elapses before the transaction is sent, an
+    /// `ExecutionError::Timeout` error is returned. If it elapses while waiting
+    /// for the transaction to confirm, an `ExecutionError::ConfirmTimeout`
+    /// error is returned instead so that the transaction hash is not lost.
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.timeout = Some(value);
+        self
+    }
+
+    /// Specify the expected block time of the chain. When waiting for the
+    /// transaction to be confirmed, the poll intervals are derived from it
+    /// unless they were explicitly configured with the resolve condition, so
+    /// that the node is polled often enough for chains with fast blocks and
+    /// not needlessly often for chains with slow blocks.
+    pub fn block_time(mut self, value: Duration) -> Self {
+        self.block_time = Some(value);
+        self
+    }
+
+    /// Specify a hook that is notified with the resolved transaction
+    /// parameters just before the transaction is signed and after it is sent.
+    pub fn hook(mut self, value: Arc<dyn TransactionHook>) -> Self {
+        self.hook = Some(value);
+        self
+    }
+
+    /// Specify a guard that is consulted with the resolved transaction
+    /// parameters before the transaction is signed, and that can reject it.
+    pub fn guard(mut self, value: Arc<dyn TransactionGuard>) -> Self {
+        self.guard = Some(value);
+        self
+    }
+
+    /// Specify a journal that records the transaction before it is broadcast
+    /// and when it is mined, so that it can be recovered after a crash with
+    /// [`TxJournal::recover`].
+    pub fn journal(mut self, value: TxJournal) -> Self {
+        self.journal = Some(value);
+        self
+    }
+
+    /// Specify whether to check the resolved transaction against the latest
+    /// block before it is signed. When enabled, transactions with a gas limit
+    /// above the block gas limit or a gas price below the base fee of the
+    /// next block fail with an `ExecutionError::Preflight` error instead of
+    /// being rejected by the node or never being mined.
+    pub fn preflight(mut self, value: bool) -> Self {
+        self.preflight = value;
+        self
+    }
+
+    /// Specify whether to check that the balance of the sender covers the
+    /// value and maximum fees of the resolved transaction before it is
+    /// signed. When enabled, transactions the sender can not pay for fail with
+    /// an `ExecutionError::Preflight` error with the required and available
+    /// amounts, instead of the node's insufficient funds error.
+    pub fn check_balance(mut self, value: bool) -> Self {
+        self.check_balance = value;
+        self
+    }
+
+    /// Specify an extension that adds nonstandard fields to the transaction,
+    /// for chains with custom transaction envelopes. Fields of later
+    /// extensions take precedence over fields of earlier ones.
+    ///
+    /// Note that extended transactions must be signed by the node or by a
+    /// remote signer, and that the extension fields are only included when
+    /// the transaction is sent or its gas is estimated, and not in the
+    /// `Transaction::Request` returned by `build`.
+    pub fn extension(mut self, value: impl TransactionExtension + 'static) -> Self {
+        self.extensions.push(Arc::new(value));
+        self
+    }
+
+    /// Specify the ERC20 token to pay transaction fees with on chains that
+    /// support the Celo `feeCurrency` transaction field. This is a utility
+    /// method for specifying a `FeeCurrency` extension.
+    pub fn fee_currency(self, token: Address) -> Self {
+        self.extension(FeeCurrency(token))
+    }
+
+    /// Specify a signed EIP-7702 authorization to include in the transaction,
+    /// which makes it a set code transaction that delegates the code of the
+    /// authorizing account to a contract. Set code transactions must have a
+    /// receiver.
+    ///
+    /// Transactions signed with a private key or an external signer are
+    /// signed as type 4 transactions by the runtime, and the authorization
+    /// list is sent as an extension field for all other accounts.
+    pub fn authorization(mut self, value: SignedAuthorization) -> Self {
+        self.authorization_list.push(value);
+        self
+    }
+
+    /// Returns the extensions of transactions that are signed by the node or
+    /// by a remote signer, including the authorization list.
+    fn node_extensions(&self) -> Vec<Arc<dyn TransactionExtension>> {
+        let mut extensions = Vec::with_capacity(self.extensions.len() + 1);
+        if !self.authorization_list.is_empty() {
+            extensions.push(Arc::new(AuthorizationList(self.authorization_list.clone())) as _);
+        }
+        extensions.extend(self.extensions.iter().cloned());
+        extensions
+    }
+
     /// Estimate the gas required for this transaction.
     pub async fn estimate_gas(self) -> Result<U256, ExecutionError> {
+        self.estimate_gas_with(None, None).await
+    }
+
+    /// Estimate the gas required for this transaction at the specified block
+    /// and with the specified state overrides. This allows estimating the gas
+    /// of transactions that depend on state changes that have not yet been
+    /// mined, such as a pending token approval.
+    ///
+    /// Note that state overrides for gas estimation are not supported by all
+    /// nodes.
+    pub async fn estimate_gas_with(
+        self,
+        block: Option<BlockId>,
+        overrides: Option<&StateOverrides>,
+    ) -> Result<U256, ExecutionError> {
+        let from = self.from.as_ref().map(|account| account.address());
+        let gas_price = self.gas_price.and_then(|gas_price| gas_price.value());
+
+        build::estimate_gas_with(
+            &self.web3,
+            CallRequest {
+                from,
+                to: self.to,
+                gas: None,
+                gas_price,
+                value: self.value,
+                data: self.data.clone(),
+                transaction_type: None,
+                access_list: None,
+            },
+            &self.node_extensions(),
+            block,
+            overrides,
+        )
+        .await
+    }
+
+    /// Simulate this transaction at the specified block, or the latest block
+    /// if none is specified, and return the balance, nonce, code and storage
+    /// changes it would make without sending it. This allows inspecting
+    /// exactly what a transaction changes, for example for risk checks.
+    ///
+    /// The simulation uses `trace_call` with a `stateDiff` trace for the
+    /// `Parity` backend, and `debug_traceCall` with the `prestateTracer` in
+    /// diff mode for the `Geth` backend. Transaction extensions are not
+    /// included in the simulated call.
+    pub async fn simulate_state_changes(
+        self,
+        backend: TraceBackend,
+        block: Option<BlockNumber>,
+    ) -> Result<StateChanges, ExecutionError> {
         let from = self.from.map(|account| account.address());
         let gas_price = self.gas_price.and_then(|gas_price| gas_price.value());
 
-        self.web3
-            .eth()
-            .estimate_gas(
-                CallRequest {
-                    from,
-                    to: self.to,
-                    gas: None,
-                    gas_price,
-                    value: self.value,
-                    data: self.data.clone(),
-                    transaction_type: None,
-                    access_list: None,
-                },
-                None,
-            )
-            .await
-            .map_err(From::from)
+        simulate::state_changes(
+            &self.web3,
+            CallRequest {
+                from,
+                to: self.to,
+                gas: self.gas,
+                gas_price,
+                value: self.value,
+                data: self.data,
+                transaction_type: None,
+                access_list: None,
+            },
+            block,
+            backend,
+        )
+        .await
+    }
+
+    /// Simulate this transaction locally on top of the latest block. See
+    /// [`TransactionBuilder::simulate_local_at`] for details.
+    pub async fn simulate_local(self) -> Result<LocalSimulation, ExecutionError> {
+        self.simulate_local_at(BlockNumber::Latest).await
+    }
+
+    /// Simulate this transaction locally on top of the specified block and
+    /// return its gas usage, return data, logs, revert reason and state
+    /// changes without sending it.
+    ///
+    /// Unlike [`TransactionBuilder::simulate_state_changes`], the transaction
+    /// is executed in an embedded EVM, with the accounts and storage it reads
+    /// fetched from the node using standard `eth_*` methods. This works with
+    /// any node, including hosted providers that do not support tracing or
+    /// charge extra for it, but it requires more requests for transactions
+    /// that read a lot of state. Only the `ecrecover` and `identity`
+    /// precompiled contracts are supported.
+    ///
+    /// Like `eth_call`, the transaction is not charged any fees unless a gas
+    /// price is specified, and its nonce is not checked. Transaction
+    /// extensions are not included in the simulation.
+    pub async fn simulate_local_at(
+        self,
+        block: BlockNumber,
+    ) -> Result<LocalSimulation, ExecutionError> {
+        let request = local::Request {
+            from: self
+                .from
+                .map(|account| account.address())
+                .unwrap_or_default(),
+            to: self.to,
+            gas: self.gas,
+            gas_price: self.gas_price.and_then(|gas_price| gas_price.value()),
+            value: self.value,
+            data: self.data,
+        };
+        local::simulate(&self.web3, request, block).await
+    }
+}
+
+/// Runs a future with an optional timeout. Returns `None` if the timeout
+/// elapsed before the future completed.
+pub(crate) async fn timeout<F: Future>(duration: Option<Duration>, future: F) -> Option<F::Output> {
+    let duration = match duration {
+        Some(duration) => duration,
+        None => return Some(future.await),
+    };
+
+    futures::pin_mut!(future);
+    match future::select(future, Delay::new(duration)).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+/// Runs a fallible future with an optional timeout, returning an
+/// `ExecutionError::Timeout` error if the timeout elapsed.
+pub(crate) async fn with_timeout<F, R, E>(
+    duration: Option<Duration>,
+    future: F,
+) -> Result<R, ExecutionError>
+where
+    F: Future<Output = Result<R, E>>,
+    ExecutionError: From<E>,
+{
+    match timeout(duration, future).await {
+        Some(result) => Ok(result?),
+        None => Err(ExecutionError::Timeout(
+            duration.expect("only futures with a timeout can time out"),
+        )),
     }
 }
 
@@ -211,8 +621,29 @@ mod tests {
     use crate::errors::ExecutionError;
     use crate::test::prelude::*;
     use hex_literal::hex;
+    use web3::error::Error as Web3Error;
     use web3::types::{H2048, H256};
 
+    #[test]
+    fn account_balance() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let account = Account::Local(address, None);
+
+        transport.add_response(json!("0x2a"));
+        let balance = account.balance(&web3).immediate().expect("success");
+        assert_eq!(balance, 42.into());
+        transport.assert_request("eth_getBalance", &[json!(address), json!("latest")]);
+
+        transport.add_response(json!("0x2b"));
+        let balance = account.balance_at(&web3, 7).immediate().expect("success");
+        assert_eq!(balance, 43.into());
+        transport.assert_request("eth_getBalance", &[json!(address), json!("0x7")]);
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn tx_builder_estimate_gas() {
         let mut transport = TestTransport::new();
@@ -239,6 +670,48 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn tx_builder_estimate_gas_with_overrides() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let to = addr!("0x0123456789012345678901234567890123456789");
+        let mut overrides = StateOverrides::new();
+        overrides.insert(to, AccountOverride::default().balance(1.into()));
+
+        transport.add_response(json!("0x42"));
+        let estimate_gas = TransactionBuilder::new(web3.clone())
+            .to(to)
+            .estimate_gas_with(None, Some(&overrides))
+            .immediate()
+            .expect("success");
+
+        assert_eq!(estimate_gas, 0x42.into());
+        transport.assert_request(
+            "eth_estimateGas",
+            &[
+                json!({ "to": to }),
+                json!("latest"),
+                json!({ format!("{:?}", to): { "balance": "0x1" } }),
+            ],
+        );
+
+        transport.add_response(json!("0x43"));
+        TransactionBuilder::new(web3)
+            .to(to)
+            .estimate_gas_with(Some(BlockId::Hash(H256::repeat_byte(1))), None)
+            .immediate()
+            .expect("success");
+        transport.assert_request(
+            "eth_estimateGas",
+            &[
+                json!({ "to": to }),
+                json!({ "blockHash": H256::repeat_byte(1) }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn tx_send_local() {
         let mut transport = TestTransport::new();
@@ -393,4 +866,21 @@ mod tests {
         transport.assert_request("eth_getTransactionReceipt", &[json!(tx_hash)]);
         transport.assert_no_more_requests();
     }
+
+    #[test]
+    fn timeout_elapses() {
+        let duration = Duration::from_millis(10);
+        let result =
+            with_timeout(duration.into(), future::pending::<Result<(), Web3Error>>()).wait();
+        assert!(
+            matches!(result, Err(ExecutionError::Timeout(d)) if d == duration),
+            "expected timeout but got {:?}",
+            result
+        );
+
+        let result = with_timeout(duration.into(), future::ready(Ok::<_, Web3Error>(42))).wait();
+        assert_eq!(result.unwrap(), 42);
+        let result = with_timeout(None, future::ready(Ok::<_, Web3Error>(42))).wait();
+        assert_eq!(result.unwrap(), 42);
+    }
 }
@@ -0,0 +1,382 @@
+//! Implementation of EIP-7702 set code transactions, which set the code of
+//! externally owned accounts to delegate to a contract.
+//!
+//! Accounts authorize the delegation by signing an [`Authorization`], and the
+//! signed authorizations are included in the authorization list of a type 4
+//! transaction, which can be sent by any account.
+
+use crate::errors::ExecutionError;
+use crate::transaction::extension::TransactionExtension;
+use crate::transaction::signer::{self, Signer};
+use crate::transaction::{rlp, Account, Transaction};
+use ethcontract_common::hash::keccak256;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use web3::api::Web3;
+use web3::helpers;
+use web3::signing;
+use web3::types::{AccessList, Address, BlockNumber, Bytes, H256, U256, U64};
+use web3::Transport;
+
+/// The type of set code transactions.
+pub const SET_CODE_TX_TYPE: u8 = 0x04;
+
+/// The magic byte that prefixes the encoded authorization for signing.
+const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// An authorization for an account to delegate its code to a contract.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Authorization {
+    /// The chain ID the authorization is valid on, or 0 for an authorization
+    /// that is valid on all chains.
+    pub chain_id: U256,
+    /// The address of the contract the account delegates to. Authorizing the
+    /// zero address clears the delegation of the account.
+    pub address: Address,
+    /// The nonce of the authorizing account at the time the authorization is
+    /// processed.
+    pub nonce: U256,
+}
+
+impl Authorization {
+    /// Creates a new authorization for delegating to the specified contract.
+    pub fn new(chain_id: impl Into<U256>, address: Address, nonce: impl Into<U256>) -> Self {
+        Authorization {
+            chain_id: chain_id.into(),
+            address,
+            nonce: nonce.into(),
+        }
+    }
+
+    /// Returns the hash that is signed by the authorizing account.
+    pub fn signing_hash(&self) -> H256 {
+        let mut encoded = vec![AUTHORIZATION_MAGIC];
+        encoded.extend(rlp::list(&self.encode_fields()));
+        H256(keccak256(encoded))
+    }
+
+    /// Signs the authorization with a signer.
+    pub async fn sign(&self, signer: &dyn Signer) -> Result<SignedAuthorization, ExecutionError> {
+        let (y_parity, signature) =
+            signer::sign_hash_with_parity(signer, self.signing_hash()).await?;
+        Ok(SignedAuthorization {
+            chain_id: self.chain_id,
+            address: self.address,
+            nonce: self.nonce,
+            y_parity: y_parity.into(),
+            r: U256::from_big_endian(signature.r.as_bytes()),
+            s: U256::from_big_endian(signature.s.as_bytes()),
+        })
+    }
+
+    fn encode_fields(&self) -> Vec<u8> {
+        let mut fields = rlp::uint(self.chain_id);
+        fields.extend(rlp::bytes(self.address.as_bytes()));
+        fields.extend(rlp::uint(self.nonce));
+        fields
+    }
+}
+
+/// A signed authorization, in the format of the authorization list of
+/// JSON-RPC transaction objects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedAuthorization {
+    /// The chain ID the authorization is valid on.
+    pub chain_id: U256,
+    /// The address of the contract the account delegates to.
+    pub address: Address,
+    /// The nonce of the authorizing account.
+    pub nonce: U256,
+    /// The `y` parity of the signature.
+    pub y_parity: U64,
+    /// The `r` value of the signature.
+    pub r: U256,
+    /// The `s` value of the signature.
+    pub s: U256,
+}
+
+impl SignedAuthorization {
+    /// Returns the unsigned authorization.
+    pub fn authorization(&self) -> Authorization {
+        Authorization {
+            chain_id: self.chain_id,
+            address: self.address,
+            nonce: self.nonce,
+        }
+    }
+
+    /// Recovers the address of the authorizing account, or returns `None` if
+    /// the signature is invalid.
+    pub fn authority(&self) -> Option<Address> {
+        let hash = self.authorization().signing_hash();
+        let mut signature = [0; 64];
+        self.r.to_big_endian(&mut signature[..32]);
+        self.s.to_big_endian(&mut signature[32..]);
+        signing::recover(hash.as_bytes(), &signature, self.y_parity.as_u64() as _).ok()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut fields = self.authorization().encode_fields();
+        fields.extend(rlp::uint(self.y_parity.as_u64().into()));
+        fields.extend(rlp::uint(self.r));
+        fields.extend(rlp::uint(self.s));
+        rlp::list(&fields)
+    }
+}
+
+/// A transaction extension with the authorization list of a set code
+/// transaction, for transactions that are signed by the node or by a remote
+/// signer.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuthorizationList(pub Vec<SignedAuthorization>);
+
+impl TransactionExtension for AuthorizationList {
+    fn fields(&self) -> Map<String, Value> {
+        let mut fields = Map::new();
+        fields.insert(
+            "type".to_owned(),
+            helpers::serialize(&U64::from(SET_CODE_TX_TYPE)),
+        );
+        fields.insert("authorizationList".to_owned(), helpers::serialize(&self.0));
+        fields
+    }
+}
+
+/// A set code transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetCodeTransaction {
+    /// The chain ID of the transaction.
+    pub chain_id: u64,
+    /// The nonce of the sender.
+    pub nonce: U256,
+    /// The maximum priority fee per gas.
+    pub max_priority_fee_per_gas: U256,
+    /// The maximum fee per gas.
+    pub max_fee_per_gas: U256,
+    /// The gas limit of the transaction.
+    pub gas: U256,
+    /// The receiver of the transaction. Set code transactions cannot create
+    /// contracts.
+    pub to: Address,
+    /// The ETH value sent with the transaction.
+    pub value: U256,
+    /// The data of the transaction.
+    pub data: Bytes,
+    /// The access list of the transaction.
+    pub access_list: AccessList,
+    /// The signed authorizations of the transaction.
+    pub authorization_list: Vec<SignedAuthorization>,
+}
+
+impl SetCodeTransaction {
+    /// Returns the hash that is signed by the sender of the transaction.
+    pub fn signing_hash(&self) -> H256 {
+        let mut encoded = vec![SET_CODE_TX_TYPE];
+        encoded.extend(self.encode_payload(None));
+        H256(keccak256(encoded))
+    }
+
+    /// Signs the transaction, returning the raw signed transaction.
+    pub async fn sign(&self, signer: &dyn Signer) -> Result<Transaction, ExecutionError> {
+        let (y_parity, signature) =
+            signer::sign_hash_with_parity(signer, self.signing_hash()).await?;
+        let mut raw = vec![SET_CODE_TX_TYPE];
+        raw.extend(self.encode_payload(Some((y_parity, signature.r, signature.s))));
+        Ok(Transaction::Raw {
+            hash: H256(keccak256(&raw)),
+            bytes: Bytes(raw),
+        })
+    }
+
+    /// Encodes the RLP payload of the transaction, with the signature
+    /// fields if the transaction is signed.
+    fn encode_payload(&self, signature: Option<(u64, H256, H256)>) -> Vec<u8> {
+        let mut fields = Vec::new();
+        fields.extend(rlp::uint(self.chain_id.into()));
+        fields.extend(rlp::uint(self.nonce));
+        fields.extend(rlp::uint(self.max_priority_fee_per_gas));
+        fields.extend(rlp::uint(self.max_fee_per_gas));
+        fields.extend(rlp::uint(self.gas));
+        fields.extend(rlp::bytes(self.to.as_bytes()));
+        fields.extend(rlp::uint(self.value));
+        fields.extend(rlp::bytes(&self.data.0));
+        fields.extend(rlp::access_list(&self.access_list));
+        rlp::append_list(
+            &mut fields,
+            self.authorization_list
+                .iter()
+                .map(|authorization| authorization.encode()),
+        );
+        if let Some((y_parity, r, s)) = signature {
+            fields.extend(rlp::uint(y_parity.into()));
+            fields.extend(rlp::uint(U256::from_big_endian(r.as_bytes())));
+            fields.extend(rlp::uint(U256::from_big_endian(s.as_bytes())));
+        }
+        rlp::list(&fields)
+    }
+}
+
+impl Account {
+    /// Signs an authorization with the account. Only accounts with a private
+    /// key or an external signer can sign authorizations, since nodes do not
+    /// support signing them.
+    pub async fn sign_authorization(
+        &self,
+        authorization: &Authorization,
+    ) -> Result<SignedAuthorization, ExecutionError> {
+        match self {
+            Account::Offline(key, _) => authorization.sign(key).await,
+            Account::Signer(signer, _) => authorization.sign(signer.as_ref()).await,
+            account => Err(ExecutionError::AuthorizationUnsupported(account.address())),
+        }
+    }
+
+    /// Signs an authorization for delegating the code of the account to the
+    /// specified contract, with the chain ID of the account or the node and
+    /// the pending nonce of the account.
+    ///
+    /// Note that the nonce of the sender of a set code transaction is
+    /// incremented before its authorizations are processed, so an account
+    /// that sends its own authorization must sign it with the nonce after the
+    /// transaction nonce using [`Account::sign_authorization`] instead.
+    pub async fn authorize<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        address: Address,
+    ) -> Result<SignedAuthorization, ExecutionError> {
+        let chain_id = match self {
+            Account::Offline(_, Some(chain_id)) | Account::Signer(_, Some(chain_id)) => *chain_id,
+            _ => web3.eth().chain_id().await?.as_u64(),
+        };
+        let nonce = web3
+            .eth()
+            .transaction_count(self.address(), Some(BlockNumber::Pending))
+            .await?;
+        self.sign_authorization(&Authorization::new(chain_id, address, nonce))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use serde_json::json;
+
+    fn key() -> PrivateKey {
+        key!("0x0102030405060708091011121314151617181920212223242526272829303132")
+    }
+
+    #[test]
+    fn authorization_signing_hash() {
+        let authorization = Authorization::new(1, Address::repeat_byte(0x42), 7);
+        let mut encoded = vec![AUTHORIZATION_MAGIC, 0xd7, 0x01, 0x94];
+        encoded.extend_from_slice(&[0x42; 20]);
+        encoded.push(0x07);
+        assert_eq!(authorization.signing_hash(), H256(keccak256(encoded)));
+    }
+
+    #[test]
+    fn signed_authorization_recovers_authority() {
+        let key = key();
+        let authorization = Authorization::new(1, Address::repeat_byte(0x42), 7);
+        let signed = authorization.sign(&key).immediate().unwrap();
+
+        assert_eq!(signed.authorization(), authorization);
+        assert_eq!(signed.authority(), Some(key.public_address()));
+        assert_eq!(
+            SignedAuthorization {
+                nonce: 8.into(),
+                ..signed
+            }
+            .authority()
+            .filter(|authority| *authority == key.public_address()),
+            None,
+        );
+
+        let value = serde_json::to_value(signed).unwrap();
+        assert_eq!(value["chainId"], json!("0x1"));
+        assert_eq!(value["nonce"], json!("0x7"));
+        assert_eq!(
+            serde_json::from_value::<SignedAuthorization>(value).unwrap(),
+            signed
+        );
+    }
+
+    #[test]
+    fn node_accounts_cannot_sign_authorizations() {
+        let address = Address::repeat_byte(0x01);
+        let authorization = Authorization::new(1, Address::repeat_byte(0x42), 0);
+        let err = Account::Local(address, None)
+            .sign_authorization(&authorization)
+            .immediate()
+            .unwrap_err();
+        assert!(matches!(err, ExecutionError::AuthorizationUnsupported(a) if a == address));
+    }
+
+    #[test]
+    fn authorize_with_pending_nonce() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let key = key();
+        let address = key.public_address();
+        let delegate = Address::repeat_byte(0x42);
+
+        transport.add_response(json!("0x5"));
+        let signed = Account::Offline(key, Some(10))
+            .authorize(&web3, delegate)
+            .immediate()
+            .unwrap();
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(address), json!("pending")],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(signed.authorization(), Authorization::new(10, delegate, 5));
+        assert_eq!(signed.authority(), Some(address));
+    }
+
+    #[test]
+    fn set_code_transaction_encoding() {
+        let key = key();
+        let authorization = Authorization::new(1, Address::repeat_byte(0x42), 0)
+            .sign(&key)
+            .immediate()
+            .unwrap();
+        let tx = SetCodeTransaction {
+            chain_id: 1,
+            nonce: 0.into(),
+            max_priority_fee_per_gas: 1.into(),
+            max_fee_per_gas: 2.into(),
+            gas: 100_000.into(),
+            to: key.public_address(),
+            value: 0.into(),
+            data: Bytes::default(),
+            access_list: Vec::new(),
+            authorization_list: vec![authorization],
+        };
+
+        let raw = match tx.sign(&key).immediate().unwrap() {
+            Transaction::Raw { bytes, hash } => {
+                assert_eq!(hash, H256(keccak256(&bytes.0)));
+                bytes.0
+            }
+            _ => panic!("expected raw transaction"),
+        };
+        assert_eq!(raw[0], SET_CODE_TX_TYPE);
+
+        // The unsigned fields of the transaction precede the signed
+        // authorization list and the transaction signature.
+        let mut fields = vec![0x01, 0x80, 0x01, 0x02, 0x83, 0x01, 0x86, 0xa0, 0x94];
+        fields.extend_from_slice(key.public_address().as_bytes());
+        fields.extend_from_slice(&[0x80, 0x80, 0xc0]);
+        let payload = &raw[3..];
+        assert!(payload.starts_with(&fields));
+        assert!(raw
+            .windows(authorization.encode().len())
+            .any(|window| window == authorization.encode().as_slice()));
+    }
+}
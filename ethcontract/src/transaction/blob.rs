@@ -0,0 +1,755 @@
+//! Implementation of EIP-4844 blob transactions.
+//!
+//! Blob transactions carry blobs of data that are made available by the
+//! consensus layer for a limited time, with only commitments to the blobs being
+//! accessible on chain. The blobs are sent in a sidecar along with the signed
+//! transaction, which references them by their versioned hashes.
+//!
+//! KZG commitments and proofs for the blobs are computed with a [`Kzg`]
+//! implementation, which is expected to wrap a KZG library loaded with the
+//! Ethereum trusted setup. This crate does not bundle a KZG library or the
+//! trusted setup, so sidecars can also be created from precomputed
+//! commitments and proofs with [`BlobSidecar::from_parts`].
+
+use crate::errors::{BlobError, ExecutionError};
+use crate::rpc;
+use crate::transaction::signer::{self, Signer};
+use crate::transaction::{gas_price, rlp};
+use crate::transaction::{with_timeout, PendingTransaction};
+use ethcontract_common::hash::{keccak256, sha256};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use web3::api::Web3;
+use web3::types::{AccessList, Address, BlockNumber, Bytes, CallRequest, H256, U256};
+use web3::Transport;
+
+/// The type of blob transactions.
+pub const BLOB_TX_TYPE: u8 = 0x03;
+
+/// The number of field elements in a blob.
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+
+/// The size of a field element in bytes.
+pub const BYTES_PER_FIELD_ELEMENT: usize = 32;
+
+/// The size of a blob in bytes.
+pub const BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * BYTES_PER_FIELD_ELEMENT;
+
+/// The amount of blob gas used by each blob.
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+
+/// The version byte of versioned hashes of KZG commitments.
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// The number of data bytes stored in each field element by
+/// [`Blob::from_data`], which keeps the first byte of each field element zero
+/// so that it is always a valid element of the BLS12-381 scalar field.
+const USABLE_BYTES_PER_FIELD_ELEMENT: usize = BYTES_PER_FIELD_ELEMENT - 1;
+
+/// A blob of data.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Blob(Vec<u8>);
+
+impl Blob {
+    /// Creates a blob from its raw bytes, which must be exactly
+    /// `BYTES_PER_BLOB` bytes of field elements.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, BlobError> {
+        if bytes.len() != BYTES_PER_BLOB {
+            return Err(BlobError::InvalidBlobSize(bytes.len()));
+        }
+        Ok(Blob(bytes))
+    }
+
+    /// Encodes data into as many blobs as needed, storing 31 bytes of data in
+    /// each field element. The last blob is padded with zeros, so the length
+    /// of the data needs to be known for decoding it with [`Blob::data`].
+    pub fn from_data(data: &[u8]) -> Vec<Self> {
+        let per_blob = FIELD_ELEMENTS_PER_BLOB * USABLE_BYTES_PER_FIELD_ELEMENT;
+        data.chunks(per_blob)
+            .map(|data| {
+                let mut blob = vec![0; BYTES_PER_BLOB];
+                for (element, chunk) in blob
+                    .chunks_mut(BYTES_PER_FIELD_ELEMENT)
+                    .zip(data.chunks(USABLE_BYTES_PER_FIELD_ELEMENT))
+                {
+                    element[1..=chunk.len()].copy_from_slice(chunk);
+                }
+                Blob(blob)
+            })
+            .collect()
+    }
+
+    /// Returns the data stored in the blob by [`Blob::from_data`], including
+    /// any padding.
+    pub fn data(&self) -> Vec<u8> {
+        self.0
+            .chunks(BYTES_PER_FIELD_ELEMENT)
+            .flat_map(|element| element[1..].iter().copied())
+            .collect()
+    }
+
+    /// Returns the raw bytes of the blob.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for Blob {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // NOTE: Blobs are too large to be usefully formatted, so only their
+        //   hash is shown.
+        f.debug_tuple("Blob")
+            .field(&H256(keccak256(&self.0)))
+            .finish()
+    }
+}
+
+/// A KZG commitment to a blob.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KzgCommitment(pub [u8; 48]);
+
+impl KzgCommitment {
+    /// Returns the versioned hash of the commitment, which is how blob
+    /// transactions and the `BLOBHASH` opcode reference the blob.
+    pub fn versioned_hash(&self) -> H256 {
+        let mut hash = sha256(self.0);
+        hash[0] = VERSIONED_HASH_VERSION_KZG;
+        H256(hash)
+    }
+}
+
+/// A KZG proof of a blob against its commitment.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KzgProof(pub [u8; 48]);
+
+/// An implementation of the KZG commitment scheme with the Ethereum trusted
+/// setup, used for computing blob commitments and proofs.
+pub trait Kzg: Debug + Send + Sync {
+    /// Computes the commitment to a blob.
+    fn blob_to_commitment(&self, blob: &Blob) -> Result<KzgCommitment, BlobError>;
+
+    /// Computes the proof of a blob against its commitment.
+    fn compute_blob_proof(
+        &self,
+        blob: &Blob,
+        commitment: &KzgCommitment,
+    ) -> Result<KzgProof, BlobError>;
+}
+
+/// The blobs of a transaction along with their commitments and proofs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlobSidecar {
+    blobs: Vec<Blob>,
+    commitments: Vec<KzgCommitment>,
+    proofs: Vec<KzgProof>,
+}
+
+impl BlobSidecar {
+    /// Creates a sidecar for blobs, computing their commitments and proofs.
+    pub fn new(blobs: Vec<Blob>, kzg: &dyn Kzg) -> Result<Self, BlobError> {
+        let commitments = blobs
+            .iter()
+            .map(|blob| kzg.blob_to_commitment(blob))
+            .collect::<Result<Vec<_>, _>>()?;
+        let proofs = blobs
+            .iter()
+            .zip(&commitments)
+            .map(|(blob, commitment)| kzg.compute_blob_proof(blob, commitment))
+            .collect::<Result<Vec<_>, _>>()?;
+        BlobSidecar::from_parts(blobs, commitments, proofs)
+    }
+
+    /// Creates a sidecar from blobs with precomputed commitments and proofs.
+    /// Note that the commitments and proofs are not verified.
+    pub fn from_parts(
+        blobs: Vec<Blob>,
+        commitments: Vec<KzgCommitment>,
+        proofs: Vec<KzgProof>,
+    ) -> Result<Self, BlobError> {
+        if blobs.is_empty() {
+            return Err(BlobError::NoBlobs);
+        }
+        if commitments.len() != blobs.len() || proofs.len() != blobs.len() {
+            return Err(BlobError::SidecarMismatch {
+                blobs: blobs.len(),
+                commitments: commitments.len(),
+                proofs: proofs.len(),
+            });
+        }
+        Ok(BlobSidecar {
+            blobs,
+            commitments,
+            proofs,
+        })
+    }
+
+    /// Returns the blobs.
+    pub fn blobs(&self) -> &[Blob] {
+        &self.blobs
+    }
+
+    /// Returns the commitments of the blobs.
+    pub fn commitments(&self) -> &[KzgCommitment] {
+        &self.commitments
+    }
+
+    /// Returns the proofs of the blobs.
+    pub fn proofs(&self) -> &[KzgProof] {
+        &self.proofs
+    }
+
+    /// Returns the versioned hashes of the blobs.
+    pub fn versioned_hashes(&self) -> Vec<H256> {
+        self.commitments
+            .iter()
+            .map(KzgCommitment::versioned_hash)
+            .collect()
+    }
+
+    /// Returns the amount of blob gas used by the blobs.
+    pub fn blob_gas(&self) -> u64 {
+        GAS_PER_BLOB * self.blobs.len() as u64
+    }
+}
+
+/// Returns the current base fee per blob gas with `eth_blobBaseFee`.
+pub async fn blob_base_fee<T: Transport>(web3: &Web3<T>) -> Result<U256, ExecutionError> {
+    Ok(rpc::call(web3, "eth_blobBaseFee", ()).await?)
+}
+
+/// The fully resolved parameters of a blob transaction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlobTransaction {
+    /// The chain ID of the transaction.
+    pub chain_id: u64,
+    /// The nonce of the transaction.
+    pub nonce: U256,
+    /// The maximum priority fee per gas.
+    pub max_priority_fee_per_gas: U256,
+    /// The maximum fee per gas.
+    pub max_fee_per_gas: U256,
+    /// The gas limit of the transaction.
+    pub gas: U256,
+    /// The receiver of the transaction. Blob transactions cannot create
+    /// contracts, so the receiver is required.
+    pub to: Address,
+    /// The amount of ETH sent with the transaction.
+    pub value: U256,
+    /// The calldata of the transaction.
+    pub data: Bytes,
+    /// The access list of the transaction.
+    pub access_list: AccessList,
+    /// The maximum fee per blob gas.
+    pub max_fee_per_blob_gas: U256,
+    /// The versioned hashes of the blobs of the transaction.
+    pub blob_versioned_hashes: Vec<H256>,
+}
+
+impl BlobTransaction {
+    /// Returns the hash that is signed by the sender of the transaction.
+    pub fn signing_hash(&self) -> H256 {
+        let mut encoded = vec![BLOB_TX_TYPE];
+        encoded.extend(self.encode_payload(None));
+        H256(keccak256(encoded))
+    }
+
+    /// Signs the transaction for the specified sidecar, which must contain the
+    /// blobs of the versioned hashes of the transaction.
+    pub async fn sign(
+        &self,
+        signer: &dyn Signer,
+        sidecar: &BlobSidecar,
+    ) -> Result<SignedBlobTransaction, ExecutionError> {
+        if self.blob_versioned_hashes != sidecar.versioned_hashes() {
+            return Err(BlobError::VersionedHashMismatch.into());
+        }
+
+        let (y_parity, signature) =
+            signer::sign_hash_with_parity(signer, self.signing_hash()).await?;
+        let mut payload = vec![BLOB_TX_TYPE];
+        payload.extend(self.encode_payload(Some((y_parity, signature.r, signature.s))));
+        let hash = H256(keccak256(&payload));
+
+        // NOTE: The network representation of the transaction, as sent to
+        //   nodes, wraps the signed payload in a list with the sidecar.
+        let mut wrapper = payload[1..].to_vec();
+        rlp::append_list(
+            &mut wrapper,
+            sidecar.blobs.iter().map(|blob| rlp::bytes(&blob.0)),
+        );
+        rlp::append_list(
+            &mut wrapper,
+            sidecar
+                .commitments
+                .iter()
+                .map(|commitment| rlp::bytes(&commitment.0)),
+        );
+        rlp::append_list(
+            &mut wrapper,
+            sidecar.proofs.iter().map(|proof| rlp::bytes(&proof.0)),
+        );
+        let mut raw = vec![BLOB_TX_TYPE];
+        raw.extend(rlp::list(&wrapper));
+
+        Ok(SignedBlobTransaction {
+            hash,
+            raw: Bytes(raw),
+        })
+    }
+
+    /// Encodes the RLP payload of the transaction, with the signature
+    /// fields if the transaction is signed.
+    fn encode_payload(&self, signature: Option<(u64, H256, H256)>) -> Vec<u8> {
+        let mut fields = Vec::new();
+        fields.extend(rlp::uint(self.chain_id.into()));
+        fields.extend(rlp::uint(self.nonce));
+        fields.extend(rlp::uint(self.max_priority_fee_per_gas));
+        fields.extend(rlp::uint(self.max_fee_per_gas));
+        fields.extend(rlp::uint(self.gas));
+        fields.extend(rlp::bytes(self.to.as_bytes()));
+        fields.extend(rlp::uint(self.value));
+        fields.extend(rlp::bytes(&self.data.0));
+        fields.extend(rlp::access_list(&self.access_list));
+        fields.extend(rlp::uint(self.max_fee_per_blob_gas));
+        rlp::append_list(
+            &mut fields,
+            self.blob_versioned_hashes
+                .iter()
+                .map(|hash| rlp::bytes(hash.as_bytes())),
+        );
+        if let Some((y_parity, r, s)) = signature {
+            fields.extend(rlp::uint(y_parity.into()));
+            fields.extend(rlp::uint(U256::from_big_endian(r.as_bytes())));
+            fields.extend(rlp::uint(U256::from_big_endian(s.as_bytes())));
+        }
+        rlp::list(&fields)
+    }
+}
+
+/// A signed blob transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignedBlobTransaction {
+    /// The hash of the transaction, which does not depend on the sidecar.
+    pub hash: H256,
+    /// The network representation of the signed transaction including the
+    /// sidecar, as sent with `eth_sendRawTransaction`.
+    pub raw: Bytes,
+}
+
+/// A builder for blob transactions. Parameters that are not specified are
+/// resolved from the node when building the transaction.
+#[derive(Clone, Debug)]
+#[must_use = "blob transactions do nothing unless you `.build()` or `.broadcast()` them"]
+pub struct BlobTransactionBuilder<T: Transport> {
+    web3: Web3<T>,
+    signer: Arc<dyn Signer>,
+    sidecar: BlobSidecar,
+    /// The receiver of the transaction.
+    pub to: Address,
+    /// The optional amount of ETH to send, defaults to zero.
+    pub value: Option<U256>,
+    /// The optional calldata, defaults to empty calldata.
+    pub data: Option<Bytes>,
+    /// The optional gas limit, estimated if not specified.
+    pub gas: Option<U256>,
+    /// The optional nonce, the pending transaction count of the sender if not
+    /// specified.
+    pub nonce: Option<U256>,
+    /// The optional maximum fee per gas, the node's gas price if not
+    /// specified.
+    pub max_fee_per_gas: Option<U256>,
+    /// The optional maximum priority fee per gas, the node's suggested
+    /// priority fee, or the median priority fee of recent blocks if the node
+    /// does not suggest priority fees, if not specified.
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The optional maximum fee per blob gas, twice the current blob base fee
+    /// if not specified.
+    pub max_fee_per_blob_gas: Option<U256>,
+    /// The access list of the transaction.
+    pub access_list: AccessList,
+    /// The optional timeout for building and broadcasting the transaction.
+    pub timeout: Option<Duration>,
+}
+
+impl<T: Transport> BlobTransactionBuilder<T> {
+    /// Creates a new builder for a transaction to the specified address
+    /// carrying the blobs of a sidecar, signed by the specified signer.
+    pub fn new(web3: Web3<T>, signer: Arc<dyn Signer>, to: Address, sidecar: BlobSidecar) -> Self {
+        BlobTransactionBuilder {
+            web3,
+            signer,
+            sidecar,
+            to,
+            value: None,
+            data: None,
+            gas: None,
+            nonce: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_blob_gas: None,
+            access_list: AccessList::new(),
+            timeout: None,
+        }
+    }
+
+    /// Specify the amount of ETH to send with the transaction.
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Specify the calldata of the transaction.
+    pub fn data(mut self, value: Bytes) -> Self {
+        self.data = Some(value);
+        self
+    }
+
+    /// Specify the gas limit of the transaction.
+    pub fn gas(mut self, value: U256) -> Self {
+        self.gas = Some(value);
+        self
+    }
+
+    /// Specify the nonce of the transaction.
+    pub fn nonce(mut self, value: U256) -> Self {
+        self.nonce = Some(value);
+        self
+    }
+
+    /// Specify the maximum fee per gas.
+    pub fn max_fee_per_gas(mut self, value: U256) -> Self {
+        self.max_fee_per_gas = Some(value);
+        self
+    }
+
+    /// Specify the maximum priority fee per gas.
+    pub fn max_priority_fee_per_gas(mut self, value: U256) -> Self {
+        self.max_priority_fee_per_gas = Some(value);
+        self
+    }
+
+    /// Specify the maximum fee per blob gas. Transactions are only included
+    /// in blocks with a blob base fee that does not exceed this value.
+    pub fn max_fee_per_blob_gas(mut self, value: U256) -> Self {
+        self.max_fee_per_blob_gas = Some(value);
+        self
+    }
+
+    /// Specify the access list of the transaction.
+    pub fn access_list(mut self, value: AccessList) -> Self {
+        self.access_list = value;
+        self
+    }
+
+    /// Specify a timeout for building and broadcasting the transaction.
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.timeout = Some(value);
+        self
+    }
+
+    /// Resolves the parameters of the transaction that were not specified.
+    pub async fn resolve(&self) -> Result<BlobTransaction, ExecutionError> {
+        let eth = self.web3.eth();
+        let from = self.signer.address();
+        let value = self.value.unwrap_or_default();
+        let data = self.data.clone().unwrap_or_default();
+
+        let chain_id = eth.chain_id().await?.as_u64();
+        let nonce = match self.nonce {
+            Some(nonce) => nonce,
+            None => {
+                eth.transaction_count(from, Some(BlockNumber::Pending))
+                    .await?
+            }
+        };
+        let max_fee_per_gas = match self.max_fee_per_gas {
+            Some(fee) => fee,
+            None => eth.gas_price().await?,
+        };
+        let max_priority_fee_per_gas = match self.max_priority_fee_per_gas {
+            Some(fee) => fee,
+            None => {
+                gas_price::suggest_priority_fee(&self.web3)
+                    .await?
+                    .capped(max_fee_per_gas)
+                    .max_priority_fee_per_gas
+            }
+        };
+        let max_fee_per_blob_gas = match self.max_fee_per_blob_gas {
+            Some(fee) => fee,
+            None => blob_base_fee(&self.web3).await?.saturating_mul(2.into()),
+        };
+        let gas = match self.gas {
+            Some(gas) => gas,
+            None => {
+                let request = CallRequest {
+                    from: Some(from),
+                    to: Some(self.to),
+                    value: Some(value),
+                    data: Some(data.clone()),
+                    ..Default::default()
+                };
+                eth.estimate_gas(request, None).await?
+            }
+        };
+
+        Ok(BlobTransaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas,
+            to: self.to,
+            value,
+            data,
+            access_list: self.access_list.clone(),
+            max_fee_per_blob_gas,
+            blob_versioned_hashes: self.sidecar.versioned_hashes(),
+        })
+    }
+
+    /// Resolves and signs the transaction.
+    pub async fn build(self) -> Result<SignedBlobTransaction, ExecutionError> {
+        let tx = self.resolve().await?;
+        tx.sign(&*self.signer, &self.sidecar).await
+    }
+
+    /// Signs and broadcasts the transaction without waiting for confirmation.
+    /// Returns a handle for the pending transaction that can be used to wait
+    /// for its confirmation.
+    pub async fn broadcast(self) -> Result<PendingTransaction, ExecutionError> {
+        let web3 = self.web3.clone();
+        with_timeout(self.timeout, async move {
+            let tx = self.resolve().await?;
+            let signed = tx.sign(&*self.signer, &self.sidecar).await?;
+            let hash = web3.eth().send_raw_transaction(signed.raw.clone()).await?;
+            if hash != signed.hash {
+                return Err(ExecutionError::UnexpectedTransactionHash);
+            }
+            Ok(PendingTransaction {
+                hash,
+                nonce: Some(tx.nonce),
+                raw: Some(signed.raw),
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use web3::signing;
+    use web3::types::AccessListItem;
+
+    /// A KZG implementation returning fake commitments and proofs derived
+    /// from the hash of the blob.
+    #[derive(Debug)]
+    struct FakeKzg;
+
+    impl Kzg for FakeKzg {
+        fn blob_to_commitment(&self, blob: &Blob) -> Result<KzgCommitment, BlobError> {
+            let mut commitment = [0xc0; 48];
+            commitment[..32].copy_from_slice(&keccak256(blob.as_bytes()));
+            Ok(KzgCommitment(commitment))
+        }
+
+        fn compute_blob_proof(
+            &self,
+            _: &Blob,
+            commitment: &KzgCommitment,
+        ) -> Result<KzgProof, BlobError> {
+            let mut proof = commitment.0;
+            proof[47] = 0xff;
+            Ok(KzgProof(proof))
+        }
+    }
+
+    fn sidecar() -> BlobSidecar {
+        BlobSidecar::new(Blob::from_data(b"hello blobs"), &FakeKzg).unwrap()
+    }
+
+    #[test]
+    fn blob_data_encoding() {
+        let data = (0..200_000).map(|i| i as u8).collect::<Vec<_>>();
+        let blobs = Blob::from_data(&data);
+        assert_eq!(blobs.len(), 2);
+        assert!(blobs
+            .iter()
+            .all(|blob| blob.as_bytes().len() == BYTES_PER_BLOB
+                && blob.as_bytes().chunks(32).all(|element| element[0] == 0)));
+
+        let decoded = blobs.iter().flat_map(Blob::data).collect::<Vec<_>>();
+        assert_eq!(&decoded[..data.len()], &data[..]);
+        assert!(decoded[data.len()..].iter().all(|byte| *byte == 0));
+
+        assert!(matches!(
+            Blob::from_bytes(vec![0; 42]),
+            Err(BlobError::InvalidBlobSize(42))
+        ));
+    }
+
+    #[test]
+    fn sidecar_versioned_hashes() {
+        let sidecar = sidecar();
+        let commitment = sidecar.commitments()[0];
+        let mut hash = sha256(commitment.0);
+        hash[0] = 0x01;
+        assert_eq!(sidecar.versioned_hashes(), vec![H256(hash)]);
+        assert_eq!(sidecar.blob_gas(), 131_072);
+
+        assert!(matches!(
+            BlobSidecar::from_parts(Vec::new(), Vec::new(), Vec::new()),
+            Err(BlobError::NoBlobs)
+        ));
+        assert!(matches!(
+            BlobSidecar::from_parts(sidecar.blobs().to_vec(), Vec::new(), Vec::new()),
+            Err(BlobError::SidecarMismatch {
+                blobs: 1,
+                commitments: 0,
+                proofs: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn rlp_encoding() {
+        assert_eq!(rlp::uint(0.into()), [0x80]);
+        assert_eq!(rlp::uint(0x7f.into()), [0x7f]);
+        assert_eq!(rlp::uint(0x0400.into()), [0x82, 0x04, 0x00]);
+        assert_eq!(rlp::bytes(b"dog"), [0x83, b'd', b'o', b'g']);
+        assert_eq!(rlp::list(&[]), [0xc0]);
+
+        let long = rlp::bytes(&[0xaa; 1024]);
+        assert_eq!(long[..3], [0xb9, 0x04, 0x00]);
+        assert_eq!(long.len(), 1027);
+        assert_eq!(rlp::list(&long)[..3], [0xf9, 0x04, 0x03]);
+    }
+
+    #[test]
+    fn sign_blob_transaction() {
+        let key = PrivateKey::from_raw([0x42; 32]).unwrap();
+        let sidecar = sidecar();
+        let tx = BlobTransaction {
+            chain_id: 1,
+            nonce: 7.into(),
+            max_priority_fee_per_gas: 1_000_000_000u64.into(),
+            max_fee_per_gas: 30_000_000_000u64.into(),
+            gas: 21_000.into(),
+            to: Address::repeat_byte(0x11),
+            value: 0.into(),
+            data: Bytes::default(),
+            access_list: vec![AccessListItem {
+                address: Address::repeat_byte(0x22),
+                storage_keys: vec![H256::repeat_byte(0x33)],
+            }],
+            max_fee_per_blob_gas: 10.into(),
+            blob_versioned_hashes: sidecar.versioned_hashes(),
+        };
+
+        let signed = tx.sign(&key, &sidecar).immediate().unwrap();
+        let raw = &signed.raw.0;
+        assert_eq!(raw[0], BLOB_TX_TYPE);
+
+        // The wrapper is a list of the signed payload and the sidecar, with
+        // the transaction hash only covering the payload.
+        let wrapper = &raw[1..];
+        assert_eq!(wrapper[..2], [0xfa, 0x02]);
+        let payload_start = 4;
+        let payload_len = 2 + wrapper[payload_start + 1] as usize;
+        let payload = &wrapper[payload_start..payload_start + payload_len];
+        assert_eq!(
+            signed.hash,
+            H256(keccak256([&[BLOB_TX_TYPE][..], payload].concat())),
+        );
+
+        // The signature recovers the sender for the signing hash.
+        let fields = &payload[2..];
+        let r = &fields[fields.len() - 66..fields.len() - 33];
+        let s = &fields[fields.len() - 33..];
+        let y_parity = fields[fields.len() - 67];
+        assert_eq!((r[0], s[0]), (0xa0, 0xa0));
+        let signature = [&r[1..], &s[1..]].concat();
+        let recovery_id = match y_parity {
+            0x80 => 0,
+            parity => parity as i32,
+        };
+        assert_eq!(
+            signing::recover(tx.signing_hash().as_bytes(), &signature, recovery_id),
+            Ok(key.public_address()),
+        );
+
+        let mismatch = BlobTransaction {
+            blob_versioned_hashes: Vec::new(),
+            ..tx
+        };
+        assert!(matches!(
+            mismatch.sign(&key, &sidecar).immediate(),
+            Err(ExecutionError::Blob(BlobError::VersionedHashMismatch))
+        ));
+    }
+
+    #[test]
+    fn build_and_broadcast() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let key = PrivateKey::from_raw([0x42; 32]).unwrap();
+        let from = key.public_address();
+        let to = Address::repeat_byte(0x11);
+
+        let builder = BlobTransactionBuilder::new(web3, Arc::new(key), to, sidecar())
+            .max_priority_fee_per_gas(1.into());
+
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!("0x7"));
+        transport.add_response(json!("0x100"));
+        transport.add_response(json!("0x3"));
+        transport.add_response(json!("0x5208"));
+        let tx = builder.resolve().immediate().unwrap();
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        transport.assert_request("eth_gasPrice", &[]);
+        transport.assert_request("eth_blobBaseFee", &[]);
+        transport.assert_request(
+            "eth_estimateGas",
+            &[json!({ "from": from, "to": to, "value": "0x0", "data": "0x" })],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(
+            (
+                tx.nonce,
+                tx.max_fee_per_gas,
+                tx.max_fee_per_blob_gas,
+                tx.gas
+            ),
+            (7.into(), 0x100.into(), 6.into(), 21_000.into()),
+        );
+
+        let signed = tx
+            .sign(&*builder.signer, &builder.sidecar)
+            .immediate()
+            .unwrap();
+        let builder = builder
+            .nonce(tx.nonce)
+            .max_fee_per_gas(tx.max_fee_per_gas)
+            .max_fee_per_blob_gas(tx.max_fee_per_blob_gas)
+            .gas(tx.gas);
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!(signed.hash));
+        let pending = builder.broadcast().immediate().unwrap();
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_request("eth_sendRawTransaction", &[json!(signed.raw)]);
+        transport.assert_no_more_requests();
+        assert_eq!(
+            pending,
+            PendingTransaction {
+                hash: signed.hash,
+                nonce: Some(7.into()),
+                raw: Some(signed.raw),
+            },
+        );
+    }
+}
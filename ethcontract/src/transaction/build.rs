@@ -4,14 +4,27 @@
 //! remaining parameters are queried from the node before finalizing the
 //! transaction.
 
+use crate::block::BlockTag;
 use crate::errors::ExecutionError;
 use crate::secret::{Password, PrivateKey};
-use crate::transaction::gas_price::GasPrice;
-use crate::transaction::{Account, TransactionBuilder};
+use crate::transaction::extension::{self, TransactionExtension};
+use crate::transaction::gas_price::{self, GasPrice};
+use crate::transaction::guard::{self, TransactionGuard};
+use crate::transaction::hook::{MethodCall, TransactionHook, TransactionInfo};
+use crate::transaction::overrides::StateOverrides;
+use crate::transaction::preflight;
+use crate::transaction::preview::TransactionPreview;
+use crate::transaction::signer::{self, Signer};
+use crate::transaction::{
+    Account, AuthorizationList, RemoteSigner, SetCodeTransaction, SignedAuthorization,
+    TransactionBuilder, SET_CODE_TX_TYPE,
+};
+use std::sync::Arc;
 use web3::api::Web3;
+use web3::helpers;
 use web3::types::{
-    Address, Bytes, CallRequest, RawTransaction, SignedTransaction, TransactionCondition,
-    TransactionParameters, TransactionRequest, H256, U256,
+    Address, BlockId, BlockNumber, Bytes, CallRequest, RawTransaction, SignedTransaction,
+    TransactionCondition, TransactionParameters, TransactionRequest, H256, U256,
 };
 use web3::Transport;
 
@@ -22,60 +35,271 @@ impl<T: Transport> TransactionBuilder<T> {
     /// signed transactions or raw signed transaction `Bytes` when sending a raw
     /// transaction.
     pub async fn build(self) -> Result<Transaction, ExecutionError> {
+        Ok(self.build_with_info().await?.0)
+    }
+
+    /// Build a prepared transaction along with its resolved parameters,
+    /// notifying the transaction hook just before signing.
+    pub(crate) async fn build_with_info(
+        self,
+    ) -> Result<(Transaction, TransactionInfo), ExecutionError> {
         let gas_price = self.gas_price.unwrap_or_default();
+        let extensions = match &self.from {
+            Some(Account::Offline(..)) | Some(Account::Signer(..)) => self.extensions.clone(),
+            _ => self.node_extensions(),
+        };
+        let hooks = SignHooks {
+            method: self.function.as_ref().and_then(|function| {
+                MethodCall::decode(function, self.data.as_ref().map_or(&[], |data| &data.0))
+            }),
+            hook: self.hook,
+            guard: self.guard,
+            preflight: self.preflight,
+            check_balance: self.check_balance,
+        };
+        let nonce = match (self.nonce, self.nonce_tag, &self.from) {
+            (None, Some(tag), Some(from)) => {
+                Some(transaction_count(&self.web3, from.address(), tag).await?)
+            }
+            (nonce, _, _) => nonce,
+        };
         let options = TransactionOptions {
             to: self.to,
             gas: self.gas,
             value: self.value,
             data: self.data,
-            nonce: self.nonce,
+            nonce,
+            extensions,
+            authorization_list: self.authorization_list,
         };
 
         let tx = match self.from {
-            None => Transaction::Request(
-                build_transaction_request_for_local_signing(
+            None => {
+                let (request, info) = build_transaction_request_for_local_signing(
                     self.web3,
                     None,
                     gas_price,
                     TransactionRequestOptions(options, None),
+                    hooks,
                 )
-                .await?,
-            ),
-            Some(Account::Local(from, condition)) => Transaction::Request(
-                build_transaction_request_for_local_signing(
+                .await?;
+                (Transaction::Request(request), info)
+            }
+            Some(Account::Local(from, condition)) => {
+                let (request, info) = build_transaction_request_for_local_signing(
                     self.web3,
                     Some(from),
                     gas_price,
                     TransactionRequestOptions(options, condition),
+                    hooks,
                 )
-                .await?,
-            ),
+                .await?;
+                (Transaction::Request(request), info)
+            }
             Some(Account::Locked(from, password, condition)) => {
-                build_transaction_signed_with_locked_account(
+                let (signed, info) = build_transaction_signed_with_locked_account(
                     self.web3,
                     from,
                     password,
                     gas_price,
                     TransactionRequestOptions(options, condition),
+                    hooks,
                 )
-                .await
-                .map(|signed| Transaction::Raw {
+                .await?;
+                let tx = Transaction::Raw {
                     bytes: signed.raw,
                     hash: signed.tx.hash,
-                })?
+                };
+                (tx, info)
+            }
+            Some(Account::Offline(key, chain_id)) if !options.authorization_list.is_empty() => {
+                build_set_code_transaction(
+                    self.web3,
+                    Arc::new(key),
+                    chain_id,
+                    gas_price,
+                    options,
+                    hooks,
+                )
+                .await?
+            }
+            Some(Account::Signer(signer, chain_id)) if !options.authorization_list.is_empty() => {
+                build_set_code_transaction(self.web3, signer, chain_id, gas_price, options, hooks)
+                    .await?
             }
             Some(Account::Offline(key, chain_id)) => {
-                build_offline_signed_transaction(self.web3, key, chain_id, gas_price, options)
-                    .await
-                    .map(|signed| Transaction::Raw {
-                        bytes: signed.raw_transaction,
-                        hash: signed.transaction_hash,
-                    })?
+                let (signed, info) = build_offline_signed_transaction(
+                    self.web3, key, chain_id, gas_price, options, hooks,
+                )
+                .await?;
+                let tx = Transaction::Raw {
+                    bytes: signed.raw_transaction,
+                    hash: signed.transaction_hash,
+                };
+                (tx, info)
+            }
+            Some(Account::Signer(signer, chain_id)) => {
+                let (signed, info) = build_signer_signed_transaction(
+                    self.web3, signer, chain_id, gas_price, options, hooks,
+                )
+                .await?;
+                let tx = Transaction::Raw {
+                    bytes: signed.raw_transaction,
+                    hash: signed.transaction_hash,
+                };
+                (tx, info)
+            }
+            Some(Account::Remote(signer)) => {
+                let ((bytes, hash), info) =
+                    build_remote_signed_transaction(self.web3, signer, gas_price, options, hooks)
+                        .await?;
+                (Transaction::Raw { bytes, hash }, info)
             }
         };
 
         Ok(tx)
     }
+
+    /// Resolves the parameters of the transaction the same way as building
+    /// it does, and returns them as a preview without signing or sending the
+    /// transaction.
+    ///
+    /// Note that the resolved nonce and fees may change between previewing
+    /// and sending the transaction, so they should be set explicitly on the
+    /// builder when the sent transaction must match the preview exactly.
+    pub async fn preview(self) -> Result<TransactionPreview, ExecutionError> {
+        let gas_price = self.gas_price.unwrap_or_default();
+        let method = self.function.as_ref().and_then(|function| {
+            MethodCall::decode(function, self.data.as_ref().map_or(&[], |data| &data.0))
+        });
+        let signed_by_runtime = matches!(
+            &self.from,
+            Some(Account::Offline(..)) | Some(Account::Signer(..))
+        );
+        if signed_by_runtime {
+            extension::ensure_none(&self.extensions)?;
+        }
+        let from = match &self.from {
+            Some(account) => account.address(),
+            None => *self
+                .web3
+                .eth()
+                .accounts()
+                .await?
+                .first()
+                .ok_or(ExecutionError::NoLocalAccounts)?,
+        };
+        let options = TransactionOptions {
+            to: self.to,
+            gas: self.gas,
+            value: self.value,
+            data: self.data.clone(),
+            nonce: self.nonce,
+            extensions: self.node_extensions(),
+            authorization_list: Vec::new(),
+        };
+
+        let gas = resolve_gas_limit(&self.web3, from, gas_price, &options).await?;
+        let gas_price = gas_price.resolve(&self.web3).await?;
+        let max_priority_fee_per_gas = if signed_by_runtime && !self.authorization_list.is_empty() {
+            Some(
+                gas_price::suggest_priority_fee(&self.web3)
+                    .await?
+                    .capped(gas_price)
+                    .max_priority_fee_per_gas,
+            )
+        } else {
+            None
+        };
+        let nonce = match (self.nonce, self.nonce_tag) {
+            (Some(nonce), _) => nonce,
+            (None, Some(tag)) => transaction_count(&self.web3, from, tag).await?,
+            (None, None) if signed_by_runtime => {
+                self.web3.eth().transaction_count(from, None).await?
+            }
+            (None, None) => {
+                self.web3
+                    .eth()
+                    .transaction_count(from, Some(BlockNumber::Pending))
+                    .await?
+            }
+        };
+        let chain_id = match &self.from {
+            Some(Account::Offline(_, Some(chain_id)))
+            | Some(Account::Signer(_, Some(chain_id))) => *chain_id,
+            _ => self.web3.eth().chain_id().await?.as_u64(),
+        };
+
+        let transaction_type = if self.authorization_list.is_empty() {
+            None
+        } else {
+            Some(SET_CODE_TX_TYPE.into())
+        };
+        let mut extra_fields = serde_json::Map::new();
+        for extension in &self.extensions {
+            extra_fields.extend(extension.fields());
+        }
+
+        Ok(TransactionPreview {
+            from,
+            to: self.to,
+            nonce,
+            value: self.value.unwrap_or_default(),
+            data: self.data.unwrap_or_default(),
+            method: None,
+            args: Vec::new(),
+            gas,
+            gas_price,
+            max_priority_fee_per_gas,
+            chain_id,
+            transaction_type,
+            authorization_list: self.authorization_list,
+            extra_fields,
+        }
+        .with_method(method))
+    }
+}
+
+/// The hooks to notify before signing a transaction.
+#[derive(Default)]
+struct SignHooks {
+    /// The decoded contract method call of the transaction.
+    method: Option<MethodCall>,
+    /// The hook to notify.
+    hook: Option<Arc<dyn TransactionHook>>,
+    /// The guard to consult.
+    guard: Option<Arc<dyn TransactionGuard>>,
+    /// Whether to check the transaction against the latest block.
+    preflight: bool,
+    /// Whether to check the balance of the sender.
+    check_balance: bool,
+}
+
+impl SignHooks {
+    /// Performs the preflight and balance checks, consults the guard and
+    /// notifies the hook that the transaction is about to be signed. Returns an
+    /// error if the checks failed or the guard rejected the transaction.
+    async fn before_sign<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        info: &TransactionInfo,
+    ) -> Result<(), ExecutionError> {
+        if self.preflight {
+            preflight::check(web3, info).await?;
+        }
+        if self.check_balance {
+            preflight::check_balance(web3, info).await?;
+        }
+        if let Some(guard) = &self.guard {
+            guard::check(guard.as_ref(), info)
+                .await
+                .map_err(ExecutionError::Rejected)?;
+        }
+        if let Some(hook) = &self.hook {
+            hook.before_sign(info);
+        }
+        Ok(())
+    }
 }
 
 /// Represents a prepared and optionally signed transaction that is ready for
@@ -128,6 +352,12 @@ struct TransactionOptions {
     pub data: Option<Bytes>,
     /// The transaction nonce.
     pub nonce: Option<U256>,
+    /// The extensions with additional transaction fields.
+    pub extensions: Vec<Arc<dyn TransactionExtension>>,
+    /// The signed authorizations of a set code transaction. These are only
+    /// used for transactions signed by the runtime, and are included in the
+    /// extensions otherwise.
+    pub authorization_list: Vec<SignedAuthorization>,
 }
 
 /// Transaction options specific to `TransactionRequests` since they may also
@@ -160,28 +390,48 @@ impl TransactionRequestOptions {
     }
 }
 
+/// Retrieves the transaction count of an account in the block with the
+/// specified tag.
+async fn transaction_count<T: Transport>(
+    web3: &Web3<T>,
+    address: Address,
+    tag: BlockTag,
+) -> Result<U256, ExecutionError> {
+    let count = web3
+        .transport()
+        .execute(
+            "eth_getTransactionCount",
+            vec![helpers::serialize(&address), helpers::serialize(&tag)],
+        )
+        .await?;
+    Ok(helpers::decode(count)?)
+}
+
 /// Build a transaction request to locally signed by the node before sending.
 async fn build_transaction_request_for_local_signing<T: Transport>(
     web3: Web3<T>,
     from: Option<Address>,
     gas_price: GasPrice,
     options: TransactionRequestOptions,
-) -> Result<TransactionRequest, ExecutionError> {
+    hooks: SignHooks,
+) -> Result<(TransactionRequest, TransactionInfo), ExecutionError> {
     let from = match from {
         Some(address) => address,
         None => *web3
             .eth()
             .accounts()
             .await?
-            .get(0)
+            .first()
             .ok_or(ExecutionError::NoLocalAccounts)?,
     };
     let gas = resolve_gas_limit(&web3, from, gas_price, &options.0).await?;
     let gas_price = gas_price.resolve_for_transaction_request(&web3).await?;
 
     let request = options.build_request(from, gas_price, Some(gas));
+    let info = TransactionInfo::from_request(&request, hooks.method.clone());
+    hooks.before_sign(&web3, &info).await?;
 
-    Ok(request)
+    Ok((request, info))
 }
 
 /// Build a locally signed transaction with a locked account.
@@ -191,14 +441,30 @@ async fn build_transaction_signed_with_locked_account<T: Transport>(
     password: Password,
     gas_price: GasPrice,
     options: TransactionRequestOptions,
-) -> Result<RawTransaction, ExecutionError> {
+    hooks: SignHooks,
+) -> Result<(RawTransaction, TransactionInfo), ExecutionError> {
     let gas = resolve_gas_limit(&web3, from, gas_price, &options.0).await?;
     let gas_price = gas_price.resolve_for_transaction_request(&web3).await?;
 
+    let extensions = options.0.extensions.clone();
     let request = options.build_request(from, gas_price, Some(gas));
-    let signed_tx = web3.personal().sign_transaction(request, &password).await?;
+    let info = TransactionInfo::from_request(&request, hooks.method.clone());
+    hooks.before_sign(&web3, &info).await?;
+    let signed_tx = if extensions.is_empty() {
+        web3.personal().sign_transaction(request, &password).await?
+    } else {
+        let params = vec![
+            extension::extend(&request, &extensions),
+            helpers::serialize(&password.as_ref()),
+        ];
+        let raw = web3
+            .transport()
+            .execute("personal_signTransaction", params)
+            .await?;
+        helpers::decode(raw)?
+    };
 
-    Ok(signed_tx)
+    Ok((signed_tx, info))
 }
 
 /// Build an offline signed transaction.
@@ -212,29 +478,173 @@ async fn build_offline_signed_transaction<T: Transport>(
     chain_id: Option<u64>,
     gas_price: GasPrice,
     options: TransactionOptions,
-) -> Result<SignedTransaction, ExecutionError> {
+    hooks: SignHooks,
+) -> Result<(SignedTransaction, TransactionInfo), ExecutionError> {
+    extension::ensure_none(&options.extensions)?;
     let gas = resolve_gas_limit(&web3, key.public_address(), gas_price, &options).await?;
     let gas_price = gas_price.resolve(&web3).await?;
 
-    let signed = web3
-        .accounts()
-        .sign_transaction(
-            TransactionParameters {
-                nonce: options.nonce,
-                gas_price: Some(gas_price),
-                gas,
-                to: options.to,
-                value: options.value.unwrap_or_default(),
-                data: options.data.unwrap_or_default(),
-                chain_id,
-                transaction_type: None,
-                access_list: None,
-            },
-            &key,
-        )
-        .await?;
+    let parameters = TransactionParameters {
+        nonce: options.nonce,
+        gas_price: Some(gas_price),
+        gas,
+        to: options.to,
+        value: options.value.unwrap_or_default(),
+        data: options.data.unwrap_or_default(),
+        chain_id,
+        transaction_type: None,
+        access_list: None,
+    };
+    let info =
+        TransactionInfo::from_parameters(key.public_address(), &parameters, hooks.method.clone());
+    hooks.before_sign(&web3, &info).await?;
+
+    let signed = web3.accounts().sign_transaction(parameters, &key).await?;
+
+    Ok((signed, info))
+}
+
+/// Build a transaction signed by an external signer.
+///
+/// Signers sign the transaction hash, so all transaction parameters including
+/// the nonce and chain ID are resolved before signing.
+async fn build_signer_signed_transaction<T: Transport>(
+    web3: Web3<T>,
+    signer: Arc<dyn Signer>,
+    chain_id: Option<u64>,
+    gas_price: GasPrice,
+    options: TransactionOptions,
+    hooks: SignHooks,
+) -> Result<(SignedTransaction, TransactionInfo), ExecutionError> {
+    extension::ensure_none(&options.extensions)?;
+    let from = signer.address();
+    let gas = resolve_gas_limit(&web3, from, gas_price, &options).await?;
+    let gas_price = gas_price.resolve(&web3).await?;
+    let nonce = match options.nonce {
+        Some(nonce) => nonce,
+        None => web3.eth().transaction_count(from, None).await?,
+    };
+    let chain_id = match chain_id {
+        Some(chain_id) => chain_id,
+        None => web3.eth().chain_id().await?.as_u64(),
+    };
+
+    let parameters = TransactionParameters {
+        nonce: Some(nonce),
+        gas_price: Some(gas_price),
+        gas,
+        to: options.to,
+        value: options.value.unwrap_or_default(),
+        data: options.data.unwrap_or_default(),
+        chain_id: Some(chain_id),
+        transaction_type: None,
+        access_list: None,
+    };
+    let info = TransactionInfo::from_parameters(from, &parameters, hooks.method.clone());
+    hooks.before_sign(&web3, &info).await?;
+
+    let signed = signer::sign_transaction(&web3, signer.as_ref(), parameters).await?;
+
+    Ok((signed, info))
+}
+
+/// Build a set code transaction signed with a private key or an external
+/// signer.
+///
+/// The transaction is signed as a type 4 transaction, with the resolved gas
+/// price as its maximum fee and the suggested priority fee, capped to the
+/// maximum fee, as its maximum priority fee.
+async fn build_set_code_transaction<T: Transport>(
+    web3: Web3<T>,
+    signer: Arc<dyn Signer>,
+    chain_id: Option<u64>,
+    gas_price: GasPrice,
+    mut options: TransactionOptions,
+    hooks: SignHooks,
+) -> Result<(Transaction, TransactionInfo), ExecutionError> {
+    extension::ensure_none(&options.extensions)?;
+    let to = options.to.ok_or(ExecutionError::MissingReceiver)?;
+    let authorization_list = std::mem::take(&mut options.authorization_list);
+    options.extensions = vec![Arc::new(AuthorizationList(authorization_list.clone()))];
+
+    let from = signer.address();
+    let gas = resolve_gas_limit(&web3, from, gas_price, &options).await?;
+    let gas_price = gas_price.resolve(&web3).await?;
+    let priority_fee = gas_price::suggest_priority_fee(&web3)
+        .await?
+        .capped(gas_price);
+    let nonce = match options.nonce {
+        Some(nonce) => nonce,
+        None => web3.eth().transaction_count(from, None).await?,
+    };
+    let chain_id = match chain_id {
+        Some(chain_id) => chain_id,
+        None => web3.eth().chain_id().await?.as_u64(),
+    };
 
-    Ok(signed)
+    let tx = SetCodeTransaction {
+        chain_id,
+        nonce,
+        max_priority_fee_per_gas: priority_fee.max_priority_fee_per_gas,
+        max_fee_per_gas: gas_price,
+        gas,
+        to,
+        value: options.value.unwrap_or_default(),
+        data: options.data.unwrap_or_default(),
+        access_list: Vec::new(),
+        authorization_list,
+    };
+    let mut info = TransactionInfo::from_parameters(
+        from,
+        &TransactionParameters {
+            nonce: Some(nonce),
+            gas_price: Some(gas_price),
+            gas,
+            to: Some(to),
+            value: tx.value,
+            data: tx.data.clone(),
+            chain_id: Some(chain_id),
+            transaction_type: Some(SET_CODE_TX_TYPE.into()),
+            access_list: None,
+        },
+        hooks.method.clone(),
+    );
+    info.priority_fee = Some(priority_fee);
+    hooks.before_sign(&web3, &info).await?;
+
+    Ok((tx.sign(signer.as_ref()).await?, info))
+}
+
+/// Build a transaction signed by a remote signer.
+///
+/// Remote signers may not have access to the node, so the nonce and gas
+/// price are resolved before signing.
+async fn build_remote_signed_transaction<T: Transport>(
+    web3: Web3<T>,
+    signer: RemoteSigner,
+    gas_price: GasPrice,
+    mut options: TransactionOptions,
+    hooks: SignHooks,
+) -> Result<((Bytes, H256), TransactionInfo), ExecutionError> {
+    let from = signer.address();
+    let gas = resolve_gas_limit(&web3, from, gas_price, &options).await?;
+    let gas_price = gas_price.resolve(&web3).await?;
+    if options.nonce.is_none() {
+        options.nonce = Some(
+            web3.eth()
+                .transaction_count(from, Some(BlockNumber::Pending))
+                .await?,
+        );
+    }
+
+    let extensions = options.extensions.clone();
+    let request =
+        TransactionRequestOptions(options, None).build_request(from, Some(gas_price), Some(gas));
+    let info = TransactionInfo::from_request(&request, hooks.method.clone());
+    hooks.before_sign(&web3, &info).await?;
+    let signed = signer.sign_transaction(request, &extensions).await?;
+
+    Ok((signed, info))
 }
 
 async fn resolve_gas_limit<T: Transport>(
@@ -245,9 +655,9 @@ async fn resolve_gas_limit<T: Transport>(
 ) -> Result<U256, ExecutionError> {
     match options.gas {
         Some(value) => Ok(value),
-        None => Ok(web3
-            .eth()
-            .estimate_gas(
+        None => {
+            estimate_gas(
+                web3,
                 CallRequest {
                     from: Some(from),
                     to: options.to,
@@ -258,16 +668,56 @@ async fn resolve_gas_limit<T: Transport>(
                     transaction_type: None,
                     access_list: None,
                 },
-                None,
+                &options.extensions,
             )
-            .await?),
+            .await
+        }
     }
 }
 
+/// Estimates the gas of a call request with the fields of the specified
+/// transaction extensions.
+pub(crate) async fn estimate_gas<T: Transport>(
+    web3: &Web3<T>,
+    request: CallRequest,
+    extensions: &[Arc<dyn TransactionExtension>],
+) -> Result<U256, ExecutionError> {
+    estimate_gas_with(web3, request, extensions, None, None).await
+}
+
+/// Estimates the gas of a call request with the fields of the specified
+/// transaction extensions, at the specified block and with the specified
+/// state overrides.
+pub(crate) async fn estimate_gas_with<T: Transport>(
+    web3: &Web3<T>,
+    request: CallRequest,
+    extensions: &[Arc<dyn TransactionExtension>],
+    block: Option<BlockId>,
+    overrides: Option<&StateOverrides>,
+) -> Result<U256, ExecutionError> {
+    if extensions.is_empty() && block.is_none() && overrides.is_none() {
+        return Ok(web3.eth().estimate_gas(request, None).await?);
+    }
+
+    let mut params = vec![extension::extend(&request, extensions)];
+    if block.is_some() || overrides.is_some() {
+        params.push(helpers::serialize(
+            &block.unwrap_or_else(|| BlockNumber::Latest.into()),
+        ));
+    }
+    if let Some(overrides) = overrides {
+        params.push(helpers::serialize(overrides));
+    }
+    let gas = web3.transport().execute("eth_estimateGas", params).await?;
+    Ok(helpers::decode(gas)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test::prelude::*;
+    use crate::transaction::{Authorization, PriorityFee, PriorityFeeSource};
+    use ethcontract_common::abi::{Function, Param, ParamType, StateMutability, Token};
 
     #[test]
     fn tx_build_local() {
@@ -283,8 +733,10 @@ mod tests {
             Some(from),
             GasPrice::Standard,
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build local transaction");
 
         transport.assert_request(
@@ -313,8 +765,10 @@ mod tests {
             None,
             GasPrice::Standard,
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build local transaction");
 
         transport.assert_request("eth_accounts", &[]);
@@ -347,8 +801,10 @@ mod tests {
             None,
             GasPrice::Scaled(2.0),
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build local transaction");
 
         transport.assert_request("eth_accounts", &[]);
@@ -375,8 +831,10 @@ mod tests {
             Some(from),
             GasPrice::Scaled(2.0),
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build local transaction");
 
         transport.assert_request("eth_estimateGas", &[json!({ "from": json!(from) })]);
@@ -402,8 +860,10 @@ mod tests {
             Some(from),
             GasPrice::Value(1337.into()),
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build local transaction");
 
         transport.assert_request(
@@ -427,8 +887,10 @@ mod tests {
             None,
             GasPrice::Standard,
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect_err("unexpected success building transaction");
 
         transport.assert_request("eth_accounts", &[]);
@@ -478,8 +940,10 @@ mod tests {
                 },
                 None,
             ),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build locked transaction");
 
         transport.assert_request(
@@ -535,8 +999,10 @@ mod tests {
             pw.into(),
             GasPrice::Scaled(2.0),
             TransactionRequestOptions::default(),
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build locked transaction");
 
         transport.assert_request("eth_estimateGas", &[json!({ "from": from })]);
@@ -586,8 +1052,10 @@ mod tests {
                 to: Some(to),
                 ..Default::default()
             },
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build offline transaction");
 
         // assert that we ask the node for all the missing values
@@ -616,8 +1084,10 @@ mod tests {
                 nonce: Some(nonce),
                 ..Default::default()
             },
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build offline transaction");
 
         transport.assert_request("eth_gasPrice", &[]);
@@ -634,8 +1104,10 @@ mod tests {
                 nonce: Some(nonce),
                 ..Default::default()
             },
+            SignHooks::default(),
         )
         .immediate()
+        .map(|(tx, _)| tx)
         .expect("failed to build offline transaction");
 
         // assert that if we provide all the values then we can sign right away
@@ -645,4 +1117,185 @@ mod tests {
         assert_eq!(tx1, tx2);
         assert_eq!(tx2, tx3);
     }
+
+    #[test]
+    fn tx_preview() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let from = key.public_address();
+        let to = addr!("0x0102030405060708091011121314151617181920");
+        #[allow(deprecated)]
+        let function = Function {
+            name: "transfer".to_owned(),
+            inputs: vec![
+                Param {
+                    name: "to".to_owned(),
+                    kind: ParamType::Address,
+                },
+                Param {
+                    name: "amount".to_owned(),
+                    kind: ParamType::Uint(256),
+                },
+            ],
+            outputs: vec![],
+            constant: false,
+            state_mutability: StateMutability::NonPayable,
+        };
+        let data = function
+            .encode_input(&[Token::Address(to), Token::Uint(1000.into())])
+            .unwrap();
+
+        transport.add_response(json!("0x5208"));
+        transport.add_response(json!("0x3b9aca00"));
+        transport.add_response(json!("0x7"));
+        transport.add_response(json!("0x1"));
+
+        let mut builder = TransactionBuilder::new(web3)
+            .from(Account::Offline(key, None))
+            .to(to)
+            .data(Bytes(data.clone()));
+        builder.function = Some(function);
+        let preview = builder.preview().immediate().expect("preview failed");
+
+        transport.assert_request(
+            "eth_estimateGas",
+            &[json!({
+                "from": from,
+                "to": to,
+                "data": Bytes(data.clone()),
+            })],
+        );
+        transport.assert_request("eth_gasPrice", &[]);
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("latest")]);
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_no_more_requests();
+
+        assert_eq!(
+            preview,
+            TransactionPreview {
+                from,
+                to: Some(to),
+                nonce: 7.into(),
+                data: Bytes(data),
+                method: Some("transfer(address,uint256)".to_owned()),
+                args: vec![format!("{:?}", to), "1000".to_owned()],
+                gas: 21_000.into(),
+                gas_price: 1_000_000_000.into(),
+                chain_id: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(preview.max_fee(), U256::from(21_000_000_000_000u64));
+        assert_eq!(
+            serde_json::to_value(&preview).unwrap()["method"],
+            json!("transfer(address,uint256)")
+        );
+    }
+
+    #[test]
+    fn tx_build_set_code() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let from: Address = key.public_address();
+        let to = addr!("0x0000000000000000000000000000000000000042");
+        let authorization = Authorization::new(1, to, 1).sign(&key).immediate().unwrap();
+
+        let gas = uint!("0x9a5");
+        let gas_price = uint!("0x1ce");
+        let nonce = uint!("0x42");
+
+        let priority_fee = uint!("0x2a");
+
+        transport.add_response(json!(gas));
+        transport.add_response(json!(gas_price));
+        transport.add_response(json!(priority_fee));
+        transport.add_response(json!(nonce));
+
+        let (tx, info) = TransactionBuilder::new(web3.clone())
+            .from(Account::Offline(key.clone(), Some(1)))
+            .to(from)
+            .authorization(authorization)
+            .build_with_info()
+            .immediate()
+            .expect("failed to build set code transaction");
+
+        transport.assert_request(
+            "eth_estimateGas",
+            &[json!({
+                "from": from,
+                "to": from,
+                "type": "0x4",
+                "authorizationList": [authorization],
+            })],
+        );
+        transport.assert_request("eth_gasPrice", &[]);
+        transport.assert_request("eth_maxPriorityFeePerGas", &[]);
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("latest")]);
+        transport.assert_no_more_requests();
+        assert_eq!(
+            info.priority_fee,
+            Some(PriorityFee {
+                max_priority_fee_per_gas: priority_fee,
+                source: PriorityFeeSource::Node,
+            })
+        );
+
+        let expected = SetCodeTransaction {
+            chain_id: 1,
+            nonce,
+            max_priority_fee_per_gas: priority_fee,
+            max_fee_per_gas: gas_price,
+            gas,
+            to: from,
+            value: 0.into(),
+            data: Bytes::default(),
+            access_list: Vec::new(),
+            authorization_list: vec![authorization],
+        }
+        .sign(&key)
+        .immediate()
+        .unwrap();
+        assert_eq!(tx, expected);
+
+        let err = TransactionBuilder::new(web3.clone())
+            .from(Account::Offline(key, Some(1)))
+            .authorization(authorization)
+            .build()
+            .immediate()
+            .unwrap_err();
+        assert!(matches!(err, ExecutionError::MissingReceiver));
+        transport.assert_no_more_requests();
+
+        // Transactions signed by the node include the authorization list as
+        // an extension.
+        let hash = H256::repeat_byte(0xff);
+        transport.add_response(json!(hash));
+        let pending = TransactionBuilder::new(web3)
+            .from(Account::Local(from, None))
+            .to(from)
+            .gas(gas)
+            .gas_price(GasPrice::Value(gas_price))
+            .authorization(authorization)
+            .broadcast()
+            .immediate()
+            .expect("failed to send set code transaction");
+
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": from,
+                "gas": gas,
+                "gasPrice": gas_price,
+                "type": "0x4",
+                "authorizationList": [authorization],
+            })],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(pending.hash, hash);
+    }
 }
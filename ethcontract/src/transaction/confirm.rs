@@ -7,7 +7,7 @@
 //! some of this can move upstream into the `web3` crate.
 
 use crate::errors::ExecutionError;
-use crate::transaction::TransactionResult;
+use crate::transaction::{self, TransactionResult};
 use futures_timer::Delay;
 use std::cmp::min;
 use std::time::Duration;
@@ -37,6 +37,8 @@ pub struct ConfirmParams {
     pub poll_interval_factor: f32,
     /// The maximum number of blocks to wait for a transaction to get confirmed.
     pub block_timeout: Option<usize>,
+    /// The maximum amount of time to wait for a transaction to get confirmed.
+    pub timeout: Option<Duration>,
 }
 
 /// Default minimal delay between polling the node for transaction confirmation.
@@ -76,6 +78,7 @@ impl ConfirmParams {
             poll_interval_max: DEFAULT_POLL_INTERVAL_MAX,
             poll_interval_factor: DEFAULT_POLL_INTERVAL_FACTOR,
             block_timeout: DEFAULT_BLOCK_TIMEOUT,
+            timeout: None,
         }
     }
 
@@ -97,6 +100,26 @@ impl ConfirmParams {
         self
     }
 
+    /// Derives the exponential backoff settings from the expected block time
+    /// of the chain, so that the node is polled a few times per block at
+    /// first and at most once per block after backing off.
+    #[inline]
+    pub fn block_time(self, block_time: Duration) -> Self {
+        self.poll_interval(block_time / 4, block_time, 2.0)
+    }
+
+    /// Derives the exponential backoff settings from a block time hint,
+    /// unless they were changed from their defaults.
+    pub(crate) fn with_block_time_hint(self, block_time: Option<Duration>) -> Self {
+        let is_default = self.poll_interval_min == DEFAULT_POLL_INTERVAL_MIN
+            && self.poll_interval_max == DEFAULT_POLL_INTERVAL_MAX
+            && (self.poll_interval_factor - DEFAULT_POLL_INTERVAL_FACTOR).abs() < f32::EPSILON;
+        match block_time {
+            Some(block_time) if is_default => self.block_time(block_time),
+            _ => self,
+        }
+    }
+
     /// Set new value for [`poll_interval_min`].
     ///
     /// [`poll_interval_min`]: #structfield.poll_interval_min
@@ -132,6 +155,15 @@ impl ConfirmParams {
         self.block_timeout = block_timeout;
         self
     }
+
+    /// Set new value for [`timeout`].
+    ///
+    /// [`timeout`]: #structfield.timeout
+    #[inline]
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
 }
 
 impl Default for ConfirmParams {
@@ -141,10 +173,29 @@ impl Default for ConfirmParams {
 }
 
 /// Waits for a transaction to be confirmed.
+///
+/// Returns an `ExecutionError::ConfirmTimeout` error if the transaction does
+/// not get confirmed within the block timeout or timeout of the confirmation
+/// parameters.
 pub async fn wait_for_confirmation<T: Transport>(
     web3: &Web3<T>,
     tx: H256,
     params: ConfirmParams,
+) -> Result<TransactionReceipt, ExecutionError> {
+    let duration = params.timeout;
+    transaction::timeout(duration, confirm(web3, tx, params))
+        .await
+        .unwrap_or_else(|| {
+            Err(ExecutionError::ConfirmTimeout(Box::new(
+                TransactionResult::Hash(tx),
+            )))
+        })
+}
+
+async fn confirm<T: Transport>(
+    web3: &Web3<T>,
+    tx: H256,
+    params: ConfirmParams,
 ) -> Result<TransactionReceipt, ExecutionError> {
     let mut latest_block = None;
     let mut context = ConfirmationContext {
@@ -301,6 +352,24 @@ mod tests {
         })
     }
 
+    #[test]
+    fn block_time_hint() {
+        let block_time = Duration::from_secs(2);
+        let params = ConfirmParams::mined().with_block_time_hint(Some(block_time));
+        assert_eq!(params.poll_interval_min, Duration::from_millis(500));
+        assert_eq!(params.poll_interval_max, block_time);
+
+        let explicit = Duration::from_millis(100);
+        let params = ConfirmParams::mined()
+            .poll_interval(explicit, explicit, 1.0)
+            .with_block_time_hint(Some(block_time));
+        assert_eq!(params.poll_interval_min, explicit);
+        assert_eq!(params.poll_interval_max, explicit);
+
+        let params = ConfirmParams::mined().with_block_time_hint(None);
+        assert_eq!(params.poll_interval_min, DEFAULT_POLL_INTERVAL_MIN);
+    }
+
     #[test]
     fn confirm_mined_transaction() {
         let mut transport = TestTransport::new();
@@ -601,4 +670,32 @@ mod tests {
         transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
         transport.assert_no_more_requests();
     }
+
+    #[test]
+    fn confirmation_timeout_duration() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let hash = H256::repeat_byte(0xff);
+        let params = ConfirmParams::mined()
+            .poll_interval_min(Duration::from_secs(10))
+            .timeout(Some(Duration::from_millis(10)));
+
+        transport.add_response(json!("0x0"));
+        transport.add_response(json!(null));
+
+        let confirm = wait_for_confirmation(&web3, hash, params).wait();
+
+        assert!(
+            match &confirm {
+                Err(ExecutionError::ConfirmTimeout(tx)) => tx.hash() == hash,
+                _ => false,
+            },
+            "expected confirmation to time out but got {:?}",
+            confirm
+        );
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        transport.assert_no_more_requests();
+    }
 }
@@ -0,0 +1,176 @@
+//! Implementation of transaction extensions, which add nonstandard fields to
+//! transactions for chains with custom transaction envelopes, such as the
+//! `feeCurrency` field on Celo.
+
+use crate::errors::ExecutionError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt::Debug;
+use std::sync::Arc;
+use web3::helpers;
+use web3::types::Address;
+
+/// An extension of a transaction with additional fields.
+///
+/// Extension fields are added to the JSON-RPC transaction objects that are
+/// sent to the node for gas estimation, and to the node or remote signer for
+/// signing. Since the fields are part of the transaction envelope, extended
+/// transactions cannot be signed offline or with an external hash signer.
+pub trait TransactionExtension: Debug + Send + Sync {
+    /// Returns the additional fields of the transaction, keyed by their
+    /// JSON-RPC field names.
+    fn fields(&self) -> Map<String, Value>;
+}
+
+/// A transaction extension for paying transaction fees with an ERC20 token
+/// instead of the native currency, using the Celo `feeCurrency` field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FeeCurrency(pub Address);
+
+impl TransactionExtension for FeeCurrency {
+    fn fields(&self) -> Map<String, Value> {
+        let mut fields = Map::new();
+        fields.insert("feeCurrency".to_owned(), helpers::serialize(&self.0));
+        fields
+    }
+}
+
+/// A transaction extension with arbitrary additional fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExtraFields(pub Map<String, Value>);
+
+impl ExtraFields {
+    /// Adds a field to the extension.
+    pub fn field(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        self.0.insert(name.into(), helpers::serialize(&value));
+        self
+    }
+}
+
+impl TransactionExtension for ExtraFields {
+    fn fields(&self) -> Map<String, Value> {
+        self.0.clone()
+    }
+}
+
+/// Serializes a JSON-RPC transaction object with the fields of the specified
+/// extensions. Fields of later extensions take precedence over fields of
+/// earlier extensions and over standard transaction fields.
+pub(crate) fn extend<S: Serialize>(
+    transaction: &S,
+    extensions: &[Arc<dyn TransactionExtension>],
+) -> Value {
+    let mut value = helpers::serialize(transaction);
+    if let Value::Object(object) = &mut value {
+        for extension in extensions {
+            object.extend(extension.fields());
+        }
+    }
+    value
+}
+
+/// Returns an error if there are extensions for a transaction that is signed
+/// without the node or a remote signer.
+pub(crate) fn ensure_none(
+    extensions: &[Arc<dyn TransactionExtension>],
+) -> Result<(), ExecutionError> {
+    if extensions.is_empty() {
+        Ok(())
+    } else {
+        Err(ExecutionError::UnsupportedExtensions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, ResolveCondition, TransactionBuilder};
+    use web3::api::Web3;
+    use web3::types::{TransactionRequest, H256};
+
+    #[test]
+    fn extend_transaction_request() {
+        let request = TransactionRequest {
+            from: Address::repeat_byte(1),
+            gas: Some(0x1337.into()),
+            ..Default::default()
+        };
+        let extensions: [Arc<dyn TransactionExtension>; 2] = [
+            Arc::new(FeeCurrency(Address::repeat_byte(2))),
+            Arc::new(
+                ExtraFields::default()
+                    .field("gatewayFee", "0x42")
+                    .field("gas", "0x1"),
+            ),
+        ];
+
+        assert_eq!(
+            extend(&request, &extensions),
+            json!({
+                "from": Address::repeat_byte(1),
+                "gas": "0x1",
+                "feeCurrency": Address::repeat_byte(2),
+                "gatewayFee": "0x42",
+            }),
+        );
+    }
+
+    #[test]
+    fn send_with_fee_currency() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let from = Address::repeat_byte(1);
+        let token = Address::repeat_byte(3);
+        let hash = H256::repeat_byte(0xff);
+
+        transport.add_response(json!("0x1337")); // gas limit
+        transport.add_response(json!(hash));
+        let result = TransactionBuilder::new(web3)
+            .from(Account::Local(from, None))
+            .to(Address::repeat_byte(2))
+            .fee_currency(token)
+            .resolve(ResolveCondition::Pending)
+            .send()
+            .immediate()
+            .expect("send failed");
+
+        assert_eq!(result.hash(), hash);
+        transport.assert_request(
+            "eth_estimateGas",
+            &[json!({
+                "from": from,
+                "to": Address::repeat_byte(2),
+                "feeCurrency": token,
+            })],
+        );
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": Address::repeat_byte(2),
+                "gas": "0x1337",
+                "feeCurrency": token,
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn extensions_unsupported_for_offline_signing() {
+        let transport = TestTransport::new();
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+
+        let result = TransactionBuilder::new(Web3::new(transport.clone()))
+            .from(Account::Offline(key, Some(1)))
+            .gas(1.into())
+            .gas_price(1.into())
+            .nonce(0.into())
+            .fee_currency(Address::repeat_byte(3))
+            .build()
+            .immediate();
+
+        assert!(matches!(result, Err(ExecutionError::UnsupportedExtensions)));
+        transport.assert_no_more_requests();
+    }
+}
@@ -1,10 +1,15 @@
 //! Implementation of gas price estimation.
 
-use crate::errors::ExecutionError;
+use crate::errors::{ExecutionError, RpcError};
+use crate::rpc;
 use primitive_types::U256;
 use web3::api::Web3;
+use web3::error::Error as Web3Error;
 use web3::Transport;
 
+/// The number of blocks used for forecasting fees from the fee history.
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
 /// The gas price setting to use.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GasPrice {
@@ -22,6 +27,10 @@ pub enum GasPrice {
     /// the transaction `SendFuture` to not query the node for a gas price
     /// estimation.
     Value(U256),
+    /// A gas price forecast from the fee history of recent blocks for the
+    /// specified urgency, see [`forecast_fee`]. This requires the node to
+    /// support the EIP-1559 `eth_feeHistory` method.
+    Forecast(FeeUrgency),
 }
 
 impl GasPrice {
@@ -48,8 +57,8 @@ impl GasPrice {
 
     /// Resolves the gas price into a value. Returns a future that resolves once
     /// the gas price is calculated as this may require contacting the node for
-    /// gas price estimates in the case of `GasPrice::Standard` and
-    /// `GasPrice::Scaled`.
+    /// gas price estimates in the case of `GasPrice::Standard`,
+    /// `GasPrice::Scaled` and `GasPrice::Forecast`.
     pub async fn resolve<T: Transport>(self, web3: &Web3<T>) -> Result<U256, ExecutionError> {
         let resolved_gas_price = match self {
             GasPrice::Standard => web3.eth().gas_price().await?,
@@ -58,6 +67,7 @@ impl GasPrice {
                 scale_gas_price(gas_price, factor)
             }
             GasPrice::Value(value) => value,
+            GasPrice::Forecast(urgency) => forecast_fee(web3, urgency).await?.gas_price(),
         };
 
         Ok(resolved_gas_price)
@@ -109,6 +119,146 @@ impl_gas_price_from_integer! {
     u8, u16, u32, u64, u128, usize,
 }
 
+/// The urgency of a transaction, used for forecasting fees.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeeUrgency {
+    /// A low fee that may take a while to be included, using the 10th
+    /// percentile of recent priority fees.
+    Slow,
+    /// A fee that is usually included within a few blocks, using the median
+    /// of recent priority fees.
+    Standard,
+    /// A high fee that is usually included in the next block, using the 90th
+    /// percentile of recent priority fees.
+    Fast,
+}
+
+impl FeeUrgency {
+    /// Returns the percentile of the priority fees of recent blocks used for
+    /// forecasting fees for this urgency.
+    pub fn percentile(self) -> f64 {
+        match self {
+            FeeUrgency::Slow => 10.0,
+            FeeUrgency::Standard => 50.0,
+            FeeUrgency::Fast => 90.0,
+        }
+    }
+}
+
+/// EIP-1559 fee suggestions forecast from the fee history of recent blocks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FeeForecast {
+    /// The base fee per gas of the next block.
+    pub base_fee_per_gas: U256,
+    /// The suggested maximum fee per gas, which allows for the base fee to
+    /// double before the transaction is no longer includable.
+    pub max_fee_per_gas: U256,
+    /// The suggested maximum priority fee per gas.
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl FeeForecast {
+    /// Creates a fee forecast from a fee history with the priority fees at the
+    /// percentile with the specified index. Returns `None` if the fee history
+    /// does not include base fees or priority fees at the percentile.
+    pub fn from_history(history: &rpc::FeeHistory, percentile_index: usize) -> Option<Self> {
+        let base_fee_per_gas = history.next_base_fee_per_gas()?;
+        let max_priority_fee_per_gas = history.median_reward(percentile_index)?;
+        Some(FeeForecast {
+            base_fee_per_gas,
+            max_fee_per_gas: base_fee_per_gas
+                .saturating_mul(2.into())
+                .saturating_add(max_priority_fee_per_gas),
+            max_priority_fee_per_gas,
+        })
+    }
+
+    /// Returns the forecast gas price for legacy transactions, which is the
+    /// base fee of the next block plus the priority fee.
+    pub fn gas_price(&self) -> U256 {
+        self.base_fee_per_gas
+            .saturating_add(self.max_priority_fee_per_gas)
+    }
+}
+
+/// Forecasts EIP-1559 fees for a transaction with the specified urgency from
+/// the fee history of recent blocks.
+pub async fn forecast_fee<T: Transport>(
+    web3: &Web3<T>,
+    urgency: FeeUrgency,
+) -> Result<FeeForecast, ExecutionError> {
+    let history = rpc::fee_history(web3, FEE_HISTORY_BLOCKS, &[urgency.percentile()]).await?;
+    FeeForecast::from_history(&history, 0).ok_or_else(|| {
+        Web3Error::InvalidResponse("fee history without base or priority fees".to_owned()).into()
+    })
+}
+
+/// The strategy that was used for choosing the priority fee of an EIP-1559
+/// transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PriorityFeeSource {
+    /// The priority fee was specified explicitly.
+    Specified,
+    /// The priority fee suggested by the node with `eth_maxPriorityFeePerGas`.
+    Node,
+    /// The median priority fee of recent blocks from `eth_feeHistory`, used
+    /// for nodes that do not support `eth_maxPriorityFeePerGas`.
+    FeeHistory,
+}
+
+/// The priority fee of an EIP-1559 transaction along with the strategy that
+/// was used for choosing it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PriorityFee {
+    /// The maximum priority fee per gas.
+    pub max_priority_fee_per_gas: U256,
+    /// How the priority fee was chosen.
+    pub source: PriorityFeeSource,
+}
+
+impl PriorityFee {
+    /// Creates an explicitly specified priority fee.
+    pub fn specified(max_priority_fee_per_gas: U256) -> Self {
+        PriorityFee {
+            max_priority_fee_per_gas,
+            source: PriorityFeeSource::Specified,
+        }
+    }
+
+    /// Caps the priority fee to the maximum fee of the transaction, since
+    /// transactions with a larger priority fee are invalid.
+    pub fn capped(self, max_fee_per_gas: U256) -> Self {
+        PriorityFee {
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.min(max_fee_per_gas),
+            ..self
+        }
+    }
+}
+
+/// Suggests a priority fee for an EIP-1559 transaction. This uses the priority
+/// fee suggested by the node, and falls back to the median priority fee of
+/// recent blocks for nodes that do not support `eth_maxPriorityFeePerGas`.
+pub async fn suggest_priority_fee<T: Transport>(
+    web3: &Web3<T>,
+) -> Result<PriorityFee, ExecutionError> {
+    match rpc::call(web3, "eth_maxPriorityFeePerGas", ()).await {
+        Ok(max_priority_fee_per_gas) => Ok(PriorityFee {
+            max_priority_fee_per_gas,
+            source: PriorityFeeSource::Node,
+        }),
+        Err(Web3Error::Rpc(err))
+            if RpcError::from_jsonrpc(&err) == Some(RpcError::MethodNotFound) =>
+        {
+            let forecast = forecast_fee(web3, FeeUrgency::Standard).await?;
+            Ok(PriorityFee {
+                max_priority_fee_per_gas: forecast.max_priority_fee_per_gas,
+                source: PriorityFeeSource::FeeHistory,
+            })
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 /// Apply a scaling factor to a gas price.
 fn scale_gas_price(gas_price: U256, factor: f64) -> U256 {
     // NOTE: U256 does not support floating point multiplication we have to
@@ -122,6 +272,7 @@ fn scale_gas_price(gas_price: U256, factor: f64) -> U256 {
 mod tests {
     use super::*;
     use crate::test::prelude::*;
+    use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
 
     #[test]
     fn gas_price_scalling() {
@@ -169,6 +320,62 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn forecast_fees() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x6e", "0x78"],
+            "gasUsedRatio": [0.5, 0.9],
+            "reward": [["0x5"], ["0x9"]],
+        }));
+        let forecast = forecast_fee(&web3, FeeUrgency::Fast)
+            .immediate()
+            .expect("error forecasting fee");
+
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([90.0])],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(
+            forecast,
+            FeeForecast {
+                base_fee_per_gas: 120.into(),
+                max_fee_per_gas: 249.into(),
+                max_priority_fee_per_gas: 9.into(),
+            }
+        );
+
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x5"]],
+        }));
+        assert_eq!(
+            GasPrice::Forecast(FeeUrgency::Slow)
+                .resolve(&web3)
+                .immediate()
+                .expect("error resolving gas price"),
+            105.into()
+        );
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([10.0])],
+        );
+
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "gasUsedRatio": [],
+        }));
+        assert!(forecast_fee(&web3, FeeUrgency::Standard)
+            .immediate()
+            .is_err());
+    }
+
     #[test]
     fn resolve_gas_price_for_transaction_request() {
         let mut transport = TestTransport::new();
@@ -205,4 +412,50 @@ mod tests {
         );
         transport.assert_no_more_requests();
     }
+
+    #[test]
+    fn priority_fee_suggestion() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!("0x3b9aca00"));
+        assert_eq!(
+            suggest_priority_fee(&web3).immediate().unwrap(),
+            PriorityFee {
+                max_priority_fee_per_gas: 1_000_000_000.into(),
+                source: PriorityFeeSource::Node,
+            }
+        );
+        transport.assert_request("eth_maxPriorityFeePerGas", &[]);
+        transport.assert_no_more_requests();
+
+        transport.add_error(Web3Error::Rpc(JsonrpcError {
+            code: ErrorCode::MethodNotFound,
+            message: "the method eth_maxPriorityFeePerGas does not exist/is not available"
+                .to_owned(),
+            data: None,
+        }));
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x6e"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x7"]],
+        }));
+        let fee = suggest_priority_fee(&web3).immediate().unwrap();
+        assert_eq!(
+            fee,
+            PriorityFee {
+                max_priority_fee_per_gas: 7.into(),
+                source: PriorityFeeSource::FeeHistory,
+            }
+        );
+        transport.assert_request("eth_maxPriorityFeePerGas", &[]);
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([50.0])],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(fee.capped(5.into()).max_priority_fee_per_gas, 5.into());
+    }
 }
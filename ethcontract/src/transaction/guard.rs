@@ -0,0 +1,185 @@
+//! Guards for enforcing policies on outgoing transactions before they are
+//! signed, for example allowlists of contracts and methods or value limits.
+
+use crate::transaction::TransactionInfo;
+use futures::future::{self, BoxFuture};
+use std::fmt::Debug;
+
+/// The decision of a `TransactionGuard` for an outgoing transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GuardDecision {
+    /// The transaction is approved and can be signed.
+    Approve,
+    /// The transaction is rejected for the specified reason.
+    Reject(String),
+    /// The transaction requires explicit confirmation, for the specified
+    /// reason, before it can be signed. See
+    /// [`TransactionGuard::confirm`] for more details.
+    RequireConfirmation(String),
+}
+
+/// A guard that is consulted before any transaction is signed or sent, and
+/// that can approve or reject it.
+///
+/// Guards can be set for individual transactions with
+/// [`TransactionBuilder::guard`](crate::transaction::TransactionBuilder::guard)
+/// or for all methods of a contract instance with its `MethodDefaults`.
+/// Rejected transactions fail with an `ExecutionError::Rejected` error
+/// without being signed.
+pub trait TransactionGuard: Debug + Send + Sync {
+    /// Checks the fully resolved transaction parameters of an outgoing
+    /// transaction.
+    fn check<'a>(&'a self, tx: &'a TransactionInfo) -> BoxFuture<'a, GuardDecision>;
+
+    /// Requests confirmation for a transaction for which the `check` method
+    /// returned `GuardDecision::RequireConfirmation`. This can be used for
+    /// implementing human-in-the-loop flows, and the transaction is rejected
+    /// if this resolves to `false`.
+    ///
+    /// By default, no transactions are confirmed.
+    fn confirm<'a>(&'a self, tx: &'a TransactionInfo, reason: &'a str) -> BoxFuture<'a, bool> {
+        let _ = (tx, reason);
+        Box::pin(future::ready(false))
+    }
+}
+
+/// Consults a guard for a transaction, returning the reason if it was
+/// rejected.
+pub(crate) async fn check(
+    guard: &dyn TransactionGuard,
+    tx: &TransactionInfo,
+) -> Result<(), String> {
+    match guard.check(tx).await {
+        GuardDecision::Approve => Ok(()),
+        GuardDecision::Reject(reason) => Err(reason),
+        GuardDecision::RequireConfirmation(reason) => {
+            if guard.confirm(tx, &reason).await {
+                Ok(())
+            } else {
+                Err(reason)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ExecutionError;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, TransactionBuilder};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use web3::api::Web3;
+    use web3::types::{Address, U256};
+
+    /// A guard that rejects transactions above a value limit, and requires
+    /// confirmation for transactions to unknown addresses.
+    #[derive(Debug)]
+    struct PolicyGuard {
+        allowed: Address,
+        max_value: U256,
+        confirm: bool,
+        asked: AtomicBool,
+    }
+
+    impl PolicyGuard {
+        fn new(confirm: bool) -> Self {
+            PolicyGuard {
+                allowed: Address::repeat_byte(1),
+                max_value: 100.into(),
+                confirm,
+                asked: AtomicBool::new(false),
+            }
+        }
+    }
+
+    impl TransactionGuard for PolicyGuard {
+        fn check<'a>(&'a self, tx: &'a TransactionInfo) -> BoxFuture<'a, GuardDecision> {
+            let decision = if tx.value > self.max_value {
+                GuardDecision::Reject("value too high".to_owned())
+            } else if tx.to != Some(self.allowed) {
+                GuardDecision::RequireConfirmation("unknown receiver".to_owned())
+            } else {
+                GuardDecision::Approve
+            };
+            Box::pin(future::ready(decision))
+        }
+
+        fn confirm<'a>(&'a self, _: &'a TransactionInfo, reason: &'a str) -> BoxFuture<'a, bool> {
+            assert_eq!(reason, "unknown receiver");
+            self.asked.store(true, Ordering::SeqCst);
+            Box::pin(future::ready(self.confirm))
+        }
+    }
+
+    fn transaction(
+        transport: &TestTransport,
+        guard: Arc<PolicyGuard>,
+        to: Address,
+        value: U256,
+    ) -> TransactionBuilder<TestTransport> {
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        TransactionBuilder::new(Web3::new(transport.clone()))
+            .from(Account::Offline(key, Some(77777)))
+            .to(to)
+            .value(value)
+            .gas(0x1337.into())
+            .gas_price(0x00ba_b10c.into())
+            .nonce(0x42.into())
+            .guard(guard)
+    }
+
+    #[test]
+    fn guard_approves_transaction() {
+        let transport = TestTransport::new();
+        let guard = Arc::new(PolicyGuard::new(false));
+
+        let tx = transaction(&transport, guard.clone(), Address::repeat_byte(1), 1.into())
+            .build()
+            .immediate();
+
+        assert!(tx.is_ok(), "unexpected error {:?}", tx);
+        assert!(!guard.asked.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn guard_rejects_transaction() {
+        let transport = TestTransport::new();
+        let guard = Arc::new(PolicyGuard::new(true));
+
+        let result = transaction(&transport, guard, Address::repeat_byte(1), 1000.into())
+            .send()
+            .immediate();
+
+        assert!(
+            matches!(&result, Err(ExecutionError::Rejected(reason)) if reason == "value too high"),
+            "unexpected result {:?}",
+            result,
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn guard_requires_confirmation() {
+        let transport = TestTransport::new();
+
+        let guard = Arc::new(PolicyGuard::new(true));
+        let tx = transaction(&transport, guard.clone(), Address::repeat_byte(2), 1.into())
+            .build()
+            .immediate();
+        assert!(tx.is_ok(), "unexpected error {:?}", tx);
+        assert!(guard.asked.load(Ordering::SeqCst));
+
+        let guard = Arc::new(PolicyGuard::new(false));
+        let tx = transaction(&transport, guard.clone(), Address::repeat_byte(2), 1.into())
+            .build()
+            .immediate();
+        assert!(
+            matches!(&tx, Err(ExecutionError::Rejected(reason)) if reason == "unknown receiver"),
+            "unexpected result {:?}",
+            tx,
+        );
+        assert!(guard.asked.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,262 @@
+//! Hooks for observing outgoing transactions, for example for capturing audit
+//! logs of everything that was signed and sent.
+
+use crate::abi;
+use crate::transaction::{PendingTransaction, PriorityFee};
+use ethcontract_common::abi::{Function, Token};
+use ethcontract_common::abiext::FunctionExt;
+use std::fmt::Debug;
+use web3::types::{Address, Bytes, TransactionParameters, TransactionRequest, U256};
+
+/// The resolved parameters of an outgoing transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionInfo {
+    /// The sender of the transaction.
+    pub from: Address,
+    /// The receiver of the transaction, or `None` for contract deployments.
+    pub to: Option<Address>,
+    /// The gas limit of the transaction.
+    pub gas: U256,
+    /// The gas price of the transaction. This is `None` if the gas price is
+    /// determined by the node when it signs the transaction. For EIP-1559
+    /// transactions, this is the maximum fee per gas.
+    pub gas_price: Option<U256>,
+    /// The priority fee of EIP-1559 transactions, along with the strategy
+    /// that was used for choosing it. This is `None` for legacy transactions.
+    pub priority_fee: Option<PriorityFee>,
+    /// The ETH value sent with the transaction.
+    pub value: U256,
+    /// The transaction calldata.
+    pub data: Bytes,
+    /// The transaction nonce. This is `None` if the nonce is determined by the
+    /// node or the signer when signing the transaction.
+    pub nonce: Option<U256>,
+    /// The decoded contract method call, if the transaction was created by a
+    /// contract method builder.
+    pub method: Option<MethodCall>,
+}
+
+/// A decoded contract method call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodCall {
+    /// The signature of the method, for example `transfer(address,uint256)`.
+    pub signature: String,
+    /// The decoded method parameters.
+    pub params: Vec<Token>,
+}
+
+impl MethodCall {
+    /// Decodes a method call from its ABI function and calldata. Returns `None`
+    /// if the calldata does not match the function.
+    pub fn decode(function: &Function, data: &[u8]) -> Option<Self> {
+        if data.len() < 4 || data[..4] != function.selector() {
+            return None;
+        }
+        Some(MethodCall {
+            signature: function.abi_signature(),
+            params: abi::decode_inputs(function, &data[4..]).ok()?,
+        })
+    }
+}
+
+impl TransactionInfo {
+    /// Returns the maximum fee in wei that the transaction can pay, that is
+    /// the gas limit multiplied by the gas price. This is `None` if the gas
+    /// price is determined by the node when it signs the transaction.
+    pub fn max_fee(&self) -> Option<U256> {
+        self.gas.checked_mul(self.gas_price?)
+    }
+
+    pub(crate) fn from_request(request: &TransactionRequest, method: Option<MethodCall>) -> Self {
+        TransactionInfo {
+            from: request.from,
+            to: request.to,
+            gas: request.gas.unwrap_or_default(),
+            gas_price: request.gas_price,
+            priority_fee: None,
+            value: request.value.unwrap_or_default(),
+            data: request.data.clone().unwrap_or_default(),
+            nonce: request.nonce,
+            method,
+        }
+    }
+
+    pub(crate) fn from_parameters(
+        from: Address,
+        parameters: &TransactionParameters,
+        method: Option<MethodCall>,
+    ) -> Self {
+        TransactionInfo {
+            from,
+            to: parameters.to,
+            gas: parameters.gas,
+            gas_price: parameters.gas_price,
+            priority_fee: None,
+            value: parameters.value,
+            data: parameters.data.clone(),
+            nonce: parameters.nonce,
+            method,
+        }
+    }
+}
+
+/// A hook that is notified of outgoing transactions.
+///
+/// Hooks can be set for individual transactions with
+/// [`TransactionBuilder::hook`](crate::transaction::TransactionBuilder::hook)
+/// or for all methods of a contract instance with its `MethodDefaults`.
+pub trait TransactionHook: Debug + Send + Sync {
+    /// Called with the fully resolved transaction parameters just before the
+    /// transaction is signed. For transactions that are signed by the node,
+    /// this is called just before the transaction is sent.
+    fn before_sign(&self, tx: &TransactionInfo) {
+        let _ = tx;
+    }
+
+    /// Called after the transaction was successfully sent.
+    fn after_send(&self, tx: &TransactionInfo, pending: &PendingTransaction) {
+        let _ = (tx, pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, TransactionBuilder};
+    use ethcontract_common::abi::{Param, ParamType, StateMutability};
+    use std::sync::{Arc, Mutex};
+    use web3::api::Web3;
+
+    fn transfer() -> Function {
+        #[allow(deprecated)]
+        Function {
+            name: "transfer".to_owned(),
+            inputs: vec![
+                Param {
+                    name: "to".to_owned(),
+                    kind: ParamType::Address,
+                },
+                Param {
+                    name: "amount".to_owned(),
+                    kind: ParamType::Uint(256),
+                },
+            ],
+            outputs: vec![],
+            constant: false,
+            state_mutability: StateMutability::NonPayable,
+        }
+    }
+
+    #[test]
+    fn decode_method_call() {
+        let function = transfer();
+        let params = vec![
+            Token::Address(Address::repeat_byte(1)),
+            Token::Uint(42.into()),
+        ];
+        let data = function.encode_input(&params).unwrap();
+
+        assert_eq!(
+            MethodCall::decode(&function, &data),
+            Some(MethodCall {
+                signature: "transfer(address,uint256)".to_owned(),
+                params,
+            })
+        );
+        assert_eq!(MethodCall::decode(&function, &data[..4]), None);
+        assert_eq!(MethodCall::decode(&function, &[0; 68]), None);
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingHook {
+        events: Mutex<Vec<(&'static str, TransactionInfo)>>,
+    }
+
+    impl TransactionHook for RecordingHook {
+        fn before_sign(&self, tx: &TransactionInfo) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(("before_sign", tx.clone()));
+        }
+
+        fn after_send(&self, tx: &TransactionInfo, pending: &PendingTransaction) {
+            assert_eq!(pending.nonce, tx.nonce);
+            self.events.lock().unwrap().push(("after_send", tx.clone()));
+        }
+    }
+
+    #[test]
+    fn hook_is_notified_of_outgoing_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let function = transfer();
+        let params = vec![
+            Token::Address(Address::repeat_byte(1)),
+            Token::Uint(42.into()),
+        ];
+        let data = Bytes(function.encode_input(&params).unwrap());
+        let hook = Arc::new(RecordingHook::default());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let from = key.public_address();
+        let mut builder = TransactionBuilder::new(web3)
+            .from(Account::Offline(key, Some(77777)))
+            .to(Address::repeat_byte(2))
+            .data(data.clone())
+            .gas(0x1337.into())
+            .gas_price(0x00ba_b10c.into())
+            .nonce(0x42.into())
+            .hook(hook.clone());
+        builder.function = Some(function);
+
+        let hash = match builder.clone().build().immediate().unwrap() {
+            crate::transaction::Transaction::Raw { hash, .. } => hash,
+            _ => panic!("expected raw transaction"),
+        };
+        hook.events.lock().unwrap().clear();
+
+        transport.add_response(json!(hash));
+        builder.broadcast().immediate().expect("broadcast failed");
+
+        let info = TransactionInfo {
+            from,
+            to: Some(Address::repeat_byte(2)),
+            gas: 0x1337.into(),
+            gas_price: Some(0x00ba_b10c.into()),
+            priority_fee: None,
+            value: 0.into(),
+            data,
+            nonce: Some(0x42.into()),
+            method: Some(MethodCall {
+                signature: "transfer(address,uint256)".to_owned(),
+                params,
+            }),
+        };
+        assert_eq!(
+            *hook.events.lock().unwrap(),
+            vec![("before_sign", info.clone()), ("after_send", info)],
+        );
+    }
+
+    #[test]
+    fn max_fee() {
+        let mut info = TransactionInfo {
+            from: Address::zero(),
+            to: None,
+            gas: 21000.into(),
+            gas_price: Some(2.into()),
+            priority_fee: None,
+            value: 0.into(),
+            data: Default::default(),
+            nonce: None,
+            method: None,
+        };
+        assert_eq!(info.max_fee(), Some(42000.into()));
+
+        info.gas_price = None;
+        assert_eq!(info.max_fee(), None);
+    }
+}
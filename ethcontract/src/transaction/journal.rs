@@ -0,0 +1,971 @@
+//! Implementation of a persistent journal of sent transactions, so that
+//! transactions that were signed or broadcast before a crash can be found and
+//! resumed when the process restarts.
+//!
+//! Transactions sent with a journal are recorded before they are broadcast,
+//! and each transition of their lifecycle is recorded as it happens. On
+//! startup, [`TxJournal::recover`] reconciles the unfinished entries of the
+//! journal against the chain:
+//!
+//! ```ignore
+//! let journal = TxJournal::new(FileStore::new("transactions.journal"));
+//! for recovered in journal.recover(&web3).await? {
+//!     match recovered.action {
+//!         Recovery::Pending(_) | Recovery::Rebroadcast(_) => {
+//!             journal.wait(&web3, &recovered.entry, ConfirmParams::mined()).await?;
+//!         }
+//!         Recovery::Replace => {
+//!             journal.replace(&recovered.entry, retry_transaction()).await?;
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use crate::errors::{ExecutionError, JournalError};
+use crate::transaction::confirm::ConfirmParams;
+use crate::transaction::{PendingTransaction, TransactionBuilder, TransactionResult};
+use futures::future::{self, BoxFuture};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::{Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{Address, BlockNumber, TransactionId, TransactionReceipt, H256, U64};
+use web3::Transport;
+
+/// The state of a journaled transaction.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum JournalStatus {
+    /// The transaction was signed but it is not known whether it was received
+    /// by the node.
+    Signed,
+    /// The transaction was received by the node.
+    Broadcast,
+    /// The transaction was mined.
+    #[serde(rename_all = "camelCase")]
+    Mined {
+        /// The hash of the block the transaction was mined in.
+        block_hash: H256,
+        /// The number of the block the transaction was mined in.
+        block_number: U64,
+        /// Whether the transaction executed successfully.
+        success: bool,
+    },
+    /// The nonce of the transaction was used by a different transaction, so
+    /// the transaction can no longer be mined.
+    Dropped,
+    /// The transaction was replaced by a transaction with the same nonce.
+    Replaced {
+        /// The hash of the replacement transaction.
+        by: H256,
+    },
+}
+
+impl JournalStatus {
+    /// Returns whether the transaction reached a final state and needs no
+    /// further recovery.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            JournalStatus::Mined { .. } | JournalStatus::Dropped | JournalStatus::Replaced { .. }
+        )
+    }
+
+    /// Returns the name of the status, as used by journal stores.
+    pub fn name(&self) -> &'static str {
+        match self {
+            JournalStatus::Signed => "signed",
+            JournalStatus::Broadcast => "broadcast",
+            JournalStatus::Mined { .. } => "mined",
+            JournalStatus::Dropped => "dropped",
+            JournalStatus::Replaced { .. } => "replaced",
+        }
+    }
+}
+
+/// A journaled transaction with its latest state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// The sender of the transaction.
+    pub from: Address,
+    /// The pending transaction, with the raw signed transaction for
+    /// transactions that were not signed by the node.
+    pub transaction: PendingTransaction,
+    /// The state of the transaction.
+    #[serde(flatten)]
+    pub status: JournalStatus,
+}
+
+impl JournalEntry {
+    /// Creates a new journal entry for a transaction.
+    pub fn new(from: Address, transaction: PendingTransaction, status: JournalStatus) -> Self {
+        JournalEntry {
+            from,
+            transaction,
+            status,
+        }
+    }
+
+    /// Returns the hash of the transaction.
+    pub fn hash(&self) -> H256 {
+        self.transaction.hash
+    }
+
+    /// Returns the entry with a different status.
+    pub fn with_status(&self, status: JournalStatus) -> Self {
+        JournalEntry {
+            status,
+            ..self.clone()
+        }
+    }
+}
+
+/// Storage for journal entries.
+///
+/// Stores must make recorded entries durable before returning, since
+/// transactions are broadcast right after they were recorded.
+pub trait JournalStore: Debug + Send + Sync {
+    /// Records an entry, replacing the previously recorded state of the
+    /// transaction with the same hash.
+    fn record<'a>(&'a self, entry: &'a JournalEntry) -> BoxFuture<'a, Result<(), JournalError>>;
+
+    /// Returns the latest state of all recorded transactions, in the order
+    /// they were first recorded.
+    fn entries(&self) -> BoxFuture<'_, Result<Vec<JournalEntry>, JournalError>>;
+}
+
+/// A journal of sent transactions.
+#[derive(Clone, Debug)]
+pub struct TxJournal(Arc<dyn JournalStore>);
+
+/// The recovery of an unfinished journal entry.
+#[derive(Clone, Debug)]
+pub struct Recovered {
+    /// The entry with its reconciled state.
+    pub entry: JournalEntry,
+    /// The action that was taken for the entry.
+    pub action: Recovery,
+}
+
+/// The outcome of reconciling an unfinished journal entry with the chain.
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Recovery {
+    /// The transaction was mined.
+    Mined(TransactionReceipt),
+    /// The transaction is known to the node and still pending.
+    Pending(PendingTransaction),
+    /// The transaction was no longer known to the node and was broadcast
+    /// again.
+    Rebroadcast(PendingTransaction),
+    /// The nonce of the transaction was used by a different transaction.
+    Dropped,
+    /// The transaction is not known to the node and could not be broadcast
+    /// again, either because it was signed by the node or because the node
+    /// rejected it, and should be replaced with [`TxJournal::replace`].
+    Replace,
+}
+
+impl TxJournal {
+    /// Creates a new journal with the specified store.
+    pub fn new(store: impl JournalStore + 'static) -> Self {
+        TxJournal(Arc::new(store))
+    }
+
+    /// Records an entry.
+    pub async fn record(&self, entry: &JournalEntry) -> Result<(), JournalError> {
+        self.0.record(entry).await
+    }
+
+    /// Returns the latest state of all journaled transactions.
+    pub async fn entries(&self) -> Result<Vec<JournalEntry>, JournalError> {
+        self.0.entries().await
+    }
+
+    /// Returns the journaled transactions that did not reach a final state.
+    pub async fn unfinished(&self) -> Result<Vec<JournalEntry>, JournalError> {
+        let mut entries = self.entries().await?;
+        entries.retain(|entry| !entry.status.is_final());
+        Ok(entries)
+    }
+
+    /// Reconciles the unfinished journal entries against the chain, recording
+    /// their new states.
+    ///
+    /// Mined transactions and transactions whose nonce was used by a
+    /// different transaction are finished. Transactions that are no longer
+    /// known to the node are broadcast again if they were signed by the
+    /// runtime, and need to be replaced otherwise.
+    pub async fn recover<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+    ) -> Result<Vec<Recovered>, ExecutionError> {
+        let mut recovered = Vec::new();
+        for entry in self.unfinished().await? {
+            let (status, action) = reconcile(web3, &entry).await?;
+            let entry = entry.with_status(status);
+            self.record(&entry).await?;
+            recovered.push(Recovered { entry, action });
+        }
+        Ok(recovered)
+    }
+
+    /// Waits for a journaled transaction to be mined, recording its receipt.
+    /// Returns an error if the transaction failed.
+    pub async fn wait<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        entry: &JournalEntry,
+        params: ConfirmParams,
+    ) -> Result<TransactionResult, ExecutionError> {
+        let result = entry.transaction.wait(web3, params).await;
+        let receipt = match &result {
+            Ok(TransactionResult::Receipt(receipt)) => Some(receipt),
+            Err(ExecutionError::Failure(receipt)) => Some(receipt.as_ref()),
+            _ => None,
+        };
+        if let Some(status) = receipt.and_then(mined_status) {
+            self.record(&entry.with_status(status)).await?;
+        }
+        result
+    }
+
+    /// Broadcasts a replacement for a journaled transaction, with the nonce of
+    /// the transaction, and records the transaction as replaced.
+    ///
+    /// The replacement must be sent by the sender of the transaction, and
+    /// must have a higher gas price than the transaction if it may still be
+    /// pending, for example the same transaction with a scaled gas price or a
+    /// no-op transfer to cancel it.
+    pub async fn replace<T: Transport>(
+        &self,
+        entry: &JournalEntry,
+        replacement: TransactionBuilder<T>,
+    ) -> Result<PendingTransaction, ExecutionError> {
+        let nonce = entry
+            .transaction
+            .nonce
+            .ok_or_else(|| JournalError::UnknownNonce(entry.hash()))?;
+        let sender = replacement.from.as_ref().map(|account| account.address());
+        if sender != Some(entry.from) {
+            return Err(JournalError::SenderMismatch(entry.from).into());
+        }
+
+        let pending = replacement
+            .nonce(nonce)
+            .journal(self.clone())
+            .broadcast()
+            .await?;
+        self.record(&entry.with_status(JournalStatus::Replaced { by: pending.hash }))
+            .await?;
+        Ok(pending)
+    }
+}
+
+/// Reconciles a journal entry with the chain, returning its new status.
+async fn reconcile<T: Transport>(
+    web3: &Web3<T>,
+    entry: &JournalEntry,
+) -> Result<(JournalStatus, Recovery), ExecutionError> {
+    let pending = &entry.transaction;
+    if let Some(receipt) = web3.eth().transaction_receipt(pending.hash).await? {
+        if let Some(status) = mined_status(&receipt) {
+            return Ok((status, Recovery::Mined(receipt)));
+        }
+    }
+    if web3
+        .eth()
+        .transaction(TransactionId::Hash(pending.hash))
+        .await?
+        .is_some()
+    {
+        return Ok((JournalStatus::Broadcast, Recovery::Pending(pending.clone())));
+    }
+
+    if let Some(nonce) = pending.nonce {
+        let count = web3
+            .eth()
+            .transaction_count(entry.from, Some(BlockNumber::Latest))
+            .await?;
+        if nonce < count {
+            // NOTE: The transaction may have been mined after its receipt was
+            //   requested, so check again before giving up on it.
+            if let Some(receipt) = web3.eth().transaction_receipt(pending.hash).await? {
+                if let Some(status) = mined_status(&receipt) {
+                    return Ok((status, Recovery::Mined(receipt)));
+                }
+            }
+            return Ok((JournalStatus::Dropped, Recovery::Dropped));
+        }
+    }
+
+    match pending.rebroadcast(web3).await {
+        Ok(true) => Ok((
+            JournalStatus::Broadcast,
+            Recovery::Rebroadcast(pending.clone()),
+        )),
+        Ok(false) | Err(ExecutionError::Web3(Web3Error::Rpc(_))) => {
+            Ok((entry.status.clone(), Recovery::Replace))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the status of a mined transaction from its receipt, or `None` if
+/// the receipt is for a pending transaction.
+fn mined_status(receipt: &TransactionReceipt) -> Option<JournalStatus> {
+    Some(JournalStatus::Mined {
+        block_hash: receipt.block_hash?,
+        block_number: receipt.block_number?,
+        success: receipt.status == Some(1.into()),
+    })
+}
+
+/// A journal store that appends entries to a file, with one JSON encoded
+/// entry per line.
+///
+/// The file grows with every recorded transition, and can be compacted with
+/// [`FileStore::compact`] to only keep the unfinished transactions.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    /// Creates a new store for the journal file at the specified path. The
+    /// file is created when the first entry is recorded.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileStore {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Returns the path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rewrites the journal file with only the latest state of the
+    /// unfinished transactions.
+    pub fn compact(&self) -> Result<(), JournalError> {
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.read()?;
+        entries.retain(|entry| !entry.status.is_final());
+
+        let mut contents = Vec::new();
+        for entry in &entries {
+            serde_json::to_writer(&mut contents, entry)?;
+            contents.push(b'\n');
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn append(&self, entry: &JournalEntry) -> Result<(), JournalError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let _lock = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(&self.path)?;
+        let end = truncate_partial_line(&mut file)?;
+        file.seek(SeekFrom::Start(end))?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn read(&self) -> Result<Vec<JournalEntry>, JournalError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries = Vec::<JournalEntry>::new();
+        let lines = contents.split_terminator('\n').collect::<Vec<_>>();
+        for (index, line) in lines.iter().enumerate() {
+            let entry = match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) => entry,
+                // NOTE: A crash while appending can leave a partially written
+                //   last line, for a transaction that was never broadcast.
+                Err(_) if index + 1 == lines.len() && !contents.ends_with('\n') => break,
+                Err(_) => return Err(JournalError::Corrupt(index + 1)),
+            };
+            match entries.iter_mut().find(|e| e.hash() == entry.hash()) {
+                Some(existing) => *existing = entry,
+                None => entries.push(entry),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Truncates a partially written last line, left by a crash while appending,
+/// so that the next entry starts on a new line. Returns the new length of the
+/// file.
+fn truncate_partial_line(file: &mut fs::File) -> Result<u64, JournalError> {
+    let len = file.metadata()?.len();
+    let mut buffer = [0; 4096];
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(buffer.len() as u64);
+        let chunk = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(newline) = chunk.iter().rposition(|byte| *byte == b'\n') {
+            end = start + newline as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+    if end != len {
+        file.set_len(end)?;
+    }
+    Ok(end)
+}
+
+impl JournalStore for FileStore {
+    fn record<'a>(&'a self, entry: &'a JournalEntry) -> BoxFuture<'a, Result<(), JournalError>> {
+        Box::pin(future::ready(self.append(entry)))
+    }
+
+    fn entries(&self) -> BoxFuture<'_, Result<Vec<JournalEntry>, JournalError>> {
+        Box::pin(future::ready(self.read()))
+    }
+}
+
+/// A connection to an SQLite database, implemented for the SQLite library
+/// used by the application.
+pub trait SqliteConnection: Debug + Send + Sync {
+    /// Executes a statement with the specified positional parameters.
+    fn execute<'a>(
+        &'a self,
+        statement: &'a str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<(), JournalError>>;
+
+    /// Executes a query with the specified positional parameters, returning
+    /// the columns of the resulting rows.
+    fn query<'a>(
+        &'a self,
+        statement: &'a str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<Vec<Vec<Value>>, JournalError>>;
+}
+
+/// A journal store that keeps the latest state of each transaction in an
+/// SQLite table.
+#[derive(Debug)]
+pub struct SqliteStore<C> {
+    connection: C,
+    table: String,
+}
+
+impl<C: SqliteConnection> SqliteStore<C> {
+    /// Creates a new store with entries in the specified table. The table
+    /// is created with [`SqliteStore::init`].
+    pub fn new(connection: C, table: impl Into<String>) -> Self {
+        SqliteStore {
+            connection,
+            table: table.into(),
+        }
+    }
+
+    /// Creates the journal table if it doesn't exist.
+    pub async fn init(&self) -> Result<(), JournalError> {
+        self.connection
+            .execute(&self.create_table_statement(), Vec::new())
+            .await
+    }
+
+    /// Returns the statement for creating the journal table.
+    pub fn create_table_statement(&self) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             hash TEXT PRIMARY KEY, \
+             sender TEXT NOT NULL, \
+             nonce TEXT, \
+             status TEXT NOT NULL, \
+             entry TEXT NOT NULL)",
+            self.table,
+        )
+    }
+
+    /// Returns the statement for recording an entry.
+    pub fn upsert_statement(&self) -> String {
+        format!(
+            "INSERT INTO {} (hash, sender, nonce, status, entry) VALUES (?1, ?2, ?3, ?4, ?5) \
+             ON CONFLICT (hash) DO UPDATE SET status = excluded.status, entry = excluded.entry",
+            self.table,
+        )
+    }
+
+    /// Returns the query for the recorded entries.
+    pub fn select_statement(&self) -> String {
+        format!("SELECT entry FROM {} ORDER BY rowid", self.table)
+    }
+
+    async fn upsert(&self, entry: &JournalEntry) -> Result<(), JournalError> {
+        let params = vec![
+            Value::String(format!("{:?}", entry.hash())),
+            Value::String(format!("{:?}", entry.from)),
+            entry
+                .transaction
+                .nonce
+                .map_or(Value::Null, |nonce| Value::String(nonce.to_string())),
+            Value::String(entry.status.name().to_owned()),
+            Value::String(serde_json::to_string(entry)?),
+        ];
+        self.connection
+            .execute(&self.upsert_statement(), params)
+            .await
+    }
+
+    async fn select(&self) -> Result<Vec<JournalEntry>, JournalError> {
+        let rows = self
+            .connection
+            .query(&self.select_statement(), Vec::new())
+            .await?;
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| match row.first() {
+                Some(Value::String(entry)) => Ok(serde_json::from_str(entry)?),
+                _ => Err(JournalError::Corrupt(index + 1)),
+            })
+            .collect()
+    }
+}
+
+impl<C: SqliteConnection> JournalStore for SqliteStore<C> {
+    fn record<'a>(&'a self, entry: &'a JournalEntry) -> BoxFuture<'a, Result<(), JournalError>> {
+        Box::pin(self.upsert(entry))
+    }
+
+    fn entries(&self) -> BoxFuture<'_, Result<Vec<JournalEntry>, JournalError>> {
+        Box::pin(self.select())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use crate::transaction::Account;
+    use std::env;
+    use web3::types::{Bytes, H2048};
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "ethcontract-journal-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn entry(hash: u8, nonce: u64, status: JournalStatus) -> JournalEntry {
+        JournalEntry::new(
+            Address::repeat_byte(1),
+            PendingTransaction {
+                hash: H256::repeat_byte(hash),
+                nonce: Some(nonce.into()),
+                raw: Some(Bytes(vec![hash])),
+            },
+            status,
+        )
+    }
+
+    fn receipt(hash: H256, status: u64) -> serde_json::Value {
+        json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": "0x2",
+            "blockHash": H256::repeat_byte(0xbb),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": U64::from(status),
+        })
+    }
+
+    #[test]
+    fn file_store_keeps_latest_state() {
+        let path = temp_path("file");
+        let _ = fs::remove_file(&path);
+        let store = FileStore::new(&path);
+        let journal = TxJournal::new(FileStore::new(&path));
+
+        let first = entry(1, 0, JournalStatus::Signed);
+        let second = entry(2, 1, JournalStatus::Signed);
+        let mined = first.with_status(JournalStatus::Mined {
+            block_hash: H256::repeat_byte(0xbb),
+            block_number: 2.into(),
+            success: true,
+        });
+        for entry in &[&first, &second, &mined] {
+            journal.record(entry).immediate().unwrap();
+        }
+        assert_eq!(
+            journal.entries().immediate().unwrap(),
+            vec![mined.clone(), second.clone()],
+        );
+        assert_eq!(
+            journal.unfinished().immediate().unwrap(),
+            vec![second.clone()]
+        );
+
+        // A partially written last line is ignored, but malformed entries
+        // before it are reported.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"from\":").unwrap();
+        assert_eq!(store.read().unwrap(), vec![mined, second.clone()]);
+        file.write_all(b"\n").unwrap();
+        assert!(matches!(store.read(), Err(JournalError::Corrupt(4))));
+
+        fs::write(&path, "").unwrap();
+        journal.record(&second).immediate().unwrap();
+        journal
+            .record(&second.with_status(JournalStatus::Dropped))
+            .immediate()
+            .unwrap();
+        store.compact().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_store_appends_after_torn_write() {
+        let path = temp_path("torn");
+        let _ = fs::remove_file(&path);
+        let store = FileStore::new(&path);
+
+        let first = entry(1, 0, JournalStatus::Signed);
+        let second = entry(2, 1, JournalStatus::Signed);
+        store.append(&first).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"from\":").unwrap();
+        store.append(&second).unwrap();
+
+        let entries = store.read();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(entries.unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn reconcile_transaction_mined_while_reconciling() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let entry = entry(1, 0, JournalStatus::Broadcast);
+
+        transport.add_response(json!(null));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x1"));
+        transport.add_response(receipt(entry.hash(), 1));
+        let (status, action) = reconcile(&web3, &entry).immediate().unwrap();
+
+        transport.assert_request("eth_getTransactionReceipt", &[json!(entry.hash())]);
+        transport.assert_request("eth_getTransactionByHash", &[json!(entry.hash())]);
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(entry.from), json!("latest")],
+        );
+        transport.assert_request("eth_getTransactionReceipt", &[json!(entry.hash())]);
+        transport.assert_no_more_requests();
+        assert_eq!(
+            status,
+            JournalStatus::Mined {
+                block_hash: H256::repeat_byte(0xbb),
+                block_number: 2.into(),
+                success: true,
+            },
+        );
+        assert!(matches!(action, Recovery::Mined(_)));
+    }
+
+    #[test]
+    fn sent_transactions_are_journaled() {
+        let path = temp_path("send");
+        let _ = fs::remove_file(&path);
+        let journal = TxJournal::new(FileStore::new(&path));
+
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let key: PrivateKey =
+            key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let from = key.public_address();
+
+        let tx = TransactionBuilder::new(web3)
+            .from(Account::Offline(key, Some(1)))
+            .to(Address::zero())
+            .gas(21_000.into())
+            .gas_price(1.into())
+            .nonce(0x42.into())
+            .journal(journal.clone());
+        let raw = match tx.clone().build().immediate().unwrap() {
+            crate::transaction::Transaction::Raw { bytes, .. } => bytes,
+            _ => panic!("expected raw transaction"),
+        };
+        let hash = H256(ethcontract_common::hash::keccak256(&raw.0));
+
+        transport.add_response(json!(hash));
+        transport.add_response(json!("0x2"));
+        transport.add_response(receipt(hash, 0));
+        let err = tx.confirmations(0).send().immediate().unwrap_err();
+        assert!(matches!(err, ExecutionError::Failure(_)));
+
+        let entries = journal.entries().immediate().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            entries,
+            vec![JournalEntry::new(
+                from,
+                PendingTransaction {
+                    hash,
+                    nonce: Some(0x42.into()),
+                    raw: Some(raw),
+                },
+                JournalStatus::Mined {
+                    block_hash: H256::repeat_byte(0xbb),
+                    block_number: 2.into(),
+                    success: false,
+                },
+            )],
+        );
+    }
+
+    #[test]
+    fn recover_unfinished_transactions() {
+        let path = temp_path("recover");
+        let _ = fs::remove_file(&path);
+        let journal = TxJournal::new(FileStore::new(&path));
+
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let from = Address::repeat_byte(1);
+
+        let mined = entry(1, 0, JournalStatus::Broadcast);
+        let pending = entry(2, 1, JournalStatus::Broadcast);
+        let dropped = entry(3, 1, JournalStatus::Signed);
+        let lost = entry(4, 2, JournalStatus::Signed);
+        let mut unsigned = entry(5, 3, JournalStatus::Broadcast);
+        unsigned.transaction.raw = None;
+        let finished = entry(6, 4, JournalStatus::Dropped);
+        for entry in &[&mined, &pending, &dropped, &lost, &unsigned, &finished] {
+            journal.record(entry).immediate().unwrap();
+        }
+
+        transport.add_response(receipt(mined.hash(), 1));
+        transport.add_response(json!(null));
+        transport.add_response(json!({
+            "hash": pending.hash(),
+            "nonce": "0x1",
+            "from": from,
+            "to": Address::repeat_byte(2),
+            "value": "0x0",
+            "gas": "0x5208",
+            "gasPrice": "0x1",
+            "input": "0x",
+        }));
+        transport.add_response(json!(null));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(null));
+        transport.add_response(json!(null));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(lost.hash()));
+        transport.add_response(json!(null));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x2"));
+        let recovered = journal.recover(&web3).immediate().unwrap();
+
+        transport.assert_request("eth_getTransactionReceipt", &[json!(mined.hash())]);
+        for entry in &[&pending, &dropped, &lost, &unsigned] {
+            transport.assert_request("eth_getTransactionReceipt", &[json!(entry.hash())]);
+            transport.assert_request("eth_getTransactionByHash", &[json!(entry.hash())]);
+            if entry.hash() != pending.hash() {
+                transport
+                    .assert_request("eth_getTransactionCount", &[json!(from), json!("latest")]);
+            }
+            if entry.hash() == dropped.hash() {
+                transport.assert_request("eth_getTransactionReceipt", &[json!(entry.hash())]);
+            }
+            if entry.hash() == lost.hash() {
+                transport.assert_request("eth_sendRawTransaction", &[json!("0x04")]);
+            }
+        }
+        transport.assert_no_more_requests();
+
+        let actions = recovered
+            .iter()
+            .map(|recovered| (recovered.entry.hash(), &recovered.action))
+            .collect::<Vec<_>>();
+        assert!(matches!(actions[0].1, Recovery::Mined(_)));
+        assert!(matches!(actions[1].1, Recovery::Pending(p) if *p == pending.transaction));
+        assert!(matches!(actions[2].1, Recovery::Dropped));
+        assert!(matches!(actions[3].1, Recovery::Rebroadcast(p) if *p == lost.transaction));
+        assert!(matches!(actions[4].1, Recovery::Replace));
+        assert_eq!(
+            actions.iter().map(|(hash, _)| *hash).collect::<Vec<_>>(),
+            vec![
+                mined.hash(),
+                pending.hash(),
+                dropped.hash(),
+                lost.hash(),
+                unsigned.hash()
+            ],
+        );
+
+        // Reconciled states are recorded, and only entries that still need to
+        // be mined or replaced remain unfinished.
+        let unfinished = journal.unfinished().immediate().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            unfinished,
+            vec![
+                pending,
+                lost.with_status(JournalStatus::Broadcast),
+                unsigned
+            ],
+        );
+    }
+
+    #[test]
+    fn replace_journaled_transaction() {
+        let path = temp_path("replace");
+        let _ = fs::remove_file(&path);
+        let journal = TxJournal::new(FileStore::new(&path));
+
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let from = Address::repeat_byte(1);
+        let stuck = entry(1, 7, JournalStatus::Broadcast);
+        journal.record(&stuck).immediate().unwrap();
+
+        let replacement = TransactionBuilder::new(web3)
+            .to(from)
+            .gas(21_000.into())
+            .gas_price(2.into());
+        let err = journal
+            .replace(&stuck, replacement.clone())
+            .immediate()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ExecutionError::Journal(JournalError::SenderMismatch(address)) if address == from
+        ));
+
+        let hash = H256::repeat_byte(2);
+        transport.add_response(json!(hash));
+        let pending = journal
+            .replace(&stuck, replacement.from(Account::Local(from, None)))
+            .immediate()
+            .unwrap();
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": from,
+                "gas": "0x5208",
+                "gasPrice": "0x2",
+                "nonce": "0x7",
+            })],
+        );
+        transport.assert_no_more_requests();
+
+        let entries = journal.entries().immediate().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                stuck.with_status(JournalStatus::Replaced { by: hash }),
+                JournalEntry::new(from, pending, JournalStatus::Broadcast),
+            ],
+        );
+    }
+
+    #[derive(Debug, Default)]
+    struct TestConnection(Mutex<Vec<(String, Vec<Value>)>>, Vec<Vec<Value>>);
+
+    impl SqliteConnection for TestConnection {
+        fn execute<'a>(
+            &'a self,
+            statement: &'a str,
+            params: Vec<Value>,
+        ) -> BoxFuture<'a, Result<(), JournalError>> {
+            self.0.lock().unwrap().push((statement.to_owned(), params));
+            Box::pin(async { Ok(()) })
+        }
+
+        fn query<'a>(
+            &'a self,
+            statement: &'a str,
+            params: Vec<Value>,
+        ) -> BoxFuture<'a, Result<Vec<Vec<Value>>, JournalError>> {
+            self.0.lock().unwrap().push((statement.to_owned(), params));
+            let rows = self.1.clone();
+            Box::pin(async move { Ok(rows) })
+        }
+    }
+
+    #[test]
+    fn sqlite_store_statements() {
+        let recorded = entry(1, 2, JournalStatus::Signed);
+        let connection = TestConnection(
+            Mutex::default(),
+            vec![vec![json!(serde_json::to_string(&recorded).unwrap())]],
+        );
+        let store = SqliteStore::new(connection, "journal");
+
+        store.init().immediate().unwrap();
+        store.record(&recorded).immediate().unwrap();
+        assert_eq!(store.entries().immediate().unwrap(), vec![recorded.clone()]);
+
+        let statements = store.connection.0.lock().unwrap().clone();
+        assert_eq!(
+            statements,
+            vec![
+                (store.create_table_statement(), Vec::new()),
+                (
+                    "INSERT INTO journal (hash, sender, nonce, status, entry) \
+                     VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT (hash) DO UPDATE \
+                     SET status = excluded.status, entry = excluded.entry"
+                        .to_owned(),
+                    vec![
+                        json!(recorded.hash()),
+                        json!(recorded.from),
+                        json!("2"),
+                        json!("signed"),
+                        json!(serde_json::to_string(&recorded).unwrap()),
+                    ],
+                ),
+                (
+                    "SELECT entry FROM journal ORDER BY rowid".to_owned(),
+                    Vec::new()
+                ),
+            ],
+        );
+        assert!(store
+            .create_table_statement()
+            .starts_with("CREATE TABLE IF NOT EXISTS journal (hash TEXT PRIMARY KEY"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Implementation of transaction signing with keys that are managed by a cloud
+//! key management service (KMS) such as AWS KMS or GCP Cloud KMS, so that
+//! private keys never have to be loaded into application memory.
+
+use crate::errors::SignerError;
+use crate::transaction::signer::{EcdsaSignature, Signer, SECP256K1_N};
+use ethcontract_common::hash::keccak256;
+use futures::future::BoxFuture;
+use std::fmt::Debug;
+use std::sync::Arc;
+use web3::types::{Address, H256, U256};
+
+/// A client for a key management service that signs digests with a secp256k1
+/// key.
+///
+/// This is implemented on top of the provider's SDK or API, for example with
+/// the AWS KMS `Sign` operation using the `ECDSA_SHA_256` signing algorithm
+/// and the `DIGEST` message type, or the GCP Cloud KMS `asymmetricSign`
+/// method with an `EC_SIGN_SECP256K1_SHA256` key.
+pub trait KmsClient: Debug + Send + Sync {
+    /// Signs a 32-byte digest, returning the DER encoded ECDSA signature.
+    ///
+    /// Note that the digest is already hashed, and must be signed as is
+    /// without hashing it again.
+    fn sign_digest(&self, digest: H256) -> BoxFuture<'_, Result<Vec<u8>, SignerError>>;
+}
+
+/// A signer for an account with a key that is managed by a KMS.
+///
+/// KMS signers are used with `Account::Signer`, which takes care of
+/// normalizing the signatures returned by the KMS to have a low `s` value as
+/// required by Ethereum, and of recovering the recovery ID that is not
+/// included in the DER encoded signature.
+#[derive(Clone, Debug)]
+pub struct KmsSigner {
+    address: Address,
+    client: Arc<dyn KmsClient>,
+}
+
+impl KmsSigner {
+    /// Creates a new KMS signer for the account with the specified address.
+    pub fn new(client: Arc<dyn KmsClient>, address: Address) -> Self {
+        KmsSigner { address, client }
+    }
+
+    /// Creates a new KMS signer from the DER encoded public key of the KMS
+    /// key, as returned by the AWS KMS `GetPublicKey` operation, or by the
+    /// GCP Cloud KMS `getPublicKey` method once decoded from PEM.
+    pub fn from_public_key(
+        client: Arc<dyn KmsClient>,
+        public_key: &[u8],
+    ) -> Result<Self, SignerError> {
+        Ok(KmsSigner::new(client, public_key_address(public_key)?))
+    }
+}
+
+impl Signer for KmsSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<EcdsaSignature, SignerError>> {
+        Box::pin(async move {
+            let der = self.client.sign_digest(hash).await?;
+            decode_der_signature(&der).ok_or(SignerError::InvalidSignature)
+        })
+    }
+}
+
+/// Computes the address for a DER encoded secp256k1 public key.
+fn public_key_address(public_key: &[u8]) -> Result<Address, SignerError> {
+    // NOTE: The DER encoded `SubjectPublicKeyInfo` ends with the uncompressed
+    //   public key, which is tagged with a leading 0x04 byte.
+    let start = public_key
+        .len()
+        .checked_sub(65)
+        .ok_or(SignerError::InvalidPublicKey)?;
+    let key = &public_key[start..];
+    if key[0] != 0x04 || secp256k1::PublicKey::from_slice(key).is_err() {
+        return Err(SignerError::InvalidPublicKey);
+    }
+
+    let hash = keccak256(&key[1..]);
+    Ok(Address::from_slice(&hash[12..]))
+}
+
+/// Decodes a DER encoded ECDSA signature.
+fn decode_der_signature(der: &[u8]) -> Option<EcdsaSignature> {
+    fn decode_integer(data: &[u8]) -> Option<(U256, &[u8])> {
+        match data {
+            [0x02, len, rest @ ..] if *len as usize <= rest.len() => {
+                let (value, rest) = rest.split_at(*len as usize);
+                let value = match value {
+                    [0x00, value @ ..] => value,
+                    value => value,
+                };
+                if value.is_empty() || value.len() > 32 {
+                    return None;
+                }
+                Some((U256::from_big_endian(value), rest))
+            }
+            _ => None,
+        }
+    }
+
+    let sequence = match der {
+        [0x30, len, rest @ ..] if *len as usize == rest.len() => rest,
+        _ => return None,
+    };
+    let (r, rest) = decode_integer(sequence)?;
+    let (s, rest) = decode_integer(rest)?;
+    if !rest.is_empty() || r.is_zero() || s.is_zero() || r >= SECP256K1_N || s >= SECP256K1_N {
+        return None;
+    }
+
+    let mut signature = EcdsaSignature::default();
+    r.to_big_endian(signature.r.as_bytes_mut());
+    s.to_big_endian(signature.s.as_bytes_mut());
+    Some(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, TransactionBuilder};
+    use futures::future;
+    use secp256k1::{Message, Secp256k1};
+    use web3::api::Web3;
+
+    /// A KMS client that signs with a local private key, optionally returning
+    /// signatures with a high `s` value.
+    #[derive(Debug)]
+    struct LocalKms {
+        key: PrivateKey,
+        high_s: bool,
+    }
+
+    impl KmsClient for LocalKms {
+        fn sign_digest(&self, digest: H256) -> BoxFuture<'_, Result<Vec<u8>, SignerError>> {
+            let message = Message::from_slice(digest.as_bytes()).unwrap();
+            let signature = Secp256k1::signing_only()
+                .sign(&message, &self.key)
+                .serialize_compact();
+            let r = U256::from_big_endian(&signature[..32]);
+            let mut s = U256::from_big_endian(&signature[32..]);
+            if self.high_s {
+                s = SECP256K1_N - s;
+            }
+            Box::pin(future::ready(Ok(encode_der_signature(r, s))))
+        }
+    }
+
+    fn encode_der_signature(r: U256, s: U256) -> Vec<u8> {
+        fn encode_integer(value: U256) -> Vec<u8> {
+            let mut bytes = [0; 32];
+            value.to_big_endian(&mut bytes);
+            let mut bytes = bytes[((value.leading_zeros() / 8) as usize).min(31)..].to_vec();
+            if bytes[0] & 0x80 != 0 {
+                bytes.insert(0, 0);
+            }
+            let mut integer = vec![0x02, bytes.len() as u8];
+            integer.extend(bytes);
+            integer
+        }
+
+        let mut sequence = encode_integer(r);
+        sequence.extend(encode_integer(s));
+        let mut der = vec![0x30, sequence.len() as u8];
+        der.extend(sequence);
+        der
+    }
+
+    fn builder(account: Account) -> TransactionBuilder<TestTransport> {
+        TransactionBuilder::new(Web3::new(TestTransport::new()))
+            .from(account)
+            .to(Address::repeat_byte(2))
+            .value(28.into())
+            .gas(0x1337.into())
+            .gas_price(0x00ba_b10c.into())
+            .nonce(0x42.into())
+    }
+
+    #[test]
+    fn sign_transaction() {
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let expected = builder(Account::Offline(key.clone(), Some(77777)))
+            .build()
+            .immediate()
+            .unwrap();
+
+        for high_s in [false, true].iter().copied() {
+            let kms = LocalKms {
+                key: key.clone(),
+                high_s,
+            };
+            let signer = KmsSigner::new(Arc::new(kms), key.public_address());
+            let tx = builder(Account::Signer(Arc::new(signer), Some(77777)))
+                .build()
+                .immediate()
+                .expect("signing failed");
+
+            assert_eq!(tx, expected);
+        }
+    }
+
+    #[test]
+    fn address_from_public_key() {
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let public_key = secp256k1::PublicKey::from_secret_key(&Secp256k1::signing_only(), &key)
+            .serialize_uncompressed();
+
+        // DER encoded `SubjectPublicKeyInfo` prefix for secp256k1 public keys.
+        let mut der = hex::decode("3056301006072a8648ce3d020106052b8104000a034200").unwrap();
+        der.extend_from_slice(&public_key);
+
+        assert_eq!(public_key_address(&der).unwrap(), key.public_address());
+        assert!(public_key_address(&der[..64]).is_err());
+        assert!(public_key_address(&[0; 88]).is_err());
+    }
+
+    #[test]
+    fn decode_invalid_der_signature() {
+        let valid = encode_der_signature(1.into(), 2.into());
+        assert_eq!(
+            decode_der_signature(&valid),
+            Some(EcdsaSignature {
+                r: H256::from_low_u64_be(1),
+                s: H256::from_low_u64_be(2),
+            })
+        );
+
+        assert_eq!(decode_der_signature(&valid[1..]), None);
+        assert_eq!(decode_der_signature(&valid[..valid.len() - 1]), None);
+        assert_eq!(
+            decode_der_signature(&encode_der_signature(0.into(), 2.into())),
+            None
+        );
+        assert_eq!(
+            decode_der_signature(&encode_der_signature(SECP256K1_N, 2.into())),
+            None
+        );
+    }
+}
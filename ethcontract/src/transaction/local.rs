@@ -0,0 +1,462 @@
+//! Implementation of local simulations of transactions, that execute a
+//! transaction in an embedded EVM against state fetched from the node, instead
+//! of relying on the tracing APIs of the node.
+
+use crate::errors::{revert, ExecutionError};
+use crate::evm::{self, AccountChange, AccountInfo, Database, Env, Outcome};
+use crate::transaction::{AccountChanges, StateChanges, ValueChange};
+use futures::future;
+use std::collections::{HashMap, HashSet};
+use web3::api::Web3;
+use web3::types::{Address, BlockId, BlockNumber, Bytes, Log, H256, U256, U64};
+use web3::Transport;
+
+/// The result of simulating a transaction locally.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalSimulation {
+    /// How the execution of the transaction ended.
+    pub status: SimulationStatus,
+    /// The gas used by the transaction, including the intrinsic gas and
+    /// after refunds.
+    pub gas_used: U256,
+    /// The data returned by the transaction, or the revert data if it
+    /// reverted.
+    pub output: Bytes,
+    /// The logs emitted by the transaction. Only the address, topics and data
+    /// of the logs are set.
+    pub logs: Vec<Log>,
+    /// The address of the deployed contract, if the transaction is a
+    /// successful contract deployment.
+    pub contract_address: Option<Address>,
+    /// The changes the transaction makes to the state of the accounts it
+    /// modifies.
+    pub state_changes: StateChanges,
+}
+
+impl LocalSimulation {
+    /// Returns whether the transaction executed successfully.
+    pub fn is_success(&self) -> bool {
+        self.status == SimulationStatus::Success
+    }
+}
+
+/// How the execution of a locally simulated transaction ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SimulationStatus {
+    /// The transaction executed successfully.
+    Success,
+    /// The transaction reverted, with the revert reason if it could be
+    /// decoded.
+    Revert(Option<String>),
+    /// The transaction halted exceptionally, for example because it ran out
+    /// of gas or executed an invalid opcode, consuming all of its gas.
+    Halt(String),
+}
+
+/// The parameters of a transaction to simulate.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Request {
+    pub from: Address,
+    pub to: Option<Address>,
+    pub gas: Option<U256>,
+    pub gas_price: Option<U256>,
+    pub value: Option<U256>,
+    pub data: Option<Bytes>,
+}
+
+/// Simulates a transaction on top of the state of a block.
+///
+/// The EVM reads state synchronously, so the simulation starts out without
+/// any state and records the accounts, storage slots and block hashes that
+/// the execution reads but are not yet known. These are then fetched from the
+/// node and the transaction is executed again, until it executes without
+/// reading any unknown state. Transactions whose reads depend on previously
+/// read state, such as calls to addresses read from storage, need one round
+/// of requests per level of dependency.
+pub(crate) async fn simulate<T: Transport>(
+    web3: &Web3<T>,
+    request: Request,
+    block: BlockNumber,
+) -> Result<LocalSimulation, ExecutionError> {
+    let header = web3
+        .eth()
+        .block(BlockId::Number(block))
+        .await?
+        .ok_or(ExecutionError::MissingBlock)?;
+    let chain_id = web3.eth().chain_id().await?;
+
+    // NOTE: Fetch all state from the same block, even if a new block is mined
+    //   while the simulation is running.
+    let block = header.number.map_or(block, BlockNumber::Number);
+    let gas_price = request.gas_price.unwrap_or_default();
+    let env = Env {
+        chain_id: chain_id.low_u64(),
+        number: header.number.unwrap_or_default().as_u64(),
+        timestamp: header.timestamp.low_u64(),
+        coinbase: header.author,
+        gas_limit: saturating_u64(header.gas_limit),
+        // NOTE: Like `eth_call`, transactions without a gas price are not
+        //   charged any fees, so the base fee is ignored for them.
+        base_fee: if gas_price.is_zero() {
+            U256::zero()
+        } else {
+            header.base_fee_per_gas.unwrap_or_default()
+        },
+        prevrandao: header.mix_hash.unwrap_or_default(),
+    };
+    let tx = evm::Transaction {
+        from: request.from,
+        to: request.to,
+        value: request.value.unwrap_or_default(),
+        data: request.data.map(|data| data.0).unwrap_or_default(),
+        gas_limit: request.gas.map_or(env.gas_limit, saturating_u64),
+        gas_price,
+        nonce: None,
+        access_list: Vec::new(),
+    };
+
+    let mut prestate = Prestate::default();
+    loop {
+        let mut db = PrestateDb {
+            prestate: &prestate,
+            missing: Missing::default(),
+        };
+        let result = evm::transact(&mut db, &env, &tx);
+        let missing = db.missing;
+        if missing.is_empty() {
+            let result =
+                result.map_err(|err| ExecutionError::InvalidTransaction(err.to_string()))?;
+            return Ok(prestate.simulation(result));
+        }
+        prestate.fetch(web3, block, missing).await?;
+    }
+}
+
+fn saturating_u64(value: U256) -> u64 {
+    if value > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        value.as_u64()
+    }
+}
+
+/// The state read by a transaction, as fetched from the node.
+#[derive(Debug, Default)]
+struct Prestate {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, U256), U256>,
+    block_hashes: HashMap<u64, H256>,
+}
+
+/// The state read by an execution that was not yet fetched from the node.
+#[derive(Debug, Default)]
+struct Missing {
+    accounts: HashSet<Address>,
+    storage: HashSet<(Address, U256)>,
+    block_hashes: HashSet<u64>,
+}
+
+impl Missing {
+    fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storage.is_empty() && self.block_hashes.is_empty()
+    }
+}
+
+/// A database that records reads of unknown state.
+struct PrestateDb<'a> {
+    prestate: &'a Prestate,
+    missing: Missing,
+}
+
+impl Database for PrestateDb<'_> {
+    fn account(&mut self, address: Address) -> AccountInfo {
+        match self.prestate.accounts.get(&address) {
+            Some(info) => info.clone(),
+            None => {
+                self.missing.accounts.insert(address);
+                AccountInfo::default()
+            }
+        }
+    }
+
+    fn storage(&mut self, address: Address, slot: U256) -> U256 {
+        match self.prestate.storage.get(&(address, slot)) {
+            Some(value) => *value,
+            None => {
+                self.missing.storage.insert((address, slot));
+                U256::zero()
+            }
+        }
+    }
+
+    fn block_hash(&mut self, number: u64) -> H256 {
+        match self.prestate.block_hashes.get(&number) {
+            Some(hash) => *hash,
+            None => {
+                self.missing.block_hashes.insert(number);
+                H256::zero()
+            }
+        }
+    }
+}
+
+impl Prestate {
+    /// Fetches missing state from the node at a block.
+    async fn fetch<T: Transport>(
+        &mut self,
+        web3: &Web3<T>,
+        block: BlockNumber,
+        missing: Missing,
+    ) -> Result<(), ExecutionError> {
+        let eth = web3.eth();
+        let at = Some(block);
+
+        let accounts = future::try_join_all(missing.accounts.into_iter().map(|address| {
+            let eth = eth.clone();
+            async move {
+                let (balance, nonce, code) = future::try_join3(
+                    eth.balance(address, at),
+                    eth.transaction_count(address, at),
+                    eth.code(address, at),
+                )
+                .await?;
+                let info = AccountInfo {
+                    balance,
+                    nonce: saturating_u64(nonce),
+                    code: code.0.into(),
+                };
+                Ok::<_, web3::Error>((address, info))
+            }
+        }));
+        let storage = future::try_join_all(missing.storage.into_iter().map(|(address, slot)| {
+            let eth = eth.clone();
+            async move {
+                let value = eth.storage(address, slot, at).await?;
+                Ok::<_, web3::Error>(((address, slot), U256::from_big_endian(value.as_bytes())))
+            }
+        }));
+        let block_hashes = future::try_join_all(missing.block_hashes.into_iter().map(|number| {
+            let eth = eth.clone();
+            async move {
+                let block = eth
+                    .block(BlockId::Number(BlockNumber::Number(U64::from(number))))
+                    .await?;
+                let hash = block.and_then(|block| block.hash).unwrap_or_default();
+                Ok::<_, web3::Error>((number, hash))
+            }
+        }));
+
+        let (accounts, storage, block_hashes) =
+            future::try_join3(accounts, storage, block_hashes).await?;
+        self.accounts.extend(accounts);
+        self.storage.extend(storage);
+        self.block_hashes.extend(block_hashes);
+        Ok(())
+    }
+
+    /// Converts the result of executing a transaction on top of this state
+    /// into a simulation result.
+    fn simulation(&self, result: evm::ExecutionResult) -> LocalSimulation {
+        let (status, output) = match result.outcome {
+            Outcome::Success(output) => (SimulationStatus::Success, output),
+            Outcome::Revert(output) => (
+                SimulationStatus::Revert(revert::decode_reason(&output)),
+                output,
+            ),
+            Outcome::Halt(halt) => (SimulationStatus::Halt(halt.to_string()), Vec::new()),
+        };
+        let logs = result
+            .logs
+            .into_iter()
+            .map(|log| Log {
+                address: log.address,
+                topics: log.topics,
+                data: Bytes(log.data),
+                block_hash: None,
+                block_number: None,
+                transaction_hash: None,
+                transaction_index: None,
+                log_index: None,
+                transaction_log_index: None,
+                log_type: None,
+                removed: None,
+            })
+            .collect();
+        let accounts = result
+            .changes
+            .into_iter()
+            .map(|(address, change)| (address, self.account_changes(address, change)))
+            .filter(|(_, changes)| *changes != AccountChanges::default())
+            .collect();
+
+        LocalSimulation {
+            status,
+            gas_used: result.gas_used.into(),
+            output: Bytes(output),
+            logs,
+            contract_address: result.contract_address,
+            state_changes: StateChanges { accounts },
+        }
+    }
+
+    fn account_changes(&self, address: Address, change: AccountChange) -> AccountChanges {
+        let destroyed = change.destroyed;
+        let before = self.accounts.get(&address).cloned().unwrap_or_default();
+        let after = if destroyed {
+            AccountInfo::default()
+        } else {
+            change.info
+        };
+        let word = |value: U256| {
+            let mut word = H256::zero();
+            value.to_big_endian(word.as_bytes_mut());
+            word
+        };
+        let storage = change
+            .storage
+            .into_iter()
+            .filter_map(|(slot, value)| {
+                let before = self
+                    .storage
+                    .get(&(address, slot))
+                    .copied()
+                    .unwrap_or_default();
+                let after = if destroyed { U256::zero() } else { value };
+                Some((word(slot), ValueChange::new(word(before), word(after))?))
+            })
+            .collect();
+
+        AccountChanges {
+            balance: ValueChange::new(before.balance, after.balance),
+            nonce: ValueChange::new(before.nonce.into(), after.nonce.into()),
+            code: ValueChange::new(Bytes(before.code.to_vec()), Bytes(after.code.to_vec())),
+            storage,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev;
+    use crate::transaction::TransactionBuilder;
+
+    /// Deploys a contract with the specified runtime code.
+    async fn deploy(node: &dev::DevNode, runtime: &str) -> Address {
+        let code = format!("61{:04x}80600c6000396000f3{}", runtime.len() / 2, runtime);
+        let receipt = TransactionBuilder::new(node.web3())
+            .from(node.account(0))
+            .data(Bytes(hex::decode(code).unwrap()))
+            .send()
+            .await
+            .unwrap();
+        receipt.as_receipt().unwrap().contract_address.unwrap()
+    }
+
+    #[test]
+    fn simulates_transactions_against_node_state() {
+        futures::executor::block_on(async {
+            let node = dev::spawn();
+            // A counter that increments slot 0, emits the new value in a log
+            // with topic 7 and returns it.
+            let counter = deploy(
+                &node,
+                "6000546001018060005580600052600760206000a160206000f3",
+            )
+            .await;
+            let increment = || {
+                TransactionBuilder::new(node.web3())
+                    .from(node.account(1))
+                    .to(counter)
+            };
+            increment().send().await.unwrap();
+
+            let simulation = increment().simulate_local().await.unwrap();
+            assert!(simulation.is_success());
+
+            let two = H256::from_low_u64_be(2);
+            assert_eq!(simulation.output, Bytes(two.as_bytes().to_vec()));
+            assert_eq!(simulation.logs.len(), 1);
+            assert_eq!(simulation.logs[0].address, counter);
+            assert_eq!(simulation.logs[0].topics, vec![H256::from_low_u64_be(7)]);
+            assert_eq!(simulation.logs[0].data, simulation.output);
+
+            let changes = simulation.state_changes.account(counter).unwrap();
+            assert_eq!(
+                changes.storage.get(&H256::zero()),
+                Some(&ValueChange {
+                    before: H256::from_low_u64_be(1),
+                    after: two,
+                }),
+            );
+            let sender = simulation
+                .state_changes
+                .account(node.accounts()[1])
+                .unwrap();
+            assert_eq!(sender.balance, None);
+            assert_eq!(
+                sender.nonce,
+                Some(ValueChange {
+                    before: 1.into(),
+                    after: 2.into(),
+                }),
+            );
+
+            let receipt = increment().send().await.unwrap();
+            assert_eq!(
+                receipt.as_receipt().unwrap().gas_used,
+                Some(simulation.gas_used),
+            );
+        });
+    }
+
+    #[test]
+    fn simulates_reverts_and_halts() {
+        futures::executor::block_on(async {
+            let node = dev::spawn();
+            // A contract that reverts with `Error("no")`.
+            let reverts = deploy(
+                &node,
+                "6308c379a060e01b6000526020600452600260245261\
+                 6e6f60f01b60445260646000fd",
+            )
+            .await;
+            let simulation = TransactionBuilder::new(node.web3())
+                .from(node.account(0))
+                .to(reverts)
+                .simulate_local()
+                .await
+                .unwrap();
+            assert_eq!(
+                simulation.status,
+                SimulationStatus::Revert(Some("no".to_owned()))
+            );
+            assert_eq!(simulation.state_changes.accounts.len(), 1);
+
+            let invalid = deploy(&node, "fe").await;
+            let simulation = TransactionBuilder::new(node.web3())
+                .to(invalid)
+                .gas(100_000.into())
+                .simulate_local_at(BlockNumber::Latest)
+                .await
+                .unwrap();
+            assert_eq!(
+                simulation.status,
+                SimulationStatus::Halt("invalid opcode: 0xfe".to_owned())
+            );
+            assert_eq!(simulation.gas_used, 100_000.into());
+
+            let err = TransactionBuilder::new(node.web3())
+                .to(invalid)
+                .gas(1_000.into())
+                .simulate_local()
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, ExecutionError::InvalidTransaction(_)),
+                "{:?}",
+                err
+            );
+        });
+    }
+}
@@ -0,0 +1,226 @@
+//! Implementation of nonce gap detection and repair for accounts whose earlier
+//! transactions were dropped from the transaction pool, leaving later
+//! transactions queued behind the missing nonces.
+
+use crate::errors::ExecutionError;
+use crate::rpc::{self, PoolState};
+use crate::transaction::{Account, PendingTransaction, TransactionBuilder};
+use std::collections::BTreeSet;
+use web3::api::Web3;
+use web3::types::{Address, BlockNumber, U256};
+use web3::Transport;
+
+/// The gas limit used for no-op filler transactions.
+const FILLER_GAS: u64 = 21_000;
+
+/// The nonce gaps of an account, comparing the nonces of its mined, pending
+/// and queued transactions.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NonceGaps {
+    /// The address of the account.
+    pub address: Address,
+    /// The transaction count of the account at the latest block, which is the
+    /// next nonce to be mined.
+    pub latest: U256,
+    /// The transaction count of the account including pending transactions,
+    /// which is the next nonce that can be executed.
+    pub pending: U256,
+    /// The nonces of the transactions of the account that are queued in the
+    /// transaction pool and cannot be executed until the gaps are filled.
+    pub queued: Vec<U256>,
+    /// The missing nonces that block the queued transactions.
+    pub missing: Vec<U256>,
+}
+
+impl NonceGaps {
+    /// Returns `true` if the account has no nonce gaps.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Returns suggested no-op transactions for filling the nonce gaps, which
+    /// transfer no ETH from the account to itself with the missing nonces.
+    ///
+    /// The transactions use the default gas price of the transaction builder
+    /// and can be further configured before being sent.
+    pub fn fill_transactions<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        account: &Account,
+    ) -> Vec<TransactionBuilder<T>> {
+        self.missing
+            .iter()
+            .map(|nonce| {
+                TransactionBuilder::new(web3.clone())
+                    .from(account.clone())
+                    .to(self.address)
+                    .value(U256::zero())
+                    .gas(FILLER_GAS.into())
+                    .nonce(*nonce)
+            })
+            .collect()
+    }
+}
+
+impl Account {
+    /// Detects nonce gaps for the account by comparing its transaction count
+    /// at the latest and pending blocks with the transactions of the account
+    /// in the node's transaction pool.
+    ///
+    /// This requires the node to support the `txpool_content` method, and
+    /// retrieves the entire content of the transaction pool.
+    pub async fn pending_nonce_gaps<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+    ) -> Result<NonceGaps, ExecutionError> {
+        let address = self.address();
+        let latest = web3
+            .eth()
+            .transaction_count(address, Some(BlockNumber::Latest))
+            .await?;
+        let pending = web3
+            .eth()
+            .transaction_count(address, Some(BlockNumber::Pending))
+            .await?;
+        let pooled = rpc::txpool_content(web3)
+            .await?
+            .account_transactions(address);
+
+        let queued = pooled
+            .iter()
+            .filter(|pooled| pooled.state == PoolState::Queued)
+            .map(|pooled| pooled.transaction.nonce)
+            .collect::<Vec<_>>();
+        let nonces = pooled
+            .iter()
+            .map(|pooled| pooled.transaction.nonce)
+            .collect::<BTreeSet<_>>();
+        let missing = match queued.iter().max() {
+            Some(max) if *max > pending => {
+                let (start, end) = (pending.as_u64(), max.as_u64());
+                (start..end)
+                    .map(U256::from)
+                    .filter(|nonce| !nonces.contains(nonce))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(NonceGaps {
+            address,
+            latest,
+            pending,
+            queued,
+            missing,
+        })
+    }
+
+    /// Detects nonce gaps for the account and broadcasts no-op filler
+    /// transactions for the missing nonces, returning the pending filler
+    /// transactions. See [`NonceGaps::fill_transactions`] for more details.
+    ///
+    /// Note that filler transactions are broadcast in nonce order, and that
+    /// broadcasting stops at the first error.
+    pub async fn repair_gaps<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+    ) -> Result<Vec<PendingTransaction>, ExecutionError> {
+        let gaps = self.pending_nonce_gaps(web3).await?;
+
+        let mut pending = Vec::with_capacity(gaps.missing.len());
+        for tx in gaps.fill_transactions(web3, self) {
+            pending.push(tx.broadcast().await?);
+        }
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use web3::types::H256;
+
+    fn pool_transaction(from: Address, nonce: u64) -> serde_json::Value {
+        json!({
+            "hash": H256::from_low_u64_be(nonce),
+            "nonce": U256::from(nonce),
+            "from": from,
+            "to": Address::repeat_byte(2),
+            "value": "0x0",
+            "gas": "0x5208",
+            "gasPrice": "0x1",
+            "input": "0x",
+        })
+    }
+
+    #[test]
+    fn detect_and_repair_nonce_gaps() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let from = Address::repeat_byte(1);
+        let account = Account::Local(from, None);
+
+        transport.add_response(json!("0x3")); // latest nonce
+        transport.add_response(json!("0x5")); // pending nonce
+        transport.add_response(json!({
+            "pending": {
+                format!("{:?}", from): {
+                    "3": pool_transaction(from, 3),
+                    "4": pool_transaction(from, 4),
+                },
+            },
+            "queued": {
+                format!("{:?}", from): {
+                    "6": pool_transaction(from, 6),
+                    "8": pool_transaction(from, 8),
+                },
+            },
+        }));
+        transport.add_response(json!(H256::repeat_byte(5)));
+        transport.add_response(json!(H256::repeat_byte(7)));
+        let pending = account.repair_gaps(&web3).immediate().unwrap();
+
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("latest")]);
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        transport.assert_request("txpool_content", &[]);
+        for nonce in &[5, 7] {
+            transport.assert_request(
+                "eth_sendTransaction",
+                &[json!({
+                    "from": from,
+                    "to": from,
+                    "gas": "0x5208",
+                    "value": "0x0",
+                    "nonce": U256::from(*nonce),
+                })],
+            );
+        }
+        transport.assert_no_more_requests();
+
+        assert_eq!(
+            pending.iter().map(|tx| tx.hash).collect::<Vec<_>>(),
+            vec![H256::repeat_byte(5), H256::repeat_byte(7)],
+        );
+        assert_eq!(pending[0].nonce, Some(5.into()));
+    }
+
+    #[test]
+    fn no_gaps_without_queued_transactions() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let from = Address::repeat_byte(1);
+
+        transport.add_response(json!("0x3"));
+        transport.add_response(json!("0x4"));
+        transport.add_response(json!({ "pending": {}, "queued": {} }));
+        let gaps = Account::Local(from, None)
+            .pending_nonce_gaps(&web3)
+            .immediate()
+            .unwrap();
+
+        assert!(gaps.is_empty());
+        assert_eq!(gaps.latest, 3.into());
+        assert_eq!(gaps.pending, 4.into());
+    }
+}
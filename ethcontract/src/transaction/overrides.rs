@@ -0,0 +1,258 @@
+//! Implementation of state overrides for simulating transactions against a
+//! modified chain state, as supported by Geth and compatible nodes.
+
+use crate::storage::StorageSlot;
+use crate::tokens::{Error as TokenError, Tokenize};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use web3::types::{Address, Bytes, H256, U256, U64};
+
+/// A set of account state overrides keyed by account address.
+pub type StateOverrides = BTreeMap<Address, AccountOverride>;
+
+/// Overrides for the state of an account when simulating a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    /// Overrides the balance of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// Overrides the nonce of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    /// Overrides the code of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Replaces the entire storage of the account with the specified slots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<H256, H256>>,
+    /// Overrides the specified storage slots of the account, keeping all other
+    /// slots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+impl AccountOverride {
+    /// Overrides the balance of the account.
+    pub fn balance(mut self, value: U256) -> Self {
+        self.balance = Some(value);
+        self
+    }
+
+    /// Overrides the nonce of the account.
+    pub fn nonce(mut self, value: U64) -> Self {
+        self.nonce = Some(value);
+        self
+    }
+
+    /// Overrides the code of the account.
+    pub fn code(mut self, value: Bytes) -> Self {
+        self.code = Some(value);
+        self
+    }
+
+    /// Overrides a single storage slot of the account, keeping all other
+    /// slots. For example, this can be used for overriding an ERC20 allowance
+    /// so that a transaction depending on a pending approval can be estimated.
+    pub fn storage(mut self, slot: H256, value: H256) -> Self {
+        self.state_diff
+            .get_or_insert_with(Default::default)
+            .insert(slot, value);
+        self
+    }
+
+    /// Overrides the value of a state variable, keeping all other slots. State
+    /// variables packed into the same slot keep the values set with previous
+    /// overrides, and are zero otherwise.
+    pub fn storage_value<T: Tokenize>(
+        mut self,
+        slot: &StorageSlot<T>,
+        value: T,
+    ) -> Result<Self, TokenError> {
+        let state_diff = self.state_diff.get_or_insert_with(Default::default);
+        let word = state_diff.entry(slot.slot()).or_default();
+        *word = slot.encode(*word, value)?;
+        Ok(self)
+    }
+}
+
+/// A builder for the state overrides of multiple accounts, for example for
+/// simulating a call as if an account owned a token balance.
+///
+/// Overrides apply to the currently selected account, which can be changed
+/// with [`OverrideBuilder::account`]. State variables are set with the storage
+/// slots of generated contract bindings, and encoding errors are reported
+/// when building the overrides:
+///
+/// ```ignore
+/// let overrides = OverrideBuilder::new(token.address())
+///     .set(storage::balances(alice), 1_000u64)
+///     .account(alice)
+///     .balance(U256::exp10(18))
+///     .build()?;
+/// token.transfer(bob, 1_000.into()).from(alice).call().state_overrides(overrides);
+/// ```
+#[derive(Debug, Default)]
+pub struct OverrideBuilder {
+    overrides: StateOverrides,
+    account: Address,
+    error: Option<TokenError>,
+}
+
+impl OverrideBuilder {
+    /// Creates a new builder with overrides for the specified account.
+    pub fn new(account: Address) -> Self {
+        OverrideBuilder {
+            account,
+            ..Default::default()
+        }
+    }
+
+    /// Selects the account the following overrides apply to.
+    pub fn account(mut self, address: Address) -> Self {
+        self.account = address;
+        self
+    }
+
+    /// Overrides the value of a state variable of the selected account.
+    pub fn set<T: Tokenize>(mut self, slot: StorageSlot<T>, value: impl Into<T>) -> Self {
+        if self.error.is_none() {
+            let account = self.take_account();
+            match account.storage_value(&slot, value.into()) {
+                Ok(account) => {
+                    self.overrides.insert(self.account, account);
+                }
+                Err(err) => self.error = Some(err),
+            }
+        }
+        self
+    }
+
+    /// Overrides a storage slot of the selected account.
+    pub fn storage(mut self, slot: H256, value: H256) -> Self {
+        let account = self.take_account().storage(slot, value);
+        self.overrides.insert(self.account, account);
+        self
+    }
+
+    /// Overrides the balance of the selected account.
+    pub fn balance(mut self, value: U256) -> Self {
+        let account = self.take_account().balance(value);
+        self.overrides.insert(self.account, account);
+        self
+    }
+
+    /// Overrides the nonce of the selected account.
+    pub fn nonce(mut self, value: U64) -> Self {
+        let account = self.take_account().nonce(value);
+        self.overrides.insert(self.account, account);
+        self
+    }
+
+    /// Overrides the code of the selected account.
+    pub fn code(mut self, value: Bytes) -> Self {
+        let account = self.take_account().code(value);
+        self.overrides.insert(self.account, account);
+        self
+    }
+
+    /// Builds the state overrides, returning an error if a state variable
+    /// value could not be encoded, for example because it does not fit the
+    /// Solidity type of the variable.
+    pub fn build(self) -> Result<StateOverrides, TokenError> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.overrides),
+        }
+    }
+
+    fn take_account(&mut self) -> AccountOverride {
+        self.overrides.remove(&self.account).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    #[test]
+    fn serialize_overrides() {
+        let mut overrides = StateOverrides::new();
+        overrides.insert(
+            Address::repeat_byte(1),
+            AccountOverride::default()
+                .balance(42.into())
+                .storage(H256::zero(), H256::repeat_byte(2)),
+        );
+
+        assert_eq!(
+            json!(overrides),
+            json!({
+                "0x0101010101010101010101010101010101010101": {
+                    "balance": "0x2a",
+                    "stateDiff": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000000":
+                            "0x0202020202020202020202020202020202020202020202020202020202020202",
+                    },
+                },
+            }),
+        );
+    }
+
+    #[test]
+    fn build_overrides() {
+        let (token, alice) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let balance = StorageSlot::<U256>::new(3.into(), 0, 32, "uint256");
+        let decimals = StorageSlot::<u8>::new(4.into(), 0, 1, "uint8");
+
+        let overrides = OverrideBuilder::new(token)
+            .set(balance, 1_000u64)
+            .set(decimals, 6u8)
+            .account(alice)
+            .balance(42.into())
+            .build()
+            .unwrap();
+        assert_eq!(
+            json!(overrides),
+            json!({
+                "0x0101010101010101010101010101010101010101": {
+                    "stateDiff": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000003":
+                            "0x00000000000000000000000000000000000000000000000000000000000003e8",
+                        "0x0000000000000000000000000000000000000000000000000000000000000004":
+                            "0x0000000000000000000000000000000000000000000000000000000000000006",
+                    },
+                },
+                "0x0202020202020202020202020202020202020202": {
+                    "balance": "0x2a",
+                },
+            }),
+        );
+
+        let small = StorageSlot::<u32>::new(5.into(), 0, 3, "uint24");
+        assert!(matches!(
+            OverrideBuilder::new(token).set(small, 1u32 << 24).build(),
+            Err(TokenError::IntegerMismatch)
+        ));
+    }
+
+    #[test]
+    fn override_packed_values() {
+        let owner = StorageSlot::<Address>::new(0.into(), 0, 20, "address");
+        let paused = StorageSlot::<bool>::new(0.into(), 20, 1, "bool");
+        let overrides = AccountOverride::default()
+            .storage_value(&owner, Address::repeat_byte(1))
+            .unwrap()
+            .storage_value(&paused, true)
+            .unwrap();
+
+        let mut word = [0; 32];
+        word[11] = 1;
+        word[12..].copy_from_slice(&[1; 20]);
+        assert_eq!(
+            overrides.state_diff.unwrap().get(&H256::zero()),
+            Some(&H256(word)),
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Implementation of resumable handles to broadcast transactions.
+
+use crate::errors::ExecutionError;
+use crate::transaction::confirm::{self, ConfirmParams};
+use crate::transaction::extension;
+use crate::transaction::journal::{JournalEntry, JournalStatus};
+use crate::transaction::{with_timeout, Transaction, TransactionBuilder, TransactionResult};
+use serde::{Deserialize, Serialize};
+use web3::api::Web3;
+use web3::helpers;
+use web3::types::{Address, Bytes, H256, U256, U64};
+use web3::Transport;
+
+/// A handle to a transaction that was broadcast to the network but not
+/// necessarily mined yet.
+///
+/// The handle is serializable so that it can be persisted right after the
+/// transaction was broadcast, and used to resume waiting for confirmation,
+/// for example after a process restart.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingTransaction {
+    /// The transaction hash.
+    pub hash: H256,
+    /// The transaction nonce. This is only `None` for transactions that were
+    /// signed by the node without specifying a nonce.
+    pub nonce: Option<U256>,
+    /// The raw signed transaction. This is `None` for transactions that were
+    /// signed by the node.
+    pub raw: Option<Bytes>,
+}
+
+impl<T: Transport> TransactionBuilder<T> {
+    /// Sign (if required) and broadcast the transaction without waiting for
+    /// confirmation. Returns a handle for the pending transaction that can be
+    /// used to wait for its confirmation.
+    ///
+    /// Note that the resolve condition of the builder is ignored.
+    pub async fn broadcast(self) -> Result<PendingTransaction, ExecutionError> {
+        Ok(self.broadcast_with_sender().await?.0)
+    }
+
+    /// Broadcasts the transaction, returning the pending transaction along
+    /// with its resolved sender.
+    pub(crate) async fn broadcast_with_sender(
+        self,
+    ) -> Result<(PendingTransaction, Address), ExecutionError> {
+        let web3 = self.web3.clone();
+        let timeout = self.timeout;
+        let hook = self.hook.clone();
+        let journal = self.journal.clone();
+        let extensions = self.node_extensions();
+
+        with_timeout(timeout, async {
+            let (tx, info) = self.build_with_info().await?;
+            let pending = match tx {
+                Transaction::Request(tx) => {
+                    let hash = if extensions.is_empty() {
+                        web3.eth().send_transaction(tx).await?
+                    } else {
+                        let params = vec![extension::extend(&tx, &extensions)];
+                        let hash = web3
+                            .transport()
+                            .execute("eth_sendTransaction", params)
+                            .await?;
+                        helpers::decode(hash)?
+                    };
+                    PendingTransaction {
+                        hash,
+                        nonce: info.nonce,
+                        raw: None,
+                    }
+                }
+                Transaction::Raw { bytes, hash } => {
+                    let pending = PendingTransaction {
+                        hash,
+                        nonce: info.nonce.or_else(|| decode_nonce(&bytes.0)),
+                        raw: Some(bytes.clone()),
+                    };
+                    if let Some(journal) = &journal {
+                        journal
+                            .record(&JournalEntry::new(
+                                info.from,
+                                pending.clone(),
+                                JournalStatus::Signed,
+                            ))
+                            .await?;
+                    }
+
+                    let node_hash = web3.eth().send_raw_transaction(bytes).await?;
+                    if node_hash != hash {
+                        return Err(ExecutionError::UnexpectedTransactionHash);
+                    }
+                    pending
+                }
+            };
+
+            if let Some(journal) = &journal {
+                journal
+                    .record(&JournalEntry::new(
+                        info.from,
+                        pending.clone(),
+                        JournalStatus::Broadcast,
+                    ))
+                    .await?;
+            }
+            if let Some(hook) = hook {
+                hook.after_send(&info, &pending);
+            }
+            Ok((pending, info.from))
+        })
+        .await
+    }
+}
+
+impl PendingTransaction {
+    /// Waits for the transaction to be confirmed. Returns an error if the
+    /// transaction failed.
+    pub async fn wait<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        params: ConfirmParams,
+    ) -> Result<TransactionResult, ExecutionError> {
+        let tx_receipt = confirm::wait_for_confirmation(web3, self.hash, params).await?;
+        match tx_receipt.status {
+            Some(U64([1])) => Ok(TransactionResult::Receipt(tx_receipt)),
+            _ => Err(ExecutionError::Failure(Box::new(tx_receipt))),
+        }
+    }
+
+    /// Broadcasts the raw signed transaction again. This is useful when
+    /// resuming a pending transaction that may have been dropped by the
+    /// node in the meantime. Returns `false` if the raw transaction is not
+    /// available.
+    pub async fn rebroadcast<T: Transport>(&self, web3: &Web3<T>) -> Result<bool, ExecutionError> {
+        let raw = match &self.raw {
+            Some(raw) => raw.clone(),
+            None => return Ok(false),
+        };
+
+        let node_hash = web3.eth().send_raw_transaction(raw).await?;
+        if node_hash != self.hash {
+            return Err(ExecutionError::UnexpectedTransactionHash);
+        }
+        Ok(true)
+    }
+}
+
+/// Decodes the nonce from a raw signed transaction.
+fn decode_nonce(raw: &[u8]) -> Option<U256> {
+    // NOTE: Typed transactions are prefixed with their type and have the chain
+    //   ID as their first field, legacy transactions start with an RLP list.
+    let (typed, payload) = match raw.first()? {
+        0x00..=0x7f => (true, &raw[1..]),
+        _ => (false, raw),
+    };
+
+    let (list, fields, _) = decode_rlp(payload)?;
+    if !list {
+        return None;
+    }
+    let fields = if typed { decode_rlp(fields)?.2 } else { fields };
+
+    match decode_rlp(fields)? {
+        (false, nonce, _) if nonce.len() <= 32 => Some(U256::from_big_endian(nonce)),
+        _ => None,
+    }
+}
+
+/// Decodes a single RLP item, returning whether or not it is a list, its
+/// payload and the remaining data.
+fn decode_rlp(data: &[u8]) -> Option<(bool, &[u8], &[u8])> {
+    let prefix = *data.first()?;
+    let (list, offset, len) = match prefix {
+        0x00..=0x7f => return Some((false, &data[..1], &data[1..])),
+        0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+        0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+        _ => {
+            let (list, len_of_len) = if prefix < 0xc0 {
+                (false, (prefix - 0xb7) as usize)
+            } else {
+                (true, (prefix - 0xf7) as usize)
+            };
+            if len_of_len > 8 {
+                return None;
+            }
+            let len = data
+                .get(1..1 + len_of_len)?
+                .iter()
+                .fold(0u64, |len, byte| (len << 8) | *byte as u64);
+            (list, 1 + len_of_len, len as usize)
+        }
+    };
+
+    let end = offset.checked_add(len)?;
+    Some((list, data.get(offset..end)?, &data[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::Account;
+    use web3::types::{Address, H2048};
+
+    #[test]
+    fn broadcast_returns_pending_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let builder = TransactionBuilder::new(web3)
+            .from(Account::Offline(key, Some(77777)))
+            .to(Address::zero())
+            .gas(0x1337.into())
+            .gas_price(0x00ba_b10c.into())
+            .nonce(0x42.into());
+        let tx = builder.clone().build().immediate().unwrap();
+        let (raw, hash) = match tx {
+            Transaction::Raw { bytes, hash } => (bytes, hash),
+            _ => panic!("expected raw transaction"),
+        };
+        assert_eq!(decode_nonce(&raw.0), Some(0x42.into()));
+
+        transport.add_response(json!(hash));
+        let pending = builder.broadcast().immediate().expect("broadcast failed");
+
+        assert_eq!(
+            pending,
+            PendingTransaction {
+                hash,
+                nonce: Some(0x42.into()),
+                raw: Some(raw.clone()),
+            }
+        );
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn resume_pending_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let hash = H256::repeat_byte(0x42);
+        let persisted = serde_json::to_string(&PendingTransaction {
+            hash,
+            nonce: Some(1.into()),
+            raw: Some(Bytes(vec![0x13, 0x37])),
+        })
+        .unwrap();
+        let pending = serde_json::from_str::<PendingTransaction>(&persisted).unwrap();
+
+        transport.add_response(json!(hash));
+        assert!(pending.rebroadcast(&web3).immediate().unwrap());
+
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": "0x1",
+            "blockHash": H256::repeat_byte(1),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x1",
+        }));
+        let result = pending
+            .wait(&web3, ConfirmParams::mined())
+            .immediate()
+            .expect("confirmation failed");
+
+        assert_eq!(result.hash(), hash);
+        assert!(result.is_receipt());
+        transport.assert_request("eth_sendRawTransaction", &[json!("0x1337")]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn decode_nonce_from_typed_transaction() {
+        // EIP-1559 transaction with chain ID 1 and nonce 0x0102
+        let raw = [0x02, 0xc5, 0x01, 0x82, 0x01, 0x02, 0x80];
+        assert_eq!(decode_nonce(&raw), Some(0x0102.into()));
+        assert_eq!(decode_nonce(&[0x02]), None);
+        assert_eq!(decode_nonce(&[]), None);
+    }
+}
@@ -0,0 +1,237 @@
+//! Implementation of preflight checks of outgoing transactions against the
+//! current block gas limit, base fee and sender balance, so that transactions
+//! that would be rejected by the node or never be mined fail with a
+//! descriptive error.
+
+use crate::errors::{ExecutionError, PreflightError};
+use crate::transaction::TransactionInfo;
+use web3::api::Web3;
+use web3::types::{Block, BlockId, BlockNumber, H256, U256};
+use web3::Transport;
+
+/// The maximum change of the base fee between consecutive blocks, as the
+/// denominator of a fraction of the parent base fee, as specified by EIP-1559.
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+/// The ratio between the gas limit and the gas target of a block, as specified
+/// by EIP-1559.
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// Checks the resolved parameters of a transaction against the latest block.
+///
+/// The gas limit of the transaction must not exceed the gas limit of the
+/// latest block, and its gas price must cover the base fee of the next block
+/// if the chain supports EIP-1559. The fee check is skipped for transactions
+/// whose gas price is determined by the node when signing.
+pub(crate) async fn check<T: Transport>(
+    web3: &Web3<T>,
+    info: &TransactionInfo,
+) -> Result<(), ExecutionError> {
+    let block = web3
+        .eth()
+        .block(BlockId::Number(BlockNumber::Latest))
+        .await?
+        .ok_or(ExecutionError::MissingBlock)?;
+
+    if info.gas > block.gas_limit {
+        return Err(PreflightError::ExceedsBlockGasLimit {
+            gas: info.gas,
+            block_gas_limit: block.gas_limit,
+        }
+        .into());
+    }
+
+    if let (Some(gas_price), Some(base_fee)) = (info.gas_price, next_base_fee(&block)) {
+        if gas_price < base_fee {
+            return Err(PreflightError::FeeBelowBaseFee {
+                max_fee: gas_price,
+                base_fee,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the balance of the sender covers the value and the maximum fees
+/// of a transaction, that is `value + gas_price * gas`. Only the value is
+/// checked for transactions whose gas price is determined by the node when
+/// signing.
+pub(crate) async fn check_balance<T: Transport>(
+    web3: &Web3<T>,
+    info: &TransactionInfo,
+) -> Result<(), ExecutionError> {
+    let required = info
+        .gas_price
+        .unwrap_or_default()
+        .checked_mul(info.gas)
+        .and_then(|fees| fees.checked_add(info.value))
+        .unwrap_or(U256::MAX);
+    let available = web3
+        .eth()
+        .balance(info.from, Some(BlockNumber::Latest))
+        .await?;
+
+    if available < required {
+        return Err(PreflightError::InsufficientFunds {
+            required,
+            available,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Computes the base fee of the block following the specified block, or `None`
+/// if the chain does not support EIP-1559.
+fn next_base_fee(block: &Block<H256>) -> Option<U256> {
+    let base_fee = block.base_fee_per_gas?;
+    let target = block.gas_limit / ELASTICITY_MULTIPLIER;
+    if target.is_zero() || block.gas_used == target {
+        return Some(base_fee);
+    }
+
+    let change = |delta: U256| base_fee * delta / target / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+    if block.gas_used > target {
+        Some(base_fee + change(block.gas_used - target).max(1.into()))
+    } else {
+        Some(base_fee.saturating_sub(change(target - block.gas_used)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use web3::types::{Address, Bytes};
+
+    fn block(gas_limit: u64, gas_used: u64, base_fee: Option<u64>) -> serde_json::Value {
+        json!({
+            "hash": H256::zero(),
+            "parentHash": H256::zero(),
+            "sha3Uncles": H256::zero(),
+            "miner": Address::zero(),
+            "stateRoot": H256::zero(),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": "0x1",
+            "gasUsed": U256::from(gas_used),
+            "gasLimit": U256::from(gas_limit),
+            "baseFeePerGas": base_fee.map(U256::from),
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x0",
+            "difficulty": "0x0",
+            "totalDifficulty": "0x0",
+            "sealFields": [],
+            "uncles": [],
+            "transactions": [],
+            "size": "0x0",
+            "mixHash": H256::zero(),
+            "nonce": "0x0000000000000000",
+        })
+    }
+
+    fn info(gas: u64, gas_price: Option<u64>) -> TransactionInfo {
+        TransactionInfo {
+            from: Address::repeat_byte(1),
+            to: Some(Address::repeat_byte(2)),
+            gas: gas.into(),
+            gas_price: gas_price.map(U256::from),
+            priority_fee: None,
+            value: U256::zero(),
+            data: Bytes::default(),
+            nonce: None,
+            method: None,
+        }
+    }
+
+    fn check_against(
+        block: serde_json::Value,
+        info: TransactionInfo,
+    ) -> Result<(), ExecutionError> {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(block);
+        let result = check(&web3, &info).immediate();
+
+        transport.assert_request("eth_getBlockByNumber", &[json!("latest"), json!(false)]);
+        transport.assert_no_more_requests();
+        result
+    }
+
+    fn check_balance_against(balance: u64, info: TransactionInfo) -> Result<(), ExecutionError> {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!(U256::from(balance)));
+        let result = check_balance(&web3, &info).immediate();
+
+        transport.assert_request("eth_getBalance", &[json!(info.from), json!("latest")]);
+        transport.assert_no_more_requests();
+        result
+    }
+
+    #[test]
+    fn next_base_fee_follows_gas_used() {
+        let next_base_fee = |gas_used, base_fee| {
+            next_base_fee(&serde_json::from_value(block(100, gas_used, base_fee)).unwrap())
+        };
+
+        assert_eq!(next_base_fee(50, Some(800)), Some(800.into()));
+        assert_eq!(next_base_fee(100, Some(800)), Some(900.into()));
+        assert_eq!(next_base_fee(0, Some(800)), Some(700.into()));
+        assert_eq!(next_base_fee(51, Some(1)), Some(2.into()));
+        assert_eq!(next_base_fee(100, None), None);
+    }
+
+    #[test]
+    fn preflight_accepts_valid_transaction() {
+        assert!(check_against(block(100, 50, Some(10)), info(100, Some(10))).is_ok());
+        assert!(check_against(block(100, 100, None), info(21, Some(1))).is_ok());
+        assert!(check_against(block(100, 100, Some(10)), info(21, None)).is_ok());
+    }
+
+    #[test]
+    fn preflight_rejects_gas_above_block_gas_limit() {
+        assert!(matches!(
+            check_against(block(100, 50, Some(10)), info(101, Some(10))),
+            Err(ExecutionError::Preflight(PreflightError::ExceedsBlockGasLimit {
+                gas,
+                block_gas_limit,
+            })) if gas == 101.into() && block_gas_limit == 100.into()
+        ));
+    }
+
+    #[test]
+    fn preflight_checks_sender_balance() {
+        let info = |gas_price| TransactionInfo {
+            value: 1000.into(),
+            ..self::info(21, gas_price)
+        };
+
+        assert!(check_balance_against(1210, info(Some(10))).is_ok());
+        assert!(check_balance_against(1000, info(None)).is_ok());
+        assert!(matches!(
+            check_balance_against(1209, info(Some(10))),
+            Err(ExecutionError::Preflight(PreflightError::InsufficientFunds {
+                required,
+                available,
+            })) if required == 1210.into() && available == 1209.into()
+        ));
+    }
+
+    #[test]
+    fn preflight_rejects_fee_below_next_base_fee() {
+        assert!(matches!(
+            check_against(block(100, 100, Some(80)), info(21, Some(85))),
+            Err(ExecutionError::Preflight(PreflightError::FeeBelowBaseFee {
+                max_fee,
+                base_fee,
+            })) if max_fee == 85.into() && base_fee == 90.into()
+        ));
+    }
+}
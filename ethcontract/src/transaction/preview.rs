@@ -0,0 +1,73 @@
+//! Implementation of transaction previews, which show the fully resolved
+//! parameters of a transaction before it is signed, for example for
+//! displaying them in approval flows.
+
+use crate::rpc::DisplayToken;
+use crate::transaction::{MethodCall, SignedAuthorization};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use web3::types::{Address, Bytes, U256};
+
+/// The fully resolved parameters of a transaction that is about to be signed.
+///
+/// Previews are created with
+/// [`TransactionBuilder::preview`](crate::transaction::TransactionBuilder::preview),
+/// which resolves the sender, nonce, gas, gas price and chain ID the same way
+/// as building the transaction does, without signing or sending it. They can
+/// be serialized to JSON for display by user interfaces and approval systems.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionPreview {
+    /// The sender of the transaction.
+    pub from: Address,
+    /// The receiver of the transaction, or `None` for contract deployments.
+    pub to: Option<Address>,
+    /// The transaction nonce. For transactions signed by the node, this is the
+    /// pending transaction count of the sender the node would use.
+    pub nonce: U256,
+    /// The ETH value in wei sent with the transaction.
+    pub value: U256,
+    /// The calldata of the transaction.
+    pub data: Bytes,
+    /// The signature of the called contract method, if it could be decoded.
+    pub method: Option<String>,
+    /// The human readable arguments of the called contract method.
+    pub args: Vec<String>,
+    /// The gas limit of the transaction.
+    pub gas: U256,
+    /// The gas price of the transaction, or its maximum fee per gas for typed
+    /// transactions with a priority fee.
+    pub gas_price: U256,
+    /// The maximum priority fee per gas, for set code transactions signed by
+    /// the runtime.
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The chain ID the transaction is signed for.
+    pub chain_id: u64,
+    /// The EIP-2718 type of the transaction, if it is a typed transaction.
+    pub transaction_type: Option<u64>,
+    /// The signed authorizations of a set code transaction.
+    pub authorization_list: Vec<SignedAuthorization>,
+    /// The additional fields of the transaction extensions.
+    pub extra_fields: Map<String, Value>,
+}
+
+impl TransactionPreview {
+    /// Sets the decoded contract method call of the previewed transaction.
+    pub(crate) fn with_method(mut self, method: Option<MethodCall>) -> Self {
+        if let Some(method) = method {
+            self.method = Some(method.signature);
+            self.args = method
+                .params
+                .iter()
+                .map(|param| DisplayToken(param).to_string())
+                .collect();
+        }
+        self
+    }
+
+    /// Returns the maximum fee in wei the transaction can cost, that is the
+    /// gas limit multiplied by the gas price.
+    pub fn max_fee(&self) -> U256 {
+        self.gas.saturating_mul(self.gas_price)
+    }
+}
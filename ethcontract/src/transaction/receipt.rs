@@ -0,0 +1,295 @@
+//! Post-processing of transaction receipts for computing transaction fees and
+//! retrieving revert reasons.
+
+use crate::errors::{revert, ExecutionError};
+use crate::transaction::TransactionResult;
+use serde::Deserialize;
+use web3::api::Web3;
+use web3::helpers;
+use web3::types::{
+    BlockId, BlockNumber, CallRequest, Transaction, TransactionId, TransactionReceipt, H256, U256,
+    U64,
+};
+use web3::Transport;
+
+/// A transaction receipt along with additional fields computed from the
+/// receipt and the transaction.
+#[derive(Clone, Debug)]
+pub struct ReceiptDetails {
+    /// The transaction receipt.
+    pub receipt: TransactionReceipt,
+    /// The gas price that was actually paid per unit of gas. For EIP-1559
+    /// transactions this is the base fee plus the priority fee that was paid.
+    pub effective_gas_price: U256,
+    /// The execution fee in wei, that is the gas used multiplied by the
+    /// effective gas price. This is `None` if the node did not report the gas
+    /// used.
+    pub fee: Option<U256>,
+    /// The fee in wei paid for posting the transaction data to L1 on OP-stack
+    /// chains. This is `None` for other chains.
+    pub l1_fee: Option<U256>,
+    /// The revert reason for failed transactions. This is retrieved by
+    /// replaying the transaction as a call at the block it was mined in, and
+    /// is `None` for successful transactions or when the reason could not be
+    /// determined.
+    pub revert_reason: Option<String>,
+}
+
+/// Additional receipt fields that are not part of `TransactionReceipt`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReceiptFees {
+    effective_gas_price: Option<U256>,
+    l1_fee: Option<U256>,
+}
+
+impl ReceiptDetails {
+    /// Retrieves the receipt for a mined transaction and computes its details.
+    /// Returns an error if the transaction has not yet been mined.
+    ///
+    /// This may perform additional requests for retrieving the transaction
+    /// when the node does not report the effective gas price in the receipt,
+    /// and for retrieving the revert reason of failed transactions.
+    pub async fn fetch<T: Transport>(
+        web3: &Web3<T>,
+        tx_hash: H256,
+    ) -> Result<Self, ExecutionError> {
+        let receipt = web3
+            .transport()
+            .execute(
+                "eth_getTransactionReceipt",
+                vec![helpers::serialize(&tx_hash)],
+            )
+            .await?;
+        if receipt.is_null() {
+            return Err(ExecutionError::PendingTransaction(tx_hash));
+        }
+
+        let fees = serde_json::from_value::<ReceiptFees>(receipt.clone()).unwrap_or_default();
+        let receipt = helpers::decode::<TransactionReceipt>(receipt)?;
+        let block_number = receipt
+            .block_number
+            .ok_or(ExecutionError::PendingTransaction(tx_hash))?;
+
+        let mut transaction = None;
+        let effective_gas_price = match fees.effective_gas_price {
+            Some(gas_price) => gas_price,
+            None => {
+                get_transaction(web3, tx_hash, &mut transaction)
+                    .await?
+                    .gas_price
+            }
+        };
+
+        let revert_reason = if receipt.status == Some(U64::zero()) {
+            let transaction = get_transaction(web3, tx_hash, &mut transaction).await?;
+            replay(web3, transaction, block_number).await?
+        } else {
+            None
+        };
+
+        let fee = receipt
+            .gas_used
+            .and_then(|gas_used| gas_used.checked_mul(effective_gas_price));
+
+        Ok(ReceiptDetails {
+            receipt,
+            effective_gas_price,
+            fee,
+            l1_fee: fees.l1_fee,
+            revert_reason,
+        })
+    }
+
+    /// Returns the total fee in wei paid for the transaction, including the
+    /// L1 data fee on OP-stack chains.
+    pub fn total_fee(&self) -> Option<U256> {
+        self.fee?.checked_add(self.l1_fee.unwrap_or_default())
+    }
+
+    /// Returns true if the transaction was successful.
+    pub fn is_success(&self) -> bool {
+        self.receipt.status == Some(U64::one())
+    }
+}
+
+impl TransactionResult {
+    /// Retrieves the receipt details for the transaction. See
+    /// [`ReceiptDetails::fetch`] for more details.
+    pub async fn details<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+    ) -> Result<ReceiptDetails, ExecutionError> {
+        ReceiptDetails::fetch(web3, self.hash()).await
+    }
+}
+
+/// Retrieves a transaction, caching the result so that it is only requested
+/// once.
+async fn get_transaction<'a, T: Transport>(
+    web3: &Web3<T>,
+    tx_hash: H256,
+    transaction: &'a mut Option<Transaction>,
+) -> Result<&'a Transaction, ExecutionError> {
+    if transaction.is_none() {
+        *transaction = Some(
+            web3.eth()
+                .transaction(TransactionId::Hash(tx_hash))
+                .await?
+                .ok_or(ExecutionError::MissingTransaction(tx_hash))?,
+        );
+    }
+    Ok(transaction.as_ref().expect("transaction was just set"))
+}
+
+/// Replays a transaction as a call at the specified block to retrieve its
+/// revert reason.
+async fn replay<T: Transport>(
+    web3: &Web3<T>,
+    transaction: &Transaction,
+    block: U64,
+) -> Result<Option<String>, ExecutionError> {
+    let request = CallRequest {
+        from: transaction.from,
+        to: transaction.to,
+        gas: Some(transaction.gas),
+        gas_price: None,
+        value: Some(transaction.value),
+        data: Some(transaction.input.clone()),
+        transaction_type: None,
+        access_list: None,
+    };
+    let block = BlockId::Number(BlockNumber::Number(block));
+
+    match web3.eth().call(request, Some(block)).await {
+        // NOTE: Some nodes return the revert data as the call result instead of
+        //   an error.
+        Ok(output) => Ok(revert::decode_reason(&output.0)),
+        Err(err) => match ExecutionError::from(err) {
+            ExecutionError::Revert(reason) => Ok(reason),
+            ExecutionError::InvalidOpcode => Ok(None),
+            err => Err(err),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use serde_json::Value;
+    use web3::types::{Address, H2048};
+
+    fn receipt(tx_hash: H256, status: u64, extra: Value) -> Value {
+        let mut receipt = json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x1",
+            "blockNumber": "0x2",
+            "blockHash": H256::repeat_byte(3),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x100",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": format!("{:#x}", status),
+        });
+        receipt
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        receipt
+    }
+
+    fn transaction(tx_hash: H256) -> Value {
+        json!({
+            "hash": tx_hash,
+            "nonce": "0x0",
+            "blockHash": H256::repeat_byte(3),
+            "blockNumber": "0x2",
+            "transactionIndex": "0x1",
+            "from": Address::repeat_byte(1),
+            "to": Address::repeat_byte(2),
+            "value": "0x0",
+            "gasPrice": "0x10",
+            "gas": "0x1000",
+            "input": "0x1337",
+        })
+    }
+
+    #[test]
+    fn receipt_details_with_fees() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let tx_hash = H256::repeat_byte(0x42);
+
+        transport.add_response(receipt(
+            tx_hash,
+            1,
+            json!({ "effectiveGasPrice": "0x2", "l1Fee": "0x1000" }),
+        ));
+        let details = ReceiptDetails::fetch(&web3, tx_hash)
+            .immediate()
+            .expect("success");
+
+        assert!(details.is_success());
+        assert_eq!(details.effective_gas_price, 2.into());
+        assert_eq!(details.fee, Some(0x200.into()));
+        assert_eq!(details.l1_fee, Some(0x1000.into()));
+        assert_eq!(details.total_fee(), Some(0x1200.into()));
+        assert_eq!(details.revert_reason, None);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(tx_hash)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn receipt_details_for_failed_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let tx_hash = H256::repeat_byte(0x42);
+
+        transport.add_response(receipt(tx_hash, 0, json!({})));
+        transport.add_response(transaction(tx_hash));
+        transport.add_response(json!(revert::encode_reason_hex("no more tokens")));
+        let details = TransactionResult::Hash(tx_hash)
+            .details(&web3)
+            .immediate()
+            .expect("success");
+
+        assert!(!details.is_success());
+        assert_eq!(details.effective_gas_price, 0x10.into());
+        assert_eq!(details.fee, Some(0x1000.into()));
+        assert_eq!(details.total_fee(), Some(0x1000.into()));
+        assert_eq!(details.revert_reason.as_deref(), Some("no more tokens"));
+        transport.assert_request("eth_getTransactionReceipt", &[json!(tx_hash)]);
+        transport.assert_request("eth_getTransactionByHash", &[json!(tx_hash)]);
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "from": Address::repeat_byte(1),
+                    "to": Address::repeat_byte(2),
+                    "gas": "0x1000",
+                    "value": "0x0",
+                    "data": "0x1337",
+                }),
+                json!("0x2"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn receipt_details_for_pending_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let tx_hash = H256::repeat_byte(0x42);
+
+        transport.add_response(json!(null));
+        let result = ReceiptDetails::fetch(&web3, tx_hash).immediate();
+
+        assert!(
+            matches!(result, Err(ExecutionError::PendingTransaction(hash)) if hash == tx_hash),
+            "unexpected result {:?}",
+            result,
+        );
+    }
+}
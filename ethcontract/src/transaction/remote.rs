@@ -0,0 +1,204 @@
+//! Implementation of remote transaction signing, where signing is delegated
+//! to an external signing service so that private keys never have to be
+//! loaded into the process.
+
+use crate::errors::ExecutionError;
+use crate::transaction::extension::{self, TransactionExtension};
+use crate::transport::DynTransport;
+use ethcontract_common::hash::keccak256;
+use jsonrpc_core::Value;
+use std::future::Future;
+use std::sync::Arc;
+use web3::error::Error as Web3Error;
+use web3::helpers;
+use web3::types::{Address, Bytes, TransactionRequest, H256};
+use web3::{BatchTransport, Transport};
+
+/// A signer that delegates signing of transactions to an external service
+/// with the `eth_signTransaction` JSON-RPC method.
+///
+/// This is compatible with signing services such as Web3Signer (which can
+/// keep keys in an HSM or a vault) as well as trusted nodes that manage the
+/// signing account. The signed transaction is always sent through the node
+/// that is used for building the transaction, which does not need to be the
+/// same as the signing service.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    address: Address,
+    transport: DynTransport,
+}
+
+impl RemoteSigner {
+    /// Creates a new remote signer for an account that signs transactions
+    /// over the specified transport.
+    pub fn new<F, B, T>(transport: T, address: Address) -> Self
+    where
+        F: Future<Output = Result<Value, Web3Error>> + Send + 'static,
+        B: Future<Output = Result<Vec<Result<Value, Web3Error>>, Web3Error>> + Send + 'static,
+        T: Transport<Out = F> + BatchTransport<Batch = B> + Send + Sync + 'static,
+    {
+        RemoteSigner {
+            address,
+            transport: DynTransport::new(transport),
+        }
+    }
+
+    /// Creates a new remote signer for an account that signs transactions
+    /// with a signing service at the specified HTTP URL.
+    #[cfg(feature = "http")]
+    pub fn http(url: &str, address: Address) -> Result<Self, Web3Error> {
+        Ok(RemoteSigner::new(
+            web3::transports::Http::new(url)?,
+            address,
+        ))
+    }
+
+    /// Returns the address of the signing account.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Signs a fully resolved transaction request with the fields of the
+    /// specified extensions, returning the raw signed transaction and its
+    /// hash.
+    pub(crate) async fn sign_transaction(
+        &self,
+        request: TransactionRequest,
+        extensions: &[Arc<dyn TransactionExtension>],
+    ) -> Result<(Bytes, H256), ExecutionError> {
+        let response = self
+            .transport
+            .execute(
+                "eth_signTransaction",
+                vec![extension::extend(&request, extensions)],
+            )
+            .await?;
+
+        // NOTE: Nodes such as Geth return an object with the raw transaction
+        //   and the decoded transaction, while signing services such as
+        //   Web3Signer return the raw transaction directly.
+        let raw = match response {
+            Value::Object(mut object) => object.remove("raw").unwrap_or(Value::Null),
+            raw => raw,
+        };
+        let raw = helpers::decode::<Bytes>(raw)?;
+        let hash = H256(keccak256(&raw.0));
+
+        Ok((raw, hash))
+    }
+
+    /// Signs EIP-712 typed data with the `eth_signTypedData_v4` JSON-RPC
+    /// method, returning the 65-byte signature.
+    pub(crate) async fn sign_typed_data(&self, typed_data: Value) -> Result<Bytes, ExecutionError> {
+        let response = self
+            .transport
+            .execute(
+                "eth_signTypedData_v4",
+                vec![helpers::serialize(&self.address), typed_data],
+            )
+            .await?;
+        Ok(helpers::decode(response)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, ResolveCondition, TransactionBuilder};
+    use web3::api::Web3;
+
+    fn request(from: Address) -> TransactionRequest {
+        TransactionRequest {
+            from,
+            to: Some(Address::repeat_byte(2)),
+            gas: Some(0x1337.into()),
+            gas_price: Some(0x42.into()),
+            nonce: Some(0x1.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sign_transaction_with_raw_response() {
+        let mut transport = TestTransport::new();
+        let from = Address::repeat_byte(1);
+        let signer = RemoteSigner::new(transport.clone(), from);
+
+        transport.add_response(json!("0x1337"));
+        let (raw, hash) = signer
+            .sign_transaction(request(from), &[])
+            .immediate()
+            .expect("signing failed");
+
+        assert_eq!(raw, Bytes(vec![0x13, 0x37]));
+        assert_eq!(hash, H256(keccak256([0x13, 0x37])));
+        transport.assert_request(
+            "eth_signTransaction",
+            &[json!({
+                "from": from,
+                "to": Address::repeat_byte(2),
+                "gas": "0x1337",
+                "gasPrice": "0x42",
+                "nonce": "0x1",
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn sign_transaction_with_node_response() {
+        let mut transport = TestTransport::new();
+        let from = Address::repeat_byte(1);
+        let signer = RemoteSigner::new(transport.clone(), from);
+
+        transport.add_response(json!({
+            "raw": "0x1337",
+            "tx": {},
+        }));
+        let (raw, _) = signer
+            .sign_transaction(request(from), &[])
+            .immediate()
+            .expect("signing failed");
+
+        assert_eq!(raw, Bytes(vec![0x13, 0x37]));
+    }
+
+    #[test]
+    fn send_remote_signed_transaction() {
+        let mut node = TestTransport::new();
+        let web3 = Web3::new(node.clone());
+        let mut remote = TestTransport::new();
+        let from = Address::repeat_byte(1);
+        let hash = H256(keccak256([0x13, 0x37]));
+
+        node.add_response(json!("0x1")); // nonce
+        remote.add_response(json!("0x1337")); // signed transaction
+        node.add_response(json!(hash)); // transaction hash
+        let result = TransactionBuilder::new(web3)
+            .from(Account::Remote(RemoteSigner::new(remote.clone(), from)))
+            .to(Address::repeat_byte(2))
+            .gas(0x1337.into())
+            .gas_price(0x42.into())
+            .resolve(ResolveCondition::Pending)
+            .send()
+            .immediate()
+            .expect("send failed");
+
+        assert_eq!(result.hash(), hash);
+        node.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        node.assert_request("eth_sendRawTransaction", &[json!("0x1337")]);
+        node.assert_no_more_requests();
+        remote.assert_request(
+            "eth_signTransaction",
+            &[json!({
+                "from": from,
+                "to": Address::repeat_byte(2),
+                "gas": "0x1337",
+                "gasPrice": "0x42",
+                "nonce": "0x1",
+            })],
+        );
+        remote.assert_no_more_requests();
+    }
+}
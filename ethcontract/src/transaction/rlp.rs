@@ -0,0 +1,64 @@
+//! Minimal RLP encoding for the typed transaction envelopes that are signed
+//! by the runtime instead of by `web3`.
+
+use web3::types::{AccessList, U256};
+
+/// Encodes a byte string.
+pub fn bytes(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [byte] if *byte < 0x80 => vec![*byte],
+        _ => {
+            let mut encoded = header(0x80, bytes.len());
+            encoded.extend_from_slice(bytes);
+            encoded
+        }
+    }
+}
+
+/// Encodes an integer as a byte string without leading zeros.
+pub fn uint(value: U256) -> Vec<u8> {
+    let mut word = [0; 32];
+    value.to_big_endian(&mut word);
+    bytes(&word[(value.leading_zeros() / 8) as usize..])
+}
+
+/// Encodes a list from its already encoded items.
+pub fn list(items: &[u8]) -> Vec<u8> {
+    let mut encoded = header(0xc0, items.len());
+    encoded.extend_from_slice(items);
+    encoded
+}
+
+/// Appends a list of encoded items.
+pub fn append_list(out: &mut Vec<u8>, items: impl Iterator<Item = Vec<u8>>) {
+    out.extend(list(&items.flatten().collect::<Vec<_>>()));
+}
+
+/// Encodes an EIP-2930 access list.
+pub fn access_list(access_list: &AccessList) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    append_list(
+        &mut encoded,
+        access_list.iter().map(|item| {
+            let mut entry = bytes(item.address.as_bytes());
+            append_list(
+                &mut entry,
+                item.storage_keys.iter().map(|key| bytes(key.as_bytes())),
+            );
+            list(&entry)
+        }),
+    );
+    encoded
+}
+
+fn header(offset: u8, len: usize) -> Vec<u8> {
+    if len < 56 {
+        vec![offset + len as u8]
+    } else {
+        let len = (len as u64).to_be_bytes();
+        let len = &len[(len.iter().take_while(|byte| **byte == 0).count())..];
+        let mut header = vec![offset + 55 + len.len() as u8];
+        header.extend_from_slice(len);
+        header
+    }
+}
@@ -2,42 +2,62 @@
 //! confirmation.
 
 use crate::errors::ExecutionError;
-use crate::transaction::confirm;
-use crate::transaction::{ResolveCondition, Transaction, TransactionBuilder};
-use web3::types::{TransactionReceipt, H256, U64};
+use crate::transaction::journal::{JournalEntry, JournalStatus};
+use crate::transaction::{ResolveCondition, TransactionBuilder};
+use std::time::Instant;
+use web3::types::{TransactionReceipt, H256};
 use web3::Transport;
 
 impl<T: Transport> TransactionBuilder<T> {
     /// Sign (if required) and send the transaction. Returns the transaction
     /// hash that can be used to retrieve transaction information.
+    ///
+    /// Use [`TransactionBuilder::broadcast`] instead for getting a handle to
+    /// the transaction that can be persisted before waiting for confirmation.
     pub async fn send(mut self) -> Result<TransactionResult, ExecutionError> {
         let web3 = self.web3.clone();
         let resolve = self.resolve.take().unwrap_or_default();
+        let timeout = self.timeout;
+        let block_time = self.block_time;
+        let start = Instant::now();
 
-        let tx = self.build().await?;
-        let tx_hash = match tx {
-            Transaction::Request(tx) => web3.eth().send_transaction(tx).await?,
-            Transaction::Raw { bytes, hash } => {
-                let node_hash = web3.eth().send_raw_transaction(bytes).await?;
-                if node_hash != hash {
-                    return Err(ExecutionError::UnexpectedTransactionHash);
+        let journal = self.journal.clone();
+        let (pending, from) = self.broadcast_with_sender().await?;
+
+        let params = match resolve {
+            ResolveCondition::Pending => return Ok(TransactionResult::Hash(pending.hash)),
+            ResolveCondition::Confirmed(params) => {
+                let mut params = params.with_block_time_hint(block_time);
+                // NOTE: Confirm with the remaining time so that a timeout while
+                //   waiting for confirmation keeps the transaction hash.
+                if let Some(timeout) = timeout {
+                    let remaining = timeout.checked_sub(start.elapsed()).unwrap_or_default();
+                    params.timeout = Some(match params.timeout {
+                        Some(timeout) => timeout.min(remaining),
+                        None => remaining,
+                    });
                 }
-                hash
+                params
             }
         };
 
-        let tx_receipt = match resolve {
-            ResolveCondition::Pending => return Ok(TransactionResult::Hash(tx_hash)),
-            ResolveCondition::Confirmed(params) => {
-                confirm::wait_for_confirmation(&web3, tx_hash, params).await
+        match journal {
+            Some(journal) => {
+                let entry = JournalEntry::new(from, pending, JournalStatus::Broadcast);
+                journal.wait(&web3, &entry, params).await
             }
-        }?;
-
-        match tx_receipt.status {
-            Some(U64([1])) => Ok(TransactionResult::Receipt(tx_receipt)),
-            _ => Err(ExecutionError::Failure(Box::new(tx_receipt))),
+            None => pending.wait(&web3, params).await,
         }
     }
+
+    /// Sign (if required) and broadcast the transaction, returning its hash
+    /// immediately. Unlike [`TransactionBuilder::send`], the resolve condition
+    /// of the builder is ignored and no requests are made to wait for the
+    /// transaction to be mined, so this is suited for high-throughput
+    /// broadcasting where confirmations are tracked separately.
+    pub async fn send_nowait(self) -> Result<H256, ExecutionError> {
+        Ok(self.broadcast_with_sender().await?.0.hash)
+    }
 }
 
 /// Represents the result of a sent transaction that can either be a transaction
@@ -90,3 +110,37 @@ impl TransactionResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, Transaction, TransactionBuilder};
+    use web3::types::Address;
+    use web3::Web3;
+
+    #[test]
+    fn send_nowait_skips_confirmation() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let builder = TransactionBuilder::new(web3)
+            .from(Account::Offline(key, Some(77777)))
+            .to(Address::zero())
+            .gas(0x1337.into())
+            .gas_price(0x00ba_b10c.into())
+            .nonce(0x42.into())
+            .confirmations(3);
+        let (raw, hash) = match builder.clone().build().immediate().unwrap() {
+            Transaction::Raw { bytes, hash } => (bytes, hash),
+            _ => panic!("expected raw transaction"),
+        };
+
+        transport.add_response(json!(hash));
+        let sent = builder.send_nowait().immediate().expect("send failed");
+
+        assert_eq!(sent, hash);
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw)]);
+        transport.assert_no_more_requests();
+    }
+}
@@ -0,0 +1,292 @@
+//! Implementation of transaction signing with external signers, such as
+//! hardware wallets or threshold and MPC signing systems, that sign
+//! transaction hashes.
+
+use crate::errors::{ExecutionError, SignerError};
+use crate::secret::PrivateKey;
+use futures::future::{self, BoxFuture};
+use secp256k1::{Message, Secp256k1};
+use std::fmt::Debug;
+use web3::api::Web3;
+use web3::signing::{self, Key, Signature, SigningError};
+use web3::types::{Address, SignedTransaction, TransactionParameters, H256, U256};
+use web3::Transport;
+
+/// The order of the secp256k1 curve.
+pub(crate) const SECP256K1_N: U256 = U256([
+    0xbfd2_5e8c_d036_4141,
+    0xbaae_dce6_af48_a03b,
+    0xffff_ffff_ffff_fffe,
+    0xffff_ffff_ffff_ffff,
+]);
+
+/// An ECDSA signature over the secp256k1 curve.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EcdsaSignature {
+    /// The `r` value of the signature.
+    pub r: H256,
+    /// The `s` value of the signature.
+    pub s: H256,
+}
+
+/// A signer that signs transaction hashes for an account.
+///
+/// This allows plugging in external signing systems, such as threshold or MPC
+/// signers, that are consumed by the transaction pipeline with
+/// `Account::Signer`. The transaction is fully resolved and encoded before it
+/// is signed, so signers only ever need to sign a 32-byte hash.
+///
+/// Signatures do not need to include a recovery ID or be normalized to have a
+/// low `s` value, this is done by the transaction pipeline.
+pub trait Signer: Debug + Send + Sync {
+    /// Returns the address of the signing account.
+    fn address(&self) -> Address;
+
+    /// Signs a 32-byte hash. Note that the hash must be signed as is, without
+    /// hashing it again.
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<EcdsaSignature, SignerError>>;
+}
+
+impl Signer for PrivateKey {
+    fn address(&self) -> Address {
+        self.public_address()
+    }
+
+    fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<EcdsaSignature, SignerError>> {
+        let signature = Message::from_slice(hash.as_bytes())
+            .map(|message| {
+                let signature = Secp256k1::signing_only()
+                    .sign(&message, self)
+                    .serialize_compact();
+                EcdsaSignature {
+                    r: H256::from_slice(&signature[..32]),
+                    s: H256::from_slice(&signature[32..]),
+                }
+            })
+            .map_err(|_| SignerError::InvalidSignature);
+        Box::pin(future::ready(signature))
+    }
+}
+
+/// Signs a transaction with fully resolved parameters.
+pub(crate) async fn sign_transaction<T: Transport>(
+    web3: &Web3<T>,
+    signer: &dyn Signer,
+    parameters: TransactionParameters,
+) -> Result<SignedTransaction, ExecutionError> {
+    debug_assert!(parameters.nonce.is_some() && parameters.chain_id.is_some());
+
+    // NOTE: The transaction is encoded and signed twice, once with an empty
+    //   signature to compute the hash to sign with the signer, and once with
+    //   the signature to encode the signed transaction. Since all parameters
+    //   are resolved, this does not perform any additional requests.
+    let address = signer.address();
+    let unsigned = web3
+        .accounts()
+        .sign_transaction(parameters.clone(), PresignedKey::new(address))
+        .await?;
+    let signature = signer.sign_hash(unsigned.message_hash).await?;
+    let key = PresignedKey::recover(unsigned.message_hash, address, signature)?;
+    let signed = web3.accounts().sign_transaction(parameters, key).await?;
+
+    Ok(signed)
+}
+
+/// Signs a hash with a signer, returning the 65-byte `r || s || v` signature
+/// with a low `s` value and `v` set to 27 or 28, as expected by Solidity's
+/// `ecrecover` and signature checking libraries.
+pub(crate) async fn sign_hash_recoverable(
+    signer: &dyn Signer,
+    hash: H256,
+) -> Result<Vec<u8>, SignerError> {
+    let signature = signer.sign_hash(hash).await?;
+    let key = PresignedKey::recover(hash, signer.address(), signature)?;
+
+    let mut bytes = Vec::with_capacity(65);
+    bytes.extend_from_slice(key.signature.r.as_bytes());
+    bytes.extend_from_slice(key.signature.s.as_bytes());
+    bytes.push(27 + key.recovery_id as u8);
+    Ok(bytes)
+}
+
+/// Signs a hash with a signer, returning the `y` parity of the signature and
+/// the signature with a low `s` value, as used by typed transactions.
+pub(crate) async fn sign_hash_with_parity(
+    signer: &dyn Signer,
+    hash: H256,
+) -> Result<(u64, EcdsaSignature), SignerError> {
+    let signature = signer.sign_hash(hash).await?;
+    let key = PresignedKey::recover(hash, signer.address(), signature)?;
+    Ok((key.recovery_id, key.signature))
+}
+
+/// A key that returns a signature that was computed ahead of time.
+struct PresignedKey {
+    address: Address,
+    recovery_id: u64,
+    signature: EcdsaSignature,
+}
+
+impl PresignedKey {
+    /// Creates a key with an empty signature.
+    fn new(address: Address) -> Self {
+        PresignedKey {
+            address,
+            recovery_id: 0,
+            signature: EcdsaSignature::default(),
+        }
+    }
+
+    /// Creates a key for a signature of a hash, normalizing the signature to
+    /// have a low `s` value and recovering its recovery ID.
+    fn recover(
+        hash: H256,
+        address: Address,
+        signature: EcdsaSignature,
+    ) -> Result<Self, SignerError> {
+        let r = U256::from_big_endian(signature.r.as_bytes());
+        let s = U256::from_big_endian(signature.s.as_bytes());
+        if r.is_zero() || s.is_zero() || r >= SECP256K1_N || s >= SECP256K1_N {
+            return Err(SignerError::InvalidSignature);
+        }
+        let s = if s > SECP256K1_N / 2 {
+            SECP256K1_N - s
+        } else {
+            s
+        };
+
+        let mut compact = [0; 64];
+        compact[..32].copy_from_slice(signature.r.as_bytes());
+        s.to_big_endian(&mut compact[32..]);
+        let recovery_id = (0..2)
+            .find(|&id| signing::recover(hash.as_bytes(), &compact, id).ok() == Some(address))
+            .ok_or(SignerError::InvalidSignature)?;
+
+        Ok(PresignedKey {
+            address,
+            recovery_id: recovery_id as u64,
+            signature: EcdsaSignature {
+                r: signature.r,
+                s: H256::from_slice(&compact[32..]),
+            },
+        })
+    }
+}
+
+impl Key for PresignedKey {
+    fn sign(&self, _: &[u8], chain_id: Option<u64>) -> Result<Signature, SigningError> {
+        let v = match chain_id {
+            Some(chain_id) => self.recovery_id + 35 + chain_id * 2,
+            None => self.recovery_id + 27,
+        };
+        Ok(Signature {
+            v,
+            r: self.signature.r,
+            s: self.signature.s,
+        })
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, Transaction, TransactionBuilder};
+    use std::sync::Arc;
+
+    /// A signer that returns signatures with a high `s` value.
+    #[derive(Debug)]
+    struct HighSigner(PrivateKey);
+
+    impl Signer for HighSigner {
+        fn address(&self) -> Address {
+            self.0.public_address()
+        }
+
+        fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<EcdsaSignature, SignerError>> {
+            Box::pin(async move {
+                let mut signature = self.0.sign_hash(hash).await?;
+                let s = SECP256K1_N - U256::from_big_endian(signature.s.as_bytes());
+                s.to_big_endian(signature.s.as_bytes_mut());
+                Ok(signature)
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct WrongSigner(PrivateKey, Address);
+
+    impl Signer for WrongSigner {
+        fn address(&self) -> Address {
+            self.1
+        }
+
+        fn sign_hash(&self, hash: H256) -> BoxFuture<'_, Result<EcdsaSignature, SignerError>> {
+            self.0.sign_hash(hash)
+        }
+    }
+
+    fn builder(transport: &TestTransport, account: Account) -> TransactionBuilder<TestTransport> {
+        TransactionBuilder::new(Web3::new(transport.clone()))
+            .from(account)
+            .to(Address::repeat_byte(2))
+            .value(28.into())
+            .gas(0x1337.into())
+            .gas_price(0x00ba_b10c.into())
+            .nonce(0x42.into())
+    }
+
+    #[test]
+    fn sign_transaction_with_signer() {
+        let mut transport = TestTransport::new();
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+
+        let expected = builder(&transport, Account::Offline(key.clone(), Some(77777)))
+            .build()
+            .immediate()
+            .unwrap();
+
+        let signers: [Arc<dyn Signer>; 2] =
+            [Arc::new(key.clone()), Arc::new(HighSigner(key.clone()))];
+        for signer in signers.iter().cloned() {
+            let tx = builder(&transport, Account::Signer(signer, Some(77777)))
+                .build()
+                .immediate()
+                .expect("signing failed");
+            assert_eq!(tx, expected);
+        }
+
+        transport.add_response(json!("0x4d2")); // chain ID
+        let tx = builder(&transport, Account::Signer(Arc::new(key), None))
+            .build()
+            .immediate()
+            .expect("signing failed");
+        assert!(matches!(tx, Transaction::Raw { .. }));
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn signature_for_wrong_account() {
+        let transport = TestTransport::new();
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let signer = WrongSigner(key, Address::repeat_byte(1));
+
+        let result = builder(&transport, Account::Signer(Arc::new(signer), Some(1)))
+            .build()
+            .immediate();
+
+        assert!(
+            matches!(
+                result,
+                Err(ExecutionError::Signer(SignerError::InvalidSignature))
+            ),
+            "unexpected result {:?}",
+            result,
+        );
+    }
+}
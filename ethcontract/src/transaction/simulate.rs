@@ -0,0 +1,518 @@
+//! Implementation of state diff simulations of transactions, that report the
+//! balance, nonce, code and storage changes a transaction would make without
+//! sending it.
+
+use crate::errors::ExecutionError;
+use crate::rpc::{self, TraceBackend, TraceOptions};
+use ethcontract_common::abi::Token;
+pub use ethcontract_common::storage::{StorageLayout, StorageType, StorageVariable};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use web3::api::Web3;
+use web3::types::{
+    Address, BlockNumber, Bytes, CallRequest, Diff, StateDiff, TraceType, H256, U256,
+};
+use web3::Transport;
+
+/// A change of a value of the state of an account. Accounts that do not exist
+/// have a zero balance and nonce, empty code and zero storage.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValueChange<T> {
+    /// The value before the transaction.
+    pub before: T,
+    /// The value after the transaction.
+    pub after: T,
+}
+
+impl<T: PartialEq> ValueChange<T> {
+    pub(super) fn new(before: T, after: T) -> Option<Self> {
+        if before == after {
+            None
+        } else {
+            Some(ValueChange { before, after })
+        }
+    }
+}
+
+/// The changes a transaction makes to the state of an account.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AccountChanges {
+    /// The change of the balance, if it changed.
+    pub balance: Option<ValueChange<U256>>,
+    /// The change of the nonce, if it changed.
+    pub nonce: Option<ValueChange<U256>>,
+    /// The change of the code, if it changed, for example because the
+    /// account is a contract deployed by the transaction.
+    pub code: Option<ValueChange<Bytes>>,
+    /// The changed storage slots.
+    pub storage: BTreeMap<H256, ValueChange<H256>>,
+}
+
+impl AccountChanges {
+    /// Decodes the changed storage slots with the storage layout of the
+    /// account's contract, returning the changes of the state variables stored
+    /// in them. See [`StorageLayout`] for which variables can be decoded.
+    pub fn decode_storage(&self, layout: &StorageLayout) -> Vec<StorageVariableChange> {
+        self.storage
+            .iter()
+            .flat_map(|(slot, change)| {
+                let before = layout.decode_slot(*slot, change.before);
+                let after = layout.decode_slot(*slot, change.after);
+                before
+                    .into_iter()
+                    .zip(after)
+                    .filter(|((_, before), (_, after))| before != after)
+                    .map(|((variable, before), (_, after))| StorageVariableChange {
+                        label: variable.label.clone(),
+                        type_label: layout.type_label(variable).to_owned(),
+                        before,
+                        after,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The changes a transaction makes to the state of all accounts it modifies.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateChanges {
+    /// The changes of the modified accounts, keyed by account address.
+    pub accounts: BTreeMap<Address, AccountChanges>,
+}
+
+impl StateChanges {
+    /// Returns the changes of an account, or `None` if the transaction does
+    /// not modify it.
+    pub fn account(&self, address: Address) -> Option<&AccountChanges> {
+        self.accounts.get(&address)
+    }
+
+    /// Returns the balance change of an account, or `None` if its balance
+    /// does not change.
+    pub fn balance(&self, address: Address) -> Option<&ValueChange<U256>> {
+        self.account(address)?.balance.as_ref()
+    }
+
+    /// Converts a `trace_call` state diff into state changes.
+    fn from_state_diff(diff: StateDiff) -> Self {
+        fn change<T: Default + PartialEq>(diff: Diff<T>) -> Option<ValueChange<T>> {
+            match diff {
+                Diff::Same => None,
+                Diff::Born(value) => ValueChange::new(T::default(), value),
+                Diff::Died(value) => ValueChange::new(value, T::default()),
+                Diff::Changed(changed) => ValueChange::new(changed.from, changed.to),
+            }
+        }
+
+        let accounts = diff
+            .0
+            .into_iter()
+            .map(|(address, diff)| {
+                let storage = diff
+                    .storage
+                    .into_iter()
+                    .filter_map(|(slot, diff)| Some((slot, change(diff)?)))
+                    .collect();
+                let changes = AccountChanges {
+                    balance: change(diff.balance),
+                    nonce: change(diff.nonce),
+                    code: change(diff.code),
+                    storage,
+                };
+                (address, changes)
+            })
+            .filter(|(_, changes)| *changes != AccountChanges::default())
+            .collect();
+        StateChanges { accounts }
+    }
+
+    /// Converts a `prestateTracer` diff mode trace into state changes.
+    ///
+    /// In diff mode, the pre state contains the modified accounts with their
+    /// modified storage slots, and the post state only the modified fields
+    /// and non-zero storage slots. Accounts missing from the post state were
+    /// self destructed.
+    fn from_prestate_diff(diff: PrestateDiff) -> Self {
+        let mut addresses = diff.pre.keys().chain(diff.post.keys()).collect::<Vec<_>>();
+        addresses.sort();
+        addresses.dedup();
+
+        let empty = PrestateAccount::default();
+        let accounts = addresses
+            .into_iter()
+            .map(|address| {
+                let pre = diff.pre.get(address).unwrap_or(&empty);
+                let post = diff.post.get(address);
+                let mut storage = BTreeMap::new();
+                for (slot, before) in &pre.storage {
+                    let after = post
+                        .and_then(|post| post.storage.get(slot))
+                        .copied()
+                        .unwrap_or_default();
+                    if let Some(change) = ValueChange::new(*before, after) {
+                        storage.insert(*slot, change);
+                    }
+                }
+                for (slot, after) in post.iter().flat_map(|post| &post.storage) {
+                    if !pre.storage.contains_key(slot) {
+                        if let Some(change) = ValueChange::new(H256::zero(), *after) {
+                            storage.insert(*slot, change);
+                        }
+                    }
+                }
+
+                let changes = AccountChanges {
+                    balance: prestate_change(pre, post, |account| account.balance),
+                    nonce: prestate_change(pre, post, |account| account.nonce.map(U256::from)),
+                    code: prestate_change(pre, post, |account| account.code.clone()),
+                    storage,
+                };
+                (*address, changes)
+            })
+            .filter(|(_, changes)| *changes != AccountChanges::default())
+            .collect();
+        StateChanges { accounts }
+    }
+}
+
+/// The result of the `prestateTracer` in diff mode.
+#[derive(Debug, Deserialize)]
+struct PrestateDiff {
+    #[serde(default)]
+    pre: BTreeMap<Address, PrestateAccount>,
+    #[serde(default)]
+    post: BTreeMap<Address, PrestateAccount>,
+}
+
+/// The state of an account returned by the `prestateTracer`.
+#[derive(Debug, Default, Deserialize)]
+struct PrestateAccount {
+    #[serde(default)]
+    balance: Option<U256>,
+    #[serde(default)]
+    nonce: Option<u64>,
+    #[serde(default)]
+    code: Option<Bytes>,
+    #[serde(default)]
+    storage: BTreeMap<H256, H256>,
+}
+
+/// Computes the change of an account field from its `prestateTracer` diff
+/// mode pre and post states. Fields missing from the post state of an account
+/// did not change.
+fn prestate_change<T: Default + PartialEq>(
+    pre: &PrestateAccount,
+    post: Option<&PrestateAccount>,
+    field: impl Fn(&PrestateAccount) -> Option<T>,
+) -> Option<ValueChange<T>> {
+    let after = match post {
+        Some(post) => field(post).or_else(|| field(pre)),
+        None => None,
+    };
+    ValueChange::new(field(pre).unwrap_or_default(), after.unwrap_or_default())
+}
+
+/// Simulates a transaction and returns the state changes it would make, using
+/// `trace_call` with a `stateDiff` trace for the `Parity` backend, or
+/// `debug_traceCall` with the `prestateTracer` in diff mode for the `Geth`
+/// backend.
+pub(crate) async fn state_changes<T: Transport>(
+    web3: &Web3<T>,
+    request: CallRequest,
+    block: Option<BlockNumber>,
+    backend: TraceBackend,
+) -> Result<StateChanges, ExecutionError> {
+    match backend {
+        TraceBackend::Parity => {
+            let trace = rpc::trace_call(web3, request, vec![TraceType::StateDiff], block).await?;
+            Ok(trace
+                .state_diff
+                .map(StateChanges::from_state_diff)
+                .unwrap_or_default())
+        }
+        TraceBackend::Geth => {
+            let diff: PrestateDiff = rpc::debug_trace_call(
+                web3,
+                request,
+                block.unwrap_or(BlockNumber::Latest),
+                &TraceOptions::prestate_tracer().tracer_config(json!({ "diffMode": true })),
+            )
+            .await?;
+            Ok(StateChanges::from_prestate_diff(diff))
+        }
+    }
+}
+
+/// The change of a state variable decoded with a storage layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageVariableChange {
+    /// The name of the state variable.
+    pub label: String,
+    /// The type of the state variable, for example `uint256`.
+    pub type_label: String,
+    /// The value before the transaction.
+    pub before: Token,
+    /// The value after the transaction.
+    pub after: Token,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    fn key(value: impl serde::Serialize) -> String {
+        json!(value).as_str().unwrap().to_owned()
+    }
+
+    fn slot(index: u64) -> H256 {
+        H256::from_low_u64_be(index)
+    }
+
+    const LAYOUT: &str = r#"{
+        "storage": [
+            { "label": "owner", "slot": "0", "offset": 0, "type": "t_address" },
+            { "label": "paused", "slot": "0", "offset": 20, "type": "t_bool" },
+            { "label": "delta", "slot": "1", "offset": 0, "type": "t_int128" },
+            { "label": "balances", "slot": "2", "offset": 0, "type": "t_mapping(t_address,t_uint256)" }
+        ],
+        "types": {
+            "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+            "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+            "t_int128": { "encoding": "inplace", "label": "int128", "numberOfBytes": "16" },
+            "t_mapping(t_address,t_uint256)": {
+                "encoding": "mapping",
+                "key": "t_address",
+                "label": "mapping(address => uint256)",
+                "numberOfBytes": "32",
+                "value": "t_uint256"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn parity_state_diff() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (sender, token) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        transport.add_response(json!({
+            "output": "0x",
+            "trace": null,
+            "vmTrace": null,
+            "stateDiff": {
+                key(sender): {
+                    "balance": { "*": { "from": "0x64", "to": "0x32" } },
+                    "nonce": { "*": { "from": "0x1", "to": "0x2" } },
+                    "code": "=",
+                    "storage": {},
+                },
+                key(token): {
+                    "balance": "=",
+                    "nonce": "=",
+                    "code": "=",
+                    "storage": {
+                        key(slot(1)): { "+": slot(5) },
+                        key(slot(2)): { "*": { "from": slot(3), "to": slot(4) } },
+                    },
+                },
+            },
+        }));
+        let changes = state_changes(
+            &web3,
+            CallRequest {
+                from: Some(sender),
+                to: Some(token),
+                ..Default::default()
+            },
+            None,
+            TraceBackend::Parity,
+        )
+        .immediate()
+        .unwrap();
+
+        transport.assert_request(
+            "trace_call",
+            &[
+                json!({ "from": sender, "to": token }),
+                json!(["stateDiff"]),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(
+            changes.balance(sender),
+            Some(&ValueChange {
+                before: 100.into(),
+                after: 50.into(),
+            }),
+        );
+        let token = changes.account(token).unwrap();
+        assert_eq!(token.balance, None);
+        assert_eq!(
+            token.storage.get(&slot(1)),
+            Some(&ValueChange {
+                before: H256::zero(),
+                after: slot(5),
+            }),
+        );
+        assert_eq!(token.storage.len(), 2);
+    }
+
+    #[test]
+    fn geth_prestate_diff() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (sender, token, deployed, destroyed) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+            Address::repeat_byte(4),
+        );
+
+        transport.add_response(json!({
+            "pre": {
+                key(sender): { "balance": "0x64", "nonce": 1 },
+                key(token): {
+                    "balance": "0x0",
+                    "code": "0x6000",
+                    "nonce": 1,
+                    "storage": { key(slot(0)): slot(7), key(slot(1)): slot(8) },
+                },
+                key(destroyed): { "balance": "0x5", "code": "0x00" },
+            },
+            "post": {
+                key(sender): { "balance": "0x32", "nonce": 2 },
+                key(token): { "storage": { key(slot(1)): slot(9), key(slot(2)): slot(1) } },
+                key(deployed): { "code": "0x6001", "nonce": 1 },
+            },
+        }));
+        let changes = state_changes(
+            &web3,
+            CallRequest {
+                from: Some(sender),
+                to: Some(token),
+                ..Default::default()
+            },
+            Some(BlockNumber::Number(42.into())),
+            TraceBackend::Geth,
+        )
+        .immediate()
+        .unwrap();
+
+        transport.assert_request(
+            "debug_traceCall",
+            &[
+                json!({ "from": sender, "to": token }),
+                json!("0x2a"),
+                json!({
+                    "tracer": "prestateTracer",
+                    "tracerConfig": { "diffMode": true },
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        let sender = changes.account(sender).unwrap();
+        assert_eq!(
+            sender.nonce,
+            Some(ValueChange {
+                before: 1.into(),
+                after: 2.into(),
+            }),
+        );
+
+        let token = changes.account(token).unwrap();
+        assert_eq!(token.balance, None);
+        assert_eq!(token.code, None);
+        let storage = token
+            .storage
+            .iter()
+            .map(|(slot, change)| (*slot, change.before, change.after))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            storage,
+            vec![
+                (slot(0), slot(7), H256::zero()),
+                (slot(1), slot(8), slot(9)),
+                (slot(2), H256::zero(), slot(1)),
+            ],
+        );
+
+        let deployed = changes.account(deployed).unwrap();
+        assert_eq!(
+            deployed.code.as_ref().unwrap().after,
+            Bytes(vec![0x60, 0x01])
+        );
+        assert_eq!(deployed.balance, None);
+
+        let destroyed = changes.account(destroyed).unwrap();
+        assert_eq!(destroyed.balance.as_ref().unwrap().after, U256::zero());
+        assert_eq!(destroyed.code.as_ref().unwrap().after, Bytes::default());
+    }
+
+    #[test]
+    fn decode_storage_layout() {
+        let layout = StorageLayout::from_json(LAYOUT).unwrap();
+        let owner = Address::repeat_byte(0x42);
+
+        let mut before = [0u8; 32];
+        before[12..].copy_from_slice(owner.as_bytes());
+        let mut after = before;
+        after[11] = 1;
+
+        let mut changes = AccountChanges::default();
+        changes.storage.insert(
+            slot(0),
+            ValueChange {
+                before: H256(before),
+                after: H256(after),
+            },
+        );
+        changes.storage.insert(
+            slot(1),
+            ValueChange {
+                before: H256::zero(),
+                after: H256::from_low_u64_be(u64::MAX),
+            },
+        );
+        changes.storage.insert(
+            slot(2),
+            ValueChange {
+                before: H256::zero(),
+                after: slot(1),
+            },
+        );
+
+        let decoded = changes.decode_storage(&layout);
+        assert_eq!(
+            decoded,
+            vec![
+                StorageVariableChange {
+                    label: "paused".to_owned(),
+                    type_label: "bool".to_owned(),
+                    before: Token::Bool(false),
+                    after: Token::Bool(true),
+                },
+                StorageVariableChange {
+                    label: "delta".to_owned(),
+                    type_label: "int128".to_owned(),
+                    before: Token::Int(U256::zero()),
+                    after: Token::Int(U256::from(u64::MAX)),
+                },
+            ],
+        );
+
+        let negative = H256([0xff; 32]);
+        assert_eq!(
+            layout.decode_slot(slot(1), negative)[0].1,
+            Token::Int(U256::MAX),
+        );
+        assert_eq!(
+            layout.decode_slot(slot(0), H256(before))[0].1,
+            Token::Address(owner),
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Implementation of human readable summaries of sent transactions and
+//! deployments, for example for posting operation reports to chat services.
+
+use crate::chains::{ChainConfig, NativeCurrency};
+use crate::rpc::DisplayToken;
+use crate::transaction::{ReceiptDetails, TransactionInfo};
+use crate::units::format_units;
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+use web3::types::{Address, H256, U256};
+
+/// A summary of a sent transaction, including the decoded contract method
+/// call, the ETH value, the fee and a block explorer link.
+///
+/// Summaries are created from the transaction parameters passed to
+/// [`TransactionHook::after_send`](crate::transaction::TransactionHook::after_send),
+/// and can be completed with the receipt details once the transaction is
+/// mined. They are rendered as multiple lines of text with `Display`, and can
+/// be serialized to JSON with amounts formatted in the native currency of the
+/// chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionSummary {
+    /// The transaction hash.
+    pub hash: H256,
+    /// The sender of the transaction.
+    pub from: Address,
+    /// The receiver of the transaction, or `None` for contract deployments.
+    pub to: Option<Address>,
+    /// The address of the deployed contract, if the transaction is a mined
+    /// contract deployment.
+    pub contract_address: Option<Address>,
+    /// The signature of the called contract method, if it could be decoded.
+    pub method: Option<String>,
+    /// The human readable arguments of the called contract method.
+    pub args: Vec<String>,
+    /// The ETH value in wei sent with the transaction.
+    pub value: U256,
+    /// The fee in wei paid for the transaction, if it was mined.
+    pub fee: Option<U256>,
+    /// Whether the transaction was successful, if it was mined.
+    pub success: Option<bool>,
+    /// The native currency of the chain, used for formatting amounts.
+    pub currency: NativeCurrency,
+    /// The block explorer URL of the transaction.
+    pub explorer_url: Option<String>,
+}
+
+impl TransactionSummary {
+    /// Creates a new summary for a sent transaction from its hash and its
+    /// resolved parameters. Amounts are formatted in ETH by default.
+    pub fn new(hash: H256, info: &TransactionInfo) -> Self {
+        let (method, args) = match &info.method {
+            Some(method) => (
+                Some(method.signature.clone()),
+                method
+                    .params
+                    .iter()
+                    .map(|param| DisplayToken(param).to_string())
+                    .collect(),
+            ),
+            None => (None, Vec::new()),
+        };
+
+        TransactionSummary {
+            hash,
+            from: info.from,
+            to: info.to,
+            contract_address: None,
+            method,
+            args,
+            value: info.value,
+            fee: None,
+            success: None,
+            currency: NativeCurrency::new("ETH", 18),
+            explorer_url: None,
+        }
+    }
+
+    /// Uses the native currency and block explorer of the specified chain.
+    pub fn chain(mut self, chain: &ChainConfig) -> Self {
+        self.currency = chain.currency.clone();
+        self.explorer_url = chain.tx_url(self.hash);
+        self
+    }
+
+    /// Completes the summary with the receipt details of the mined
+    /// transaction.
+    pub fn receipt(mut self, details: &ReceiptDetails) -> Self {
+        self.contract_address = details.receipt.contract_address;
+        self.fee = details.total_fee();
+        self.success = Some(details.is_success());
+        self
+    }
+
+    fn amount(&self, amount: U256) -> String {
+        format!(
+            "{} {}",
+            format_units(amount, self.currency.decimals),
+            self.currency.symbol,
+        )
+    }
+}
+
+impl Display for TransactionSummary {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match (&self.method, self.to, self.contract_address) {
+            (Some(method), Some(to), _) => write!(f, "called {} on {:?}", method, to)?,
+            (_, Some(to), _) => write!(f, "sent transaction to {:?}", to)?,
+            (_, None, Some(address)) => write!(f, "deployed contract at {:?}", address)?,
+            (_, None, None) => write!(f, "deployed contract")?,
+        }
+        if !self.args.is_empty() {
+            write!(f, "\nargs: {}", self.args.join(", "))?;
+        }
+        write!(f, "\nfrom: {:?}", self.from)?;
+        write!(f, "\nvalue: {}", self.amount(self.value))?;
+        if let Some(fee) = self.fee {
+            write!(f, "\nfee: {}", self.amount(fee))?;
+        }
+        match self.success {
+            Some(true) => write!(f, "\nstatus: success")?,
+            Some(false) => write!(f, "\nstatus: failed")?,
+            None => write!(f, "\nstatus: pending")?,
+        }
+        match &self.explorer_url {
+            Some(url) => write!(f, "\n{}", url),
+            None => write!(f, "\nhash: {:?}", self.hash),
+        }
+    }
+}
+
+/// The JSON representation of a transaction summary.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryJson<'a> {
+    hash: H256,
+    from: Address,
+    to: Option<Address>,
+    contract_address: Option<Address>,
+    method: Option<&'a str>,
+    args: &'a [String],
+    value: String,
+    fee: Option<String>,
+    currency: &'a str,
+    success: Option<bool>,
+    explorer_url: Option<&'a str>,
+}
+
+/// Serializes the summary with amounts formatted as decimal strings in the
+/// native currency of the chain.
+impl Serialize for TransactionSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let decimals = self.currency.decimals;
+        SummaryJson {
+            hash: self.hash,
+            from: self.from,
+            to: self.to,
+            contract_address: self.contract_address,
+            method: self.method.as_deref(),
+            args: &self.args,
+            value: format_units(self.value, decimals),
+            fee: self.fee.map(|fee| format_units(fee, decimals)),
+            currency: &self.currency.symbol,
+            success: self.success,
+            explorer_url: self.explorer_url.as_deref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains;
+    use crate::test::prelude::*;
+    use crate::transaction::MethodCall;
+    use ethcontract_common::abi::Token;
+    use web3::types::{Bytes, TransactionReceipt, U64};
+
+    fn transfer_info() -> TransactionInfo {
+        TransactionInfo {
+            from: Address::repeat_byte(1),
+            to: Some(Address::repeat_byte(2)),
+            gas: 100_000.into(),
+            gas_price: Some(1_000_000_000.into()),
+            priority_fee: None,
+            value: 500_000_000_000_000_000u64.into(),
+            data: Bytes::default(),
+            nonce: None,
+            method: Some(MethodCall {
+                signature: "transfer(address,uint256)".to_owned(),
+                params: vec![
+                    Token::Address(Address::repeat_byte(3)),
+                    Token::Uint(42.into()),
+                ],
+            }),
+        }
+    }
+
+    fn details(contract_address: Option<Address>) -> ReceiptDetails {
+        ReceiptDetails {
+            receipt: TransactionReceipt {
+                contract_address,
+                status: Some(U64::one()),
+                ..Default::default()
+            },
+            effective_gas_price: 1_000_000_000.into(),
+            fee: Some(21_000_000_000_000u64.into()),
+            l1_fee: None,
+            revert_reason: None,
+        }
+    }
+
+    #[test]
+    fn display_method_call_summary() {
+        let hash = H256::repeat_byte(0x42);
+        let summary = TransactionSummary::new(hash, &transfer_info())
+            .chain(&chains::GNOSIS)
+            .receipt(&details(None));
+
+        assert_eq!(
+            summary.to_string(),
+            "called transfer(address,uint256) on 0x0202020202020202020202020202020202020202\n\
+             args: 0x0303030303030303030303030303030303030303, 42\n\
+             from: 0x0101010101010101010101010101010101010101\n\
+             value: 0.5 xDAI\n\
+             fee: 0.000021 xDAI\n\
+             status: success\n\
+             https://gnosisscan.io/tx/\
+             0x4242424242424242424242424242424242424242424242424242424242424242",
+        );
+    }
+
+    #[test]
+    fn display_pending_deployment_summary() {
+        let info = TransactionInfo {
+            to: None,
+            value: U256::zero(),
+            method: None,
+            ..transfer_info()
+        };
+        let summary = TransactionSummary::new(H256::repeat_byte(0x42), &info);
+
+        assert_eq!(
+            summary.to_string(),
+            "deployed contract\n\
+             from: 0x0101010101010101010101010101010101010101\n\
+             value: 0 ETH\n\
+             status: pending\n\
+             hash: 0x4242424242424242424242424242424242424242424242424242424242424242",
+        );
+
+        let summary = summary.receipt(&details(Some(Address::repeat_byte(4))));
+        assert!(summary
+            .to_string()
+            .starts_with("deployed contract at 0x0404040404040404040404040404040404040404\n"));
+    }
+
+    #[test]
+    fn serialize_summary() {
+        let hash = H256::repeat_byte(0x42);
+        let summary = TransactionSummary::new(hash, &transfer_info())
+            .chain(&chains::MAINNET)
+            .receipt(&details(None));
+
+        assert_eq!(
+            json!(summary),
+            json!({
+                "hash": hash,
+                "from": Address::repeat_byte(1),
+                "to": Address::repeat_byte(2),
+                "contractAddress": null,
+                "method": "transfer(address,uint256)",
+                "args": ["0x0303030303030303030303030303030303030303", "42"],
+                "value": "0.5",
+                "fee": "0.000021",
+                "currency": "ETH",
+                "success": true,
+                "explorerUrl": format!("https://etherscan.io/tx/{:?}", hash),
+            }),
+        );
+    }
+}
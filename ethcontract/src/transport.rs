@@ -4,8 +4,35 @@
 //! implementation. This transport is used by default for generated contract APIs
 //! to help create a more ergonimic experience by making the generated struct not
 //! be generic on the underlying transport (at the small cost of some dynamic
-//! dispatch and extra allocations).
-
+//! dispatch and extra allocations). It also includes `HttpTransport` for
+//! endpoints that require custom headers or authentication, and
+//! `LenientTransport` for talking to nodes that encode quantities in
+//! nonstandard ways, `SchedulingTransport` for sending time critical requests
+//! ahead of bulk traffic, `Eip1193Transport` for browser wallets,
+//! `RecordingTransport` and `ReplayTransport` for recording node interactions
+//! to fixtures and replaying them in deterministic tests, and
+//! `ReconnectingTransport` for long running connections that are monitored
+//! with heartbeats and re-established when they stop responding.
+
+mod eip1193;
+mod fixture;
+#[cfg(feature = "http")]
+mod http;
+mod lenient;
+mod reconnect;
+mod scheduler;
+
+pub use self::eip1193::{Eip1193Provider, Eip1193Transport};
+pub use self::fixture::{Interaction, RecordedResponse, RecordingTransport, ReplayTransport};
+#[cfg(feature = "http")]
+pub use self::http::{HttpRequest, HttpTransport, HttpTransportBuilder, HttpVersion};
+pub use self::lenient::LenientTransport;
+pub use self::reconnect::{
+    HealthEvent, HeartbeatOptions, ReconnectingTransport, DEFAULT_HEARTBEAT_INTERVAL,
+    DEFAULT_HEARTBEAT_TIMEOUT,
+};
+pub use self::scheduler::{Priority, SchedulingTransport, DEFAULT_MAX_SKIPS};
+use crate::log::{AdaptiveBlockRange, DEFAULT_BLOCK_PAGE_SIZE};
 use futures::future::BoxFuture;
 use futures::FutureExt as _;
 use jsonrpc_core::Call;
@@ -64,7 +91,7 @@ where
 
     #[inline(always)]
     fn send_batch_boxed(&self, requests: Vec<(RequestId, Call)>) -> BoxedBatch {
-        self.send_batch(requests.into_iter()).boxed()
+        self.send_batch(requests).boxed()
     }
 
     #[inline(always)]
@@ -78,6 +105,7 @@ where
 #[derive(Debug)]
 pub struct DynTransport {
     inner: Arc<dyn TransportBoxed>,
+    block_range: AdaptiveBlockRange,
 }
 
 impl DynTransport {
@@ -89,15 +117,22 @@ impl DynTransport {
         T: Transport<Out = F> + BatchTransport<Batch = B> + Send + Sync + 'static,
     {
         let inner_ref: &dyn Any = &inner;
-        let inner_arc = match inner_ref.downcast_ref::<DynTransport>() {
+        if let Some(dyn_transport) = inner_ref.downcast_ref::<DynTransport>() {
             // NOTE: If a `DynTransport` is being created from another
-            //   `DynTransport`, then just clone its inner transport instead of
-            //   re-wrapping it.
-            Some(dyn_transport) => dyn_transport.inner.clone(),
-            None => Arc::new(inner),
-        };
+            //   `DynTransport`, then just clone it instead of re-wrapping it.
+            return dyn_transport.clone();
+        }
 
-        DynTransport { inner: inner_arc }
+        DynTransport {
+            inner: Arc::new(inner),
+            block_range: AdaptiveBlockRange::new(DEFAULT_BLOCK_PAGE_SIZE),
+        }
+    }
+
+    /// Returns the adaptive block range for paginated log queries learned for
+    /// this transport, shared by all of its clones.
+    pub fn block_range(&self) -> &AdaptiveBlockRange {
+        &self.block_range
     }
 
     /// Casts this transport into the underlying type.
@@ -110,6 +145,7 @@ impl Clone for DynTransport {
     fn clone(&self) -> Self {
         DynTransport {
             inner: self.inner.clone(),
+            block_range: self.block_range.clone(),
         }
     }
 }
@@ -144,6 +180,15 @@ impl BatchTransport for DynTransport {
     }
 }
 
+/// Returns the method name of a JSON RPC call.
+fn method_name(request: &Call) -> String {
+    match request {
+        Call::MethodCall(call) => call.method.clone(),
+        Call::Notification(notification) => notification.method.clone(),
+        Call::Invalid { .. } => String::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,8 +214,7 @@ mod tests {
         dyn_transport
             .execute("test", vec![json!(42)])
             .immediate()
-            .err()
-            .expect("failed");
+            .expect_err("failed");
         transport.assert_request("test", &[json!(42)]);
         transport.assert_no_more_requests();
     }
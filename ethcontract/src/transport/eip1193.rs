@@ -0,0 +1,238 @@
+//! Implementation of a transport over EIP-1193 providers, such as browser
+//! wallets injected at `window.ethereum` or WalletConnect sessions. Requests
+//! that require the user's approval, such as sending transactions or signing
+//! messages, are approved by the user in their wallet, so contract bindings
+//! can be used with browser wallets from Rust-WASM dapps.
+
+use crate::transaction::Account;
+use futures::future::{self, BoxFuture};
+use jsonrpc_core::types::{Call, MethodCall, Params};
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use web3::error::Error as Web3Error;
+use web3::types::Address;
+use web3::{helpers, BatchTransport, RequestId, Transport};
+
+/// An EIP-1193 provider, exposing the `request({ method, params })` function
+/// of a wallet.
+///
+/// Errors reported by the provider should be returned as `Web3Error::Rpc`
+/// errors with the EIP-1193 error code, so that they can be classified with
+/// [`RpcError`](crate::errors::RpcError), for example to detect that the user
+/// rejected a request.
+///
+/// Note that the transport can only be used with generated contract bindings
+/// if the provider is `Send` and `Sync`. Providers wrapping JavaScript objects
+/// on single threaded WASM targets can forward requests to a JavaScript task,
+/// for example with `wasm_bindgen_futures::spawn_local`, and return the
+/// receiving end of a channel.
+pub trait Eip1193Provider: Debug + Send + Sync {
+    /// Sends a request to the provider.
+    fn request(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'static, Result<Value, Web3Error>>;
+}
+
+/// A transport that sends all requests to an EIP-1193 provider.
+///
+/// The provider handles reads as well as transactions, so transactions should
+/// be sent from an `Account::Local` account of the wallet, which can be
+/// requested with [`Eip1193Transport::account`].
+#[derive(Clone, Debug)]
+pub struct Eip1193Transport {
+    provider: Arc<dyn Eip1193Provider>,
+    id: Arc<AtomicUsize>,
+}
+
+impl Eip1193Transport {
+    /// Creates a new transport for the specified provider.
+    pub fn new(provider: impl Eip1193Provider + 'static) -> Self {
+        Eip1193Transport {
+            provider: Arc::new(provider),
+            id: Default::default(),
+        }
+    }
+
+    /// Requests access to the accounts of the wallet with
+    /// `eth_requestAccounts`, which prompts the user to connect the wallet if
+    /// it is not connected yet.
+    pub async fn request_accounts(&self) -> Result<Vec<Address>, Web3Error> {
+        let accounts = self.execute("eth_requestAccounts", vec![]).await?;
+        helpers::decode(accounts)
+    }
+
+    /// Requests access to the accounts of the wallet and returns an account
+    /// for sending transactions from the selected account, which are approved
+    /// by the user in their wallet.
+    pub async fn account(&self) -> Result<Account, Web3Error> {
+        let address = self
+            .request_accounts()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                Web3Error::InvalidResponse("wallet did not return any accounts".to_owned())
+            })?;
+        Ok(Account::Local(address, None))
+    }
+
+    /// Asks the wallet to switch to the chain with the specified ID with
+    /// `wallet_switchEthereumChain`.
+    pub async fn switch_chain(&self, chain_id: u64) -> Result<(), Web3Error> {
+        let params = vec![json!({ "chainId": format!("{:#x}", chain_id) })];
+        self.execute("wallet_switchEthereumChain", params).await?;
+        Ok(())
+    }
+}
+
+impl Transport for Eip1193Transport {
+    type Out = BoxFuture<'static, Result<Value, Web3Error>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.id.fetch_add(1, Ordering::AcqRel);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, _: RequestId, call: Call) -> Self::Out {
+        match call {
+            Call::MethodCall(MethodCall { method, params, .. }) => {
+                let params = match params {
+                    Params::Array(params) => params,
+                    Params::Map(params) => vec![Value::Object(params)],
+                    Params::None => Vec::new(),
+                };
+                self.provider.request(&method, params)
+            }
+            _ => Box::pin(future::err(Web3Error::Transport(
+                "EIP-1193 providers only support method calls".to_owned(),
+            ))),
+        }
+    }
+}
+
+impl BatchTransport for Eip1193Transport {
+    type Batch = BoxFuture<'static, Result<Vec<Result<Value, Web3Error>>, Web3Error>>;
+
+    /// Sends all requests of the batch concurrently, since EIP-1193 providers
+    /// do not support batch requests.
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests
+            .into_iter()
+            .map(|(id, call)| self.send(id, call))
+            .collect::<Vec<_>>();
+        Box::pin(async move { Ok(future::join_all(requests).await) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{ExecutionError, RpcError};
+    use crate::test::prelude::*;
+    use crate::transaction::{ResolveCondition, TransactionBuilder};
+    use std::sync::Mutex;
+    use web3::api::Web3;
+    use web3::types::H256;
+
+    type Requests = Arc<Mutex<Vec<(String, Vec<Value>)>>>;
+
+    /// A provider with canned responses for each method.
+    #[derive(Debug, Default)]
+    struct Wallet {
+        requests: Requests,
+    }
+
+    impl Eip1193Provider for Wallet {
+        fn request(
+            &self,
+            method: &str,
+            params: Vec<Value>,
+        ) -> BoxFuture<'static, Result<Value, Web3Error>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((method.to_owned(), params));
+            let response = match method {
+                "eth_requestAccounts" => Ok(json!([Address::repeat_byte(1)])),
+                "eth_chainId" => Ok(json!("0x1")),
+                "eth_sendTransaction" => Ok(json!(H256::repeat_byte(2))),
+                _ => Err(Web3Error::Rpc(jsonrpc_core::Error {
+                    code: 4001.into(),
+                    message: "User rejected the request.".to_owned(),
+                    data: None,
+                })),
+            };
+            Box::pin(future::ready(response))
+        }
+    }
+
+    fn wallet() -> (Eip1193Transport, Requests) {
+        let wallet = Wallet::default();
+        let requests = wallet.requests.clone();
+        (Eip1193Transport::new(wallet), requests)
+    }
+
+    #[test]
+    fn sends_transactions_through_wallet() {
+        let (transport, requests) = wallet();
+        let account = transport.account().immediate().unwrap();
+        assert_eq!(account.address(), Address::repeat_byte(1));
+
+        let result = TransactionBuilder::new(Web3::new(transport))
+            .from(account)
+            .to(Address::repeat_byte(3))
+            .gas(21000.into())
+            .gas_price(1.into())
+            .nonce(0.into())
+            .resolve(ResolveCondition::Pending)
+            .send()
+            .immediate()
+            .unwrap();
+
+        assert_eq!(result.hash(), H256::repeat_byte(2));
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1].0, "eth_sendTransaction");
+        assert_eq!(requests[1].1[0]["from"], json!(Address::repeat_byte(1)));
+    }
+
+    #[test]
+    fn classifies_rejections() {
+        let (transport, requests) = wallet();
+        let err = transport.switch_chain(100).immediate().unwrap_err();
+        assert_eq!(
+            requests.lock().unwrap()[0],
+            (
+                "wallet_switchEthereumChain".to_owned(),
+                vec![json!({ "chainId": "0x64" })]
+            )
+        );
+        assert_eq!(
+            ExecutionError::from(err).rpc_error(),
+            Some(RpcError::UserRejected)
+        );
+    }
+
+    #[test]
+    fn batches_are_sent_as_single_requests() {
+        let (transport, requests) = wallet();
+        let calls = vec![
+            transport.prepare("eth_chainId", vec![]),
+            transport.prepare("eth_requestAccounts", vec![]),
+        ];
+        let results = transport.send_batch(calls).immediate().unwrap();
+
+        assert_eq!(results[0].as_ref().unwrap(), &json!("0x1"));
+        assert_eq!(
+            results[1].as_ref().unwrap(),
+            &json!([Address::repeat_byte(1)])
+        );
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+}
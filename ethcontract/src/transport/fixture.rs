@@ -0,0 +1,403 @@
+//! Implementation of transports for recording the JSON RPC requests and
+//! responses of a test run to a fixture file and replaying them, so that code
+//! built on contract bindings can be tested deterministically and offline
+//! against the captured behaviour of a real node.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use ethcontract::transport::{RecordingTransport, ReplayTransport};
+//! use ethcontract::{Http, Web3};
+//!
+//! // Record the interactions with a node once...
+//! let transport = RecordingTransport::new(Http::new("http://localhost:8545")?);
+//! let web3 = Web3::new(transport.clone());
+//! web3.eth().block_number().await?;
+//! transport.save("tests/fixtures/block_number.json")?;
+//!
+//! // ...and replay them in tests without the node.
+//! let transport = ReplayTransport::load("tests/fixtures/block_number.json")?;
+//! let web3 = Web3::new(transport.clone());
+//! web3.eth().block_number().await?;
+//! assert!(transport.unused().is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::FixtureError;
+use futures::future::{self, BoxFuture, FutureExt as _, TryFutureExt as _};
+use jsonrpc_core::types::{Call, Error as JsonrpcError, Params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use web3::error::Error as Web3Error;
+use web3::{helpers, BatchTransport, RequestId, Transport};
+
+/// A recorded JSON RPC request and its response.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The method of the request.
+    pub method: String,
+    /// The parameters of the request.
+    pub params: Vec<Value>,
+    /// The response of the node.
+    #[serde(flatten)]
+    pub response: RecordedResponse,
+}
+
+/// The recorded response to a JSON RPC request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordedResponse {
+    /// The request succeeded with the result.
+    Result(Value),
+    /// The request failed with the JSON RPC error.
+    Error(JsonrpcError),
+}
+
+impl RecordedResponse {
+    fn into_result(self) -> Result<Value, Web3Error> {
+        match self {
+            RecordedResponse::Result(result) => Ok(result),
+            RecordedResponse::Error(err) => Err(Web3Error::Rpc(err)),
+        }
+    }
+}
+
+/// A transport that records the requests and responses of the wrapped
+/// transport.
+///
+/// Successful responses and JSON RPC errors are recorded in the order in which
+/// the responses are received. Other errors, such as connection failures, are
+/// not recorded since they are not part of the behaviour of the node. Clones
+/// of the transport share the recorded interactions.
+#[derive(Clone, Debug)]
+pub struct RecordingTransport<T> {
+    inner: T,
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+}
+
+impl<T> RecordingTransport<T> {
+    /// Wraps a transport, recording its requests and responses.
+    pub fn new(inner: T) -> Self {
+        RecordingTransport {
+            inner,
+            interactions: Default::default(),
+        }
+    }
+
+    /// Returns the interactions recorded so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions.lock().unwrap().clone()
+    }
+
+    /// Saves the interactions recorded so far to a fixture file, which can be
+    /// loaded with [`ReplayTransport::load`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FixtureError> {
+        let fixture = serde_json::to_string_pretty(&self.interactions())?;
+        fs::write(path, fixture)?;
+        Ok(())
+    }
+
+    /// Returns a reference to the wrapped transport.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Transport for RecordingTransport<T>
+where
+    T: Transport,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, Result<Value, Web3Error>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let parts = request_parts(&request);
+        let interactions = self.interactions.clone();
+        self.inner
+            .send(id, request)
+            .inspect(move |result| record(&interactions, parts, result))
+            .boxed()
+    }
+}
+
+impl<T> BatchTransport for RecordingTransport<T>
+where
+    T: BatchTransport,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    type Batch = BoxFuture<'static, Result<Vec<Result<Value, Web3Error>>, Web3Error>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        let parts = requests
+            .iter()
+            .map(|(_, request)| request_parts(request))
+            .collect::<Vec<_>>();
+        let interactions = self.interactions.clone();
+        self.inner
+            .send_batch(requests)
+            .map_ok(move |results| {
+                for (request, result) in parts.into_iter().zip(&results) {
+                    record(&interactions, request, result);
+                }
+                results
+            })
+            .boxed()
+    }
+}
+
+/// A transport that serves the responses of recorded interactions.
+///
+/// Requests are matched to interactions by their method and parameters, so
+/// that concurrent requests can be replayed in any order. Identical requests,
+/// such as repeated polls of the latest block number, are served the recorded
+/// responses in order. Requests without a matching interaction fail with a
+/// transport error. Clones of the transport share the remaining interactions.
+#[derive(Clone, Debug)]
+pub struct ReplayTransport {
+    interactions: Arc<Mutex<Vec<Option<Interaction>>>>,
+    id: Arc<AtomicUsize>,
+}
+
+impl ReplayTransport {
+    /// Creates a transport replaying the specified interactions.
+    pub fn new(interactions: Vec<Interaction>) -> Self {
+        ReplayTransport {
+            interactions: Arc::new(Mutex::new(interactions.into_iter().map(Some).collect())),
+            id: Default::default(),
+        }
+    }
+
+    /// Loads the interactions to replay from a fixture file saved with
+    /// [`RecordingTransport::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        let fixture = fs::read(path)?;
+        Ok(ReplayTransport::new(serde_json::from_slice(&fixture)?))
+    }
+
+    /// Returns the interactions that were not replayed yet, for checking that
+    /// a test made all of the recorded requests.
+    pub fn unused(&self) -> Vec<Interaction> {
+        let interactions = self.interactions.lock().unwrap();
+        interactions.iter().flatten().cloned().collect()
+    }
+
+    /// Returns the recorded response for a request.
+    fn replay(&self, request: &Call) -> Result<Value, Web3Error> {
+        let (method, params) = request_parts(request);
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions
+            .iter_mut()
+            .find(|interaction| {
+                matches!(
+                    interaction,
+                    Some(interaction) if interaction.method == method && interaction.params == params
+                )
+            })
+            .and_then(Option::take)
+            .ok_or_else(|| {
+                Web3Error::Transport(format!(
+                    "no recorded response for {}({})",
+                    method,
+                    Value::from(params),
+                ))
+            })?
+            .response
+            .into_result()
+    }
+}
+
+impl Transport for ReplayTransport {
+    type Out = BoxFuture<'static, Result<Value, Web3Error>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        let id = self.id.fetch_add(1, Ordering::AcqRel);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, _: RequestId, request: Call) -> Self::Out {
+        future::ready(self.replay(&request)).boxed()
+    }
+}
+
+impl BatchTransport for ReplayTransport {
+    type Batch = BoxFuture<'static, Result<Vec<Result<Value, Web3Error>>, Web3Error>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let results = requests
+            .into_iter()
+            .map(|(_, request)| self.replay(&request))
+            .collect();
+        future::ok(results).boxed()
+    }
+}
+
+/// Returns the method and parameters of a JSON RPC call.
+fn request_parts(request: &Call) -> (String, Vec<Value>) {
+    let (method, params) = match request {
+        Call::MethodCall(call) => (&call.method, &call.params),
+        Call::Notification(notification) => (&notification.method, &notification.params),
+        Call::Invalid { .. } => return (String::new(), Vec::new()),
+    };
+    let params = match params {
+        Params::Array(params) => params.clone(),
+        Params::Map(params) => vec![Value::Object(params.clone())],
+        Params::None => Vec::new(),
+    };
+    (method.clone(), params)
+}
+
+/// Records the response to a request, ignoring errors that are not JSON RPC
+/// errors.
+fn record(
+    interactions: &Mutex<Vec<Interaction>>,
+    (method, params): (String, Vec<Value>),
+    result: &Result<Value, Web3Error>,
+) {
+    let response = match result {
+        Ok(result) => RecordedResponse::Result(result.clone()),
+        Err(Web3Error::Rpc(err)) => RecordedResponse::Error(err.clone()),
+        Err(_) => return,
+    };
+    interactions.lock().unwrap().push(Interaction {
+        method,
+        params,
+        response,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use jsonrpc_core::ErrorCode;
+    use std::env;
+    use web3::api::Web3;
+    use web3::types::U64;
+
+    fn rpc_error() -> JsonrpcError {
+        JsonrpcError {
+            code: ErrorCode::ServerError(-32000),
+            message: "header not found".to_owned(),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn records_and_replays_interactions() {
+        let mut inner = TestTransport::new();
+        let transport = RecordingTransport::new(inner.clone());
+        let web3 = Web3::new(transport.clone());
+
+        inner.add_response(json!("0x2a"));
+        assert_eq!(
+            web3.eth().block_number().immediate().unwrap(),
+            U64::from(42)
+        );
+        inner.add_error(Web3Error::Rpc(rpc_error()));
+        assert!(web3.eth().chain_id().immediate().is_err());
+        inner.add_response(json!(["0x1", "0x2"]));
+        let results = transport
+            .send_batch(vec![
+                transport.prepare("eth_getBalance", vec![json!("0x01"), json!("latest")]),
+                transport.prepare("eth_getBalance", vec![json!("0x02"), json!("latest")]),
+            ])
+            .immediate()
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let path = env::temp_dir().join(format!("ethcontract-fixture-{}.json", std::process::id()));
+        transport.save(&path).unwrap();
+        let replay = ReplayTransport::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(replay.unused(), transport.interactions());
+
+        // Batched and concurrent requests are replayed in any order.
+        let web3 = Web3::new(replay.clone());
+        let balance = replay
+            .execute("eth_getBalance", vec![json!("0x02"), json!("latest")])
+            .immediate()
+            .unwrap();
+        assert_eq!(balance, json!("0x2"));
+        assert!(matches!(
+            web3.eth().chain_id().immediate(),
+            Err(Web3Error::Rpc(err)) if err == rpc_error()
+        ));
+        let results = replay
+            .send_batch(vec![
+                replay.prepare("eth_blockNumber", vec![]),
+                replay.prepare("eth_getBalance", vec![json!("0x01"), json!("latest")]),
+            ])
+            .immediate()
+            .unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &json!("0x2a"));
+        assert_eq!(results[1].as_ref().unwrap(), &json!("0x1"));
+        assert!(replay.unused().is_empty());
+
+        // Interactions are only replayed once.
+        assert!(matches!(
+            web3.eth().block_number().immediate(),
+            Err(Web3Error::Transport(_))
+        ));
+    }
+
+    #[test]
+    fn replays_identical_requests_in_order() {
+        let replay = ReplayTransport::new(
+            (1..=2)
+                .map(|number| Interaction {
+                    method: "eth_blockNumber".to_owned(),
+                    params: vec![],
+                    response: RecordedResponse::Result(json!(U64::from(number))),
+                })
+                .collect(),
+        );
+        let web3 = Web3::new(replay);
+        assert_eq!(web3.eth().block_number().immediate().unwrap(), U64::from(1));
+        assert_eq!(web3.eth().block_number().immediate().unwrap(), U64::from(2));
+    }
+
+    #[test]
+    fn interaction_fixture_format() {
+        let interactions = vec![
+            Interaction {
+                method: "eth_chainId".to_owned(),
+                params: vec![],
+                response: RecordedResponse::Result(json!("0x1")),
+            },
+            Interaction {
+                method: "eth_getBlockByNumber".to_owned(),
+                params: vec![json!("0x1"), json!(false)],
+                response: RecordedResponse::Error(rpc_error()),
+            },
+        ];
+        let fixture = json!([
+            { "method": "eth_chainId", "params": [], "result": "0x1" },
+            {
+                "method": "eth_getBlockByNumber",
+                "params": ["0x1", false],
+                "error": { "code": -32000, "message": "header not found" },
+            },
+        ]);
+        assert_eq!(json!(interactions), fixture);
+        assert_eq!(
+            serde_json::from_value::<Vec<Interaction>>(fixture).unwrap(),
+            interactions,
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! Implementation of a transport that tolerates nonconformant encodings of
+//! quantities in JSON RPC results, such as JSON numbers or hex strings with
+//! leading zeros, as returned by some private chains and mock nodes.
+
+use super::method_name;
+use futures::future::{BoxFuture, FutureExt as _, TryFutureExt as _};
+use futures::stream::{BoxStream, StreamExt as _};
+use jsonrpc_core::Call;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+use web3::api::SubscriptionId;
+use web3::error::Error as Web3Error;
+use web3::types::U256;
+use web3::{BatchTransport, DuplexTransport, RequestId, Transport};
+
+/// The fields of `eth_*` results that contain quantities.
+const QUANTITY_FIELDS: &[&str] = &[
+    // blocks
+    "number",
+    "difficulty",
+    "totalDifficulty",
+    "size",
+    "gasLimit",
+    "gasUsed",
+    "timestamp",
+    "baseFeePerGas",
+    // transactions
+    "blockNumber",
+    "transactionIndex",
+    "nonce",
+    "value",
+    "gas",
+    "gasPrice",
+    "maxFeePerGas",
+    "maxPriorityFeePerGas",
+    "chainId",
+    "type",
+    "v",
+    "r",
+    "s",
+    // receipts and logs
+    "cumulativeGasUsed",
+    "effectiveGasPrice",
+    "status",
+    "logIndex",
+    "transactionLogIndex",
+    // fee history
+    "oldestBlock",
+    "reward",
+    // sync status
+    "startingBlock",
+    "currentBlock",
+    "highestBlock",
+];
+
+/// The `eth_*` methods whose results are quantities.
+const QUANTITY_METHODS: &[&str] = &[
+    "eth_blockNumber",
+    "eth_chainId",
+    "eth_estimateGas",
+    "eth_gasPrice",
+    "eth_getBalance",
+    "eth_getBlockTransactionCountByHash",
+    "eth_getBlockTransactionCountByNumber",
+    "eth_getTransactionCount",
+    "eth_getUncleCountByBlockHash",
+    "eth_getUncleCountByBlockNumber",
+    "eth_maxPriorityFeePerGas",
+    "net_peerCount",
+];
+
+/// A transport that normalizes the quantities in the JSON RPC results of the
+/// wrapped transport, so that they can be decoded by the contract bindings
+/// when talking to nodes that do not encode them as specified.
+///
+/// Quantities are accepted as JSON numbers or as hex strings with or without
+/// a `0x` prefix, in any case and with leading zeros, and are normalized to
+/// minimal `0x` prefixed hex strings. Only the results of methods in the
+/// `eth_` namespace and the fields of their results that are known to contain
+/// quantities are normalized, since data such as hashes must keep their
+/// leading zeros. Additional quantity fields and methods can be configured.
+///
+/// Note that JSON numbers that do not fit into 64 bits are parsed as floating
+/// point numbers by the underlying transport, and so are only as precise as
+/// a 64-bit float.
+#[derive(Clone, Debug)]
+pub struct LenientTransport<T> {
+    inner: T,
+    quantities: Arc<Quantities>,
+}
+
+#[derive(Clone, Debug)]
+struct Quantities {
+    fields: HashSet<String>,
+    methods: HashSet<String>,
+}
+
+impl<T> LenientTransport<T> {
+    /// Wraps a transport, normalizing the quantities in its results.
+    pub fn new(inner: T) -> Self {
+        LenientTransport {
+            inner,
+            quantities: Arc::new(Quantities {
+                fields: QUANTITY_FIELDS
+                    .iter()
+                    .map(|&field| field.to_owned())
+                    .collect(),
+                methods: QUANTITY_METHODS
+                    .iter()
+                    .map(|&method| method.to_owned())
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Adds a field of result objects that contains a quantity, or an array of
+    /// quantities, for example for nonstandard fields of a private chain.
+    pub fn quantity_field(mut self, field: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.quantities)
+            .fields
+            .insert(field.into());
+        self
+    }
+
+    /// Adds a method whose result is a quantity. Methods outside of the `eth_`
+    /// namespace are only normalized if they are added with this method.
+    pub fn quantity_method(mut self, method: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.quantities)
+            .methods
+            .insert(method.into());
+        self
+    }
+
+    /// Returns a reference to the wrapped transport.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Transport for LenientTransport<T>
+where
+    T: Transport,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, Result<Value, Web3Error>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let method = method_name(&request);
+        let quantities = self.quantities.clone();
+        self.inner
+            .send(id, request)
+            .map_ok(move |result| quantities.normalize_result(&method, result))
+            .boxed()
+    }
+}
+
+impl<T> BatchTransport for LenientTransport<T>
+where
+    T: BatchTransport,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    type Batch = BoxFuture<'static, Result<Vec<Result<Value, Web3Error>>, Web3Error>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        let methods = requests
+            .iter()
+            .map(|(_, request)| method_name(request))
+            .collect::<Vec<_>>();
+        let quantities = self.quantities.clone();
+        self.inner
+            .send_batch(requests)
+            .map_ok(move |results| {
+                results
+                    .into_iter()
+                    .zip(methods)
+                    .map(|(result, method)| {
+                        result.map(|result| quantities.normalize_result(&method, result))
+                    })
+                    .collect()
+            })
+            .boxed()
+    }
+}
+
+impl<T> DuplexTransport for LenientTransport<T>
+where
+    T: DuplexTransport,
+    T::Out: Send + 'static,
+    T::NotificationStream: Send + 'static,
+{
+    type NotificationStream = BoxStream<'static, Value>;
+
+    fn subscribe(&self, id: SubscriptionId) -> Result<Self::NotificationStream, Web3Error> {
+        let quantities = self.quantities.clone();
+        Ok(self
+            .inner
+            .subscribe(id)?
+            .map(move |mut notification| {
+                quantities.normalize_fields(&mut notification);
+                notification
+            })
+            .boxed())
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Web3Error> {
+        self.inner.unsubscribe(id)
+    }
+}
+
+impl Quantities {
+    /// Normalizes the quantities in the result of a method.
+    fn normalize_result(&self, method: &str, mut result: Value) -> Value {
+        if self.methods.contains(method) {
+            normalize_quantity(&mut result);
+        } else if method.starts_with("eth_") {
+            self.normalize_fields(&mut result);
+        }
+        result
+    }
+
+    /// Normalizes the quantity fields of the objects in a value.
+    fn normalize_fields(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                let is_block = object.contains_key("parentHash");
+                for (field, value) in object.iter_mut() {
+                    // NOTE: The nonce of a block is 8 bytes of data and not a
+                    //   quantity, so its leading zeros must be kept.
+                    if is_block && field == "nonce" {
+                        continue;
+                    }
+                    if self.fields.contains(field) {
+                        normalize_quantities(value);
+                    } else {
+                        self.normalize_fields(value);
+                    }
+                }
+            }
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.normalize_fields(value)),
+            _ => {}
+        }
+    }
+}
+
+/// Normalizes a quantity, or an array of quantities such as the base fees and
+/// rewards of `eth_feeHistory`. Other values, such as the objects of full
+/// transactions in a block, are normalized recursively by their fields.
+fn normalize_quantities(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(normalize_quantities),
+        _ => normalize_quantity(value),
+    }
+}
+
+/// Normalizes a JSON number or hex string quantity to a minimal `0x` prefixed
+/// hex string. Values that are not quantities are left unchanged.
+fn normalize_quantity(value: &mut Value) {
+    let normalized = match value {
+        Value::Number(number) => match number.as_u64() {
+            Some(number) => format!("{:#x}", number),
+            None => match number.as_f64().and_then(float_to_u256) {
+                Some(number) => format!("{:#x}", number),
+                None => return,
+            },
+        },
+        Value::String(string) => {
+            let digits = string
+                .strip_prefix("0x")
+                .or_else(|| string.strip_prefix("0X"))
+                .unwrap_or(string);
+            if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return;
+            }
+            let digits = digits.trim_start_matches('0').to_ascii_lowercase();
+            if digits.is_empty() {
+                "0x0".to_owned()
+            } else {
+                format!("0x{}", digits)
+            }
+        }
+        _ => return,
+    };
+    *value = Value::String(normalized);
+}
+
+/// Converts a non-negative integral float to a 256-bit integer.
+fn float_to_u256(value: f64) -> Option<U256> {
+    if !value.is_finite() || value < 0.0 || value.fract() != 0.0 {
+        return None;
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32 - 1075;
+    let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+    if exponent >= 0 {
+        if exponent > 256 - 53 {
+            return None;
+        }
+        Some(U256::from(mantissa) << exponent)
+    } else {
+        Some(U256::from(mantissa >> -exponent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use web3::api::Web3;
+    use web3::types::{Block, H256, U64};
+
+    #[test]
+    fn normalize_quantities() {
+        let normalize = |mut value: Value| {
+            normalize_quantity(&mut value);
+            value
+        };
+
+        assert_eq!(normalize(json!(42)), json!("0x2a"));
+        assert_eq!(normalize(json!("0x002A")), json!("0x2a"));
+        assert_eq!(normalize(json!("0X2a")), json!("0x2a"));
+        assert_eq!(normalize(json!("2a")), json!("0x2a"));
+        assert_eq!(normalize(json!("0x")), json!("0x0"));
+        assert_eq!(normalize(json!("0x0000")), json!("0x0"));
+        assert_eq!(
+            normalize(json!(1e21)),
+            json!(format!("{:#x}", U256::exp10(21)))
+        );
+        assert_eq!(normalize(json!(-1)), json!(-1));
+        assert_eq!(normalize(json!("pending")), json!("pending"));
+    }
+
+    #[test]
+    fn lenient_block_number() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(LenientTransport::new(transport.clone()));
+
+        transport.add_response(json!(42));
+        let block = web3.eth().block_number().immediate().unwrap();
+        assert_eq!(block, U64::from(42));
+
+        transport.add_response(json!(
+            "0x0000000000000000000000000000000000000000000000000000000000000000001"
+        ));
+        let balance = web3
+            .eth()
+            .balance(Default::default(), None)
+            .immediate()
+            .unwrap();
+        assert_eq!(balance, 1.into());
+    }
+
+    #[test]
+    fn lenient_block_fields() {
+        let mut transport = TestTransport::new();
+        let web3 =
+            Web3::new(LenientTransport::new(transport.clone()).quantity_field("l1BlockNumber"));
+
+        transport.add_response(json!({
+            "hash": H256::repeat_byte(1),
+            "parentHash": H256::zero(),
+            "sha3Uncles": H256::zero(),
+            "miner": "0x0000000000000000000000000000000000000000",
+            "stateRoot": H256::zero(),
+            "transactionsRoot": H256::zero(),
+            "receiptsRoot": H256::zero(),
+            "number": 42,
+            "gasUsed": "0X10",
+            "gasLimit": 30_000_000,
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x00",
+            "difficulty": "0x",
+            "totalDifficulty": 0,
+            "sealFields": [],
+            "uncles": [],
+            "transactions": [],
+            "size": 1000,
+            "mixHash": H256::zero(),
+            "nonce": "0x0000000000000000",
+            "l1BlockNumber": 7,
+        }));
+        let block: Block<H256> = web3
+            .eth()
+            .block(U64::from(42).into())
+            .immediate()
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.number, Some(42.into()));
+        assert_eq!(block.gas_used, 0x10.into());
+        assert_eq!(block.gas_limit, 30_000_000.into());
+        assert_eq!(block.size, Some(1000.into()));
+        assert_eq!(block.nonce, Some(Default::default()));
+    }
+
+    #[test]
+    fn lenient_transport_ignores_other_namespaces() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(LenientTransport::new(transport.clone()));
+
+        transport.add_response(json!([{ "blockNumber": 42 }]));
+        let result: Value = crate::rpc::call(&web3, "trace_block", ("0x2a",))
+            .immediate()
+            .unwrap();
+        assert_eq!(result, json!([{ "blockNumber": 42 }]));
+    }
+}
@@ -0,0 +1,426 @@
+//! Implementation of a transport that re-establishes its connection when it
+//! stops responding, with heartbeat monitoring for long running services.
+
+use futures::future::{self, BoxFuture, Either, FutureExt as _};
+use futures::stream::{self, BoxStream, Stream, StreamExt as _};
+use futures_timer::Delay;
+use jsonrpc_core::{Call, Value};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use web3::api::{SubscriptionId, Web3};
+use web3::error::Error as Web3Error;
+use web3::types::BlockHeader;
+use web3::{helpers, BatchTransport, DuplexTransport, RequestId, Transport};
+
+/// The default interval between heartbeats.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The default time to wait for the response to a heartbeat.
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A function establishing a new connection.
+type Connect<T> = dyn Fn() -> BoxFuture<'static, Result<T, Web3Error>> + Send + Sync;
+
+/// A transport that can re-establish its connection, for example for
+/// WebSocket connections of services that run for days and must survive
+/// connections that drop or silently stop delivering data.
+///
+/// Requests are sent over the current connection, which is replaced when
+/// [`ReconnectingTransport::reconnect`] is called. The connection is monitored
+/// and re-established automatically while the stream returned by
+/// [`ReconnectingTransport::heartbeat`] is polled:
+///
+/// ```ignore
+/// let url = "wss://node.example.com".to_owned();
+/// let transport = ReconnectingTransport::connect(move || {
+///     let url = url.clone();
+///     async move { WebSocket::new(&url).await }
+/// })
+/// .await?;
+/// let heartbeat = transport.heartbeat(HeartbeatOptions {
+///     head_timeout: Some(Duration::from_secs(60)),
+///     ..Default::default()
+/// });
+/// tokio::spawn(heartbeat.for_each(|event| async move { log::info!("{:?}", event) }));
+/// ```
+///
+/// Note that subscriptions of the previous connection end when it is
+/// replaced, so subscribers must subscribe again, as log streams of a
+/// [`LogSource`](crate::log::LogSource) do by falling back to polling.
+#[derive(Clone)]
+pub struct ReconnectingTransport<T> {
+    current: Arc<Mutex<T>>,
+    connect: Arc<Connect<T>>,
+}
+
+impl<T: Transport> ReconnectingTransport<T> {
+    /// Establishes a connection with the specified function, which is called
+    /// again for every reconnection.
+    pub async fn connect<F, C>(connect: F) -> Result<Self, Web3Error>
+    where
+        F: Fn() -> C + Send + Sync + 'static,
+        C: Future<Output = Result<T, Web3Error>> + Send + 'static,
+    {
+        let connect: Arc<Connect<T>> = Arc::new(move || connect().boxed());
+        let transport = connect().await?;
+        Ok(ReconnectingTransport {
+            current: Arc::new(Mutex::new(transport)),
+            connect,
+        })
+    }
+
+    /// Returns the transport of the current connection.
+    pub fn current(&self) -> T {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Establishes a new connection that replaces the current connection. The
+    /// current connection is kept if the new connection fails.
+    pub async fn reconnect(&self) -> Result<(), Web3Error> {
+        let transport = (self.connect)().await?;
+        *self.current.lock().unwrap() = transport;
+        Ok(())
+    }
+
+    /// Returns a stream of health events of the connection.
+    ///
+    /// The node is pinged with `eth_chainId` every heartbeat interval, and if
+    /// a head timeout is specified then new heads are subscribed to and the
+    /// connection is considered stale when no new head arrives within the
+    /// timeout. Unresponsive and stale connections are re-established, and
+    /// failed reconnections are retried every heartbeat interval. The
+    /// connection is only monitored while the stream is polled.
+    pub fn heartbeat(&self, options: HeartbeatOptions) -> impl Stream<Item = HealthEvent>
+    where
+        T: DuplexTransport + Send + Sync + 'static,
+        T::Out: Send,
+        T::NotificationStream: Send,
+    {
+        let now = Instant::now();
+        let monitor = Monitor {
+            transport: self.clone(),
+            options,
+            heads: None,
+            last_head: now,
+            next_heartbeat: now + options.interval,
+            reconnecting: false,
+            events: VecDeque::new(),
+        };
+        stream::unfold(monitor, Monitor::next)
+    }
+}
+
+impl<T: Debug> Debug for ReconnectingTransport<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ReconnectingTransport")
+            .field("current", &*self.current.lock().unwrap())
+            .finish()
+    }
+}
+
+impl<T: Transport> Transport for ReconnectingTransport<T> {
+    type Out = T::Out;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.current().prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        self.current().send(id, request)
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for ReconnectingTransport<T> {
+    type Batch = T::Batch;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        self.current().send_batch(requests)
+    }
+}
+
+impl<T: DuplexTransport> DuplexTransport for ReconnectingTransport<T> {
+    type NotificationStream = T::NotificationStream;
+
+    fn subscribe(&self, id: SubscriptionId) -> Result<Self::NotificationStream, Web3Error> {
+        self.current().subscribe(id)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Web3Error> {
+        self.current().unsubscribe(id)
+    }
+}
+
+/// Options for monitoring the health of a connection, see
+/// [`ReconnectingTransport::heartbeat`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HeartbeatOptions {
+    /// The interval between heartbeats.
+    pub interval: Duration,
+    /// The time to wait for the response to a heartbeat before considering
+    /// the connection unresponsive.
+    pub timeout: Duration,
+    /// The time within which new heads are expected to arrive, or `None` to
+    /// not subscribe to new heads. This should be a multiple of the block time
+    /// of the chain. Note that staleness is only checked with heartbeats.
+    pub head_timeout: Option<Duration>,
+}
+
+impl Default for HeartbeatOptions {
+    fn default() -> Self {
+        HeartbeatOptions {
+            interval: DEFAULT_HEARTBEAT_INTERVAL,
+            timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+            head_timeout: None,
+        }
+    }
+}
+
+/// A health event of a monitored connection.
+#[derive(Clone, Debug)]
+pub enum HealthEvent {
+    /// The node responded to a heartbeat.
+    Alive {
+        /// The time it took the node to respond.
+        latency: Duration,
+    },
+    /// The node did not respond to a heartbeat in time or responded with an
+    /// error, or the new heads subscription failed or was closed.
+    Unresponsive(Web3Error),
+    /// No new head arrived within the head timeout.
+    Stale {
+        /// The time since the last new head or since subscribing.
+        elapsed: Duration,
+    },
+    /// The connection was re-established.
+    Reconnected,
+    /// Re-establishing the connection failed. The reconnection is retried
+    /// after the heartbeat interval.
+    ReconnectFailed(Web3Error),
+}
+
+/// Internal state of a heartbeat stream.
+struct Monitor<T: Transport> {
+    transport: ReconnectingTransport<T>,
+    options: HeartbeatOptions,
+    heads: Option<BoxStream<'static, Result<BlockHeader, Web3Error>>>,
+    last_head: Instant,
+    next_heartbeat: Instant,
+    reconnecting: bool,
+    events: VecDeque<HealthEvent>,
+}
+
+impl<T> Monitor<T>
+where
+    T: DuplexTransport + Send + Sync + 'static,
+    T::Out: Send,
+    T::NotificationStream: Send,
+{
+    async fn next(mut self) -> Option<(HealthEvent, Self)> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some((event, self));
+            }
+            self.poll().await;
+        }
+    }
+
+    /// Waits for the next new head or heartbeat and checks the connection.
+    async fn poll(&mut self) {
+        if self.reconnecting {
+            self.wait_for_heartbeat().await;
+            self.reconnect().await;
+            return;
+        }
+
+        if self.options.head_timeout.is_some() && self.heads.is_none() {
+            let web3 = Web3::new(self.transport.current());
+            match web3.eth_subscribe().subscribe_new_heads().await {
+                Ok(heads) => {
+                    self.heads = Some(heads.boxed());
+                    self.last_head = Instant::now();
+                }
+                Err(err) => return self.fail(HealthEvent::Unresponsive(err)).await,
+            }
+        }
+
+        let head = match &mut self.heads {
+            Some(heads) => Either::Left(heads.next()),
+            None => Either::Right(future::pending()),
+        };
+        let delay = Delay::new(
+            self.next_heartbeat
+                .saturating_duration_since(Instant::now()),
+        );
+        let head = match future::select(head, delay).await {
+            Either::Left((head, _)) => Some(head),
+            Either::Right(_) => None,
+        };
+        match head {
+            Some(Some(Ok(_))) => {
+                self.last_head = Instant::now();
+                return;
+            }
+            Some(Some(Err(err))) => return self.fail(HealthEvent::Unresponsive(err)).await,
+            Some(None) => {
+                let err = Web3Error::Transport("new heads subscription closed".to_owned());
+                return self.fail(HealthEvent::Unresponsive(err)).await;
+            }
+            None => {}
+        }
+
+        self.next_heartbeat = Instant::now() + self.options.interval;
+        match ping(&self.transport.current(), self.options.timeout).await {
+            Ok(latency) => self.events.push_back(HealthEvent::Alive { latency }),
+            Err(err) => return self.fail(HealthEvent::Unresponsive(err)).await,
+        }
+        if let Some(head_timeout) = self.options.head_timeout {
+            let elapsed = self.last_head.elapsed();
+            if elapsed > head_timeout {
+                self.fail(HealthEvent::Stale { elapsed }).await;
+            }
+        }
+    }
+
+    async fn wait_for_heartbeat(&mut self) {
+        Delay::new(
+            self.next_heartbeat
+                .saturating_duration_since(Instant::now()),
+        )
+        .await;
+        self.next_heartbeat = Instant::now() + self.options.interval;
+    }
+
+    /// Records a failure of the connection and re-establishes it.
+    async fn fail(&mut self, event: HealthEvent) {
+        self.events.push_back(event);
+        self.reconnect().await;
+    }
+
+    async fn reconnect(&mut self) {
+        self.heads = None;
+        match self.transport.reconnect().await {
+            Ok(()) => {
+                self.reconnecting = false;
+                self.next_heartbeat = Instant::now() + self.options.interval;
+                self.events.push_back(HealthEvent::Reconnected);
+            }
+            Err(err) => {
+                self.reconnecting = true;
+                self.next_heartbeat = Instant::now() + self.options.interval;
+                self.events.push_back(HealthEvent::ReconnectFailed(err));
+            }
+        }
+    }
+}
+
+/// Pings the node with `eth_chainId` and returns the time it took to respond.
+async fn ping<T: Transport>(transport: &T, timeout: Duration) -> Result<Duration, Web3Error> {
+    let start = Instant::now();
+    let request = Box::pin(transport.execute("eth_chainId", vec![]));
+    match future::select(request, Delay::new(timeout)).await {
+        Either::Left((result, _)) => {
+            helpers::decode::<web3::types::U256>(result?)?;
+            Ok(start.elapsed())
+        }
+        Either::Right(_) => Err(Web3Error::Transport(format!(
+            "heartbeat timed out after {:?}",
+            timeout
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn options(head_timeout: Option<Duration>) -> HeartbeatOptions {
+        HeartbeatOptions {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(1),
+            head_timeout,
+        }
+    }
+
+    #[test]
+    fn reconnects_unresponsive_connections() {
+        let mut transport = TestTransport::new();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let reconnecting = {
+            let transport = transport.clone();
+            let connections = connections.clone();
+            ReconnectingTransport::connect(move || {
+                let connected = connections.fetch_add(1, Ordering::SeqCst);
+                let transport = transport.clone();
+                async move {
+                    match connected {
+                        1 => Err(Web3Error::Unreachable),
+                        _ => Ok(transport),
+                    }
+                }
+            })
+            .immediate()
+            .unwrap()
+        };
+
+        transport.add_error(Web3Error::Unreachable);
+        transport.add_response(json!("0x1"));
+        let events = reconnecting
+            .heartbeat(options(None))
+            .take(4)
+            .collect::<Vec<_>>()
+            .wait();
+
+        assert!(matches!(
+            &events[..],
+            [
+                HealthEvent::Unresponsive(Web3Error::Unreachable),
+                HealthEvent::ReconnectFailed(Web3Error::Unreachable),
+                HealthEvent::Reconnected,
+                HealthEvent::Alive { .. },
+            ]
+        ));
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn detects_stale_subscriptions() {
+        let mut transport = TestTransport::new();
+        let reconnecting = {
+            let transport = transport.clone();
+            ReconnectingTransport::connect(move || future::ok(transport.clone()))
+                .immediate()
+                .unwrap()
+        };
+
+        transport.add_response(json!("0x1234"));
+        transport.add_response(json!("0x1"));
+        let events = reconnecting
+            .heartbeat(options(Some(Duration::from_millis(1))))
+            .take(3)
+            .collect::<Vec<_>>()
+            .wait();
+
+        assert!(matches!(
+            &events[..],
+            [
+                HealthEvent::Alive { .. },
+                HealthEvent::Stale { elapsed },
+                HealthEvent::Reconnected,
+            ] if *elapsed > Duration::from_millis(1)
+        ));
+        transport.assert_request("eth_subscribe", &[json!("newHeads")]);
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_no_more_requests();
+    }
+}
@@ -0,0 +1,462 @@
+//! Implementation of a transport that schedules the requests of the wrapped
+//! transport by priority, so that latency sensitive requests such as
+//! transaction sends are not delayed by bulk traffic sharing the transport.
+
+use super::method_name;
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt as _};
+use jsonrpc_core::Call;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use web3::api::SubscriptionId;
+use web3::error::Error as Web3Error;
+use web3::{BatchTransport, DuplexTransport, RequestId, Transport};
+
+/// The default number of times that a waiting request can be passed over by
+/// requests of higher priority before it is sent.
+pub const DEFAULT_MAX_SKIPS: usize = 16;
+
+/// The priority class of a request, ordered from highest to lowest priority.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Time critical requests, such as transaction sends and nonce reads.
+    High,
+    /// Requests without a configured priority.
+    Normal,
+    /// Bulk requests, such as log queries and traces for backfills.
+    Low,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The methods that have a high priority by default.
+const HIGH_PRIORITY_METHODS: &[&str] = &[
+    "eth_sendRawTransaction",
+    "eth_sendTransaction",
+    "eth_getTransactionCount",
+    "eth_gasPrice",
+    "eth_maxPriorityFeePerGas",
+    "eth_feeHistory",
+];
+
+/// The methods that have a low priority by default.
+const LOW_PRIORITY_METHODS: &[&str] = &[
+    "eth_getLogs",
+    "eth_getFilterLogs",
+    "eth_getBlockReceipts",
+    "debug_traceBlockByNumber",
+    "debug_traceTransaction",
+    "trace_block",
+    "trace_filter",
+    "trace_replayTransaction",
+    "trace_transaction",
+];
+
+/// A transport that limits the number of concurrent requests of the wrapped
+/// transport and sends queued requests in order of priority.
+///
+/// The priority of a request is determined by its method, with transaction
+/// sends, nonce reads and gas price queries having a high priority and log
+/// queries and traces having a low priority by default. Transports returned
+/// by [`with_priority`](Self::with_priority) share the same queue but send all
+/// of their requests with a fixed priority, so that for example an indexer
+/// backfill can use a low priority transport while sending transactions over
+/// the same connection with high priority.
+///
+/// Requests of the same priority are sent in order. To keep bulk traffic from
+/// starving, a waiting request is sent once it has been passed over by the
+/// configured number of requests of higher priority. Batches are sent as a
+/// single request with the highest priority of their calls. Subscriptions are
+/// not scheduled.
+#[derive(Clone, Debug)]
+pub struct SchedulingTransport<T> {
+    inner: T,
+    scheduler: Arc<Scheduler>,
+    methods: Arc<HashMap<String, Priority>>,
+    priority: Option<Priority>,
+}
+
+impl<T> SchedulingTransport<T> {
+    /// Wraps a transport, allowing at most the specified number of concurrent
+    /// requests.
+    pub fn new(inner: T, max_concurrent: usize) -> Self {
+        let methods = HIGH_PRIORITY_METHODS
+            .iter()
+            .map(|&method| (method.to_owned(), Priority::High))
+            .chain(
+                LOW_PRIORITY_METHODS
+                    .iter()
+                    .map(|&method| (method.to_owned(), Priority::Low)),
+            )
+            .collect();
+        SchedulingTransport {
+            inner,
+            scheduler: Arc::new(Scheduler::new(max_concurrent.max(1))),
+            methods: Arc::new(methods),
+            priority: None,
+        }
+    }
+
+    /// Sets the priority of requests for a method.
+    pub fn method_priority(mut self, method: impl Into<String>, priority: Priority) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into(), priority);
+        self
+    }
+
+    /// Sets the number of times that a waiting request can be passed over by
+    /// requests of higher priority before it is sent. Defaults to
+    /// [`DEFAULT_MAX_SKIPS`].
+    pub fn max_skips(self, value: usize) -> Self {
+        self.scheduler.lock().max_skips = value;
+        self
+    }
+
+    /// Returns a transport that shares the queue of this transport but sends
+    /// all of its requests with the specified priority.
+    pub fn with_priority(&self, priority: Priority) -> Self
+    where
+        T: Clone,
+    {
+        SchedulingTransport {
+            priority: Some(priority),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the priority of a request for the specified method.
+    pub fn priority(&self, method: &str) -> Priority {
+        self.priority
+            .or_else(|| self.methods.get(method).copied())
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of requests waiting to be sent.
+    pub fn queued(&self) -> usize {
+        let state = self.scheduler.lock();
+        state.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Returns a reference to the wrapped transport.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Transport for SchedulingTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+{
+    type Out = BoxFuture<'static, Result<Value, Web3Error>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let permit = self
+            .scheduler
+            .acquire(self.priority(&method_name(&request)));
+        let inner = self.inner.clone();
+        async move {
+            let _permit = permit.await;
+            inner.send(id, request).await
+        }
+        .boxed()
+    }
+}
+
+impl<T> BatchTransport for SchedulingTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+    T::Batch: Send + 'static,
+{
+    type Batch = BoxFuture<'static, Result<Vec<Result<Value, Web3Error>>, Web3Error>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        let priority = requests
+            .iter()
+            .map(|(_, request)| self.priority(&method_name(request)))
+            .min()
+            .unwrap_or_default();
+        let permit = self.scheduler.acquire(priority);
+        let inner = self.inner.clone();
+        async move {
+            let _permit = permit.await;
+            inner.send_batch(requests).await
+        }
+        .boxed()
+    }
+}
+
+impl<T> DuplexTransport for SchedulingTransport<T>
+where
+    T: DuplexTransport + Send + Sync + 'static,
+    T::Out: Send + 'static,
+    T::NotificationStream: Send + 'static,
+{
+    type NotificationStream = T::NotificationStream;
+
+    fn subscribe(&self, id: SubscriptionId) -> Result<Self::NotificationStream, Web3Error> {
+        self.inner.subscribe(id)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Web3Error> {
+        self.inner.unsubscribe(id)
+    }
+}
+
+/// The request queue shared by scheduling transports.
+#[derive(Debug)]
+struct Scheduler(Mutex<State>);
+
+#[derive(Debug)]
+struct State {
+    max_concurrent: usize,
+    max_skips: usize,
+    in_flight: usize,
+    /// The waiting requests of each priority class.
+    queues: [VecDeque<oneshot::Sender<Permit>>; 3],
+    /// The number of times the first waiting request of each priority class
+    /// was passed over.
+    skips: [usize; 3],
+}
+
+/// A permit for sending a request, which releases its slot when dropped.
+#[derive(Debug)]
+struct Permit(Option<Arc<Scheduler>>);
+
+impl Scheduler {
+    fn new(max_concurrent: usize) -> Self {
+        Scheduler(Mutex::new(State {
+            max_concurrent,
+            max_skips: DEFAULT_MAX_SKIPS,
+            in_flight: 0,
+            queues: Default::default(),
+            skips: [0; 3],
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.0.lock().expect("scheduler mutex poisoned")
+    }
+
+    /// Waits for a slot for sending a request with the specified priority.
+    fn acquire(self: &Arc<Self>, priority: Priority) -> BoxFuture<'static, Permit> {
+        let mut state = self.lock();
+        if state.in_flight < state.max_concurrent {
+            state.in_flight += 1;
+            let permit = Permit(Some(self.clone()));
+            return async move { permit }.boxed();
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        state.queues[priority.index()].push_back(sender);
+        async move {
+            // NOTE: The sender is only dropped without sending a permit if the
+            //   scheduler is dropped, which can't happen while it is queued.
+            receiver.await.expect("scheduler dropped queued request")
+        }
+        .boxed()
+    }
+
+    /// Releases a slot, handing it over to the next waiting request.
+    fn release(self: &Arc<Self>) {
+        loop {
+            let next = {
+                let mut state = self.lock();
+                match state.next() {
+                    Some(next) => next,
+                    None => {
+                        state.in_flight -= 1;
+                        return;
+                    }
+                }
+            };
+            match next.send(Permit(Some(self.clone()))) {
+                Ok(()) => return,
+                // NOTE: The waiting request was dropped, so disarm the permit
+                //   and hand the slot over to the next one instead.
+                Err(mut permit) => permit.0 = None,
+            }
+        }
+    }
+}
+
+impl State {
+    /// Returns the next waiting request, which is the first request of the
+    /// highest priority class, unless a request of a lower priority class was
+    /// passed over too many times.
+    fn next(&mut self) -> Option<oneshot::Sender<Permit>> {
+        let waiting = Priority::ALL
+            .iter()
+            .map(|priority| priority.index())
+            .filter(|&class| !self.queues[class].is_empty())
+            .collect::<Vec<_>>();
+        let class = waiting
+            .iter()
+            .copied()
+            .find(|&class| self.skips[class] >= self.max_skips)
+            .or_else(|| waiting.first().copied())?;
+
+        for &other in &waiting {
+            if other > class {
+                self.skips[other] += 1;
+            }
+        }
+        self.skips[class] = 0;
+        self.queues[class].pop_front()
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.0.take() {
+            scheduler.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use web3::api::Web3;
+    use web3::types::{Address, U256};
+
+    fn scheduler(max_concurrent: usize) -> Arc<Scheduler> {
+        Arc::new(Scheduler::new(max_concurrent))
+    }
+
+    #[test]
+    fn high_priority_requests_are_sent_first() {
+        let scheduler = scheduler(1);
+        let permit = scheduler.acquire(Priority::Normal).now_or_never().unwrap();
+
+        let mut low = scheduler.acquire(Priority::Low);
+        let mut normal = scheduler.acquire(Priority::Normal);
+        let mut high = scheduler.acquire(Priority::High);
+        assert!((&mut low).now_or_never().is_none());
+        assert!((&mut normal).now_or_never().is_none());
+        assert!((&mut high).now_or_never().is_none());
+
+        drop(permit);
+        assert!((&mut low).now_or_never().is_none());
+        assert!((&mut normal).now_or_never().is_none());
+        let permit = (&mut high).now_or_never().unwrap();
+
+        drop(permit);
+        assert!((&mut low).now_or_never().is_none());
+        let permit = (&mut normal).now_or_never().unwrap();
+
+        drop(permit);
+        let permit = (&mut low).now_or_never().unwrap();
+
+        drop(permit);
+        assert_eq!(scheduler.lock().in_flight, 0);
+    }
+
+    #[test]
+    fn low_priority_requests_do_not_starve() {
+        let scheduler = scheduler(1);
+        scheduler.lock().max_skips = 2;
+        let mut permit = scheduler.acquire(Priority::High).now_or_never().unwrap();
+
+        let mut low = scheduler.acquire(Priority::Low);
+        assert!((&mut low).now_or_never().is_none());
+        for _ in 0..2 {
+            let mut high = scheduler.acquire(Priority::High);
+            drop(permit);
+            assert!((&mut low).now_or_never().is_none());
+            permit = (&mut high).now_or_never().unwrap();
+        }
+
+        let mut high = scheduler.acquire(Priority::High);
+        drop(permit);
+        assert!((&mut high).now_or_never().is_none());
+        drop((&mut low).now_or_never().unwrap());
+        drop((&mut high).now_or_never().unwrap());
+        assert_eq!(scheduler.lock().in_flight, 0);
+    }
+
+    #[test]
+    fn dropped_requests_release_their_slot() {
+        let scheduler = scheduler(1);
+        let permit = scheduler.acquire(Priority::Normal).now_or_never().unwrap();
+
+        let dropped = scheduler.acquire(Priority::High);
+        let mut waiting = scheduler.acquire(Priority::Normal);
+        drop(dropped);
+        drop(permit);
+        let permit = (&mut waiting).now_or_never().unwrap();
+
+        // A permit that was handed over but never received is released too.
+        let received = scheduler.acquire(Priority::Normal);
+        drop(permit);
+        drop(received);
+        assert_eq!(scheduler.lock().in_flight, 0);
+        assert!(scheduler.lock().queues.iter().all(VecDeque::is_empty));
+    }
+
+    #[test]
+    fn request_priorities() {
+        let transport = SchedulingTransport::new(TestTransport::new(), 1)
+            .method_priority("eth_call", Priority::Low);
+        assert_eq!(transport.priority("eth_sendRawTransaction"), Priority::High);
+        assert_eq!(transport.priority("eth_getLogs"), Priority::Low);
+        assert_eq!(transport.priority("eth_call"), Priority::Low);
+        assert_eq!(transport.priority("eth_blockNumber"), Priority::Normal);
+
+        let backfill = transport.with_priority(Priority::Low);
+        assert_eq!(backfill.priority("eth_sendRawTransaction"), Priority::Low);
+    }
+
+    #[test]
+    fn sends_requests_through_inner_transport() {
+        let mut inner = TestTransport::new();
+        let transport = SchedulingTransport::new(inner.clone(), 2);
+        let web3 = Web3::new(transport.clone());
+
+        inner.add_response(json!("0x2a"));
+        let nonce = web3
+            .eth()
+            .transaction_count(Address::repeat_byte(1), None)
+            .immediate()
+            .unwrap();
+        assert_eq!(nonce, U256::from(42));
+        inner.assert_request(
+            "eth_getTransactionCount",
+            &[json!(Address::repeat_byte(1)), json!("latest")],
+        );
+
+        inner.add_response(json!([json!("0x1"), json!("0x2")]));
+        let requests = vec![
+            transport.prepare("eth_blockNumber", vec![]),
+            transport.prepare("eth_chainId", vec![]),
+        ];
+        let results = transport.send_batch(requests).immediate().unwrap();
+        assert_eq!(results.len(), 2);
+        inner.assert_request("eth_blockNumber", &[]);
+        inner.assert_request("eth_chainId", &[]);
+        inner.assert_no_more_requests();
+        assert_eq!(transport.queued(), 0);
+    }
+}
@@ -0,0 +1,355 @@
+//! Conversions between token amounts as human readable decimal strings and
+//! their integer representation in the token's smallest unit.
+//!
+//! For example, one and a half Ether is `1.5` with 18 decimals, which is
+//! represented on-chain as `1500000000000000000` Wei.
+
+use crate::errors::ParseUnitsError;
+use crate::tokens::{Error as TokenError, Tokenize};
+use ethcontract_common::abi::Token;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use web3::types::U256;
+
+/// The maximum number of decimals supported. Larger values would overflow a
+/// 256-bit integer for any amount greater than zero.
+pub const MAX_DECIMALS: u32 = 77;
+
+/// Parses a decimal string amount into an integer amount with the specified
+/// number of decimals.
+///
+/// Returns an error if the amount has more fractional digits than the number
+/// of decimals (which would lose precision) or if the resulting amount does
+/// not fit in a 256-bit integer.
+///
+/// ```
+/// # use ethcontract::units::parse_units;
+/// assert_eq!(parse_units("1.5", 18).unwrap(), 1_500_000_000_000_000_000u64.into());
+/// ```
+pub fn parse_units(amount: &str, decimals: u32) -> Result<U256, ParseUnitsError> {
+    if decimals > MAX_DECIMALS {
+        return Err(ParseUnitsError::Overflow);
+    }
+
+    let invalid = || ParseUnitsError::InvalidNumber(amount.to_owned());
+    let (whole, fraction) = match amount.find('.') {
+        Some(dot) => (&amount[..dot], &amount[dot + 1..]),
+        None => (amount, ""),
+    };
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(ParseUnitsError::TooManyDecimals(decimals));
+    }
+
+    let digits = whole
+        .bytes()
+        .chain(fraction.bytes())
+        .chain((fraction.len()..decimals as usize).map(|_| b'0'));
+    let mut value = U256::zero();
+    for digit in digits {
+        value = value
+            .checked_mul(10.into())
+            .and_then(|value| value.checked_add((digit - b'0').into()))
+            .ok_or(ParseUnitsError::Overflow)?;
+    }
+
+    Ok(value)
+}
+
+/// Formats an integer amount with the specified number of decimals as a
+/// decimal string. Trailing fractional zeros are omitted.
+///
+/// ```
+/// # use ethcontract::units::format_units;
+/// assert_eq!(format_units(1_500_000_000_000_000_000u64.into(), 18), "1.5");
+/// ```
+pub fn format_units(amount: U256, decimals: u32) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+
+    let (whole, fraction) = if digits.len() > decimals {
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        (whole.to_owned(), fraction.to_owned())
+    } else {
+        (
+            "0".to_owned(),
+            format!("{:0>width$}", digits, width = decimals),
+        )
+    };
+
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// A marker trait for specifying the number of decimals of a token amount at
+/// the type level.
+///
+/// Generated bindings for ERC20 tokens define a `TokenDecimals` type
+/// implementing this trait when configured with the number of decimals of the
+/// token.
+pub trait Decimals {
+    /// The number of decimals.
+    const DECIMALS: u32;
+}
+
+/// A token amount with a known number of decimals.
+///
+/// This is a thin wrapper around the raw integer amount that is displayed and
+/// parsed as a decimal amount, preventing amounts of tokens with different
+/// decimals from being accidentally mixed up. It is encoded as a `uint256` in
+/// contract calls.
+pub struct TokenAmount<D> {
+    raw: U256,
+    _decimals: PhantomData<D>,
+}
+
+impl<D: Decimals> TokenAmount<D> {
+    /// Creates a token amount from a raw amount in the token's smallest unit.
+    pub fn from_raw(raw: U256) -> Self {
+        TokenAmount {
+            raw,
+            _decimals: PhantomData,
+        }
+    }
+
+    /// Returns the raw amount in the token's smallest unit.
+    pub fn into_raw(self) -> U256 {
+        self.raw
+    }
+
+    /// Returns the number of decimals of the token amount.
+    pub fn decimals(&self) -> u32 {
+        D::DECIMALS
+    }
+
+    /// Parses a decimal string amount.
+    pub fn parse(amount: &str) -> Result<Self, ParseUnitsError> {
+        parse_units(amount, D::DECIMALS).map(Self::from_raw)
+    }
+
+    /// Checked addition. Returns `None` if overflow occurred.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.raw.checked_add(other.raw).map(Self::from_raw)
+    }
+
+    /// Checked subtraction. Returns `None` if overflow occurred.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.raw.checked_sub(other.raw).map(Self::from_raw)
+    }
+}
+
+// NOTE: Implement common traits manually so that they don't require the
+//   decimals marker type to implement them.
+
+impl<D> Clone for TokenAmount<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for TokenAmount<D> {}
+
+impl<D> PartialEq for TokenAmount<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<D> Eq for TokenAmount<D> {}
+
+impl<D> PartialOrd for TokenAmount<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<D> Ord for TokenAmount<D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.raw.cmp(&other.raw)
+    }
+}
+
+impl<D> Hash for TokenAmount<D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state)
+    }
+}
+
+impl<D> Default for TokenAmount<D> {
+    fn default() -> Self {
+        TokenAmount {
+            raw: U256::zero(),
+            _decimals: PhantomData,
+        }
+    }
+}
+
+impl<D: Decimals> Debug for TokenAmount<D> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("TokenAmount")
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
+
+impl<D: Decimals> Display for TokenAmount<D> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&format_units(self.raw, D::DECIMALS))
+    }
+}
+
+impl<D: Decimals> FromStr for TokenAmount<D> {
+    type Err = ParseUnitsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<D: Decimals> From<TokenAmount<D>> for U256 {
+    fn from(amount: TokenAmount<D>) -> Self {
+        amount.raw
+    }
+}
+
+impl<D: Decimals> Tokenize for TokenAmount<D> {
+    fn from_token(token: Token) -> Result<Self, TokenError> {
+        U256::from_token(token).map(Self::from_raw)
+    }
+
+    fn into_token(self) -> Token {
+        self.raw.into_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> U256 {
+        U256::from_dec_str(s).unwrap()
+    }
+
+    #[test]
+    fn parse_amounts() {
+        for (amount, decimals, expected) in &[
+            ("1.5", 18, dec("1500000000000000000")),
+            ("1", 18, dec("1000000000000000000")),
+            ("0.000001", 6, U256::one()),
+            (".5", 1, U256::from(5)),
+            ("5.", 1, U256::from(50)),
+            ("1.100", 2, U256::from(110)),
+            ("42", 0, U256::from(42)),
+            ("007", 0, U256::from(7)),
+        ] {
+            assert_eq!(parse_units(amount, *decimals).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_amounts() {
+        for amount in &["", ".", "1.2.3", "-1", "1e18", " 1", "0x10"] {
+            assert!(matches!(
+                parse_units(amount, 18),
+                Err(ParseUnitsError::InvalidNumber(_))
+            ));
+        }
+        assert!(matches!(
+            parse_units("1.0000001", 6),
+            Err(ParseUnitsError::TooManyDecimals(6))
+        ));
+    }
+
+    #[test]
+    fn parse_overflow() {
+        let max = U256::MAX.to_string();
+        assert_eq!(parse_units(&max, 0).unwrap(), U256::MAX);
+        assert!(matches!(
+            parse_units(&format!("{}0", max), 0),
+            Err(ParseUnitsError::Overflow)
+        ));
+        assert!(matches!(
+            parse_units("1", 78),
+            Err(ParseUnitsError::Overflow)
+        ));
+        assert!(matches!(
+            parse_units("1000", 75),
+            Err(ParseUnitsError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn format_amounts() {
+        for (amount, decimals, expected) in &[
+            (dec("1500000000000000000"), 18, "1.5"),
+            (dec("1000000000000000000"), 18, "1"),
+            (U256::one(), 6, "0.000001"),
+            (U256::zero(), 18, "0"),
+            (U256::from(42), 0, "42"),
+            (U256::from(110), 2, "1.1"),
+        ] {
+            assert_eq!(format_units(*amount, *decimals), *expected);
+        }
+    }
+
+    struct Usdc;
+
+    impl Decimals for Usdc {
+        const DECIMALS: u32 = 6;
+    }
+
+    #[test]
+    fn token_amount_display_and_parse() {
+        let amount = "1.5".parse::<TokenAmount<Usdc>>().unwrap();
+        assert_eq!(amount.into_raw(), U256::from(1_500_000));
+        assert_eq!(amount.to_string(), "1.5");
+        assert_eq!(format!("{:?}", amount), "TokenAmount(1.5)");
+        assert!("1.0000001".parse::<TokenAmount<Usdc>>().is_err());
+    }
+
+    #[test]
+    fn token_amount_tokenize() {
+        let amount = TokenAmount::<Usdc>::from_raw(42.into());
+        assert_eq!(amount.into_token(), Token::Uint(42.into()));
+        assert_eq!(
+            TokenAmount::<Usdc>::from_token(Token::Uint(42.into())).unwrap(),
+            amount
+        );
+        assert!(TokenAmount::<Usdc>::from_token(Token::Bool(true)).is_err());
+    }
+
+    #[test]
+    fn token_amount_arithmetic() {
+        let one = TokenAmount::<Usdc>::parse("1").unwrap();
+        let two = TokenAmount::<Usdc>::parse("2").unwrap();
+        assert_eq!(one.checked_add(one), Some(two));
+        assert_eq!(two.checked_sub(one), Some(one));
+        assert_eq!(one.checked_sub(two), None);
+        assert!(one < two);
+    }
+
+    #[test]
+    fn format_round_trips() {
+        for amount in &["1.5", "0.000000000000000001", "123456789.987654321"] {
+            let value = parse_units(amount, 18).unwrap();
+            assert_eq!(format_units(value, 18), *amount);
+        }
+    }
+}